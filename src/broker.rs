@@ -0,0 +1,205 @@
+//! Sharing one MCP server between ssh-hub processes.
+//!
+//! With `[broker] enabled = true`, the first MCP server to start listens on a
+//! unix socket (`$XDG_RUNTIME_DIR/ssh-hub/broker.sock`). ssh-hub processes
+//! started later by other clients find it and relay their stdio over the
+//! socket instead of serving themselves, so every client shares one
+//! connection pool, one config watcher, and one set of caches.
+//!
+//! The owning process keeps running until its own client and every relayed
+//! client have disconnected. If it dies, relayed clients lose their session;
+//! the next ssh-hub to start takes over the socket.
+
+use std::fs::Permissions;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{UnixListener, UnixStream};
+
+use crate::server_registry::ServerRegistry;
+
+/// What this process does after [`claim`].
+#[derive(Debug)]
+pub enum Role {
+    /// No broker was running: serve clients that connect to this listener.
+    Owner(UnixListener),
+    /// Another process owns the socket: relay stdio to it with [`proxy`].
+    Client(UnixStream),
+}
+
+/// Default socket path: the user's runtime directory, or the cache directory
+/// on platforms without one. Processes using a config file other than the
+/// default get a socket of their own, so they never share a server loaded
+/// from a different config.
+#[must_use]
+pub fn socket_path() -> Option<PathBuf> {
+    let config = ServerRegistry::config_path().ok();
+    let name = socket_name(
+        config.as_deref(),
+        ServerRegistry::default_config_path().ok().as_deref(),
+    );
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .map(|dir| dir.join("ssh-hub").join(name))
+}
+
+/// `broker.sock` for the default config, else `broker-<hash>.sock` with a
+/// hash of the config path.
+#[must_use]
+pub fn socket_name(config: Option<&Path>, default: Option<&Path>) -> String {
+    match config {
+        Some(config) if Some(config) != default => {
+            let digest = Sha256::digest(config.as_os_str().as_encoded_bytes());
+            format!("broker-{}.sock", &hex::encode(digest)[..16])
+        }
+        _ => "broker.sock".to_string(),
+    }
+}
+
+/// Connect to the broker at `path`, or become it if none is listening.
+///
+/// Claims are serialized through a lock file next to the socket, so two
+/// processes starting together can't both replace a stale socket.
+///
+/// # Errors
+///
+/// Returns an error if the socket directory, lock file, or socket cannot be
+/// created.
+pub async fn claim(path: &Path) -> io::Result<Role> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+        std::fs::set_permissions(dir, Permissions::from_mode(0o700))?;
+    }
+    let lock = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path.with_extension("lock"))?;
+    lock.lock()?;
+
+    match UnixStream::connect(path).await {
+        Ok(stream) => return Ok(Role::Client(stream)),
+        // Nothing there, or a socket left behind by a process that exited.
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+            ) => {}
+        Err(e) => return Err(e),
+    }
+    match std::fs::remove_file(path) {
+        Ok(()) => tracing::debug!("Removed stale broker socket {}", path.display()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, Permissions::from_mode(0o600))?;
+    Ok(Role::Owner(listener))
+}
+
+/// Relay stdin to the broker and the broker's replies to stdout until either
+/// side closes.
+///
+/// # Errors
+///
+/// Returns an error if reading or writing stdio or the socket fails.
+pub async fn proxy(stream: UnixStream) -> io::Result<()> {
+    let (mut from_broker, mut to_broker) = stream.into_split();
+    let upstream = tokio::spawn(async move {
+        tokio::io::copy(&mut tokio::io::stdin(), &mut to_broker).await?;
+        to_broker.shutdown().await
+    });
+
+    let mut stdout = tokio::io::stdout();
+    let result = tokio::io::copy(&mut from_broker, &mut stdout).await;
+    upstream.abort();
+    result?;
+    stdout.flush().await
+}
+
+/// Call one MCP tool on the running broker at `path` and return its text
+/// output. Used by CLI commands that inspect the shared server's state.
+///
+/// Connects directly instead of through [`claim`], so a missing broker is an
+/// error rather than a new owner.
+///
+/// # Errors
+///
+/// Returns an error if no broker is listening, the socket closes before the
+/// reply, or the reply is not a tool result.
+pub async fn call_tool(
+    path: &Path,
+    tool: &str,
+    arguments: serde_json::Value,
+) -> io::Result<String> {
+    let stream = UnixStream::connect(path).await?;
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+
+    let initialize = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": "2025-03-26",
+            "capabilities": {},
+            "clientInfo": { "name": "ssh-hub-cli", "version": env!("CARGO_PKG_VERSION") },
+        },
+    });
+    send(&mut write, &initialize).await?;
+    reply(&mut lines, 1).await?;
+    send(
+        &mut write,
+        &serde_json::json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+    )
+    .await?;
+
+    let call = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": { "name": tool, "arguments": arguments },
+    });
+    send(&mut write, &call).await?;
+    let result = reply(&mut lines, 2).await?;
+    result["content"]
+        .as_array()
+        .and_then(|content| content.iter().find_map(|c| c["text"].as_str()))
+        .map(ToString::to_string)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "tool result has no text"))
+}
+
+async fn send(write: &mut OwnedWriteHalf, message: &serde_json::Value) -> io::Result<()> {
+    let mut line = message.to_string();
+    line.push('\n');
+    write.write_all(line.as_bytes()).await
+}
+
+/// Read messages until the response to request `id`, skipping notifications.
+async fn reply(
+    lines: &mut Lines<BufReader<OwnedReadHalf>>,
+    id: u64,
+) -> io::Result<serde_json::Value> {
+    while let Some(line) = lines.next_line().await? {
+        let Ok(mut message) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+        if message["id"].as_u64() != Some(id) {
+            continue;
+        }
+        if let Some(error) = message.get("error") {
+            return Err(io::Error::other(format!(
+                "broker replied with an error: {error}"
+            )));
+        }
+        return Ok(message["result"].take());
+    }
+    Err(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "broker closed the connection",
+    ))
+}
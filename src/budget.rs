@@ -0,0 +1,237 @@
+//! Context budgets: how many bytes each tool family may return.
+//!
+//! ```toml
+//! [budget.read]        # remote_read, remote_tail
+//! per_call = 262144    # bytes in one response
+//! per_minute = 2097152 # bytes across all calls in the last 60 seconds
+//!
+//! [budget.bash]        # remote_bash, command templates
+//! per_call = 0         # 0 disables a limit
+//! ```
+//!
+//! A response over the per-call limit, or over what is left of the per-minute
+//! limit, is cut at a line boundary and followed by a JSON notice telling the
+//! model to refine its query. Once the per-minute limit is spent, calls in
+//! that family are refused until older responses age out of the window.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::tools::{ErrorCode, ToolError};
+use crate::utils::output::MAX_INLINE_OUTPUT;
+
+/// Length of the sliding per-minute window.
+const WINDOW: Duration = Duration::from_mins(1);
+
+/// Tools that share a budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ToolFamily {
+    /// `remote_read`, `remote_tail`
+    Read,
+    /// `remote_glob`
+    Search,
+    /// `remote_bash` and command templates
+    Bash,
+}
+
+impl ToolFamily {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Search => "search",
+            Self::Bash => "bash",
+        }
+    }
+
+    fn default_limits(self) -> Limits {
+        match self {
+            Self::Read | Self::Bash => Limits {
+                per_call: 256 * 1024,
+                per_minute: 2 * 1024 * 1024,
+            },
+            Self::Search => Limits {
+                per_call: MAX_INLINE_OUTPUT,
+                per_minute: 1024 * 1024,
+            },
+        }
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            Self::Read => "Read a line range with offset and limit instead of the whole file.",
+            Self::Search => "Narrow the pattern or base path, or lower limit.",
+            Self::Bash => {
+                "Filter the output on the server (grep, head, tail, wc), or redirect it to a file and read slices of it."
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BudgetConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read: Option<FamilyBudget>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<FamilyBudget>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bash: Option<FamilyBudget>,
+}
+
+/// Limits for one tool family, in bytes. Unset fields use the built-in
+/// default; `0` disables the limit.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FamilyBudget {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_call: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_minute: Option<usize>,
+}
+
+/// Effective limits for a family, in bytes (`0` = unlimited).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub per_call: usize,
+    pub per_minute: usize,
+}
+
+impl Limits {
+    /// Limits for `family`, from `config` where set and the defaults otherwise.
+    #[must_use]
+    pub fn for_family(family: ToolFamily, config: Option<&BudgetConfig>) -> Self {
+        let defaults = family.default_limits();
+        let configured = config.and_then(|c| match family {
+            ToolFamily::Read => c.read,
+            ToolFamily::Search => c.search,
+            ToolFamily::Bash => c.bash,
+        });
+        let Some(configured) = configured else {
+            return defaults;
+        };
+        Self {
+            per_call: configured.per_call.unwrap_or(defaults.per_call),
+            per_minute: configured.per_minute.unwrap_or(defaults.per_minute),
+        }
+    }
+}
+
+/// Bytes returned per family over the last minute.
+#[derive(Debug, Default)]
+pub struct BudgetUsage {
+    spent: HashMap<ToolFamily, VecDeque<(Instant, usize)>>,
+}
+
+impl BudgetUsage {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bytes returned by `family` in the minute before `now`.
+    pub fn spent(&mut self, family: ToolFamily, now: Instant) -> usize {
+        let entries = self.spent.entry(family).or_default();
+        while entries
+            .front()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) >= WINDOW)
+        {
+            entries.pop_front();
+        }
+        entries.iter().map(|(_, bytes)| bytes).sum()
+    }
+
+    /// Refuse a call up front when the family's per-minute budget is spent.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ErrorCode::BudgetExceeded`] error saying when budget
+    /// frees up.
+    pub fn check(
+        &mut self,
+        family: ToolFamily,
+        limits: Limits,
+        now: Instant,
+    ) -> Result<(), ToolError> {
+        if limits.per_minute == 0 || self.spent(family, now) < limits.per_minute {
+            return Ok(());
+        }
+        let wait = self.spent[&family]
+            .front()
+            .map_or(WINDOW, |(at, _)| WINDOW.saturating_sub(now - *at));
+        let message = format!(
+            "the {} budget of {} bytes per minute is used up. Retry in {}s. {}",
+            family.name(),
+            limits.per_minute,
+            wait.as_secs().max(1),
+            family.hint()
+        );
+        Err(ToolError::new(ErrorCode::BudgetExceeded, message))
+    }
+
+    /// Record `output` against the family's budget, cutting it down first if
+    /// it exceeds the per-call limit or the rest of the per-minute limit.
+    pub fn charge(
+        &mut self,
+        family: ToolFamily,
+        limits: Limits,
+        output: String,
+        now: Instant,
+    ) -> String {
+        let remaining = match limits.per_minute {
+            0 => usize::MAX,
+            per_minute => per_minute.saturating_sub(self.spent(family, now)),
+        };
+        let per_call = match limits.per_call {
+            0 => usize::MAX,
+            per_call => per_call,
+        };
+        let (allowed, limit) = if per_call <= remaining {
+            (per_call, "per_call")
+        } else {
+            (remaining, "per_minute")
+        };
+
+        let output = if output.len() > allowed {
+            truncate_for_budget(family, &output, allowed, limit)
+        } else {
+            output
+        };
+        self.spent
+            .entry(family)
+            .or_default()
+            .push_back((now, output.len()));
+        output
+    }
+}
+
+/// Cut `output` to at most `allowed` bytes, preferring a line boundary, and
+/// append a notice describing what was dropped.
+fn truncate_for_budget(family: ToolFamily, output: &str, allowed: usize, limit: &str) -> String {
+    let mut end = allowed;
+    while !output.is_char_boundary(end) {
+        end -= 1;
+    }
+    if let Some(newline) = output[..end].rfind('\n') {
+        end = newline + 1;
+    }
+    let notice = serde_json::json!({
+        "truncated": "budget",
+        "family": family.name(),
+        "limit": limit,
+        "returned_bytes": end,
+        "total_bytes": output.len(),
+        "hint": family.hint(),
+    });
+    let mut out = output[..end].to_string();
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    let _ = write!(
+        out,
+        "\n[Output truncated by the {} budget; refine your query]\n{notice}",
+        family.name()
+    );
+    out
+}
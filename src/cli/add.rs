@@ -1,10 +1,11 @@
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use colored::Colorize;
 
 use crate::connection;
+use crate::discovery::DiscoveryConfig;
 use crate::metadata;
 use crate::server_registry::{self, ServerRegistry};
 
@@ -15,25 +16,131 @@ use super::spinner;
 /// Timeout for the connectivity test after adding a server (10 seconds).
 const CONNECTION_TEST_TIMEOUT_MS: u64 = 10_000;
 
+/// How `add` behaves without a person at the terminal.
+#[derive(clap::Args, Debug, Default, Clone, Copy)]
+pub struct AddOptions {
+    /// Never prompt: overwrite an existing entry, and don't save if the connection test fails
+    #[arg(short = 'y', long)]
+    pub yes: bool,
+
+    /// Never prompt, and save even if the connection test fails
+    #[arg(long)]
+    pub force: bool,
+
+    /// Save without testing the connection or collecting metadata
+    #[arg(long)]
+    pub no_test: bool,
+}
+
+/// What to add: a connection string, or a cloud instance to look up.
+#[derive(clap::Args, Debug, Default, Clone)]
+pub struct AddTarget {
+    /// SSH connection string (see CONNECTION FORMATS below)
+    #[arg(required_unless_present_any = ["aws_instance", "gcp_instance"])]
+    #[arg(conflicts_with_all = ["aws_instance", "gcp_instance"])]
+    pub connection: Option<String>,
+
+    /// EC2 instance ID: address, user, and key come from the aws CLI
+    #[arg(long, value_name = "ID", conflicts_with = "gcp_instance")]
+    pub aws_instance: Option<String>,
+
+    /// AWS region of --aws-instance (default: the aws CLI's region)
+    #[arg(long, requires = "aws_instance")]
+    pub region: Option<String>,
+
+    /// Compute Engine instance name: address, user, and key come from gcloud
+    #[arg(long, value_name = "NAME")]
+    pub gcp_instance: Option<String>,
+
+    /// Zone of --gcp-instance (default: gcloud's zone)
+    #[arg(long, requires = "gcp_instance")]
+    pub zone: Option<String>,
+
+    /// Project of --gcp-instance (default: gcloud's project)
+    #[arg(long, requires = "gcp_instance")]
+    pub project: Option<String>,
+
+    /// Login user for the instance, overriding the one looked up
+    #[arg(short = 'u', long, conflicts_with = "connection")]
+    pub user: Option<String>,
+}
+
+impl AddTarget {
+    fn discovery(&self) -> Option<DiscoveryConfig> {
+        if self.aws_instance.is_none() && self.gcp_instance.is_none() {
+            return None;
+        }
+        Some(DiscoveryConfig {
+            aws_instance: self.aws_instance.clone(),
+            gcp_instance: self.gcp_instance.clone(),
+            region: self.region.clone(),
+            zone: self.zone.clone(),
+            project: self.project.clone(),
+            ..DiscoveryConfig::default()
+        })
+    }
+}
+
+/// Describe the instance and build the connection string and key for it.
+async fn describe(
+    lookup: &DiscoveryConfig,
+    user: Option<String>,
+    identity: Option<PathBuf>,
+) -> Result<(String, Option<PathBuf>)> {
+    let sp = spinner::start("Describing instance...");
+    let details = match lookup.describe_instance().await {
+        Ok(details) => {
+            spinner::finish_ok(&sp, &format!("Instance at {}", details.host.cyan()));
+            details
+        }
+        Err(e) => {
+            spinner::finish_failed(&sp, &format!("Describing the instance failed: {e}"));
+            return Err(e);
+        }
+    };
+    let user = user
+        .or(details.user)
+        .ok_or_else(|| anyhow!("Couldn't tell the instance's login user; pass it with --user"))?;
+    Ok((
+        format!("{user}@{}", details.host),
+        identity.or(details.identity),
+    ))
+}
+
 pub async fn run(
     name: String,
-    connection: String,
+    target: AddTarget,
     port: Option<u16>,
     identity: Option<PathBuf>,
     resolve_host: Option<String>,
+    readonly: bool,
+    options: AddOptions,
 ) -> Result<()> {
-    let mut config = ServerRegistry::load()?;
+    let config = ServerRegistry::load()?;
 
     if let Some(existing) = config.get(&name) {
-        if !prompt_overwrite(&name, existing)? {
+        if options.yes || options.force {
+            println!(
+                "{} Overwriting existing server {}",
+                "!".yellow().bold(),
+                name.bold(),
+            );
+        } else if !prompt_overwrite(&name, existing)? {
             return Ok(());
         }
     }
 
+    let discovery = target.discovery();
+    let (connection, identity) = match (target.connection, &discovery) {
+        (Some(connection), _) => (connection, identity),
+        (None, Some(lookup)) => describe(lookup, target.user, identity).await?,
+        (None, None) => return Err(anyhow!("Give a connection string or an instance")),
+    };
+
     let conn_info = parse_connection_string(&connection, port)?;
     let resolve_host = resolve_host.filter(|s| !s.is_empty());
 
-    println!("{} Adding server {}", "+".green().bold(), name.bold(),);
+    println!("{} Adding server {}", "+".green().bold(), name.bold());
     println!(
         "  {} {}@{}:{}",
         "connect:".dimmed(),
@@ -41,10 +148,20 @@ pub async fn run(
         conn_info.host.cyan(),
         conn_info.port.to_string().cyan(),
     );
-    println!("  {}    {}", "path:".dimmed(), conn_info.remote_path.cyan(),);
+    println!("  {}    {}", "path:".dimmed(), conn_info.remote_path.cyan());
     if let Some(ref rh) = resolve_host {
         println!("  {} {}", "resolve:".dimmed(), rh.cyan());
     }
+    if let Some(ref id) = identity {
+        println!(
+            "  {}     {}",
+            "key:".dimmed(),
+            id.display().to_string().cyan()
+        );
+    }
+    if readonly {
+        println!("  {}    {}", "mode:".dimmed(), "readonly".yellow());
+    }
 
     if let Some(ref id) = identity {
         add_key_to_agent(id);
@@ -58,10 +175,21 @@ pub async fn run(
         identity: identity.map(|p| p.to_string_lossy().to_string()),
         auth: server_registry::AuthMethod::Auto,
         resolve_host,
-        metadata: None,
+        readonly,
+        discovery,
+        ..Default::default()
     };
 
-    test_and_save(&name, entry, &mut config).await
+    if options.no_test {
+        ServerRegistry::modify(|config| config.insert(name.clone(), entry))?;
+        println!(
+            "{} Server {} saved without a connection test",
+            "ok".green(),
+            name.bold(),
+        );
+        return Ok(());
+    }
+    test_and_save(&name, entry, options).await
 }
 
 /// Show current config and ask user whether to overwrite.
@@ -147,7 +275,7 @@ fn add_key_to_agent(id: &Path) {
 async fn test_and_save(
     name: &str,
     mut entry: server_registry::ServerEntry,
-    config: &mut ServerRegistry,
+    options: AddOptions,
 ) -> Result<()> {
     let params = params_from_config(name, &entry);
 
@@ -159,7 +287,20 @@ async fn test_and_save(
         }
         Err(e) => {
             spinner::finish_failed(&sp, &format!("Connection failed: {e}"));
-            return prompt_save_on_failure(name, entry, config);
+            if options.force {
+                ServerRegistry::modify(|config| config.insert(name.to_string(), entry))?;
+                println!(
+                    "  {} Saved despite the failed connection test",
+                    "ok".green()
+                );
+                return Ok(());
+            }
+            if !options.yes {
+                prompt_save_on_failure(name, entry)?;
+            }
+            return Err(anyhow!(
+                "Connection test for '{name}' failed (use --force to save anyway)"
+            ));
         }
     };
 
@@ -185,18 +326,13 @@ async fn test_and_save(
         }
     }
 
-    config.insert(name.to_string(), entry);
-    config.save()?;
+    ServerRegistry::modify(|config| config.insert(name.to_string(), entry))?;
     println!("{} Server {} is up and running", "ok".green(), name.bold());
     Ok(())
 }
 
 /// Prompt the user to save config even though the connection failed.
-fn prompt_save_on_failure(
-    name: &str,
-    entry: server_registry::ServerEntry,
-    config: &mut ServerRegistry,
-) -> Result<()> {
+fn prompt_save_on_failure(name: &str, entry: server_registry::ServerEntry) -> Result<()> {
     println!();
     print!("  Save server config anyway? {}: ", "[y/N]".dimmed());
     std::io::stdout().flush()?;
@@ -204,8 +340,7 @@ fn prompt_save_on_failure(
     std::io::stdin().read_line(&mut save_choice)?;
 
     if save_choice.trim().eq_ignore_ascii_case("y") {
-        config.insert(name.to_string(), entry);
-        config.save()?;
+        ServerRegistry::modify(|config| config.insert(name.to_string(), entry))?;
         println!(
             "  {} Saved to {}",
             "ok".green(),
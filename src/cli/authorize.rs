@@ -0,0 +1,339 @@
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use russh::keys::PublicKey;
+
+use crate::connection::{ConnectionParams, SshConnection};
+use crate::server_registry::{AuthMethod, ServerRegistry};
+use crate::utils::path::shell_escape;
+
+use super::params_from_config;
+use super::spinner;
+
+/// Timeout for the remote `authorized_keys` update (30 seconds).
+const AUTHORIZE_TIMEOUT_MS: u64 = 30_000;
+
+/// Default keys looked for when neither `--key` nor the server's identity is set.
+const DEFAULT_KEYS: [&str; 5] = [
+    "id_ed25519",
+    "id_rsa",
+    "id_ecdsa",
+    "id_ed25519_sk",
+    "id_ecdsa_sk",
+];
+
+/// A public key ready to append to `authorized_keys`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorizedKey {
+    /// The line to append: `<type> <base64> [comment]`.
+    pub line: String,
+    /// `<type> <base64>`, used to tell whether the key is already installed.
+    pub key_id: String,
+}
+
+/// Parse the contents of an OpenSSH `.pub` file.
+///
+/// # Errors
+///
+/// Returns an error if the file holds no valid public key.
+pub fn parse_public_key(contents: &str) -> Result<AuthorizedKey> {
+    let line = contents
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+        .ok_or_else(|| anyhow!("no public key found"))?;
+    PublicKey::from_openssh(line).map_err(|e| anyhow!("not an OpenSSH public key: {e}"))?;
+    let mut fields = line.split_whitespace();
+    let (Some(kind), Some(data)) = (fields.next(), fields.next()) else {
+        bail!("not an OpenSSH public key");
+    };
+    Ok(AuthorizedKey {
+        line: line.to_string(),
+        key_id: format!("{kind} {data}"),
+    })
+}
+
+/// Shell command that installs `key` in `~/.ssh/authorized_keys` unless it's
+/// already there, fixing directory and file permissions on the way. Prints
+/// `added` or `present`.
+#[must_use]
+pub fn authorize_command(key: &AuthorizedKey) -> String {
+    format!(
+        "umask 077 && mkdir -p \"$HOME/.ssh\" && f=\"$HOME/.ssh/authorized_keys\" && \
+         touch \"$f\" && chmod 700 \"$HOME/.ssh\" && chmod 600 \"$f\" && \
+         if grep -qF {id} \"$f\"; then echo present; \
+         else {{ [ -s \"$f\" ] && [ -n \"$(tail -c 1 \"$f\")\" ] && echo; printf '%s\\n' {line}; }} >> \"$f\" && echo added; fi",
+        id = shell_escape(&key.key_id),
+        line = shell_escape(&key.line),
+    )
+}
+
+/// Shell command that removes every `authorized_keys` line holding `key`.
+/// Prints `removed` or `absent`.
+#[must_use]
+pub fn remove_key_command(key: &AuthorizedKey) -> String {
+    format!(
+        "umask 077 && f=\"$HOME/.ssh/authorized_keys\" && \
+         if [ -f \"$f\" ] && grep -qF {id} \"$f\"; then \
+         {{ grep -vF {id} \"$f\" || true; }} > \"$f.ssh-hub\" && cat \"$f.ssh-hub\" > \"$f\" && \
+         rm -f \"$f.ssh-hub\" && echo removed; else echo absent; fi",
+        id = shell_escape(&key.key_id),
+    )
+}
+
+pub async fn run(name: &str, password_once: bool, key: Option<PathBuf>) -> Result<()> {
+    let config = ServerRegistry::load()?;
+    let entry = config
+        .get(name)
+        .ok_or_else(|| anyhow!("Server '{name}' not found in config. Run 'ssh-hub add' first."))?
+        .clone();
+    let params = params_from_config(name, &entry);
+
+    let public_path = match key {
+        Some(path) => public_key_path(&path),
+        None => default_public_key(params.identity.as_deref())?,
+    };
+    let public_key = read_public_key(&public_path)?;
+
+    println!(
+        "{} Authorizing {} on {}",
+        ">".blue().bold(),
+        public_path.display().to_string().underline(),
+        name.bold(),
+    );
+
+    let password = if password_once {
+        Some(read_password(&format!(
+            "  Password for {}@{}: ",
+            entry.user, entry.host
+        ))?)
+    } else {
+        None
+    };
+
+    let conn = match connect(&params, password.as_deref()).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            if !password_once {
+                println!(
+                    "  No existing access. Use {} to log in with a password once.",
+                    format!("ssh-hub authorize {name} --password-once").dimmed(),
+                );
+            }
+            return Err(e);
+        }
+    };
+    drop(password);
+    install_key(&conn, &public_key).await?;
+
+    let private_path = public_path.with_extension("");
+    if !verify_key_login(params, &private_path).await {
+        return Ok(());
+    }
+
+    // Remember a non-default key so later connections offer it first.
+    if entry.identity.is_none() && private_path.exists() && !is_default_key(&private_path) {
+        set_identity(name, &private_path)?;
+    }
+
+    println!("{} Server {} accepts your key", "ok".green(), name.bold());
+    Ok(())
+}
+
+/// Connect with existing access, or with `password` when given. Refuses
+/// servers without a POSIX shell, since the key scripts need one.
+pub(super) async fn connect(
+    params: &ConnectionParams,
+    password: Option<&str>,
+) -> Result<SshConnection> {
+    let sp = spinner::start("Connecting...");
+    let connected = match password {
+        Some(password) => SshConnection::connect_with_password(params.clone(), password).await,
+        None => SshConnection::connect(params.clone()).await,
+    };
+    let conn = match connected {
+        Ok(conn) => conn,
+        Err(e) => {
+            spinner::finish_failed(&sp, "connection failed");
+            return Err(e);
+        }
+    };
+    if let Some(error) = conn.posix_required("key installation") {
+        spinner::finish_failed(&sp, "unsupported shell");
+        conn.disconnect().await;
+        bail!(error);
+    }
+    spinner::clear(&sp);
+    Ok(conn)
+}
+
+/// Append `key` to the server's `authorized_keys` unless it's there, then
+/// disconnect.
+pub(super) async fn install_key(conn: &SshConnection, key: &AuthorizedKey) -> Result<()> {
+    let sp = spinner::start("Updating authorized_keys...");
+    let result = conn
+        .exec(&authorize_command(key), Some(AUTHORIZE_TIMEOUT_MS))
+        .await;
+    conn.disconnect().await;
+    match result {
+        Ok(r) if r.exit_code == 0 && r.stdout.trim() == "present" => {
+            spinner::finish_ok(&sp, "Key already authorized");
+            Ok(())
+        }
+        Ok(r) if r.exit_code == 0 => {
+            spinner::finish_ok(&sp, "Key added to ~/.ssh/authorized_keys");
+            Ok(())
+        }
+        Ok(r) => {
+            spinner::finish_failed(&sp, "authorized_keys update failed");
+            bail!("{}", r.stderr.trim());
+        }
+        Err(e) => {
+            spinner::finish_failed(&sp, "authorized_keys update failed");
+            Err(e)
+        }
+    }
+}
+
+/// Remove `key` from the server's `authorized_keys`, then disconnect.
+pub(super) async fn remove_key(conn: &SshConnection, key: &AuthorizedKey) -> Result<()> {
+    let result = conn
+        .exec(&remove_key_command(key), Some(AUTHORIZE_TIMEOUT_MS))
+        .await;
+    conn.disconnect().await;
+    match result? {
+        r if r.exit_code == 0 => Ok(()),
+        r => bail!("{}", r.stderr.trim()),
+    }
+}
+
+/// Read and parse an OpenSSH `.pub` file.
+pub(super) fn read_public_key(path: &Path) -> Result<AuthorizedKey> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    parse_public_key(&contents).with_context(|| format!("Invalid public key {}", path.display()))
+}
+
+/// Point the server's `identity` at `private_path` and authenticate with
+/// that key only.
+pub(super) fn set_identity(name: &str, private_path: &Path) -> Result<()> {
+    let identity = private_path.to_string_lossy().to_string();
+    ServerRegistry::modify(|config| {
+        if let Some(entry) = config.servers.get_mut(name) {
+            entry.identity = Some(identity.clone());
+            entry.auth = AuthMethod::Key;
+        }
+    })?;
+    println!("  {} identity -> {}", "update".blue(), identity.cyan());
+    println!("  {} auth -> {}", "update".blue(), "key".cyan());
+    Ok(())
+}
+
+/// Check that the key now logs in on its own. Prints the outcome.
+pub(super) async fn verify_key_login(mut params: ConnectionParams, private_path: &Path) -> bool {
+    if private_path.exists() {
+        params.identity = Some(private_path.to_path_buf());
+        params.auth_method = AuthMethod::Key;
+    }
+    let sp = spinner::start("Verifying key login...");
+    match SshConnection::connect(params).await {
+        Ok(conn) => {
+            conn.disconnect().await;
+            spinner::finish_ok(&sp, "Key login works");
+            true
+        }
+        Err(e) => {
+            spinner::finish_warn(&sp, &format!("Key login failed: {e}"));
+            println!(
+                "  If the key has a passphrase, load it with {}.",
+                format!("ssh-add {}", private_path.display()).dimmed(),
+            );
+            false
+        }
+    }
+}
+
+/// `path` if it names a `.pub` file, otherwise the `.pub` next to it.
+pub(super) fn public_key_path(path: &Path) -> PathBuf {
+    if path.extension().is_some_and(|e| e == "pub") {
+        path.to_path_buf()
+    } else {
+        let mut public = path.as_os_str().to_owned();
+        public.push(".pub");
+        PathBuf::from(public)
+    }
+}
+
+/// The server's identity key, or the first default key that exists.
+fn default_public_key(identity: Option<&Path>) -> Result<PathBuf> {
+    if let Some(identity) = identity {
+        return Ok(public_key_path(identity));
+    }
+    let ssh_dir = dirs::home_dir()
+        .map(|h| h.join(".ssh"))
+        .ok_or_else(|| anyhow!("Could not determine home directory"))?;
+    DEFAULT_KEYS
+        .iter()
+        .map(|name| ssh_dir.join(format!("{name}.pub")))
+        .find(|p| p.exists())
+        .ok_or_else(|| {
+            anyhow!(
+                "No public key found in {}. Create one with 'ssh-keygen -t ed25519' or pass --key.",
+                ssh_dir.display()
+            )
+        })
+}
+
+fn is_default_key(path: &Path) -> bool {
+    dirs::home_dir().is_some_and(|home| {
+        DEFAULT_KEYS
+            .iter()
+            .any(|name| path == home.join(".ssh").join(name))
+    })
+}
+
+/// Prompt for a password without echoing it. Reads a line from stdin when it
+/// isn't a terminal, so the password can be piped in.
+pub(super) fn read_password(prompt: &str) -> Result<String> {
+    print!("{prompt}");
+    std::io::stdout().flush()?;
+    if !std::io::stdin().is_terminal() {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        return Ok(line.trim_end_matches(['\r', '\n']).to_string());
+    }
+
+    terminal::enable_raw_mode()?;
+    let result = read_password_raw();
+    terminal::disable_raw_mode()?;
+    println!();
+    result
+}
+
+fn read_password_raw() -> Result<String> {
+    let mut password = String::new();
+    loop {
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => return Ok(password),
+            KeyCode::Char('c' | 'd') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                bail!("Aborted");
+            }
+            KeyCode::Char(c) => password.push(c),
+            KeyCode::Backspace => {
+                password.pop();
+            }
+            KeyCode::Esc => bail!("Aborted"),
+            _ => {}
+        }
+    }
+}
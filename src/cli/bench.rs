@@ -0,0 +1,83 @@
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+
+use crate::connection::SshConnection;
+use crate::server_registry::ServerRegistry;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::benchmark_server::handler::{limits, measure};
+use crate::tools::benchmark_server::{BenchmarkServerInput, Throughput, Timing};
+
+use super::params_from_config;
+use super::spinner;
+
+pub async fn run(server: &str, rounds: u32, payload_kb: u64) -> Result<()> {
+    let config = ServerRegistry::load()?;
+    let entry = config
+        .get(server)
+        .ok_or_else(|| anyhow!("Server '{server}' not found in config"))?
+        .clone();
+    let (rounds, payload_bytes) = limits(&BenchmarkServerInput {
+        server: server.to_string(),
+        rounds: Some(rounds),
+        payload_kb: Some(payload_kb),
+    });
+
+    let sp = spinner::start_root(format!("Connecting to {server}..."));
+    let conn = match SshConnection::connect(params_from_config(server, &entry)).await {
+        Ok(c) => c,
+        Err(e) => {
+            spinner::finish_failed(&sp, "connection failed");
+            return Err(e);
+        }
+    };
+    sp.set_message(format!(
+        "Measuring {server} ({rounds} rounds, {} KiB payload)...",
+        payload_bytes / 1024
+    ));
+    let report = measure(
+        &conn,
+        server,
+        rounds,
+        payload_bytes,
+        TimeoutPolicy::for_server(&entry),
+    )
+    .await;
+    conn.disconnect().await;
+    let report = match report {
+        Ok(report) => {
+            spinner::clear(&sp);
+            report
+        }
+        Err(e) => {
+            spinner::finish_failed(&sp, "benchmark failed");
+            return Err(e);
+        }
+    };
+
+    println!(
+        "{}",
+        format!("{:<14}  {:>9}  {:>9}  {:>9}", "", "MIN", "MEDIAN", "MAX").bold()
+    );
+    print_timing("channel open", report.channel_open_ms);
+    print_timing("command", report.command_ms);
+    println!();
+    print_throughput("upload", report.upload);
+    print_throughput("download", report.download);
+    Ok(())
+}
+
+fn print_timing(label: &str, timing: Timing) {
+    println!(
+        "{label:<14}  {:>7.1}ms  {:>7.1}ms  {:>7.1}ms",
+        timing.min, timing.median, timing.max
+    );
+}
+
+fn print_throughput(label: &str, throughput: Throughput) {
+    println!(
+        "{label:<14}  {:>7.2} MiB/s  ({} KiB in {:.0}ms)",
+        throughput.mib_per_sec,
+        throughput.bytes / 1024,
+        throughput.elapsed_ms
+    );
+}
@@ -0,0 +1,84 @@
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+
+use crate::connection::SshConnection;
+use crate::server_registry::ServerRegistry;
+use crate::tools::check_drift::handler::{check, plan_checks};
+use crate::tools::provision::history::ProvisionHistory;
+
+use super::params_from_config;
+use super::provision::print_step;
+use super::spinner;
+
+pub async fn run(server: &str, recipe: Option<&str>) -> Result<()> {
+    let config = ServerRegistry::load()?;
+    let entry = config
+        .get(server)
+        .ok_or_else(|| anyhow!("Server '{server}' not found in config"))?
+        .clone();
+    let applied = ProvisionHistory::load().applied(server);
+    let plan = plan_checks(&config, &applied, server, recipe).map_err(|e| anyhow!(e.message))?;
+    let config_dir = ServerRegistry::config_path()?
+        .parent()
+        .map(std::path::Path::to_path_buf)
+        .ok_or_else(|| anyhow!("Could not determine config directory"))?;
+
+    let sp = spinner::start_root(format!("Connecting to {server}..."));
+    let conn = match SshConnection::connect(params_from_config(server, &entry)).await {
+        Ok(c) => c,
+        Err(e) => {
+            spinner::finish_failed(&sp, "connection failed");
+            return Err(e);
+        }
+    };
+    if let Some(error) = conn.posix_required("check-drift") {
+        spinner::clear(&sp);
+        conn.disconnect().await;
+        return Err(anyhow!(error));
+    }
+    sp.set_message(format!("Checking {server} for drift..."));
+    let output = check(&conn, server, plan, &config_dir).await;
+    spinner::clear(&sp);
+    conn.disconnect().await;
+
+    for drift in &output.recipes {
+        let status = if !drift.errors.is_empty() {
+            "check failed".red()
+        } else if drift.drifted.is_empty() {
+            "in sync".green()
+        } else {
+            format!("{} drifted", drift.drifted.len()).yellow()
+        };
+        println!(
+            "{} {} {} {}  {status}",
+            ">".blue().bold(),
+            drift.recipe.bold(),
+            "on".dimmed(),
+            server.bold(),
+        );
+        for step in drift.drifted.iter().chain(&drift.errors) {
+            print_step(step);
+        }
+        for command in &drift.unchecked {
+            println!("  {} {}", "unchecked   ".dimmed(), command.dimmed());
+        }
+    }
+    for name in &output.missing_recipes {
+        println!(
+            "{} recipe {} was applied but is no longer in servers.toml",
+            "warn".yellow(),
+            name.bold(),
+        );
+    }
+
+    if output.drifted > 0 {
+        return Err(anyhow!(
+            "{} step(s) drifted on '{server}'; run `ssh-hub provision` to reconcile",
+            output.drifted
+        ));
+    }
+    if output.recipes.iter().any(|r| !r.errors.is_empty()) {
+        return Err(anyhow!("Drift check failed on '{server}'"));
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use sha2::{Digest, Sha256};
+use tokio::time::timeout;
+
+use crate::connection::{ConnectionParams, SshConnection};
+use crate::server_registry::ServerRegistry;
+use crate::utils::command::CommandBuilder;
+use crate::utils::path::normalize_remote_path;
+
+use super::params_from_config;
+
+/// Upper bound on connect + listing. Completion must never hang the shell.
+const COMPLETION_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long a cached directory listing is reused.
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Print completion candidates for a `server:path` word, one per line.
+///
+/// Without a `:` the word is completed against configured server names.
+/// Unreachable servers, timeouts, and unknown names produce no output —
+/// completion errors are never shown to the user.
+pub async fn run(word: &str) {
+    let Ok(config) = ServerRegistry::load() else {
+        return;
+    };
+
+    let Some((server, path)) = word.split_once(':') else {
+        for name in config.servers.keys().filter(|n| n.starts_with(word)) {
+            println!("{name}:");
+        }
+        return;
+    };
+    let Some(entry) = config.servers.get(server) else {
+        return;
+    };
+
+    let (dir, prefix) = split_completion_path(path);
+    let remote_dir =
+        normalize_remote_path(if dir.is_empty() { "." } else { dir }, &entry.remote_path);
+
+    let entries = if let Some(entries) = read_cache(server, &remote_dir) {
+        entries
+    } else {
+        let params = params_from_config(server, entry);
+        let Ok(Some(entries)) =
+            timeout(COMPLETION_TIMEOUT, list_remote_dir(params, &remote_dir)).await
+        else {
+            return;
+        };
+        write_cache(server, &remote_dir, &entries);
+        entries
+    };
+
+    for candidate in filter_candidates(&entries, prefix) {
+        println!("{server}:{dir}{candidate}");
+    }
+}
+
+/// Split a partial remote path into the directory to list and the prefix to
+/// match: `src/ma` → (`src/`, `ma`), `ma` → (empty, `ma`).
+#[must_use]
+pub fn split_completion_path(path: &str) -> (&str, &str) {
+    match path.rfind('/') {
+        Some(i) => path.split_at(i + 1),
+        None => ("", path),
+    }
+}
+
+/// Entries (as printed by `ls -1Ap`, directories ending in `/`) that start
+/// with `prefix`. Dotfiles are only offered once the prefix starts with `.`.
+#[must_use]
+pub fn filter_candidates<'a>(entries: &'a [String], prefix: &str) -> Vec<&'a str> {
+    entries
+        .iter()
+        .map(String::as_str)
+        .filter(|e| e.starts_with(prefix) && (prefix.starts_with('.') || !e.starts_with('.')))
+        .collect()
+}
+
+async fn list_remote_dir(params: ConnectionParams, remote_dir: &str) -> Option<Vec<String>> {
+    let conn = SshConnection::connect(params).await.ok()?;
+    let command = CommandBuilder::new("ls")
+        .word("-1Ap")
+        .cwd(remote_dir)
+        .build();
+    let result = conn.exec(&command, None).await;
+    conn.disconnect().await;
+    let result = result.ok().filter(|r| r.exit_code == 0)?;
+    Some(result.stdout.lines().map(ToString::to_string).collect())
+}
+
+fn cache_file(server: &str, remote_dir: &str) -> Option<PathBuf> {
+    let key = hex::encode(Sha256::digest(format!("{server}\0{remote_dir}")));
+    Some(
+        dirs::cache_dir()?
+            .join("ssh-hub")
+            .join("completions")
+            .join(&key[..16]),
+    )
+}
+
+fn read_cache(server: &str, remote_dir: &str) -> Option<Vec<String>> {
+    let path = cache_file(server, remote_dir)?;
+    let age = SystemTime::now()
+        .duration_since(std::fs::metadata(&path).ok()?.modified().ok()?)
+        .ok()?;
+    if age > CACHE_TTL {
+        return None;
+    }
+    let content = std::fs::read_to_string(path).ok()?;
+    Some(content.lines().map(ToString::to_string).collect())
+}
+
+fn write_cache(server: &str, remote_dir: &str, entries: &[String]) {
+    let Some(path) = cache_file(server, remote_dir) else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let _ = std::fs::write(path, entries.join("\n"));
+}
@@ -2,6 +2,7 @@ use std::path::PathBuf;
 
 use anyhow::{anyhow, Result};
 
+use crate::connection::resolve::LocalBind;
 use crate::connection::ConnectionParams;
 use crate::server_registry::ServerEntry;
 
@@ -95,7 +96,17 @@ pub fn params_from_config(name: &str, entry: &ServerEntry) -> ConnectionParams {
             .as_ref()
             .map(|p| PathBuf::from(shellexpand_tilde(p))),
         auth_method: entry.auth.clone(),
+        agent_socket: entry.agent_socket.as_deref().map(shellexpand_tilde),
         server_name: Some(name.to_string()),
+        resolve: entry.resolve.clone(),
+        shell: entry.remote_shell(),
+        max_channels: entry.max_channels,
+        strict_host_key: entry.strict_host_key.unwrap_or_default(),
+        address_family: entry.address_family.unwrap_or_default(),
+        bind: LocalBind {
+            address: entry.bind_address,
+            interface: entry.bind_interface.clone(),
+        },
     }
 }
 
@@ -0,0 +1,359 @@
+//! `ssh-hub daemon`: one long-lived MCP server on the broker socket.
+//!
+//! `daemon install` writes a systemd user unit (Linux) or a launchd agent
+//! (macOS) that runs `ssh-hub daemon run` at login, and enables `[broker]`
+//! so MCP clients relay to it. Each new project window then attaches to a
+//! warm connection pool instead of connecting to every server again.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Result};
+use colored::Colorize;
+
+use crate::profile;
+use crate::server_registry::{BrokerConfig, ServerRegistry};
+
+/// How often `daemon run` checks whether another process still owns the
+/// broker socket.
+#[cfg(unix)]
+const OWNER_POLL: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[derive(clap::Subcommand, Debug, Clone, Copy)]
+pub enum DaemonAction {
+    /// Serve MCP clients on the broker socket until stopped (what the service runs)
+    Run,
+
+    /// Install and start the service that runs `ssh-hub daemon run` at login
+    Install {
+        /// Print the unit or plist instead of installing it
+        #[arg(long)]
+        print: bool,
+
+        /// Write the service file without starting it
+        #[arg(long)]
+        no_start: bool,
+    },
+
+    /// Stop the service and remove its file
+    Uninstall,
+}
+
+/// Service managers `daemon install` writes for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Manager {
+    Systemd,
+    Launchd,
+}
+
+impl Manager {
+    fn current() -> Result<Self> {
+        if cfg!(target_os = "macos") {
+            Ok(Self::Launchd)
+        } else if cfg!(target_os = "linux") {
+            Ok(Self::Systemd)
+        } else {
+            bail!("ssh-hub daemon supports systemd (Linux) and launchd (macOS) only")
+        }
+    }
+
+    /// Where the service file goes.
+    fn file(self, name: &str) -> Result<PathBuf> {
+        let home = dirs::home_dir().ok_or_else(|| anyhow!("No home directory"))?;
+        Ok(match self {
+            Self::Systemd => dirs::config_dir()
+                .unwrap_or_else(|| home.join(".config"))
+                .join("systemd/user")
+                .join(format!("{name}.service")),
+            Self::Launchd => home
+                .join("Library/LaunchAgents")
+                .join(format!("{}.plist", launchd_label(name))),
+        })
+    }
+}
+
+pub async fn run(action: DaemonAction) -> Result<()> {
+    match action {
+        DaemonAction::Run => serve().await,
+        DaemonAction::Install { print, no_start } => install(print, no_start),
+        DaemonAction::Uninstall => uninstall(),
+    }
+}
+
+/// `ssh-hub` for the default profile, `ssh-hub-<profile>` otherwise.
+fn service_name() -> String {
+    profile::scoped("ssh-hub", profile::current())
+}
+
+fn launchd_label(name: &str) -> String {
+    format!("com.perceptron-studios.{name}")
+}
+
+/// Arguments the service starts ssh-hub with: the profile, and the config
+/// file when it isn't the default one, since the service doesn't see this
+/// shell's environment.
+fn service_args() -> Vec<String> {
+    let mut args = Vec::new();
+    if let (Ok(config), Ok(default)) = (
+        ServerRegistry::config_path(),
+        ServerRegistry::config_path_from(None, None, profile::current()),
+    ) {
+        if config != default {
+            args.push("--config".to_string());
+            args.push(config.to_string_lossy().into_owned());
+        }
+    }
+    if let Some(name) = profile::current() {
+        args.push("--profile".to_string());
+        args.push(name.to_string());
+    }
+    args.extend(["daemon".to_string(), "run".to_string()]);
+    args
+}
+
+/// A systemd user unit that runs `exe args` at login and restarts it if it
+/// fails.
+#[must_use]
+pub fn systemd_unit(exe: &Path, args: &[String]) -> String {
+    let command: Vec<String> = std::iter::once(exe.to_string_lossy().into_owned())
+        .chain(args.iter().cloned())
+        .map(|word| systemd_quote(&word))
+        .collect();
+    format!(
+        "[Unit]\n\
+         Description=ssh-hub MCP server shared by MCP clients\n\
+         Documentation=https://github.com/Perceptron-Studios/ssh-hub\n\
+         \n\
+         [Service]\n\
+         ExecStart={}\n\
+         Restart=on-failure\n\
+         RestartSec=5\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        command.join(" ")
+    )
+}
+
+/// Quote a word for `ExecStart=`, escaping systemd's `%` specifiers and `$`
+/// variable expansion.
+fn systemd_quote(word: &str) -> String {
+    let word = word.replace('%', "%%").replace('$', "$$");
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/._-+=:@,%$".contains(c));
+    if plain {
+        word
+    } else {
+        format!("\"{}\"", word.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// A launchd agent that runs `exe args` at login and keeps it running,
+/// logging to `log`.
+#[must_use]
+pub fn launchd_plist(label: &str, exe: &Path, args: &[String], log: &Path) -> String {
+    let mut arguments = String::new();
+    for arg in std::iter::once(exe.to_string_lossy().into_owned()).chain(args.iter().cloned()) {
+        let _ = writeln!(arguments, "        <string>{}</string>", xml_escape(&arg));
+    }
+    let log = xml_escape(&log.to_string_lossy());
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \
+         \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n\
+         \x20   <key>Label</key>\n\
+         \x20   <string>{}</string>\n\
+         \x20   <key>ProgramArguments</key>\n\
+         \x20   <array>\n\
+         {arguments}\
+         \x20   </array>\n\
+         \x20   <key>RunAtLoad</key>\n\
+         \x20   <true/>\n\
+         \x20   <key>KeepAlive</key>\n\
+         \x20   <dict>\n\
+         \x20       <key>SuccessfulExit</key>\n\
+         \x20       <false/>\n\
+         \x20   </dict>\n\
+         \x20   <key>StandardErrorPath</key>\n\
+         \x20   <string>{log}</string>\n\
+         </dict>\n\
+         </plist>\n",
+        xml_escape(label),
+    )
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The service file's content for this ssh-hub binary.
+fn render(manager: Manager, name: &str) -> Result<String> {
+    let exe = std::env::current_exe()?;
+    let exe = exe.canonicalize().unwrap_or(exe);
+    let args = service_args();
+    Ok(match manager {
+        Manager::Systemd => systemd_unit(&exe, &args),
+        Manager::Launchd => {
+            let home = dirs::home_dir().ok_or_else(|| anyhow!("No home directory"))?;
+            let log = home.join("Library/Logs").join(format!("{name}.log"));
+            launchd_plist(&launchd_label(name), &exe, &args, &log)
+        }
+    })
+}
+
+fn install(print: bool, no_start: bool) -> Result<()> {
+    let manager = Manager::current()?;
+    let name = service_name();
+    let content = render(manager, &name)?;
+    if print {
+        print!("{content}");
+        return Ok(());
+    }
+
+    let file = manager.file(&name)?;
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&file, content)?;
+    println!(
+        "{} Wrote {}",
+        "ok".green(),
+        file.display().to_string().underline()
+    );
+
+    ServerRegistry::modify(|config| {
+        config
+            .broker
+            .get_or_insert_with(BrokerConfig::default)
+            .enabled = true;
+    })?;
+    println!(
+        "{} Enabled [broker] in {}, so MCP clients relay to the daemon",
+        "ok".green(),
+        ServerRegistry::config_path()?.display(),
+    );
+
+    if no_start {
+        return Ok(());
+    }
+    match manager {
+        Manager::Systemd => {
+            service_command("systemctl", &["--user", "daemon-reload"])?;
+            service_command(
+                "systemctl",
+                &["--user", "enable", "--now", &format!("{name}.service")],
+            )?;
+            println!(
+                "{} Started {name}.service; logs: {}",
+                "ok".green(),
+                format!("journalctl --user -u {name}").dimmed(),
+            );
+        }
+        Manager::Launchd => {
+            let plist = file.to_string_lossy();
+            // Replace an agent loaded by an earlier install
+            let _ = service_command("launchctl", &["unload", &plist]);
+            service_command("launchctl", &["load", "-w", &plist])?;
+            println!("{} Loaded {}", "ok".green(), launchd_label(&name));
+        }
+    }
+    Ok(())
+}
+
+fn uninstall() -> Result<()> {
+    let manager = Manager::current()?;
+    let name = service_name();
+    let file = manager.file(&name)?;
+    if !file.exists() {
+        println!(
+            "{} No service installed at {}",
+            "!".yellow().bold(),
+            file.display()
+        );
+        return Ok(());
+    }
+    let stopped = match manager {
+        Manager::Systemd => service_command(
+            "systemctl",
+            &["--user", "disable", "--now", &format!("{name}.service")],
+        ),
+        Manager::Launchd => {
+            service_command("launchctl", &["unload", "-w", &file.to_string_lossy()])
+        }
+    };
+    if let Err(e) = stopped {
+        println!("  {} {e}", "warn".yellow());
+    }
+    std::fs::remove_file(&file)?;
+    if manager == Manager::Systemd {
+        let _ = service_command("systemctl", &["--user", "daemon-reload"]);
+    }
+    println!(
+        "{} Removed {}. [broker] stays enabled: the first MCP client to start \
+         serves the others, as before.",
+        "ok".green(),
+        file.display()
+    );
+    Ok(())
+}
+
+/// Run `systemctl` or `launchctl`, failing with its stderr.
+fn service_command(program: &str, args: &[&str]) -> Result<()> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| anyhow!("Failed to run {program}: {e}"))?;
+    if !output.status.success() {
+        bail!(
+            "{program} {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Own the broker socket and serve every client relayed through it. When
+/// another ssh-hub owns the socket, such as an MCP server started before
+/// the daemon, wait for it to exit and take over.
+#[cfg(unix)]
+async fn serve() -> Result<()> {
+    use crate::broker::{self, Role};
+    use crate::server::RemoteSessionServer;
+
+    let path = broker::socket_path()
+        .ok_or_else(|| anyhow!("No runtime directory for the broker socket"))?;
+    let config = ServerRegistry::load().unwrap_or_else(|e| {
+        tracing::warn!("Failed to load config, starting with empty config: {e}");
+        ServerRegistry::default()
+    });
+    let server = RemoteSessionServer::new(config);
+    loop {
+        match broker::claim(&path).await? {
+            Role::Owner(listener) => {
+                tracing::info!("Daemon listening on {}", path.display());
+                return server.run_daemon(listener).await;
+            }
+            Role::Client(stream) => {
+                drop(stream);
+                tracing::info!(
+                    "Another ssh-hub owns {}; waiting for it to exit",
+                    path.display()
+                );
+                tokio::time::sleep(OWNER_POLL).await;
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn serve() -> Result<()> {
+    bail!("ssh-hub daemon needs the broker socket, which is unix-only")
+}
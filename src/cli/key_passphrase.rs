@@ -0,0 +1,86 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+
+use crate::connection::{decrypt_key, is_encrypted_key_error};
+use crate::keychain;
+use crate::server_registry::ServerRegistry;
+
+use super::authorize;
+use super::params_from_config;
+
+/// Keys ssh-hub tries when a server has no identity file, in order.
+const DEFAULT_KEYS: [&str; 3] = ["id_ed25519", "id_rsa", "id_ecdsa"];
+
+pub async fn run(name: &str, delete: bool) -> Result<()> {
+    let config = ServerRegistry::load()?;
+    let entry = config
+        .get(name)
+        .ok_or_else(|| anyhow!("Server '{name}' not found in config"))?
+        .clone();
+    let key_path = encrypted_key(params_from_config(name, &entry).identity).await?;
+
+    if delete {
+        if keychain::delete_key_passphrase(&key_path).await? {
+            println!(
+                "{} Passphrase for {} removed from the keychain.",
+                "-".red().bold(),
+                key_path.display().to_string().bold(),
+            );
+        } else {
+            println!(
+                "{} No passphrase stored for {}.",
+                "!".yellow().bold(),
+                key_path.display().to_string().bold(),
+            );
+        }
+        return Ok(());
+    }
+
+    let passphrase =
+        authorize::read_password(&format!("  Passphrase for {}: ", key_path.display()))?;
+    decrypt_key(&key_path, Some(passphrase.clone()))
+        .await
+        .with_context(|| {
+            format!(
+                "The passphrase doesn't decrypt {}, so it was not stored",
+                key_path.display()
+            )
+        })?;
+    keychain::store_key_passphrase(&key_path, &passphrase).await?;
+    println!(
+        "{} Passphrase for {} stored in the keychain. ssh-hub decrypts the key with it when ssh-agent doesn't have it.",
+        "ok".green(),
+        key_path.display().to_string().bold(),
+    );
+    Ok(())
+}
+
+/// Ask for the passphrase of an encrypted key while connecting. Set as the
+/// connection prompt when the CLI runs in a terminal.
+pub(super) fn prompt(path: &Path) -> Option<String> {
+    authorize::read_password(&format!("  Passphrase for {}: ", path.display()))
+        .ok()
+        .filter(|passphrase| !passphrase.is_empty())
+}
+
+/// The server's identity file, or else the first encrypted default key.
+async fn encrypted_key(identity: Option<PathBuf>) -> Result<PathBuf> {
+    if let Some(path) = identity {
+        return Ok(path);
+    }
+    let home = dirs::home_dir().ok_or_else(|| anyhow!("Cannot find the home directory"))?;
+    for name in DEFAULT_KEYS {
+        let path = home.join(".ssh").join(name);
+        if !path.exists() {
+            continue;
+        }
+        if let Err(e) = decrypt_key(&path, None).await {
+            if is_encrypted_key_error(&e) {
+                return Ok(path);
+            }
+        }
+    }
+    bail!("The server has no identity file and none of the default keys in ~/.ssh is encrypted")
+}
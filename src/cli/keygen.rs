@@ -0,0 +1,146 @@
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+use russh::keys::ssh_key::rand_core::OsRng;
+use russh::keys::ssh_key::LineEnding;
+use russh::keys::{Algorithm, PrivateKey};
+
+use crate::connection::resolve::is_unreachable;
+use crate::connection::{ConnectionParams, SshConnection};
+use crate::server_registry::ServerRegistry;
+
+use super::authorize::{self, AuthorizedKey};
+use super::params_from_config;
+
+/// File name of the key `keygen` writes when `--output` isn't given.
+const DEFAULT_KEY_NAME: &str = "ssh-hub_ed25519";
+
+/// Comment stored in generated keys when `--comment` isn't given.
+const DEFAULT_COMMENT: &str = "ssh-hub";
+
+pub async fn run(
+    servers: Vec<String>,
+    output: Option<PathBuf>,
+    comment: Option<String>,
+) -> Result<()> {
+    let config = ServerRegistry::load()?;
+    if let Some(missing) = servers.iter().find(|s| config.get(s).is_none()) {
+        bail!("Server '{missing}' not found in config");
+    }
+
+    let private_path = match output {
+        Some(path) => path,
+        None => ssh_dir()?.join(DEFAULT_KEY_NAME),
+    };
+    let key = generate(&private_path, comment.as_deref().unwrap_or(DEFAULT_COMMENT))?;
+    println!(
+        "{} Generated ed25519 key {}",
+        "+".green().bold(),
+        private_path.display().to_string().underline(),
+    );
+
+    let mut failed = Vec::new();
+    for name in &servers {
+        let Some(entry) = config.get(name) else {
+            continue;
+        };
+        println!();
+        println!("{} Installing on {}", ">".blue().bold(), name.bold());
+        let params = params_from_config(name, entry);
+        let installed = match connect(&params, &entry.user, &entry.host).await {
+            Ok(conn) => authorize::install_key(&conn, &key).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = installed {
+            println!("  {} {e}", "x".red());
+            failed.push(name.as_str());
+            continue;
+        }
+        if authorize::verify_key_login(params, &private_path).await {
+            authorize::set_identity(name, &private_path)?;
+        } else {
+            failed.push(name.as_str());
+        }
+    }
+
+    if !failed.is_empty() {
+        bail!("Key not installed on: {}", failed.join(", "));
+    }
+    if servers.is_empty() {
+        println!(
+            "  Install it with {}.",
+            format!(
+                "ssh-hub authorize <server> --key {}",
+                private_path.display()
+            )
+            .dimmed(),
+        );
+    }
+    Ok(())
+}
+
+/// Log in with existing access. When that fails for a reason other than
+/// the server being unreachable, and someone is at the terminal, ask for
+/// the password once, like `ssh-copy-id`.
+async fn connect(params: &ConnectionParams, user: &str, host: &str) -> Result<SshConnection> {
+    let error = match authorize::connect(params, None).await {
+        Ok(conn) => return Ok(conn),
+        Err(e) => e,
+    };
+    if is_unreachable(&error) || !std::io::stdin().is_terminal() {
+        return Err(error);
+    }
+    println!("  No existing access ({error}).");
+    let password = authorize::read_password(&format!("  Password for {user}@{host}: "))?;
+    authorize::connect(params, Some(&password)).await
+}
+
+/// Write a new ed25519 key pair to `private_path` and `<private_path>.pub`.
+/// Refuses to overwrite existing files.
+///
+/// # Errors
+///
+/// Returns an error if either file exists or can't be written.
+pub(super) fn generate(private_path: &Path, comment: &str) -> Result<AuthorizedKey> {
+    let public_path = authorize::public_key_path(private_path);
+    for path in [private_path, public_path.as_path()] {
+        if path.exists() {
+            bail!(
+                "{} already exists; pass --output to write the key elsewhere",
+                path.display()
+            );
+        }
+    }
+    if let Some(dir) = private_path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        if !dir.exists() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+            }
+        }
+    }
+
+    let mut key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519)
+        .map_err(|e| anyhow!("Failed to generate key: {e}"))?;
+    key.set_comment(comment);
+    key.write_openssh_file(private_path, LineEnding::LF)
+        .with_context(|| format!("Failed to write {}", private_path.display()))?;
+    let public = key
+        .public_key()
+        .to_openssh()
+        .map_err(|e| anyhow!("Failed to encode public key: {e}"))?;
+    std::fs::write(&public_path, format!("{public}\n"))
+        .with_context(|| format!("Failed to write {}", public_path.display()))?;
+    authorize::parse_public_key(&public)
+}
+
+pub(super) fn ssh_dir() -> Result<PathBuf> {
+    dirs::home_dir()
+        .map(|h| h.join(".ssh"))
+        .ok_or_else(|| anyhow!("Could not determine home directory"))
+}
@@ -1,34 +1,13 @@
-use std::time::{Duration, Instant};
-
 use anyhow::Result;
 use colored::Colorize;
-use futures::future::join_all;
-use tokio::net::TcpStream;
-use tokio::time::timeout;
 
 use crate::metadata::SystemMetadata;
 use crate::server_registry::{ServerEntry, ServerRegistry};
 
+use super::probe::{bounded, probe_reachability};
 use super::spinner;
 
-const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
-
-/// TCP-level reachability probe to the SSH port.
-///
-/// Returns `(reachable, latency_ms)` — latency is `Some` only on success.
-async fn probe_reachability(host: &str, port: u16) -> (bool, Option<u32>) {
-    let addr = format!("{host}:{port}");
-    let start = Instant::now();
-    match timeout(PROBE_TIMEOUT, TcpStream::connect(&addr)).await {
-        Ok(Ok(_)) => {
-            let ms = u32::try_from(start.elapsed().as_millis()).unwrap_or(u32::MAX);
-            (true, Some(ms))
-        }
-        _ => (false, None),
-    }
-}
-
-pub async fn run(no_probe: bool) -> Result<()> {
+pub async fn run(no_probe: bool, jobs: usize, tags: &[String]) -> Result<()> {
     let config = ServerRegistry::load()?;
 
     if config.servers.is_empty() {
@@ -40,8 +19,21 @@ pub async fn run(no_probe: bool) -> Result<()> {
         return Ok(());
     }
 
+    let servers: Vec<_> = config
+        .servers
+        .iter()
+        .filter(|(_, entry)| entry.has_tags(tags))
+        .collect();
+    if servers.is_empty() {
+        println!(
+            "{}",
+            format!("No servers tagged {}.", tags.join(", ")).dimmed()
+        );
+        return Ok(());
+    }
+
     if no_probe {
-        for (name, entry) in &config.servers {
+        for (name, entry) in servers {
             print_server(name, entry);
         }
         return Ok(());
@@ -49,11 +41,10 @@ pub async fn run(no_probe: bool) -> Result<()> {
 
     // Probe all servers concurrently behind a single spinner.
     let sp = spinner::start_root("Probing reachability...");
-    let servers: Vec<_> = config.servers.iter().collect();
     let probes = servers
         .iter()
-        .map(|(_, entry)| probe_reachability(&entry.host, entry.port));
-    let results = join_all(probes).await;
+        .map(|(_, entry)| probe_reachability(entry.connect_host(), entry.port));
+    let results = bounded(probes, jobs).await;
     spinner::clear(&sp);
 
     for ((name, entry), (reachable, latency_ms)) in servers.iter().zip(results) {
@@ -93,6 +84,25 @@ fn print_server(name: &str, entry: &ServerEntry) {
     if let Some(ref cmd) = entry.resolve_host {
         println!("  {} {}", "resolve:".dimmed(), cmd.cyan());
     }
+    if let Some(ref ip) = entry.resolve {
+        println!("  {} {}", "pinned:".dimmed(), ip.cyan());
+    }
+    let shell = entry.remote_shell();
+    if !shell.is_posix() {
+        println!("  {} {}", "shell:".dimmed(), shell.to_string().cyan());
+    }
+    if let Some(replica) = &entry.read_replica {
+        println!("  {} {}", "reads:".dimmed(), replica.cyan());
+    }
+    if entry.readonly {
+        println!("  {} {}", "mode:".dimmed(), "readonly".yellow());
+    }
+    if let Some(root) = &entry.scratch_root {
+        println!("  {} {}", "scratch:".dimmed(), root.cyan());
+    }
+    if !entry.tags.is_empty() {
+        println!("  {}    {}", "tags:".dimmed(), entry.tags.join(", ").cyan());
+    }
     if let Some(meta) = format_metadata(entry) {
         println!("{meta}");
     }
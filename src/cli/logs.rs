@@ -0,0 +1,127 @@
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+
+use crate::connection::SshConnection;
+use crate::server_registry::ServerRegistry;
+use crate::tools::remote_bash::handler::{background_job_id, background_log_file};
+use crate::utils::command::CommandBuilder;
+use crate::utils::path::shell_escape;
+
+use super::params_from_config;
+use super::spinner;
+use super::status::format_age;
+use super::tui::{parse_background_jobs, unix_now, JOBS_COMMAND};
+
+/// Command printing the last `lines` lines of `log_file`. With `follow`, it
+/// keeps printing until the job stops writing to the log: `tail --pid` ends
+/// with the process still holding it open, found through `/proc`. Without
+/// `/proc` or GNU `tail`, it follows until interrupted.
+#[must_use]
+pub fn logs_command(log_file: &str, lines: usize, follow: bool) -> String {
+    if !follow {
+        return CommandBuilder::new("tail")
+            .words(["-n", &lines.to_string()])
+            .arg(log_file)
+            .build();
+    }
+    let file = shell_escape(log_file);
+    format!(
+        "f={file}; [ -f \"$f\" ] || {{ echo \"tail: $f: No such file or directory\" >&2; exit 1; }}; \
+         if [ -d /proc/self/fd ]; then \
+         pid=; for p in /proc/[0-9]*; do \
+         ls -l \"$p/fd\" 2>/dev/null | grep -qF \"$f\" && {{ pid=${{p#/proc/}}; break; }}; done; \
+         [ -n \"$pid\" ] || exec tail -n {lines} \"$f\"; \
+         tail --pid=\"$pid\" -n 0 /dev/null 2>/dev/null && exec tail -n {lines} --pid=\"$pid\" -f \"$f\"; \
+         fi; exec tail -n {lines} -f \"$f\""
+    )
+}
+
+pub async fn run(server: &str, job: Option<&str>, lines: usize, follow: bool) -> Result<()> {
+    let config = ServerRegistry::load()?;
+    let entry = config
+        .get(server)
+        .ok_or_else(|| anyhow!("Server '{server}' not found in config"))?
+        .clone();
+    let log_file = match job {
+        Some(job) => Some(
+            background_log_file(job)
+                .or_else(|| background_job_id(job).map(|_| job.to_string()))
+                .ok_or_else(|| {
+                    anyhow!("'{job}' is not a job ID. Run `ssh-hub logs {server}` to list jobs")
+                })?,
+        ),
+        None => None,
+    };
+
+    let sp = spinner::start_root(format!("Connecting to {server}..."));
+    let conn = match SshConnection::connect(params_from_config(server, &entry)).await {
+        Ok(c) => c,
+        Err(e) => {
+            spinner::finish_failed(&sp, "connection failed");
+            return Err(e);
+        }
+    };
+    spinner::clear(&sp);
+    if let Some(error) = conn.posix_required("logs") {
+        conn.disconnect().await;
+        return Err(anyhow!(error));
+    }
+
+    let result = match log_file {
+        Some(log_file) => print_log(&conn, server, &log_file, lines, follow).await,
+        None => list_jobs(&conn, server).await,
+    };
+    conn.disconnect().await;
+    result
+}
+
+async fn print_log(
+    conn: &SshConnection,
+    server: &str,
+    log_file: &str,
+    lines: usize,
+    follow: bool,
+) -> Result<()> {
+    let command = logs_command(log_file, lines, follow);
+    let result = conn
+        .exec_stream_stdout(&command, None, tokio::io::stdout(), None)
+        .await?;
+    if result.exit_code != 0 {
+        let stderr = result.stderr.trim();
+        if stderr.contains("No such file") {
+            return Err(anyhow!(
+                "{log_file} doesn't exist on {server}; the job may never have run there, or /tmp was cleaned up"
+            ));
+        }
+        return Err(anyhow!("tail exited with {}: {stderr}", result.exit_code));
+    }
+    Ok(())
+}
+
+async fn list_jobs(conn: &SshConnection, server: &str) -> Result<()> {
+    let result = conn.exec(JOBS_COMMAND, None).await?;
+    let jobs = parse_background_jobs(&result.stdout);
+    if jobs.is_empty() {
+        println!("No background jobs on {server}.");
+        return Ok(());
+    }
+    let now = unix_now();
+    for job in &jobs {
+        let state = match job.running {
+            Some(true) => "running".green(),
+            Some(false) => "done   ".dimmed(),
+            None => "?      ".dimmed(),
+        };
+        println!(
+            "{state}  {:<16} {:>5} ago {:>9}B",
+            background_job_id(&job.log_file).unwrap_or(&job.log_file),
+            format_age(now.saturating_sub(job.modified)),
+            job.bytes,
+        );
+    }
+    println!(
+        "\n{}",
+        format!("Show one with: ssh-hub logs {server} <job-id> [--follow]").dimmed()
+    );
+    Ok(())
+}
@@ -3,7 +3,19 @@ use std::path::Path;
 use anyhow::Result;
 use colored::Colorize;
 
-pub fn run(directory: &Path, claude: bool, codex: bool) -> Result<()> {
+use crate::profile;
+use crate::server_registry::{ProjectMapping, ServerRegistry};
+
+/// `server` and `remote_path` record which server backs the project, for
+/// sync tools to fill in the path on the other side. Under a `--profile`,
+/// the installed server is started with the same profile.
+pub fn run(
+    directory: &Path,
+    claude: bool,
+    codex: bool,
+    server: Option<String>,
+    remote_path: Option<String>,
+) -> Result<()> {
     // When neither flag is provided, configure both
     let (do_claude, do_codex) = if !claude && !codex {
         (true, true)
@@ -19,17 +31,56 @@ pub fn run(directory: &Path, claude: bool, codex: bool) -> Result<()> {
         return Err(anyhow::anyhow!("'{}' is not a directory", target.display()));
     }
 
+    if let Some(server) = server {
+        record_project(&target, &server, remote_path)?;
+    }
+
+    let args = server_args(profile::current());
     if do_claude {
-        install_claude_config(&target)?;
+        install_claude_config(&target, &args)?;
     }
     if do_codex {
-        install_codex_config(&target)?;
+        install_codex_config(&target, &args)?;
     }
 
     Ok(())
 }
 
-fn install_claude_config(target: &Path) -> Result<()> {
+fn record_project(target: &Path, server: &str, remote_path: Option<String>) -> Result<()> {
+    let key = target.to_string_lossy().to_string();
+    let mapping = ServerRegistry::modify(|config| {
+        let entry = config.get(server).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Server '{server}' not found. Use 'ssh-hub list' to see configured servers."
+            )
+        })?;
+        let mapping = ProjectMapping {
+            server: server.to_string(),
+            remote_path: remote_path.unwrap_or_else(|| entry.remote_path.clone()),
+        };
+        config.projects.insert(key, mapping.clone());
+        Ok::<_, anyhow::Error>(mapping)
+    })??;
+
+    println!(
+        "  {} Project: {} -> {}:{}",
+        "ok".green(),
+        target.display().to_string().dimmed(),
+        mapping.server,
+        mapping.remote_path,
+    );
+    Ok(())
+}
+
+/// Arguments the MCP client starts `ssh-hub` with.
+#[must_use]
+pub fn server_args(profile: Option<&str>) -> Vec<String> {
+    profile
+        .map(|name| vec!["--profile".to_string(), name.to_string()])
+        .unwrap_or_default()
+}
+
+fn install_claude_config(target: &Path, args: &[String]) -> Result<()> {
     let path = target.join(".mcp.json");
 
     let mut root: serde_json::Value = if path.exists() {
@@ -54,7 +105,7 @@ fn install_claude_config(target: &Path) -> Result<()> {
         "ssh-hub".to_string(),
         serde_json::json!({
             "command": "ssh-hub",
-            "args": []
+            "args": args
         }),
     );
 
@@ -69,7 +120,7 @@ fn install_claude_config(target: &Path) -> Result<()> {
     Ok(())
 }
 
-fn install_codex_config(target: &Path) -> Result<()> {
+fn install_codex_config(target: &Path, args: &[String]) -> Result<()> {
     let codex_dir = target.join(".codex");
     let path = codex_dir.join("config.toml");
 
@@ -95,7 +146,10 @@ fn install_codex_config(target: &Path) -> Result<()> {
         "command".to_string(),
         toml::Value::String("ssh-hub".to_string()),
     );
-    entry.insert("args".to_string(), toml::Value::Array(vec![]));
+    entry.insert(
+        "args".to_string(),
+        toml::Value::Array(args.iter().cloned().map(toml::Value::String).collect()),
+    );
 
     servers_table.insert("ssh-hub".to_string(), toml::Value::Table(entry));
 
@@ -1,18 +1,48 @@
 mod add;
+mod authorize;
+mod bench;
+mod check_drift;
+mod complete;
 mod connection;
+mod daemon;
+mod key_passphrase;
+mod keygen;
 mod list;
+mod logs;
 mod mcp_install;
+mod pool;
+mod probe;
+mod provision;
 mod remove;
+mod rotate_keys;
+mod run;
 mod spinner;
+mod status;
+mod sudo_password;
+mod support_bundle;
+mod tui;
+mod tunnel;
 mod update;
 mod upgrade;
 
+use std::io::IsTerminal;
 use std::path::PathBuf;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 
+pub use add::{AddOptions, AddTarget};
+pub use authorize::remove_key_command;
+pub use authorize::{authorize_command, parse_public_key, AuthorizedKey};
+pub use complete::{filter_candidates, split_completion_path};
 pub use connection::{params_from_config, parse_connection_string, ConnectionInfo};
+pub use daemon::{launchd_plist, systemd_unit, DaemonAction};
+pub use logs::logs_command;
+pub use remove::select_removals;
+pub use rotate_keys::select_servers;
+pub use status::{format_age, parse_banner};
+pub use support_bundle::sanitize_registry;
+pub use tui::{parse_background_jobs, BackgroundJob};
 
 /// MCP server for remote SSH sessions
 #[derive(Parser, Debug)]
@@ -21,12 +51,13 @@ pub use connection::{params_from_config, parse_connection_string, ConnectionInfo
 #[command(long_about = "\
 MCP server that exposes remote file operations and shell execution over SSH.
 
-Configured servers are stored in ~/.config/ssh-hub/servers.toml. MCP tools \
+Configured servers are stored in ~/.config/ssh-hub/servers.toml, or the file \
+named by --config or SSH_HUB_CONFIG. MCP tools \
 auto-connect on first use — no manual connection step needed.")]
 #[command(after_long_help = "\
 SSH KEY SETUP:
     Your SSH public key must be authorized on the remote server:
-      ssh-copy-id -i ~/.ssh/id_ed25519.pub user@host
+      ssh-hub authorize <server> --password-once
 
     For cloud VMs (GCP, AWS), bind the key via the provider's metadata service.
     See: https://github.com/Perceptron-Studios/ssh-hub/blob/main/docs/server-setup.md
@@ -52,13 +83,36 @@ AGENTS:
       Register a new server. Required before MCP tools can reach it.
       Run `ssh-hub add --help` for connection string formats.
 
-    Other commands (list, remove, upgrade, mcp-install) are self-explanatory
+    MCP server logs, including connection and auth failures, are written to
+    ~/.local/share/ssh-hub/logs/ssh-hub.log.<date> unless [logging] in
+    servers.toml says otherwise.
+
+    Other commands (list, status, pool, tui, logs, remove, authorize, keygen, rotate-keys, sudo-password, provision, check-drift, upgrade, mcp-install, support-bundle) are self-explanatory
     from the descriptions above.")]
 pub struct Cli {
     /// Enable verbose logging
     #[arg(short = 'v', long = "verbose", global = true)]
     pub verbose: bool,
 
+    /// Plain-ASCII output without Unicode spinners or arrows (auto-detected from TERM and locale)
+    #[arg(long, global = true)]
+    pub ascii: bool,
+
+    /// Also write logs to this file, rotated daily (the MCP server defaults to
+    /// ~/.local/share/ssh-hub/logs/ssh-hub.log)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Use this config file instead of ~/.config/ssh-hub/servers.toml (overrides
+    /// `SSH_HUB_CONFIG` and `SSH_HUB_CONFIG_DIR`)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Use a separate set of servers and secrets: servers-<NAME>.toml, keychain
+    /// entries, and broker socket of their own
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
     #[command(subcommand)]
     pub command: Option<Command>,
 }
@@ -71,7 +125,12 @@ Add a server to the config (or reconfigure an existing one).
 
 Tests SSH connectivity and collects system metadata (OS, arch, package manager) \
 on success. Use -i for passphrase-protected keys — runs ssh-add to load the key \
-into the agent (prompts once for the passphrase).")]
+into the agent (prompts once for the passphrase).
+
+If the connection test fails, asks whether to save the entry anyway and exits \
+non-zero. For scripts and CI, --yes answers every prompt without asking (and \
+doesn't save a failing server), --force also saves it, and --no-test skips \
+the connection test entirely.")]
     #[command(after_long_help = "\
 CONNECTION FORMATS:
     user@host              Port 22, path ~
@@ -82,13 +141,26 @@ CONNECTION FORMATS:
 EXAMPLES:
     ssh-hub add prod deploy@10.0.0.5:/var/www
     ssh-hub add dev me@devbox
-    ssh-hub add gpu root@gpu-server:2222 -i ~/.ssh/gpu_key")]
+    ssh-hub add gpu root@gpu-server:2222 -i ~/.ssh/gpu_key
+    ssh-hub add prod-db ops@db1 --readonly       Diagnostics only: MCP tools refuse changes
+    ssh-hub add ci deploy@10.0.0.9 --yes         For scripts: no prompts, exit 1 if the connection fails
+    ssh-hub add new1 root@10.0.0.10 --no-test    Save without connecting (e.g. before ssh-hub authorize)
+
+CLOUD INSTANCES:
+    With --aws-instance or --gcp-instance instead of a connection string, the
+    address, login user, and key are looked up with the aws or gcloud CLI, and
+    the instance is saved as the server's discovery lookup so a new IP after a
+    restart is found again on connect and by ssh-hub update.
+
+    ssh-hub add web --gcp-instance web-1 --zone europe-west1-b
+    ssh-hub add api --aws-instance i-0abc123 --region us-east-1
+    ssh-hub add api --aws-instance i-0abc123 --user ec2-user    When the AMI's user isn't known")]
     Add {
         /// Server name (alias used in MCP tools and CLI commands)
         name: String,
 
-        /// SSH connection string (see CONNECTION FORMATS below)
-        connection: String,
+        #[command(flatten)]
+        target: AddTarget,
 
         /// Override SSH port from the connection string
         #[arg(short = 'p', long)]
@@ -101,12 +173,175 @@ EXAMPLES:
         /// Shell command that outputs the current host/IP on stdout (run during `ssh-hub update`)
         #[arg(long, value_name = "COMMAND")]
         resolve_host: Option<String>,
+
+        /// Mark the server readonly: MCP tools refuse writes and mutating commands
+        #[arg(long)]
+        readonly: bool,
+
+        #[command(flatten)]
+        options: AddOptions,
     },
 
-    /// Remove a server from config. Active MCP sessions are not affected
-    Remove {
-        /// Server name to remove
+    /// Install your public key on a server so it accepts key logins (like ssh-copy-id)
+    #[command(long_about = "\
+Install your public key in the server's ~/.ssh/authorized_keys.
+
+Logs in with existing access, or with a password typed once when \
+--password-once is given (never stored), and appends the key unless it is \
+already there, creating ~/.ssh with the right permissions. Then checks that \
+the key logs in on its own. The key is --key, else the server's identity, \
+else the first of ~/.ssh/id_ed25519, id_rsa, id_ecdsa, id_ed25519_sk, \
+id_ecdsa_sk.")]
+    #[command(after_long_help = "\
+EXAMPLES:
+    ssh-hub add web1 deploy@10.0.0.7
+    ssh-hub authorize web1 --password-once        Onboard a fresh server
+    ssh-hub authorize web1 --key ~/.ssh/deploy    Add a second key using current access")]
+    Authorize {
+        /// Server name
+        name: String,
+
+        /// Log in with a password for this run instead of an existing key
+        #[arg(long)]
+        password_once: bool,
+
+        /// Public key to install (a private key path picks the .pub next to it)
+        #[arg(short = 'k', long)]
+        key: Option<PathBuf>,
+    },
+
+    /// Generate an ed25519 key and optionally install it on servers
+    #[command(long_about = "\
+Generate a new ed25519 key pair (default ~/.ssh/ssh-hub_ed25519, never \
+overwritten) and optionally install it on servers.
+
+For each server given, logs in with existing access, adds the public key to \
+authorized_keys, checks that the new key logs in on its own, and then makes \
+it the server's identity with auth = \"key\". When existing access doesn't \
+work and a terminal is attached, asks once for the server's password, like \
+ssh-copy-id. The password is used for that login only and never stored.")]
+    #[command(after_long_help = "\
+EXAMPLES:
+    ssh-hub keygen                              Just write the key pair
+    ssh-hub keygen web1                         Install it on web1 and use it there
+    ssh-hub keygen --for web1 --for web2        Same, for two servers")]
+    Keygen {
+        /// Install the key on this server and make it its identity
+        servers: Vec<String>,
+
+        /// Install the key on this server and make it its identity (repeatable)
+        #[arg(long = "for", value_name = "SERVER")]
+        for_servers: Vec<String>,
+
+        /// Path of the private key to write (the public key gets .pub appended)
+        #[arg(short = 'o', long)]
+        output: Option<PathBuf>,
+
+        /// Comment stored in the key (default: ssh-hub)
+        #[arg(short = 'C', long)]
+        comment: Option<String>,
+    },
+
+    /// Replace the SSH keys of several servers with a fresh one, with rollback
+    #[command(name = "rotate-keys")]
+    #[command(long_about = "\
+Generate a fresh ed25519 key and switch the selected servers to it.
+
+On each server: installs the new key using current access, checks that it \
+logs in on its own, points the server's identity at it, and removes the old \
+identity's key from authorized_keys (unless --keep-old). If the new key \
+doesn't log in, it is removed again and the server keeps its old key. Exits \
+non-zero when any server could not be rotated.")]
+    #[command(after_long_help = "\
+EXAMPLES:
+    ssh-hub rotate-keys --tag prod              Every server tagged prod (see update --tags)
+    ssh-hub rotate-keys web1 web2 --keep-old    Leave the old keys authorized")]
+    RotateKeys {
+        /// Servers to rotate
+        servers: Vec<String>,
+
+        /// Also rotate every server with this tag
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Rotate every configured server
+        #[arg(long)]
+        all: bool,
+
+        /// Leave the old keys authorized on the servers
+        #[arg(long)]
+        keep_old: bool,
+
+        /// Path of the new private key (default: ~/.ssh/ssh-hub_ed25519_<timestamp>)
+        #[arg(short = 'o', long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Store a server's sudo password in the OS keychain for sudo commands
+    #[command(name = "sudo-password")]
+    #[command(long_about = "\
+Store a server's sudo password in the OS keychain (macOS Keychain, or the \
+Secret Service through secret-tool on Linux).
+
+Prompts for the password, checks on the server that sudo accepts it, and only \
+then stores it. The remote_bash tool uses it when called with sudo: true, \
+passing it to sudo on stdin. It is never written to the config.")]
+    #[command(after_long_help = "\
+EXAMPLES:
+    ssh-hub sudo-password web1              Store (or replace) the password
+    ssh-hub sudo-password web1 --delete     Remove it from the keychain")]
+    SudoPassword {
+        /// Server name
         name: String,
+
+        /// Remove the stored password instead
+        #[arg(long)]
+        delete: bool,
+    },
+
+    /// Store the passphrase of a server's encrypted key in the OS keychain
+    #[command(name = "key-passphrase")]
+    #[command(long_about = "\
+Store the passphrase of a server's encrypted private key in the OS keychain \
+(macOS Keychain, or the Secret Service through secret-tool on Linux).
+
+The key is the server's identity file, or the first encrypted default key \
+(~/.ssh/id_ed25519, id_rsa, id_ecdsa) when it has none. Prompts for the \
+passphrase, checks that it decrypts the key, and only then stores it. ssh-hub \
+uses it to decrypt the key when ssh-agent doesn't hold it, which lets the MCP \
+server connect without a running agent.")]
+    #[command(after_long_help = "\
+EXAMPLES:
+    ssh-hub key-passphrase web1             Store (or replace) the passphrase
+    ssh-hub key-passphrase web1 --delete    Remove it from the keychain")]
+    KeyPassphrase {
+        /// Server name
+        name: String,
+
+        /// Remove the stored passphrase instead
+        #[arg(long)]
+        delete: bool,
+    },
+
+    /// Remove servers from config. Active MCP sessions are not affected
+    #[command(after_long_help = "\
+EXAMPLES:
+    ssh-hub remove web1                 Remove one server
+    ssh-hub remove web1 web2 'tmp-*'    Remove several; quote globs for the shell
+    ssh-hub remove old-box --purge      Also delete what ssh-hub keeps about it
+
+--purge also deletes the server's stored sudo password, provision history, \
+and project mappings. Once no remaining server uses its host, the cached DNS \
+address goes too, and so do the known_hosts lines naming just that host and \
+port, as ssh-hub writes them when it learns a key.")]
+    Remove {
+        /// Server names or glob patterns to remove
+        #[arg(required = true)]
+        names: Vec<String>,
+
+        /// Also delete stored secrets, caches, and known host keys
+        #[arg(long)]
+        purge: bool,
     },
 
     /// List configured servers with connection details, metadata, and reachability
@@ -114,6 +349,31 @@ EXAMPLES:
         /// Skip TCP reachability probing
         #[arg(long)]
         no_probe: bool,
+
+        /// Maximum servers probed at once
+        #[arg(short = 'j', long, default_value_t = probe::DEFAULT_PROBE_CONCURRENCY)]
+        jobs: usize,
+
+        /// Only list servers with this tag (repeat to require several)
+        #[arg(short = 't', long, value_name = "TAG")]
+        tag: Vec<String>,
+    },
+
+    /// Probe all servers (TCP, SSH banner, auth) and show a connectivity table
+    #[command(long_about = "\
+Probe all configured servers concurrently and show a connectivity table.
+
+Each server gets a TCP connect (latency), a read of its SSH banner, and a full \
+SSH authentication dry-run using the stored settings. The METADATA column shows \
+how long ago `ssh-hub update` last collected system metadata.")]
+    Status {
+        /// Skip the authentication dry-run (TCP and banner only)
+        #[arg(long)]
+        no_auth: bool,
+
+        /// Maximum servers probed at once
+        #[arg(short = 'j', long, default_value_t = probe::DEFAULT_PROBE_CONCURRENCY)]
+        jobs: usize,
     },
 
     /// Register ssh-hub as an MCP server in a project directory
@@ -123,11 +383,18 @@ Register ssh-hub as an MCP server in a project directory.
 
 Writes the config file so Claude Code (.mcp.json) and/or Codex (.codex/config.toml) \
 discover ssh-hub as an MCP server. Without --claude or --codex, configures both. \
-MCP tools auto-connect to configured servers on first use.")]
+MCP tools auto-connect to configured servers on first use.
+
+With --server, also records in servers.toml that the server backs this project, \
+with its root at --remote-path (default: the server's remote_path). sync_push and \
+sync_status then mirror files under the project to the same relative path on the \
+server, and sync_pull brings files under that remote root back into the project.")]
     #[command(after_long_help = "\
 EXAMPLES:
     ssh-hub mcp-install                           Both clients, current dir
-    ssh-hub mcp-install /path/to/project --claude Claude Code only")]
+    ssh-hub mcp-install /path/to/project --claude Claude Code only
+    ssh-hub mcp-install --server staging --remote-path /srv/app
+                                                  Map this project to /srv/app on staging")]
     McpInstall {
         /// Target project directory (default: current working directory)
         #[arg(default_value = ".")]
@@ -140,6 +407,14 @@ EXAMPLES:
         /// Configure for Codex (.codex/config.toml)
         #[arg(long)]
         codex: bool,
+
+        /// Record this server as the one backing the project
+        #[arg(long)]
+        server: Option<String>,
+
+        /// Project root on the server (default: the server's `remote_path`)
+        #[arg(long, requires = "server")]
+        remote_path: Option<String>,
     },
 
     /// Update server metadata and optionally change connection settings
@@ -161,7 +436,14 @@ EXAMPLES:
     ssh-hub update --all                         Update all servers
     ssh-hub update staging --resolve-host 'gcloud compute instances describe my-vm --format=...'
                                                  Store a host-resolve command
-    ssh-hub update staging --resolve-host ''     Clear the resolve command")]
+    ssh-hub update staging --resolve-host ''     Clear the resolve command
+    ssh-hub update staging --resolve 10.1.2.3    Pin an IP for the host, bypassing DNS
+    ssh-hub update staging --resolve ''          Clear the pinned IP
+    ssh-hub update winbox --shell powershell     Treat the login shell as PowerShell
+    ssh-hub update prod --readonly true          Refuse changes from MCP tools (false lifts it)
+    ssh-hub update app1 --read-replica app2      Serve remote_read/remote_glob/sync_pull from app2 ('' clears)
+    ssh-hub update app1 --scratch-root /scratch  Make workspace_create directories under /scratch ('' clears)
+    ssh-hub update web1 --tags prod,web          Tag a server for rotate-keys --tag ('' clears)")]
     Update {
         /// Server name to update
         name: Option<String>,
@@ -170,25 +452,196 @@ EXAMPLES:
         #[arg(long)]
         all: bool,
 
-        /// Update the stored SSH host before connecting
-        #[arg(long)]
-        host: Option<String>,
+        #[command(flatten)]
+        overrides: update::ConnectionOverrides,
+    },
 
-        /// Update the stored SSH port before connecting
-        #[arg(short = 'p', long)]
-        port: Option<u16>,
+    /// Show the running MCP server's pooled connections: age, activity, channels, bytes, health
+    #[command(long_about = "\
+Show the pooled SSH connections of the running MCP server.
 
-        /// Update the stored remote base path before connecting
+For each connection: age, time since last activity, channels in use and opened \
+so far, bytes sent and received on command channels, and health (healthy, \
+saturated when every channel slot is busy, or closed). The pool lives inside \
+the MCP server, so this asks the shared broker for it; enable [broker] in \
+servers.toml first. Without a broker, call the pool_stats MCP tool instead.")]
+    Pool,
+
+    /// Keep one MCP server running in the background for every MCP client to share
+    #[command(long_about = "\
+Keep one MCP server running in the background, shared by every MCP client.
+
+`daemon install` writes a systemd user unit (Linux, ~/.config/systemd/user/) or \
+a launchd agent (macOS, ~/Library/LaunchAgents/) that runs `ssh-hub daemon run` \
+at login, enables [broker] in servers.toml, and starts it. MCP clients still \
+start ssh-hub as before, but relay to the daemon over the broker socket, so a \
+new project window attaches to warm SSH connections instead of opening its \
+own. Unlike a broker started by a client, the daemon keeps running after the \
+last client disconnects. With --profile or --config, the service is installed \
+for that profile or file.")]
+    #[command(after_long_help = "\
+EXAMPLES:
+    ssh-hub daemon install              Install and start the service
+    ssh-hub daemon install --print      Show the unit or plist without installing it
+    ssh-hub pool                        Connections held by the daemon
+    ssh-hub daemon uninstall            Stop and remove the service")]
+    Daemon {
+        #[command(subcommand)]
+        action: DaemonAction,
+    },
+
+    /// Interactive dashboard: servers, reachability, connection tests, background jobs
+    #[command(long_about = "\
+Interactive terminal dashboard for configured servers.
+
+Shows every server with live reachability. Select a server to see its config \
+and metadata, test the SSH connection, list background remote_bash jobs \
+(log files under /tmp, marked running while a process still writes to them), \
+or remove it from the config.
+
+KEYS: j/k or arrows move, r refresh, t test connection, b background jobs, \
+d remove (asks to confirm), q quit.")]
+    Tui,
+
+    /// Show the output of a background command started through the MCP server
+    #[command(long_about = "\
+Show the log of a background job started with remote_bash run_in_background.
+
+Without a job ID, lists the server's background jobs with their IDs, newest \
+first. With one, prints the last lines of its log; --follow keeps printing new \
+output until the job exits (or until interrupted, where the remote can't tell \
+which process writes the log). The job ID is in the remote_bash response.")]
+    #[command(after_long_help = "\
+EXAMPLES:
+    ssh-hub logs gpu1                             List background jobs
+    ssh-hub logs gpu1 1760601234.512 --follow     Watch a job until it finishes
+    ssh-hub logs gpu1 1760601234.512 -n 500       Last 500 lines")]
+    Logs {
+        /// Server name
+        server: String,
+
+        /// Job ID (or the full log file path) from the background command
+        job: Option<String>,
+
+        /// Keep printing new output until the job exits
+        #[arg(short = 'f', long)]
+        follow: bool,
+
+        /// Lines of existing output to show
+        #[arg(short = 'n', long, default_value_t = 50)]
+        lines: usize,
+    },
+
+    /// Run a command saved for a server under [servers.<name>.commands]
+    #[command(long_about = "\
+Run a command saved for a server under [servers.<name>.commands] in \
+servers.toml, the same one the MCP run_saved_command tool runs. Values for \
+the command's {param} placeholders are given as name=value and are \
+shell-escaped. Prints the command's stdout and stderr and fails when it exits \
+non-zero. Without a command name, lists the server's saved commands.")]
+    #[command(after_long_help = "\
+EXAMPLES:
+    ssh-hub run staging
+    ssh-hub run staging deploy
+    ssh-hub run staging deploy branch=release-2.1")]
+    Run {
+        /// Server name
+        server: String,
+
+        /// Saved command to run (omit to list them)
+        command: Option<String>,
+
+        /// Values for the command's placeholders, as name=value
+        args: Vec<String>,
+    },
+
+    /// Measure latency and throughput of the SSH connection to a server
+    #[command(long_about = "\
+Measure the SSH connection to a server: how long opening a channel takes \
+(the round trip) and how long a no-op command takes, as minimum, median, and \
+maximum over several rounds, then upload and download throughput with a test \
+payload sent through a command's stdin and stdout.
+
+Use it to choose between servers, or to tell a slow link from a slow remote \
+when syncs drag. The MCP benchmark_server tool takes the same measurements.")]
+    #[command(after_long_help = "\
+EXAMPLES:
+    ssh-hub bench web1
+    ssh-hub bench web1 --rounds 10 --payload-kb 8192")]
+    Bench {
+        /// Server name
+        server: String,
+
+        /// Latency samples to take (max 20)
+        #[arg(long, default_value_t = 5)]
+        rounds: u32,
+
+        /// Test payload size in KiB, each way (max 16384)
+        #[arg(long, default_value_t = 1024)]
+        payload_kb: u64,
+    },
+
+    /// Tunnel local TCP connections through a server with a SOCKS5 proxy (like ssh -D)
+    #[command(long_about = "\
+Run a local SOCKS5 proxy whose connections are made from the server, like \
+`ssh -D`. Point a browser or tool at it to reach hosts only the server can \
+see, such as internal dashboards or databases.
+
+The proxy listens on 127.0.0.1 only and needs no authentication. Host names \
+are resolved by the server when the client uses socks5h://. It runs until \
+interrupted or until the SSH connection drops.")]
+    #[command(after_long_help = "\
+EXAMPLES:
+    ssh-hub tunnel bastion --socks 1080
+    curl --proxy socks5h://127.0.0.1:1080 http://grafana.internal:3000/")]
+    Tunnel {
+        /// Server name
+        server: String,
+
+        /// Local port for the SOCKS5 proxy (0 picks a free one)
+        #[arg(long, value_name = "PORT")]
+        socks: u16,
+    },
+
+    /// Apply a provisioning recipe from servers.toml to a server
+    #[command(long_about = "\
+Apply a provisioning recipe declared under [recipes.<name>] in servers.toml.
+
+Installs missing packages, places files whose content or mode differs, runs \
+commands whose 'unless' check fails, and enables and starts systemd services. \
+Steps that already match are left alone, so re-running a recipe is safe. Use \
+--dry-run to see what would change without touching the server.")]
+    #[command(after_long_help = "\
+EXAMPLES:
+    ssh-hub provision web1 nginx --dry-run   Show what would change
+    ssh-hub provision web1 nginx             Apply the recipe")]
+    Provision {
+        /// Server name
+        server: String,
+
+        /// Recipe name
+        recipe: String,
+
+        /// Report what would change without changing anything
         #[arg(long)]
-        remote_path: Option<String>,
+        dry_run: bool,
+    },
 
-        /// Update the stored SSH private key path before connecting
-        #[arg(short = 'i', long)]
-        identity: Option<PathBuf>,
+    /// Report where a server has drifted from its provisioning recipes
+    #[command(name = "check-drift")]
+    #[command(long_about = "\
+Report where a server has drifted from its provisioning recipes.
 
-        /// Shell command that resolves the current host/IP on stdout (empty string clears)
-        #[arg(long, value_name = "COMMAND")]
-        resolve_host: Option<String>,
+Re-checks every step of each recipe applied to the server with `ssh-hub \
+provision` (or the provision MCP tool), or only --recipe, without changing \
+anything. Exits non-zero when any step has drifted.")]
+    CheckDrift {
+        /// Server name
+        server: String,
+
+        /// Check only this recipe (default: every recipe applied to the server)
+        #[arg(long)]
+        recipe: Option<String>,
     },
 
     /// Upgrade ssh-hub to the latest release via cargo install
@@ -200,6 +653,33 @@ Use --check to preview the available version without installing.")]
         #[arg(long)]
         check: bool,
     },
+
+    /// Collect a sanitized support bundle (config, versions, saved outputs) for bug reports
+    #[command(name = "support-bundle")]
+    #[command(long_about = "\
+Collect a sanitized support bundle for attaching to bug reports.
+
+Gathers the ssh-hub version and platform, the server config with hosts, users, \
+key paths, and template and recipe commands redacted, and an index of saved remote_bash \
+outputs. Each file is shown for review and can be excluded before the tarball \
+is written.")]
+    SupportBundle {
+        /// Output path (default: ./ssh-hub-support-<timestamp>.tar.gz)
+        #[arg(short = 'o', long)]
+        output: Option<PathBuf>,
+
+        /// Include every section without prompting
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
+    /// Print remote path completions for a `server:path` word (used by shell completion scripts)
+    #[command(name = "complete-remote", hide = true)]
+    CompleteRemote {
+        /// Partial `server:path` word being completed
+        #[arg(default_value = "")]
+        word: String,
+    },
 }
 
 /// Dispatch a CLI command to its handler.
@@ -209,44 +689,126 @@ Use --check to preview the available version without installing.")]
 /// Returns an error if the command's underlying operation fails (I/O, network,
 /// config parse, etc.).
 pub async fn run(command: Command) -> Result<()> {
+    // Encrypted keys without a stored passphrase are asked for, when there
+    // is someone to ask.
+    if std::io::stdin().is_terminal() {
+        crate::connection::set_passphrase_prompt(key_passphrase::prompt);
+    }
     match command {
         Command::Add {
             name,
-            connection,
+            target,
             port,
             identity,
             resolve_host,
-        } => add::run(name, connection, port, identity, resolve_host).await,
+            readonly,
+            options,
+        } => {
+            add::run(
+                name,
+                target,
+                port,
+                identity,
+                resolve_host,
+                readonly,
+                options,
+            )
+            .await
+        }
+
+        Command::Authorize {
+            name,
+            password_once,
+            key,
+        } => authorize::run(&name, password_once, key).await,
+
+        Command::Keygen {
+            servers,
+            for_servers,
+            output,
+            comment,
+        } => keygen::run([servers, for_servers].concat(), output, comment).await,
+
+        Command::RotateKeys {
+            servers,
+            tag,
+            all,
+            keep_old,
+            output,
+        } => rotate_keys::run(servers, tag, all, keep_old, output).await,
+
+        Command::SudoPassword { name, delete } => sudo_password::run(&name, delete).await,
+
+        Command::KeyPassphrase { name, delete } => key_passphrase::run(&name, delete).await,
 
-        Command::Remove { name } => remove::run(&name),
+        Command::Remove { names, purge } => remove::run(&names, purge).await,
 
-        Command::List { no_probe } => list::run(no_probe).await,
+        Command::List {
+            no_probe,
+            jobs,
+            tag,
+        } => list::run(no_probe, jobs, &tag).await,
+
+        Command::Status { no_auth, jobs } => status::run(no_auth, jobs).await,
 
         Command::McpInstall {
             directory,
             claude,
             codex,
-        } => mcp_install::run(&directory, claude, codex),
+            server,
+            remote_path,
+        } => mcp_install::run(&directory, claude, codex, server, remote_path),
 
         Command::Update {
             name,
             all,
-            host,
-            port,
-            remote_path,
-            identity,
-            resolve_host,
-        } => {
-            let overrides = update::ConnectionOverrides {
-                host,
-                port,
-                remote_path,
-                identity,
-                resolve_host,
-            };
-            update::run(name, all, overrides).await
+            overrides,
+        } => update::run(name, all, overrides).await,
+
+        Command::Pool => pool::run().await,
+
+        Command::Daemon { action } => daemon::run(action).await,
+
+        Command::Tui => tui::run().await,
+
+        Command::Logs {
+            server,
+            job,
+            follow,
+            lines,
+        } => logs::run(&server, job.as_deref(), lines, follow).await,
+
+        Command::Tunnel { server, socks } => tunnel::run(&server, socks).await,
+
+        Command::Run {
+            server,
+            command,
+            args,
+        } => run::run(&server, command.as_deref(), &args).await,
+
+        Command::Bench {
+            server,
+            rounds,
+            payload_kb,
+        } => bench::run(&server, rounds, payload_kb).await,
+
+        Command::Provision {
+            server,
+            recipe,
+            dry_run,
+        } => provision::run(&server, &recipe, dry_run).await,
+
+        Command::CheckDrift { server, recipe } => {
+            check_drift::run(&server, recipe.as_deref()).await
         }
 
         Command::Upgrade { check } => upgrade::run(check),
+
+        Command::SupportBundle { output, yes } => support_bundle::run(output, yes),
+
+        Command::CompleteRemote { word } => {
+            complete::run(&word).await;
+            Ok(())
+        }
     }
 }
@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use colored::Colorize;
+
+use crate::connection::{Health, PoolStats};
+use crate::tools::sync_pull::handler::format_size;
+
+use super::format_age;
+
+/// How long to wait for the broker's answer.
+#[cfg(unix)]
+const BROKER_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub async fn run() -> Result<()> {
+    let stats = fetch_stats().await?;
+    if stats.connections.is_empty() {
+        println!("{}", "No pooled connections.".dimmed());
+        return Ok(());
+    }
+
+    let targets: Vec<String> = stats
+        .connections
+        .iter()
+        .map(|c| format!("{}@{}:{}", c.user, c.host, c.port))
+        .collect();
+    let name_width = stats
+        .connections
+        .iter()
+        .map(|c| c.server.len())
+        .max()
+        .unwrap_or(0)
+        .max("SERVER".len());
+    let target_width = targets
+        .iter()
+        .map(String::len)
+        .max()
+        .unwrap_or(0)
+        .max("TARGET".len());
+
+    println!(
+        "{}",
+        format!(
+            "{:<name_width$}  {:<target_width$}  {:<9}  {:>5}  {:>5}  {:>8}  {:>6}  {:>10}  {:>10}",
+            "SERVER", "TARGET", "HEALTH", "AGE", "IDLE", "CHANNELS", "OPENED", "SENT", "RECEIVED"
+        )
+        .bold()
+    );
+    for (entry, target) in stats.connections.iter().zip(&targets) {
+        let health = match entry.health {
+            Health::Healthy => format!("{:<9}", "healthy").green(),
+            Health::Saturated => format!("{:<9}", "saturated").yellow(),
+            Health::Closed => format!("{:<9}", "closed").red(),
+        };
+        let channels = format!("{}/{}", entry.channels_in_use, entry.max_channels);
+        println!(
+            "{:<name_width$}  {target:<target_width$}  {health}  {:>5}  {:>5}  {channels:>8}  {:>6}  {:>10}  {:>10}",
+            entry.server,
+            format_age(entry.age_secs),
+            format_age(entry.idle_secs),
+            entry.channels_opened,
+            format_size(entry.bytes_sent),
+            format_size(entry.bytes_received),
+        );
+    }
+
+    Ok(())
+}
+
+/// Ask the running broker for its pool. The pool lives inside the MCP
+/// server, so without a shared broker there is nothing to connect to.
+#[cfg(unix)]
+async fn fetch_stats() -> Result<PoolStats> {
+    use std::io;
+
+    use crate::broker;
+
+    let path = broker::socket_path()
+        .ok_or_else(|| anyhow!("No runtime directory for the broker socket"))?;
+    let call = broker::call_tool(&path, "pool_stats", serde_json::json!({}));
+    let output = match tokio::time::timeout(BROKER_TIMEOUT, call).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e))
+            if matches!(
+                e.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+            ) =>
+        {
+            bail!(
+                "No ssh-hub broker is running at {}. Enable [broker] in servers.toml to \
+                 inspect the MCP server's pool from the CLI, or call the pool_stats tool.",
+                path.display()
+            )
+        }
+        Ok(Err(e)) => bail!("Broker at {} failed: {e}", path.display()),
+        Err(_) => bail!(
+            "Broker at {} did not answer within {}s",
+            path.display(),
+            BROKER_TIMEOUT.as_secs()
+        ),
+    };
+    serde_json::from_str(&output)
+        .map_err(|e| anyhow!("Unexpected pool_stats output ({e}): {output}"))
+}
+
+#[cfg(not(unix))]
+async fn fetch_stats() -> Result<PoolStats> {
+    bail!("ssh-hub pool needs the broker socket, which is unix-only. Call the pool_stats tool instead.")
+}
@@ -0,0 +1,37 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use futures::stream::{self, StreamExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+pub(super) const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Probes in flight at once when `--jobs` isn't given. Large registries
+/// would otherwise open hundreds of simultaneous TCP connects, which looks
+/// like a port scan to intrusion detection.
+pub(super) const DEFAULT_PROBE_CONCURRENCY: usize = 16;
+
+/// TCP-level reachability probe to the SSH port.
+///
+/// Returns `(reachable, latency_ms)` — latency is `Some` only on success.
+pub(super) async fn probe_reachability(host: &str, port: u16) -> (bool, Option<u32>) {
+    let addr = format!("{host}:{port}");
+    let start = Instant::now();
+    match timeout(PROBE_TIMEOUT, TcpStream::connect(&addr)).await {
+        Ok(Ok(_)) => {
+            let ms = u32::try_from(start.elapsed().as_millis()).unwrap_or(u32::MAX);
+            (true, Some(ms))
+        }
+        _ => (false, None),
+    }
+}
+
+/// Run `probes` with at most `limit` in flight, returning results in input order.
+pub(super) async fn bounded<I, F>(probes: I, limit: usize) -> Vec<F::Output>
+where
+    I: IntoIterator<Item = F>,
+    F: Future,
+{
+    stream::iter(probes).buffered(limit.max(1)).collect().await
+}
@@ -0,0 +1,115 @@
+use anyhow::{anyhow, Result};
+use colored::{ColoredString, Colorize};
+
+use crate::connection::SshConnection;
+use crate::server_registry::ServerRegistry;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::provision::handler::{apply, find_recipe};
+use crate::tools::provision::{StepReport, StepStatus};
+
+use super::params_from_config;
+use super::spinner;
+
+pub async fn run(server: &str, recipe_name: &str, dry_run: bool) -> Result<()> {
+    let config = ServerRegistry::load()?;
+    let entry = config
+        .get(server)
+        .ok_or_else(|| anyhow!("Server '{server}' not found in config"))?
+        .clone();
+    let recipe = find_recipe(&config, recipe_name, server).map_err(|e| anyhow!(e.message))?;
+    let config_dir = ServerRegistry::config_path()?
+        .parent()
+        .map(std::path::Path::to_path_buf)
+        .ok_or_else(|| anyhow!("Could not determine config directory"))?;
+
+    let sp = spinner::start_root(format!("Connecting to {server}..."));
+    let conn = match SshConnection::connect(params_from_config(server, &entry)).await {
+        Ok(c) => c,
+        Err(e) => {
+            spinner::finish_failed(&sp, "connection failed");
+            return Err(e);
+        }
+    };
+    if let Some(error) = conn.posix_required("provision") {
+        spinner::clear(&sp);
+        conn.disconnect().await;
+        return Err(anyhow!(error));
+    }
+    sp.set_message(if dry_run {
+        format!("Checking {recipe_name} on {server}...")
+    } else {
+        format!("Applying {recipe_name} to {server}...")
+    });
+    let report = apply(
+        &conn,
+        server,
+        recipe_name,
+        &recipe,
+        &config_dir,
+        dry_run,
+        TimeoutPolicy::for_server(&entry),
+    )
+    .await;
+    spinner::clear(&sp);
+    conn.disconnect().await;
+
+    println!(
+        "{} {} {} {}{}",
+        ">".blue().bold(),
+        recipe_name.bold(),
+        "on".dimmed(),
+        server.bold(),
+        if dry_run {
+            " (dry run)".dimmed()
+        } else {
+            "".normal()
+        },
+    );
+    for step in &report.steps {
+        print_step(step);
+    }
+
+    let ok = report
+        .steps
+        .iter()
+        .filter(|s| s.status == StepStatus::Ok)
+        .count();
+    let verb = if dry_run { "to change" } else { "changed" };
+    println!();
+    println!(
+        "  {} ok, {} {verb}, {} steps total",
+        ok,
+        report.changed,
+        report.steps.len(),
+    );
+
+    if report.failed {
+        return Err(anyhow!("Recipe '{recipe_name}' failed on '{server}'"));
+    }
+    Ok(())
+}
+
+pub(super) fn print_step(step: &StepReport) {
+    let detail = step
+        .detail
+        .as_deref()
+        .map(|d| d.lines().collect::<Vec<_>>().join(" | "))
+        .map_or_else(String::new, |d| format!("  {}", d.dimmed()));
+    println!(
+        "  {} {:<8} {}{detail}",
+        status_label(step.status),
+        step.kind.to_string(),
+        step.target,
+    );
+}
+
+fn status_label(status: StepStatus) -> ColoredString {
+    let label = format!("{:<12}", status.to_string());
+    match status {
+        StepStatus::Ok => label.green(),
+        StepStatus::Changed => label.blue(),
+        StepStatus::WouldChange => label.yellow(),
+        StepStatus::Failed => label.red(),
+        StepStatus::Skipped => label.dimmed(),
+    }
+}
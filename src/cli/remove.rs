@@ -1,21 +1,145 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use colored::Colorize;
 
-use crate::server_registry::ServerRegistry;
+use crate::connection::forget_known_host;
+use crate::connection::resolve::forget_cached_host;
+use crate::keychain;
+use crate::server_registry::{ServerEntry, ServerRegistry};
+use crate::tools::provision::history;
+use crate::utils::gitignore::glob_match;
 
-pub fn run(name: &str) -> Result<()> {
-    let mut config = ServerRegistry::load().unwrap_or_default();
+/// Servers matching `patterns`, each a name or a glob such as `tmp-*`,
+/// sorted and without duplicates, plus the patterns that matched nothing.
+#[must_use]
+pub fn select_removals<'a>(
+    config: &ServerRegistry,
+    patterns: &'a [String],
+) -> (Vec<String>, Vec<&'a str>) {
+    let mut selected = Vec::new();
+    let mut unmatched = Vec::new();
+    for pattern in patterns {
+        let before = selected.len();
+        for name in config.servers.keys() {
+            if (name == pattern || glob_match(pattern, name)) && !selected.contains(name) {
+                selected.push(name.clone());
+            }
+        }
+        let matched = selected.len() > before || selected.contains(pattern);
+        if !matched {
+            unmatched.push(pattern.as_str());
+        }
+    }
+    selected.sort();
+    (selected, unmatched)
+}
 
-    if config.remove(name).is_some() {
-        config.save()?;
-        println!("{} Server {} removed.", "-".red().bold(), name.bold());
-    } else {
+/// What was removed from the config with one server.
+struct Removed {
+    name: String,
+    entry: ServerEntry,
+    projects: usize,
+    /// No remaining server uses the same host, so what is cached for the
+    /// host can go too.
+    host_unused: bool,
+    /// No remaining server uses the same host and port.
+    address_unused: bool,
+}
+
+pub async fn run(patterns: &[String], purge: bool) -> Result<()> {
+    if patterns.is_empty() {
+        bail!("Name the servers to remove");
+    }
+    let (removed, unmatched) = ServerRegistry::modify(|config| {
+        let (names, unmatched) = select_removals(config, patterns);
+        let unmatched: Vec<String> = unmatched.into_iter().map(String::from).collect();
+        let mut removed = Vec::new();
+        for name in names {
+            let Some(entry) = config.remove(&name) else {
+                continue;
+            };
+            let mut projects = 0;
+            if purge {
+                config.projects.retain(|_, mapping| {
+                    let keep = mapping.server != name;
+                    projects += usize::from(!keep);
+                    keep
+                });
+            }
+            removed.push(Removed {
+                name,
+                entry,
+                projects,
+                host_unused: false,
+                address_unused: false,
+            });
+        }
+        for r in &mut removed {
+            r.host_unused = !config.servers.values().any(|e| e.host == r.entry.host);
+            r.address_unused = !config
+                .servers
+                .values()
+                .any(|e| e.host == r.entry.host && e.port == r.entry.port);
+        }
+        (removed, unmatched)
+    })?;
+
+    for pattern in &unmatched {
         println!(
             "{} Server {} not found in config.",
             "!".yellow().bold(),
-            name.bold(),
+            pattern.bold(),
         );
     }
-
+    for r in &removed {
+        println!("{} Server {} removed.", "-".red().bold(), r.name.bold());
+        if purge {
+            purge_server(r).await;
+        }
+    }
     Ok(())
 }
+
+/// Delete what ssh-hub keeps about a removed server outside its config
+/// entry, and print what went.
+async fn purge_server(r: &Removed) {
+    let mut purged = Vec::new();
+    match keychain::delete_sudo_password(&r.name).await {
+        Ok(true) => purged.push("sudo password".to_string()),
+        Ok(false) => {}
+        Err(e) if keychain::Backend::current().is_none() => {
+            tracing::debug!("No keychain to purge: {e}");
+        }
+        Err(e) => warn(&format!("sudo password not deleted: {e}")),
+    }
+    if history::forget_server(&r.name) {
+        purged.push("provision history".to_string());
+    }
+    if r.projects > 0 {
+        purged.push(plural(r.projects, "project mapping"));
+    }
+    if r.host_unused && forget_cached_host(&r.entry.host) {
+        purged.push("cached DNS address".to_string());
+    }
+    if r.address_unused {
+        match forget_known_host(&r.entry.host, r.entry.port) {
+            Ok(0) => {}
+            Ok(lines) => purged.push(plural(lines, "known_hosts line")),
+            Err(e) => warn(&format!("known_hosts not cleaned up: {e}")),
+        }
+    }
+    if !purged.is_empty() {
+        println!("  {} {}", "purged:".dimmed(), purged.join(", "));
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+fn warn(message: &str) {
+    println!("  {} {message}", "!".yellow().bold());
+}
@@ -0,0 +1,192 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Result};
+use colored::Colorize;
+
+use crate::server_registry::{AuthMethod, ServerEntry, ServerRegistry};
+
+use super::authorize::{self, AuthorizedKey};
+use super::keygen;
+use super::params_from_config;
+
+/// Servers to rotate: the named ones plus every server tagged `tag`, or all
+/// of them with `all`. Sorted and without duplicates.
+///
+/// # Errors
+///
+/// Returns an error if nothing is selected, a named server isn't configured,
+/// or no server carries `tag`.
+pub fn select_servers(
+    config: &ServerRegistry,
+    names: &[String],
+    tag: Option<&str>,
+    all: bool,
+) -> Result<Vec<String>> {
+    if names.is_empty() && tag.is_none() && !all {
+        bail!("Name the servers to rotate, or pass --tag or --all");
+    }
+    if let Some(missing) = names.iter().find(|n| config.get(n).is_none()) {
+        bail!("Server '{missing}' not found in config");
+    }
+    let mut selected: Vec<String> = config
+        .servers
+        .iter()
+        .filter(|(name, entry)| {
+            all || names.contains(name) || tag.is_some_and(|t| entry.tags.iter().any(|e| e == t))
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    selected.sort();
+    if selected.is_empty() {
+        bail!("No server is tagged '{}'", tag.unwrap_or_default());
+    }
+    Ok(selected)
+}
+
+/// What happened on one server.
+enum Outcome {
+    Rotated,
+    /// The new key didn't log in and was removed again.
+    RolledBack(String),
+    Failed(String),
+}
+
+pub async fn run(
+    names: Vec<String>,
+    tag: Option<String>,
+    all: bool,
+    keep_old: bool,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let config = ServerRegistry::load()?;
+    let targets = select_servers(&config, &names, tag.as_deref(), all)?;
+
+    let private_path = match output {
+        Some(path) => path,
+        None => keygen::ssh_dir()?.join(format!("ssh-hub_ed25519_{}", unix_now())),
+    };
+    let new_key = keygen::generate(&private_path, "ssh-hub")?;
+    println!(
+        "{} Generated ed25519 key {}",
+        "+".green().bold(),
+        private_path.display().to_string().underline(),
+    );
+
+    let mut failures = Vec::new();
+    for name in &targets {
+        let Some(entry) = config.get(name) else {
+            continue;
+        };
+        println!();
+        println!("{} Rotating {}", ">".blue().bold(), name.bold());
+        match rotate(name, entry, &new_key, &private_path, keep_old).await {
+            Outcome::Rotated => {}
+            Outcome::RolledBack(reason) => {
+                println!("  {} rolled back: {reason}", "x".red());
+                failures.push(name.as_str());
+            }
+            Outcome::Failed(reason) => {
+                println!("  {} {reason}", "x".red());
+                failures.push(name.as_str());
+            }
+        }
+    }
+
+    println!();
+    let rotated = targets.len() - failures.len();
+    println!(
+        "{} {rotated}/{} server(s) now use {}",
+        if failures.is_empty() {
+            "ok".green()
+        } else {
+            "!".yellow().bold()
+        },
+        targets.len(),
+        private_path.display(),
+    );
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "Rotation failed on: {} (their previous keys still work)",
+            failures.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Install `new_key`, confirm it logs in, switch the server's identity to it,
+/// and remove the old identity's key from `authorized_keys`.
+async fn rotate(
+    name: &str,
+    entry: &ServerEntry,
+    new_key: &AuthorizedKey,
+    private_path: &Path,
+    keep_old: bool,
+) -> Outcome {
+    let params = params_from_config(name, entry);
+    let old_key = params
+        .identity
+        .as_deref()
+        .map(authorize::public_key_path)
+        .and_then(|p| authorize::read_public_key(&p).ok());
+
+    let installed = match authorize::connect(&params, None).await {
+        Ok(conn) => authorize::install_key(&conn, new_key).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = installed {
+        return Outcome::Failed(e.to_string());
+    }
+
+    if !authorize::verify_key_login(params.clone(), private_path).await {
+        let rollback = match authorize::connect(&params, None).await {
+            Ok(conn) => authorize::remove_key(&conn, new_key).await,
+            Err(e) => Err(e),
+        };
+        return match rollback {
+            Ok(()) => Outcome::RolledBack("the new key did not log in".to_string()),
+            Err(e) => Outcome::Failed(format!(
+                "the new key did not log in, and removing it again failed: {e}"
+            )),
+        };
+    }
+    if let Err(e) = authorize::set_identity(name, private_path) {
+        return Outcome::Failed(format!("failed to update the config: {e}"));
+    }
+    if entry.auth == AuthMethod::Agent {
+        println!(
+            "  {} auth is 'agent', so load the new key with {}",
+            "note".yellow(),
+            format!("ssh-add {}", private_path.display()).dimmed(),
+        );
+    }
+
+    match old_key {
+        _ if keep_old => {}
+        Some(old) if old.key_id != new_key.key_id => {
+            let mut new_params = params;
+            new_params.identity = Some(private_path.to_path_buf());
+            new_params.auth_method = AuthMethod::Key;
+            let removed = match authorize::connect(&new_params, None).await {
+                Ok(conn) => authorize::remove_key(&conn, &old).await,
+                Err(e) => Err(e),
+            };
+            match removed {
+                Ok(()) => println!("  {} Old key removed from authorized_keys", "ok".green()),
+                Err(e) => println!("  {} Old key left in place: {e}", "warn".yellow()),
+            }
+        }
+        Some(_) => {}
+        None => println!(
+            "  {} No identity was configured, so no old key was removed",
+            "note".yellow(),
+        ),
+    }
+    Outcome::Rotated
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
@@ -0,0 +1,68 @@
+use std::io::Write;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+
+use crate::connection::SshConnection;
+use crate::server_registry::ServerRegistry;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::run_saved_command::handler::{parse_args, render};
+use crate::tools::RunSavedCommandInput;
+
+use super::params_from_config;
+use super::spinner;
+
+/// Run the saved command `name` on `server`, or list the server's saved
+/// commands when `name` is `None`.
+pub async fn run(server: &str, name: Option<&str>, words: &[String]) -> Result<()> {
+    let config = ServerRegistry::load()?;
+    let entry = config
+        .get(server)
+        .ok_or_else(|| anyhow!("Server '{server}' not found in config"))?
+        .clone();
+    let Some(name) = name else {
+        if entry.commands.is_empty() {
+            println!("No saved commands for {server}. Add them under [servers.{server}.commands].");
+        }
+        for (name, command) in &entry.commands {
+            println!("{}  {}", name.bold(), command.dimmed());
+        }
+        return Ok(());
+    };
+    let input = RunSavedCommandInput {
+        server: server.to_string(),
+        command: name.to_string(),
+        args: Some(parse_args(words).map_err(|e| anyhow!(e))?),
+        timeout: None,
+        override_window: None,
+    };
+    let command = render(&entry.commands, &input).map_err(|e| anyhow!(e.message))?;
+    let timeout = TimeoutPolicy::for_server(&entry)
+        .resolve(None, false)
+        .map_err(|e| anyhow!(e))?;
+
+    let sp = spinner::start_root(format!("Connecting to {server}..."));
+    let conn = match SshConnection::connect(params_from_config(server, &entry)).await {
+        Ok(c) => c,
+        Err(e) => {
+            spinner::finish_failed(&sp, "connection failed");
+            return Err(e);
+        }
+    };
+    sp.set_message(format!("Running {name} on {server}..."));
+    let result = conn.exec(&command, timeout).await;
+    spinner::clear(&sp);
+    conn.disconnect().await;
+    let result = result?;
+
+    print!("{}", result.stdout);
+    eprint!("{}", result.stderr);
+    std::io::stdout().flush()?;
+    if result.exit_code != 0 {
+        return Err(anyhow!(
+            "'{name}' exited with code {} on '{server}'",
+            result.exit_code
+        ));
+    }
+    Ok(())
+}
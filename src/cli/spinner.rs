@@ -4,6 +4,8 @@ use std::time::Duration;
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
 
+use crate::utils::glyphs::Glyphs;
+
 /// Tick interval for spinner animation.
 const TICK_MS: u64 = 80;
 
@@ -13,9 +15,6 @@ const INDENT: &str = "  ";
 /// 256-color index for spinner dots (208 = orange).
 const SPINNER_COLOR: u8 = 208;
 
-/// Braille spinner frames used across all CLI spinners.
-const TICK_STRINGS: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏", " "];
-
 /// Create an indented spinner for sub-operations under a header.
 #[must_use]
 pub fn start(message: impl Into<Cow<'static, str>>) -> ProgressBar {
@@ -35,7 +34,7 @@ fn create(template: &str, message: impl Into<Cow<'static, str>>) -> ProgressBar
     let pb = ProgressBar::new_spinner();
     pb.set_style(
         ProgressStyle::default_spinner()
-            .tick_strings(TICK_STRINGS)
+            .tick_strings(Glyphs::current().spinner)
             .template(template)
             .expect("valid template"),
     );
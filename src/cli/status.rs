@@ -0,0 +1,223 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use colored::{ColoredString, Colorize};
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use crate::connection::SshConnection;
+use crate::server_registry::{ServerEntry, ServerRegistry};
+
+use super::params_from_config;
+use super::probe::{bounded, PROBE_TIMEOUT};
+use super::spinner;
+
+const AUTH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Longest identification line allowed by RFC 4253, including CR LF.
+const MAX_BANNER_LEN: usize = 255;
+
+/// Result of probing one server.
+struct ServerStatus {
+    latency_ms: Option<u32>,
+    banner: Option<String>,
+    /// `None` when the auth dry-run was skipped or never attempted.
+    auth: Option<Result<(), String>>,
+}
+
+pub async fn run(no_auth: bool, jobs: usize) -> Result<()> {
+    let config = ServerRegistry::load()?;
+
+    if config.servers.is_empty() {
+        println!("{}", "No servers configured.".dimmed());
+        println!(
+            "Run {} to add one.",
+            "ssh-hub add <name> user@host:/path".bold(),
+        );
+        return Ok(());
+    }
+
+    let label = if no_auth {
+        "Probing servers..."
+    } else {
+        "Probing servers and testing auth..."
+    };
+    let sp = spinner::start_root(label);
+    let mut servers: Vec<_> = config.servers.iter().collect();
+    servers.sort_by_key(|(name, _)| name.as_str());
+    let results = bounded(
+        servers
+            .iter()
+            .map(|(name, entry)| probe_server(name, entry, no_auth)),
+        jobs,
+    )
+    .await;
+    spinner::clear(&sp);
+
+    let name_width = servers
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("SERVER".len());
+    let banner_width = results
+        .iter()
+        .filter_map(|s| s.banner.as_ref().map(String::len))
+        .max()
+        .unwrap_or(0)
+        .max("BANNER".len());
+
+    println!(
+        "{}",
+        format!(
+            "{:<name_width$}  {:<9}  {:>7}  {:<banner_width$}  {:<6}  METADATA",
+            "SERVER", "REACHABLE", "LATENCY", "BANNER", "AUTH"
+        )
+        .bold()
+    );
+
+    let now = unix_now();
+    let mut failures = Vec::new();
+    for ((name, entry), status) in servers.iter().zip(&results) {
+        let reachable = if status.latency_ms.is_some() {
+            pad("yes", 9).green()
+        } else {
+            pad("no", 9).red()
+        };
+        let latency = status
+            .latency_ms
+            .map_or_else(|| "-".to_string(), |ms| format!("{ms}ms"));
+        let banner = status.banner.as_deref().unwrap_or("-");
+        let auth = match &status.auth {
+            Some(Ok(())) => pad("ok", 6).green(),
+            Some(Err(e)) => {
+                failures.push((name, e));
+                pad("failed", 6).red()
+            }
+            None => pad("-", 6).dimmed(),
+        };
+        let metadata = entry
+            .metadata
+            .as_ref()
+            .and_then(|m| m.collected_at)
+            .map_or_else(
+                || "never".dimmed(),
+                |at| format!("{} ago", format_age(now.saturating_sub(at))).normal(),
+            );
+        println!(
+            "{:<name_width$}  {reachable}  {latency:>7}  {banner:<banner_width$}  {auth}  {metadata}",
+            name.as_str()
+        );
+    }
+
+    for (name, error) in failures {
+        println!("{} {}: {error}", "warn".yellow(), name.bold());
+    }
+
+    Ok(())
+}
+
+async fn probe_server(name: &str, entry: &ServerEntry, no_auth: bool) -> ServerStatus {
+    let Some((latency_ms, banner)) = probe_banner(entry.connect_host(), entry.port).await else {
+        return ServerStatus {
+            latency_ms: None,
+            banner: None,
+            auth: None,
+        };
+    };
+
+    let auth = if no_auth {
+        None
+    } else {
+        Some(auth_dry_run(name, entry).await)
+    };
+
+    ServerStatus {
+        latency_ms: Some(latency_ms),
+        banner,
+        auth,
+    }
+}
+
+/// TCP connect and read the SSH identification line.
+///
+/// Returns `None` if the port is unreachable; the banner is `None` if the
+/// server accepted the connection but didn't identify itself in time.
+async fn probe_banner(host: &str, port: u16) -> Option<(u32, Option<String>)> {
+    let start = Instant::now();
+    let mut stream = timeout(PROBE_TIMEOUT, TcpStream::connect(format!("{host}:{port}")))
+        .await
+        .ok()?
+        .ok()?;
+    let latency_ms = u32::try_from(start.elapsed().as_millis()).unwrap_or(u32::MAX);
+
+    let mut buf = vec![0u8; MAX_BANNER_LEN];
+    let mut len = 0;
+    let banner = timeout(PROBE_TIMEOUT, async {
+        while len < buf.len() {
+            let n = stream.read(&mut buf[len..]).await.ok()?;
+            if n == 0 {
+                break;
+            }
+            len += n;
+            if let Some(banner) = parse_banner(&buf[..len]) {
+                return Some(banner);
+            }
+        }
+        None
+    })
+    .await
+    .ok()
+    .flatten();
+
+    Some((latency_ms, banner))
+}
+
+/// Full SSH connect + authenticate, then disconnect immediately.
+pub(super) async fn auth_dry_run(name: &str, entry: &ServerEntry) -> Result<(), String> {
+    let params = params_from_config(name, entry);
+    match timeout(AUTH_TIMEOUT, SshConnection::connect(params)).await {
+        Ok(Ok(conn)) => {
+            conn.disconnect().await;
+            Ok(())
+        }
+        Ok(Err(e)) => Err(format!("{e:#}")),
+        Err(_) => Err(format!("timed out after {}s", AUTH_TIMEOUT.as_secs())),
+    }
+}
+
+/// Extract the SSH identification string (`SSH-2.0-...`) from the bytes
+/// received so far. Servers may send other lines before it (RFC 4253 §4.2).
+#[must_use]
+pub fn parse_banner(data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data);
+    let mut lines = text.split('\n');
+    // The last segment is incomplete until a newline arrives.
+    lines.next_back();
+    lines
+        .map(|line| line.trim_end_matches('\r'))
+        .find(|line| line.starts_with("SSH-"))
+        .map(ToString::to_string)
+}
+
+/// Compact human-readable age: `42s`, `5m`, `3h`, `2d`.
+#[must_use]
+pub fn format_age(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3_600 => format!("{}m", secs / 60),
+        3_600..86_400 => format!("{}h", secs / 3_600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
+
+fn pad(s: &str, width: usize) -> ColoredString {
+    format!("{s:<width$}").normal()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
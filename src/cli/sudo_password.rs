@@ -0,0 +1,97 @@
+use anyhow::{anyhow, bail, Result};
+use colored::Colorize;
+
+use crate::connection::{ConnectionParams, SshConnection};
+use crate::keychain;
+use crate::server_registry::ServerRegistry;
+
+use super::authorize;
+use super::params_from_config;
+use super::spinner;
+
+/// Timeout for the remote `sudo` check (30 seconds).
+const CHECK_TIMEOUT_MS: u64 = 30_000;
+
+/// Forgets cached sudo credentials first so the password is really checked.
+const CHECK_COMMAND: &str = "sudo -k -S -p '' true";
+
+pub async fn run(name: &str, delete: bool) -> Result<()> {
+    let config = ServerRegistry::load()?;
+    let entry = config
+        .get(name)
+        .ok_or_else(|| anyhow!("Server '{name}' not found in config"))?
+        .clone();
+
+    if delete {
+        if keychain::delete_sudo_password(name).await? {
+            println!(
+                "{} Sudo password for {} removed from the keychain.",
+                "-".red().bold(),
+                name.bold(),
+            );
+        } else {
+            println!(
+                "{} No sudo password stored for {}.",
+                "!".yellow().bold(),
+                name.bold(),
+            );
+        }
+        return Ok(());
+    }
+
+    let password = authorize::read_password(&format!(
+        "  sudo password for {}@{}: ",
+        entry.user, entry.host
+    ))?;
+    check_password(name, &params_from_config(name, &entry), &password).await?;
+    keychain::store_sudo_password(name, &password).await?;
+    println!(
+        "{} Sudo password for {} stored in the keychain. remote_bash runs commands with it when called with sudo: true.",
+        "ok".green(),
+        name.bold(),
+    );
+    Ok(())
+}
+
+/// Log in and confirm that sudo accepts `password`.
+async fn check_password(name: &str, params: &ConnectionParams, password: &str) -> Result<()> {
+    let sp = spinner::start("Checking sudo...");
+    let conn = match SshConnection::connect(params.clone()).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            spinner::finish_failed(&sp, "connection failed");
+            return Err(e);
+        }
+    };
+    if let Some(error) = conn.posix_required("sudo") {
+        spinner::finish_failed(&sp, "unsupported shell");
+        conn.disconnect().await;
+        bail!(error);
+    }
+    let stdin = format!("{password}\n");
+    let result = conn
+        .exec_raw(
+            CHECK_COMMAND,
+            Some(stdin.as_bytes()),
+            Some(CHECK_TIMEOUT_MS),
+        )
+        .await;
+    conn.disconnect().await;
+    match result {
+        Ok(r) if r.exit_code == 0 => {
+            spinner::finish_ok(&sp, "sudo accepted the password");
+            Ok(())
+        }
+        Ok(r) => {
+            spinner::finish_failed(&sp, "sudo rejected the password");
+            bail!(
+                "sudo on '{name}' rejected the password, so it was not stored: {}",
+                r.stderr.trim()
+            );
+        }
+        Err(e) => {
+            spinner::finish_failed(&sp, "sudo check failed");
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::json;
+
+use crate::server_registry::ServerRegistry;
+use crate::utils::glyphs;
+
+/// Placeholder for values removed from the bundle.
+const REDACTED: &str = "<redacted>";
+
+/// Saved `remote_bash` outputs listed in the bundle (names and sizes only).
+const MAX_SAVED_OUTPUTS: usize = 20;
+
+/// One file in the bundle, offered for review before writing.
+struct Section {
+    name: &'static str,
+    description: &'static str,
+    content: String,
+}
+
+pub fn run(output: Option<PathBuf>, yes: bool) -> Result<()> {
+    let generated_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    let sections = collect_sections(generated_at);
+
+    println!(
+        "{} Support bundle contents {}",
+        ">".blue().bold(),
+        "(review before sharing)".dimmed(),
+    );
+    let mut included = Vec::new();
+    for section in sections {
+        println!();
+        println!(
+            "  {} {}",
+            section.name.bold(),
+            format!("— {}", section.description).dimmed()
+        );
+        for line in section.content.lines() {
+            println!("    {}", line.dimmed());
+        }
+        if yes || confirm(&format!("Include {}?", section.name))? {
+            included.push(section);
+        } else {
+            println!("  {}", "skipped".yellow());
+        }
+    }
+
+    if included.is_empty() {
+        println!("{}", "Nothing selected — no bundle written.".dimmed());
+        return Ok(());
+    }
+
+    let path =
+        output.unwrap_or_else(|| PathBuf::from(format!("ssh-hub-support-{generated_at}.tar.gz")));
+    write_bundle(&path, &included)?;
+    println!();
+    println!(
+        "  {} Wrote {} ({} files)",
+        "ok".green(),
+        path.display().to_string().bold(),
+        included.len(),
+    );
+    Ok(())
+}
+
+fn collect_sections(generated_at: u64) -> Vec<Section> {
+    let config = ServerRegistry::load();
+    let config_content = match &config {
+        Ok(config) => toml::to_string_pretty(&sanitize_registry(config))
+            .unwrap_or_else(|e| format!("# failed to serialize config: {e}\n")),
+        Err(e) => format!("# failed to load config: {e}\n"),
+    };
+
+    let file_logging = config
+        .as_ref()
+        .ok()
+        .and_then(|c| c.logging.as_ref())
+        .is_none_or(|l| l.enabled);
+    let log_note = if file_logging {
+        "MCP server logs are not included (they name hosts); see the [logging] file, default ~/.local/share/ssh-hub/logs/"
+    } else {
+        "MCP server file logging is disabled; attach the MCP client's server log if relevant"
+    };
+
+    let environment = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "generated_at": generated_at,
+        "ascii_mode": glyphs::ascii_mode(),
+        "servers": config.as_ref().map_or(0, |c| c.servers.len()),
+        "templates": config.as_ref().map_or(0, |c| c.templates.len()),
+        "recipes": config.as_ref().map_or(0, |c| c.recipes.len()),
+        "notes": [
+            log_note,
+            "connection pool state lives in the running MCP server and is not captured",
+        ],
+    });
+
+    vec![
+        Section {
+            name: "environment.json",
+            description: "ssh-hub version, platform, and output mode",
+            content: serde_json::to_string_pretty(&environment).unwrap_or_default(),
+        },
+        Section {
+            name: "servers.toml",
+            description: "config with hosts, users, keys, and commands redacted; metadata kept",
+            content: config_content,
+        },
+        Section {
+            name: "saved-outputs.txt",
+            description: "recent large remote_bash outputs saved to disk (names and sizes only)",
+            content: saved_outputs_index(&std::env::temp_dir().join("ssh-hub")),
+        },
+    ]
+}
+
+/// Copy of `config` safe to attach to a public bug report. Server names,
+/// ports, auth methods, limits, and system metadata are kept; anything that
+/// identifies a host, account, key, or command is replaced.
+#[must_use]
+pub fn sanitize_registry(config: &ServerRegistry) -> ServerRegistry {
+    let mut clean = config.clone();
+    for entry in clean.servers.values_mut() {
+        entry.host = REDACTED.to_string();
+        entry.user = REDACTED.to_string();
+        if entry.remote_path != "~" {
+            entry.remote_path = REDACTED.to_string();
+        }
+        let redact = |v: &mut Option<String>| {
+            if v.is_some() {
+                *v = Some(REDACTED.to_string());
+            }
+        };
+        redact(&mut entry.identity);
+        redact(&mut entry.agent_socket);
+        redact(&mut entry.resolve_host);
+        redact(&mut entry.resolve);
+        if let Some(metadata) = entry.metadata.as_mut() {
+            redact(&mut metadata.hostname);
+        }
+        for command in entry.commands.values_mut() {
+            *command = REDACTED.to_string();
+        }
+        if let Some(discovery) = entry.discovery.as_mut() {
+            redact(&mut discovery.command);
+            redact(&mut discovery.aws_instance);
+            redact(&mut discovery.gcp_instance);
+            redact(&mut discovery.project);
+        }
+    }
+    redact_settings(&mut clean.defaults);
+    for (_, settings) in &mut clean.patterns {
+        redact_settings(settings);
+    }
+    for template in clean.templates.values_mut() {
+        template.command = REDACTED.to_string();
+        for param in template.params.values_mut() {
+            param.default = None;
+        }
+    }
+    if let Some(file) = clean.logging.as_mut().and_then(|l| l.file.as_mut()) {
+        *file = REDACTED.to_string();
+    }
+    for recipe in clean.recipes.values_mut() {
+        for file in &mut recipe.files {
+            file.src = REDACTED.to_string();
+            file.dest = REDACTED.to_string();
+        }
+        for command in &mut recipe.commands {
+            command.run = REDACTED.to_string();
+            if command.unless.is_some() {
+                command.unless = Some(REDACTED.to_string());
+            }
+        }
+    }
+    // Local project roots name the user's directories; keep only the count
+    // and the servers behind them.
+    clean.projects = std::mem::take(&mut clean.projects)
+        .into_values()
+        .enumerate()
+        .map(|(i, mut mapping)| {
+            mapping.remote_path = REDACTED.to_string();
+            (format!("{REDACTED}-{}", i + 1), mapping)
+        })
+        .collect();
+    clean
+}
+
+/// Redact the identifying keys of a `[defaults]` or pattern table, which
+/// hold server settings as raw TOML.
+fn redact_settings(settings: &mut toml::Table) {
+    for key in [
+        "host",
+        "user",
+        "identity",
+        "agent_socket",
+        "resolve_host",
+        "resolve",
+        "remote_path",
+    ] {
+        if let Some(value) = settings.get_mut(key).filter(|v| v.as_str() != Some("~")) {
+            *value = toml::Value::String(REDACTED.to_string());
+        }
+    }
+    for table in ["discovery", "commands"] {
+        if let Some(toml::Value::Table(table)) = settings.get_mut(table) {
+            for (_, value) in table.iter_mut() {
+                *value = toml::Value::String(REDACTED.to_string());
+            }
+        }
+    }
+}
+
+fn saved_outputs_index(dir: &Path) -> String {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return "(none)\n".to_string();
+    };
+    let mut files: Vec<(String, u64, u64)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|e| {
+            let meta = e.metadata().ok()?;
+            let modified = meta
+                .modified()
+                .ok()?
+                .duration_since(UNIX_EPOCH)
+                .ok()?
+                .as_secs();
+            Some((
+                e.file_name().to_string_lossy().into_owned(),
+                meta.len(),
+                modified,
+            ))
+        })
+        .collect();
+    if files.is_empty() {
+        return "(none)\n".to_string();
+    }
+    files.sort_by_key(|f| std::cmp::Reverse(f.2));
+    let mut index = String::new();
+    for (name, size, modified) in files.iter().take(MAX_SAVED_OUTPUTS) {
+        let _ = writeln!(index, "{modified}  {size:>10}  {name}");
+    }
+    index
+}
+
+fn write_bundle(path: &Path, sections: &[Section]) -> Result<()> {
+    let file =
+        std::fs::File::create(path).with_context(|| format!("Cannot create {}", path.display()))?;
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    for section in sections {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(section.content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        archive.append_data(
+            &mut header,
+            format!("ssh-hub-support/{}", section.name),
+            section.content.as_bytes(),
+        )?;
+    }
+    archive.into_inner()?.finish()?.flush()?;
+    Ok(())
+}
+
+fn confirm(prompt: &str) -> Result<bool> {
+    print!("  {prompt} {}: ", "[Y/n]".dimmed());
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(answer.is_empty() || answer.eq_ignore_ascii_case("y"))
+}
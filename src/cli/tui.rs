@@ -0,0 +1,467 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures::StreamExt;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Cell, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::time::timeout;
+
+use crate::connection::SshConnection;
+use crate::server_registry::{ServerEntry, ServerRegistry};
+
+use super::params_from_config;
+use super::probe::probe_reachability;
+use super::status::{auth_dry_run, format_age};
+
+/// Upper bound on connect + listing when fetching background jobs.
+const JOBS_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Lists `remote_bash` background logs as `mtime size state path`, preceded
+/// by `proc=1` when `/proc` can tell which logs are still held open.
+pub(super) const JOBS_COMMAND: &str = "\
+[ -d /proc/self/fd ] && echo proc=1; \
+open=$(ls -l /proc/[0-9]*/fd 2>/dev/null | grep -o '/tmp/ssh-hub-bg-[^ ]*\\.log' | sort -u); \
+for f in /tmp/ssh-hub-bg-*.log; do [ -f \"$f\" ] || continue; \
+m=$(stat -c %Y \"$f\" 2>/dev/null || stat -f %m \"$f\"); \
+s=$(wc -c < \"$f\" | tr -d ' '); \
+case \"$open\" in *\"$f\"*) r=running;; *) r=done;; esac; \
+echo \"$m $s $r $f\"; done; true";
+
+/// A `remote_bash` background job, identified by its log file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackgroundJob {
+    pub log_file: String,
+    /// Unix timestamp of the last write to the log.
+    pub modified: u64,
+    pub bytes: u64,
+    /// `None` when the remote has no `/proc` to check open files.
+    pub running: Option<bool>,
+}
+
+/// Parse the output of the background job listing, newest first.
+#[must_use]
+pub fn parse_background_jobs(stdout: &str) -> Vec<BackgroundJob> {
+    let has_proc = stdout.lines().any(|l| l.trim() == "proc=1");
+    let mut jobs: Vec<BackgroundJob> = stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, ' ');
+            let modified = fields.next()?.parse().ok()?;
+            let bytes = fields.next()?.parse().ok()?;
+            let state = fields.next()?;
+            let log_file = fields.next()?.to_string();
+            Some(BackgroundJob {
+                log_file,
+                modified,
+                bytes,
+                running: has_proc.then_some(state == "running"),
+            })
+        })
+        .collect();
+    jobs.sort_by_key(|j| std::cmp::Reverse(j.modified));
+    jobs
+}
+
+enum Reachability {
+    Probing,
+    Reachable(Option<u32>),
+    Unreachable,
+}
+
+enum Pending<T> {
+    NotRun,
+    Running,
+    Done(T),
+}
+
+struct ServerRow {
+    name: String,
+    entry: ServerEntry,
+    reachability: Reachability,
+    auth: Pending<Result<(), String>>,
+    jobs: Pending<Result<Vec<BackgroundJob>, String>>,
+}
+
+/// Results delivered from background tasks to the UI loop.
+enum Update {
+    Probe(String, bool, Option<u32>),
+    Auth(String, Result<(), String>),
+    Jobs(String, Result<Vec<BackgroundJob>, String>),
+}
+
+struct Dashboard {
+    rows: Vec<ServerRow>,
+    table: TableState,
+    message: String,
+    confirm_remove: bool,
+    updates: UnboundedSender<Update>,
+}
+
+pub async fn run() -> Result<()> {
+    let (tx, mut rx) = unbounded_channel();
+    let mut dashboard = Dashboard {
+        rows: Vec::new(),
+        table: TableState::default(),
+        message: String::new(),
+        confirm_remove: false,
+        updates: tx,
+    };
+    dashboard.reload()?;
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut dashboard, &mut rx).await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    dashboard: &mut Dashboard,
+    rx: &mut tokio::sync::mpsc::UnboundedReceiver<Update>,
+) -> Result<()> {
+    let mut events = EventStream::new();
+    loop {
+        terminal.draw(|frame| dashboard.draw(frame))?;
+        tokio::select! {
+            event = events.next() => match event {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                    if !dashboard.handle_key(key)? {
+                        return Ok(());
+                    }
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+                None => return Ok(()),
+            },
+            Some(update) = rx.recv() => dashboard.apply(update),
+        }
+    }
+}
+
+impl Dashboard {
+    /// Reload the config and re-probe every server.
+    fn reload(&mut self) -> Result<()> {
+        let config = ServerRegistry::load()?;
+        let mut servers: Vec<_> = config.servers.into_iter().collect();
+        servers.sort_by(|a, b| a.0.cmp(&b.0));
+        self.rows = servers
+            .into_iter()
+            .map(|(name, entry)| ServerRow {
+                name,
+                entry,
+                reachability: Reachability::Probing,
+                auth: Pending::NotRun,
+                jobs: Pending::NotRun,
+            })
+            .collect();
+        for row in &self.rows {
+            let tx = self.updates.clone();
+            let name = row.name.clone();
+            let host = row.entry.connect_host().to_string();
+            let port = row.entry.port;
+            tokio::spawn(async move {
+                let (reachable, latency) = probe_reachability(&host, port).await;
+                let _ = tx.send(Update::Probe(name, reachable, latency));
+            });
+        }
+        let selected = self.table.selected().unwrap_or(0);
+        self.table.select(if self.rows.is_empty() {
+            None
+        } else {
+            Some(selected.min(self.rows.len() - 1))
+        });
+        Ok(())
+    }
+
+    fn selected(&mut self) -> Option<&mut ServerRow> {
+        self.table.selected().and_then(|i| self.rows.get_mut(i))
+    }
+
+    /// Handle a key press. Returns `false` to quit.
+    fn handle_key(&mut self, key: KeyEvent) -> Result<bool> {
+        if self.confirm_remove {
+            self.confirm_remove = false;
+            if key.code == KeyCode::Char('y') {
+                self.remove_selected()?;
+            } else {
+                self.message = "Remove cancelled".to_string();
+            }
+            return Ok(true);
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(false);
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::Char('r') => {
+                self.reload()?;
+                self.message = "Refreshed".to_string();
+            }
+            KeyCode::Char('t') => self.start_auth_test(),
+            KeyCode::Char('b') => self.start_jobs_fetch(),
+            KeyCode::Char('d') => {
+                if let Some(name) = self.selected().map(|r| r.name.clone()) {
+                    self.confirm_remove = true;
+                    self.message = format!("Remove '{name}' from config? (y/N)");
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn start_auth_test(&mut self) {
+        let tx = self.updates.clone();
+        let Some(row) = self.selected() else {
+            return;
+        };
+        row.auth = Pending::Running;
+        let name = row.name.clone();
+        let entry = row.entry.clone();
+        self.message = format!("Testing connection to '{name}'...");
+        tokio::spawn(async move {
+            let result = auth_dry_run(&name, &entry).await;
+            let _ = tx.send(Update::Auth(name, result));
+        });
+    }
+
+    fn start_jobs_fetch(&mut self) {
+        let tx = self.updates.clone();
+        let Some(row) = self.selected() else {
+            return;
+        };
+        row.jobs = Pending::Running;
+        let name = row.name.clone();
+        let entry = row.entry.clone();
+        self.message = format!("Listing background jobs on '{name}'...");
+        tokio::spawn(async move {
+            let result = fetch_jobs(&name, &entry).await;
+            let _ = tx.send(Update::Jobs(name, result));
+        });
+    }
+
+    fn remove_selected(&mut self) -> Result<()> {
+        let Some(name) = self.selected().map(|r| r.name.clone()) else {
+            return Ok(());
+        };
+        self.message = if ServerRegistry::modify(|config| config.remove(&name).is_some())? {
+            format!("Removed '{name}'")
+        } else {
+            format!("'{name}' is no longer in the config")
+        };
+        self.reload()
+    }
+
+    fn apply(&mut self, update: Update) {
+        let name = match &update {
+            Update::Probe(name, ..) | Update::Auth(name, _) | Update::Jobs(name, _) => name,
+        };
+        let Some(row) = self.rows.iter_mut().find(|r| &r.name == name) else {
+            return;
+        };
+        match update {
+            Update::Probe(_, reachable, latency) => {
+                row.reachability = if reachable {
+                    Reachability::Reachable(latency)
+                } else {
+                    Reachability::Unreachable
+                };
+            }
+            Update::Auth(name, result) => {
+                self.message = match &result {
+                    Ok(()) => format!("'{name}': connection ok"),
+                    Err(_) => format!("'{name}': connection failed"),
+                };
+                row.auth = Pending::Done(result);
+            }
+            Update::Jobs(name, result) => {
+                self.message = match &result {
+                    Ok(jobs) => format!("'{name}': {} background job logs", jobs.len()),
+                    Err(_) => format!("'{name}': could not list background jobs"),
+                };
+                row.jobs = Pending::Done(result);
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(2)]).areas(frame.area());
+        let [list, details] =
+            Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)])
+                .areas(main);
+
+        let rows = self.rows.iter().map(|row| {
+            let (reach, color) = match row.reachability {
+                Reachability::Probing => ("...".to_string(), Color::DarkGray),
+                Reachability::Reachable(ms) => (
+                    ms.map_or_else(|| "yes".to_string(), |ms| format!("{ms}ms")),
+                    Color::Green,
+                ),
+                Reachability::Unreachable => ("no".to_string(), Color::Red),
+            };
+            let (auth, auth_color) = match &row.auth {
+                Pending::NotRun => ("-", Color::DarkGray),
+                Pending::Running => ("...", Color::DarkGray),
+                Pending::Done(Ok(())) => ("ok", Color::Green),
+                Pending::Done(Err(_)) => ("failed", Color::Red),
+            };
+            Row::new(vec![
+                Cell::from(row.name.clone()),
+                Cell::from(format!(
+                    "{}@{}:{}",
+                    row.entry.user, row.entry.host, row.entry.port
+                )),
+                Cell::from(reach).style(Style::new().fg(color)),
+                Cell::from(auth).style(Style::new().fg(auth_color)),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(25),
+                Constraint::Percentage(45),
+                Constraint::Length(8),
+                Constraint::Length(6),
+            ],
+        )
+        .header(Row::new(["SERVER", "ADDRESS", "REACH", "AUTH"]).bold())
+        .block(Block::bordered().title(" ssh-hub servers "))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+        frame.render_stateful_widget(table, list, &mut self.table);
+
+        let detail = self
+            .table
+            .selected()
+            .and_then(|i| self.rows.get(i))
+            .map_or_else(|| vec![Line::from("No servers configured.")], detail_lines);
+        frame.render_widget(
+            Paragraph::new(detail)
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().title(" details ")),
+            details,
+        );
+
+        let help = Line::from(vec![
+            "j/k".bold(),
+            " move  ".into(),
+            "r".bold(),
+            " refresh  ".into(),
+            "t".bold(),
+            " test connection  ".into(),
+            "b".bold(),
+            " background jobs  ".into(),
+            "d".bold(),
+            " remove  ".into(),
+            "q".bold(),
+            " quit".into(),
+        ]);
+        frame.render_widget(
+            Paragraph::new(vec![Line::from(self.message.as_str()).yellow(), help]),
+            footer,
+        );
+    }
+}
+
+fn detail_lines(row: &ServerRow) -> Vec<Line<'_>> {
+    let now = unix_now();
+    let field = |label: &'static str, value: String| {
+        Line::from(vec![
+            Span::from(format!("{label:<10}")).dark_gray(),
+            value.into(),
+        ])
+    };
+    let entry = &row.entry;
+    let mut lines = vec![
+        Line::from(row.name.as_str()).bold(),
+        field("host", format!("{}:{}", entry.host, entry.port)),
+        field("user", entry.user.clone()),
+        field("path", entry.remote_path.clone()),
+        field("auth", entry.auth.to_string()),
+    ];
+    if let Some(ip) = &entry.resolve {
+        lines.push(field("pinned", ip.clone()));
+    }
+    if let Some(meta) = &entry.metadata {
+        if let Some(summary) = meta.summary_line() {
+            lines.push(field("system", summary));
+        }
+        if let Some(at) = meta.collected_at {
+            lines.push(field(
+                "metadata",
+                format!("{} ago", format_age(now.saturating_sub(at))),
+            ));
+        }
+    }
+    if let Pending::Done(Err(e)) = &row.auth {
+        lines.push(Line::default());
+        lines.push(Line::from(format!("connection failed: {e}")).red());
+    }
+
+    lines.push(Line::default());
+    lines.push(Line::from("background jobs").bold());
+    match &row.jobs {
+        Pending::NotRun => lines.push(Line::from("press b to list").dark_gray()),
+        Pending::Running => lines.push(Line::from("listing...").dark_gray()),
+        Pending::Done(Err(e)) => lines.push(Line::from(e.clone()).red()),
+        Pending::Done(Ok(jobs)) if jobs.is_empty() => {
+            lines.push(Line::from("none").dark_gray());
+        }
+        Pending::Done(Ok(jobs)) => {
+            for job in jobs {
+                let state = match job.running {
+                    Some(true) => "running".green(),
+                    Some(false) => "done   ".dark_gray(),
+                    None => "?      ".dark_gray(),
+                };
+                lines.push(Line::from(vec![
+                    state,
+                    format!(
+                        " {:>5} ago {:>8}B  {}",
+                        format_age(now.saturating_sub(job.modified)),
+                        job.bytes,
+                        job.log_file
+                    )
+                    .into(),
+                ]));
+            }
+        }
+    }
+    lines
+}
+
+async fn fetch_jobs(name: &str, entry: &ServerEntry) -> Result<Vec<BackgroundJob>, String> {
+    let params = params_from_config(name, entry);
+    let listing = async {
+        let conn = SshConnection::connect(params)
+            .await
+            .map_err(|e| format!("{e:#}"))?;
+        if let Some(error) = conn.posix_required("background jobs") {
+            conn.disconnect().await;
+            return Err(error);
+        }
+        let result = conn.exec(JOBS_COMMAND, None).await;
+        conn.disconnect().await;
+        result
+            .map(|r| parse_background_jobs(&r.stdout))
+            .map_err(|e| e.to_string())
+    };
+    timeout(JOBS_TIMEOUT, listing)
+        .await
+        .unwrap_or_else(|_| Err(format!("timed out after {}s", JOBS_TIMEOUT.as_secs())))
+}
+
+pub(super) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
@@ -0,0 +1,65 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use tokio::net::TcpListener;
+
+use crate::connection::{socks, SshConnection};
+use crate::server_registry::ServerRegistry;
+
+use super::params_from_config;
+use super::spinner;
+
+/// How often to check that the SSH connection is still up.
+const LIVENESS_INTERVAL: Duration = Duration::from_secs(5);
+
+pub async fn run(server: &str, socks_port: u16) -> Result<()> {
+    let config = ServerRegistry::load()?;
+    let entry = config
+        .get(server)
+        .ok_or_else(|| anyhow!("Server '{server}' not found in config"))?
+        .clone();
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, socks_port))
+        .await
+        .with_context(|| format!("Cannot listen on 127.0.0.1:{socks_port}"))?;
+    let address = listener.local_addr()?;
+
+    let sp = spinner::start_root(format!("Connecting to {server}..."));
+    let conn = match SshConnection::connect(params_from_config(server, &entry)).await {
+        Ok(c) => Arc::new(c),
+        Err(e) => {
+            spinner::finish_failed(&sp, "connection failed");
+            return Err(e);
+        }
+    };
+    spinner::clear(&sp);
+
+    println!(
+        "{} SOCKS5 proxy on {} tunnels through {}.",
+        "+".green().bold(),
+        address.to_string().bold(),
+        server.bold(),
+    );
+    println!(
+        "{}",
+        format!("Use it with: curl --proxy socks5h://{address} http://example.internal/").dimmed()
+    );
+    println!("{}", "Press Ctrl-C to stop.".dimmed());
+
+    let opener = Arc::clone(&conn);
+    let open = move |host: String, port: u16, peer: SocketAddr| {
+        let conn = Arc::clone(&opener);
+        async move { conn.open_tunnel(&host, port, peer).await }
+    };
+    let proxy = tokio::spawn(socks::serve(listener, open));
+    while !conn.is_closed().await {
+        tokio::time::sleep(LIVENESS_INTERVAL).await;
+    }
+    proxy.abort();
+    Err(anyhow!(
+        "The connection to {server} closed; the proxy has stopped"
+    ))
+}
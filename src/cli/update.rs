@@ -1,14 +1,14 @@
+use std::net::IpAddr;
 use std::path::PathBuf;
 use std::time::Duration;
 
 use anyhow::{anyhow, Result};
 use colored::Colorize;
-use tokio::process::Command as TokioCommand;
-use tokio::time::timeout;
 
 use crate::connection::SshConnection;
+use crate::discovery;
 use crate::metadata::SystemMetadata;
-use crate::server_registry::{ServerEntry, ServerRegistry};
+use crate::server_registry::{RemoteShell, ServerEntry, ServerRegistry};
 use crate::{metadata, metadata::diff};
 
 use super::params_from_config;
@@ -16,13 +16,52 @@ use super::spinner;
 
 const RESOLVE_TIMEOUT: Duration = Duration::from_secs(10);
 
-#[derive(Default)]
+/// Stored settings to change before connecting, from `ssh-hub update` flags.
+#[derive(Debug, Default, clap::Args)]
 pub struct ConnectionOverrides {
+    /// Update the stored SSH host before connecting
+    #[arg(long)]
     pub host: Option<String>,
+
+    /// Update the stored SSH port before connecting
+    #[arg(short = 'p', long)]
     pub port: Option<u16>,
+
+    /// Update the stored remote base path before connecting
+    #[arg(long)]
     pub remote_path: Option<String>,
+
+    /// Update the stored SSH private key path before connecting
+    #[arg(short = 'i', long)]
     pub identity: Option<PathBuf>,
+
+    /// Shell command that resolves the current host/IP on stdout (empty string clears)
+    #[arg(long, value_name = "COMMAND")]
     pub resolve_host: Option<String>,
+
+    /// Pin an IP address for the host, bypassing DNS (empty string clears)
+    #[arg(long, value_name = "IP")]
+    pub resolve: Option<String>,
+
+    /// Remote login shell (detected automatically for Windows servers)
+    #[arg(long, value_enum)]
+    pub shell: Option<RemoteShell>,
+
+    /// Refuse writes and mutating commands from MCP tools on this server
+    #[arg(long, value_name = "BOOL")]
+    pub readonly: Option<bool>,
+
+    /// Configured server that shares this one's filesystem and serves its reads (empty string clears)
+    #[arg(long, value_name = "NAME")]
+    pub read_replica: Option<String>,
+
+    /// Directory the workspace tools make scratch directories in (empty string clears)
+    #[arg(long, value_name = "PATH")]
+    pub scratch_root: Option<String>,
+
+    /// Comma-separated tags for selecting groups of servers (empty string clears)
+    #[arg(long, value_name = "TAGS")]
+    pub tags: Option<String>,
 }
 
 impl ConnectionOverrides {
@@ -32,6 +71,12 @@ impl ConnectionOverrides {
             || self.remote_path.is_some()
             || self.identity.is_some()
             || self.resolve_host.is_some()
+            || self.resolve.is_some()
+            || self.shell.is_some()
+            || self.readonly.is_some()
+            || self.read_replica.is_some()
+            || self.scratch_root.is_some()
+            || self.tags.is_some()
     }
 }
 
@@ -48,8 +93,23 @@ pub async fn run(name: Option<String>, all: bool, overrides: ConnectionOverrides
         ));
     }
 
+    if let Some(ip) = overrides.resolve.as_deref().filter(|ip| !ip.is_empty()) {
+        if ip.parse::<IpAddr>().is_err() {
+            return Err(anyhow!("--resolve expects an IP address, got '{ip}'"));
+        }
+    }
+
+    if let Some(root) = overrides.scratch_root.as_deref().filter(|r| !r.is_empty()) {
+        if !(root.starts_with('/') || root == "~" || root.starts_with("~/")) {
+            return Err(anyhow!(
+                "--scratch-root expects an absolute or ~/ path, got '{root}'"
+            ));
+        }
+    }
+
     let mut config = ServerRegistry::load()?;
 
+    let mut updated = Vec::new();
     if all {
         let names: Vec<String> = config.servers.keys().cloned().collect();
         if names.is_empty() {
@@ -59,14 +119,34 @@ pub async fn run(name: Option<String>, all: bool, overrides: ConnectionOverrides
         for server_name in &names {
             update_single(server_name, &mut config, ConnectionOverrides::default()).await;
         }
+        updated = names;
     } else if let Some(server_name) = name {
         if config.get(&server_name).is_none() {
             return Err(anyhow!("Server '{server_name}' not found in config"));
         }
+        if let Some(replica) = overrides.read_replica.as_deref().filter(|r| !r.is_empty()) {
+            if replica == server_name {
+                return Err(anyhow!("A server can't be its own read replica"));
+            }
+            if config.get(replica).is_none() {
+                return Err(anyhow!("Read replica '{replica}' not found in config"));
+            }
+        }
         update_single(&server_name, &mut config, overrides).await;
+        updated.push(server_name);
     }
 
-    config.save()?;
+    // Write back only the entries this command touched; anything else may
+    // have changed on disk while servers were being contacted.
+    ServerRegistry::modify(|fresh| {
+        for name in &updated {
+            if let Some(entry) = config.servers.remove(name) {
+                if fresh.get(name).is_some() {
+                    fresh.insert(name.clone(), entry);
+                }
+            }
+        }
+    })?;
     Ok(())
 }
 
@@ -98,57 +178,72 @@ fn apply_overrides(entry: &mut ServerEntry, overrides: ConnectionOverrides) {
             entry.resolve_host = Some(rh);
         }
     }
-}
-
-/// Run a shell command that outputs a hostname/IP on stdout.
-///
-/// Returns the trimmed first line of output.
-///
-/// # Errors
-///
-/// Returns an error if:
-/// - The shell process cannot be spawned
-/// - The command exceeds [`RESOLVE_TIMEOUT`]
-/// - The process exits with a non-zero status
-/// - stdout is empty after trimming
-async fn run_resolve_host(command: &str) -> Result<String> {
-    let child = TokioCommand::new("sh")
-        .args(["-c", command])
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .spawn()
-        .map_err(|e| anyhow!("Failed to spawn resolve_host command: {e}"))?;
-
-    let output = timeout(RESOLVE_TIMEOUT, child.wait_with_output())
-        .await
-        .map_err(|_| {
-            anyhow!(
-                "resolve_host timed out after {}s",
-                RESOLVE_TIMEOUT.as_secs()
-            )
-        })?
-        .map_err(|e| anyhow!("resolve_host command failed: {e}"))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!(
-            "resolve_host exited with {}: {}",
-            output.status,
-            stderr.trim()
-        ));
+    if let Some(ip) = overrides.resolve {
+        if ip.is_empty() {
+            println!("  {} resolve cleared", "update".blue());
+            entry.resolve = None;
+        } else {
+            println!("  {} resolve -> {}", "update".blue(), ip.cyan());
+            entry.resolve = Some(ip);
+        }
     }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let host = stdout
-        .lines()
-        .next()
-        .map_or_else(String::new, |line| line.trim().to_string());
-
-    if host.is_empty() {
-        return Err(anyhow!("resolve_host command produced empty output"));
+    if let Some(shell) = overrides.shell {
+        println!(
+            "  {} shell -> {}",
+            "update".blue(),
+            shell.to_string().cyan()
+        );
+        entry.shell = Some(shell);
+    }
+    if let Some(readonly) = overrides.readonly {
+        println!(
+            "  {} readonly -> {}",
+            "update".blue(),
+            readonly.to_string().cyan()
+        );
+        entry.readonly = readonly;
+    }
+    if let Some(replica) = overrides.read_replica {
+        if replica.is_empty() {
+            println!("  {} read_replica cleared", "update".blue());
+            entry.read_replica = None;
+        } else {
+            println!("  {} read_replica -> {}", "update".blue(), replica.cyan());
+            entry.read_replica = Some(replica);
+        }
     }
+    if let Some(root) = overrides.scratch_root {
+        if root.is_empty() {
+            println!("  {} scratch_root cleared", "update".blue());
+            entry.scratch_root = None;
+        } else {
+            println!("  {} scratch_root -> {}", "update".blue(), root.cyan());
+            entry.scratch_root = Some(root);
+        }
+    }
+    if let Some(tags) = overrides.tags {
+        entry.tags = parse_tags(&tags);
+        if entry.tags.is_empty() {
+            println!("  {} tags cleared", "update".blue());
+        } else {
+            println!(
+                "  {} tags -> {}",
+                "update".blue(),
+                entry.tags.join(", ").cyan()
+            );
+        }
+    }
+}
 
-    Ok(host)
+/// Split a comma-separated `--tags` value, dropping blanks and duplicates.
+fn parse_tags(tags: &str) -> Vec<String> {
+    let mut parsed: Vec<String> = Vec::new();
+    for tag in tags.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        if !parsed.iter().any(|t| t == tag) {
+            parsed.push(tag.to_string());
+        }
+    }
+    parsed
 }
 
 /// Resolve the dynamic host if configured and no explicit `--host` override was given.
@@ -159,12 +254,19 @@ async fn maybe_resolve_host(entry: &mut ServerEntry, explicit_host: bool) {
     if explicit_host {
         return;
     }
-    let Some(ref cmd) = entry.resolve_host else {
-        return;
+    // A `discovery` entry, e.g. from `ssh-hub add --gcp-instance`, resolves
+    // here too when no resolve_host command is set
+    let cmd = match (&entry.resolve_host, &entry.discovery) {
+        (Some(cmd), _) => cmd.clone(),
+        (None, Some(lookup)) => match lookup.lookup_command() {
+            Ok(cmd) => cmd,
+            Err(_) => return,
+        },
+        (None, None) => return,
     };
 
     let sp = spinner::start("Resolving host...");
-    match run_resolve_host(cmd).await {
+    match discovery::run_lookup("resolve_host", &cmd, RESOLVE_TIMEOUT).await {
         Ok(resolved) if resolved == entry.host => {
             spinner::finish_ok(&sp, "Host unchanged");
         }
@@ -1,12 +1,180 @@
-use std::path::Path;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 
 use anyhow::{anyhow, Context, Result};
-use russh::client::Handle;
-use russh::keys::agent::client::AgentClient;
-use russh::keys::{load_secret_key, Algorithm, HashAlg, PrivateKeyWithHashAlg, PublicKey};
+use russh::client::{Handle, KeyboardInteractiveAuthResponse};
+use russh::keys::agent::client::{AgentClient, AgentStream};
+use russh::keys::{
+    load_public_key, load_secret_key, Algorithm, HashAlg, PrivateKey, PrivateKeyWithHashAlg,
+    PublicKey,
+};
+use tokio::sync::Mutex;
 
 use super::session::{ConnectionParams, SshHandler};
+use crate::keychain;
+
+/// What a security key needs before the agent can sign with it.
+pub const SECURITY_KEY_HINT: &str = "touch your security key when it blinks";
+
+/// Whether `algorithm` is a FIDO2 security key (`sk-ssh-ed25519@openssh.com`
+/// or `sk-ecdsa-sha2-nistp256@openssh.com`). Those keys sign on the
+/// authenticator, so ssh-hub can only use them through ssh-agent.
+#[must_use]
+pub fn is_security_key(algorithm: &Algorithm) -> bool {
+    matches!(
+        algorithm,
+        Algorithm::SkEd25519 | Algorithm::SkEcdsaSha2NistP256
+    )
+}
+
+/// The public key of a security key file, read from `<path>.pub` or from
+/// the key file itself. `None` for other key types and unreadable files.
+#[must_use]
+pub fn security_key_public(path: &Path) -> Option<PublicKey> {
+    let mut pub_path = path.as_os_str().to_owned();
+    pub_path.push(".pub");
+    let public = load_public_key(&pub_path).ok().or_else(|| {
+        load_secret_key(path, None)
+            .ok()
+            .map(|k| k.public_key().clone())
+    })?;
+    is_security_key(&public.algorithm()).then_some(public)
+}
+
+/// The server accepted none of the credentials tried, or they couldn't be
+/// used at all (no agent, no key file).
+#[derive(Debug)]
+pub struct AuthFailed(pub String);
+
+impl fmt::Display for AuthFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for AuthFailed {}
+
+/// A key file that is encrypted with a passphrase ssh-hub doesn't have:
+/// none is stored in the keychain and there is no one to prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedKey {
+    pub path: PathBuf,
+}
+
+impl fmt::Display for EncryptedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The key {} is encrypted with a passphrase, and no passphrase for it is stored. \
+             Load it into ssh-agent with 'ssh-add {0}', or store the passphrase with \
+             'ssh-hub key-passphrase <server>'.",
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for EncryptedKey {}
+
+/// Asks for the passphrase of an encrypted key; `None` when the user
+/// declines.
+pub type PassphrasePrompt = fn(&Path) -> Option<String>;
+
+static PASSPHRASE_PROMPT: OnceLock<PassphrasePrompt> = OnceLock::new();
+
+/// Passphrases that decrypted a key, so each key is asked for once per
+/// process. The lock also keeps concurrent connections from prompting at
+/// the same time.
+static PASSPHRASES: Mutex<Option<HashMap<PathBuf, String>>> = Mutex::const_new(None);
+
+/// Let connections ask for the passphrase of an encrypted key that has none
+/// stored in the keychain. The CLI sets this when it runs in a terminal; the
+/// MCP server never does, so there the key fails with [`EncryptedKey`].
+pub fn set_passphrase_prompt(prompt: PassphrasePrompt) {
+    let _ = PASSPHRASE_PROMPT.set(prompt);
+}
+
+/// Decrypt the key at `path` with `passphrase` (`None` for unencrypted keys).
+///
+/// # Errors
+///
+/// Returns the key loader's error, e.g. [`russh::keys::Error::KeyIsEncrypted`]
+/// for an encrypted key without a passphrase, or a decryption error for a
+/// wrong one.
+pub async fn decrypt_key(path: &Path, passphrase: Option<String>) -> Result<PrivateKey> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || load_secret_key(&path, passphrase.as_deref()))
+        .await
+        .context("Key loading task panicked")?
+        .map_err(anyhow::Error::from)
+}
+
+/// Whether `error` came from loading an encrypted key without its passphrase.
+#[must_use]
+pub fn is_encrypted_key_error(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<russh::keys::Error>(),
+        Some(russh::keys::Error::KeyIsEncrypted)
+    )
+}
+
+/// Load the private key at `path`, decrypting it with the passphrase from
+/// the keychain or the prompt when it is encrypted. `None` when the file
+/// can't be used as a key at all.
+async fn load_key(path: &Path) -> Result<Option<PrivateKey>> {
+    let error = match decrypt_key(path, None).await {
+        Ok(key) => return Ok(Some(key)),
+        Err(e) if is_encrypted_key_error(&e) => e,
+        Err(e) => {
+            tracing::debug!("Failed to load key {:?}: {:#}", path, e);
+            return Ok(None);
+        }
+    };
+    tracing::debug!("{:?} is encrypted: {}", path, error);
+
+    let mut cache = PASSPHRASES.lock().await;
+    let cache = cache.get_or_insert_with(HashMap::new);
+    if let Some(passphrase) = cache.get(path) {
+        return decrypt_key(path, Some(passphrase.clone())).await.map(Some);
+    }
+    match keychain::key_passphrase(path).await {
+        Ok(Some(passphrase)) => match decrypt_key(path, Some(passphrase.clone())).await {
+            Ok(key) => {
+                cache.insert(path.to_path_buf(), passphrase);
+                return Ok(Some(key));
+            }
+            Err(e) => tracing::warn!(
+                "The passphrase stored for {} doesn't decrypt it: {e:#}",
+                path.display()
+            ),
+        },
+        Ok(None) => {}
+        Err(e) => tracing::debug!("No keychain to look up the key passphrase in: {e:#}"),
+    }
+
+    let Some(prompt) = PASSPHRASE_PROMPT.get().copied() else {
+        return Err(EncryptedKey {
+            path: path.to_path_buf(),
+        }
+        .into());
+    };
+    let asked = path.to_path_buf();
+    let passphrase = tokio::task::spawn_blocking(move || prompt(&asked))
+        .await
+        .context("Passphrase prompt panicked")?;
+    let Some(passphrase) = passphrase else {
+        return Err(EncryptedKey {
+            path: path.to_path_buf(),
+        }
+        .into());
+    };
+    let key = decrypt_key(path, Some(passphrase.clone()))
+        .await
+        .with_context(|| format!("Could not decrypt {} with that passphrase", path.display()))?;
+    cache.insert(path.to_path_buf(), passphrase);
+    Ok(Some(key))
+}
 
 /// Authenticate with the SSH server using the configured auth method.
 pub async fn authenticate(
@@ -15,13 +183,22 @@ pub async fn authenticate(
 ) -> Result<()> {
     match &params.auth_method {
         crate::server_registry::AuthMethod::Auto => authenticate_auto(session, params).await,
-        crate::server_registry::AuthMethod::Agent => try_agent_auth(session, &params.user).await,
+        crate::server_registry::AuthMethod::Agent => {
+            try_agent_auth(session, &params.user, params.agent_socket.as_deref(), None).await
+        }
         crate::server_registry::AuthMethod::Key => {
             let key_path = params
                 .identity
                 .as_ref()
                 .ok_or_else(|| anyhow!("Auth method is 'key' but no identity file specified"))?;
-            if try_key_auth(session, &params.user, key_path).await? {
+            if try_key_auth(
+                session,
+                &params.user,
+                params.agent_socket.as_deref(),
+                key_path,
+            )
+            .await?
+            {
                 Ok(())
             } else {
                 Err(anyhow!("Key authentication failed"))
@@ -30,6 +207,44 @@ pub async fn authenticate(
     }
 }
 
+/// Authenticate with a password, for onboarding a host that has no key
+/// installed yet. Servers that only offer keyboard-interactive get the
+/// password as the answer to each prompt.
+pub async fn authenticate_password(
+    session: &mut Handle<SshHandler>,
+    user: &str,
+    password: &str,
+) -> Result<()> {
+    match session.authenticate_password(user, password).await {
+        Ok(result) if result.success() => return Ok(()),
+        Ok(result) => tracing::debug!("Password auth rejected: {:?}", result),
+        Err(e) => tracing::debug!("Password auth error: {}", e),
+    }
+
+    let mut response = session
+        .authenticate_keyboard_interactive_start(user, None)
+        .await
+        .context("Keyboard-interactive auth failed")?;
+    // Bounded so a server that keeps asking can't loop forever.
+    for _ in 0..MAX_INTERACTIVE_ROUNDS {
+        match response {
+            KeyboardInteractiveAuthResponse::Success => return Ok(()),
+            KeyboardInteractiveAuthResponse::Failure { .. } => break,
+            KeyboardInteractiveAuthResponse::InfoRequest { prompts, .. } => {
+                let answers = prompts.iter().map(|_| password.to_string()).collect();
+                response = session
+                    .authenticate_keyboard_interactive_respond(answers)
+                    .await
+                    .context("Keyboard-interactive auth failed")?;
+            }
+        }
+    }
+    Err(anyhow!("Password authentication failed for user '{user}'"))
+}
+
+/// Maximum keyboard-interactive prompt rounds answered with the password.
+const MAX_INTERACTIVE_ROUNDS: usize = 4;
+
 /// Auto auth: try all methods in order.
 ///
 /// Order: explicit identity (highest signal) → SSH agent → default key paths.
@@ -38,22 +253,44 @@ async fn authenticate_auto(
     params: &ConnectionParams,
 ) -> Result<()> {
     let mut methods_tried = Vec::new();
+    // The agent may hold an encrypted key, so it only ends the attempt if
+    // nothing else works.
+    let mut encrypted: Option<EncryptedKey> = None;
 
     // 1. Explicit identity file (user specified — highest signal)
     if let Some(key_path) = &params.identity {
         tracing::debug!("Trying identity file: {:?}", key_path);
-        if try_key_auth(session, &params.user, key_path).await? {
-            tracing::debug!("Authenticated via identity file");
-            return Ok(());
+        match try_key_auth(
+            session,
+            &params.user,
+            params.agent_socket.as_deref(),
+            key_path,
+        )
+        .await
+        {
+            Ok(true) => {
+                tracing::debug!("Authenticated via identity file");
+                return Ok(());
+            }
+            Ok(false) => {}
+            Err(e) => match e.downcast::<EncryptedKey>() {
+                Ok(e) => encrypted = Some(e),
+                Err(e) => return Err(e),
+            },
         }
         methods_tried.push("identity file");
     }
 
     // 2. SSH agent
-    match try_agent_auth(session, &params.user).await {
+    // A security key the user didn't touch is worth saying so in the error
+    let mut touch_hint = "";
+    match try_agent_auth(session, &params.user, params.agent_socket.as_deref(), None).await {
         Ok(()) => return Ok(()),
         Err(e) => {
             tracing::debug!("Agent auth failed: {}", e);
+            if e.to_string().contains(SECURITY_KEY_HINT) {
+                touch_hint = " A security key was tried; touch it when it blinks and try again.";
+            }
             methods_tried.push("agent");
         }
     }
@@ -66,16 +303,36 @@ async fn authenticate_auto(
 
         if let Some(key_path) = key_path {
             tracing::debug!("Trying default key: {:?}", key_path);
-            if try_key_auth(session, &params.user, &key_path).await? {
-                tracing::debug!("Authenticated via {:?}", key_path);
-                return Ok(());
+            match try_key_auth(
+                session,
+                &params.user,
+                params.agent_socket.as_deref(),
+                &key_path,
+            )
+            .await
+            {
+                Ok(true) => {
+                    tracing::debug!("Authenticated via {:?}", key_path);
+                    return Ok(());
+                }
+                Ok(false) => {}
+                Err(e) => match e.downcast::<EncryptedKey>() {
+                    Ok(e) => {
+                        encrypted.get_or_insert(e);
+                    }
+                    Err(e) => return Err(e),
+                },
             }
         }
     }
     methods_tried.push("default keys");
 
+    if let Some(e) = encrypted {
+        return Err(e.into());
+    }
+
     Err(anyhow!(
-        "Authentication failed. Tried: {}. Check your credentials and run 'ssh-hub add' to reconfigure.",
+        "Authentication failed. Tried: {}. Check your credentials and run 'ssh-hub add' to reconfigure.{touch_hint}",
         methods_tried.join(", ")
     ))
 }
@@ -108,21 +365,110 @@ fn rsa_hash_for_key(key: &PublicKey, cached_rsa_hash: Option<HashAlg>) -> Option
     }
 }
 
+/// Agent pipe of the OpenSSH for Windows `ssh-agent` service.
+pub const WINDOWS_AGENT_PIPE: &str = r"\\.\pipe\openssh-ssh-agent";
+
+/// Where to reach ssh-agent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentLocation {
+    /// Unix domain socket, as in `SSH_AUTH_SOCK`.
+    Socket(String),
+    /// Windows named pipe, e.g. the OpenSSH for Windows agent.
+    NamedPipe(String),
+    /// Pageant, the agent that comes with `PuTTY`.
+    Pageant,
+}
+
+/// The agent to use: the server's `agent_socket`, else `SSH_AUTH_SOCK`
+/// (`env_socket`), else the OpenSSH agent pipe on Windows. `None` when
+/// there is nothing to connect to.
+#[must_use]
+pub fn agent_location<'a>(
+    configured: Option<&'a str>,
+    env_socket: Option<&'a str>,
+) -> Option<AgentLocation> {
+    let set = |s: Option<&'a str>| s.map(str::trim).filter(|s| !s.is_empty());
+    let socket = set(configured).or_else(|| set(env_socket));
+    let Some(socket) = socket else {
+        return cfg!(windows).then(|| AgentLocation::NamedPipe(WINDOWS_AGENT_PIPE.to_string()));
+    };
+    if socket.eq_ignore_ascii_case("pageant") {
+        Some(AgentLocation::Pageant)
+    } else if socket.starts_with(r"\\.\pipe\") || socket.starts_with("//./pipe/") {
+        Some(AgentLocation::NamedPipe(socket.to_string()))
+    } else {
+        Some(AgentLocation::Socket(socket.to_string()))
+    }
+}
+
 /// Maximum agent keys to try before giving up (avoids "too many auth failures").
 const MAX_AGENT_KEYS: usize = 10;
 
-/// Try SSH agent authentication.
-async fn try_agent_auth(session: &mut Handle<SshHandler>, user: &str) -> Result<()> {
-    let mut agent = AgentClient::connect_env()
-        .await
-        .context("Failed to connect to SSH agent (is SSH_AUTH_SOCK set?)")?;
+/// Try SSH agent authentication, with every agent key or only `only`.
+///
+/// Security keys ask for a touch on the authenticator while the agent
+/// signs, so each attempt with one logs [`SECURITY_KEY_HINT`] first.
+async fn try_agent_auth(
+    session: &mut Handle<SshHandler>,
+    user: &str,
+    agent_socket: Option<&str>,
+    only: Option<&PublicKey>,
+) -> Result<()> {
+    let env_socket = std::env::var("SSH_AUTH_SOCK").ok();
+    let location = agent_location(agent_socket, env_socket.as_deref()).ok_or_else(|| {
+        anyhow!("Failed to connect to SSH agent: SSH_AUTH_SOCK is not set and no agent_socket is configured")
+    })?;
+    match location {
+        #[cfg(unix)]
+        AgentLocation::Socket(path) => {
+            let agent = AgentClient::connect_uds(&path)
+                .await
+                .with_context(|| format!("Failed to connect to SSH agent at {path}"))?;
+            auth_with_agent(session, user, agent, only).await
+        }
+        #[cfg(windows)]
+        AgentLocation::NamedPipe(pipe) => {
+            let agent = AgentClient::connect_named_pipe(&pipe)
+                .await
+                .with_context(|| format!("Failed to connect to SSH agent at {pipe}"))?;
+            auth_with_agent(session, user, agent, only).await
+        }
+        #[cfg(windows)]
+        AgentLocation::Pageant => {
+            let agent = AgentClient::connect_pageant().await;
+            auth_with_agent(session, user, agent, only).await
+        }
+        #[cfg(windows)]
+        AgentLocation::Socket(path) => Err(anyhow!(
+            "Agent socket {path} is not a named pipe; on Windows use \\\\.\\pipe\\<name> or pageant"
+        )),
+        #[cfg(not(windows))]
+        AgentLocation::NamedPipe(_) | AgentLocation::Pageant => Err(anyhow!(
+            "Named-pipe and Pageant agents are only available on Windows"
+        )),
+    }
+}
 
-    let identities = agent
+/// Authenticate with the keys of a connected agent.
+async fn auth_with_agent<S: AgentStream + Send + Unpin + 'static>(
+    session: &mut Handle<SshHandler>,
+    user: &str,
+    mut agent: AgentClient<S>,
+    only: Option<&PublicKey>,
+) -> Result<()> {
+    let mut identities = agent
         .request_identities()
         .await
         .context("Failed to list keys from SSH agent")?;
 
-    if identities.is_empty() {
+    if let Some(wanted) = only {
+        identities.retain(|key| key.key_data() == wanted.key_data());
+        if identities.is_empty() {
+            return Err(anyhow!(
+                "not loaded in ssh-agent. Run 'ssh-add' with the key file, then {SECURITY_KEY_HINT}."
+            ));
+        }
+    } else if identities.is_empty() {
         return Err(anyhow!("SSH agent has no keys. Run 'ssh-add' first."));
     }
 
@@ -132,6 +478,7 @@ async fn try_agent_auth(session: &mut Handle<SshHandler>, user: &str) -> Result<
 
     // Query RSA hash once and cache for all keys
     let cached_rsa_hash = query_rsa_hash(session).await;
+    let mut tried_security_key = false;
 
     for (i, key) in identities.iter().take(try_count).enumerate() {
         let hash_alg = rsa_hash_for_key(key, cached_rsa_hash);
@@ -142,6 +489,13 @@ async fn try_agent_auth(session: &mut Handle<SshHandler>, user: &str) -> Result<
             key.algorithm(),
             hash_alg,
         );
+        if is_security_key(&key.algorithm()) {
+            tried_security_key = true;
+            tracing::info!(
+                "Signing in as {user} with security key {}: {SECURITY_KEY_HINT}",
+                key.comment()
+            );
+        }
         match session
             .authenticate_publickey_with(user, key.clone(), hash_alg, &mut agent)
             .await
@@ -164,27 +518,38 @@ async fn try_agent_auth(session: &mut Handle<SshHandler>, user: &str) -> Result<
         }
     }
 
+    let hint = if tried_security_key {
+        format!(" A security key was tried; {SECURITY_KEY_HINT} and try again.")
+    } else {
+        String::new()
+    };
     Err(anyhow!(
-        "SSH agent has {total} key(s) but none of the first {try_count} were accepted",
+        "SSH agent has {total} key(s) but none of the first {try_count} were accepted.{hint}",
     ))
 }
 
 /// Try to authenticate with a specific key file.
+///
+/// Fails with [`EncryptedKey`] when the key is encrypted and its passphrase
+/// is neither stored nor given at the prompt.
 async fn try_key_auth(
     session: &mut Handle<SshHandler>,
     user: &str,
+    agent_socket: Option<&str>,
     key_path: &Path,
 ) -> Result<bool> {
-    let key_path_owned = key_path.to_path_buf();
-    let key = match tokio::task::spawn_blocking(move || load_secret_key(&key_path_owned, None))
-        .await
-        .context("Key loading task panicked")?
-    {
-        Ok(k) => k,
-        Err(e) => {
-            tracing::debug!("Failed to load key {:?}: {}", key_path, e);
-            return Ok(false);
-        }
+    if let Some(public) = security_key_public(key_path) {
+        tracing::debug!(
+            "{:?} is a security key; signing through ssh-agent",
+            key_path
+        );
+        return try_agent_auth(session, user, agent_socket, Some(&public))
+            .await
+            .map(|()| true)
+            .map_err(|e| anyhow!("Security key {}: {e}", key_path.display()));
+    }
+    let Some(key) = load_key(key_path).await? else {
+        return Ok(false);
     };
 
     let cached_rsa_hash = query_rsa_hash(session).await;
@@ -0,0 +1,67 @@
+//! Gzip-compressed command output, for commands whose stdout is large
+//! enough that the transfer dominates, such as log dumps over slow links.
+//!
+//! The remote wrapper prints one header byte, `Z` when gzip follows or `P`
+//! when the remote has no `gzip` and the output is plain, then the output.
+//! Stderr is left uncompressed.
+
+use std::io::Read;
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+
+use super::session::{ExecRawResult, ExecResult};
+
+/// Wrap a POSIX `command` so its stdout is gzipped on the remote, keeping
+/// its exit code (plain `sh` has no `pipefail`, so the code travels on fd 3).
+/// The result is one compound command, so it can follow `cd dir &&`.
+#[must_use]
+pub fn compress_command(command: &str) -> String {
+    format!(
+        "{{ if command -v gzip >/dev/null 2>&1; then z='gzip -c'; printf Z; else z=cat; printf P; fi; \
+         {{ s=$( {{ {{ ( {command}\n) 3>&- 4>&-; echo $? >&3; }} | $z >&4 3>&-; }} 3>&1 ); exit \"${{s:-1}}\"; }} 4>&1; }}"
+    )
+}
+
+/// Decode the output of a [`compress_command`] run.
+///
+/// # Errors
+///
+/// Returns an error if the gzip stream is corrupt.
+pub fn decode_compressed(raw: ExecRawResult) -> Result<ExecResult> {
+    Ok(ExecResult {
+        stdout: decompress(raw.stdout, false)?,
+        stderr: raw.stderr,
+        exit_code: raw.exit_code,
+    })
+}
+
+/// Decode the output of a [`compress_command`] run that was cut short,
+/// keeping as much of the gzip stream as decompresses.
+#[must_use]
+pub fn decode_compressed_partial(raw: ExecRawResult) -> ExecResult {
+    ExecResult {
+        stdout: decompress(raw.stdout, true).unwrap_or_default(),
+        stderr: raw.stderr,
+        exit_code: raw.exit_code,
+    }
+}
+
+/// Stdout of a [`compress_command`] run, decompressed. With `partial`, a
+/// truncated gzip stream is not an error.
+fn decompress(raw: Vec<u8>, partial: bool) -> Result<String> {
+    let stdout = match raw.split_first() {
+        Some((b'Z', gzipped)) => {
+            let mut stdout = Vec::new();
+            let read = GzDecoder::new(gzipped).read_to_end(&mut stdout);
+            if !partial {
+                read.context("Failed to decompress command output")?;
+            }
+            stdout
+        }
+        Some((b'P', plain)) => plain.to_vec(),
+        // The wrapper never ran, e.g. the login shell failed first
+        _ => raw,
+    };
+    Ok(String::from_utf8_lossy(&stdout).into_owned())
+}
@@ -0,0 +1,143 @@
+//! [`RemoteExecutor`], the operations tool handlers run against a server.
+//!
+//! Handlers that only run commands and read, write, or list files take any
+//! `RemoteExecutor`, so they work the same against a live [`SshConnection`]
+//! and the in-memory [`FakeExecutor`](super::FakeExecutor) used in tests.
+
+use std::future::Future;
+
+use anyhow::Result;
+
+use crate::server_registry::RemoteShell;
+use crate::utils::glob::{GlobKind, GlobMatches, GlobPage};
+
+use super::session::{ExecOutcome, ExecRawResult, ExecResult, SshConnection};
+
+/// Commands and file operations on one remote server.
+pub trait RemoteExecutor: Send + Sync {
+    /// Base remote path that commands run in and relative paths resolve against.
+    fn remote_path(&self) -> &str;
+
+    /// Remote shell dialect.
+    fn shell(&self) -> RemoteShell;
+
+    /// Why a feature built on POSIX utilities is unavailable, or `None`
+    /// when the remote has a POSIX shell.
+    fn posix_required(&self, feature: &str) -> Option<String> {
+        self.shell().posix_required(feature)
+    }
+
+    /// Run `command` in the base path.
+    fn exec(
+        &self,
+        command: &str,
+        timeout_ms: Option<u64>,
+    ) -> impl Future<Output = Result<ExecResult>> + Send;
+
+    /// Run `command` in the base path with raw byte output, piping
+    /// `stdin_data` to it.
+    fn exec_raw(
+        &self,
+        command: &str,
+        stdin_data: Option<&[u8]>,
+        timeout_ms: Option<u64>,
+    ) -> impl Future<Output = Result<ExecRawResult>> + Send;
+
+    /// Run `command` like [`Self::exec_raw`], stopping it if `cancelled`
+    /// completes first.
+    fn exec_raw_cancellable(
+        &self,
+        command: &str,
+        stdin_data: Option<&[u8]>,
+        timeout_ms: Option<u64>,
+        cancelled: impl Future<Output = ()> + Send,
+    ) -> impl Future<Output = Result<ExecOutcome>> + Send;
+
+    /// Read a file as raw bytes.
+    fn read_file_raw(&self, path: &str) -> impl Future<Output = Result<Vec<u8>>> + Send;
+
+    /// Read a file as UTF-8 text, replacing invalid sequences.
+    fn read_file(&self, path: &str) -> impl Future<Output = Result<String>> + Send {
+        async move {
+            let bytes = self.read_file_raw(path).await?;
+            Ok(String::from_utf8_lossy(&bytes).into_owned())
+        }
+    }
+
+    /// Replace a file's contents.
+    fn write_file_raw(&self, path: &str, content: &[u8])
+        -> impl Future<Output = Result<()>> + Send;
+
+    /// Replace a file's contents with text.
+    fn write_file(&self, path: &str, content: &str) -> impl Future<Output = Result<()>> + Send {
+        self.write_file_raw(path, content.as_bytes())
+    }
+
+    /// One page of the entries under `base_path` matching a glob pattern.
+    fn glob(
+        &self,
+        pattern: &str,
+        base_path: Option<&str>,
+        kind: GlobKind,
+        page: GlobPage,
+    ) -> impl Future<Output = Result<GlobMatches>> + Send;
+}
+
+impl RemoteExecutor for SshConnection {
+    fn remote_path(&self) -> &str {
+        Self::remote_path(self)
+    }
+
+    fn shell(&self) -> RemoteShell {
+        Self::shell(self)
+    }
+
+    fn exec(
+        &self,
+        command: &str,
+        timeout_ms: Option<u64>,
+    ) -> impl Future<Output = Result<ExecResult>> + Send {
+        Self::exec(self, command, timeout_ms)
+    }
+
+    fn exec_raw(
+        &self,
+        command: &str,
+        stdin_data: Option<&[u8]>,
+        timeout_ms: Option<u64>,
+    ) -> impl Future<Output = Result<ExecRawResult>> + Send {
+        Self::exec_raw(self, command, stdin_data, timeout_ms)
+    }
+
+    fn exec_raw_cancellable(
+        &self,
+        command: &str,
+        stdin_data: Option<&[u8]>,
+        timeout_ms: Option<u64>,
+        cancelled: impl Future<Output = ()> + Send,
+    ) -> impl Future<Output = Result<ExecOutcome>> + Send {
+        Self::exec_raw_cancellable(self, command, stdin_data, timeout_ms, cancelled)
+    }
+
+    fn read_file_raw(&self, path: &str) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        Self::read_file_raw(self, path)
+    }
+
+    fn write_file_raw(
+        &self,
+        path: &str,
+        content: &[u8],
+    ) -> impl Future<Output = Result<()>> + Send {
+        Self::write_file_raw(self, path, content)
+    }
+
+    fn glob(
+        &self,
+        pattern: &str,
+        base_path: Option<&str>,
+        kind: GlobKind,
+        page: GlobPage,
+    ) -> impl Future<Output = Result<GlobMatches>> + Send {
+        Self::glob(self, pattern, base_path, kind, page)
+    }
+}
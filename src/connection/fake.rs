@@ -0,0 +1,237 @@
+//! [`FakeExecutor`], an in-memory [`RemoteExecutor`] for testing tool
+//! handlers without an SSH server.
+//!
+//! Files live in a map keyed by the exact path handlers pass in. Commands
+//! are answered from canned responses, matched by substring in the order
+//! they were added; anything unmatched exits 127.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+
+use crate::server_registry::RemoteShell;
+use crate::utils::glob::{page_matches, GlobKind, GlobMatches, GlobPage, GlobPattern};
+
+use super::executor::RemoteExecutor;
+use super::session::{ExecOutcome, ExecRawResult, ExecResult};
+
+/// Exit code of a command with no canned response, as `sh` reports an
+/// unknown command.
+const EXIT_CODE_UNMATCHED: i32 = 127;
+
+/// In-memory stand-in for a remote server.
+#[derive(Debug)]
+pub struct FakeExecutor {
+    remote_path: String,
+    shell: RemoteShell,
+    files: Mutex<BTreeMap<String, Vec<u8>>>,
+    responses: Vec<(String, ExecResult)>,
+    commands: Mutex<Vec<String>>,
+}
+
+impl FakeExecutor {
+    /// A POSIX server with base path `remote_path`, no files, and no
+    /// canned responses.
+    #[must_use]
+    pub fn new(remote_path: &str) -> Self {
+        Self {
+            remote_path: remote_path.to_string(),
+            shell: RemoteShell::Posix,
+            files: Mutex::new(BTreeMap::new()),
+            responses: Vec::new(),
+            commands: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Use `shell` as the remote shell dialect.
+    #[must_use]
+    pub fn with_shell(mut self, shell: RemoteShell) -> Self {
+        self.shell = shell;
+        self
+    }
+
+    /// Add a file at `path`.
+    #[must_use]
+    pub fn with_file(self, path: &str, content: impl Into<Vec<u8>>) -> Self {
+        self.lock_files().insert(path.to_string(), content.into());
+        self
+    }
+
+    /// Answer commands containing `fragment` with `stdout` and exit code 0.
+    #[must_use]
+    pub fn on_command(self, fragment: &str, stdout: &str) -> Self {
+        self.on_command_result(
+            fragment,
+            ExecResult {
+                stdout: stdout.to_string(),
+                stderr: String::new(),
+                exit_code: 0,
+            },
+        )
+    }
+
+    /// Answer commands containing `fragment` with `result`.
+    #[must_use]
+    pub fn on_command_result(mut self, fragment: &str, result: ExecResult) -> Self {
+        self.responses.push((fragment.to_string(), result));
+        self
+    }
+
+    /// Current content of the file at `path`.
+    #[must_use]
+    pub fn file(&self, path: &str) -> Option<Vec<u8>> {
+        self.lock_files().get(path).cloned()
+    }
+
+    /// Commands run so far, in order.
+    #[must_use]
+    pub fn commands(&self) -> Vec<String> {
+        self.commands
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone()
+    }
+
+    fn lock_files(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Vec<u8>>> {
+        self.files
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn respond(&self, command: &str) -> ExecResult {
+        self.commands
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .push(command.to_string());
+        self.responses
+            .iter()
+            .find(|(fragment, _)| command.contains(fragment.as_str()))
+            .map_or_else(
+                || ExecResult {
+                    stdout: String::new(),
+                    stderr: format!("fake: no response for `{command}`"),
+                    exit_code: EXIT_CODE_UNMATCHED,
+                },
+                |(_, result)| result.clone(),
+            )
+    }
+
+    /// Paths below `base` relative to it, with every directory on the way
+    /// listed once with a trailing `/`.
+    fn entries_below(&self, base: &str) -> Vec<String> {
+        let prefix = format!("{}/", base.trim_end_matches('/'));
+        let mut entries = Vec::new();
+        for path in self.lock_files().keys() {
+            let Some(rel) = path.strip_prefix(&prefix) else {
+                continue;
+            };
+            for (i, _) in rel.match_indices('/') {
+                let dir = format!("{}/", &rel[..i]);
+                if !entries.contains(&dir) {
+                    entries.push(dir);
+                }
+            }
+            entries.push(rel.to_string());
+        }
+        entries
+    }
+}
+
+impl RemoteExecutor for FakeExecutor {
+    fn remote_path(&self) -> &str {
+        &self.remote_path
+    }
+
+    fn shell(&self) -> RemoteShell {
+        self.shell
+    }
+
+    fn exec(
+        &self,
+        command: &str,
+        _timeout_ms: Option<u64>,
+    ) -> impl Future<Output = Result<ExecResult>> + Send {
+        let result = self.respond(command);
+        async move { Ok(result) }
+    }
+
+    fn exec_raw(
+        &self,
+        command: &str,
+        _stdin_data: Option<&[u8]>,
+        _timeout_ms: Option<u64>,
+    ) -> impl Future<Output = Result<ExecRawResult>> + Send {
+        let result = self.respond(command);
+        async move {
+            Ok(ExecRawResult {
+                stdout: result.stdout.into_bytes(),
+                stderr: result.stderr,
+                exit_code: result.exit_code,
+            })
+        }
+    }
+
+    /// Canned responses are ready at once, so the command finishes unless
+    /// `cancelled` already has; a cancelled one keeps its canned output as
+    /// the output so far.
+    fn exec_raw_cancellable(
+        &self,
+        command: &str,
+        stdin_data: Option<&[u8]>,
+        timeout_ms: Option<u64>,
+        cancelled: impl Future<Output = ()> + Send,
+    ) -> impl Future<Output = Result<ExecOutcome>> + Send {
+        let result = self.exec_raw(command, stdin_data, timeout_ms);
+        async move {
+            let result = result.await?;
+            tokio::select! {
+                biased;
+                () = cancelled => Ok(ExecOutcome::Cancelled(result)),
+                () = std::future::ready(()) => Ok(ExecOutcome::Finished(result)),
+            }
+        }
+    }
+
+    fn read_file_raw(&self, path: &str) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        let content = self.file(path).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Failed to read file: {path}: No such file or directory"),
+            )
+            .into()
+        });
+        async move { content }
+    }
+
+    fn write_file_raw(
+        &self,
+        path: &str,
+        content: &[u8],
+    ) -> impl Future<Output = Result<()>> + Send {
+        self.lock_files().insert(path.to_string(), content.to_vec());
+        async { Ok(()) }
+    }
+
+    fn glob(
+        &self,
+        pattern: &str,
+        base_path: Option<&str>,
+        kind: GlobKind,
+        page: GlobPage,
+    ) -> impl Future<Output = Result<GlobMatches>> + Send {
+        let matches = GlobPattern::new(pattern)
+            .map_err(|e| anyhow!("Invalid glob pattern: {e}"))
+            .map(|glob| {
+                let entries = self.entries_below(base_path.unwrap_or(&self.remote_path));
+                page_matches(
+                    &glob,
+                    entries.iter().map(|e| (e.as_str(), None)),
+                    kind,
+                    page,
+                )
+            });
+        async move { matches }
+    }
+}
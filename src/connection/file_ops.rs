@@ -2,10 +2,17 @@
 //!
 //! Wrappers around [`exec`](SshConnection::exec) and
 //! [`exec_raw`](SshConnection::exec_raw) for common remote file operations.
+//! Remotes without a POSIX shell (Windows) use SFTP instead.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{anyhow, Result};
 
-use crate::utils::path::{shell_escape, shell_escape_remote_path};
+use crate::utils::glob::{page_matches, GlobKind, GlobMatches, GlobPage, GlobPattern, GlobSort};
+use crate::utils::path::{home_relative, shell_escape, shell_escape_remote_path};
 
 use super::SshConnection;
 
@@ -15,23 +22,187 @@ const FILE_IO_TIMEOUT_MS: u64 = 60_000;
 /// Default timeout for glob/find operations (30 seconds).
 const GLOB_TIMEOUT_MS: u64 = 30_000;
 
-/// Maximum number of files returned by a glob operation.
-const GLOB_MAX_RESULTS: usize = 1000;
+/// Suffix of the temporary file a write goes to before it is renamed over
+/// the target.
+pub const TMP_SUFFIX: &str = ".ssh-hub.tmp";
+
+/// Shell script that writes stdin to `path` through a temporary file
+/// `<path>.ssh-hub.tmp.XXXXXX`, unique per write so concurrent writers
+/// don't share one, and renames it into place, so a dropped connection
+/// leaves the old file intact. The target's mode carries over, and a new
+/// file gets the mode the umask allows. Symlinks, files owned by another
+/// user, and directories where the temporary file can't be created are
+/// written in place instead, as `cat >` would.
+#[must_use]
+pub fn atomic_write_command(path: &str) -> String {
+    format!(
+        "f={}; \
+         if [ -d \"$f\" ]; then echo \"$f: Is a directory\" >&2; exit 1; fi; \
+         if [ -L \"$f\" ] || {{ [ -e \"$f\" ] && [ ! -O \"$f\" ]; }} || \
+         ! t=$(mktemp \"$f{TMP_SUFFIX}.XXXXXX\" 2>/dev/null); \
+         then exec cat > \"$f\"; fi; \
+         if cat > \"$t\"; then \
+         if [ -e \"$f\" ]; then chmod --reference=\"$f\" \"$t\" 2>/dev/null || \
+         chmod \"$(stat -c %a \"$f\" 2>/dev/null || stat -f %Lp \"$f\")\" \"$t\"; \
+         else chmod \"$(printf %o $((0666 & ~0$(umask))))\" \"$t\"; fi; \
+         mv -f \"$t\" \"$f\" && exit 0; fi; rm -f \"$t\"; exit 1",
+        shell_escape_remote_path(path),
+    )
+}
+
+/// Shell script that extracts a gzip'd tar stream from stdin into `dir`.
+/// The archive is unpacked into a staging directory inside `dir` first and
+/// each file renamed into place, so an interrupted transfer changes nothing.
+/// With `preserve`, files keep the archived mode instead of having the
+/// remote umask applied, and the archived mtime; without it they get the
+/// archived mode less the umask and the time of extraction.
+#[must_use]
+pub fn atomic_extract_command(dir: &str, preserve: bool) -> String {
+    let flags = if preserve { "xzpf" } else { "xzmf" };
+    format!(
+        "mkdir -p {0} && cd {0} && s=$(mktemp -d '{TMP_SUFFIX}.XXXXXX') || exit 1; \
+         if tar {flags} - -C \"$s\"; then \
+         (cd \"$s\" && find . ! -type d -exec sh -c \
+         'for f; do mkdir -p \"../${{f%/*}}\" && mv -f \"$f\" \"../$f\" || exit 1; done' sh {{}} +); \
+         rc=$?; else rc=$?; fi; rm -rf \"$s\"; exit $rc",
+        shell_escape_remote_path(dir),
+    )
+}
+
+/// A marker for [`framed_read_command`], random per read so no file holds
+/// it by chance.
+#[must_use]
+pub fn read_sentinel() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(nanos);
+    format!("ssh-hub-{:016x}", hasher.finish())
+}
+
+/// Exit status of [`read_failure_check`] when the file doesn't exist.
+pub const READ_EXIT_MISSING: i32 = 66;
+
+/// Exit status of [`read_failure_check`] when the file exists but can't be
+/// read.
+pub const READ_EXIT_DENIED: i32 = 77;
+
+/// Shell group to run after a command reading `path` failed: it exits with
+/// [`READ_EXIT_MISSING`] or [`READ_EXIT_DENIED`] when the file is missing
+/// or unreadable, and 1 otherwise. The command's own stderr is kept.
+#[must_use]
+pub fn read_failure_check(path: &str) -> String {
+    let path = shell_escape_remote_path(path);
+    format!(
+        "{{ [ -e {path} ] || exit {READ_EXIT_MISSING}; [ -r {path} ] || exit {READ_EXIT_DENIED}; exit 1; }}"
+    )
+}
+
+/// The error for a read that exited with `exit_code`, an [`io::Error`] of
+/// kind `NotFound` or `PermissionDenied` where [`read_failure_check`] told.
+#[must_use]
+pub fn read_failure(exit_code: i32, stderr: &str) -> anyhow::Error {
+    let kind = match exit_code {
+        READ_EXIT_MISSING => io::ErrorKind::NotFound,
+        READ_EXIT_DENIED => io::ErrorKind::PermissionDenied,
+        _ => io::ErrorKind::Other,
+    };
+    let message = format!("Failed to read file: {}", stderr.trim_end());
+    io::Error::new(kind, message).into()
+}
+
+/// Shell script that prints the file at `path` between `<sentinel>:begin`
+/// and `<sentinel>:end` lines. Whatever else the login shell prints — a
+/// MOTD, a banner echoed from `.bashrc`, a goodbye from `.bash_logout` —
+/// lands outside the frame and is dropped by [`unframe_output`]. A failed
+/// `cat` exits as [`read_failure_check`] describes.
+#[must_use]
+pub fn framed_read_command(path: &str, sentinel: &str) -> String {
+    format!(
+        "printf '%s:begin\\n' {sentinel}; cat {} || {}; printf '\\n%s:end\\n' {sentinel}",
+        shell_escape_remote_path(path),
+        read_failure_check(path),
+    )
+}
+
+/// The content [`framed_read_command`] printed between its markers. When
+/// the begin marker arrives as `\r\n`, the channel has a terminal
+/// translating newlines (`stty onlcr`), and every `\r\n` in the content is
+/// turned back into `\n`.
+///
+/// # Errors
+/// Returns an error if either marker is missing.
+pub fn unframe_output(stdout: &[u8], sentinel: &str) -> Result<Vec<u8>> {
+    let begin = format!("{sentinel}:begin");
+    let start = find(stdout, begin.as_bytes()).ok_or_else(|| {
+        anyhow!("Failed to read file: the remote shell's output is missing the start marker")
+    })?;
+    let after = &stdout[start + begin.len()..];
+    let (crlf, content) = if let Some(rest) = after.strip_prefix(b"\r\n") {
+        (true, rest)
+    } else if let Some(rest) = after.strip_prefix(b"\n") {
+        (false, rest)
+    } else {
+        return Err(anyhow!("Failed to read file: malformed start marker"));
+    };
+    let end = format!("{}{sentinel}:end", if crlf { "\r\n" } else { "\n" });
+    let end = rfind(content, end.as_bytes()).ok_or_else(|| {
+        anyhow!("Failed to read file: the remote shell's output is missing the end marker")
+    })?;
+    let content = &content[..end];
+    if !crlf {
+        return Ok(content.to_vec());
+    }
+    let mut restored = Vec::with_capacity(content.len());
+    let mut bytes = content.iter().peekable();
+    while let Some(&b) = bytes.next() {
+        if b == b'\r' && bytes.peek() == Some(&&b'\n') {
+            continue;
+        }
+        restored.push(b);
+    }
+    Ok(restored)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|w| w == needle)
+}
+
+/// Entries listed by a glob before giving up, bounding time and memory on
+/// huge trees. Matches are filtered from this listing.
+const GLOB_SCAN_LIMIT: usize = 200_000;
 
 impl SshConnection {
-    /// Read a file as raw bytes from the remote machine.
+    /// Read a file as raw bytes from the remote machine. The content is
+    /// framed with sentinels (see [`framed_read_command`]) so output from
+    /// the remote user's shell startup files can't leak into it.
     ///
     /// # Errors
-    /// Returns an error if the remote `cat` command fails or the file does not exist.
+    /// Returns an error if the remote `cat` command fails, the file does
+    /// not exist, or the output isn't framed as expected.
     pub async fn read_file_raw(&self, path: &str) -> Result<Vec<u8>> {
-        let command = format!("cat {}", shell_escape_remote_path(path));
+        if !self.shell().is_posix() {
+            let sftp = self.open_sftp().await?;
+            return sftp
+                .read(home_relative(path))
+                .await
+                .map_err(|e| anyhow!("Failed to read file: {e}"));
+        }
+        let sentinel = read_sentinel();
+        let command = framed_read_command(path, &sentinel);
         let result = self
             .exec_raw(&command, None, Some(FILE_IO_TIMEOUT_MS))
             .await?;
         if result.exit_code != 0 {
-            return Err(anyhow!("Failed to read file: {}", result.stderr));
+            return Err(read_failure(result.exit_code, &result.stderr));
         }
-        Ok(result.stdout)
+        unframe_output(&result.stdout, &sentinel)
     }
 
     /// Read a file as UTF-8 text from the remote machine.
@@ -45,15 +216,22 @@ impl SshConnection {
         Ok(String::from_utf8_lossy(&bytes).into_owned())
     }
 
-    /// Write raw bytes to a file on the remote machine.
+    /// Write raw bytes to a file on the remote machine, replacing it
+    /// atomically (see [`atomic_write_command`]).
     ///
     /// Uses stdin piping instead of heredoc to avoid delimiter collisions.
     ///
     /// # Errors
     /// Returns an error if the remote write command fails.
     pub async fn write_file_raw(&self, path: &str, content: &[u8]) -> Result<()> {
-        let escaped_path = shell_escape_remote_path(path);
-        let command = format!("cat > {escaped_path}");
+        if !self.shell().is_posix() {
+            let sftp = self.open_sftp().await?;
+            return sftp
+                .write(home_relative(path), content)
+                .await
+                .map_err(|e| anyhow!("Failed to write file: {e}"));
+        }
+        let command = atomic_write_command(path);
         let result = self
             .exec_raw(&command, Some(content), Some(FILE_IO_TIMEOUT_MS))
             .await?;
@@ -71,19 +249,80 @@ impl SshConnection {
         self.write_file_raw(path, content.as_bytes()).await
     }
 
-    /// List files matching a glob pattern.
+    /// List files and/or directories under `base_path` matching a glob
+    /// pattern (see [`crate::utils::glob`]), one `page` of them in name or
+    /// mtime order.
+    ///
+    /// The remote only lists entries (starting from the pattern's literal
+    /// leading directory); matching happens locally so `**`, braces and
+    /// classes behave the same on every platform.
     ///
     /// # Errors
-    /// Returns an error if the remote `find` command fails.
-    pub async fn glob(&self, pattern: &str, base_path: Option<&str>) -> Result<Vec<String>> {
+    /// Returns an error if the pattern is invalid or the listing fails.
+    pub async fn glob(
+        &self,
+        pattern: &str,
+        base_path: Option<&str>,
+        kind: GlobKind,
+        page: GlobPage,
+    ) -> Result<GlobMatches> {
+        let glob = GlobPattern::new(pattern).map_err(|e| anyhow!("Invalid glob pattern: {e}"))?;
         let path = base_path.unwrap_or(&self.params().remote_path);
+        let with_mtime = page.sort == GlobSort::Mtime;
+        let entries = if self.shell().is_posix() {
+            self.glob_list(path, glob.base_dir(), kind, with_mtime)
+                .await?
+        } else {
+            self.sftp_walk_entries(path, GLOB_SCAN_LIMIT, kind.dirs())
+                .await?
+        };
+        let incomplete = entries.len() >= GLOB_SCAN_LIMIT;
+        let mut matches = page_matches(
+            &glob,
+            entries.iter().map(|(path, mtime)| (path.as_str(), *mtime)),
+            kind,
+            page,
+        );
+        matches.incomplete = incomplete;
+        Ok(matches)
+    }
+
+    /// List entries below `start` (relative to `path`) with `find`, one per
+    /// line, directories with a trailing `/`. With `with_mtime`, each entry
+    /// carries its mtime, read with whichever `find -printf` or `stat` the
+    /// remote has.
+    async fn glob_list(
+        &self,
+        path: &str,
+        start: &str,
+        kind: GlobKind,
+        with_mtime: bool,
+    ) -> Result<Vec<(String, Option<f64>)>> {
+        let start = if start.is_empty() {
+            ".".to_string()
+        } else {
+            shell_escape(start)
+        };
+        let action = if with_mtime {
+            format!(" {}", self.capabilities().await.stat_action(true))
+        } else {
+            String::new()
+        };
+        let mut finds = Vec::new();
+        if kind.dirs() {
+            finds.push(format!(
+                "find {start} -mindepth 1 -type d{action} 2>/dev/null | sed 's|$|/|'"
+            ));
+        }
+        if kind.files() {
+            finds.push(format!("find {start} -type f{action} 2>/dev/null"));
+        }
         let result = self
             .exec(
                 &format!(
-                    "cd {} && find . -path {} -type f 2>/dev/null | head -{}",
+                    "cd {} && {{ {}; }} | head -n {GLOB_SCAN_LIMIT}",
                     shell_escape_remote_path(path),
-                    shell_escape(pattern),
-                    GLOB_MAX_RESULTS
+                    finds.join("; "),
                 ),
                 Some(GLOB_TIMEOUT_MS),
             )
@@ -95,11 +334,18 @@ impl SshConnection {
             return Err(anyhow!("Glob failed: {}", result.stderr));
         }
 
-        Ok(result
-            .stdout
-            .lines()
-            .filter(|l| !l.is_empty())
-            .map(|l| l.trim_start_matches("./").to_string())
+        let lines = result.stdout.lines().filter(|l| !l.is_empty());
+        if !with_mtime {
+            return Ok(lines.map(|l| (l.to_string(), None)).collect());
+        }
+        // `<size> <mtime> <path>`
+        Ok(lines
+            .filter_map(|l| {
+                let mut fields = l.splitn(3, ' ');
+                let _size = fields.next()?;
+                let mtime = fields.next()?.parse::<f64>().ok();
+                Some((fields.next()?.to_string(), mtime))
+            })
             .collect())
     }
 }
@@ -0,0 +1,192 @@
+//! Deciding whether to trust a host key seen for the first time.
+//!
+//! Keys already in `~/.ssh/known_hosts` are verified and a changed key is
+//! always refused. A new key is handled per the server's `strict_host_key`
+//! setting: with `ask`, the MCP server shows its fingerprint through MCP
+//! elicitation and records it only once the user confirms.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+use anyhow::{anyhow, Result};
+use russh::keys::{known_hosts, HashAlg, PublicKey};
+
+use crate::server_registry::StrictHostKey;
+
+/// The connection was refused over the server's host key: it changed, it
+/// is new under `strict_host_key = "yes"`, or it wasn't confirmed.
+#[derive(Debug)]
+pub struct HostKeyRejected(pub String);
+
+impl fmt::Display for HostKeyRejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for HostKeyRejected {}
+
+/// What to do with a host key that isn't in `known_hosts` yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewKeyAction {
+    /// Record the key and connect (trust on first use).
+    Learn,
+    /// Hold the connection until someone confirms the fingerprint.
+    Ask,
+    /// Refuse to connect.
+    Refuse,
+}
+
+/// The action for a new key under `policy`. `can_ask` is whether anyone is
+/// there to confirm it; `ask` falls back to recording the key otherwise, so
+/// clients without elicitation keep working as before.
+#[must_use]
+pub fn new_key_action(policy: StrictHostKey, can_ask: bool) -> NewKeyAction {
+    match policy {
+        StrictHostKey::Ask if can_ask => NewKeyAction::Ask,
+        StrictHostKey::Ask | StrictHostKey::AcceptNew => NewKeyAction::Learn,
+        StrictHostKey::Yes => NewKeyAction::Refuse,
+    }
+}
+
+/// A host key offered by a server that `known_hosts` doesn't know.
+#[derive(Debug, Clone)]
+pub struct NewHostKey {
+    pub host: String,
+    pub port: u16,
+    /// Configured name of the server, when connecting from the config.
+    pub server_name: Option<String>,
+    key: PublicKey,
+}
+
+impl NewHostKey {
+    #[must_use]
+    pub fn new(host: String, port: u16, server_name: Option<String>, key: PublicKey) -> Self {
+        Self {
+            host,
+            port,
+            server_name,
+            key,
+        }
+    }
+
+    /// Key type, e.g. `ssh-ed25519`.
+    #[must_use]
+    pub fn algorithm(&self) -> String {
+        self.key.algorithm().as_str().to_string()
+    }
+
+    /// SHA-256 fingerprint in OpenSSH's format, e.g. `SHA256:uN8f...`.
+    #[must_use]
+    pub fn fingerprint(&self) -> String {
+        self.key.fingerprint(HashAlg::Sha256).to_string()
+    }
+
+    /// The question put to the user, worded like OpenSSH's.
+    #[must_use]
+    pub fn prompt(&self) -> String {
+        let server = self
+            .server_name
+            .as_ref()
+            .map_or_else(String::new, |name| format!("'{name}' "));
+        format!(
+            "The authenticity of host {server}({}:{}) can't be established.\n\
+             {} key fingerprint is {}.\n\
+             Compare it with the server's own (ssh-keygen -lf /etc/ssh/ssh_host_*_key.pub). \
+             Trust this key and add it to ~/.ssh/known_hosts?",
+            self.host,
+            self.port,
+            self.algorithm(),
+            self.fingerprint(),
+        )
+    }
+
+    /// Add the key to `~/.ssh/known_hosts`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `known_hosts` can't be written.
+    pub fn learn(&self) -> Result<()> {
+        known_hosts::learn_known_hosts(&self.host, self.port, &self.key)
+            .map_err(|e| anyhow!("Failed to save host key to known_hosts: {e}"))
+    }
+
+    /// Why the connection was refused under `strict_host_key = "yes"`.
+    #[must_use]
+    pub fn refusal(&self) -> String {
+        format!(
+            "Host key for {}:{} is not in ~/.ssh/known_hosts and strict_host_key is \"yes\". \
+             Its {} fingerprint is {}. After checking it against the server, add it with \
+             `ssh-keyscan -p {} {} >> ~/.ssh/known_hosts`, or set strict_host_key = \"ask\".",
+            self.host,
+            self.port,
+            self.algorithm(),
+            self.fingerprint(),
+            self.port,
+            self.host,
+        )
+    }
+}
+
+/// Someone who can be asked whether to trust a new host key.
+pub trait HostKeyConfirm: Send + Sync {
+    /// Ask whether to trust `key`. `Ok(false)` when the user declines.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no answer could be obtained.
+    fn confirm<'a>(
+        &'a self,
+        key: &'a NewHostKey,
+    ) -> Pin<Box<dyn Future<Output = Result<bool>> + Send + 'a>>;
+}
+
+/// `known_hosts` contents without the lines ssh-hub writes when it learns a
+/// key for `host`:`port`, and how many were dropped. Those lines name one
+/// unhashed host: `host`, or `[host]:port` off port 22. Hashed lines and
+/// lines listing several hosts stay. An identical line added by hand, e.g.
+/// from `ssh-keyscan`, can't be told apart and is dropped too.
+#[must_use]
+pub fn forget_known_host_lines(contents: &str, host: &str, port: u16) -> (String, usize) {
+    let pattern = if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{host}]:{port}")
+    };
+    let mut kept = String::with_capacity(contents.len());
+    let mut dropped = 0;
+    for line in contents.split_inclusive('\n') {
+        if line.split_whitespace().next() == Some(pattern.as_str()) {
+            dropped += 1;
+        } else {
+            kept.push_str(line);
+        }
+    }
+    (kept, dropped)
+}
+
+/// Drop the lines ssh-hub added for `host`:`port` from the `known_hosts`
+/// file russh reads. Returns how many were dropped.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but can't be read or rewritten.
+pub fn forget_known_host(host: &str, port: u16) -> Result<usize> {
+    let Some(home) = dirs::home_dir() else {
+        return Ok(0);
+    };
+    let dir = if cfg!(windows) { "ssh" } else { ".ssh" };
+    let path = home.join(dir).join("known_hosts");
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(anyhow!("Failed to read {}: {e}", path.display())),
+    };
+    let (kept, dropped) = forget_known_host_lines(&contents, host, port);
+    if dropped > 0 {
+        std::fs::write(&path, kept)
+            .map_err(|e| anyhow!("Failed to write {}: {e}", path.display()))?;
+    }
+    Ok(dropped)
+}
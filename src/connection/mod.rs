@@ -1,7 +1,38 @@
 mod auth;
+mod compress;
+mod executor;
+mod fake;
 mod file_ops;
+mod host_key;
 mod pool;
+pub mod resolve;
 mod session;
+mod shell;
+pub mod socks;
+mod stats;
+mod transfer;
 
+pub use auth::{
+    agent_location, decrypt_key, is_encrypted_key_error, is_security_key, security_key_public,
+    set_passphrase_prompt, AgentLocation, AuthFailed, EncryptedKey, PassphrasePrompt,
+    SECURITY_KEY_HINT, WINDOWS_AGENT_PIPE,
+};
+pub use compress::{compress_command, decode_compressed, decode_compressed_partial};
+pub use executor::RemoteExecutor;
+pub use fake::FakeExecutor;
+pub use file_ops::{
+    atomic_extract_command, atomic_write_command, framed_read_command, read_failure,
+    read_failure_check, read_sentinel, unframe_output, READ_EXIT_DENIED, READ_EXIT_MISSING,
+    TMP_SUFFIX,
+};
+pub use host_key::{
+    forget_known_host, forget_known_host_lines, new_key_action, HostKeyConfirm, HostKeyRejected,
+    NewHostKey, NewKeyAction,
+};
 pub use pool::ConnectionPool;
-pub use session::{ConnectionParams, SshConnection};
+pub use session::{
+    kill_group_command, split_pid_marker, ConnectionLost, ConnectionParams, ExecOutcome,
+    ExecRawResult, ExecResult, SshConnection, StreamedResult, TimedOut, DEFAULT_MAX_CHANNELS,
+};
+pub use stats::{ConnectError, Health, PoolEntry, PoolStats};
+pub use transfer::{FileStat, RemoteCapabilities, Sftp, Sha256Tool};
@@ -3,6 +3,7 @@ use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
 
 use super::session::ConnectionParams;
+use super::stats::{ConnectError, PoolEntry};
 use super::SshConnection;
 
 /// Thread-safe pool of named SSH connections.
@@ -12,10 +13,16 @@ use super::SshConnection;
 /// duplicate connections to the same server. The lock is held only during the
 /// "check pool → connect → insert" window — once a connection is pooled, all
 /// callers proceed without blocking.
+///
+/// Servers with the same host, user, port, and authentication share one SSH
+/// connection, each under its own name and with its own base path (see
+/// [`ConnectionPool::share`]).
 pub struct ConnectionPool {
     connections: RwLock<HashMap<String, Arc<SshConnection>>>,
     /// Per-server locks that serialize connection establishment.
     connect_locks: RwLock<HashMap<String, Arc<Mutex<()>>>>,
+    /// Last failed connect per server, cleared when a connection is pooled.
+    errors: RwLock<HashMap<String, ConnectError>>,
 }
 
 impl Default for ConnectionPool {
@@ -30,6 +37,7 @@ impl ConnectionPool {
         Self {
             connections: RwLock::new(HashMap::new()),
             connect_locks: RwLock::new(HashMap::new()),
+            errors: RwLock::new(HashMap::new()),
         }
     }
 
@@ -62,17 +70,62 @@ impl ConnectionPool {
     /// Insert a new connection into the pool, returning the `Arc` handle to it.
     pub async fn insert(&self, name: String, conn: SshConnection) -> Arc<SshConnection> {
         let arc = Arc::new(conn);
+        self.errors.write().await.remove(&name);
         let mut guard = self.connections.write().await;
         guard.insert(name, Arc::clone(&arc));
         arc
     }
 
+    /// Pool `name` on the open connection of another server with the
+    /// [same transport](ConnectionParams::same_transport), if there is one,
+    /// instead of connecting again.
+    pub async fn share(&self, name: &str, params: &ConnectionParams) -> Option<Arc<SshConnection>> {
+        let existing = {
+            let guard = self.connections.read().await;
+            guard
+                .values()
+                .filter(|conn| conn.params().same_transport(params))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        for conn in existing {
+            if conn.is_closed().await {
+                continue;
+            }
+            tracing::debug!(
+                "Sharing the connection of '{}' with '{name}'",
+                conn.params().server_name.as_deref().unwrap_or_default(),
+            );
+            return Some(
+                self.insert(name.to_string(), conn.share(params.clone()))
+                    .await,
+            );
+        }
+        None
+    }
+
     /// Remove and return a connection by name.
     pub async fn remove(&self, name: &str) -> Option<Arc<SshConnection>> {
         let mut guard = self.connections.write().await;
         guard.remove(name)
     }
 
+    /// Remove `name` along with every server sharing its SSH connection, so
+    /// none of them is handed a session that's about to be closed. Returns
+    /// the connection `name` had.
+    pub async fn remove_with_sharers(&self, name: &str) -> Option<Arc<SshConnection>> {
+        let mut guard = self.connections.write().await;
+        let conn = guard.remove(name)?;
+        guard.retain(|other, shared| {
+            let keep = !conn.shares_transport(shared);
+            if !keep {
+                tracing::debug!("Dropping '{other}', which shares the connection of '{name}'");
+            }
+            keep
+        });
+        Some(conn)
+    }
+
     /// List all connected server names.
     pub async fn list(&self) -> Vec<String> {
         let guard = self.connections.read().await;
@@ -88,6 +141,30 @@ impl ConnectionPool {
             .collect()
     }
 
+    /// Activity and health of every pooled connection, sorted by server name.
+    pub async fn stats(&self) -> Vec<PoolEntry> {
+        let guard = self.connections.read().await;
+        let mut entries: Vec<PoolEntry> = guard
+            .iter()
+            .map(|(name, conn)| conn.snapshot(name))
+            .collect();
+        entries.sort_by(|a, b| a.server.cmp(&b.server));
+        entries
+    }
+
+    /// Remember why connecting to `name` failed, for `list_servers`.
+    pub async fn record_error(&self, name: &str, message: impl Into<String>) {
+        let mut guard = self.errors.write().await;
+        guard.insert(name.to_string(), ConnectError::new(message));
+    }
+
+    /// Why the last attempt to connect to `name` failed, unless a
+    /// connection has been pooled since.
+    pub async fn last_error(&self, name: &str) -> Option<ConnectError> {
+        let guard = self.errors.read().await;
+        guard.get(name).cloned()
+    }
+
     /// Check if a server name has an active connection.
     pub async fn contains(&self, name: &str) -> bool {
         let guard = self.connections.read().await;
@@ -0,0 +1,379 @@
+//! Address resolution for SSH connects: per-server IP pinning and a
+//! persistent cache of successful DNS lookups.
+//!
+//! A live lookup is always tried first. The cache is only consulted when it
+//! fails, so split-DNS and VPN setups where `host` intermittently stops
+//! resolving keep working with the last known address.
+//!
+//! When a name has several A/AAAA records, [`connect_any`] races them with
+//! staggered starts, so a dual-stack server with broken IPv6 connects over
+//! IPv4 after a short delay instead of hanging until the connect timeout.
+//! [`filter_addrs`] and [`LocalBind`] apply a server's `address_family`,
+//! `bind_address`, and `bind_interface` first.
+
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::net::{TcpSocket, TcpStream};
+
+use crate::server_registry::AddressFamily;
+
+/// A server could not be reached at its address: the name didn't resolve,
+/// no address accepted a TCP connection, or the connect timed out. Carried
+/// in the error chain so callers can tell this apart from SSH and auth
+/// failures, which another address wouldn't fix.
+#[derive(Debug)]
+pub struct Unreachable(pub String);
+
+impl std::fmt::Display for Unreachable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Unreachable {}
+
+/// Whether `error` says the server's address didn't answer.
+#[must_use]
+pub fn is_unreachable(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .any(<dyn std::error::Error>::is::<Unreachable>)
+}
+
+/// How long a cached resolution may stand in for a failed lookup (24 hours).
+pub const DNS_CACHE_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// A successful resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedAddr {
+    pub ip: IpAddr,
+    /// Unix timestamp (seconds) of the lookup.
+    pub resolved_at: u64,
+}
+
+/// Hostname → last successful resolution, persisted as JSON in the user cache dir.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DnsCache {
+    #[serde(default)]
+    pub hosts: BTreeMap<String, CachedAddr>,
+}
+
+impl DnsCache {
+    /// Cached address for `host` if it is younger than [`DNS_CACHE_TTL_SECS`].
+    #[must_use]
+    pub fn lookup(&self, host: &str, now: u64) -> Option<IpAddr> {
+        self.hosts
+            .get(host)
+            .filter(|c| now.saturating_sub(c.resolved_at) <= DNS_CACHE_TTL_SECS)
+            .map(|c| c.ip)
+    }
+
+    /// Record a successful resolution. Returns `true` if the cache changed
+    /// enough to be worth saving (new address, or the entry is past half its TTL).
+    pub fn record(&mut self, host: &str, ip: IpAddr, now: u64) -> bool {
+        let stale = self.hosts.get(host).is_none_or(|c| {
+            c.ip != ip || now.saturating_sub(c.resolved_at) > DNS_CACHE_TTL_SECS / 2
+        });
+        if stale {
+            self.hosts.insert(
+                host.to_string(),
+                CachedAddr {
+                    ip,
+                    resolved_at: now,
+                },
+            );
+        }
+        stale
+    }
+
+    /// Drop the entry for `host`. Returns whether there was one.
+    pub fn forget(&mut self, host: &str) -> bool {
+        self.hosts.remove(host).is_some()
+    }
+
+    fn path() -> Option<PathBuf> {
+        Some(dirs::cache_dir()?.join("ssh-hub").join("dns-cache.json"))
+    }
+
+    fn load() -> Self {
+        Self::path()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        let Some(path) = Self::path() else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                tracing::debug!("Cannot create DNS cache dir: {e}");
+                return;
+            }
+        }
+        match serde_json::to_string_pretty(self) {
+            Ok(json) => {
+                if let Err(e) = std::fs::write(&path, json) {
+                    tracing::debug!("Cannot write DNS cache: {e}");
+                }
+            }
+            Err(e) => tracing::debug!("Cannot serialize DNS cache: {e}"),
+        }
+    }
+}
+
+/// Drop `host` from the DNS cache on disk. Returns whether it was cached.
+#[must_use]
+pub fn forget_cached_host(host: &str) -> bool {
+    let mut cache = DnsCache::load();
+    let forgotten = cache.forget(host);
+    if forgotten {
+        cache.save();
+    }
+    forgotten
+}
+
+/// Resolve the socket address to connect to: the first of
+/// [`resolve_addrs`].
+///
+/// # Errors
+///
+/// Returns an error if `pinned` is not an IP address, or if the lookup fails
+/// and no fresh cached address exists.
+pub async fn resolve_addr(host: &str, port: u16, pinned: Option<&str>) -> Result<SocketAddr> {
+    let addrs = resolve_addrs(host, port, pinned).await?;
+    Ok(addrs[0])
+}
+
+/// Resolve every socket address worth trying, in connection order
+/// (see [`interleave_families`]). Never empty.
+///
+/// Order: `pinned` IP → IP-literal `host` → live DNS lookup of all A/AAAA
+/// records (the first is cached on success) → cached resolution within the TTL.
+///
+/// # Errors
+///
+/// Returns an error if `pinned` is not an IP address, or if the lookup fails
+/// and no fresh cached address exists.
+pub async fn resolve_addrs(host: &str, port: u16, pinned: Option<&str>) -> Result<Vec<SocketAddr>> {
+    if let Some(pinned) = pinned {
+        let ip: IpAddr = pinned
+            .parse()
+            .map_err(|_| anyhow!("Invalid resolve address '{pinned}': expected an IP"))?;
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+
+    let now = unix_now();
+    match tokio::net::lookup_host((host, port)).await {
+        Ok(addrs) => {
+            let addrs = interleave_families(addrs.collect());
+            let first = addrs
+                .first()
+                .ok_or_else(|| anyhow!("DNS lookup for '{host}' returned no addresses"))?;
+            let mut cache = DnsCache::load();
+            if cache.record(host, first.ip(), now) {
+                cache.save();
+            }
+            Ok(addrs)
+        }
+        Err(e) => {
+            if let Some(ip) = DnsCache::load().lookup(host, now) {
+                tracing::warn!("DNS lookup for '{host}' failed ({e}); using cached address {ip}");
+                return Ok(vec![SocketAddr::new(ip, port)]);
+            }
+            Err(Unreachable(format!("Failed to resolve '{host}': {e}")).into())
+        }
+    }
+}
+
+/// Order addresses for connection attempts as RFC 8305 suggests: drop
+/// duplicates, keep the resolver's preferred family first, then alternate
+/// between IPv6 and IPv4 so a broken family costs one attempt delay rather
+/// than one per address.
+#[must_use]
+pub fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let mut unique: Vec<SocketAddr> = Vec::with_capacity(addrs.len());
+    for addr in addrs {
+        if !unique.contains(&addr) {
+            unique.push(addr);
+        }
+    }
+    let Some(first) = unique.first() else {
+        return unique;
+    };
+    let prefer_v6 = first.is_ipv6();
+    let (mut preferred, mut other): (Vec<_>, Vec<_>) =
+        unique.into_iter().partition(|a| a.is_ipv6() == prefer_v6);
+    let mut ordered = Vec::with_capacity(preferred.len() + other.len());
+    preferred.reverse();
+    other.reverse();
+    loop {
+        match (preferred.pop(), other.pop()) {
+            (None, None) => return ordered,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+/// Where connections start from on this machine, for multi-homed
+/// workstations and routes that only exist through one interface.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocalBind {
+    /// Local address to connect from.
+    pub address: Option<IpAddr>,
+    /// Network interface to connect through (Linux only).
+    pub interface: Option<String>,
+}
+
+/// Keep the addresses `family` allows, and that a `bind` address can reach
+/// (an IPv4 source can't connect to an IPv6 server, and the reverse).
+///
+/// # Errors
+///
+/// Returns an error naming the setting when no address is left.
+pub fn filter_addrs(
+    host: &str,
+    addrs: Vec<SocketAddr>,
+    family: AddressFamily,
+    bind: &LocalBind,
+) -> Result<Vec<SocketAddr>> {
+    let found = addrs.len();
+    let kept: Vec<SocketAddr> = addrs
+        .into_iter()
+        .filter(|a| family.allows(a.ip()))
+        .filter(|a| bind.address.is_none_or(|b| b.is_ipv4() == a.is_ipv4()))
+        .collect();
+    if kept.is_empty() && found > 0 {
+        let setting = match bind.address {
+            Some(address) if family.allows(address) => format!("bind_address = \"{address}\""),
+            _ => format!("address_family = \"{family}\""),
+        };
+        return Err(anyhow!(
+            "None of the {found} addresses of '{host}' can be used with {setting}"
+        ));
+    }
+    Ok(kept)
+}
+
+/// Open a TCP connection to the first of `addrs` that answers, starting a
+/// new attempt every `attempt_delay` (or as soon as the previous one fails)
+/// while earlier attempts keep running ("happy eyeballs").
+///
+/// # Errors
+///
+/// Returns the last error if every address failed.
+pub async fn connect_any(
+    addrs: &[SocketAddr],
+    attempt_delay: Duration,
+) -> Result<(TcpStream, SocketAddr)> {
+    connect_any_from(addrs, attempt_delay, &LocalBind::default()).await
+}
+
+/// Like [`connect_any`], with every connection started from `bind`.
+///
+/// # Errors
+///
+/// Returns the last error if every address failed.
+pub async fn connect_any_from(
+    addrs: &[SocketAddr],
+    attempt_delay: Duration,
+    bind: &LocalBind,
+) -> Result<(TcpStream, SocketAddr)> {
+    let mut next = 0;
+    let mut attempts = FuturesUnordered::new();
+    let mut last_error = None;
+    loop {
+        if attempts.is_empty() {
+            let Some(&addr) = addrs.get(next) else {
+                return Err(match last_error {
+                    Some((addr, e)) => {
+                        Unreachable(format!("Failed to connect to {addr}: {e}")).into()
+                    }
+                    None => anyhow!("No addresses to connect to"),
+                });
+            };
+            attempts.push(attempt(addr, bind));
+            next += 1;
+        }
+        tokio::select! {
+            Some((addr, result)) = attempts.next() => match result {
+                Ok(stream) => return Ok((stream, addr)),
+                Err(e) => {
+                    tracing::debug!("Connecting to {addr} failed: {e}");
+                    last_error = Some((addr, e));
+                }
+            },
+            () = tokio::time::sleep(attempt_delay), if next < addrs.len() => {
+                tracing::debug!("No answer yet, also trying {}", addrs[next]);
+                attempts.push(attempt(addrs[next], bind));
+                next += 1;
+            }
+        }
+    }
+}
+
+async fn attempt(addr: SocketAddr, bind: &LocalBind) -> (SocketAddr, std::io::Result<TcpStream>) {
+    (addr, connect_from(addr, bind).await)
+}
+
+async fn connect_from(addr: SocketAddr, bind: &LocalBind) -> std::io::Result<TcpStream> {
+    if bind.address.is_none() && bind.interface.is_none() {
+        return TcpStream::connect(addr).await;
+    }
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    if let Some(interface) = &bind.interface {
+        bind_device(&socket, interface)?;
+    }
+    if let Some(ip) = bind.address {
+        socket.bind(SocketAddr::new(ip, 0))?;
+    }
+    socket.connect(addr).await
+}
+
+#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+fn bind_device(socket: &TcpSocket, interface: &str) -> std::io::Result<()> {
+    socket.bind_device(Some(interface.as_bytes())).map_err(|e| {
+        std::io::Error::new(
+            e.kind(),
+            format!("cannot bind to interface {interface}: {e}"),
+        )
+    })
+}
+
+#[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+fn bind_device(_socket: &TcpSocket, _interface: &str) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "bind_interface is only supported on Linux; use bind_address instead",
+    ))
+}
+
+/// `"ipv4"` or `"ipv6"`, as recorded in server metadata.
+#[must_use]
+pub fn address_family(addr: &SocketAddr) -> &'static str {
+    if addr.is_ipv4() {
+        "ipv4"
+    } else {
+        "ipv6"
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
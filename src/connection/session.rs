@@ -1,18 +1,24 @@
+use std::future::Future;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Result};
 use russh::client::{self, Handle};
 use russh::keys::PublicKey;
-use russh::{ChannelMsg, Disconnect};
-use tokio::sync::Mutex;
+use russh::{ChannelMsg, ChannelStream, Disconnect, Sig};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{Mutex, OnceCell, OwnedSemaphorePermit, Semaphore};
 
-use crate::server_registry::AuthMethod;
-use crate::utils::path::shell_escape_remote_path;
+use crate::server_registry::{AddressFamily, AuthMethod, RemoteShell, StrictHostKey};
 
-use super::auth;
+use super::auth::{self, AuthFailed, EncryptedKey};
+use super::host_key::{new_key_action, HostKeyConfirm, HostKeyRejected, NewHostKey, NewKeyAction};
+use super::resolve;
+use super::stats::{ConnectionStats, PoolEntry, Usage};
+use super::transfer::RemoteCapabilities;
 
 /// Stdin is written to the SSH channel in chunks of this size.
 const STDIN_CHUNK_SIZE: usize = 32 * 1024;
@@ -23,6 +29,12 @@ const SSH_EXTENDED_DATA_STDERR: u32 = 1;
 /// Exit code used when the server closes the channel without sending an exit status.
 const EXIT_CODE_NO_STATUS: i32 = -1;
 
+/// Prefix of the stderr line naming the shell's PID in cancellable commands.
+const PID_MARKER: &str = "ssh-hub-pid:";
+
+/// Timeout for killing a cancelled command's process group.
+const KILL_TIMEOUT_MS: u64 = 10_000;
+
 /// Interval between SSH keepalive probes.
 const KEEPALIVE_INTERVAL_SECS: u64 = 30;
 
@@ -33,6 +45,10 @@ const KEEPALIVE_MAX_FAILURES: usize = 3;
 /// complete within this time, the connection is considered dead.
 const CHANNEL_OPEN_TIMEOUT_SECS: u64 = 10;
 
+/// Concurrent channels per connection when `max_channels` isn't configured.
+/// Stays below OpenSSH's default `MaxSessions` of 10.
+pub const DEFAULT_MAX_CHANNELS: usize = 8;
+
 /// Time budget for draining buffered data from a timed-out channel to
 /// unblock the session loop before sending `SSH_MSG_CHANNEL_CLOSE`.
 const CHANNEL_DRAIN_TIMEOUT_SECS: u64 = 4;
@@ -42,12 +58,44 @@ const CHANNEL_DRAIN_TIMEOUT_SECS: u64 = 4;
 /// TCP teardown when the last `Arc<SshConnection>` is dropped.
 const DISCONNECT_TIMEOUT_SECS: u64 = 2;
 
+/// How long a connection attempt to one address may go unanswered before
+/// the next resolved address is tried alongside it (RFC 8305's default).
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
 /// Timeout for the initial TCP + SSH handshake + authentication.
 /// Without this, connecting to an unreachable host falls back to the OS
 /// TCP timeout (~2 minutes), blocking the per-server connect lock and
 /// stalling all concurrent tool calls to that server.
 const CONNECT_TIMEOUT_SECS: u64 = 15;
 
+/// Start of the [`ConnectionLost`] message when a channel operation fails.
+const UNUSABLE: &str = "Connection unusable — retry to auto-reconnect";
+
+/// The SSH connection stopped working during a call, and was marked closed
+/// so the next call reconnects.
+#[derive(Debug)]
+pub struct ConnectionLost(pub String);
+
+impl std::fmt::Display for ConnectionLost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ConnectionLost {}
+
+/// A command or channel ran out of time.
+#[derive(Debug)]
+pub struct TimedOut(pub String);
+
+impl std::fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
 /// Parameters needed to establish an SSH connection.
 /// Decoupled from CLI args — can be built from config, MCP tool input, or CLI.
 #[derive(Debug, Clone)]
@@ -58,19 +106,70 @@ pub struct ConnectionParams {
     pub remote_path: String,
     pub identity: Option<PathBuf>,
     pub auth_method: AuthMethod,
+    /// ssh-agent to use instead of `SSH_AUTH_SOCK`: a socket path, a Windows
+    /// named pipe, or `pageant`.
+    pub agent_socket: Option<String>,
     /// Server alias — used for keychain lookups.
     pub server_name: Option<String>,
+    /// Pinned IP address that bypasses DNS for `host`. Host key checks still
+    /// use `host`.
+    pub resolve: Option<String>,
+    /// Remote shell dialect used to wrap commands.
+    pub shell: RemoteShell,
+    /// Maximum concurrently open channels; further opens queue.
+    /// `None` uses [`DEFAULT_MAX_CHANNELS`].
+    pub max_channels: Option<usize>,
+    /// How a host key not yet in `known_hosts` is treated.
+    pub strict_host_key: StrictHostKey,
+    /// IP versions to connect over.
+    pub address_family: AddressFamily,
+    /// Local address and interface to connect from.
+    pub bind: resolve::LocalBind,
+}
+
+impl ConnectionParams {
+    /// Whether a server with `other` params can share this server's SSH
+    /// connection (see [`SshConnection::share`]): everything that decides how
+    /// the session is opened and authenticated must match, leaving only the
+    /// base path, shell, and name to differ.
+    #[must_use]
+    pub fn same_transport(&self, other: &Self) -> bool {
+        self.host == other.host
+            && self.user == other.user
+            && self.port == other.port
+            && self.identity == other.identity
+            && self.auth_method == other.auth_method
+            && self.agent_socket == other.agent_socket
+            && self.resolve == other.resolve
+            && self.max_channels == other.max_channels
+            && self.strict_host_key == other.strict_host_key
+            && self.address_family == other.address_family
+            && self.bind == other.bind
+    }
 }
 
 /// SSH client handler for russh — carries host info for key verification.
 pub(super) struct SshHandler {
     host: String,
     port: u16,
+    new_key: NewKeyAction,
+    /// A new key held back for confirmation under [`NewKeyAction::Ask`].
+    unconfirmed: Arc<std::sync::Mutex<Option<PublicKey>>>,
 }
 
 impl SshHandler {
-    pub fn new(host: String, port: u16) -> Self {
-        Self { host, port }
+    pub fn new(
+        host: String,
+        port: u16,
+        new_key: NewKeyAction,
+        unconfirmed: Arc<std::sync::Mutex<Option<PublicKey>>>,
+    ) -> Self {
+        Self {
+            host,
+            port,
+            new_key,
+            unconfirmed,
+        }
     }
 }
 
@@ -88,31 +187,61 @@ impl client::Handler for SshHandler {
                 tracing::debug!("Host key verified for {}:{}", self.host, self.port);
                 Ok(true)
             }
-            Ok(false) => {
-                // TOFU: first time seeing this host — learn the key
-                tracing::debug!(
-                    "New host key for {}:{}, adding to known_hosts",
-                    self.host,
-                    self.port
-                );
-                if let Err(e) =
-                    known_hosts::learn_known_hosts(&self.host, self.port, server_public_key)
-                {
-                    tracing::warn!("Failed to save host key to known_hosts: {}", e);
+            Ok(false) => match self.new_key {
+                NewKeyAction::Learn => {
+                    // TOFU: first time seeing this host — learn the key
+                    tracing::debug!(
+                        "New host key for {}:{}, adding to known_hosts",
+                        self.host,
+                        self.port
+                    );
+                    if let Err(e) =
+                        known_hosts::learn_known_hosts(&self.host, self.port, server_public_key)
+                    {
+                        tracing::warn!("Failed to save host key to known_hosts: {}", e);
+                    }
+                    Ok(true)
                 }
-                Ok(true)
-            }
-            Err(russh::keys::Error::KeyChanged { line }) => Err(anyhow!(
+                NewKeyAction::Ask => {
+                    // Reject for now; the connection is retried once the
+                    // user confirms, outside the handshake timeout
+                    tracing::debug!(
+                        "New host key for {}:{}, asking for confirmation",
+                        self.host,
+                        self.port
+                    );
+                    *self
+                        .unconfirmed
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner) =
+                        Some(server_public_key.clone());
+                    Ok(false)
+                }
+                NewKeyAction::Refuse => Err(HostKeyRejected(
+                    NewHostKey::new(
+                        self.host.clone(),
+                        self.port,
+                        None,
+                        server_public_key.clone(),
+                    )
+                    .refusal(),
+                )
+                .into()),
+            },
+            Err(russh::keys::Error::KeyChanged { line }) => Err(HostKeyRejected(format!(
                 "HOST KEY VERIFICATION FAILED for {}:{}. \
                      The server's key has changed since it was last recorded \
                      (known_hosts line {}). This could indicate a man-in-the-middle attack. \
                      If the server was legitimately reinstalled, remove line {} from \
                      ~/.ssh/known_hosts and reconnect.",
-                self.host,
-                self.port,
-                line,
-                line
-            )),
+                self.host, self.port, line, line
+            ))
+            .into()),
+            Err(e) if self.new_key == NewKeyAction::Refuse => Err(HostKeyRejected(format!(
+                "Could not verify host key for {}:{}: {e}",
+                self.host, self.port
+            ))
+            .into()),
             Err(e) => {
                 tracing::warn!(
                     "Could not verify host key for {}:{}: {}. Accepting.",
@@ -134,10 +263,26 @@ struct ChannelOutput {
 }
 
 /// Manages an SSH connection to a remote host.
+///
+/// Several servers can use one connection through [`SshConnection::share`]:
+/// the session, channel slots, and counters are shared, while the base path
+/// and shell are each server's own.
 pub struct SshConnection {
     session: Arc<Mutex<Handle<SshHandler>>>,
     params: ConnectionParams,
     force_closed: Arc<AtomicBool>,
+    /// Transfer tools available on the remote, probed on first use.
+    pub(super) capabilities: OnceCell<RemoteCapabilities>,
+    /// One permit per open channel, so bursts of tool calls queue instead of
+    /// exceeding the server's `MaxSessions`.
+    channel_slots: Arc<Semaphore>,
+    max_channels: usize,
+    /// Address the TCP connection went to, out of those `host` resolved to.
+    peer_addr: SocketAddr,
+    stats: Arc<ConnectionStats>,
+    /// Commands [`disconnect`](Self::disconnect) runs first, such as
+    /// stopping remote processes started for this connection.
+    cleanup: Arc<std::sync::Mutex<Vec<String>>>,
 }
 
 impl SshConnection {
@@ -148,6 +293,39 @@ impl SshConnection {
     /// Returns an error if the TCP connection, SSH handshake, or
     /// authentication fails.
     pub async fn connect(params: ConnectionParams) -> Result<Self> {
+        Self::connect_with(params, None, None).await
+    }
+
+    /// Establish a new SSH connection authenticated with a password instead
+    /// of keys. Used to install a key on a host that doesn't accept one yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TCP connection, SSH handshake, or password
+    /// authentication fails.
+    pub async fn connect_with_password(params: ConnectionParams, password: &str) -> Result<Self> {
+        Self::connect_with(params, Some(password), None).await
+    }
+
+    /// Establish a new SSH connection, asking `confirm` whether to trust a
+    /// host key seen for the first time when `strict_host_key` is `ask`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TCP connection, SSH handshake, or
+    /// authentication fails, or the new host key isn't confirmed.
+    pub async fn connect_confirming(
+        params: ConnectionParams,
+        confirm: &dyn HostKeyConfirm,
+    ) -> Result<Self> {
+        Self::connect_with(params, None, Some(confirm)).await
+    }
+
+    async fn connect_with(
+        params: ConnectionParams,
+        password: Option<&str>,
+        confirm: Option<&dyn HostKeyConfirm>,
+    ) -> Result<Self> {
         tracing::debug!(
             "Connecting to {}@{}:{} (path: {})",
             params.user,
@@ -156,35 +334,146 @@ impl SshConnection {
             params.remote_path,
         );
 
+        let new_key = new_key_action(params.strict_host_key, confirm.is_some());
+        let unconfirmed = Arc::new(std::sync::Mutex::new(None));
+        let (mut session, peer_addr) = match Self::handshake(&params, new_key, &unconfirmed).await {
+            Ok(connected) => connected,
+            Err(e) => {
+                let key = unconfirmed
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .take();
+                let (Some(key), Some(confirm)) = (key, confirm) else {
+                    return Err(e);
+                };
+                let key = NewHostKey::new(
+                    params.host.clone(),
+                    params.port,
+                    params.server_name.clone(),
+                    key,
+                );
+                if !confirm.confirm(&key).await? {
+                    return Err(HostKeyRejected(format!(
+                        "Host key {} for {}:{} was not confirmed, so the connection was not made",
+                        key.fingerprint(),
+                        params.host,
+                        params.port
+                    ))
+                    .into());
+                }
+                key.learn()?;
+                // The key is recorded now, so any other key is refused as changed
+                Self::handshake(&params, NewKeyAction::Refuse, &unconfirmed).await?
+            }
+        };
+
+        let authenticated = match password {
+            Some(password) => {
+                auth::authenticate_password(&mut session, &params.user, password).await
+            }
+            None => auth::authenticate(&mut session, &params).await,
+        };
+        authenticated.map_err(|e| {
+            if e.is::<EncryptedKey>() {
+                e
+            } else {
+                AuthFailed(format!("{e:#}")).into()
+            }
+        })?;
+
+        tracing::debug!("SSH connection established");
+
+        let max_channels = params.max_channels.unwrap_or(DEFAULT_MAX_CHANNELS).max(1);
+        Ok(Self {
+            session: Arc::new(Mutex::new(session)),
+            params,
+            force_closed: Arc::new(AtomicBool::new(false)),
+            capabilities: OnceCell::new(),
+            channel_slots: Arc::new(Semaphore::new(max_channels)),
+            max_channels,
+            peer_addr,
+            stats: Arc::new(ConnectionStats::new()),
+            cleanup: Arc::new(std::sync::Mutex::new(Vec::new())),
+        })
+    }
+
+    /// Use this connection for another server with the
+    /// [same transport](ConnectionParams::same_transport), running its
+    /// commands in `params.remote_path` instead. Channels of both count
+    /// against one `max_channels`.
+    #[must_use]
+    pub fn share(&self, params: ConnectionParams) -> Self {
+        Self {
+            session: Arc::clone(&self.session),
+            params,
+            force_closed: Arc::clone(&self.force_closed),
+            capabilities: OnceCell::new_with(self.capabilities.get().copied()),
+            channel_slots: Arc::clone(&self.channel_slots),
+            max_channels: self.max_channels,
+            peer_addr: self.peer_addr,
+            stats: Arc::clone(&self.stats),
+            cleanup: Arc::clone(&self.cleanup),
+        }
+    }
+
+    /// Run `command` on the remote when this connection is
+    /// [disconnected](Self::disconnect), e.g. to stop a process it started.
+    /// Registering the same command twice runs it once.
+    pub fn on_disconnect(&self, command: String) {
+        let mut cleanup = self
+            .cleanup
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if !cleanup.contains(&command) {
+            cleanup.push(command);
+        }
+    }
+
+    /// Whether `other` runs over the same SSH connection as this one.
+    #[must_use]
+    pub fn shares_transport(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.session, &other.session)
+    }
+
+    /// Connect over TCP and complete the SSH handshake, without
+    /// authenticating. A new host key held back under [`NewKeyAction::Ask`]
+    /// fails the handshake and is left in `unconfirmed`.
+    async fn handshake(
+        params: &ConnectionParams,
+        new_key: NewKeyAction,
+        unconfirmed: &Arc<std::sync::Mutex<Option<PublicKey>>>,
+    ) -> Result<(Handle<SshHandler>, SocketAddr)> {
         let config = Arc::new(client::Config {
             keepalive_interval: Some(Duration::from_secs(KEEPALIVE_INTERVAL_SECS)),
             keepalive_max: KEEPALIVE_MAX_FAILURES,
             ..client::Config::default()
         });
-        let handler = SshHandler::new(params.host.clone(), params.port);
+        let handler = SshHandler::new(
+            params.host.clone(),
+            params.port,
+            new_key,
+            Arc::clone(unconfirmed),
+        );
+        let addrs =
+            resolve::resolve_addrs(&params.host, params.port, params.resolve.as_deref()).await?;
+        let addrs =
+            resolve::filter_addrs(&params.host, addrs, params.address_family, &params.bind)?;
 
-        let mut session = tokio::time::timeout(
-            Duration::from_secs(CONNECT_TIMEOUT_SECS),
-            client::connect(config, (params.host.as_str(), params.port), handler),
-        )
+        tokio::time::timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS), async {
+            let (stream, addr) =
+                resolve::connect_any_from(&addrs, CONNECTION_ATTEMPT_DELAY, &params.bind).await?;
+            tracing::debug!("TCP connection to {addr} established");
+            let session = client::connect_stream(config, stream, handler).await?;
+            anyhow::Ok((session, addr))
+        })
         .await
         .map_err(|_| {
-            anyhow!(
+            resolve::Unreachable(format!(
                 "Connection timed out after {CONNECT_TIMEOUT_SECS}s \
                  (host may be unreachable)"
-            )
+            ))
         })?
-        .context("Failed to connect to SSH server")?;
-
-        auth::authenticate(&mut session, &params).await?;
-
-        tracing::debug!("SSH connection established");
-
-        Ok(Self {
-            session: Arc::new(Mutex::new(session)),
-            params,
-            force_closed: Arc::new(AtomicBool::new(false)),
-        })
+        .context("Failed to connect to SSH server")
     }
 
     /// Get the base remote path for this connection.
@@ -193,12 +482,24 @@ impl SshConnection {
         &self.params.remote_path
     }
 
+    /// Remote shell dialect for this connection.
+    #[must_use]
+    pub fn shell(&self) -> RemoteShell {
+        self.params.shell
+    }
+
     /// Get the connection parameters.
     #[must_use]
     pub fn params(&self) -> &ConnectionParams {
         &self.params
     }
 
+    /// Address this connection reached the server at.
+    #[must_use]
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
     /// Check whether the underlying SSH session has been closed.
     ///
     /// Returns `true` if the session was explicitly marked dead (e.g. channel
@@ -211,12 +512,132 @@ impl SshConnection {
         session.is_closed()
     }
 
+    /// Activity counters and health for `pool_stats`. Doesn't wait for the
+    /// session lock, so it answers even while the session is stuck.
+    pub(super) fn snapshot(&self, server: &str) -> PoolEntry {
+        let closed = self.force_closed.load(Ordering::Relaxed)
+            || self.session.try_lock().is_ok_and(|s| s.is_closed());
+        let usage = Usage {
+            channels_in_use: self.max_channels - self.channel_slots.available_permits(),
+            max_channels: self.max_channels,
+            closed,
+        };
+        self.stats.snapshot(server, &self.params, &usage)
+    }
+
     /// Mark this connection as dead. Subsequent `is_closed()` calls return
     /// `true` without acquiring the session mutex.
     pub fn mark_closed(&self) {
         self.force_closed.store(true, Ordering::Relaxed);
     }
 
+    /// Open a new session channel on this connection.
+    ///
+    /// Waits for a free channel slot first (see `max_channels`); the returned
+    /// permit must be held for as long as the channel is in use. Queueing time
+    /// doesn't count against the open timeout.
+    ///
+    /// Locks the session only for `channel_open_session`, with a timeout so a
+    /// dead connection (e.g. after OS suspend) is detected and marked closed
+    /// instead of hanging.
+    pub(super) async fn open_channel(
+        &self,
+    ) -> Result<(russh::Channel<client::Msg>, OwnedSemaphorePermit)> {
+        let permit = if let Ok(permit) = Arc::clone(&self.channel_slots).try_acquire_owned() {
+            permit
+        } else {
+            tracing::debug!("All channels busy, queueing channel open");
+            Arc::clone(&self.channel_slots)
+                .acquire_owned()
+                .await
+                .context("Channel queue closed")?
+        };
+
+        match tokio::time::timeout(Duration::from_secs(CHANNEL_OPEN_TIMEOUT_SECS), async {
+            let session = self.session.lock().await;
+            session
+                .channel_open_session()
+                .await
+                .context("Failed to open channel")
+        })
+        .await
+        {
+            Ok(Ok(ch)) => {
+                self.stats.channel_opened();
+                Ok((ch, permit))
+            }
+            Ok(Err(e)) => {
+                // channel_open_session returned an error — connection is unusable.
+                tracing::warn!("Channel open failed: {e}");
+                self.mark_closed();
+                Err(ConnectionLost(format!("{UNUSABLE}: {e:#}")).into())
+            }
+            Err(_elapsed) => {
+                tracing::warn!(
+                    "Channel open timed out after {CHANNEL_OPEN_TIMEOUT_SECS}s, \
+                     connection likely dead"
+                );
+                self.mark_closed();
+                Err(ConnectionLost(format!(
+                    "Timed out opening SSH channel ({CHANNEL_OPEN_TIMEOUT_SECS}s). \
+                     The connection may be dead — retry to auto-reconnect."
+                ))
+                .into())
+            }
+        }
+    }
+
+    /// Open a session channel and close it again, returning how long the
+    /// open took: one request and reply over the connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the channel can't be opened (see `open_channel`).
+    pub async fn time_channel_open(&self) -> Result<Duration> {
+        let started = Instant::now();
+        let (channel, _permit) = self.open_channel().await?;
+        let elapsed = started.elapsed();
+        let _ = channel.close().await;
+        Ok(elapsed)
+    }
+
+    /// Open a `direct-tcpip` channel to `host`:`port` as the server sees
+    /// them, as `ssh -L` and `ssh -D` do. `originator` is the local client
+    /// the connection is made for.
+    ///
+    /// Takes no channel slot, since sshd's `MaxSessions` doesn't count
+    /// forwarded connections. A target that refuses fails only this channel
+    /// and leaves the connection usable.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server refuses the channel (the target is
+    /// unreachable, or forwarding is disabled) or doesn't answer in time.
+    pub async fn open_tunnel(
+        &self,
+        host: &str,
+        port: u16,
+        originator: SocketAddr,
+    ) -> Result<ChannelStream<client::Msg>> {
+        let channel = tokio::time::timeout(Duration::from_secs(CHANNEL_OPEN_TIMEOUT_SECS), async {
+            let session = self.session.lock().await;
+            session
+                .channel_open_direct_tcpip(
+                    host,
+                    u32::from(port),
+                    originator.ip().to_string(),
+                    u32::from(originator.port()),
+                )
+                .await
+        })
+        .await
+        .map_err(|_| {
+            anyhow!("Timed out opening a tunnel to {host}:{port} ({CHANNEL_OPEN_TIMEOUT_SECS}s)")
+        })?
+        .with_context(|| format!("The server could not open a tunnel to {host}:{port}"))?;
+        Ok(channel.into_stream())
+    }
+
     /// Open a channel, execute a command, and collect all output with an optional timeout.
     ///
     /// If `stdin_data` is provided, it is written to the channel in
@@ -227,68 +648,16 @@ impl SshConnection {
     /// commands over the same SSH connection.
     async fn run_channel(
         &self,
-        command: &str,
+        full_command: String,
         stdin_data: Option<&[u8]>,
         timeout_ms: Option<u64>,
     ) -> Result<ChannelOutput> {
-        // Lock ONLY for channel creation, then drop.
-        // Timeout prevents hanging on dead connections (e.g. after OS suspend).
-        let mut channel =
-            match tokio::time::timeout(Duration::from_secs(CHANNEL_OPEN_TIMEOUT_SECS), async {
-                let session = self.session.lock().await;
-                session
-                    .channel_open_session()
-                    .await
-                    .context("Failed to open channel")
-            })
-            .await
-            {
-                Ok(Ok(ch)) => ch,
-                Ok(Err(e)) => {
-                    // channel_open_session returned an error — connection is unusable.
-                    tracing::warn!("Channel open failed: {e}");
-                    self.mark_closed();
-                    return Err(e.context("Connection unusable — retry to auto-reconnect"));
-                }
-                Err(_elapsed) => {
-                    tracing::warn!(
-                        "Channel open timed out after {CHANNEL_OPEN_TIMEOUT_SECS}s, \
-                     connection likely dead"
-                    );
-                    self.mark_closed();
-                    return Err(anyhow!(
-                        "Timed out opening SSH channel ({CHANNEL_OPEN_TIMEOUT_SECS}s). \
-                     The connection may be dead — retry to auto-reconnect."
-                    ));
-                }
-            };
-
-        let full_command = format!(
-            "cd {} && {}",
-            shell_escape_remote_path(&self.params.remote_path),
-            command,
-        );
-
-        if let Err(e) = channel.exec(true, full_command).await {
-            // exec failure after opening a channel means the connection is broken.
-            self.mark_closed();
-            return Err(anyhow!(e).context("Connection unusable — retry to auto-reconnect"));
-        }
+        let (mut channel, _permit) = self.start_channel(full_command).await?;
 
         // Write stdin if provided, then always close stdin so interactive
         // commands get EOF instead of hanging indefinitely.
-        if let Some(data) = stdin_data {
-            for chunk in data.chunks(STDIN_CHUNK_SIZE) {
-                if let Err(e) = channel.data(chunk).await {
-                    self.mark_closed();
-                    return Err(anyhow!(e).context("Connection unusable — retry to auto-reconnect"));
-                }
-            }
-        }
-        if let Err(e) = channel.eof().await {
-            self.mark_closed();
-            return Err(anyhow!(e).context("Connection unusable — retry to auto-reconnect"));
-        }
+        self.send_stdin(&channel, stdin_data.unwrap_or_default())
+            .await?;
 
         // Collect output
         let output = if let Some(ms) = timeout_ms {
@@ -301,17 +670,58 @@ impl SshConnection {
                 Ok(output) => output,
                 Err(_elapsed) => {
                     self.cleanup_timed_out_channel(&mut channel).await;
-                    return Err(anyhow!("Command timed out"));
+                    return Err(TimedOut("Command timed out".to_string()).into());
                 }
             }
         } else {
             Self::collect_channel_output(&mut channel).await
         };
+        self.stats.transferred(
+            stdin_data.map_or(0, <[u8]>::len),
+            output.stdout.len() + output.stderr.len(),
+        );
 
         Ok(output)
     }
 
-    /// Clean up a channel after a command timeout.
+    /// Open a channel and start `full_command` on it.
+    async fn start_channel(
+        &self,
+        full_command: String,
+    ) -> Result<(russh::Channel<client::Msg>, OwnedSemaphorePermit)> {
+        let (channel, permit) = self.open_channel().await?;
+        if let Err(e) = channel.exec(true, full_command).await {
+            // exec failure after opening a channel means the connection is broken.
+            self.mark_closed();
+            return Err(ConnectionLost(format!("{UNUSABLE}: {e}")).into());
+        }
+        Ok((channel, permit))
+    }
+
+    /// Write `data` to a channel's stdin in [`STDIN_CHUNK_SIZE`] chunks,
+    /// then send EOF.
+    async fn send_stdin(&self, channel: &russh::Channel<client::Msg>, data: &[u8]) -> Result<()> {
+        for chunk in data.chunks(STDIN_CHUNK_SIZE) {
+            self.send_data(channel, chunk).await?;
+        }
+        self.send_eof(channel).await
+    }
+
+    async fn send_data(&self, channel: &russh::Channel<client::Msg>, chunk: &[u8]) -> Result<()> {
+        channel.data(chunk).await.map_err(|e| {
+            self.mark_closed();
+            ConnectionLost(format!("{UNUSABLE}: {e}")).into()
+        })
+    }
+
+    async fn send_eof(&self, channel: &russh::Channel<client::Msg>) -> Result<()> {
+        channel.eof().await.map_err(|e| {
+            self.mark_closed();
+            ConnectionLost(format!("{UNUSABLE}: {e}")).into()
+        })
+    }
+
+    /// Clean up a channel after a command timeout or cancellation.
     ///
     /// Two-phase approach to prevent head-of-line blocking:
     ///
@@ -326,7 +736,7 @@ impl SshConnection {
     ///    immediately.
     ///
     /// If close fails, the connection is likely broken — escalate to
-    /// [`Self::disconnect`].
+    /// [`Self::close_session`].
     async fn cleanup_timed_out_channel(&self, channel: &mut russh::Channel<client::Msg>) {
         // Phase 1: drain buffered data to unblock the session loop.
         // This won't complete naturally (remote keeps sending without a
@@ -351,7 +761,7 @@ impl SshConnection {
             }
             Err(e) => {
                 tracing::warn!("Channel close failed after drain: {e}");
-                self.disconnect().await;
+                self.close_session().await;
             }
         }
     }
@@ -363,15 +773,37 @@ impl SshConnection {
         while channel.wait().await.is_some() {}
     }
 
-    /// Send `SSH_MSG_DISCONNECT` so sshd can immediately clean up child
-    /// processes, then mark the connection dead.
+    /// Run the commands registered with [`on_disconnect`](Self::on_disconnect),
+    /// then send `SSH_MSG_DISCONNECT` so sshd can immediately clean up child
+    /// processes, and mark the connection dead.
     ///
     /// Both the mutex acquisition and the disconnect send go through an
     /// aggressive timeout — if the session is saturated (bounded sender
     /// full), we fall back to `mark_closed()` alone. The connection will be
     /// evicted from the pool, and when the last `Arc<SshConnection>` drops,
     /// the sender closes, the session loop exits, and TCP tears down.
+    ///
+    /// A connection [shared](Self::share) with other servers closes for them
+    /// too, so none of them keeps using a session that was given up on.
     pub async fn disconnect(&self) {
+        let cleanup = std::mem::take(
+            &mut *self
+                .cleanup
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        );
+        if !cleanup.is_empty() && !self.force_closed.load(Ordering::Relaxed) {
+            let timeout_ms = DISCONNECT_TIMEOUT_SECS * 1000;
+            if let Err(e) = self.exec_bare(&cleanup.join("; "), Some(timeout_ms)).await {
+                tracing::debug!("Cleanup before disconnect failed: {e}");
+            }
+        }
+        self.close_session().await;
+    }
+
+    /// Send `SSH_MSG_DISCONNECT` and mark the connection dead, without the
+    /// cleanup commands, which a broken session couldn't run anyway.
+    async fn close_session(&self) {
         self.mark_closed();
 
         let result = tokio::time::timeout(Duration::from_secs(DISCONNECT_TIMEOUT_SECS), async {
@@ -398,33 +830,9 @@ impl SshConnection {
 
     /// Drain all output from a channel until it closes.
     async fn collect_channel_output(channel: &mut russh::Channel<client::Msg>) -> ChannelOutput {
-        let mut stdout = Vec::new();
-        let mut stderr = Vec::new();
-        let mut exit_code = None;
-
-        loop {
-            match channel.wait().await {
-                Some(ChannelMsg::Data { data }) => {
-                    stdout.extend_from_slice(&data);
-                }
-                Some(ChannelMsg::ExtendedData { data, ext }) => {
-                    if ext == SSH_EXTENDED_DATA_STDERR {
-                        stderr.extend_from_slice(&data);
-                    }
-                }
-                Some(ChannelMsg::ExitStatus { exit_status }) => {
-                    exit_code = Some(exit_status.cast_signed());
-                }
-                None => break,
-                _ => {}
-            }
-        }
-
-        ChannelOutput {
-            stdout,
-            stderr,
-            exit_code: exit_code.unwrap_or(EXIT_CODE_NO_STATUS),
-        }
+        let mut output = PartialOutput::default();
+        while output.add(channel.wait().await) {}
+        output.finish()
     }
 
     /// Execute a command on the remote machine.
@@ -433,7 +841,25 @@ impl SshConnection {
     /// Returns an error if the SSH channel cannot be opened, the command
     /// fails to start, or the optional timeout expires.
     pub async fn exec(&self, command: &str, timeout_ms: Option<u64>) -> Result<ExecResult> {
-        let output = self.run_channel(command, None, timeout_ms).await?;
+        let full_command = self.params.shell.in_dir(&self.params.remote_path, command);
+        let output = self.run_channel(full_command, None, timeout_ms).await?;
+        Ok(ExecResult {
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            exit_code: output.exit_code,
+        })
+    }
+
+    /// Execute a command verbatim in the login shell and directory, without
+    /// the base-path `cd` prefix. Used to probe the shell dialect before it
+    /// is known.
+    ///
+    /// # Errors
+    /// Returns an error if the SSH channel cannot be opened or the timeout expires.
+    pub async fn exec_bare(&self, command: &str, timeout_ms: Option<u64>) -> Result<ExecResult> {
+        let output = self
+            .run_channel(command.to_string(), None, timeout_ms)
+            .await?;
         Ok(ExecResult {
             stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
             stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
@@ -452,13 +878,319 @@ impl SshConnection {
         stdin_data: Option<&[u8]>,
         timeout_ms: Option<u64>,
     ) -> Result<ExecRawResult> {
-        let output = self.run_channel(command, stdin_data, timeout_ms).await?;
+        let full_command = self.params.shell.in_dir(&self.params.remote_path, command);
+        let output = self
+            .run_channel(full_command, stdin_data, timeout_ms)
+            .await?;
         Ok(ExecRawResult {
             stdout: output.stdout,
             stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
             exit_code: output.exit_code,
         })
     }
+
+    /// Execute a command like [`Self::exec_raw`], stopping it if `cancelled`
+    /// completes first.
+    ///
+    /// On cancellation the command gets `SIGTERM` over the channel and, on a
+    /// POSIX remote, its process group is killed from a second channel, so
+    /// children it started stop too. The output received until then is
+    /// returned as [`ExecOutcome::Cancelled`].
+    ///
+    /// # Errors
+    /// Returns an error if the SSH channel cannot be opened, stdin data
+    /// fails to write, or the optional timeout expires.
+    pub async fn exec_raw_cancellable(
+        &self,
+        command: &str,
+        stdin_data: Option<&[u8]>,
+        timeout_ms: Option<u64>,
+        cancelled: impl Future<Output = ()>,
+    ) -> Result<ExecOutcome> {
+        let posix = self.params.shell.is_posix();
+        let mut full_command = self.params.shell.in_dir(&self.params.remote_path, command);
+        if posix {
+            // A stderr line naming the shell's PID, to find the process group
+            full_command = format!("echo {PID_MARKER}$$ >&2; {full_command}");
+        }
+        let (mut channel, _permit) = self.start_channel(full_command).await?;
+        self.send_stdin(&channel, stdin_data.unwrap_or_default())
+            .await?;
+
+        let mut output = PartialOutput::default();
+        let collect = async {
+            tokio::pin!(cancelled);
+            loop {
+                tokio::select! {
+                    msg = channel.wait() => if !output.add(msg) {
+                        return false;
+                    },
+                    () = &mut cancelled => return true,
+                }
+            }
+        };
+        let Some(was_cancelled) = within(timeout_ms, collect).await else {
+            self.cleanup_timed_out_channel(&mut channel).await;
+            return Err(TimedOut("Command timed out".to_string()).into());
+        };
+        let (pid, stderr) = split_pid_marker(&output.stderr);
+        if was_cancelled {
+            self.stop_command(&mut channel, pid.filter(|_| posix)).await;
+        }
+        let result = ExecRawResult {
+            stderr: String::from_utf8_lossy(&stderr).into_owned(),
+            exit_code: output.exit_code.unwrap_or(EXIT_CODE_NO_STATUS),
+            stdout: output.stdout,
+        };
+        self.stats.transferred(
+            stdin_data.map_or(0, <[u8]>::len),
+            result.stdout.len() + result.stderr.len(),
+        );
+        Ok(if was_cancelled {
+            ExecOutcome::Cancelled(result)
+        } else {
+            ExecOutcome::Finished(result)
+        })
+    }
+
+    /// Stop a cancelled command: `SIGTERM` over its channel, then the
+    /// process group of `pid` killed from a second channel, then the channel
+    /// drained and closed.
+    async fn stop_command(&self, channel: &mut russh::Channel<client::Msg>, pid: Option<u32>) {
+        // Servers before OpenSSH 7.9 ignore signal requests; the group kill
+        // below covers them
+        if let Err(e) = channel.signal(Sig::TERM).await {
+            tracing::debug!("Failed to signal cancelled command: {e}");
+        }
+        if let Some(pid) = pid {
+            if let Err(e) = self
+                .exec_bare(&kill_group_command(pid), Some(KILL_TIMEOUT_MS))
+                .await
+            {
+                tracing::warn!("Failed to kill the process group of cancelled command {pid}: {e}");
+            }
+        }
+        self.cleanup_timed_out_channel(channel).await;
+    }
+}
+
+/// Output collected from a channel so far.
+#[derive(Default)]
+struct PartialOutput {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    exit_code: Option<i32>,
+}
+
+impl PartialOutput {
+    /// Add one channel message. Returns `false` once the channel has closed.
+    fn add(&mut self, msg: Option<ChannelMsg>) -> bool {
+        match msg {
+            Some(ChannelMsg::Data { data }) => self.stdout.extend_from_slice(&data),
+            Some(ChannelMsg::ExtendedData { data, ext }) if ext == SSH_EXTENDED_DATA_STDERR => {
+                self.stderr.extend_from_slice(&data);
+            }
+            Some(ChannelMsg::ExitStatus { exit_status }) => {
+                self.exit_code = Some(exit_status.cast_signed());
+            }
+            None => return false,
+            _ => {}
+        }
+        true
+    }
+
+    fn finish(self) -> ChannelOutput {
+        ChannelOutput {
+            stdout: self.stdout,
+            stderr: self.stderr,
+            exit_code: self.exit_code.unwrap_or(EXIT_CODE_NO_STATUS),
+        }
+    }
+}
+
+/// Split the `ssh-hub-pid:<pid>` line written by
+/// [`SshConnection::exec_raw_cancellable`] out of `stderr`. Login scripts may
+/// print before it, so it is looked for on every line.
+#[must_use]
+pub fn split_pid_marker(stderr: &[u8]) -> (Option<u32>, Vec<u8>) {
+    let mut start = 0;
+    while start < stderr.len() {
+        let end = stderr[start..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(stderr.len(), |i| start + i + 1);
+        if let Some(pid) = stderr[start..end].strip_prefix(PID_MARKER.as_bytes()) {
+            let pid = std::str::from_utf8(pid)
+                .ok()
+                .and_then(|p| p.trim().parse().ok());
+            if pid.is_some() {
+                let mut rest = stderr[..start].to_vec();
+                rest.extend_from_slice(&stderr[end..]);
+                return (pid, rest);
+            }
+        }
+        start = end;
+    }
+    (None, stderr.to_vec())
+}
+
+/// Shell command that stops the process group `pid` belongs to: `SIGTERM`,
+/// then `SIGKILL` a second later for anything still running. Falls back to
+/// the process itself when `ps` can't name the group.
+#[must_use]
+pub fn kill_group_command(pid: u32) -> String {
+    format!(
+        "g=$(ps -o pgid= -p {pid} 2>/dev/null | tr -d ' '); g=${{g:-{pid}}}; \
+         kill -TERM -$g 2>/dev/null || kill -TERM {pid} 2>/dev/null; \
+         sleep 1; kill -KILL -$g 2>/dev/null; true"
+    )
+}
+
+impl SshConnection {
+    /// Execute a command with stdin read from `stdin` until EOF, a chunk at
+    /// a time. Only one chunk is held in memory however much is sent; stdout
+    /// is discarded.
+    ///
+    /// # Errors
+    /// Returns an error if the SSH channel cannot be opened, `stdin` fails to
+    /// read, or the optional timeout expires.
+    pub async fn exec_stream_stdin<R>(
+        &self,
+        command: &str,
+        mut stdin: R,
+        timeout_ms: Option<u64>,
+    ) -> Result<StreamedResult>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let full_command = self.params.shell.in_dir(&self.params.remote_path, command);
+        let (mut channel, _permit) = self.start_channel(full_command).await?;
+
+        let run = async {
+            let mut buf = vec![0; STDIN_CHUNK_SIZE];
+            let mut sent = 0;
+            loop {
+                let n = match stdin.read(&mut buf).await {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(e) => {
+                        let _ = channel.close().await;
+                        return Err(anyhow!("Failed to read data to send: {e}"));
+                    }
+                };
+                self.send_data(&channel, &buf[..n]).await?;
+                sent += n as u64;
+            }
+            self.send_eof(&channel).await?;
+            let output = Self::collect_channel_output(&mut channel).await;
+            Ok((sent, output))
+        };
+        let Some(result) = within(timeout_ms, run).await else {
+            self.cleanup_timed_out_channel(&mut channel).await;
+            return Err(TimedOut("Command timed out".to_string()).into());
+        };
+        let (sent, output) = result?;
+        self.stats.transferred(
+            usize::try_from(sent).unwrap_or(usize::MAX),
+            output.stdout.len() + output.stderr.len(),
+        );
+        Ok(StreamedResult {
+            bytes: sent,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            exit_code: output.exit_code,
+        })
+    }
+
+    /// Execute a command and write its stdout into `stdout` as it arrives,
+    /// rather than collecting it in memory.
+    ///
+    /// # Errors
+    /// Returns an error if the SSH channel cannot be opened, stdin data
+    /// fails to send, writing to `stdout` fails, or the optional timeout
+    /// expires.
+    pub async fn exec_stream_stdout<W>(
+        &self,
+        command: &str,
+        stdin_data: Option<&[u8]>,
+        mut stdout: W,
+        timeout_ms: Option<u64>,
+    ) -> Result<StreamedResult>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let full_command = self.params.shell.in_dir(&self.params.remote_path, command);
+        let (mut channel, _permit) = self.start_channel(full_command).await?;
+        self.send_stdin(&channel, stdin_data.unwrap_or_default())
+            .await?;
+
+        let run = async {
+            let mut received = 0;
+            let mut stderr = Vec::new();
+            let mut exit_code = None;
+            loop {
+                match channel.wait().await {
+                    Some(ChannelMsg::Data { data }) => {
+                        if let Err(e) = stdout.write_all(&data).await {
+                            let _ = channel.close().await;
+                            return Err(anyhow!("Failed to write command output: {e}"));
+                        }
+                        received += data.len() as u64;
+                    }
+                    Some(ChannelMsg::ExtendedData { data, ext })
+                        if ext == SSH_EXTENDED_DATA_STDERR =>
+                    {
+                        stderr.extend_from_slice(&data);
+                    }
+                    Some(ChannelMsg::ExitStatus { exit_status }) => {
+                        exit_code = Some(exit_status.cast_signed());
+                    }
+                    None => break,
+                    _ => {}
+                }
+            }
+            stdout
+                .flush()
+                .await
+                .map_err(|e| anyhow!("Failed to write command output: {e}"))?;
+            Ok((received, stderr, exit_code))
+        };
+        let Some(result) = within(timeout_ms, run).await else {
+            self.cleanup_timed_out_channel(&mut channel).await;
+            return Err(TimedOut("Command timed out".to_string()).into());
+        };
+        let (received, stderr, exit_code) = result?;
+        self.stats.transferred(
+            stdin_data.map_or(0, <[u8]>::len),
+            usize::try_from(received).unwrap_or(usize::MAX) + stderr.len(),
+        );
+        Ok(StreamedResult {
+            bytes: received,
+            stderr: String::from_utf8_lossy(&stderr).into_owned(),
+            exit_code: exit_code.unwrap_or(EXIT_CODE_NO_STATUS),
+        })
+    }
+}
+
+/// Run `work`, giving up after `timeout_ms` if set.
+async fn within<T>(
+    timeout_ms: Option<u64>,
+    work: impl std::future::Future<Output = T>,
+) -> Option<T> {
+    match timeout_ms {
+        Some(ms) => tokio::time::timeout(Duration::from_millis(ms), work)
+            .await
+            .ok(),
+        None => Some(work.await),
+    }
+}
+
+/// Result of a command whose stdin or stdout was streamed.
+#[derive(Debug, Clone)]
+pub struct StreamedResult {
+    /// Bytes streamed to stdin or from stdout.
+    pub bytes: u64,
+    pub stderr: String,
+    pub exit_code: i32,
 }
 
 /// Result of executing a command.
@@ -469,6 +1201,25 @@ pub struct ExecResult {
     pub exit_code: i32,
 }
 
+/// How a command run with [`SshConnection::exec_raw_cancellable`] ended.
+#[derive(Debug, Clone)]
+pub enum ExecOutcome {
+    Finished(ExecRawResult),
+    /// Stopped by cancellation, with the output received until then.
+    Cancelled(ExecRawResult),
+}
+
+impl ExecOutcome {
+    /// The result, and whether the command was cancelled.
+    #[must_use]
+    pub fn into_parts(self) -> (ExecRawResult, bool) {
+        match self {
+            Self::Finished(result) => (result, false),
+            Self::Cancelled(result) => (result, true),
+        }
+    }
+}
+
 /// Result of executing a command with raw byte output.
 #[derive(Debug, Clone)]
 pub struct ExecRawResult {
@@ -0,0 +1,71 @@
+//! Command construction for non-POSIX remote shells (Windows OpenSSH with
+//! PowerShell or cmd.exe as the login shell).
+//!
+//! Only the working-directory prefix is translated; the command itself is
+//! passed through, so callers are responsible for speaking the remote
+//! shell's language. File operations avoid shell commands on these remotes
+//! and go over SFTP instead.
+
+use crate::server_registry::RemoteShell;
+use crate::utils::path::shell_escape_remote_path;
+
+use super::SshConnection;
+
+impl SshConnection {
+    /// Why a feature built on POSIX utilities (`sh`, `find`, `tail`,
+    /// `sha256sum`) is unavailable, or `None` when the remote has a POSIX shell.
+    #[must_use]
+    pub fn posix_required(&self, feature: &str) -> Option<String> {
+        self.shell().posix_required(feature)
+    }
+}
+
+impl RemoteShell {
+    /// Why a feature built on POSIX utilities is unavailable with this
+    /// shell, or `None` for a POSIX shell.
+    #[must_use]
+    pub fn posix_required(self, feature: &str) -> Option<String> {
+        (!self.is_posix()).then(|| {
+            format!("{feature} requires a POSIX shell on the remote (this server uses {self})")
+        })
+    }
+
+    /// Wrap `command` so it runs with `dir` as the working directory.
+    #[must_use]
+    pub fn in_dir(self, dir: &str, command: &str) -> String {
+        match self {
+            Self::Posix => format!("cd {} && {command}", shell_escape_remote_path(dir)),
+            Self::Powershell => format!(
+                "Set-Location -ErrorAction Stop -LiteralPath {}; {command}",
+                powershell_path(dir)
+            ),
+            Self::Cmd => format!("cd /d {} && {command}", cmd_path(dir)),
+        }
+    }
+}
+
+/// PowerShell path expression. `~` maps to `$HOME`; everything else is a
+/// single-quoted literal (`'` doubled).
+fn powershell_path(path: &str) -> String {
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+    if path == "~" {
+        "$HOME".to_string()
+    } else if let Some(rest) = path.strip_prefix("~/") {
+        format!("(Join-Path $HOME {})", quote(rest))
+    } else {
+        quote(path)
+    }
+}
+
+/// cmd.exe path argument. `~` maps to `%USERPROFILE%`. cmd has no escape for
+/// `"` and Windows forbids it in file names, so it is dropped.
+fn cmd_path(path: &str) -> String {
+    let path = path.replace('"', "");
+    if path == "~" {
+        "\"%USERPROFILE%\"".to_string()
+    } else if let Some(rest) = path.strip_prefix("~/") {
+        format!("\"%USERPROFILE%\\{}\"", rest.replace('/', "\\"))
+    } else {
+        format!("\"{path}\"")
+    }
+}
@@ -0,0 +1,169 @@
+//! SOCKS5 (RFC 1928) for dynamic forwarding, the `ssh -D` equivalent:
+//! clients connect to a local listener and name a target, and the server
+//! makes the TCP connection to it through a `direct-tcpip` channel.
+//!
+//! Only what tunnelling needs: the CONNECT command without authentication.
+//! Listeners bind to loopback, so nothing else on the network can use them.
+
+use std::future::Future;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+const VERSION: u8 = 0x05;
+const NO_AUTH: u8 = 0x00;
+const NO_ACCEPTABLE_METHOD: u8 = 0xFF;
+const CONNECT: u8 = 0x01;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+pub const REPLY_SUCCEEDED: u8 = 0x00;
+pub const REPLY_HOST_UNREACHABLE: u8 = 0x04;
+pub const REPLY_COMMAND_NOT_SUPPORTED: u8 = 0x07;
+pub const REPLY_ADDRESS_NOT_SUPPORTED: u8 = 0x08;
+
+/// How long a client has to name its target after connecting.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Read a client's greeting and CONNECT request, and return the target
+/// host and port. Domain names are returned unresolved, so the server
+/// resolves them (as `socks5h://` clients expect).
+///
+/// # Errors
+///
+/// Returns an error, after telling the client why where SOCKS5 can, if
+/// the client speaks another protocol, wants authentication, or asks for
+/// anything but CONNECT.
+pub async fn handshake<S>(stream: &mut S) -> Result<(String, u16)>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header).await?;
+    if header[0] != VERSION {
+        bail!("Not a SOCKS5 client (version byte {:#04x})", header[0]);
+    }
+    let mut methods = vec![0u8; usize::from(header[1])];
+    stream.read_exact(&mut methods).await?;
+    if !methods.contains(&NO_AUTH) {
+        stream.write_all(&[VERSION, NO_ACCEPTABLE_METHOD]).await?;
+        bail!("The client requires authentication, which isn't supported");
+    }
+    stream.write_all(&[VERSION, NO_AUTH]).await?;
+
+    let mut request = [0u8; 4];
+    stream.read_exact(&mut request).await?;
+    if request[0] != VERSION {
+        bail!("Not a SOCKS5 request (version byte {:#04x})", request[0]);
+    }
+    let host = match request[3] {
+        ATYP_IPV4 => {
+            let mut octets = [0u8; 4];
+            stream.read_exact(&mut octets).await?;
+            Ipv4Addr::from(octets).to_string()
+        }
+        ATYP_IPV6 => {
+            let mut octets = [0u8; 16];
+            stream.read_exact(&mut octets).await?;
+            Ipv6Addr::from(octets).to_string()
+        }
+        ATYP_DOMAIN => {
+            let len = stream.read_u8().await?;
+            let mut name = vec![0u8; usize::from(len)];
+            stream.read_exact(&mut name).await?;
+            String::from_utf8(name).map_err(|_| anyhow::anyhow!("Invalid domain name"))?
+        }
+        other => {
+            reply(stream, REPLY_ADDRESS_NOT_SUPPORTED).await?;
+            bail!("Unsupported address type {other:#04x}");
+        }
+    };
+    let port = stream.read_u16().await?;
+    if request[1] != CONNECT {
+        reply(stream, REPLY_COMMAND_NOT_SUPPORTED).await?;
+        bail!(
+            "Unsupported SOCKS command {:#04x}; only CONNECT is",
+            request[1]
+        );
+    }
+    Ok((host, port))
+}
+
+/// Send the reply to a CONNECT request. The bound address is left zero:
+/// it is the server's, and clients don't use it.
+///
+/// # Errors
+///
+/// Returns an error if the client has gone away.
+pub async fn reply<S>(stream: &mut S, code: u8) -> Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    stream
+        .write_all(&[VERSION, code, 0x00, ATYP_IPV4, 0, 0, 0, 0, 0, 0])
+        .await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Serve one SOCKS5 client: read its request, open the target with `open`
+/// (given the host, port, and the client's address), then relay bytes both
+/// ways until either side closes.
+///
+/// # Errors
+///
+/// Returns an error if the handshake fails or `open` can't reach the
+/// target; the client has been sent a failure reply by then.
+pub async fn proxy<S, F, Fut, T>(mut client: S, peer: SocketAddr, open: F) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    F: FnOnce(String, u16, SocketAddr) -> Fut,
+    Fut: Future<Output = Result<T>>,
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let (host, port) = tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake(&mut client))
+        .await
+        .map_err(|_| anyhow::anyhow!("The client sent no request in time"))??;
+    let mut target = match open(host.clone(), port, peer).await {
+        Ok(target) => target,
+        Err(e) => {
+            let _ = reply(&mut client, REPLY_HOST_UNREACHABLE).await;
+            return Err(e.context(format!("Cannot reach {host}:{port}")));
+        }
+    };
+    reply(&mut client, REPLY_SUCCEEDED).await?;
+    // Either side resetting the connection is the normal way tunnels end
+    let _ = tokio::io::copy_bidirectional(&mut client, &mut target).await;
+    Ok(())
+}
+
+/// Accept SOCKS5 clients on `listener` until the task is aborted, serving
+/// each on its own task with [`proxy`].
+pub async fn serve<F, Fut, T>(listener: TcpListener, open: F)
+where
+    F: Fn(String, u16, SocketAddr) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<T>> + Send + 'static,
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    loop {
+        let (client, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                // Out of file descriptors and the like; don't spin
+                tracing::warn!("SOCKS listener accept failed: {e}");
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let open = open.clone();
+        tokio::spawn(async move {
+            if let Err(e) = proxy(client, peer, open).await {
+                tracing::debug!("SOCKS client {peer}: {e:#}");
+            }
+        });
+    }
+}
@@ -0,0 +1,162 @@
+//! Activity counters for one pooled connection, reported by `pool_stats`.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// What the pool knows about a connection besides its counters.
+pub(super) struct Usage {
+    pub(super) channels_in_use: usize,
+    pub(super) max_channels: usize,
+    pub(super) closed: bool,
+}
+
+/// Counters updated as a connection is used.
+pub(super) struct ConnectionStats {
+    connected_at: u64,
+    last_activity: AtomicU64,
+    channels_opened: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+}
+
+impl ConnectionStats {
+    pub(super) fn new() -> Self {
+        let now = unix_now();
+        Self {
+            connected_at: now,
+            last_activity: AtomicU64::new(now),
+            channels_opened: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+        }
+    }
+
+    pub(super) fn channel_opened(&self) {
+        self.channels_opened.fetch_add(1, Ordering::Relaxed);
+        self.touch();
+    }
+
+    pub(super) fn transferred(&self, sent: usize, received: usize) {
+        self.bytes_sent.fetch_add(sent as u64, Ordering::Relaxed);
+        self.bytes_received
+            .fetch_add(received as u64, Ordering::Relaxed);
+        self.touch();
+    }
+
+    pub(super) fn snapshot(
+        &self,
+        server: &str,
+        params: &super::ConnectionParams,
+        usage: &Usage,
+    ) -> PoolEntry {
+        let health = if usage.closed {
+            Health::Closed
+        } else if usage.channels_in_use >= usage.max_channels {
+            Health::Saturated
+        } else {
+            Health::Healthy
+        };
+        let mut entry = PoolEntry {
+            server: server.to_string(),
+            user: params.user.clone(),
+            host: params.host.clone(),
+            port: params.port,
+            connected_at: self.connected_at,
+            last_activity: self.last_activity.load(Ordering::Relaxed),
+            age_secs: 0,
+            idle_secs: 0,
+            channels_opened: self.channels_opened.load(Ordering::Relaxed),
+            channels_in_use: usage.channels_in_use,
+            max_channels: usage.max_channels,
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            health,
+        };
+        entry.refresh_ages(unix_now());
+        entry
+    }
+
+    fn touch(&self) {
+        self.last_activity.store(unix_now(), Ordering::Relaxed);
+    }
+}
+
+/// How a pooled connection is doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Health {
+    Healthy,
+    /// Every channel slot is in use; new calls queue.
+    Saturated,
+    /// The session has closed; the next call reconnects.
+    Closed,
+}
+
+/// Output of the `pool_stats` tool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolStats {
+    pub connections: Vec<PoolEntry>,
+}
+
+/// A snapshot of one pooled connection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolEntry {
+    pub server: String,
+    pub user: String,
+    pub host: String,
+    pub port: u16,
+    /// Unix time the connection was established.
+    pub connected_at: u64,
+    /// Unix time a channel was last opened or finished.
+    pub last_activity: u64,
+    pub age_secs: u64,
+    pub idle_secs: u64,
+    pub channels_opened: u64,
+    pub channels_in_use: usize,
+    pub max_channels: usize,
+    /// Bytes written to and read from command channels (SFTP transfers
+    /// aren't counted).
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub health: Health,
+}
+
+/// The last failed attempt to connect to a server, kept until a connection
+/// to it succeeds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectError {
+    pub message: String,
+    /// Unix time of the attempt.
+    pub at: u64,
+}
+
+impl ConnectError {
+    #[must_use]
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            at: unix_now(),
+        }
+    }
+
+    #[must_use]
+    pub fn age_secs(&self) -> u64 {
+        unix_now().saturating_sub(self.at)
+    }
+}
+
+impl PoolEntry {
+    /// Recompute `age_secs` and `idle_secs` as of `now` (Unix seconds).
+    pub fn refresh_ages(&mut self, now: u64) {
+        self.age_secs = now.saturating_sub(self.connected_at);
+        self.idle_secs = now.saturating_sub(self.last_activity);
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
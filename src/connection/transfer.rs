@@ -0,0 +1,275 @@
+//! Transfer primitives for [`SshConnection`]: remote capability probing and
+//! SFTP sessions. Strategy selection lives in `tools::sync_transfer`.
+
+use std::ops::Deref;
+
+use anyhow::{anyhow, Context, Result};
+use russh_sftp::client::SftpSession;
+use tokio::sync::OwnedSemaphorePermit;
+
+use crate::utils::path::home_relative;
+
+use super::SshConnection;
+
+/// Timeout for the capability probe command (10 seconds).
+const PROBE_TIMEOUT_MS: u64 = 10_000;
+
+/// Shell script that prints one line per capability found on the remote.
+/// `tar` only counts when it can write gzip streams, which busybox builds
+/// may lack.
+const PROBE_COMMAND: &str = "command -v tar >/dev/null 2>&1 && \
+     tar czf - /dev/null >/dev/null 2>&1 && echo tar; \
+     for c in rsync sha256sum shasum; do command -v $c >/dev/null 2>&1 && echo $c; done; \
+     if find / -maxdepth 0 -printf '' >/dev/null 2>&1; then echo find-printf; \
+     elif stat -c %s / >/dev/null 2>&1; then echo stat-c; \
+     elif stat -f %z / >/dev/null 2>&1; then echo stat-f; fi; true";
+
+/// Transfer-related tools found on the remote host.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RemoteCapabilities {
+    /// `tar` with gzip support.
+    pub tar: bool,
+    pub rsync: bool,
+    /// Tool that hashes files with sha256.
+    pub sha256: Sha256Tool,
+    /// How to list files with their sizes and modification times.
+    pub file_stat: FileStat,
+    /// No POSIX shell (Windows): every transfer must go over SFTP.
+    pub sftp_only: bool,
+}
+
+/// Command used to hash files with sha256.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Sha256Tool {
+    /// GNU coreutils or busybox `sha256sum`.
+    #[default]
+    Sha256sum,
+    /// Perl's `shasum -a 256`, for macOS and BSD remotes without coreutils.
+    Shasum,
+}
+
+/// Command used to print file sizes and modification times, which differs
+/// between GNU, BSD, and busybox userlands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileStat {
+    /// GNU `find -printf`.
+    #[default]
+    FindPrintf,
+    /// GNU or busybox `stat -c`.
+    StatC,
+    /// BSD and macOS `stat -f`.
+    StatF,
+}
+
+impl RemoteCapabilities {
+    /// Parse the output of the capability probe.
+    #[must_use]
+    pub fn parse(stdout: &str) -> Self {
+        let found: Vec<&str> = stdout.lines().map(str::trim).collect();
+        let file_stat = if found.contains(&"stat-c") {
+            FileStat::StatC
+        } else if found.contains(&"stat-f") {
+            FileStat::StatF
+        } else {
+            FileStat::FindPrintf
+        };
+        Self {
+            tar: found.contains(&"tar"),
+            rsync: found.contains(&"rsync"),
+            sha256: if !found.contains(&"sha256sum") && found.contains(&"shasum") {
+                Sha256Tool::Shasum
+            } else {
+                Sha256Tool::Sha256sum
+            },
+            file_stat,
+            sftp_only: false,
+        }
+    }
+
+    /// Command that prints `<sha256>  <path>` for each file argument.
+    #[must_use]
+    pub fn sha256_command(&self) -> &'static str {
+        match self.sha256 {
+            Sha256Tool::Sha256sum => "sha256sum",
+            Sha256Tool::Shasum => "shasum -a 256",
+        }
+    }
+
+    /// `find` actions that print `<size> <path>` for each match, or
+    /// `<size> <mtime> <path>` with `with_mtime`. The mtime is in whole or
+    /// fractional seconds since the epoch depending on the variant.
+    #[must_use]
+    pub fn stat_action(&self, with_mtime: bool) -> &'static str {
+        match (self.file_stat, with_mtime) {
+            (FileStat::FindPrintf, false) => "-printf '%s %p\\n'",
+            (FileStat::FindPrintf, true) => "-printf '%s %T@ %p\\n'",
+            (FileStat::StatC, false) => "-exec stat -c '%s %n' {} +",
+            (FileStat::StatC, true) => "-exec stat -c '%s %Y %n' {} +",
+            (FileStat::StatF, false) => "-exec stat -f '%z %N' {} +",
+            (FileStat::StatF, true) => "-exec stat -f '%z %m %N' {} +",
+        }
+    }
+}
+
+/// An SFTP session that holds its connection's channel slot until dropped.
+pub struct Sftp {
+    session: SftpSession,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Deref for Sftp {
+    type Target = SftpSession;
+
+    fn deref(&self) -> &SftpSession {
+        &self.session
+    }
+}
+
+impl SshConnection {
+    /// Probe the remote for `tar`, `rsync`, a sha256 tool, and a way to stat
+    /// files. The result is cached for the lifetime of the connection; a
+    /// failed probe reports nothing available and assumes GNU tools.
+    /// Non-POSIX remotes are not probed and report SFTP only.
+    pub async fn capabilities(&self) -> RemoteCapabilities {
+        if !self.shell().is_posix() {
+            return RemoteCapabilities {
+                sftp_only: true,
+                ..RemoteCapabilities::default()
+            };
+        }
+        *self
+            .capabilities
+            .get_or_init(|| async {
+                match self.exec(PROBE_COMMAND, Some(PROBE_TIMEOUT_MS)).await {
+                    Ok(result) => RemoteCapabilities::parse(&result.stdout),
+                    Err(e) => {
+                        tracing::debug!("Capability probe failed: {e}");
+                        RemoteCapabilities::default()
+                    }
+                }
+            })
+            .await
+    }
+
+    /// Open an SFTP session on a new channel.
+    ///
+    /// SFTP paths are not shell-expanded: relative paths resolve from the
+    /// login directory, not the connection's base path.
+    ///
+    /// # Errors
+    /// Returns an error if the channel cannot be opened or the server has no
+    /// `sftp` subsystem.
+    pub async fn open_sftp(&self) -> Result<Sftp> {
+        let (channel, permit) = self.open_channel().await?;
+        channel
+            .request_subsystem(true, "sftp")
+            .await
+            .context("Failed to request sftp subsystem")?;
+        let session = SftpSession::new(channel.into_stream())
+            .await
+            .map_err(|e| anyhow!("SFTP session failed: {e}"))?;
+        Ok(Sftp {
+            session,
+            _permit: permit,
+        })
+    }
+
+    /// Regular files under `base` over SFTP, as paths relative to it.
+    /// Unreadable subdirectories are skipped; the walk stops after `limit` files.
+    ///
+    /// # Errors
+    /// Returns an error if the SFTP session cannot be opened or `base` cannot
+    /// be listed.
+    pub async fn sftp_walk(&self, base: &str, limit: usize) -> Result<Vec<String>> {
+        let entries = self.sftp_walk_entries(base, limit, false).await?;
+        Ok(entries.into_iter().map(|(path, _)| path).collect())
+    }
+
+    /// Like [`sftp_walk`](Self::sftp_walk), but with `include_dirs` also lists
+    /// subdirectories (with a trailing `/`); they count towards `limit`.
+    /// Each entry comes with its mtime in seconds, when the server sends one.
+    pub(super) async fn sftp_walk_entries(
+        &self,
+        base: &str,
+        limit: usize,
+        include_dirs: bool,
+    ) -> Result<Vec<(String, Option<f64>)>> {
+        let entries = self.walk(base, limit, include_dirs, &|_| false).await?;
+        Ok(entries
+            .into_iter()
+            .map(|(path, _, mtime)| (path, mtime.map(f64::from)))
+            .collect())
+    }
+
+    /// Regular files under `base` with their sizes, not descending into
+    /// directories for which `skip_dir` returns true.
+    ///
+    /// # Errors
+    /// Returns an error if the SFTP session cannot be opened or `base` cannot
+    /// be listed.
+    pub async fn sftp_walk_sizes(
+        &self,
+        base: &str,
+        skip_dir: &(dyn Fn(&str) -> bool + Sync),
+    ) -> Result<Vec<(String, u64)>> {
+        let entries = self.walk(base, usize::MAX, false, skip_dir).await?;
+        Ok(entries
+            .into_iter()
+            .map(|(path, size, _)| (path, size))
+            .collect())
+    }
+
+    async fn walk(
+        &self,
+        base: &str,
+        limit: usize,
+        include_dirs: bool,
+        skip_dir: &(dyn Fn(&str) -> bool + Sync),
+    ) -> Result<Vec<(String, u64, Option<u32>)>> {
+        let sftp = self.open_sftp().await?;
+        let root = home_relative(base);
+        let mut entries_out = Vec::new();
+        let mut pending = vec![String::new()];
+        while let Some(rel_dir) = pending.pop() {
+            let entries = if rel_dir.is_empty() {
+                sftp.read_dir(root.clone())
+                    .await
+                    .map_err(|e| anyhow!("Cannot list {base}: {e}"))?
+            } else {
+                match sftp.read_dir(format!("{root}/{rel_dir}")).await {
+                    Ok(entries) => entries,
+                    Err(_) => continue,
+                }
+            };
+            for entry in entries {
+                let name = entry.file_name();
+                if name == "." || name == ".." {
+                    continue;
+                }
+                let rel = if rel_dir.is_empty() {
+                    name
+                } else {
+                    format!("{rel_dir}/{name}")
+                };
+                let file_type = entry.file_type();
+                let metadata = entry.metadata();
+                if file_type.is_dir() {
+                    if skip_dir(&rel) {
+                        continue;
+                    }
+                    if include_dirs {
+                        entries_out.push((format!("{rel}/"), 0, metadata.mtime));
+                    }
+                    pending.push(rel);
+                } else if file_type.is_file() {
+                    entries_out.push((rel, metadata.size.unwrap_or(0), metadata.mtime));
+                }
+                if entries_out.len() >= limit {
+                    return Ok(entries_out);
+                }
+            }
+        }
+        entries_out.sort();
+        Ok(entries_out)
+    }
+}
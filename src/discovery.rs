@@ -0,0 +1,333 @@
+//! Finding the current address of a server whose IP changes, such as a
+//! cloud VM that gets a new public IP on every start.
+//!
+//! `ssh-hub update` runs a server's `resolve_host` command on request. A
+//! `discovery` entry is run by the MCP server itself, when connecting fails
+//! because the address no longer answers, and the new address is written
+//! back to `servers.toml`.
+//!
+//! `ssh-hub add --aws-instance`/`--gcp-instance` describe the instance
+//! with the provider's CLI to fill in the whole entry — address, login user,
+//! and key — and store the instance as the server's `discovery` lookup.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::process::Command as TokioCommand;
+use tokio::time::timeout;
+
+use crate::utils::path::shell_escape;
+
+/// How long a lookup command may run before it is abandoned.
+pub const LOOKUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where to look up a server's current address. Set exactly one of
+/// `command`, `aws_instance`, or `gcp_instance`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DiscoveryConfig {
+    /// Shell command that prints the address on its first line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// EC2 instance ID, looked up with the `aws` CLI.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aws_instance: Option<String>,
+    /// Compute Engine instance name, looked up with `gcloud`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gcp_instance: Option<String>,
+    /// AWS region of `aws_instance` (default: the CLI's configured region).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Zone of `gcp_instance` (default: gcloud's configured zone).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<String>,
+    /// Project of `gcp_instance` (default: gcloud's configured project).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+}
+
+impl DiscoveryConfig {
+    /// Shell command that prints the server's current address.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error unless exactly one lookup source is set.
+    pub fn lookup_command(&self) -> Result<String> {
+        match (&self.command, &self.aws_instance, &self.gcp_instance) {
+            (Some(command), None, None) => Ok(command.clone()),
+            (None, Some(instance), None) => Ok(format!(
+                "aws ec2 describe-instances --instance-ids {} \
+                 --query 'Reservations[0].Instances[0].PublicIpAddress' --output text{}",
+                shell_escape(instance),
+                flag("--region", self.region.as_deref()),
+            )),
+            (None, None, Some(instance)) => Ok(format!(
+                "gcloud compute instances describe {} \
+                 --format='get(networkInterfaces[0].accessConfigs[0].natIP)'{}{}",
+                shell_escape(instance),
+                flag("--zone", self.zone.as_deref()),
+                flag("--project", self.project.as_deref()),
+            )),
+            (None, None, None) => Err(anyhow!(
+                "discovery needs one of command, aws_instance, or gcp_instance"
+            )),
+            _ => Err(anyhow!(
+                "discovery takes only one of command, aws_instance, or gcp_instance"
+            )),
+        }
+    }
+}
+
+/// What the provider's CLI says about an instance.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Instance {
+    /// Public IP address.
+    pub host: String,
+    /// EC2 key pair the instance was launched with.
+    pub key_name: Option<String>,
+    /// EC2 AMI the instance was launched from.
+    pub image_id: Option<String>,
+    /// Compute Engine instance with OS Login enabled, where the login user
+    /// comes from the caller's OS Login profile.
+    pub os_login: bool,
+}
+
+/// Address, login user, and key to store for an instance.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstanceDetails {
+    pub host: String,
+    /// `None` when the user couldn't be told from the instance.
+    pub user: Option<String>,
+    /// Local private key the provider's tooling uses for the instance.
+    pub identity: Option<PathBuf>,
+}
+
+/// Parse `aws ec2 describe-instances --output json`.
+///
+/// # Errors
+///
+/// Returns an error if the JSON lists no instance or the instance has no
+/// public IP.
+pub fn parse_aws_instance(json: &str) -> Result<Instance> {
+    let value: Value = serde_json::from_str(json)?;
+    let instance = &value["Reservations"][0]["Instances"][0];
+    if instance.is_null() {
+        return Err(anyhow!("aws returned no instance"));
+    }
+    let host = instance["PublicIpAddress"]
+        .as_str()
+        .ok_or_else(|| anyhow!("the instance has no public IP (is it running?)"))?;
+    Ok(Instance {
+        host: host.to_string(),
+        key_name: instance["KeyName"].as_str().map(str::to_string),
+        image_id: instance["ImageId"].as_str().map(str::to_string),
+        os_login: false,
+    })
+}
+
+/// Parse `gcloud compute instances describe --format=json`.
+///
+/// # Errors
+///
+/// Returns an error if the instance has no external IP.
+pub fn parse_gcp_instance(json: &str) -> Result<Instance> {
+    let value: Value = serde_json::from_str(json)?;
+    let host = value["networkInterfaces"][0]["accessConfigs"][0]["natIP"]
+        .as_str()
+        .ok_or_else(|| anyhow!("the instance has no external IP (is it running?)"))?;
+    let os_login = value["metadata"]["items"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|item| {
+            item["key"] == "enable-oslogin"
+                && item["value"]
+                    .as_str()
+                    .is_some_and(|v| v.eq_ignore_ascii_case("true"))
+        });
+    Ok(Instance {
+        host: host.to_string(),
+        os_login,
+        ..Instance::default()
+    })
+}
+
+/// The default login user of an AMI, going by its name.
+#[must_use]
+pub fn aws_default_user(image_name: &str) -> Option<&'static str> {
+    let name = image_name.to_lowercase();
+    [
+        ("ubuntu", "ubuntu"),
+        ("debian", "admin"),
+        ("amzn", "ec2-user"),
+        ("al2023", "ec2-user"),
+        ("amazon linux", "ec2-user"),
+        ("rhel", "ec2-user"),
+        ("suse", "ec2-user"),
+        ("centos", "centos"),
+        ("fedora", "fedora"),
+        ("rocky", "rocky"),
+        ("almalinux", "ec2-user"),
+        ("bitnami", "bitnami"),
+    ]
+    .into_iter()
+    .find(|(pattern, _)| name.contains(pattern))
+    .map(|(_, user)| user)
+}
+
+impl DiscoveryConfig {
+    /// Describe the instance named by `aws_instance` or `gcp_instance` with
+    /// the provider's CLI. The user is the AMI's default on AWS, and on
+    /// Compute Engine the OS Login user or, like `gcloud compute ssh`, the
+    /// local user. The key is `~/.ssh/<key pair>.pem` on AWS and gcloud's
+    /// `~/.ssh/google_compute_engine`, when they exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither instance is set, or the CLI fails or
+    /// reports no public address.
+    pub async fn describe_instance(&self) -> Result<InstanceDetails> {
+        let ssh_dir = dirs::home_dir().map(|home| home.join(".ssh"));
+        let existing = |name: String| {
+            ssh_dir
+                .as_ref()
+                .map(|dir| dir.join(name))
+                .filter(|path| path.is_file())
+        };
+        match (&self.aws_instance, &self.gcp_instance) {
+            (Some(instance), None) => {
+                let region = flag("--region", self.region.as_deref());
+                let json = run_command(
+                    "aws",
+                    &format!(
+                        "aws ec2 describe-instances --instance-ids {} --output json{region}",
+                        shell_escape(instance),
+                    ),
+                    LOOKUP_TIMEOUT,
+                )
+                .await?;
+                let instance = parse_aws_instance(&json)?;
+                let mut user = None;
+                if let Some(image) = &instance.image_id {
+                    let name = run_command(
+                        "aws",
+                        &format!(
+                            "aws ec2 describe-images --image-ids {} \
+                             --query 'Images[0].Name' --output text{region}",
+                            shell_escape(image),
+                        ),
+                        LOOKUP_TIMEOUT,
+                    )
+                    .await
+                    .unwrap_or_default();
+                    user = aws_default_user(&name).map(str::to_string);
+                }
+                let identity = instance.key_name.as_ref().and_then(|key| {
+                    existing(format!("{key}.pem")).or_else(|| existing(key.clone()))
+                });
+                Ok(InstanceDetails {
+                    host: instance.host,
+                    user,
+                    identity,
+                })
+            }
+            (None, Some(instance)) => {
+                let scope = format!(
+                    "{}{}",
+                    flag("--zone", self.zone.as_deref()),
+                    flag("--project", self.project.as_deref()),
+                );
+                let json = run_command(
+                    "gcloud",
+                    &format!(
+                        "gcloud compute instances describe {} --format=json{scope}",
+                        shell_escape(instance),
+                    ),
+                    LOOKUP_TIMEOUT,
+                )
+                .await?;
+                let instance = parse_gcp_instance(&json)?;
+                let user = if instance.os_login {
+                    run_command(
+                        "gcloud",
+                        &format!(
+                            "gcloud compute os-login describe-profile \
+                             --format='value(posixAccounts[0].username)'{}",
+                            flag("--project", self.project.as_deref()),
+                        ),
+                        LOOKUP_TIMEOUT,
+                    )
+                    .await
+                    .ok()
+                } else {
+                    std::env::var("USER").ok()
+                };
+                Ok(InstanceDetails {
+                    host: instance.host,
+                    user: user.map(|u| u.trim().to_string()).filter(|u| !u.is_empty()),
+                    identity: existing("google_compute_engine".to_string()),
+                })
+            }
+            _ => Err(anyhow!("set exactly one of aws_instance or gcp_instance")),
+        }
+    }
+}
+
+/// ` <name> <value>` for an optional CLI flag, or nothing.
+fn flag(name: &str, value: Option<&str>) -> String {
+    value.map_or_else(String::new, |v| format!(" {name} {}", shell_escape(v)))
+}
+
+/// Run a lookup command with `sh -c` and return the first line of its
+/// output, trimmed. `label` names the setting in error messages.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The shell process cannot be spawned
+/// - The command exceeds `limit`
+/// - The process exits with a non-zero status
+/// - stdout is empty after trimming, or is the `None` the `aws` CLI prints
+///   for an instance without a public IP
+pub async fn run_lookup(label: &str, command: &str, limit: Duration) -> Result<String> {
+    let stdout = run_command(label, command, limit).await?;
+    let host = stdout
+        .lines()
+        .next()
+        .map_or_else(String::new, |line| line.trim().to_string());
+
+    if host.is_empty() || host == "None" {
+        return Err(anyhow!("{label} command produced empty output"));
+    }
+
+    Ok(host)
+}
+
+/// Run `command` with `sh -c` and return its stdout.
+async fn run_command(label: &str, command: &str, limit: Duration) -> Result<String> {
+    let child = TokioCommand::new("sh")
+        .args(["-c", command])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow!("Failed to spawn {label} command: {e}"))?;
+
+    let output = timeout(limit, child.wait_with_output())
+        .await
+        .map_err(|_| anyhow!("{label} timed out after {}s", limit.as_secs()))?
+        .map_err(|e| anyhow!("{label} command failed: {e}"))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(
+            "{label} exited with {}: {}",
+            output.status,
+            stderr.trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
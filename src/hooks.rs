@@ -0,0 +1,352 @@
+//! Hooks: commands run around tool calls on a server.
+//!
+//! ```toml
+//! [[servers.web1.hooks.pre_sync_push]]
+//! local = "npm run build"            # on the machine running ssh-hub
+//!
+//! [[servers.web1.hooks.post_sync_push]]
+//! remote = "sudo systemctl reload app"
+//! timeout_secs = 30                  # default 300
+//! ```
+//!
+//! Hooks exist for `exec` (`remote_bash`), `sync_push`, and `sync_pull`.
+//! `pre_*` hooks run in order before the tool, and the first one that fails
+//! stops the call. `post_*` hooks run in order after the tool succeeds, and
+//! a failure is reported without failing the call. Local hooks run with
+//! `sh -c` in the MCP server's working directory, remote ones in the
+//! server's `remote_path`. Every hook that ran is listed in the tool output.
+
+use std::fmt::Write;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::connection::SshConnection;
+use crate::tools::{tool_error, ErrorCode};
+
+/// How long a hook may run unless it sets `timeout_secs` (5 minutes).
+pub const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 300;
+
+/// Most output kept from a failed hook, from its end.
+const FAILED_OUTPUT_LIMIT: usize = 2000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HooksConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_exec: Vec<Hook>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_exec: Vec<Hook>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_sync_push: Vec<Hook>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_sync_push: Vec<Hook>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_sync_pull: Vec<Hook>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_sync_pull: Vec<Hook>,
+}
+
+impl HooksConfig {
+    /// Hooks to run before the tool of `stage`.
+    #[must_use]
+    pub fn pre(&self, stage: HookStage) -> &[Hook] {
+        match stage {
+            HookStage::Exec => &self.pre_exec,
+            HookStage::SyncPush => &self.pre_sync_push,
+            HookStage::SyncPull => &self.pre_sync_pull,
+        }
+    }
+
+    /// Hooks to run after the tool of `stage` succeeds.
+    #[must_use]
+    pub fn post(&self, stage: HookStage) -> &[Hook] {
+        match stage {
+            HookStage::Exec => &self.post_exec,
+            HookStage::SyncPush => &self.post_sync_push,
+            HookStage::SyncPull => &self.post_sync_pull,
+        }
+    }
+}
+
+/// One hook command. Exactly one of `local` and `remote` is set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Hook {
+    /// Command run on the machine running ssh-hub.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local: Option<String>,
+    /// Command run on the server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+/// Where a hook runs, and its command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookTarget<'a> {
+    Local(&'a str),
+    Remote(&'a str),
+}
+
+impl Hook {
+    /// Where the hook runs.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message when neither or both of `local` and `remote` are set.
+    pub fn target(&self) -> Result<HookTarget<'_>, String> {
+        match (&self.local, &self.remote) {
+            (Some(command), None) => Ok(HookTarget::Local(command)),
+            (None, Some(command)) => Ok(HookTarget::Remote(command)),
+            _ => Err("misconfigured hook: set exactly one of local and remote".to_string()),
+        }
+    }
+
+    #[must_use]
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.unwrap_or(DEFAULT_HOOK_TIMEOUT_SECS))
+    }
+}
+
+/// The tools hooks can run around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    /// `remote_bash`
+    Exec,
+    SyncPush,
+    SyncPull,
+}
+
+impl HookStage {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Exec => "exec",
+            Self::SyncPush => "sync_push",
+            Self::SyncPull => "sync_pull",
+        }
+    }
+
+    /// The tool the stage runs around.
+    #[must_use]
+    pub fn tool(self) -> &'static str {
+        match self {
+            Self::Exec => "remote_bash",
+            Self::SyncPush => "sync_push",
+            Self::SyncPull => "sync_pull",
+        }
+    }
+}
+
+/// What one hook did, as listed under `hooks` in tool output.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct HookRun {
+    /// `pre_<stage>` or `post_<stage>`.
+    pub hook: String,
+    /// `local` or `remote`.
+    pub location: &'static str,
+    pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub elapsed_ms: u64,
+    /// End of the combined stdout and stderr, for failed hooks.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub output: String,
+    /// Why the hook didn't run to completion: a timeout, a spawn or
+    /// connection failure, a misconfigured hook.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Code of the tool error for [`error`](Self::error).
+    #[serde(skip)]
+    pub error_code: Option<ErrorCode>,
+}
+
+impl HookRun {
+    #[must_use]
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none() && self.exit_code == Some(0)
+    }
+
+    /// A hook that couldn't run at all, for the reason `code`.
+    #[must_use]
+    pub fn failed(
+        hook: &str,
+        location: &'static str,
+        command: &str,
+        code: ErrorCode,
+        error: String,
+    ) -> Self {
+        Self {
+            hook: hook.to_string(),
+            location,
+            command: command.to_string(),
+            exit_code: None,
+            elapsed_ms: 0,
+            output: String::new(),
+            error: Some(error),
+            error_code: Some(code),
+        }
+    }
+
+    fn finished(
+        hook: &str,
+        location: &'static str,
+        command: &str,
+        exit_code: i32,
+        elapsed: Duration,
+        stdout: &str,
+        stderr: &str,
+    ) -> Self {
+        let output = if exit_code == 0 {
+            String::new()
+        } else {
+            output_tail(&format!("{stdout}{stderr}"))
+        };
+        Self {
+            hook: hook.to_string(),
+            location,
+            command: command.to_string(),
+            exit_code: Some(exit_code),
+            elapsed_ms: u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX),
+            output,
+            error: None,
+            error_code: None,
+        }
+    }
+
+    /// The error returned instead of the tool's output when this `pre_*`
+    /// hook failed.
+    #[must_use]
+    pub fn refusal(&self, stage: HookStage) -> String {
+        let why = match (&self.error, self.exit_code) {
+            (Some(error), _) => error.clone(),
+            (None, Some(code)) => format!("exit code {code}"),
+            (None, None) => "unknown failure".to_string(),
+        };
+        let hook = if self.command.is_empty() {
+            format!("{} hook", self.hook)
+        } else {
+            format!("{} hook `{}`", self.hook, self.command)
+        };
+        let mut message = format!("the {hook} failed ({why}), so {} did not run", stage.tool());
+        if !self.output.is_empty() {
+            message.push_str(":\n");
+            message.push_str(&self.output);
+        }
+        tool_error(self.error_code.unwrap_or(ErrorCode::Failed), message)
+    }
+}
+
+/// Run a local hook `command` with `sh -c`.
+pub async fn run_local(hook: &str, command: &str, timeout: Duration) -> HookRun {
+    let started = Instant::now();
+    let child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+    let child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            return HookRun::failed(
+                hook,
+                "local",
+                command,
+                ErrorCode::Failed,
+                format!("failed to start: {e}"),
+            )
+        }
+    };
+    match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(Ok(output)) => HookRun::finished(
+            hook,
+            "local",
+            command,
+            output.status.code().unwrap_or(-1),
+            started.elapsed(),
+            &String::from_utf8_lossy(&output.stdout),
+            &String::from_utf8_lossy(&output.stderr),
+        ),
+        Ok(Err(e)) => HookRun::failed(hook, "local", command, ErrorCode::Failed, e.to_string()),
+        Err(_) => HookRun::failed(
+            hook,
+            "local",
+            command,
+            ErrorCode::Timeout,
+            format!("timed out after {}s", timeout.as_secs()),
+        ),
+    }
+}
+
+/// Run a remote hook `command` on `conn`, in the server's `remote_path`.
+pub async fn run_remote(
+    conn: &SshConnection,
+    hook: &str,
+    command: &str,
+    timeout: Duration,
+) -> HookRun {
+    let started = Instant::now();
+    let timeout_ms = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+    match conn.exec(command, Some(timeout_ms)).await {
+        Ok(result) => HookRun::finished(
+            hook,
+            "remote",
+            command,
+            result.exit_code,
+            started.elapsed(),
+            &result.stdout,
+            &result.stderr,
+        ),
+        Err(e) => HookRun::failed(hook, "remote", command, ErrorCode::of(&e), format!("{e:#}")),
+    }
+}
+
+/// Add the hooks that ran to a tool's output: as a `hooks` field when the
+/// output is a JSON object, otherwise as lines after it.
+#[must_use]
+pub fn attach_report(output: String, runs: &[HookRun]) -> String {
+    if runs.is_empty() {
+        return output;
+    }
+    if let Ok(serde_json::Value::Object(mut object)) =
+        serde_json::from_str::<serde_json::Value>(&output)
+    {
+        if let Ok(runs) = serde_json::to_value(runs) {
+            object.insert("hooks".to_string(), runs);
+            if let Ok(json) = serde_json::to_string_pretty(&object) {
+                return json;
+            }
+        }
+    }
+    let mut output = output;
+    output.push_str("\n\nHooks:");
+    for run in runs {
+        let outcome = match (&run.error, run.exit_code) {
+            (Some(error), _) => error.clone(),
+            (None, Some(code)) => format!("exit {code} in {}ms", run.elapsed_ms),
+            (None, None) => "did not finish".to_string(),
+        };
+        let _ = write!(
+            output,
+            "\n  {} ({}) `{}`: {outcome}",
+            run.hook, run.location, run.command
+        );
+    }
+    output
+}
+
+fn output_tail(output: &str) -> String {
+    let output = output.trim_end();
+    if output.len() <= FAILED_OUTPUT_LIMIT {
+        return output.to_string();
+    }
+    let mut start = output.len() - FAILED_OUTPUT_LIMIT;
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("...{}", &output[start..])
+}
@@ -0,0 +1,325 @@
+//! Per-server sudo passwords and SSH key passphrases in the OS keychain.
+//!
+//! macOS uses the login keychain through `security`, Linux the Secret Service
+//! (GNOME Keyring, `KWallet`) through `secret-tool`. Passwords are passed to
+//! those tools on stdin, never as arguments, so they don't show up in `ps`.
+//! Other platforms have no supported keychain.
+//!
+//! Under a `--profile`, secrets are stored under services ending in
+//! `-<profile>`, apart from those of other profiles.
+
+use std::path::Path;
+use std::process::Stdio;
+
+use anyhow::{anyhow, bail, Context, Result};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::profile;
+
+/// Service name the sudo passwords are stored under.
+pub const SERVICE: &str = "ssh-hub-sudo";
+
+/// Service name the key passphrases are stored under.
+pub const KEY_SERVICE: &str = "ssh-hub-key";
+
+/// What a stored secret is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Secret {
+    /// A server's sudo password, stored under the server name.
+    SudoPassword,
+    /// The passphrase of an encrypted private key, stored under the key's path.
+    KeyPassphrase,
+}
+
+impl Secret {
+    /// Keychain service for this kind of secret under `profile`.
+    #[must_use]
+    pub fn service_name(self, profile: Option<&str>) -> String {
+        let base = match self {
+            Self::SudoPassword => SERVICE,
+            Self::KeyPassphrase => KEY_SERVICE,
+        };
+        profile::scoped(base, profile)
+    }
+
+    fn service(self) -> String {
+        self.service_name(profile::current())
+    }
+
+    /// Secret Service attribute naming what the secret belongs to.
+    fn attribute(self) -> &'static str {
+        match self {
+            Self::SudoPassword => "server",
+            Self::KeyPassphrase => "key",
+        }
+    }
+
+    fn noun(self) -> &'static str {
+        match self {
+            Self::SudoPassword => "sudo password",
+            Self::KeyPassphrase => "key passphrase",
+        }
+    }
+
+    fn label(self, account: &str) -> String {
+        match self {
+            Self::SudoPassword => format!("ssh-hub sudo password for {account}"),
+            Self::KeyPassphrase => format!("ssh-hub passphrase for {account}"),
+        }
+    }
+}
+
+/// The keychain tool available on this platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// macOS `security`.
+    Security,
+    /// Secret Service `secret-tool` (libsecret).
+    SecretTool,
+}
+
+impl Backend {
+    /// The backend for the platform ssh-hub was built for.
+    #[must_use]
+    pub fn current() -> Option<Self> {
+        if cfg!(target_os = "macos") {
+            Some(Self::Security)
+        } else if cfg!(all(unix, not(target_os = "macos"))) {
+            Some(Self::SecretTool)
+        } else {
+            None
+        }
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Self::Security => "security",
+            Self::SecretTool => "secret-tool",
+        }
+    }
+}
+
+/// Arguments and stdin that store `value` as the `secret` of `account`.
+#[must_use]
+pub fn store_invocation(
+    backend: Backend,
+    secret: Secret,
+    account: &str,
+    value: &str,
+) -> (Vec<String>, String) {
+    match backend {
+        // `security -i` reads commands from stdin, which keeps the password
+        // out of the argument list.
+        Backend::Security => (
+            vec!["-i".to_string()],
+            format!(
+                "add-generic-password -U -s {} -a {} -w {}\n",
+                security_quote(&secret.service()),
+                security_quote(account),
+                security_quote(value),
+            ),
+        ),
+        Backend::SecretTool => (
+            [
+                "store",
+                "--label",
+                &secret.label(account),
+                "service",
+                &secret.service(),
+                secret.attribute(),
+                account,
+            ]
+            .map(String::from)
+            .to_vec(),
+            value.to_string(),
+        ),
+    }
+}
+
+/// Arguments that print the `secret` of `account`.
+#[must_use]
+pub fn lookup_args(backend: Backend, secret: Secret, account: &str) -> Vec<String> {
+    let service = &secret.service();
+    match backend {
+        Backend::Security => ["find-generic-password", "-s", service, "-a", account, "-w"]
+            .map(String::from)
+            .to_vec(),
+        Backend::SecretTool => ["lookup", "service", service, secret.attribute(), account]
+            .map(String::from)
+            .to_vec(),
+    }
+}
+
+/// Arguments that delete the `secret` of `account`.
+#[must_use]
+pub fn delete_args(backend: Backend, secret: Secret, account: &str) -> Vec<String> {
+    let service = &secret.service();
+    match backend {
+        Backend::Security => ["delete-generic-password", "-s", service, "-a", account]
+            .map(String::from)
+            .to_vec(),
+        Backend::SecretTool => ["clear", "service", service, secret.attribute(), account]
+            .map(String::from)
+            .to_vec(),
+    }
+}
+
+/// Quote a word for `security -i`, which splits its input like a shell.
+#[must_use]
+pub fn security_quote(word: &str) -> String {
+    let mut quoted = String::with_capacity(word.len() + 2);
+    quoted.push('"');
+    for c in word.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Store `password` as the sudo password for `server`, replacing any
+/// previous one.
+///
+/// # Errors
+///
+/// Returns an error if there is no keychain on this platform or the keychain
+/// tool fails.
+pub async fn store_sudo_password(server: &str, password: &str) -> Result<()> {
+    store(Secret::SudoPassword, server, password).await
+}
+
+/// The stored sudo password for `server`, or `None` if there isn't one.
+///
+/// # Errors
+///
+/// Returns an error if there is no keychain on this platform or the keychain
+/// tool can't be run.
+pub async fn sudo_password(server: &str) -> Result<Option<String>> {
+    lookup(Secret::SudoPassword, server).await
+}
+
+/// Delete the stored sudo password for `server`. Returns whether there was one.
+///
+/// # Errors
+///
+/// Returns an error if there is no keychain on this platform or the keychain
+/// tool can't be run.
+pub async fn delete_sudo_password(server: &str) -> Result<bool> {
+    delete(Secret::SudoPassword, server).await
+}
+
+/// Store `passphrase` for the private key at `key_path`, replacing any
+/// previous one.
+///
+/// # Errors
+///
+/// Returns an error if there is no keychain on this platform or the keychain
+/// tool fails.
+pub async fn store_key_passphrase(key_path: &Path, passphrase: &str) -> Result<()> {
+    store(Secret::KeyPassphrase, &key_account(key_path), passphrase).await
+}
+
+/// The stored passphrase for the private key at `key_path`, or `None` if
+/// there isn't one.
+///
+/// # Errors
+///
+/// Returns an error if there is no keychain on this platform or the keychain
+/// tool can't be run.
+pub async fn key_passphrase(key_path: &Path) -> Result<Option<String>> {
+    lookup(Secret::KeyPassphrase, &key_account(key_path)).await
+}
+
+/// Delete the stored passphrase for the private key at `key_path`. Returns
+/// whether there was one.
+///
+/// # Errors
+///
+/// Returns an error if there is no keychain on this platform or the keychain
+/// tool can't be run.
+pub async fn delete_key_passphrase(key_path: &Path) -> Result<bool> {
+    delete(Secret::KeyPassphrase, &key_account(key_path)).await
+}
+
+/// Key passphrases are stored under the key file's path.
+fn key_account(key_path: &Path) -> String {
+    key_path.display().to_string()
+}
+
+async fn store(secret: Secret, account: &str, value: &str) -> Result<()> {
+    let backend = backend()?;
+    let (args, stdin) = store_invocation(backend, secret, account, value);
+    let output = run(backend, &args, Some(&stdin)).await?;
+    if !output.status.success() {
+        bail!(
+            "{} failed to store the {}: {}",
+            backend.program(),
+            secret.noun(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+async fn lookup(secret: Secret, account: &str) -> Result<Option<String>> {
+    let backend = backend()?;
+    let output = run(backend, &lookup_args(backend, secret, account), None).await?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let value = String::from_utf8(output.stdout).map_err(|_| {
+        anyhow!(
+            "The stored {} for '{account}' is not valid UTF-8",
+            secret.noun()
+        )
+    })?;
+    // `security -w` ends the value with a newline; `secret-tool` doesn't.
+    let value = value.strip_suffix('\n').unwrap_or(&value);
+    Ok(Some(value.to_string()))
+}
+
+async fn delete(secret: Secret, account: &str) -> Result<bool> {
+    if lookup(secret, account).await?.is_none() {
+        return Ok(false);
+    }
+    let backend = backend()?;
+    let output = run(backend, &delete_args(backend, secret, account), None).await?;
+    if !output.status.success() {
+        bail!(
+            "{} failed to delete the {}: {}",
+            backend.program(),
+            secret.noun(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(true)
+}
+
+fn backend() -> Result<Backend> {
+    Backend::current().ok_or_else(|| anyhow!("No supported OS keychain on this platform"))
+}
+
+async fn run(
+    backend: Backend,
+    args: &[String],
+    stdin: Option<&str>,
+) -> Result<std::process::Output> {
+    let mut child = Command::new(backend.program())
+        .args(args)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}; is it installed?", backend.program()))?;
+    if let (Some(data), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(data.as_bytes()).await?;
+    }
+    Ok(child.wait_with_output().await?)
+}
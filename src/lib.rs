@@ -1,7 +1,18 @@
+#[cfg(unix)]
+pub mod broker;
+pub mod budget;
 pub mod cli;
 pub mod connection;
+pub mod discovery;
+pub mod hooks;
+pub mod keychain;
+pub mod maintenance;
 pub mod metadata;
+pub mod metrics;
+pub mod profile;
+pub mod rate_limit;
 pub mod server;
 pub mod server_registry;
+pub mod timeout;
 pub mod tools;
 pub mod utils;
@@ -1,42 +1,135 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
 use anyhow::Result;
 use clap::Parser;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
-use ssh_hub::cli::Cli;
+use ssh_hub::cli::{Cli, Command};
 use ssh_hub::server::RemoteSessionServer;
 use ssh_hub::server_registry::ServerRegistry;
+use ssh_hub::utils::glyphs;
+use ssh_hub::utils::log_file::RollingFile;
 
-fn init_logging(verbose: bool) {
+fn init_logging(verbose: bool, log_file: Option<RollingFile>) {
     let filter = if verbose {
         EnvFilter::new("debug")
     } else {
         EnvFilter::new("info")
     };
 
+    let file_layer = log_file.map(|file| {
+        tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(Mutex::new(file))
+    });
+
     tracing_subscriber::registry()
         .with(filter)
         .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
         .init();
 }
 
+/// Log file base path and retention: `--log-file` wins, then the MCP server
+/// falls back to the `[logging]` config section. CLI commands only log to a
+/// file when asked.
+fn log_file_target(cli: &Cli, config: Option<&ServerRegistry>) -> Option<(PathBuf, usize)> {
+    let logging = config.and_then(|c| c.logging.clone()).unwrap_or_default();
+    match (&cli.log_file, config) {
+        (Some(path), _) => Some((path.clone(), logging.keep_days())),
+        (None, Some(_)) => Some((logging.path()?, logging.keep_days())),
+        (None, None) => None,
+    }
+}
+
+/// Serve through the shared broker: relay to the one already running, or
+/// become it. Falls back to serving this client alone if the socket is unusable.
+#[cfg(unix)]
+async fn run_brokered(server: RemoteSessionServer) -> Result<()> {
+    use ssh_hub::broker::{self, Role};
+
+    let Some(path) = broker::socket_path() else {
+        tracing::warn!("No runtime directory for the broker socket; serving this client alone");
+        return server.run().await;
+    };
+    match broker::claim(&path).await {
+        Ok(Role::Client(stream)) => {
+            tracing::info!("Relaying to the ssh-hub broker at {}", path.display());
+            Ok(broker::proxy(stream).await?)
+        }
+        Ok(Role::Owner(listener)) => {
+            tracing::info!(
+                "Listening for other ssh-hub processes on {}",
+                path.display()
+            );
+            server.run_shared(listener).await
+        }
+        Err(e) => {
+            tracing::warn!(
+                "Broker socket {} unavailable ({e}); serving this client alone",
+                path.display()
+            );
+            server.run().await
+        }
+    }
+}
+
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
-    init_logging(cli.verbose);
+    if let Some(name) = &cli.profile {
+        ssh_hub::profile::set(name)?;
+    }
+    if let Some(path) = &cli.config {
+        ServerRegistry::set_config_path(path);
+    }
+    let config = cli.command.is_none().then(ServerRegistry::load);
+
+    let mut log_error = None;
+    let log_file = log_file_target(&cli, config.as_ref().and_then(|c| c.as_ref().ok())).and_then(
+        |(path, keep)| {
+            RollingFile::open(path.clone(), keep)
+                .map_err(|e| log_error = Some(format!("{}: {e}", path.display())))
+                .ok()
+        },
+    );
+    let log_path = log_file.as_ref().map(RollingFile::current_path);
+
+    // The dashboard owns the terminal; log lines on stderr would corrupt it.
+    if !matches!(cli.command, Some(Command::Tui)) {
+        init_logging(cli.verbose, log_file);
+    }
+    if let Some(e) = log_error {
+        tracing::warn!("Cannot open log file {e}; logging to stderr only");
+    }
+    if cli.ascii {
+        glyphs::set_ascii_mode(true);
+    }
 
     if let Some(command) = cli.command {
         ssh_hub::cli::run(command).await
     } else {
         tracing::info!("Starting ssh-hub MCP server");
+        if let Some(path) = log_path {
+            tracing::debug!("Logging to {}", path.display());
+        }
 
-        let config = ServerRegistry::load().unwrap_or_else(|e| {
-            tracing::warn!("Failed to load config, starting with empty config: {e}");
-            ServerRegistry::default()
-        });
+        let config = config
+            .unwrap_or_else(ServerRegistry::load)
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to load config, starting with empty config: {e}");
+                ServerRegistry::default()
+            });
 
         tracing::debug!("Loaded {} configured servers", config.servers.len());
 
+        let shared = config.broker.as_ref().is_some_and(|b| b.enabled);
         let server = RemoteSessionServer::new(config);
+        #[cfg(unix)]
+        if shared {
+            return run_brokered(server).await;
+        }
         server.run().await
     }
 }
@@ -0,0 +1,354 @@
+//! Maintenance windows: when write-class tools may change a server.
+//!
+//! ```toml
+//! [servers.prod.maintenance]
+//! timezone = "+01:00"          # UTC (default) or a fixed offset
+//! outside_window = "confirm"   # "refuse" (default) or "confirm"
+//!
+//! [[servers.prod.maintenance.windows]]
+//! start = "0 22 * * mon-fri"   # cron: minute hour day-of-month month day-of-week
+//! duration = "6h"
+//! ```
+//!
+//! A window opens at every minute matching `start` and stays open for
+//! `duration`. Outside all windows, write-class tools are refused; with
+//! `outside_window = "confirm"` they run when the call carries an
+//! `override_window` reason, which is logged.
+
+use serde::{Deserialize, Serialize};
+
+use crate::tools::{ErrorCode, ToolError};
+use crate::utils::log_file::civil_date;
+
+/// Longest allowed window (one week).
+const MAX_DURATION_MINUTES: u64 = 7 * 24 * 60;
+
+/// How far ahead to look for the next opening (31 days, in minutes).
+const NEXT_OPENING_SEARCH_MINUTES: u64 = 31 * 24 * 60;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MaintenanceConfig {
+    #[serde(default)]
+    pub windows: Vec<ChangeWindow>,
+    /// `UTC` or a fixed offset such as `+01:00` / `-05:30`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub outside_window: OutsideWindow,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChangeWindow {
+    /// Cron expression for when the window opens.
+    pub start: String,
+    /// How long it stays open, e.g. `6h`, `90m`, `1h30m`, `2d`.
+    pub duration: String,
+}
+
+/// What happens to a write-class tool call outside every window.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutsideWindow {
+    #[default]
+    Refuse,
+    Confirm,
+}
+
+/// A parsed [`MaintenanceConfig`].
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    windows: Vec<(Cron, u64)>,
+    offset_secs: i64,
+    offset_label: String,
+}
+
+impl Schedule {
+    /// # Errors
+    ///
+    /// Returns a description of the first invalid window or timezone.
+    pub fn parse(config: &MaintenanceConfig) -> Result<Self, String> {
+        let (offset_secs, offset_label) = parse_timezone(config.timezone.as_deref())?;
+        let windows = config
+            .windows
+            .iter()
+            .map(|w| {
+                let cron =
+                    Cron::parse(&w.start).map_err(|e| format!("window '{}': {e}", w.start))?;
+                let minutes = parse_duration(&w.duration)
+                    .map_err(|e| format!("window '{}': {e}", w.start))?;
+                Ok((cron, minutes))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            windows,
+            offset_secs,
+            offset_label,
+        })
+    }
+
+    /// Whether a window is open at `now` (Unix seconds).
+    #[must_use]
+    pub fn is_open(&self, now: u64) -> bool {
+        let minute = self.local_minute(now);
+        self.windows.iter().any(|(cron, duration)| {
+            (0..*duration).any(|back| minute >= back && cron.matches(minute - back))
+        })
+    }
+
+    /// Local start time of the next window opening after `now`, if one
+    /// falls within the next 31 days, formatted as `YYYY-MM-DD HH:MM <tz>`.
+    #[must_use]
+    pub fn next_opening(&self, now: u64) -> Option<String> {
+        let minute = self.local_minute(now);
+        (1..=NEXT_OPENING_SEARCH_MINUTES)
+            .map(|ahead| minute + ahead)
+            .find(|m| self.windows.iter().any(|(cron, _)| cron.matches(*m)))
+            .map(|m| format!("{} {}", format_minute(m), self.offset_label))
+    }
+
+    fn local_minute(&self, now: u64) -> u64 {
+        let local = i64::try_from(now).unwrap_or(i64::MAX) + self.offset_secs;
+        u64::try_from(local.max(0)).unwrap_or(0) / 60
+    }
+}
+
+/// Check whether a write-class tool may change `server` at `now`.
+///
+/// Servers without a maintenance config are always open. An invalid config
+/// refuses every change until it's fixed.
+///
+/// # Errors
+///
+/// Returns the tool error to report when the change is not allowed.
+pub fn enforce(
+    server: &str,
+    config: Option<&MaintenanceConfig>,
+    override_reason: Option<&str>,
+    now: u64,
+) -> Result<(), ToolError> {
+    let Some(config) = config else {
+        return Ok(());
+    };
+    let schedule = Schedule::parse(config).map_err(|e| {
+        ToolError::new(
+            ErrorCode::Failed,
+            format!("maintenance config for '{server}' is invalid ({e}); refusing changes"),
+        )
+    })?;
+    if schedule.is_open(now) {
+        return Ok(());
+    }
+
+    let next = schedule
+        .next_opening(now)
+        .map_or_else(String::new, |t| format!(" The next window opens {t}."));
+    let reason = override_reason.map(str::trim).filter(|r| !r.is_empty());
+    match (config.outside_window, reason) {
+        (OutsideWindow::Confirm, Some(reason)) => {
+            tracing::warn!("Maintenance window override for '{server}': {reason}");
+            Ok(())
+        }
+        (OutsideWindow::Confirm, None) => Err(ToolError::new(
+            ErrorCode::OutsideWindow,
+            format!(
+                "'{server}' is outside its maintenance window.{next} To change it anyway, repeat the call with 'override_window' set to the reason for the change."
+            ),
+        )),
+        (OutsideWindow::Refuse, _) => Err(ToolError::new(
+            ErrorCode::OutsideWindow,
+            format!(
+                "'{server}' is outside its maintenance window and changes are refused until it opens.{next}"
+            ),
+        )),
+    }
+}
+
+/// `YYYY-MM-DD HH:MM` for minutes since the epoch.
+fn format_minute(minute: u64) -> String {
+    let (y, m, d) = civil_date(minute / 1_440);
+    let of_day = minute % 1_440;
+    format!("{y:04}-{m:02}-{d:02} {:02}:{:02}", of_day / 60, of_day % 60)
+}
+
+fn parse_timezone(tz: Option<&str>) -> Result<(i64, String), String> {
+    let tz = tz.map_or("UTC", str::trim);
+    if tz.eq_ignore_ascii_case("utc") || tz == "Z" {
+        return Ok((0, "UTC".to_string()));
+    }
+    let unsupported =
+        || format!("unsupported timezone '{tz}': use UTC or a fixed offset such as +01:00");
+    let (sign, rest) = match tz.as_bytes().first() {
+        Some(b'+') => (1, &tz[1..]),
+        Some(b'-') => (-1, &tz[1..]),
+        _ => return Err(unsupported()),
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let hours: i64 = hours.parse().map_err(|_| unsupported())?;
+    let minutes: i64 = minutes.parse().map_err(|_| unsupported())?;
+    if hours > 14 || minutes > 59 {
+        return Err(unsupported());
+    }
+    Ok((
+        sign * (hours * 3_600 + minutes * 60),
+        format!(
+            "{}{hours:02}:{minutes:02}",
+            if sign < 0 { '-' } else { '+' }
+        ),
+    ))
+}
+
+/// Parse a duration such as `6h`, `90m`, `1h30m` or `2d` into minutes.
+///
+/// # Errors
+///
+/// Returns a description of a malformed, zero, or over-long duration.
+pub fn parse_duration(s: &str) -> Result<u64, String> {
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in s.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'm' => 1,
+            'h' => 60,
+            'd' => 1_440,
+            _ => return Err(format!("invalid duration '{s}': use units d, h, m")),
+        };
+        let n: u64 = number
+            .parse()
+            .map_err(|_| format!("invalid duration '{s}'"))?;
+        total = total.saturating_add(n.saturating_mul(unit));
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(format!("invalid duration '{s}': missing unit (d, h, m)"));
+    }
+    if total == 0 || total > MAX_DURATION_MINUTES {
+        return Err(format!("duration '{s}' must be between 1m and 7d"));
+    }
+    Ok(total)
+}
+
+/// A five-field cron expression: minute, hour, day of month, month, day of
+/// week (0 or 7 is Sunday). Fields accept `*`, numbers, ranges, steps
+/// (`*/15`, `1-10/2`), lists, and three-letter month and weekday names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+impl Cron {
+    /// # Errors
+    ///
+    /// Returns a description of the first invalid field.
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "expected 5 cron fields (minute hour day month weekday), got {}",
+                fields.len()
+            ));
+        };
+        let weekdays = parse_field(weekday, 0, 7, &WEEKDAYS, 0)?;
+        // 7 is Sunday too.
+        let weekdays = (weekdays | weekdays >> 7) & 0x7f;
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, &[], 0)?,
+            hours: parse_field(hour, 0, 23, &[], 0)?,
+            days: parse_field(day, 1, 31, &[], 0)?,
+            months: parse_field(month, 1, 12, &MONTHS, 1)?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    /// Whether the minute `minute` (minutes since the epoch, local time) matches.
+    #[must_use]
+    pub fn matches(&self, minute: u64) -> bool {
+        let days = minute / 1_440;
+        let of_day = minute % 1_440;
+        let (_, month, day) = civil_date(days);
+        // 1970-01-01 was a Thursday.
+        let weekday = (days + 4) % 7;
+        let bit = |set: u64, n: u64| set & (1 << n) != 0;
+        // Like cron: when both day fields are restricted, either may match.
+        let day_ok = match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (false, true) => bit(self.days, day),
+            (true, false) => bit(self.weekdays, weekday),
+            (false, false) => bit(self.days, day) || bit(self.weekdays, weekday),
+        };
+        bit(self.minutes, of_day % 60)
+            && bit(self.hours, of_day / 60)
+            && bit(self.months, month)
+            && day_ok
+    }
+}
+
+/// Parse one cron field into a bit set. `names[i]` stands for `name_base + i`.
+fn parse_field(
+    field: &str,
+    min: u64,
+    max: u64,
+    names: &[&str],
+    name_base: u64,
+) -> Result<u64, String> {
+    let value = |s: &str| -> Result<u64, String> {
+        let lower = s.to_ascii_lowercase();
+        let n = match names.iter().position(|n| *n == lower) {
+            Some(i) => i as u64 + name_base,
+            None => s
+                .parse()
+                .map_err(|_| format!("invalid value '{s}' in '{field}'"))?,
+        };
+        if (min..=max).contains(&n) {
+            Ok(n)
+        } else {
+            Err(format!("'{s}' in '{field}' is outside {min}-{max}"))
+        }
+    };
+
+    let mut set = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u64 = step
+                    .parse()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("invalid step in '{field}'"))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else if let Some((lo, hi)) = range.split_once('-') {
+            (value(lo)?, value(hi)?)
+        } else {
+            let n = value(range)?;
+            (n, if step > 1 { max } else { n })
+        };
+        if lo > hi {
+            return Err(format!("range '{range}' in '{field}' is reversed"));
+        }
+        for n in (lo..=hi).step_by(usize::try_from(step).unwrap_or(usize::MAX)) {
+            set |= 1 << n;
+        }
+    }
+    Ok(set)
+}
@@ -3,7 +3,9 @@ use std::time::SystemTime;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+use crate::connection::resolve::address_family;
 use crate::connection::SshConnection;
+use crate::server_registry::RemoteShell;
 
 const METADATA_TIMEOUT_MS: u64 = 15_000;
 
@@ -18,8 +20,22 @@ pub struct SystemMetadata {
     pub arch: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub shell: Option<String>,
+    /// First line of `$SHELL --version`, for bash, zsh, fish, and PowerShell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell_version: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub package_manager: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// Seconds the server had been up at `collected_at`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uptime_seconds: Option<u64>,
+    /// Default file mode mask of the login shell, e.g. `0022`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub umask: Option<String>,
+    /// `ipv4` or `ipv6`: the address family the last connection used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_family: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub collected_at: Option<u64>,
 }
@@ -59,77 +75,208 @@ impl SystemMetadata {
     }
 }
 
-/// POSIX shell snippet that prints `KEY=VALUE` lines for system metadata.
+/// POSIX shell snippet that prints system metadata as one line of JSON,
+/// escaped on the remote so values may hold quotes, `=`, or newlines. Falls
+/// back to `KEY=VALUE` lines where `sed` or `awk` is missing.
 /// Works on both Linux (via `/etc/os-release`) and macOS (via `sw_vers`).
 const METADATA_COMMAND: &str = concat!(
-    r#"echo "ARCH=$(uname -m)"; "#,
-    r#"echo "OS=$(uname -s | tr '[:upper:]' '[:lower:]')"; "#,
+    r#"ARCH=$(uname -m); "#,
+    r#"OS=$(uname -s | tr '[:upper:]' '[:lower:]'); "#,
+    r#"HOST=$(uname -n); "#,
     r#"if [ -f /etc/os-release ]; then "#,
-    r#". /etc/os-release; "#,
-    r#"echo "DISTRO=${PRETTY_NAME:-${NAME} ${VERSION}}"; "#,
+    r#"DISTRO=$(. /etc/os-release; echo "${PRETTY_NAME:-${NAME} ${VERSION}}"); "#,
     r#"elif command -v sw_vers >/dev/null 2>&1; then "#,
-    r#"echo "DISTRO=macOS $(sw_vers -productVersion)"; "#,
+    r#"DISTRO="macOS $(sw_vers -productVersion)"; "#,
     r#"else "#,
-    r#"echo "DISTRO=unknown"; "#,
+    r#"DISTRO=unknown; "#,
     r#"fi; "#,
-    r#"echo "SHELL=$SHELL"; "#,
-    r#"for pm in apt dnf yum pacman apk brew; do "#,
-    r#"command -v "$pm" >/dev/null 2>&1 && echo "PKG_MANAGER=$pm" && break; "#,
-    r#"done"#,
+    r#"SHVER=; case "$SHELL" in *bash|*zsh|*fish) "#,
+    r#"SHVER=$("$SHELL" --version </dev/null 2>/dev/null | head -n 1);; esac; "#,
+    r#"UP=; if [ -r /proc/uptime ]; then UP=$(cut -d. -f1 /proc/uptime); "#,
+    r#"else BOOT=$(sysctl -n kern.boottime 2>/dev/null | sed -n 's/^{ sec = \([0-9]*\).*/\1/p'); "#,
+    r#"[ -n "$BOOT" ] && UP=$(( $(date +%s) - BOOT )); fi; "#,
+    r#"MASK=$(umask); "#,
+    r#"PKG=; for pm in apt dnf yum pacman apk brew; do "#,
+    r#"command -v "$pm" >/dev/null 2>&1 && PKG=$pm && break; "#,
+    r#"done; "#,
+    r#"if command -v sed >/dev/null 2>&1 && command -v awk >/dev/null 2>&1; then "#,
+    r#"j() { printf '%s' "$1" | tr '\t' ' ' | tr -d '\001-\011\013-\037' "#,
+    r#"| sed -e 's/\\/\\\\/g' -e 's/"/\\"/g' | awk 'NR>1{printf "\\n"} {printf "%s", $0}'; }; "#,
+    r#"printf '{"arch":"%s","os":"%s","distro":"%s","hostname":"%s","shell":"%s","shell_version":"%s",'"#,
+    r#"'"package_manager":"%s","uptime":"%s","umask":"%s"}\n' "#,
+    r#""$(j "$ARCH")" "$(j "$OS")" "$(j "$DISTRO")" "$(j "$HOST")" "$(j "$SHELL")" "$(j "$SHVER")" "#,
+    r#""$(j "$PKG")" "$(j "$UP")" "$(j "$MASK")"; "#,
+    r#"else "#,
+    r#"echo "ARCH=$ARCH"; echo "OS=$OS"; echo "DISTRO=$DISTRO"; echo "HOSTNAME=$HOST"; "#,
+    r#"echo "SHELL=$SHELL"; echo "SHELL_VERSION=$SHVER"; echo "PKG_MANAGER=$PKG"; "#,
+    r#"echo "UPTIME=$UP"; echo "UMASK=$MASK"; "#,
+    r#"fi"#,
+);
+
+/// PowerShell equivalent of [`METADATA_COMMAND`] for Windows servers.
+const POWERSHELL_METADATA_COMMAND: &str = concat!(
+    r#"$os = Get-CimInstance Win32_OperatingSystem; "#,
+    r#"$m = [ordered]@{ os = 'windows'; arch = $env:PROCESSOR_ARCHITECTURE; "#,
+    r#"distro = $os.Caption; hostname = $env:COMPUTERNAME; shell = 'powershell'; "#,
+    r#"shell_version = "$($PSVersionTable.PSVersion)"; "#,
+    r#"uptime = "$([long]((Get-Date) - $os.LastBootUpTime).TotalSeconds)" }; "#,
+    r#"if (Get-Command winget -ErrorAction SilentlyContinue) { $m.package_manager = 'winget' } "#,
+    r#"elseif (Get-Command choco -ErrorAction SilentlyContinue) { $m.package_manager = 'choco' }; "#,
+    r#"$m | ConvertTo-Json -Compress"#,
+);
+
+/// cmd.exe equivalent of [`METADATA_COMMAND`] for Windows servers.
+const CMD_METADATA_COMMAND: &str = concat!(
+    "echo OS=windows& ",
+    "echo ARCH=%PROCESSOR_ARCHITECTURE%& ",
+    "echo HOSTNAME=%COMPUTERNAME%& ",
+    "echo SHELL=cmd& ",
+    "(where winget >nul 2>&1 && echo PKG_MANAGER=winget)",
 );
 
 /// Collect system metadata from a connected server.
 ///
+/// Runs the POSIX probe first. If it yields nothing (or the connection is
+/// already known to use a Windows shell), the login shell is identified and
+/// the matching Windows probe runs instead.
+///
+/// Also records which address family the connection reached the server
+/// over, so a dual-stack host whose IPv6 is broken shows up as `ipv4`.
+///
 /// # Errors
 ///
 /// Returns an error if the SSH command fails or times out.
 pub async fn collect(conn: &SshConnection) -> Result<SystemMetadata> {
-    let result = conn
-        .exec(METADATA_COMMAND, Some(METADATA_TIMEOUT_MS))
-        .await?;
+    let mut meta = probe(conn).await?;
+    meta.address_family = Some(address_family(&conn.peer_addr()).to_string());
+    Ok(meta)
+}
+
+async fn probe(conn: &SshConnection) -> Result<SystemMetadata> {
+    let shell = if conn.shell().is_posix() {
+        let result = conn
+            .exec(METADATA_COMMAND, Some(METADATA_TIMEOUT_MS))
+            .await?;
+        let meta = parse_output(&result.stdout)?;
+        if meta.os.is_some() {
+            return Ok(meta);
+        }
+        match detect_windows_shell(conn).await? {
+            Some(shell) => shell,
+            None => return Ok(meta),
+        }
+    } else {
+        conn.shell()
+    };
+
+    let command = match shell {
+        RemoteShell::Cmd => CMD_METADATA_COMMAND,
+        _ => POWERSHELL_METADATA_COMMAND,
+    };
+    let result = conn.exec_bare(command, Some(METADATA_TIMEOUT_MS)).await?;
     parse_output(&result.stdout)
 }
 
-/// Parse `KEY=VALUE` output into a `SystemMetadata` struct.
-///
-/// Missing or unknown keys are silently ignored; empty values are treated as
-/// absent. Returns `Result` to allow future validation (e.g. rejecting
-/// malformed output) without a breaking API change.
-///
-/// # Errors
-///
-/// Currently infallible. Reserved for future validation of malformed output.
-pub fn parse_output(stdout: &str) -> Result<SystemMetadata> {
-    let mut meta = SystemMetadata::default();
+/// Identify a Windows login shell. `%OS%` only expands in cmd.exe and
+/// `$env:OS` only in PowerShell; both print `Windows_NT` there.
+async fn detect_windows_shell(conn: &SshConnection) -> Result<Option<RemoteShell>> {
+    let cmd = conn
+        .exec_bare("echo %OS%", Some(METADATA_TIMEOUT_MS))
+        .await?;
+    if cmd.stdout.trim() == "Windows_NT" {
+        return Ok(Some(RemoteShell::Cmd));
+    }
+    let ps = conn
+        .exec_bare("echo $env:OS", Some(METADATA_TIMEOUT_MS))
+        .await?;
+    if ps.stdout.trim() == "Windows_NT" {
+        return Ok(Some(RemoteShell::Powershell));
+    }
+    Ok(None)
+}
 
-    for line in stdout.lines() {
-        if let Some((key, value)) = line.split_once('=') {
-            let value = value.trim();
-            if value.is_empty() {
+/// Metadata fields as the probes print them: all strings, any of them
+/// missing.
+#[derive(Debug, Default, Deserialize)]
+struct RawMetadata {
+    arch: Option<String>,
+    os: Option<String>,
+    distro: Option<String>,
+    hostname: Option<String>,
+    shell: Option<String>,
+    shell_version: Option<String>,
+    package_manager: Option<String>,
+    uptime: Option<String>,
+    umask: Option<String>,
+}
+
+impl RawMetadata {
+    /// Read `KEY=VALUE` lines, splitting each at its first `=`.
+    fn from_key_values(stdout: &str) -> Self {
+        let mut raw = Self::default();
+        for line in stdout.lines() {
+            let Some((key, value)) = line.split_once('=') else {
                 continue;
-            }
+            };
+            let value = Some(value.to_string());
             match key.trim() {
-                "ARCH" => meta.arch = Some(value.to_string()),
-                "OS" => meta.os = Some(value.to_string()),
-                "DISTRO" => meta.distro = Some(value.to_string()),
-                "SHELL" => meta.shell = Some(value.to_string()),
-                "PKG_MANAGER" => meta.package_manager = Some(value.to_string()),
+                "ARCH" => raw.arch = value,
+                "OS" => raw.os = value,
+                "DISTRO" => raw.distro = value,
+                "HOSTNAME" => raw.hostname = value,
+                "SHELL" => raw.shell = value,
+                "SHELL_VERSION" => raw.shell_version = value,
+                "PKG_MANAGER" => raw.package_manager = value,
+                "UPTIME" => raw.uptime = value,
+                "UMASK" => raw.umask = value,
                 _ => {}
             }
         }
+        raw
     }
+}
 
-    meta.collected_at = Some(
-        SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs(),
-    );
+/// Parse probe output into a `SystemMetadata` struct.
+///
+/// The output is a line of JSON, or `KEY=VALUE` lines where the remote
+/// couldn't build it. Missing or unknown keys are silently ignored; empty
+/// values are treated as absent. Returns `Result` to allow future
+/// validation (e.g. rejecting malformed output) without a breaking API change.
+///
+/// # Errors
+///
+/// Currently infallible. Reserved for future validation of malformed output.
+pub fn parse_output(stdout: &str) -> Result<SystemMetadata> {
+    let raw = stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with('{'))
+        .find_map(|line| serde_json::from_str::<RawMetadata>(line).ok())
+        .unwrap_or_else(|| RawMetadata::from_key_values(stdout));
 
-    Ok(meta)
+    let value = |v: Option<String>| v.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    Ok(SystemMetadata {
+        os: value(raw.os),
+        distro: value(raw.distro),
+        arch: value(raw.arch),
+        shell: value(raw.shell),
+        shell_version: value(raw.shell_version),
+        package_manager: value(raw.package_manager),
+        hostname: value(raw.hostname),
+        uptime_seconds: value(raw.uptime).and_then(|v| v.parse().ok()),
+        umask: value(raw.umask),
+        address_family: None,
+        collected_at: Some(
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        ),
+    })
 }
 
-/// Compare two metadata instances, ignoring `collected_at`.
+/// Compare two metadata instances, ignoring `collected_at` and
+/// `uptime_seconds`, which change on every collection.
 /// Returns a human-readable diff string if anything changed, or `None`.
 #[must_use]
 pub fn diff(old: &SystemMetadata, new: &SystemMetadata) -> Option<String> {
@@ -138,11 +285,23 @@ pub fn diff(old: &SystemMetadata, new: &SystemMetadata) -> Option<String> {
         ("distro", old.distro.as_deref(), new.distro.as_deref()),
         ("arch", old.arch.as_deref(), new.arch.as_deref()),
         ("shell", old.shell.as_deref(), new.shell.as_deref()),
+        (
+            "shell_version",
+            old.shell_version.as_deref(),
+            new.shell_version.as_deref(),
+        ),
+        ("hostname", old.hostname.as_deref(), new.hostname.as_deref()),
+        ("umask", old.umask.as_deref(), new.umask.as_deref()),
         (
             "package_manager",
             old.package_manager.as_deref(),
             new.package_manager.as_deref(),
         ),
+        (
+            "address_family",
+            old.address_family.as_deref(),
+            new.address_family.as_deref(),
+        ),
     ];
 
     let changes: Vec<String> = fields
@@ -0,0 +1,290 @@
+//! Opt-in usage metrics for an MCP server that runs for a long time, read
+//! with the `metrics` tool as JSON or in the Prometheus text format.
+//!
+//! ```toml
+//! [metrics]
+//! enabled = true
+//! ```
+//!
+//! Nothing leaves the machine: the counters live in the MCP server's memory
+//! and start from zero on every restart.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Upper bounds of the command duration buckets, in milliseconds.
+const DURATION_BUCKETS_MS: [u64; 9] = [
+    100, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000, 300_000,
+];
+
+/// Upper bounds of the synced bytes buckets.
+const BYTES_BUCKETS: [u64; 6] = [1 << 10, 1 << 16, 1 << 20, 1 << 24, 1 << 28, 1 << 30];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MetricsConfig {
+    /// Count tool calls, errors, reconnects, command durations, and bytes
+    /// synced. Read once at startup.
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// Observations counted into fixed buckets, Prometheus style.
+#[derive(Debug, Clone, Serialize)]
+pub struct Histogram {
+    #[serde(skip)]
+    bounds: &'static [u64],
+    /// Observations at or below each bound, in the order of the bounds,
+    /// then all observations for `+Inf`. Not cumulative.
+    #[serde(skip)]
+    counts: Vec<u64>,
+    pub buckets: Vec<Bucket>,
+    pub sum: u64,
+    pub count: u64,
+}
+
+/// Observations at or below `le`, cumulative as in Prometheus.
+#[derive(Debug, Clone, Serialize)]
+pub struct Bucket {
+    /// Upper bound; `None` is `+Inf`.
+    pub le: Option<u64>,
+    pub count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [u64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len() + 1],
+            buckets: Vec::new(),
+            sum: 0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: u64) {
+        let i = self
+            .bounds
+            .iter()
+            .position(|&bound| value <= bound)
+            .unwrap_or(self.bounds.len());
+        self.counts[i] += 1;
+        self.sum = self.sum.saturating_add(value);
+        self.count += 1;
+    }
+
+    /// Cumulative counts per bound, ending with `+Inf`.
+    fn cumulative(&self) -> Vec<Bucket> {
+        let mut total = 0;
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(i, count)| {
+                total += count;
+                Bucket {
+                    le: self.bounds.get(i).copied(),
+                    count: total,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Calls and errors of one tool.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ToolCounts {
+    pub calls: u64,
+    pub errors: u64,
+}
+
+/// Counters since the MCP server started.
+#[derive(Debug)]
+pub struct Metrics {
+    started: Instant,
+    tools: BTreeMap<String, ToolCounts>,
+    reconnects: u64,
+    command_ms: Histogram,
+    synced_bytes: Histogram,
+}
+
+/// Everything [`Metrics`] counted, as returned by the `metrics` tool.
+#[derive(Debug, Serialize)]
+pub struct MetricsSnapshot {
+    pub uptime_secs: u64,
+    pub tools: BTreeMap<String, ToolCounts>,
+    pub reconnects: u64,
+    /// Durations of `remote_bash` and command template calls, in milliseconds.
+    pub command_ms: Histogram,
+    /// Bytes moved by each `sync_push` and `sync_pull`.
+    pub synced_bytes: Histogram,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            tools: BTreeMap::new(),
+            reconnects: 0,
+            command_ms: Histogram::new(&DURATION_BUCKETS_MS),
+            synced_bytes: Histogram::new(&BYTES_BUCKETS),
+        }
+    }
+
+    /// Count a call of `tool`. `command` marks calls that ran a shell
+    /// command whose duration is worth tracking.
+    pub fn record_call(&mut self, tool: &str, elapsed: Duration, error: bool, command: bool) {
+        let counts = self.tools.entry(tool.to_string()).or_default();
+        counts.calls += 1;
+        if error {
+            counts.errors += 1;
+        }
+        if command {
+            self.command_ms
+                .observe(u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX));
+        }
+    }
+
+    pub fn record_reconnect(&mut self) {
+        self.reconnects += 1;
+    }
+
+    pub fn record_synced(&mut self, bytes: u64) {
+        self.synced_bytes.observe(bytes);
+    }
+
+    #[must_use]
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let mut command_ms = self.command_ms.clone();
+        command_ms.buckets = command_ms.cumulative();
+        let mut synced_bytes = self.synced_bytes.clone();
+        synced_bytes.buckets = synced_bytes.cumulative();
+        MetricsSnapshot {
+            uptime_secs: self.started.elapsed().as_secs(),
+            tools: self.tools.clone(),
+            reconnects: self.reconnects,
+            command_ms,
+            synced_bytes,
+        }
+    }
+}
+
+impl MetricsSnapshot {
+    /// The snapshot in the Prometheus text exposition format.
+    #[must_use]
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        metric_header(
+            &mut out,
+            "uptime_seconds",
+            "gauge",
+            "Seconds since the MCP server started.",
+        );
+        let _ = writeln!(out, "ssh_hub_uptime_seconds {}", self.uptime_secs);
+
+        metric_header(
+            &mut out,
+            "tool_calls_total",
+            "counter",
+            "Tool calls by tool.",
+        );
+        for (tool, counts) in &self.tools {
+            let _ = writeln!(
+                out,
+                "ssh_hub_tool_calls_total{{tool=\"{}\"}} {}",
+                escape_label(tool),
+                counts.calls
+            );
+        }
+        metric_header(
+            &mut out,
+            "tool_errors_total",
+            "counter",
+            "Tool calls that returned an error, by tool.",
+        );
+        for (tool, counts) in &self.tools {
+            let _ = writeln!(
+                out,
+                "ssh_hub_tool_errors_total{{tool=\"{}\"}} {}",
+                escape_label(tool),
+                counts.errors
+            );
+        }
+
+        metric_header(
+            &mut out,
+            "reconnects_total",
+            "counter",
+            "Connections re-established after one died or on request.",
+        );
+        let _ = writeln!(out, "ssh_hub_reconnects_total {}", self.reconnects);
+
+        write_histogram(
+            &mut out,
+            "command_duration_seconds",
+            "Duration of remote_bash and command template calls.",
+            &self.command_ms,
+            millis_as_secs,
+        );
+        write_histogram(
+            &mut out,
+            "synced_bytes",
+            "Bytes moved by each sync_push and sync_pull.",
+            &self.synced_bytes,
+            |bytes| bytes.to_string(),
+        );
+        out
+    }
+}
+
+fn metric_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP ssh_hub_{name} {help}");
+    let _ = writeln!(out, "# TYPE ssh_hub_{name} {kind}");
+}
+
+/// Write a histogram, with `unit` turning its stored values into the
+/// exported unit.
+fn write_histogram(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    histogram: &Histogram,
+    unit: fn(u64) -> String,
+) {
+    metric_header(out, name, "histogram", help);
+    for bucket in &histogram.buckets {
+        let le = bucket.le.map_or_else(|| "+Inf".to_string(), unit);
+        let _ = writeln!(out, "ssh_hub_{name}_bucket{{le=\"{le}\"}} {}", bucket.count);
+    }
+    let _ = writeln!(out, "ssh_hub_{name}_sum {}", unit(histogram.sum));
+    let _ = writeln!(out, "ssh_hub_{name}_count {}", histogram.count);
+}
+
+/// Milliseconds as decimal seconds without trailing zeros, e.g. `2.5`.
+fn millis_as_secs(ms: u64) -> String {
+    let secs = format!("{}.{:03}", ms / 1000, ms % 1000);
+    secs.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Escape a Prometheus label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Bytes a `sync_push` or `sync_pull` reported moving, from its JSON output.
+#[must_use]
+pub fn synced_bytes(output: &str) -> Option<u64> {
+    let json: serde_json::Value = serde_json::from_str(output).ok()?;
+    json.get("transfer")?.get("bytes")?.as_u64()
+}
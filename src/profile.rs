@@ -0,0 +1,67 @@
+//! Named profiles: separate sets of servers and secrets in one account.
+//!
+//! `ssh-hub --profile work ...` reads and writes `servers-work.toml` next to
+//! the default `servers.toml`, stores sudo passwords and key passphrases
+//! under keychain services ending in `-work`, and, with the broker enabled,
+//! shares a connection pool only with other processes of the same profile.
+//! Without `--profile` nothing changes.
+
+use std::sync::OnceLock;
+
+use anyhow::{bail, Result};
+
+/// Longest profile name accepted.
+const MAX_NAME_LEN: usize = 32;
+
+/// Profile chosen with `--profile`.
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Use the profile `name` for the rest of the process. Only the first call
+/// has an effect.
+///
+/// # Errors
+///
+/// Returns an error if `name` is not a valid profile name.
+pub fn set(name: &str) -> Result<()> {
+    validate(name)?;
+    let _ = PROFILE.set(name.to_string());
+    Ok(())
+}
+
+/// The profile in use, or `None` for the default one.
+#[must_use]
+pub fn current() -> Option<&'static str> {
+    PROFILE.get().map(String::as_str)
+}
+
+/// Check that `name` can appear in file and keychain service names.
+///
+/// # Errors
+///
+/// Returns an error if `name` is empty, `default`, too long, or has
+/// characters other than ASCII letters, digits, `-`, and `_`.
+pub fn validate(name: &str) -> Result<()> {
+    if name == "default" {
+        bail!("'default' is the profile used without --profile; leave the flag out instead");
+    }
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    if !valid {
+        bail!(
+            "invalid profile name '{name}': use up to {MAX_NAME_LEN} letters, digits, '-', or '_'"
+        );
+    }
+    Ok(())
+}
+
+/// `base` for the default profile, `base-<profile>` otherwise.
+#[must_use]
+pub fn scoped(base: &str, profile: Option<&str>) -> String {
+    match profile {
+        Some(profile) => format!("{base}-{profile}"),
+        None => base.to_string(),
+    }
+}
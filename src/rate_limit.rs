@@ -0,0 +1,202 @@
+//! Rate limits: how much agents may do to one server in a given time.
+//!
+//! ```toml
+//! [servers.prod.rate_limits.bash]  # remote_bash, command templates
+//! calls_per_minute = 30
+//!
+//! [servers.prod.rate_limits.sync]  # sync_push, sync_pull
+//! bytes_per_hour = 1000000000
+//! ```
+//!
+//! Limits under `[defaults.rate_limits]` apply to every server without
+//! `rate_limits` of its own. Each server is counted on its own. Nothing is limited unless configured, and `0`
+//! disables a limit. A call over a limit is refused, saying when to retry,
+//! so a runaway agent loop can't hammer a shared server.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::tools::{ErrorCode, ToolError};
+
+/// Window for `calls_per_minute`.
+const MINUTE: Duration = Duration::from_mins(1);
+
+/// Window for `bytes_per_hour`.
+const HOUR: Duration = Duration::from_hours(1);
+
+/// Tools that share a rate limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ToolClass {
+    /// `remote_bash` and command templates
+    Bash,
+    /// `remote_write`, `remote_edit`, `remote_patch`, `restore_backup`,
+    /// `remote_snapshot`, `remote_restore`
+    Write,
+    /// `remote_read`, `remote_tail`, `remote_glob`, `remote_watch`, `remote_stat`
+    Read,
+    /// `sync_push`, `sync_pull`
+    Sync,
+}
+
+impl ToolClass {
+    /// The class of `tool`, or `None` for tools that are never limited.
+    /// Command templates count as `bash`.
+    #[must_use]
+    pub fn for_tool(tool: &str, template: bool) -> Option<Self> {
+        if template {
+            return Some(Self::Bash);
+        }
+        Some(match tool {
+            "remote_bash" | "run_saved_command" => Self::Bash,
+            "remote_write" | "remote_edit" | "remote_patch" | "restore_backup"
+            | "remote_snapshot" | "remote_restore" => Self::Write,
+            "remote_read" | "remote_tail" | "remote_glob" | "remote_watch" | "remote_stat" => {
+                Self::Read
+            }
+            "sync_push" | "sync_pull" => Self::Sync,
+            _ => return None,
+        })
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Bash => "bash",
+            Self::Write => "write",
+            Self::Read => "read",
+            Self::Sync => "sync",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bash: Option<ClassLimit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub write: Option<ClassLimit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read: Option<ClassLimit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync: Option<ClassLimit>,
+}
+
+impl RateLimitConfig {
+    /// The limit for `class`, if one is set.
+    #[must_use]
+    pub fn for_class(&self, class: ToolClass) -> Option<ClassLimit> {
+        match class {
+            ToolClass::Bash => self.bash,
+            ToolClass::Write => self.write,
+            ToolClass::Read => self.read,
+            ToolClass::Sync => self.sync,
+        }
+    }
+}
+
+/// Limits for one tool class on one server (`0` = unlimited).
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClassLimit {
+    /// Calls in any 60 seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calls_per_minute: Option<u32>,
+    /// Bytes transferred in any hour. Only sync tools report bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes_per_hour: Option<u64>,
+}
+
+/// Recent calls and transferred bytes per server and tool class.
+#[derive(Debug, Default)]
+pub struct RateUsage {
+    calls: HashMap<(String, ToolClass), VecDeque<Instant>>,
+    bytes: HashMap<(String, ToolClass), VecDeque<(Instant, u64)>>,
+}
+
+impl RateUsage {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a call to `class` on `server`, or refuse it when that would
+    /// go over `limit`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ErrorCode::Throttled`] error saying when to retry.
+    pub fn check(
+        &mut self,
+        server: &str,
+        class: ToolClass,
+        limit: ClassLimit,
+        now: Instant,
+    ) -> Result<(), ToolError> {
+        let key = (server.to_string(), class);
+        if let Some(max) = limit.bytes_per_hour.filter(|&max| max > 0) {
+            let entries = self.bytes.entry(key.clone()).or_default();
+            prune(entries, now, HOUR, |(at, _)| *at);
+            if entries.iter().map(|(_, bytes)| bytes).sum::<u64>() >= max {
+                let oldest = entries.front().map(|(at, _)| *at);
+                return Err(refusal(
+                    server,
+                    class,
+                    &format!("{max} bytes per hour"),
+                    retry_after(oldest, now, HOUR),
+                ));
+            }
+        }
+        if let Some(max) = limit.calls_per_minute.filter(|&max| max > 0) {
+            let entries = self.calls.entry(key).or_default();
+            prune(entries, now, MINUTE, |at| *at);
+            if entries.len() >= max as usize {
+                let oldest = entries.front().copied();
+                return Err(refusal(
+                    server,
+                    class,
+                    &format!("{max} calls per minute"),
+                    retry_after(oldest, now, MINUTE),
+                ));
+            }
+            entries.push_back(now);
+        }
+        Ok(())
+    }
+
+    /// Record `bytes` transferred by a finished call to `class` on `server`.
+    pub fn record_bytes(&mut self, server: &str, class: ToolClass, bytes: u64, now: Instant) {
+        if bytes > 0 {
+            self.bytes
+                .entry((server.to_string(), class))
+                .or_default()
+                .push_back((now, bytes));
+        }
+    }
+}
+
+/// Drop entries that are `window` or more older than `now`.
+fn prune<T>(entries: &mut VecDeque<T>, now: Instant, window: Duration, at: impl Fn(&T) -> Instant) {
+    while entries
+        .front()
+        .is_some_and(|e| now.saturating_duration_since(at(e)) >= window)
+    {
+        entries.pop_front();
+    }
+}
+
+/// Time until the `oldest` entry leaves the window.
+fn retry_after(oldest: Option<Instant>, now: Instant, window: Duration) -> Duration {
+    oldest.map_or(window, |at| {
+        window.saturating_sub(now.saturating_duration_since(at))
+    })
+}
+
+fn refusal(server: &str, class: ToolClass, limit: &str, wait: Duration) -> ToolError {
+    let message = format!(
+        "the {} rate limit of {limit} on '{server}' is reached. Retry in {}s.",
+        class.name(),
+        wait.as_secs().max(1)
+    );
+    ToolError::new(ErrorCode::Throttled, message)
+}
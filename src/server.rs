@@ -1,21 +1,66 @@
+use std::collections::HashMap;
 use std::future::Future;
+use std::net::SocketAddr;
+use std::path::Path;
 use std::pin::Pin;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::Result;
 use futures::future::join_all;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use rmcp::handler::server::router::tool::ToolRouter;
+use rmcp::handler::server::tool::ToolCallContext;
 use rmcp::handler::server::wrapper::Parameters;
-use rmcp::model::{Implementation, ProtocolVersion, ServerCapabilities, ServerInfo};
-use rmcp::{tool, tool_handler, tool_router, ServerHandler, ServiceExt};
+use rmcp::model::{
+    CallToolRequestParam, CallToolResult, Content, Implementation, ListToolsResult, LoggingLevel,
+    LoggingMessageNotificationParam, Meta, PaginatedRequestParam, ProtocolVersion, RawContent,
+    ServerCapabilities, ServerInfo, SetLevelRequestParam, Tool,
+};
+use rmcp::service::{ElicitationError, RequestContext};
+use rmcp::{tool, tool_router, ErrorData, Peer, RoleServer, ServerHandler, ServiceExt};
+use schemars::JsonSchema;
+use serde::Deserialize;
 use tokio::io::{stdin, stdout};
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 
+use crate::budget::{BudgetUsage, Limits, ToolFamily};
 use crate::cli::params_from_config;
-use crate::connection::{ConnectionParams, ConnectionPool, SshConnection};
-use crate::server_registry::ServerRegistry;
+use crate::connection::resolve::is_unreachable;
+use crate::connection::{
+    ConnectionParams, ConnectionPool, HostKeyConfirm, NewHostKey, SshConnection,
+};
+use crate::discovery;
+use crate::hooks::{self, Hook, HookRun, HookStage, HookTarget};
+use crate::maintenance;
+use crate::metrics::{self, Metrics};
+use crate::rate_limit::{RateUsage, ToolClass};
+use crate::server_registry::{CommandShell, ServerChanges, ServerEntry, ServerRegistry};
+use crate::timeout::TimeoutPolicy;
 use crate::tools;
+use crate::tools::command_template::{self, CommandTemplate};
+use crate::tools::list_servers::handler::ProbeCache;
+use crate::tools::provision::history::ProvisionHistory;
+use crate::tools::remote_bash::handler::OutputSizes;
+use crate::tools::remote_bash::mutation::detect_mutation;
+use crate::tools::remote_watch::handler::WatchStore;
+use crate::tools::socks_proxy::handler::ProxyStore;
+use crate::tools::sync_chunked::Progress;
+use crate::tools::sync_manifest::ManifestCache;
+use crate::tools::workspace::registry::WorkspaceRegistry;
+use crate::tools::workspace::session::SessionDirs;
+use crate::tools::{tool_error, ErrorCode, ToolError};
+use crate::utils::output::{fit_output, MAX_INLINE_OUTPUT};
+use crate::utils::path::normalize_remote_path;
+
+/// Quiet period after a config file event before reloading.
+const CONFIG_WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// How long the user has to confirm a host key seen for the first time.
+const HOST_KEY_CONFIRM_TIMEOUT: Duration = Duration::from_mins(2);
+
+/// How long shutdown waits to remove a server's workspaces.
+const WORKSPACE_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(15);
 
 /// MCP server for remote SSH sessions — manages multiple simultaneous connections.
 #[derive(Clone)]
@@ -23,9 +68,44 @@ pub struct RemoteSessionServer {
     pool: Arc<ConnectionPool>,
     config: Arc<RwLock<ServerRegistry>>,
     config_mtime: Arc<RwLock<Option<SystemTime>>>,
+    manifests: Arc<ManifestCache>,
+    watches: Arc<WatchStore>,
+    output_sizes: Arc<OutputSizes>,
+    /// SOCKS proxies started with `socks_proxy`.
+    proxies: Arc<ProxyStore>,
+    probes: Arc<ProbeCache>,
+    /// Server changes from config reloads not yet reported by `list_servers`.
+    unreported_changes: Arc<Mutex<ServerChanges>>,
+    session_dirs: Arc<SessionDirs>,
+    /// Connected clients, notified of config reloads. More than one when this
+    /// process is the shared broker.
+    peers: Arc<RwLock<Vec<Peer<RoleServer>>>>,
+    budget: Arc<Mutex<BudgetUsage>>,
+    /// Recent calls and synced bytes, for servers with `rate_limits`.
+    rate_usage: Arc<Mutex<RateUsage>>,
+    /// Usage counters, when `[metrics]` is enabled.
+    metrics: Option<Arc<Mutex<Metrics>>>,
     tool_router: ToolRouter<Self>,
 }
 
+/// Error for a server name missing from the config, listing the configured ones.
+fn server_not_found(cfg: &ServerRegistry, server: &str) -> ToolError {
+    let mut names: Vec<&str> = cfg.servers.keys().map(String::as_str).collect();
+    names.sort_unstable();
+    let message = if names.is_empty() {
+        format!(
+            "server '{server}' not found. No servers are configured. \
+             Add servers via 'ssh-hub add <name> <connection>'."
+        )
+    } else {
+        format!(
+            "server '{server}' not found. Configured servers: {}.",
+            names.join(", ")
+        )
+    };
+    ToolError::new(ErrorCode::ServerNotFound, message)
+}
+
 #[tool_router]
 impl RemoteSessionServer {
     #[must_use]
@@ -35,10 +115,27 @@ impl RemoteSessionServer {
             .and_then(|p| std::fs::metadata(p).ok())
             .and_then(|m| m.modified().ok());
 
+        let metrics = config
+            .metrics
+            .as_ref()
+            .is_some_and(|m| m.enabled)
+            .then(|| Arc::new(Mutex::new(Metrics::new())));
+
         Self {
             pool: Arc::new(ConnectionPool::new()),
             config: Arc::new(RwLock::new(config)),
             config_mtime: Arc::new(RwLock::new(initial_mtime)),
+            manifests: Arc::new(ManifestCache::new()),
+            watches: Arc::new(WatchStore::new()),
+            output_sizes: Arc::new(OutputSizes::new()),
+            proxies: Arc::new(ProxyStore::new()),
+            probes: Arc::new(ProbeCache::new()),
+            unreported_changes: Arc::new(Mutex::new(ServerChanges::default())),
+            session_dirs: Arc::new(SessionDirs::new()),
+            peers: Arc::new(RwLock::new(Vec::new())),
+            budget: Arc::new(Mutex::new(BudgetUsage::new())),
+            rate_usage: Arc::new(Mutex::new(RateUsage::new())),
+            metrics,
             tool_router: Self::tool_router(),
         }
     }
@@ -46,14 +143,114 @@ impl RemoteSessionServer {
     // ── Remote Tools ──────────────────────────────────────────────────
 
     #[tool(
-        description = "Execute a shell command on a remote server. Commands run from the connection's base path. Use this for git operations, build tools, process management, and any other shell task on the remote machine. Set run_in_background=true for long-running commands — returns immediately with a PID and log file path instead of waiting for completion."
+        description = "Execute a shell command on a remote server. Commands run from the connection's base path. Use this for git operations, build tools, process management, and any other shell task on the remote machine. Set run_in_background=true for long-running commands — returns immediately with a PID and log file path instead of waiting for completion. Foreground commands time out after at most 10 minutes; when the full output and exit code of a longer build or test run are needed, set long_running=true to allow a timeout up to the server's max_timeout_ms. Set sudo=true to run as root with the sudo password stored for the server in the OS keychain; never put a password in the command. Set shell='bash' for scripts that rely on bash syntax."
     )]
-    async fn remote_bash(&self, Parameters(input): Parameters<tools::RemoteBashInput>) -> String {
+    async fn remote_bash(
+        &self,
+        Parameters(input): Parameters<tools::RemoteBashInput>,
+        context: RequestContext<RoleServer>,
+    ) -> String {
+        let mutation = detect_mutation(&input.command)
+            .map(|reason| format!("a command that changes the server ({reason})"));
+        let sudo = input
+            .sudo
+            .unwrap_or(false)
+            .then(|| "running commands with sudo".to_string());
+        if let Some(action) = mutation.or(sudo) {
+            if let Err(e) = self.check_readonly(&input.server, &action).await {
+                return e;
+            }
+        }
+        if let Err(e) = self
+            .check_window(&input.server, input.override_window.as_deref())
+            .await
+        {
+            return e;
+        }
         let server = input.server.clone();
-        self.with_connection(&server, |conn| async {
-            tools::remote_bash::handler::handle(conn, input).await
-        })
-        .await
+        let timeouts = self.timeouts(&server).await;
+        let default_shell = {
+            let cfg = self.config.read().await;
+            cfg.get(&server)
+                .map_or(CommandShell::Login, ServerEntry::command_shell)
+        };
+        let sizes = Arc::clone(&self.output_sizes);
+        let call = self.with_connection(&server, move |conn| async move {
+            tools::remote_bash::handler::handle(
+                conn,
+                input,
+                timeouts,
+                default_shell,
+                sizes,
+                context,
+            )
+            .await
+        });
+        let call = self.within_budget(ToolFamily::Bash, call);
+        let result = Box::pin(self.with_hooks(&server, HookStage::Exec, call)).await;
+        self.manifests.invalidate_server(&server).await;
+        result
+    }
+
+    #[tool(
+        description = "Run a command saved for a server under [servers.<name>.commands] in servers.toml, such as 'deploy' or 'restart'. list_servers shows each server's saved_commands. Values in args fill the command's {param} placeholders and are shell-escaped. Returns stdout, stderr, and exit code like remote_bash. Prefer a saved command over remote_bash when one does the job: it is the operation the user vetted."
+    )]
+    async fn run_saved_command(
+        &self,
+        Parameters(input): Parameters<tools::RunSavedCommandInput>,
+    ) -> String {
+        self.maybe_reload_config().await;
+        let rendered = {
+            let cfg = self.config.read().await;
+            match cfg.get(&input.server) {
+                Some(entry) => tools::run_saved_command::handler::render(&entry.commands, &input),
+                None => return server_not_found(&cfg, &input.server).to_json(),
+            }
+        };
+        let command = match rendered {
+            Ok(command) => command,
+            Err(e) => return e.to_json(),
+        };
+        let server = input.server.clone();
+        if let Some(reason) = detect_mutation(&command) {
+            let action = format!(
+                "saved command '{}', which changes the server ({reason})",
+                input.command
+            );
+            if let Err(e) = self.check_readonly(&server, &action).await {
+                return e;
+            }
+        }
+        if let Err(e) = self
+            .check_window(&server, input.override_window.as_deref())
+            .await
+        {
+            return e;
+        }
+        let timeout = match self.timeouts(&server).await.resolve(input.timeout, false) {
+            Ok(timeout) => timeout,
+            Err(e) => return tool_error(ErrorCode::InvalidInput, e),
+        };
+        tracing::info!("run_saved_command {}: {command}", input.command);
+        let call = self.with_connection(&server, move |conn| async move {
+            command_template::handle(conn, command, timeout, "run_saved_command").await
+        });
+        let result = self.within_budget(ToolFamily::Bash, call).await;
+        self.manifests.invalidate_server(&server).await;
+        result
+    }
+
+    #[tool(
+        description = "Execute a shell command on the LOCAL machine the MCP server runs on, with 'sh -c'. Only offered when servers.toml sets allow_local = true, for clients that have no local shell of their own. Returns stdout, stderr, and exit code like remote_bash; large output is saved to a local file and summarized. Foreground only, with a timeout of at most 10 minutes."
+    )]
+    async fn local_bash(&self, Parameters(input): Parameters<tools::LocalBashInput>) -> String {
+        self.maybe_reload_config().await;
+        let allow_local = self.config.read().await.allow_local;
+        if allow_local {
+            tracing::info!("local_bash: {}", input.command);
+        }
+        let call = tools::local_bash::handler::handle(input, allow_local);
+        self.within_budget(ToolFamily::Bash, call).await
     }
 
     #[tool(
@@ -61,71 +258,804 @@ impl RemoteSessionServer {
     )]
     async fn remote_read(&self, Parameters(input): Parameters<tools::RemoteReadInput>) -> String {
         let server = input.server.clone();
-        self.with_connection(&server, |conn| async {
+        let call = self.with_read_connection(&server, |conn| async {
             tools::remote_read::handler::handle(conn, input).await
-        })
-        .await
+        });
+        self.within_budget(ToolFamily::Read, call).await
     }
 
     #[tool(
-        description = "Write content to a file on a remote server. Overwrites the file if it exists. For pushing multiple files or directories from local, use sync_push instead."
+        description = "Return the last N lines of a remote file and optionally follow it for up to 120 seconds (follow_seconds). Use this to watch build logs or the log_file of a background remote_bash command. New lines are streamed as progress notifications while following and returned in 'followed'."
+    )]
+    async fn remote_tail(
+        &self,
+        Parameters(input): Parameters<tools::RemoteTailInput>,
+        context: RequestContext<RoleServer>,
+    ) -> String {
+        let server = input.server.clone();
+        let call = self.with_connection(&server, |conn| async {
+            tools::remote_tail::handler::handle(conn, input, context).await
+        });
+        self.within_budget(ToolFamily::Read, call).await
+    }
+
+    #[tool(
+        description = "Write content to a file on a remote server. Overwrites the file if it exists. Pass verify=true to confirm the written content by sha256, and backup=true to keep a copy of the file it replaces (undo with restore_backup). For pushing multiple files or directories from local, use sync_push instead."
     )]
     async fn remote_write(&self, Parameters(input): Parameters<tools::RemoteWriteInput>) -> String {
+        if let Err(e) = self.check_readonly(&input.server, "remote_write").await {
+            return e;
+        }
+        if let Err(e) = self
+            .check_window(&input.server, input.override_window.as_deref())
+            .await
+        {
+            return e;
+        }
         let server = input.server.clone();
-        self.with_connection(&server, |conn| async {
-            tools::remote_write::handler::handle(conn, input).await
-        })
-        .await
+        let timeouts = self.timeouts(&server).await;
+        let result = self
+            .with_connection(&server, move |conn| async move {
+                tools::remote_write::handler::handle(conn, input, timeouts).await
+            })
+            .await;
+        self.manifests.invalidate_server(&server).await;
+        result
     }
 
     #[tool(
-        description = "Edit a file on a remote server using exact string replacement. The old_string must match uniquely in the file. Use replace_all to change every occurrence."
+        description = "Edit a file on a remote server using exact string replacement. The old_string must match uniquely in the file; multiple matches are reported as an error. Use replace_all to change every occurrence. Set preview=true to get a unified diff without writing; applied edits also return a diff. Set backup=true to keep a copy of the original (undo with restore_backup)."
     )]
     async fn remote_edit(&self, Parameters(input): Parameters<tools::RemoteEditInput>) -> String {
+        if !input.preview.unwrap_or(false) {
+            if let Err(e) = self.check_readonly(&input.server, "remote_edit").await {
+                return e;
+            }
+            if let Err(e) = self
+                .check_window(&input.server, input.override_window.as_deref())
+                .await
+            {
+                return e;
+            }
+        }
         let server = input.server.clone();
-        self.with_connection(&server, |conn| async {
-            tools::remote_edit::handler::handle(conn, input).await
+        let timeouts = self.timeouts(&server).await;
+        let result = self
+            .with_connection(&server, move |conn| async move {
+                tools::remote_edit::handler::handle(conn, input, timeouts).await
+            })
+            .await;
+        self.manifests.invalidate_server(&server).await;
+        result
+    }
+
+    #[tool(
+        description = "Apply a unified diff (as from diff -u or git diff) to one or more files on a remote server. Each hunk's context and removed lines must match the current file, at the line its header names or nearby; if any hunk fails, nothing is written and every failing hunk is reported with the line that differs. Use this instead of remote_edit for changes at many places. Set preview=true to check the patch and get the resulting diff without writing. Set backup=true to keep copies of the originals (undo with restore_backup)."
+    )]
+    async fn remote_patch(&self, Parameters(input): Parameters<tools::RemotePatchInput>) -> String {
+        if !input.preview.unwrap_or(false) {
+            if let Err(e) = self.check_readonly(&input.server, "remote_patch").await {
+                return e;
+            }
+            if let Err(e) = self
+                .check_window(&input.server, input.override_window.as_deref())
+                .await
+            {
+                return e;
+            }
+        }
+        let server = input.server.clone();
+        let timeouts = self.timeouts(&server).await;
+        let result = self
+            .with_connection(&server, move |conn| async move {
+                tools::remote_patch::handler::handle(conn, input, timeouts).await
+            })
+            .await;
+        self.manifests.invalidate_server(&server).await;
+        result
+    }
+
+    #[tool(
+        description = "Restore a file from a backup taken by remote_write or remote_edit with backup=true. Copies the backup (a path under ~/.ssh-hub-backups/) back to where it came from, after backing up the version it replaces."
+    )]
+    async fn restore_backup(
+        &self,
+        Parameters(input): Parameters<tools::RestoreBackupInput>,
+    ) -> String {
+        if let Err(e) = self.check_readonly(&input.server, "restore_backup").await {
+            return e;
+        }
+        if let Err(e) = self
+            .check_window(&input.server, input.override_window.as_deref())
+            .await
+        {
+            return e;
+        }
+        let server = input.server.clone();
+        let timeouts = self.timeouts(&server).await;
+        let result = self
+            .with_connection(&server, move |conn| async move {
+                tools::restore_backup::handler::handle(conn, input, timeouts).await
+            })
+            .await;
+        self.manifests.invalidate_server(&server).await;
+        result
+    }
+
+    #[tool(
+        description = "Snapshot a directory on a remote server into a timestamped .tar.gz kept on the same server (under ~/.ssh-hub-snapshots/ or the server's snapshot_dir), as a cheap rollback point before risky changes. Returns the snapshot path for remote_restore. Pass label to name it, or list=true to list the directory's existing snapshots."
+    )]
+    async fn remote_snapshot(
+        &self,
+        Parameters(input): Parameters<tools::RemoteSnapshotInput>,
+    ) -> String {
+        if !input.list.unwrap_or(false) {
+            if let Err(e) = self.check_readonly(&input.server, "remote_snapshot").await {
+                return e;
+            }
+            if let Err(e) = self
+                .check_window(&input.server, input.override_window.as_deref())
+                .await
+            {
+                return e;
+            }
+        }
+        let server = input.server.clone();
+        let timeouts = self.timeouts(&server).await;
+        let snapshot_dir = self.snapshot_dir(&server).await;
+        self.with_connection(&server, move |conn| async move {
+            tools::remote_snapshot::handler::handle(conn, input, &snapshot_dir, timeouts).await
         })
         .await
     }
 
     #[tool(
-        description = "Search for files matching a glob pattern on a remote server. Returns matching file paths relative to the search directory."
+        description = "Restore a directory from a snapshot taken by remote_snapshot. Replaces the directory the snapshot was taken of with the snapshot's contents, after snapshotting the version it replaces so the restore can be undone."
+    )]
+    async fn remote_restore(
+        &self,
+        Parameters(input): Parameters<tools::RemoteRestoreInput>,
+    ) -> String {
+        if let Err(e) = self.check_readonly(&input.server, "remote_restore").await {
+            return e;
+        }
+        if let Err(e) = self
+            .check_window(&input.server, input.override_window.as_deref())
+            .await
+        {
+            return e;
+        }
+        let server = input.server.clone();
+        let timeouts = self.timeouts(&server).await;
+        let snapshot_dir = self.snapshot_dir(&server).await;
+        let result = self
+            .with_connection(&server, move |conn| async move {
+                tools::remote_restore::handler::handle(conn, input, &snapshot_dir, timeouts).await
+            })
+            .await;
+        self.manifests.invalidate_server(&server).await;
+        result
+    }
+
+    #[tool(
+        description = "Search for files matching a glob pattern on a remote server. Returns matching file paths relative to the search directory, with total_matches and truncated. Results come in pages of limit (default 1000); pass offset=next_offset for the next page. Set sort='mtime' to list the most recently modified first."
     )]
     async fn remote_glob(&self, Parameters(input): Parameters<tools::RemoteGlobInput>) -> String {
         let server = input.server.clone();
-        self.with_connection(&server, |conn| async {
+        let call = self.with_read_connection(&server, |conn| async {
             tools::remote_glob::handler::handle(conn, input).await
+        });
+        Box::pin(self.within_budget(ToolFamily::Search, call)).await
+    }
+
+    #[tool(
+        description = "Watch files on a remote server for changes. The first call snapshots the size and modification time of every file matching a glob pattern and returns a token. Calling again with the token as 'since' reports the files added, removed, and modified in between, plus a new token. Use this to check whether build output or logs updated, instead of syncing or globbing repeatedly."
+    )]
+    async fn remote_watch(&self, Parameters(input): Parameters<tools::RemoteWatchInput>) -> String {
+        let server = input.server.clone();
+        let store = Arc::clone(&self.watches);
+        let call = self.with_read_connection(&server, |conn| async move {
+            tools::remote_watch::handler::handle(conn, &store, input).await
+        });
+        Box::pin(self.within_budget(ToolFamily::Search, call)).await
+    }
+
+    #[tool(
+        description = "Inspect one or many paths on a remote server in a single call: for each, whether it exists, its type, size, modification time (epoch seconds), octal mode, owner, group, and a symlink's target. Set sha256=true to also hash regular files. Use this to check that a write landed, to compare files before syncing, or to validate a cache, instead of running stat or sha256sum through remote_bash."
+    )]
+    async fn remote_stat(&self, Parameters(input): Parameters<tools::RemoteStatInput>) -> String {
+        let server = input.server.clone();
+        let timeouts = self.timeouts(&server).await;
+        self.with_connection_retry(&server, move |conn: Arc<SshConnection>| async move {
+            let caps = conn.capabilities().await;
+            tools::remote_stat::handler::handle(conn, input, caps, timeouts).await
         })
         .await
     }
 
-    // ── Sync Tools ────────────────────────────────────────────────────
+    #[tool(
+        description = "Collect a server's basic security posture as JSON: pending package updates (and security updates where the package manager reports them), listening TCP/UDP sockets, security-relevant sshd settings, and recent logins, plus warnings for risky findings. Runs unprivileged in one command; without root, sshd settings come from the config files rather than sshd -T."
+    )]
+    async fn remote_audit(&self, Parameters(input): Parameters<tools::RemoteAuditInput>) -> String {
+        let server = input.server.clone();
+        let timeouts = self.timeouts(&server).await;
+        self.with_connection_retry(&server, move |conn| async move {
+            tools::remote_audit::handler::handle(conn, input, timeouts).await
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Show who commands run as on a remote server: user, uid/gid, groups, home directory, umask, and sudo access (passwordless, password_required, denied, or not_installed, probed with sudo -n so it never prompts). Check this before attempting privileged operations."
+    )]
+    async fn remote_whoami(
+        &self,
+        Parameters(input): Parameters<tools::RemoteWhoamiInput>,
+    ) -> String {
+        let server = input.server.clone();
+        self.with_connection_retry(&server, |conn| async {
+            tools::remote_whoami::handler::handle(conn, input).await
+        })
+        .await
+    }
 
     #[tool(
-        description = "Push local file(s) to a connected remote server. Supports single files and entire directories. Directory walks respect .gitignore rules and skip symlinks. Use the 'exclude' parameter for additional exclusion patterns (gitignore syntax)."
+        description = "List processes on a remote server as JSON: pid, ppid, user, cpu and mem percent, start time, and command line. Filter by command substring or user, sort by cpu (default), mem, or pid. Use this instead of parsing ps output from remote_bash."
     )]
-    async fn sync_push(&self, Parameters(input): Parameters<tools::SyncPushInput>) -> String {
+    async fn remote_ps(&self, Parameters(input): Parameters<tools::RemotePsInput>) -> String {
+        let server = input.server.clone();
+        self.with_connection_retry(&server, |conn| async {
+            tools::remote_process::handler::ps(conn, input).await
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Send a signal (TERM by default, or KILL, INT, HUP, QUIT, USR1, USR2, STOP, CONT) to a process on a remote server. match_command must appear in the process's command line, otherwise nothing is sent, so a stale or reused PID is never signalled. Reports whether the process was still running a second later."
+    )]
+    async fn remote_kill(&self, Parameters(input): Parameters<tools::RemoteKillInput>) -> String {
+        if let Err(e) = self.check_readonly(&input.server, "remote_kill").await {
+            return e;
+        }
+        if let Err(e) = self
+            .check_window(&input.server, input.override_window.as_deref())
+            .await
+        {
+            return e;
+        }
         let server = input.server.clone();
         self.with_connection(&server, |conn| async {
-            tools::sync_push::handler::handle(conn, input).await
+            tools::remote_process::handler::kill(conn, input).await
         })
         .await
     }
 
     #[tool(
-        description = "Pull remote file(s) from a connected server to the local machine. Supports single files and entire directories. Use the 'files' parameter to pull a subset of a directory."
+        description = "Manage a service on a remote server: report its state as JSON (state, running, sub-state or health, enabled, pid, since), start, stop, or restart it, or read its recent logs. Works with systemd units, init scripts (service), and docker compose services (pass project_dir), picking what the remote has unless backend is set. Set sudo=true for passwordless sudo. Use this instead of parsing systemctl or docker output from remote_bash."
     )]
-    async fn sync_pull(&self, Parameters(input): Parameters<tools::SyncPullInput>) -> String {
+    async fn remote_service(
+        &self,
+        Parameters(input): Parameters<tools::RemoteServiceInput>,
+    ) -> String {
+        let server = input.server.clone();
+        let timeouts = self.timeouts(&server).await;
+        if !input.action.is_mutation() {
+            return self
+                .with_connection_retry(&server, move |conn| async move {
+                    tools::remote_service::handler::handle(conn, input, timeouts).await
+                })
+                .await;
+        }
+        if let Err(e) = self.check_readonly(&server, "remote_service").await {
+            return e;
+        }
+        if let Err(e) = self
+            .check_window(&server, input.override_window.as_deref())
+            .await
+        {
+            return e;
+        }
+        self.with_connection(&server, move |conn| async move {
+            tools::remote_service::handler::handle(conn, input, timeouts).await
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Wait on a remote server until a TCP port accepts connections (port, with host defaulting to localhost), a URL answers with 2xx or 3xx (url), or a file exists (path), checking every interval_seconds for up to timeout_seconds (default 60, max 600). Checks run on the server, so they see what it sees. Returns the attempts and elapsed time, or a TIMEOUT error. Use this after starting or deploying a service instead of looping remote_bash calls."
+    )]
+    async fn remote_wait_for(
+        &self,
+        Parameters(input): Parameters<tools::RemoteWaitForInput>,
+        context: RequestContext<RoleServer>,
+    ) -> String {
         let server = input.server.clone();
         self.with_connection(&server, |conn| async {
-            tools::sync_pull::handler::handle(conn, input).await
+            tools::remote_wait_for::handler::handle(conn, input, context).await
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Start a local SOCKS5 proxy that tunnels TCP connections through a server, like ssh -D, so local tools can reach hosts only the server can see (internal dashboards, databases, package mirrors). Listens on 127.0.0.1 at port, or a free port; returns the address and a socks5h:// proxy_url, where host names are resolved by the server. One proxy per server: calling again returns the running one. Pass stop=true to stop it. Needs TCP forwarding allowed in the server's sshd."
+    )]
+    async fn socks_proxy(&self, Parameters(input): Parameters<tools::SocksProxyInput>) -> String {
+        if input.stop == Some(true) {
+            return tools::socks_proxy::handler::stop(&self.proxies, &input.server).await;
+        }
+        self.maybe_reload_config().await;
+        if let Err(e) = self.resolve_connection(&input.server).await {
+            return e.to_json();
+        }
+        // Each client resolves the connection anew, so the proxy survives
+        // reconnects
+        let this = self.clone();
+        let server = input.server.clone();
+        let open = move |host: String, port: u16, peer: SocketAddr| {
+            let this = this.clone();
+            let server = server.clone();
+            async move {
+                let conn = this
+                    .resolve_connection(&server)
+                    .await
+                    .map_err(|e| anyhow::Error::msg(e.message))?;
+                let stream = conn.open_tunnel(&host, port, peer).await;
+                this.cleanup_if_dead(&server, &conn).await;
+                stream
+            }
+        };
+        tools::socks_proxy::handler::start(&self.proxies, input, open).await
+    }
+
+    #[tool(
+        description = "Report disk space on a remote server as JSON: filesystem, size, used, and available bytes, use percent, and mount point. Pass path to get only the filesystem holding it, e.g. before a large sync_push or build. Use this instead of parsing df output from remote_bash."
+    )]
+    async fn remote_df(&self, Parameters(input): Parameters<tools::RemoteDfInput>) -> String {
+        let server = input.server.clone();
+        self.with_connection_retry(&server, |conn| async {
+            tools::remote_disk::handler::df(conn, input).await
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Measure what takes up space in a remote directory: the size in bytes of each entry (subdirectories included, hidden entries too), largest first, plus the total. Stays on the directory's filesystem. 'partial' is set when some files couldn't be read. Use this to find what to clean up or exclude before a sync."
+    )]
+    async fn remote_du(&self, Parameters(input): Parameters<tools::RemoteDuInput>) -> String {
+        let server = input.server.clone();
+        let timeouts = self.timeouts(&server).await;
+        self.with_read_connection(&server, move |conn| async move {
+            tools::remote_disk::handler::du(conn, input, timeouts).await
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Create a unique scratch directory on a remote server for this task (under /tmp or the server's scratch_root) and return its path. Use it for experiments, downloads, and build trees instead of inventing folders in the home directory. Remove it with workspace_cleanup when done; leftovers are removed after ttl_hours or when the MCP server exits."
+    )]
+    async fn workspace_create(
+        &self,
+        Parameters(input): Parameters<tools::WorkspaceCreateInput>,
+    ) -> String {
+        if let Err(e) = self.check_readonly(&input.server, "workspace_create").await {
+            return e;
+        }
+        let server = input.server.clone();
+        let scratch_root = self.scratch_root(&server).await;
+        let name = server.clone();
+        self.with_connection(&server, move |conn| async move {
+            tools::workspace::handler::create(conn, &name, &scratch_root, input).await
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Return this session's private scratch directory on a remote server (/tmp/ssh-hub-session-<id>, or under the server's scratch_root), creating it on first use. Every call in the session gets the same directory. Use it for temporary files, logs, and intermediate output that don't need their own workspace; it is removed when the MCP server exits."
+    )]
+    async fn remote_tmpdir(
+        &self,
+        Parameters(input): Parameters<tools::RemoteTmpdirInput>,
+    ) -> String {
+        if let Err(e) = self.check_readonly(&input.server, "remote_tmpdir").await {
+            return e;
+        }
+        let server = input.server;
+        let scratch_root = self.scratch_root(&server).await;
+        let dirs = Arc::clone(&self.session_dirs);
+        let name = server.clone();
+        self.with_connection(&server, move |conn| async move {
+            tools::workspace::handler::tmpdir(conn, &dirs, &name, &scratch_root).await
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Remove a scratch directory made by workspace_create, or every one tracked for the server when path is omitted. Only tracked workspaces can be removed. Returns the removed paths and any failures."
+    )]
+    async fn workspace_cleanup(
+        &self,
+        Parameters(input): Parameters<tools::WorkspaceCleanupInput>,
+    ) -> String {
+        let server = input.server.clone();
+        let name = server.clone();
+        self.with_connection(&server, move |conn| async move {
+            tools::workspace::handler::cleanup(conn, &name, input).await
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Apply a provisioning recipe from servers.toml to a remote server: install packages, place files (with modes), run commands guarded by 'unless' checks, and enable systemd services. Every step checks current state first, so re-applying changes nothing. Pass dry_run=true to report what would change without touching the server. Returns a JSON report with per-step status (ok, changed, would_change, failed, skipped); steps after a failure are skipped."
+    )]
+    async fn provision(&self, Parameters(input): Parameters<tools::ProvisionInput>) -> String {
+        self.maybe_reload_config().await;
+        let recipe = {
+            let cfg = self.config.read().await;
+            tools::provision::handler::find_recipe(&cfg, &input.recipe, &input.server)
+        };
+        let recipe = match recipe {
+            Ok(r) => r,
+            Err(e) => return e.to_json(),
+        };
+        if !input.dry_run.unwrap_or(false) {
+            if let Err(e) = self.check_readonly(&input.server, "provision").await {
+                return e;
+            }
+            if let Err(e) = self
+                .check_window(&input.server, input.override_window.as_deref())
+                .await
+            {
+                return e;
+            }
+        }
+        let Some(config_dir) = ServerRegistry::config_path()
+            .ok()
+            .and_then(|p| p.parent().map(Path::to_path_buf))
+        else {
+            return tool_error(ErrorCode::Failed, "could not determine config directory");
+        };
+
+        let server = input.server.clone();
+        let timeouts = self.timeouts(&server).await;
+        let result = self
+            .with_connection(&server, move |conn| async move {
+                tools::provision::handler::handle(conn, input, recipe, &config_dir, timeouts).await
+            })
+            .await;
+        self.manifests.invalidate_server(&server).await;
+        result
+    }
+
+    #[tool(
+        description = "Make sure commands a task needs exist on a remote server. Pass needs (e.g. ['rg', 'jq', 'python3'], or 'command=package' when the package is named differently), or leave it out to use the server's needs from servers.toml. Returns JSON with each command's status (available with its path, missing with the package that provides it) and the install command for the detected package manager. Set install=true to install the missing packages (sudo=true for passwordless sudo), with dry_run=true to only show what would run. Call this before a task instead of finding missing tools halfway through."
+    )]
+    async fn remote_bootstrap(
+        &self,
+        Parameters(input): Parameters<tools::RemoteBootstrapInput>,
+    ) -> String {
+        self.maybe_reload_config().await;
+        let configured = {
+            let cfg = self.config.read().await;
+            match cfg.get(&input.server) {
+                Some(entry) => entry.needs.clone(),
+                None => return server_not_found(&cfg, &input.server).to_json(),
+            }
+        };
+        let server = input.server.clone();
+        let timeouts = self.timeouts(&server).await;
+        if !input.installs() {
+            return self
+                .with_connection_retry(&server, move |conn| async move {
+                    tools::remote_bootstrap::handler::handle(conn, input, configured, timeouts)
+                        .await
+                })
+                .await;
+        }
+        if let Err(e) = self.check_readonly(&server, "remote_bootstrap").await {
+            return e;
+        }
+        if let Err(e) = self
+            .check_window(&server, input.override_window.as_deref())
+            .await
+        {
+            return e;
+        }
+        self.with_connection(&server, move |conn| async move {
+            tools::remote_bootstrap::handler::handle(conn, input, configured, timeouts).await
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Check a server for drift from its provisioning recipes without changing anything. Re-evaluates every step of each recipe previously applied with provision (or the one named in 'recipe') and returns JSON listing drifted packages, files, commands, and services with what differs, so you can fix just those steps. Commands without an 'unless' check are listed as unchecked."
+    )]
+    async fn check_drift(&self, Parameters(input): Parameters<tools::CheckDriftInput>) -> String {
+        self.maybe_reload_config().await;
+        let applied = ProvisionHistory::load().applied(&input.server);
+        let plan = {
+            let cfg = self.config.read().await;
+            tools::check_drift::handler::plan_checks(
+                &cfg,
+                &applied,
+                &input.server,
+                input.recipe.as_deref(),
+            )
+        };
+        let plan = match plan {
+            Ok(p) => p,
+            Err(e) => return e.to_json(),
+        };
+        let Some(config_dir) = ServerRegistry::config_path()
+            .ok()
+            .and_then(|p| p.parent().map(Path::to_path_buf))
+        else {
+            return tool_error(ErrorCode::Failed, "could not determine config directory");
+        };
+
+        let server = input.server.clone();
+        self.with_connection(&server, |conn| async move {
+            tools::check_drift::handler::handle(conn, input, plan, &config_dir).await
         })
         .await
     }
 
+    #[tool(
+        description = "Roll a command out across several servers safely. Runs it on a canary server first (default: the first listed), checks an assertion (expect_exit_code, default 0, plus an optional expect_output regex on stdout), then continues through the rest in concurrent batches of batch_size. Aborts when the canary fails or failures after it exceed max_failures (default 0); unreached servers are reported as skipped. Returns a per-server JSON report with exit codes, durations, failure reasons, and output tails."
+    )]
+    async fn remote_rollout(
+        &self,
+        Parameters(input): Parameters<tools::RemoteRolloutInput>,
+    ) -> String {
+        self.maybe_reload_config().await;
+        {
+            let cfg = self.config.read().await;
+            if let Some(missing) = input.servers.iter().find(|s| cfg.get(s).is_none()) {
+                return server_not_found(&cfg, missing).to_json();
+            }
+        }
+        if let Some(reason) = detect_mutation(&input.command) {
+            let action = format!("a command that changes the server ({reason})");
+            for server in &input.servers {
+                if let Err(e) = self.check_readonly(server, &action).await {
+                    return e;
+                }
+            }
+        }
+        for server in &input.servers {
+            if let Err(e) = self
+                .check_window(server, input.override_window.as_deref())
+                .await
+            {
+                return e;
+            }
+        }
+        let timeouts: HashMap<String, TimeoutPolicy> = {
+            let cfg = self.config.read().await;
+            input
+                .servers
+                .iter()
+                .filter_map(|s| Some((s.clone(), TimeoutPolicy::for_server(cfg.get(s)?))))
+                .collect()
+        };
+        let plan = tools::remote_rollout::handler::plan(input, |server| {
+            timeouts.get(server).copied().unwrap_or_default()
+        });
+        let plan = match plan {
+            Ok(p) => p,
+            Err(e) => return tool_error(ErrorCode::InvalidInput, e),
+        };
+
+        let result = tools::remote_rollout::handler::handle(plan.clone(), |server| async move {
+            self.resolve_connection(&server).await
+        })
+        .await;
+        for server in std::iter::once(&plan.canary).chain(plan.batches.iter().flatten()) {
+            self.manifests.invalidate_server(server).await;
+        }
+        result
+    }
+
+    // ── Connection Tools ──────────────────────────────────────────────
+
+    #[tool(
+        description = "List the configured servers as JSON: name, user@host:port, remote path, tags, auth method, system summary and collected metadata, whether a connection is open (with its health, age, and idle time), the last connection error, and a reachability probe of the SSH port (status reachable, unreachable, dns_failed, or dns_timeout, with latency). Probe results are reused for 30 seconds. Set skip_probe=true to list servers without waiting on probes. Pass tags (e.g. ['gpu'] or ['env=staging']) to pick a server by capability or environment instead of by name, name_pattern (e.g. 'web-*') to narrow by name, and connected_only=true for servers with an open connection."
+    )]
+    async fn list_servers(&self, Parameters(input): Parameters<tools::ListServersInput>) -> String {
+        self.maybe_reload_config().await;
+        let servers = self
+            .config
+            .read()
+            .await
+            .servers
+            .iter()
+            .map(|(name, entry)| (name.clone(), entry.clone()))
+            .collect();
+        let changes = std::mem::take(&mut *self.unreported_changes.lock().await);
+        tools::list_servers::handler::handle(servers, &self.pool, &self.probes, input, changes)
+            .await
+    }
+
+    #[tool(
+        description = "Drop a server's pooled SSH connection and close the session. Use this when a connection seems half-dead (commands hang or fail with channel errors). Operations still running on it are aborted, and servers sharing the session are disconnected too; the next tool call for each connects again automatically."
+    )]
+    async fn disconnect_server(
+        &self,
+        Parameters(input): Parameters<tools::DisconnectServerInput>,
+    ) -> String {
+        let server = input.server;
+        if tools::server_connection::handler::disconnect(&self.pool, &server).await {
+            return format!(
+                "Disconnected '{server}'. The next tool call for it reconnects automatically."
+            );
+        }
+        self.maybe_reload_config().await;
+        let cfg = self.config.read().await;
+        if cfg.get(&server).is_some() {
+            format!("'{server}' has no open connection.")
+        } else {
+            server_not_found(&cfg, &server).to_json()
+        }
+    }
+
+    #[tool(
+        description = "Close a server's pooled SSH connection (if any) and open a fresh one from the current config. Use this to recover from a broken connection, or to verify that a server is reachable and authentication works."
+    )]
+    async fn reconnect_server(
+        &self,
+        Parameters(input): Parameters<tools::ReconnectServerInput>,
+    ) -> String {
+        let server = input.server;
+        let dropped = tools::server_connection::handler::disconnect(&self.pool, &server).await;
+        self.maybe_reload_config().await;
+        match self.resolve_connection(&server).await {
+            Ok(conn) => {
+                if dropped {
+                    self.record_reconnect().await;
+                }
+                let params = conn.params();
+                format!(
+                    "{} '{server}' ({}@{}:{}).",
+                    if dropped { "Reconnected" } else { "Connected" },
+                    params.user,
+                    params.host,
+                    params.port,
+                )
+            }
+            Err(e) => e.to_json(),
+        }
+    }
+
+    #[tool(
+        description = "Show every pooled SSH connection as JSON: age, seconds since last activity, channels opened and in use, bytes sent and received on command channels, and health (healthy, saturated when every channel slot is busy, or closed). Use this to debug hanging or slow calls before reaching for disconnect_server."
+    )]
+    async fn pool_stats(&self) -> String {
+        tools::server_connection::handler::stats(&self.pool).await
+    }
+
+    #[tool(
+        description = "Measure the SSH connection to a server: channel open time (the round trip), no-op command time (min, median, max over rounds, default 5), and upload and download throughput in MiB/s with a test payload (payload_kb, default 1024). Returns JSON. Use it to pick the faster of several servers or to tell a slow link from a slow command when syncs drag. Moves the payload each way, so keep it small on metered links."
+    )]
+    async fn benchmark_server(
+        &self,
+        Parameters(input): Parameters<tools::BenchmarkServerInput>,
+    ) -> String {
+        let server = input.server.clone();
+        let timeouts = self.timeouts(&server).await;
+        self.with_connection(&server, move |conn| async move {
+            tools::benchmark_server::handler::handle(conn, input, timeouts).await
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Report usage metrics since the MCP server started: calls and errors per tool, reconnects, a histogram of remote_bash and command template durations, and a histogram of bytes moved per sync. Returns JSON, or the Prometheus text format with format='prometheus'. Metrics are opt-in with [metrics] enabled = true in servers.toml."
+    )]
+    async fn metrics(&self, Parameters(input): Parameters<tools::MetricsInput>) -> String {
+        match &self.metrics {
+            Some(metrics) => tools::metrics::handler::handle(Some(&*metrics.lock().await), &input),
+            None => tools::metrics::handler::handle(None, &input),
+        }
+    }
+
+    // ── Sync Tools ────────────────────────────────────────────────────
+
+    #[tool(
+        description = "Push local file(s) to a connected remote server. Supports single files and entire directories. Directory walks respect .gitignore rules and skip symlinks. Use the 'exclude' parameter for additional exclusion patterns (gitignore syntax), 'include' to push only files matching some patterns, or 'files' to push only specific files of a directory. The transfer strategy (cat, tar, sftp, rsync) is chosen automatically and reported with throughput in the result; pass 'strategy' to override. Pass verify=true to compare sha256 of every transferred file afterwards."
+    )]
+    async fn sync_push(
+        &self,
+        Parameters(mut input): Parameters<tools::SyncPushInput>,
+        context: RequestContext<RoleServer>,
+    ) -> String {
+        if input.remote_path.is_none() {
+            input.remote_path = self
+                .project_remote_path(&input.server, &input.local_path)
+                .await;
+        }
+        if let Err(e) = self.check_readonly(&input.server, "sync_push").await {
+            return e;
+        }
+        if let Err(e) = self
+            .check_window(&input.server, input.override_window.as_deref())
+            .await
+        {
+            return e;
+        }
+        let server = input.server.clone();
+        let timeouts = self.timeouts(&server).await;
+        let cache = Arc::clone(&self.manifests);
+        let progress = Progress::from_context(&context);
+        let call = self.with_connection(&server, move |conn| async move {
+            tools::sync_push::handler::handle(conn, input, cache, progress, timeouts).await
+        });
+        let result = self.with_hooks(&server, HookStage::SyncPush, call).await;
+        self.manifests.invalidate_server(&server).await;
+        result
+    }
+
+    #[tool(
+        description = "Compare a local directory against its remote counterpart by sha256. Returns local_only, remote_only, and modified paths plus an unchanged count. Respects .gitignore and 'exclude' patterns. The remote manifest is cached briefly, so a follow-up sync_push with changed_only=true reuses it. The response includes a journal_token when the remote has inotifywait or fswatch; pass it back as 'since' to get only the paths changed since that call without re-hashing."
+    )]
+    async fn sync_status(
+        &self,
+        Parameters(mut input): Parameters<tools::SyncStatusInput>,
+    ) -> String {
+        if input.remote_path.is_none() {
+            input.remote_path = self
+                .project_remote_path(&input.server, &input.local_path)
+                .await;
+        }
+        let server = input.server.clone();
+        let timeouts = self.timeouts(&server).await;
+        let cache = Arc::clone(&self.manifests);
+        self.with_connection_retry(&server, move |conn| async move {
+            tools::sync_status::handler::handle(conn, input, cache, timeouts).await
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Pull remote file(s) from a connected server to the local machine. Supports single files and entire directories. Directory pulls skip .git, whatever the remote .gitignore ignores, and 'exclude' patterns, and are refused with a breakdown of the largest entries when they exceed a size cap (512 MiB unless 'max_bytes' or the config says otherwise). Use 'include' patterns or the 'files' parameter to pull a subset of a directory. The transfer strategy (cat, tar, sftp, rsync) is chosen automatically and reported with throughput in the result; pass 'strategy' to override. Pass verify=true to compare sha256 of every transferred file afterwards."
+    )]
+    async fn sync_pull(
+        &self,
+        Parameters(mut input): Parameters<tools::SyncPullInput>,
+        context: RequestContext<RoleServer>,
+    ) -> String {
+        let server = input.server.clone();
+        let timeouts = self.timeouts(&server).await;
+        self.maybe_reload_config().await;
+        let max_pull_bytes = {
+            let cfg = self.config.read().await;
+            if input.local_path.is_none() {
+                input.local_path = cfg.get(&server).and_then(|entry| {
+                    let remote = normalize_remote_path(&input.remote_path, &entry.remote_path);
+                    cfg.local_for_remote(&server, &remote)
+                        .map(|local| local.to_string_lossy().to_string())
+                });
+            }
+            cfg.sync.as_ref().and_then(|s| s.max_pull_bytes)
+        };
+        let progress = Progress::from_context(&context);
+        let call = self.with_read_connection(&server, move |conn| async move {
+            tools::sync_pull::handler::handle(conn, input, max_pull_bytes, progress, timeouts).await
+        });
+        self.with_hooks(&server, HookStage::SyncPull, call).await
+    }
+
     // ── Internals ─────────────────────────────────────────────────────
 
+    /// Remote counterpart of `local_path` when it lies inside a project
+    /// mapped to `server`. Relative paths are taken from the current
+    /// directory, which MCP clients set to the project root.
+    async fn project_remote_path(&self, server: &str, local_path: &str) -> Option<String> {
+        let local = std::fs::canonicalize(local_path)
+            .or_else(|_| std::path::absolute(local_path))
+            .ok()?;
+        self.maybe_reload_config().await;
+        self.config.read().await.remote_for_local(server, &local)
+    }
+
     /// Execute a closure with a named connection, auto-connecting from config if needed.
     ///
     /// After execution, checks if the connection died during the operation and
@@ -137,17 +1067,125 @@ impl RemoteSessionServer {
         self.maybe_reload_config().await;
         let conn = match self.resolve_connection(server).await {
             Ok(conn) => conn,
-            Err(msg) => return msg,
+            Err(e) => return e.to_json(),
+        };
+        let conn_ref = Arc::clone(&conn);
+        let result = f.call(conn).await;
+        self.cleanup_if_dead(server, &conn_ref).await;
+        result
+    }
+
+    /// Like [`with_connection`](Self::with_connection), for idempotent
+    /// operations: when the connection dies during the call (a failed channel
+    /// open, or keepalive declaring the session dead) and the call errored,
+    /// reconnect and run it once more.
+    async fn with_connection_retry(
+        &self,
+        server: &str,
+        f: impl AsyncConnectionFn + Clone,
+    ) -> String {
+        self.maybe_reload_config().await;
+        let conn = match self.resolve_connection(server).await {
+            Ok(conn) => conn,
+            Err(e) => return e.to_json(),
+        };
+        self.call_with_retry(server, conn, f).await
+    }
+
+    /// Like [`with_connection_retry`](Self::with_connection_retry), but runs
+    /// on the server's `read_replica` when one is configured. Falls back to
+    /// the server itself when the replica can't be reached.
+    async fn with_read_connection(
+        &self,
+        server: &str,
+        f: impl AsyncConnectionFn + Clone,
+    ) -> String {
+        self.maybe_reload_config().await;
+        let replica = {
+            let cfg = self.config.read().await;
+            cfg.read_replica(server).map(str::to_string)
+        };
+        if let Some(replica) = replica {
+            match self.resolve_connection(&replica).await {
+                Ok(conn) => {
+                    tracing::debug!("Routing read for '{server}' to replica '{replica}'");
+                    return self.call_with_retry(&replica, conn, f).await;
+                }
+                Err(e) => tracing::warn!(
+                    "Read replica '{replica}' of '{server}' is unavailable, using '{server}': {}",
+                    e.message
+                ),
+            }
+        }
+        self.with_connection_retry(server, f).await
+    }
+
+    /// Run `f` on `conn`. If it returns an error and the connection died
+    /// meanwhile, evict it, reconnect, and run `f` once more.
+    async fn call_with_retry(
+        &self,
+        server: &str,
+        conn: Arc<SshConnection>,
+        f: impl AsyncConnectionFn + Clone,
+    ) -> String {
+        let result = f.clone().call(Arc::clone(&conn)).await;
+        let died = conn.is_closed().await;
+        self.cleanup_if_dead(server, &conn).await;
+        let Some(error) = died.then(|| ToolError::from_json(&result)).flatten() else {
+            return result;
+        };
+        tracing::info!("Connection '{server}' died during the call, reconnecting to retry once");
+        let conn = match self.resolve_connection(server).await {
+            Ok(conn) => conn,
+            Err(retry) => {
+                let message = format!(
+                    "{}\nAutomatic retry failed: {}",
+                    error.message, retry.message
+                );
+                return tool_error(error.code, message);
+            }
         };
+        self.record_reconnect().await;
         let conn_ref = Arc::clone(&conn);
         let result = f.call(conn).await;
         self.cleanup_if_dead(server, &conn_ref).await;
         result
     }
 
+    async fn record_reconnect(&self) {
+        if let Some(metrics) = &self.metrics {
+            metrics.lock().await.record_reconnect();
+        }
+    }
+
+    /// Count a finished tool call, and the bytes it synced.
+    async fn record_call(
+        &self,
+        tool: &str,
+        elapsed: Duration,
+        command: bool,
+        result: &Result<CallToolResult, ErrorData>,
+    ) {
+        let Some(metrics) = &self.metrics else {
+            return;
+        };
+        let text = result_text(result);
+        let error = match result {
+            Ok(r) => r.is_error == Some(true),
+            Err(_) => true,
+        };
+        let mut metrics = metrics.lock().await;
+        metrics.record_call(tool, elapsed, error, command);
+        if matches!(tool, "sync_push" | "sync_pull") {
+            if let Some(bytes) = text.and_then(metrics::synced_bytes) {
+                metrics.record_synced(bytes);
+            }
+        }
+    }
+
     /// Resolve a connection for the given server: return from pool, or
     /// auto-connect from config under a per-server lock.
-    async fn resolve_connection(&self, server: &str) -> Result<Arc<SshConnection>, String> {
+    async fn resolve_connection(&self, server: &str) -> Result<Arc<SshConnection>, ToolError> {
         // Fast path: already in the pool — no lock needed.
         if let Some(conn) = self.pool.get(server).await {
             return Ok(conn);
@@ -156,22 +1194,9 @@ impl RemoteSessionServer {
         // Server not in pool — check config for auto-connect or produce an error.
         let params = {
             let cfg = self.config.read().await;
-            if let Some(entry) = cfg.get(server) {
-                params_from_config(server, entry)
-            } else {
-                let names: Vec<&str> = cfg.servers.keys().map(String::as_str).collect();
-                return Err(if names.is_empty() {
-                    format!(
-                        "Error: server '{server}' not found. No servers are configured. \
-                         Add servers via 'ssh-hub add <name> <connection>'."
-                    )
-                } else {
-                    format!(
-                        "Error: server '{}' not found. Configured servers: {}.",
-                        server,
-                        names.join(", ")
-                    )
-                });
+            match cfg.get(server) {
+                Some(entry) => params_from_config(server, entry),
+                None => return Err(server_not_found(&cfg, server)),
             }
         };
 
@@ -183,11 +1208,220 @@ impl RemoteSessionServer {
         if let Some(conn) = self.pool.get(server).await {
             return Ok(conn);
         }
+        if let Some(conn) = self.pool.share(server, &params).await {
+            return Ok(conn);
+        }
+
+        // Auto-connect from config, looking up a new address if the
+        // configured one doesn't answer
+        let failed = |e: anyhow::Error| {
+            let code = match ErrorCode::of(&e) {
+                ErrorCode::Failed => ErrorCode::ConnectionFailed,
+                code => code,
+            };
+            ToolError::new(
+                code,
+                format!("server '{server}' is configured but auto-connect failed: {e}"),
+            )
+        };
+        let outcome = match self.try_auto_connect(server, params).await {
+            Err(e) if is_unreachable(&e) => match self.rediscover(server).await {
+                Ok(Some(params)) => self.try_auto_connect(server, params).await,
+                Ok(None) => Err(e),
+                Err(lookup) => Err(anyhow::anyhow!("{e} (discovery failed: {lookup})")),
+            },
+            outcome => outcome,
+        };
+        match outcome {
+            Ok(conn) => Ok(conn),
+            Err(e) => {
+                self.pool.record_error(server, e.to_string()).await;
+                Err(failed(e))
+            }
+        }
+    }
+
+    /// Run the server's `discovery` lookup and save the address it finds to
+    /// the config. Returns the new connection parameters, or `None` when the
+    /// server has no `discovery` or its address hasn't changed.
+    async fn rediscover(&self, server: &str) -> Result<Option<ConnectionParams>> {
+        let lookup = {
+            let cfg = self.config.read().await;
+            match cfg.get(server).and_then(|e| e.discovery.as_ref()) {
+                Some(discovery) => discovery.lookup_command()?,
+                None => return Ok(None),
+            }
+        };
+        let address =
+            discovery::run_lookup("discovery", &lookup, discovery::LOOKUP_TIMEOUT).await?;
 
-        // Auto-connect from config
-        self.try_auto_connect(server, params).await.map_err(|e| {
-            format!("Error: server '{server}' is configured but auto-connect failed: {e}")
+        let name = server.to_string();
+        let updated = tokio::task::spawn_blocking(move || {
+            ServerRegistry::modify(|config| {
+                let entry = config.servers.get_mut(&name)?;
+                entry
+                    .set_discovered_address(&address)
+                    .then(|| (address, params_from_config(&name, entry)))
+            })
         })
+        .await??;
+        let Some((address, params)) = updated else {
+            tracing::info!("Discovery for '{server}' found the configured address");
+            return Ok(None);
+        };
+        tracing::info!("Discovery moved '{server}' to {address}");
+        // Pick up the saved config now, so the connection made with the new
+        // address isn't evicted as stale by the next reload.
+        self.maybe_reload_config().await;
+        Ok(Some(params))
+    }
+
+    /// Refuse `action` on a server configured with `readonly = true`.
+    async fn check_readonly(&self, server: &str, action: &str) -> Result<(), String> {
+        self.maybe_reload_config().await;
+        let cfg = self.config.read().await;
+        if cfg.get(server).is_some_and(|e| e.readonly) {
+            return Err(tool_error(
+                ErrorCode::Readonly,
+                format!(
+                    "'{server}' is a readonly server, so {action} is not allowed. \
+                     Only diagnostics that leave the server unchanged can run there."
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Refuse a write-class tool call on `server` outside its maintenance
+    /// window, unless the config allows `override_reason` to confirm it.
+    async fn check_window(
+        &self,
+        server: &str,
+        override_reason: Option<&str>,
+    ) -> Result<(), String> {
+        self.maybe_reload_config().await;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let cfg = self.config.read().await;
+        let config = cfg.get(server).and_then(|e| e.maintenance.as_ref());
+        maintenance::enforce(server, config, override_reason, now).map_err(|e| e.to_json())
+    }
+
+    /// Run `call` under the context budget of `family`: refuse it when the
+    /// per-minute budget is spent, and cut its output down to what is left.
+    async fn within_budget(
+        &self,
+        family: ToolFamily,
+        call: impl Future<Output = String>,
+    ) -> String {
+        let limits = {
+            let cfg = self.config.read().await;
+            Limits::for_family(family, cfg.budget.as_ref())
+        };
+        if let Err(e) = self
+            .budget
+            .lock()
+            .await
+            .check(family, limits, Instant::now())
+        {
+            return e.to_json();
+        }
+        let output = call.await;
+        self.budget
+            .lock()
+            .await
+            .charge(family, limits, output, Instant::now())
+    }
+
+    /// Count a call against the `rate_limits` of the server it names, or
+    /// refuse it when a limit is reached. Returns the limited server and
+    /// tool class when synced bytes should be recorded afterwards.
+    async fn check_rate_limit(
+        &self,
+        class: Option<ToolClass>,
+        request: &CallToolRequestParam,
+    ) -> Result<Option<(String, ToolClass)>, ToolError> {
+        let Some(class) = class else {
+            return Ok(None);
+        };
+        let Some(server) = request
+            .arguments
+            .as_ref()
+            .and_then(|args| args.get("server"))
+            .and_then(serde_json::Value::as_str)
+        else {
+            return Ok(None);
+        };
+        let limit = {
+            let cfg = self.config.read().await;
+            cfg.get(server)
+                .and_then(|e| e.rate_limits.as_ref())
+                .and_then(|limits| limits.for_class(class))
+        };
+        let Some(limit) = limit else {
+            return Ok(None);
+        };
+        self.rate_usage
+            .lock()
+            .await
+            .check(server, class, limit, Instant::now())?;
+        let counts_bytes = limit.bytes_per_hour.is_some_and(|max| max > 0);
+        Ok(counts_bytes.then(|| (server.to_string(), class)))
+    }
+
+    /// Run `call` between the server's `pre_*` and `post_*` hooks for
+    /// `stage`, and list the hooks that ran in its output. A failed `pre_*`
+    /// hook replaces the call with an error; `post_*` hooks only follow a
+    /// call that succeeded.
+    async fn with_hooks(
+        &self,
+        server: &str,
+        stage: HookStage,
+        call: impl Future<Output = String>,
+    ) -> String {
+        self.maybe_reload_config().await;
+        let hooks = {
+            let cfg = self.config.read().await;
+            cfg.get(server).and_then(|e| e.hooks.clone())
+        };
+        let Some(hooks) = hooks else {
+            return call.await;
+        };
+        let mut runs = Vec::new();
+        let pre = format!("pre_{}", stage.name());
+        for hook in hooks.pre(stage) {
+            let run = self.run_hook(server, &pre, hook).await;
+            if !run.succeeded() {
+                return hooks::attach_report(run.refusal(stage), &runs);
+            }
+            runs.push(run);
+        }
+        let output = call.await;
+        if !ToolError::is_error(&output) {
+            let post = format!("post_{}", stage.name());
+            for hook in hooks.post(stage) {
+                runs.push(self.run_hook(server, &post, hook).await);
+            }
+        }
+        hooks::attach_report(output, &runs)
+    }
+
+    async fn run_hook(&self, server: &str, name: &str, hook: &Hook) -> HookRun {
+        let command = match hook.target() {
+            Ok(HookTarget::Local(command)) => {
+                return hooks::run_local(name, command, hook.timeout()).await;
+            }
+            Ok(HookTarget::Remote(command)) => command,
+            Err(e) => return HookRun::failed(name, "unknown", "", ErrorCode::InvalidInput, e),
+        };
+        let conn = match self.resolve_connection(server).await {
+            Ok(conn) => conn,
+            Err(e) => return HookRun::failed(name, "remote", command, e.code, e.message),
+        };
+        let run = hooks::run_remote(&conn, name, command, hook.timeout()).await;
+        self.cleanup_if_dead(server, &conn).await;
+        run
     }
 
     /// Remove a connection from the pool if it died during an operation.
@@ -198,6 +1432,30 @@ impl RemoteSessionServer {
         }
     }
 
+    /// Directory scratch directories are made in on `server`.
+    async fn scratch_root(&self, server: &str) -> String {
+        let cfg = self.config.read().await;
+        cfg.get(server)
+            .and_then(|e| e.scratch_root.clone())
+            .unwrap_or_else(|| tools::workspace::handler::DEFAULT_SCRATCH_ROOT.to_string())
+    }
+
+    /// Timeout settings for commands on `server`.
+    async fn timeouts(&self, server: &str) -> TimeoutPolicy {
+        let cfg = self.config.read().await;
+        cfg.get(server)
+            .map(TimeoutPolicy::for_server)
+            .unwrap_or_default()
+    }
+
+    /// Directory snapshots are kept in on `server`.
+    async fn snapshot_dir(&self, server: &str) -> String {
+        let cfg = self.config.read().await;
+        cfg.get(server)
+            .and_then(|e| e.snapshot_dir.clone())
+            .unwrap_or_else(|| tools::dir_snapshot::DEFAULT_SNAPSHOT_DIR.to_string())
+    }
+
     /// Connect to a server using pre-resolved params and add it to the pool.
     async fn try_auto_connect(
         &self,
@@ -205,28 +1463,45 @@ impl RemoteSessionServer {
         params: ConnectionParams,
     ) -> Result<Arc<SshConnection>> {
         tracing::info!("Auto-connecting to configured server '{}'", server);
-        let conn = SshConnection::connect(params).await?;
-        Ok(self.pool.insert(server.to_string(), conn).await)
+        let conn = match self.host_key_confirm().await {
+            Some(confirm) => Box::pin(SshConnection::connect_confirming(params, &confirm)).await?,
+            None => Box::pin(SshConnection::connect(params)).await?,
+        };
+        let conn = self.pool.insert(server.to_string(), conn).await;
+        // Probe the remote's userland in the background so the first sync
+        // doesn't wait on it
+        let probe = Arc::clone(&conn);
+        tokio::spawn(async move { probe.capabilities().await });
+        Ok(conn)
+    }
+
+    /// A connected client that can be asked to confirm new host keys.
+    async fn host_key_confirm(&self) -> Option<ElicitHostKey> {
+        let peers = self.peers.read().await;
+        peers
+            .iter()
+            .find(|peer| !peer.is_transport_closed() && peer.supports_elicitation())
+            .cloned()
+            .map(ElicitHostKey)
     }
 
     /// Check if the config file has been modified since last load, and reload
     /// if so. Only evicts connections for servers whose connection-relevant
     /// fields changed or that were removed — unchanged servers keep their
     /// pooled connections.
-    async fn maybe_reload_config(&self) {
-        let Ok(path) = ServerRegistry::config_path() else {
-            return;
-        };
-
-        let Ok(current_mtime) = std::fs::metadata(&path).and_then(|m| m.modified()) else {
-            return;
-        };
+    ///
+    /// Called by the config watcher on file events and, as a fallback for
+    /// platforms where watching fails, before every tool call. Returns `None`
+    /// when nothing was reloaded.
+    async fn maybe_reload_config(&self) -> Option<ConfigReload> {
+        let path = ServerRegistry::config_path().ok()?;
+        let current_mtime = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
 
         // Fast path: mtime unchanged (read lock only).
         {
             let stored = self.config_mtime.read().await;
             if *stored == Some(current_mtime) {
-                return;
+                return None;
             }
         }
 
@@ -235,21 +1510,29 @@ impl RemoteSessionServer {
             Ok(c) => c,
             Err(e) => {
                 tracing::warn!("Config file changed but reload failed: {e}");
-                return;
+                return None;
             }
         };
 
         // Diff old vs new to find servers that need reconnection.
-        let servers_to_evict: Vec<String> = {
+        let (changes, tools_changed) = {
             let old_cfg = self.config.read().await;
-            old_cfg.changed_servers(&new_config)
+            (
+                old_cfg.server_changes(&new_config),
+                old_cfg.templates != new_config.templates
+                    || old_cfg.allow_local != new_config.allow_local,
+            )
         };
+        let servers_to_evict: Vec<&String> =
+            changes.removed.iter().chain(&changes.changed).collect();
 
         tracing::info!(
-            "Config reloaded ({} servers, {} connections evicted)",
+            "Config reloaded ({} servers, {} added, {} connections evicted)",
             new_config.servers.len(),
+            changes.added.len(),
             servers_to_evict.len(),
         );
+        let servers = new_config.servers.len();
 
         // Swap in new config.
         {
@@ -266,14 +1549,188 @@ impl RemoteSessionServer {
         // Evict and cleanly disconnect changed/removed servers concurrently.
         if !servers_to_evict.is_empty() {
             let mut futs = Vec::new();
-            for name in &servers_to_evict {
-                if let Some(conn) = self.pool.remove(name).await {
+            for name in servers_to_evict {
+                if let Some(conn) = self.pool.remove_with_sharers(name).await {
                     tracing::debug!("Evicting connection '{name}' (config changed)");
                     futs.push(async move { conn.disconnect().await });
                 }
             }
             join_all(futs).await;
         }
+
+        if !changes.is_empty() {
+            self.unreported_changes.lock().await.merge(changes.clone());
+        }
+
+        Some(ConfigReload {
+            servers,
+            changes,
+            tools_changed,
+        })
+    }
+
+    /// Watch the config directory and reload as soon as `servers.toml`
+    /// changes, so edits (including a concurrent `ssh-hub add`) take effect
+    /// and stale connections are evicted without waiting for the next tool
+    /// call. Each reload is announced to every connected client as a log
+    /// notification.
+    ///
+    /// The directory is watched rather than the file because editors and
+    /// `ServerRegistry::save` may replace the file instead of writing in place.
+    /// The returned watcher must be kept alive for events to keep flowing.
+    fn spawn_config_watcher(&self) -> Option<RecommendedWatcher> {
+        let path = ServerRegistry::config_path().ok()?;
+        let dir = path.parent()?.to_path_buf();
+        let file_name = path.file_name()?.to_os_string();
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            tracing::debug!(
+                "Config watcher disabled, cannot create {}: {e}",
+                dir.display()
+            );
+            return None;
+        }
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut watcher =
+            match notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+                if let Ok(event) = res {
+                    if event
+                        .paths
+                        .iter()
+                        .any(|p| p.file_name() == Some(&file_name))
+                    {
+                        let _ = tx.send(());
+                    }
+                }
+            }) {
+                Ok(w) => w,
+                Err(e) => {
+                    tracing::debug!("Config watcher disabled: {e}");
+                    return None;
+                }
+            };
+        if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            tracing::debug!(
+                "Config watcher disabled, cannot watch {}: {e}",
+                dir.display()
+            );
+            return None;
+        }
+
+        let server = self.clone();
+        tokio::spawn(async move {
+            while rx.recv().await.is_some() {
+                // Saves arrive as bursts of events — settle, then reload once.
+                tokio::time::sleep(CONFIG_WATCH_DEBOUNCE).await;
+                while rx.try_recv().is_ok() {}
+
+                if let Some(reload) = server.maybe_reload_config().await {
+                    let mut peers = server.peers.write().await;
+                    peers.retain(|peer| !peer.is_transport_closed());
+                    for peer in peers.iter() {
+                        announce_reload(peer, &reload).await;
+                    }
+                }
+            }
+        });
+
+        tracing::debug!("Watching {} for config changes", dir.display());
+        Some(watcher)
+    }
+
+    // ── Command templates ─────────────────────────────────────────────
+
+    /// Tool definitions for every usable command template. Templates with an
+    /// invalid name, a name that shadows a built-in tool, or a malformed
+    /// definition are skipped with a warning.
+    async fn template_tools(&self) -> Vec<Tool> {
+        self.maybe_reload_config().await;
+        let cfg = self.config.read().await;
+        cfg.templates
+            .iter()
+            .filter(|(name, template)| self.usable_template(name, template))
+            .map(|(name, template)| template.to_tool(name))
+            .collect()
+    }
+
+    fn usable_template(&self, name: &str, template: &CommandTemplate) -> bool {
+        if !command_template::is_valid_tool_name(name) {
+            tracing::warn!("Skipping template '{name}': invalid tool name");
+            return false;
+        }
+        if self.tool_router.has_route(name) {
+            tracing::warn!("Skipping template '{name}': shadows a built-in tool");
+            return false;
+        }
+        if let Err(e) = template.validate() {
+            tracing::warn!("Skipping template '{name}': {e}");
+            return false;
+        }
+        true
+    }
+
+    /// Run the command template named by `request`, or return `None` if no
+    /// such template exists.
+    async fn call_template(&self, request: &CallToolRequestParam) -> Option<String> {
+        self.maybe_reload_config().await;
+        let template = {
+            let cfg = self.config.read().await;
+            cfg.templates.get(request.name.as_ref())?.clone()
+        };
+        let name = request.name.as_ref();
+        if !self.usable_template(name, &template) {
+            return Some(tool_error(
+                ErrorCode::InvalidInput,
+                format!("template '{name}' is misconfigured (see server logs)"),
+            ));
+        }
+
+        let args = request.arguments.clone().unwrap_or_default();
+        let Some(server) = args
+            .get("server")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+        else {
+            return Some(tool_error(
+                ErrorCode::InvalidInput,
+                "missing required argument 'server'",
+            ));
+        };
+        if !template.allows_server(&server) {
+            return Some(tool_error(
+                ErrorCode::InvalidInput,
+                format!(
+                    "template '{name}' is not enabled for server '{server}'. Allowed: {}.",
+                    template.servers.join(", ")
+                ),
+            ));
+        }
+        let command = match template.render(&args) {
+            Ok(c) => c,
+            Err(e) => return Some(tool_error(ErrorCode::InvalidInput, e)),
+        };
+        if let Some(reason) = detect_mutation(&command) {
+            let action = format!("template '{name}', which changes the server ({reason})");
+            if let Err(e) = self.check_readonly(&server, &action).await {
+                return Some(e);
+            }
+        }
+        let override_reason = args.get("override_window").and_then(|v| v.as_str());
+        if let Err(e) = self.check_window(&server, override_reason).await {
+            return Some(e);
+        }
+
+        let timeout = match template.timeout_ms(self.timeouts(&server).await) {
+            Ok(timeout) => timeout,
+            Err(e) => return Some(tool_error(ErrorCode::InvalidInput, e)),
+        };
+        let tool = name.to_string();
+        let call = self.with_connection(&server, move |conn| async move {
+            command_template::handle(conn, command, timeout, &tool).await
+        });
+        let result = self.within_budget(ToolFamily::Bash, call).await;
+        self.manifests.invalidate_server(&server).await;
+        Some(result)
     }
 
     /// Run the MCP server on stdio.
@@ -282,14 +1739,276 @@ impl RemoteSessionServer {
     ///
     /// Returns an error if the stdio transport or MCP service fails.
     pub async fn run(self) -> Result<()> {
-        let transport = (stdin(), stdout());
         tracing::info!("Starting MCP server on stdio");
-        let service = self.serve(transport).await?;
+        let _watcher = self.spawn_config_watcher();
+        let result = Box::pin(self.clone().serve_client((stdin(), stdout()))).await;
+        self.cleanup_workspaces().await;
+        self.cleanup_session_dirs().await;
+        result
+    }
+
+    /// Run the MCP server on stdio and for every client relayed through the
+    /// broker socket, until all of them have disconnected.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stdio transport or MCP service fails.
+    #[cfg(unix)]
+    pub async fn run_shared(self, listener: tokio::net::UnixListener) -> Result<()> {
+        tracing::info!("Starting MCP server on stdio, shared through the broker socket");
+        let _watcher = self.spawn_config_watcher();
+        let mut clients = tokio::task::JoinSet::new();
+        clients.spawn(self.clone().serve_client((stdin(), stdout())));
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => self.accept_relayed(accepted, &mut clients),
+                Some(finished) = clients.join_next() => {
+                    log_client_exit(finished);
+                    if clients.is_empty() {
+                        tracing::info!("All clients disconnected, shutting down");
+                        self.cleanup_workspaces().await;
+                        self.cleanup_session_dirs().await;
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Serve clients relayed through the broker socket, with no stdio client
+    /// of its own, until the process is interrupted or terminated. This is
+    /// `ssh-hub daemon run`, which a systemd user unit or launchd agent keeps
+    /// running, so the pool stays warm when no client is connected.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the signal handlers cannot be installed.
+    #[cfg(unix)]
+    pub async fn run_daemon(self, listener: tokio::net::UnixListener) -> Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        tracing::info!("Starting MCP server as a daemon on the broker socket");
+        let _watcher = self.spawn_config_watcher();
+        let mut terminate = signal(SignalKind::terminate())?;
+        let mut interrupt = signal(SignalKind::interrupt())?;
+        let mut clients = tokio::task::JoinSet::new();
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => self.accept_relayed(accepted, &mut clients),
+                Some(finished) = clients.join_next() => log_client_exit(finished),
+                _ = terminate.recv() => break,
+                _ = interrupt.recv() => break,
+            }
+        }
+        tracing::info!("Daemon stopping, closing {} client sessions", clients.len());
+        clients.abort_all();
+        self.cleanup_workspaces().await;
+        self.cleanup_session_dirs().await;
+        Ok(())
+    }
+
+    /// Start serving a client that connected to the broker socket.
+    #[cfg(unix)]
+    fn accept_relayed(
+        &self,
+        accepted: std::io::Result<(tokio::net::UnixStream, tokio::net::unix::SocketAddr)>,
+        clients: &mut tokio::task::JoinSet<Result<()>>,
+    ) {
+        match accepted {
+            Ok((stream, _)) => {
+                tracing::info!("Relayed client connected");
+                clients.spawn(self.clone().serve_client(stream));
+            }
+            Err(e) => tracing::warn!("Broker socket accept failed: {e}"),
+        }
+    }
+
+    /// Remove this session's scratch directories before the process exits.
+    async fn cleanup_session_dirs(&self) {
+        for (server, path) in self.session_dirs.take_all().await {
+            let dir = path.clone();
+            let cleanup =
+                self.with_connection(&server, move |conn: Arc<SshConnection>| async move {
+                    match tools::workspace::session::remove(&conn, &dir).await {
+                        Ok(()) => "removed".to_string(),
+                        Err(e) => format!("failed: {e}"),
+                    }
+                });
+            if let Ok(result) = tokio::time::timeout(WORKSPACE_SHUTDOWN_TIMEOUT, cleanup).await {
+                tracing::info!("Session directory {path} on '{server}': {result}");
+            } else {
+                tracing::warn!("Removing session directory {path} on '{server}' timed out");
+            }
+        }
+    }
+
+    /// Remove the workspaces this process created, before it exits. Ones
+    /// that can't be reached now are removed once they expire.
+    async fn cleanup_workspaces(&self) {
+        let mut servers: Vec<String> = WorkspaceRegistry::load()
+            .owned_by(std::process::id())
+            .into_iter()
+            .map(|w| w.server)
+            .collect();
+        servers.sort_unstable();
+        servers.dedup();
+        for server in servers {
+            let name = server.clone();
+            let cleanup = self.with_connection(&server, move |conn| async move {
+                tools::workspace::handler::cleanup_owned(conn, &name).await
+            });
+            if let Ok(result) = tokio::time::timeout(WORKSPACE_SHUTDOWN_TIMEOUT, cleanup).await {
+                tracing::info!("Workspace cleanup on '{server}': {result}");
+            } else {
+                tracing::warn!("Workspace cleanup on '{server}' timed out");
+            }
+        }
+    }
+
+    /// Serve one MCP client until it disconnects.
+    async fn serve_client<T, E, A>(self, transport: T) -> Result<()>
+    where
+        T: rmcp::transport::IntoTransport<RoleServer, E, A>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let service = self.clone().serve(transport).await?;
+        self.peers.write().await.push(service.peer().clone());
         service.waiting().await?;
         Ok(())
     }
 }
 
+/// Log how a client session served over the broker socket ended.
+#[cfg(unix)]
+fn log_client_exit(finished: Result<Result<()>, tokio::task::JoinError>) {
+    match finished {
+        Ok(Err(e)) => tracing::warn!("Client session failed: {e}"),
+        Err(e) => tracing::warn!("Client session panicked: {e}"),
+        Ok(Ok(())) => tracing::debug!("Client disconnected"),
+    }
+}
+
+/// Summary of a config reload, announced to the client.
+struct ConfigReload {
+    servers: usize,
+    /// Servers added, removed, or changed. Connections to removed and
+    /// changed servers were evicted.
+    changes: ServerChanges,
+    /// Whether the tool list changed: command templates, or `allow_local`.
+    tools_changed: bool,
+}
+
+/// Send a log notification describing a config reload. Clients that don't
+/// display server logs simply ignore it. When templates or `allow_local`
+/// changed, or servers came or went, the tool list is announced as changed
+/// too, so clients that refresh their tools pick up the new set of servers
+/// mid-conversation.
+async fn announce_reload(peer: &Peer<RoleServer>, reload: &ConfigReload) {
+    let param = LoggingMessageNotificationParam {
+        level: LoggingLevel::Info,
+        logger: Some("ssh-hub".to_string()),
+        data: serde_json::json!({
+            "event": "config_reloaded",
+            "servers": reload.servers,
+            "added": reload.changes.added,
+            "removed": reload.changes.removed,
+            "changed": reload.changes.changed,
+        }),
+    };
+    if let Err(e) = peer.notify_logging_message(param).await {
+        tracing::debug!("Failed to send config reload notification: {e}");
+    }
+    if reload.tools_changed || reload.changes.servers_added_or_removed() {
+        if let Err(e) = peer.notify_tool_list_changed().await {
+            tracing::debug!("Failed to send tool list change notification: {e}");
+        }
+    }
+}
+
+/// Asks the user of an MCP client to confirm a new host key, through
+/// elicitation.
+struct ElicitHostKey(Peer<RoleServer>);
+
+/// The user's answer when asked to confirm a new host key.
+#[derive(Deserialize, JsonSchema)]
+struct HostKeyAnswer {
+    /// Trust this key and add it to `~/.ssh/known_hosts`.
+    trust: bool,
+}
+
+rmcp::elicit_safe!(HostKeyAnswer);
+
+impl HostKeyConfirm for ElicitHostKey {
+    fn confirm<'a>(
+        &'a self,
+        key: &'a NewHostKey,
+    ) -> Pin<Box<dyn Future<Output = Result<bool>> + Send + 'a>> {
+        Box::pin(async move {
+            let answer = self
+                .0
+                .elicit_with_timeout::<HostKeyAnswer>(key.prompt(), Some(HOST_KEY_CONFIRM_TIMEOUT))
+                .await;
+            match answer {
+                Ok(answer) => Ok(answer.is_some_and(|a| a.trust)),
+                Err(ElicitationError::UserDeclined | ElicitationError::UserCancelled) => Ok(false),
+                Err(e) => Err(anyhow::anyhow!(
+                    "could not ask to confirm host key {}: {e}",
+                    key.fingerprint()
+                )),
+            }
+        })
+    }
+}
+
+/// Text of the first content item of a successful call.
+fn result_text(result: &Result<CallToolResult, ErrorData>) -> Option<&str> {
+    result.as_ref().ok().and_then(|r| {
+        r.content
+            .first()
+            .and_then(|c| c.as_text())
+            .map(|t| t.text.as_str())
+    })
+}
+
+/// Apply the output cap to the plain-text blocks of a tool's result. JSON
+/// is left alone: tools returning JSON cap their own unbounded fields so it
+/// stays valid.
+async fn fit_result(mut result: CallToolResult, tool: &str) -> CallToolResult {
+    for content in &mut result.content {
+        if let RawContent::Text(text) = &mut content.raw {
+            let oversized = text.text.len() > MAX_INLINE_OUTPUT;
+            if oversized && serde_json::from_str::<serde::de::IgnoredAny>(&text.text).is_err() {
+                text.text = fit_output(std::mem::take(&mut text.text), tool).await;
+            }
+        }
+    }
+    result
+}
+
+/// Record how long the call took as `elapsed_ms` in the result's `_meta`.
+fn with_elapsed(mut result: CallToolResult, elapsed: Duration) -> CallToolResult {
+    let elapsed_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+    result
+        .meta
+        .get_or_insert_with(Meta::default)
+        .0
+        .insert("elapsed_ms".to_string(), elapsed_ms.into());
+    result
+}
+
+/// Mark a result holding a handler's [`ToolError`] as an error result.
+fn structured_error(mut result: CallToolResult) -> CallToolResult {
+    let failed = result
+        .content
+        .first()
+        .and_then(|c| c.as_text())
+        .is_some_and(|t| ToolError::is_error(&t.text));
+    if failed {
+        result.is_error = Some(true);
+    }
+    result
+}
+
 /// Trait to allow passing async closures to `with_connection`.
 trait AsyncConnectionFn: Send + 'static {
     fn call(self, conn: Arc<SshConnection>) -> Pin<Box<dyn Future<Output = String> + Send>>;
@@ -305,13 +2024,97 @@ where
     }
 }
 
-#[tool_handler]
 impl ServerHandler for RemoteSessionServer {
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let started = Instant::now();
+        let tool = request.name.to_string();
+        let routed = self.tool_router.has_route(&request.name);
+        let class = ToolClass::for_tool(&tool, !routed);
+        let counts_bytes = match self.check_rate_limit(class, &request).await {
+            Ok(counts_bytes) => counts_bytes,
+            Err(e) => {
+                let result = Ok(structured_error(CallToolResult::success(vec![
+                    Content::text(e.to_json()),
+                ])));
+                self.record_call(&tool, started.elapsed(), false, &result)
+                    .await;
+                return result.map(|r| with_elapsed(r, started.elapsed()));
+            }
+        };
+        let template = if routed {
+            None
+        } else {
+            self.call_template(&request).await
+        };
+        let command =
+            template.is_some() || matches!(tool.as_str(), "remote_bash" | "run_saved_command");
+        let result = if let Some(output) = template {
+            Ok(CallToolResult::success(vec![Content::text(output)]))
+        } else {
+            let tcc = ToolCallContext::new(self, request, context);
+            self.tool_router.call(tcc).await
+        }
+        .map(structured_error);
+        if let Some((server, class)) = counts_bytes {
+            let bytes = result_text(&result).and_then(metrics::synced_bytes);
+            if let Some(bytes) = bytes {
+                self.rate_usage
+                    .lock()
+                    .await
+                    .record_bytes(&server, class, bytes, Instant::now());
+            }
+        }
+        self.record_call(&tool, started.elapsed(), command, &result)
+            .await;
+        match result {
+            Ok(result) => Ok(with_elapsed(
+                fit_result(result, &tool).await,
+                started.elapsed(),
+            )),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, ErrorData> {
+        let mut tools = self.tool_router.list_all();
+        tools.extend(self.template_tools().await);
+        if !self.config.read().await.allow_local {
+            tools.retain(|tool| tool.name != "local_bash");
+        }
+        Ok(ListToolsResult {
+            tools,
+            meta: None,
+            next_cursor: None,
+        })
+    }
+
+    /// Accept any level. The only server-originated log messages are
+    /// occasional info-level events (e.g. config reloads), so there is
+    /// nothing to filter.
+    async fn set_level(
+        &self,
+        _request: SetLevelRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        Ok(())
+    }
+
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::LATEST,
             capabilities: ServerCapabilities {
-                tools: Some(rmcp::model::ToolsCapability { list_changed: None }),
+                tools: Some(rmcp::model::ToolsCapability {
+                    list_changed: Some(true),
+                }),
+                logging: Some(rmcp::model::JsonObject::new()),
                 ..Default::default()
             },
             server_info: Implementation::from_build_env(),
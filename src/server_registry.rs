@@ -1,18 +1,217 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 use anyhow::{anyhow, Result};
+use rmcp::schemars::{self, JsonSchema};
 use serde::{Deserialize, Serialize};
 
+use crate::budget::BudgetConfig;
+use crate::discovery::DiscoveryConfig;
+use crate::hooks::HooksConfig;
+use crate::maintenance::MaintenanceConfig;
 use crate::metadata::SystemMetadata;
+use crate::metrics::MetricsConfig;
+use crate::profile;
+use crate::rate_limit::RateLimitConfig;
+use crate::tools::command_template::CommandTemplate;
+use crate::tools::provision::Recipe;
+use crate::utils::gitignore::glob_match;
+use crate::utils::log_file;
+use crate::utils::path::shell_escape;
 
 const DEFAULT_SSH_PORT: u16 = 22;
 const DEFAULT_REMOTE_PATH: &str = "~";
 
+/// Environment variable naming the config file to use.
+pub const CONFIG_ENV: &str = "SSH_HUB_CONFIG";
+
+/// Environment variable naming a directory holding `servers.toml`.
+pub const CONFIG_DIR_ENV: &str = "SSH_HUB_CONFIG_DIR";
+
+/// Config file name for `profile`: `servers.toml`, or `servers-<profile>.toml`.
+#[must_use]
+pub fn config_file_name(profile: Option<&str>) -> String {
+    format!("{}.toml", profile::scoped("servers", profile))
+}
+
+/// Config file set by `--config`.
+static CONFIG_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ServerRegistry {
+    /// Offer the `local_bash` tool, which runs commands on the machine the
+    /// MCP server runs on. Off by default.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_local: bool,
+    /// Settings every server falls back to, under `[defaults]`. Takes any
+    /// server setting; matching patterns and the server's own entry win.
+    #[serde(default, skip_serializing_if = "toml::Table::is_empty")]
+    pub defaults: toml::Table,
     #[serde(default)]
     pub servers: HashMap<String, ServerEntry>,
+    /// Fallback settings for servers whose names match a glob, written as
+    /// `[servers."gpu-*"]`, in file order. Later patterns win over earlier
+    /// ones.
+    #[serde(skip)]
+    pub patterns: Vec<(String, toml::Table)>,
+    /// Settings each server took from `defaults` or a pattern rather than
+    /// its own entry, which saving leaves out again.
+    #[serde(skip)]
+    inherited: HashMap<String, toml::Table>,
+    /// Command templates exposed as MCP tools, keyed by tool name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<String, CommandTemplate>,
+    /// Provisioning recipes applied with the `provision` tool, keyed by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub recipes: BTreeMap<String, Recipe>,
+    /// File logging for the MCP server. Read once at startup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logging: Option<LoggingConfig>,
+    /// Sharing one MCP server between ssh-hub processes. Read once at startup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub broker: Option<BrokerConfig>,
+    /// Bytes each tool family may return per call and per minute.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<BudgetConfig>,
+    /// Usage metrics for the `metrics` tool. Read once at startup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<MetricsConfig>,
+    /// Limits for directory transfers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync: Option<SyncConfig>,
+    /// Local project directories paired with a server and remote directory,
+    /// keyed by absolute local path. Written by `ssh-hub mcp-install --server`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub projects: BTreeMap<String, ProjectMapping>,
+}
+
+/// The remote copy of a local project. Sync tools fill in the path on the
+/// other side for files under the project root.
+/// Servers that appeared, disappeared, or changed connection settings
+/// between two versions of the config, by name.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct ServerChanges {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub added: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changed: Vec<String>,
+}
+
+impl ServerChanges {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Whether the set of server names changed.
+    #[must_use]
+    pub fn servers_added_or_removed(&self) -> bool {
+        !self.added.is_empty() || !self.removed.is_empty()
+    }
+
+    /// Fold a later set of changes into this one, so the result describes
+    /// both reloads at once. A server added and then removed again drops out.
+    pub fn merge(&mut self, later: Self) {
+        for name in later.added {
+            if let Some(i) = self.removed.iter().position(|n| *n == name) {
+                self.removed.remove(i);
+                push_unique(&mut self.changed, name);
+            } else {
+                push_unique(&mut self.added, name);
+            }
+        }
+        for name in later.removed {
+            self.changed.retain(|n| *n != name);
+            if let Some(i) = self.added.iter().position(|n| *n == name) {
+                self.added.remove(i);
+            } else {
+                push_unique(&mut self.removed, name);
+            }
+        }
+        for name in later.changed {
+            if !self.added.contains(&name) {
+                push_unique(&mut self.changed, name);
+            }
+        }
+    }
+}
+
+fn push_unique(names: &mut Vec<String>, name: String) {
+    if !names.contains(&name) {
+        names.push(name);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProjectMapping {
+    /// Server that backs the project.
+    pub server: String,
+    /// Project root on the server.
+    pub remote_path: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SyncConfig {
+    /// Total bytes one `sync_pull` of a directory may transfer (0 disables
+    /// the cap). Defaults to 512 MiB.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_pull_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BrokerConfig {
+    /// Let later MCP servers proxy their clients to the first one.
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LoggingConfig {
+    /// Write MCP server logs to a file in addition to stderr.
+    #[serde(default = "default_log_enabled")]
+    pub enabled: bool,
+    /// Base path of the log file; each day's file gets a `.YYYY-MM-DD` suffix.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Daily log files kept before the oldest are deleted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_days: Option<usize>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_log_enabled(),
+            file: None,
+            keep_days: None,
+        }
+    }
+}
+
+impl LoggingConfig {
+    /// Log file base path, or `None` when file logging is disabled.
+    #[must_use]
+    pub fn path(&self) -> Option<PathBuf> {
+        if !self.enabled {
+            return None;
+        }
+        match &self.file {
+            Some(file) => match (file.strip_prefix("~/"), dirs::home_dir()) {
+                (Some(rest), Some(home)) => Some(home.join(rest)),
+                _ => Some(PathBuf::from(file)),
+            },
+            None => log_file::default_log_path(),
+        }
+    }
+
+    #[must_use]
+    pub fn keep_days(&self) -> usize {
+        self.keep_days.unwrap_or(log_file::DEFAULT_KEEP_DAYS).max(1)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,10 +225,134 @@ pub struct ServerEntry {
     pub identity: Option<String>,
     #[serde(default)]
     pub auth: AuthMethod,
+    /// Where to reach ssh-agent instead of `SSH_AUTH_SOCK`: a socket path,
+    /// a Windows named pipe (`\\.\pipe\...`), or `pageant`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_socket: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub resolve_host: Option<String>,
+    /// Pinned IP address for `host`, bypassing DNS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolve: Option<String>,
+    /// IP versions to connect over (default `any`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_family: Option<AddressFamily>,
+    /// Local address to connect from, on machines with several.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind_address: Option<IpAddr>,
+    /// Network interface to connect through, such as a VPN's `tun0`
+    /// (Linux only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind_interface: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub metadata: Option<SystemMetadata>,
+    /// Login shell on the remote. When unset, inferred from collected
+    /// metadata (Windows servers) and otherwise POSIX.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<RemoteShell>,
+    /// Interpreter `remote_bash` runs commands with. When unset, commands
+    /// go to the login shell as-is, or to `sh` when collected metadata shows
+    /// a login shell that isn't POSIX (fish, nushell, csh).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_shell: Option<CommandShell>,
+    /// What to do with a host key not yet in `~/.ssh/known_hosts`
+    /// (default `ask`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict_host_key: Option<StrictHostKey>,
+    /// Maximum concurrently open SSH channels; extra tool calls queue.
+    /// Set this at or below the server's `MaxSessions`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_channels: Option<usize>,
+    /// Another configured server sharing this one's filesystem (e.g. over
+    /// NFS) that serves read-class tools instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_replica: Option<String>,
+    /// Refuse tools that change the server; diagnostics only.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub readonly: bool,
+    /// Windows outside which write-class tools are refused.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<MaintenanceConfig>,
+    /// How many calls and bytes each tool class may spend on this server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limits: Option<RateLimitConfig>,
+    /// Commands run before and after `remote_bash`, `sync_push`, and `sync_pull`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<HooksConfig>,
+    /// Directory `workspace_create` makes scratch directories in (default `/tmp`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scratch_root: Option<String>,
+    /// Directory `remote_snapshot` keeps snapshots in (default
+    /// `~/.ssh-hub-snapshots`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_dir: Option<String>,
+    /// Labels for acting on groups of servers (e.g. `rotate-keys --tag prod`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Commands `remote_bootstrap` checks for and installs when no list is
+    /// passed, each `command` or `command=package`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub needs: Vec<String>,
+    /// Named commands run by `run_saved_command` and `ssh-hub run`, with
+    /// optional `{param}` placeholders.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub commands: BTreeMap<String, String>,
+    /// Longest foreground `remote_bash` timeout a `long_running` call may
+    /// ask for, in milliseconds (default 10 minutes). Values below 10
+    /// minutes also cap ordinary calls.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_timeout_ms: Option<u64>,
+    /// Timeout for commands whose call doesn't pass one, in milliseconds
+    /// (default 2 minutes).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_timeout_ms: Option<u64>,
+    /// Accept a timeout of `0` to run commands without one. Only for
+    /// trusted servers: a hung command holds its channel until the
+    /// connection drops.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_timeout: bool,
+    /// Where the MCP server looks up a new address when this one stops
+    /// answering.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery: Option<DiscoveryConfig>,
+}
+
+impl Default for ServerEntry {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            user: String::new(),
+            port: default_port(),
+            remote_path: default_remote_path(),
+            identity: None,
+            auth: AuthMethod::default(),
+            agent_socket: None,
+            resolve_host: None,
+            resolve: None,
+            address_family: None,
+            bind_address: None,
+            bind_interface: None,
+            metadata: None,
+            shell: None,
+            command_shell: None,
+            strict_host_key: None,
+            max_channels: None,
+            read_replica: None,
+            readonly: false,
+            maintenance: None,
+            rate_limits: None,
+            hooks: None,
+            scratch_root: None,
+            snapshot_dir: None,
+            tags: Vec::new(),
+            needs: Vec::new(),
+            commands: BTreeMap::new(),
+            max_timeout_ms: None,
+            default_timeout_ms: None,
+            no_timeout: false,
+            discovery: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
@@ -41,6 +364,139 @@ pub enum AuthMethod {
     Key,
 }
 
+/// Command language of the remote login shell.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteShell {
+    #[default]
+    Posix,
+    Powershell,
+    Cmd,
+}
+
+impl RemoteShell {
+    #[must_use]
+    pub fn is_posix(self) -> bool {
+        self == Self::Posix
+    }
+}
+
+impl std::fmt::Display for RemoteShell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Posix => f.write_str("posix"),
+            Self::Powershell => f.write_str("powershell"),
+            Self::Cmd => f.write_str("cmd"),
+        }
+    }
+}
+
+/// Interpreter a `remote_bash` command is wrapped in, rather than being
+/// handed to the login shell.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandShell {
+    /// The login shell, which is how sshd runs a command by itself.
+    Login,
+    Sh,
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Login shells that don't take POSIX `sh` syntax.
+const NON_POSIX_LOGIN_SHELLS: [&str; 6] = ["fish", "nu", "csh", "tcsh", "xonsh", "elvish"];
+
+impl CommandShell {
+    /// `command` wrapped to run under this interpreter. Bash, zsh, and fish
+    /// start as login shells, so `PATH` entries from profiles apply.
+    #[must_use]
+    pub fn wrap(self, command: &str) -> String {
+        let invocation = match self {
+            Self::Login => return command.to_string(),
+            Self::Sh => "sh -c",
+            Self::Bash => "bash -lc",
+            Self::Zsh => "zsh -lc",
+            Self::Fish => "fish -lc",
+        };
+        format!("{invocation} {}", shell_escape(command))
+    }
+}
+
+impl std::fmt::Display for CommandShell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Login => "login",
+            Self::Sh => "sh",
+            Self::Bash => "bash",
+            Self::Zsh => "zsh",
+            Self::Fish => "fish",
+        })
+    }
+}
+
+/// IP versions a server is connected over, as `AddressFamily` in
+/// `ssh_config`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFamily {
+    #[default]
+    Any,
+    /// IPv4 only.
+    Inet,
+    /// IPv6 only.
+    Inet6,
+}
+
+impl AddressFamily {
+    /// Whether `ip` may be connected to.
+    #[must_use]
+    pub fn allows(self, ip: IpAddr) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Inet => ip.is_ipv4(),
+            Self::Inet6 => ip.is_ipv6(),
+        }
+    }
+}
+
+impl std::fmt::Display for AddressFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Any => "any",
+            Self::Inet => "inet",
+            Self::Inet6 => "inet6",
+        })
+    }
+}
+
+/// How a host key seen for the first time is treated. Keys already in
+/// `~/.ssh/known_hosts` are always checked, and a changed key is always
+/// refused.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StrictHostKey {
+    /// Show the fingerprint to the user through the MCP client and record
+    /// the key only once they confirm it. Clients that can't ask, and the
+    /// CLI, record it without asking.
+    #[default]
+    Ask,
+    /// Record the key without asking (trust on first use).
+    AcceptNew,
+    /// Refuse to connect until the key is added to `known_hosts` by hand.
+    Yes,
+}
+
+impl std::fmt::Display for StrictHostKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Ask => "ask",
+            Self::AcceptNew => "accept-new",
+            Self::Yes => "yes",
+        })
+    }
+}
+
 impl std::fmt::Display for AuthMethod {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -51,6 +507,11 @@ impl std::fmt::Display for AuthMethod {
     }
 }
 
+/// Whether a `[servers]` key is a glob of server names rather than a server.
+fn is_name_pattern(name: &str) -> bool {
+    name.contains(['*', '?', '['])
+}
+
 fn default_port() -> u16 {
     DEFAULT_SSH_PORT
 }
@@ -59,6 +520,10 @@ fn default_remote_path() -> String {
     DEFAULT_REMOTE_PATH.to_string()
 }
 
+fn default_log_enabled() -> bool {
+    true
+}
+
 impl ServerRegistry {
     /// # Errors
     ///
@@ -69,26 +534,99 @@ impl ServerRegistry {
             return Ok(Self::default());
         }
         let content = std::fs::read_to_string(&path)?;
-        Ok(toml::from_str(&content)?)
+        Self::parse(&content)
+    }
+
+    /// Parse a config file, filling in each server's unset settings from
+    /// matching `[servers."<glob>"]` patterns and then `[defaults]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TOML is invalid or a server, with its
+    /// inherited settings, is not a valid entry.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut table: toml::Table = toml::from_str(content)?;
+        let servers = match table.remove("servers") {
+            Some(toml::Value::Table(servers)) => servers,
+            Some(_) => return Err(anyhow!("'servers' must be a table")),
+            None => toml::Table::new(),
+        };
+        let mut config: Self = table.try_into()?;
+
+        let mut entries = Vec::new();
+        for (name, value) in servers {
+            let toml::Value::Table(settings) = value else {
+                return Err(anyhow!("servers.\"{name}\" must be a table"));
+            };
+            if is_name_pattern(&name) {
+                config.patterns.push((name, settings));
+            } else {
+                entries.push((name, settings));
+            }
+        }
+        for (name, own) in entries {
+            let inherited = config.inherited_settings(&name, &own);
+            let mut settings = own;
+            settings.extend(inherited.clone());
+            let entry: ServerEntry = settings
+                .try_into()
+                .map_err(|e| anyhow!("server '{name}': {e}"))?;
+            if !inherited.is_empty() {
+                config.inherited.insert(name.clone(), inherited);
+            }
+            config.servers.insert(name, entry);
+        }
+        Ok(config)
+    }
+
+    /// Settings `name` takes from `[defaults]` and the patterns matching it,
+    /// for the keys its own entry `own` doesn't set.
+    fn inherited_settings(&self, name: &str, own: &toml::Table) -> toml::Table {
+        let mut inherited = self.defaults.clone();
+        for (pattern, settings) in &self.patterns {
+            if glob_match(pattern, name) {
+                inherited.extend(settings.clone());
+            }
+        }
+        inherited.retain(|key, _| !own.contains_key(key));
+        inherited
+    }
+
+    /// The config as TOML, with patterns back under `[servers]` and settings
+    /// a server inherited left out of its entry unless they were changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the config cannot be serialized.
+    pub fn to_toml(&self) -> Result<String> {
+        let mut table = toml::Table::try_from(self)?;
+        if let Some(toml::Value::Table(servers)) = table.get_mut("servers") {
+            for (name, inherited) in &self.inherited {
+                if let Some(toml::Value::Table(entry)) = servers.get_mut(name) {
+                    entry.retain(|key, value| inherited.get(key) != Some(value));
+                }
+            }
+            for (pattern, settings) in &self.patterns {
+                servers.insert(pattern.clone(), toml::Value::Table(settings.clone()));
+            }
+        }
+        Ok(toml::to_string_pretty(&table)?)
     }
 
+    /// Write the config atomically: readers see either the old or the new
+    /// file, never a partial one. Use [`Self::modify`] to change the config
+    /// on disk, so concurrent writers don't overwrite each other.
+    ///
     /// # Errors
     ///
     /// Returns an error if the config directory cannot be created or the file
     /// cannot be written.
     pub fn save(&self) -> Result<()> {
         let path = Self::config_path()?;
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                let perms = std::fs::Permissions::from_mode(0o700);
-                std::fs::set_permissions(parent, perms)?;
-            }
-        }
-        let content = toml::to_string_pretty(self)?;
+        Self::create_config_dir(&path)?;
+        let content = self.to_toml()?;
 
+        let tmp = path.with_extension(format!("toml.{}.tmp", std::process::id()));
         #[cfg(unix)]
         {
             use std::fs::OpenOptions;
@@ -100,25 +638,126 @@ impl ServerRegistry {
                 .create(true)
                 .truncate(true)
                 .mode(0o600)
-                .open(&path)?;
+                .open(&tmp)?;
             file.write_all(content.as_bytes())?;
+            file.sync_all()?;
         }
 
         #[cfg(not(unix))]
         {
-            std::fs::write(&path, content)?;
+            std::fs::write(&tmp, content)?;
         }
 
+        std::fs::rename(&tmp, &path).inspect_err(|_| {
+            let _ = std::fs::remove_file(&tmp);
+        })?;
         Ok(())
     }
 
+    /// Load the config, apply `f`, and save it, holding an exclusive lock on
+    /// `servers.toml.lock` throughout. Other ssh-hub processes modifying the
+    /// config wait for the lock, so no change is lost to a concurrent write.
+    ///
     /// # Errors
     ///
-    /// Returns an error if the platform config directory cannot be determined.
+    /// Returns an error if the lock cannot be taken or the config cannot be
+    /// read, parsed, or written.
+    pub fn modify<T>(f: impl FnOnce(&mut Self) -> T) -> Result<T> {
+        let path = Self::config_path()?;
+        Self::create_config_dir(&path)?;
+        let lock = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path.with_extension("toml.lock"))?;
+        lock.lock()?;
+
+        let mut config = Self::load()?;
+        let result = f(&mut config);
+        config.save()?;
+        Ok(result)
+    }
+
+    /// Create the directory holding the config file, readable only by the
+    /// user. A directory that already exists, such as one named by
+    /// `SSH_HUB_CONFIG`, keeps its permissions.
+    fn create_config_dir(path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            if parent.is_dir() {
+                return Ok(());
+            }
+            std::fs::create_dir_all(parent)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let perms = std::fs::Permissions::from_mode(0o700);
+                std::fs::set_permissions(parent, perms)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Use `path` as the config file for the rest of the process
+    /// (`--config`), ahead of the environment variables. Only the first call
+    /// has an effect.
+    pub fn set_config_path(path: &Path) {
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let _ = CONFIG_OVERRIDE.set(path);
+    }
+
+    /// The config file: the `--config` flag, else `SSH_HUB_CONFIG`, else
+    /// the profile's file in `SSH_HUB_CONFIG_DIR` or the platform config
+    /// directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither file is set and the platform config
+    /// directory cannot be determined.
     pub fn config_path() -> Result<PathBuf> {
-        let config_dir =
-            dirs::config_dir().ok_or_else(|| anyhow!("Could not determine config directory"))?;
-        Ok(config_dir.join("ssh-hub").join("servers.toml"))
+        if let Some(path) = CONFIG_OVERRIDE.get() {
+            return Ok(path.clone());
+        }
+        Self::config_path_from(
+            std::env::var_os(CONFIG_ENV).map(PathBuf::from),
+            std::env::var_os(CONFIG_DIR_ENV).map(PathBuf::from),
+            profile::current(),
+        )
+    }
+
+    /// The config file given the values of `SSH_HUB_CONFIG` and
+    /// `SSH_HUB_CONFIG_DIR` and the profile. Empty values count as unset. An
+    /// explicit file is used whatever the profile.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither is set and the platform config directory
+    /// cannot be determined.
+    pub fn config_path_from(
+        file: Option<PathBuf>,
+        dir: Option<PathBuf>,
+        profile: Option<&str>,
+    ) -> Result<PathBuf> {
+        let set = |path: Option<PathBuf>| path.filter(|p| !p.as_os_str().is_empty());
+        if let Some(file) = set(file) {
+            return Ok(std::path::absolute(&file).unwrap_or(file));
+        }
+        let dir = match set(dir) {
+            Some(dir) => std::path::absolute(&dir).unwrap_or(dir),
+            None => dirs::config_dir()
+                .ok_or_else(|| anyhow!("Could not determine config directory"))?
+                .join("ssh-hub"),
+        };
+        Ok(dir.join(config_file_name(profile)))
+    }
+
+    /// `servers.toml` in the platform config directory
+    /// (`~/.config/ssh-hub/servers.toml` on Linux).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the platform config directory cannot be determined.
+    pub fn default_config_path() -> Result<PathBuf> {
+        Self::config_path_from(None, None, None)
     }
 
     /// Look up a server entry by its alias name.
@@ -127,6 +766,15 @@ impl ServerRegistry {
         self.servers.get(name)
     }
 
+    /// The `read_replica` of `name`, if it names another configured server.
+    #[must_use]
+    pub fn read_replica(&self, name: &str) -> Option<&str> {
+        self.get(name)?
+            .read_replica
+            .as_deref()
+            .filter(|replica| *replica != name && self.servers.contains_key(*replica))
+    }
+
     /// Insert or replace a server entry.
     pub fn insert(&mut self, name: String, entry: ServerEntry) {
         self.servers.insert(name, entry);
@@ -134,9 +782,74 @@ impl ServerRegistry {
 
     /// Remove a server entry, returning it if it existed.
     pub fn remove(&mut self, name: &str) -> Option<ServerEntry> {
+        self.inherited.remove(name);
         self.servers.remove(name)
     }
 
+    /// Remote counterpart of the absolute local path `local` on `server`,
+    /// when it lies inside a project mapped to that server. The deepest
+    /// project root wins when projects nest.
+    #[must_use]
+    pub fn remote_for_local(&self, server: &str, local: &Path) -> Option<String> {
+        let (root, mapping) = self
+            .projects_on(server)
+            .filter(|(root, _)| local.starts_with(root))
+            .max_by_key(|(root, _)| root.components().count())?;
+        let rest: Vec<_> = local
+            .strip_prefix(root)
+            .ok()?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        if rest.is_empty() {
+            return Some(mapping.remote_path.clone());
+        }
+        Some(format!(
+            "{}/{}",
+            mapping.remote_path.trim_end_matches('/'),
+            rest.join("/")
+        ))
+    }
+
+    /// Local counterpart of `remote` on `server`, when it lies inside the
+    /// remote root of a project mapped to that server. `remote` must already
+    /// be normalized against the server's base path.
+    #[must_use]
+    pub fn local_for_remote(&self, server: &str, remote: &str) -> Option<PathBuf> {
+        self.projects_on(server)
+            .filter_map(|(root, mapping)| {
+                let remote_root = mapping.remote_path.trim_end_matches('/');
+                let rest = remote.strip_prefix(remote_root)?;
+                let rest = match rest.strip_prefix('/') {
+                    Some(rest) => rest,
+                    None if rest.is_empty() => rest,
+                    // `/srv/app2` is not inside `/srv/app`, but anything is
+                    // inside `/`
+                    None if remote_root.is_empty() => rest,
+                    None => return None,
+                };
+                let rest = rest.trim_matches('/');
+                let local = if rest.is_empty() {
+                    root.to_path_buf()
+                } else {
+                    root.join(rest)
+                };
+                Some((remote_root.len(), local))
+            })
+            .max_by_key(|(depth, _)| *depth)
+            .map(|(_, local)| local)
+    }
+
+    fn projects_on<'a>(
+        &'a self,
+        server: &'a str,
+    ) -> impl Iterator<Item = (&'a Path, &'a ProjectMapping)> + 'a {
+        self.projects
+            .iter()
+            .filter(move |(_, mapping)| mapping.server == server)
+            .map(|(root, mapping)| (Path::new(root.as_str()), mapping))
+    }
+
     /// Return server names whose connection-relevant fields changed or that
     /// were removed compared to `other`. Newly added servers are not included
     /// (there's no existing connection to evict).
@@ -153,9 +866,89 @@ impl ServerRegistry {
             })
             .collect()
     }
+
+    /// Servers added, removed, or with changed connection settings in
+    /// `other` compared to `self`, each list sorted by name.
+    #[must_use]
+    pub fn server_changes(&self, other: &Self) -> ServerChanges {
+        let mut added: Vec<String> = other
+            .servers
+            .keys()
+            .filter(|name| !self.servers.contains_key(*name))
+            .cloned()
+            .collect();
+        let (mut removed, mut changed): (Vec<String>, Vec<String>) = self
+            .changed_servers(other)
+            .into_iter()
+            .partition(|name| !other.servers.contains_key(name));
+        added.sort();
+        removed.sort();
+        changed.sort();
+        ServerChanges {
+            added,
+            removed,
+            changed,
+        }
+    }
 }
 
 impl ServerEntry {
+    /// Effective remote shell: the configured `shell`, else the shell
+    /// detected during metadata collection, else POSIX.
+    #[must_use]
+    pub fn remote_shell(&self) -> RemoteShell {
+        self.shell.unwrap_or_else(|| {
+            self.metadata
+                .as_ref()
+                .and_then(|m| m.shell.as_deref())
+                .map_or(RemoteShell::Posix, |s| match s {
+                    "powershell" => RemoteShell::Powershell,
+                    "cmd" => RemoteShell::Cmd,
+                    _ => RemoteShell::Posix,
+                })
+        })
+    }
+
+    /// Interpreter for `remote_bash` calls that don't pick one: the
+    /// configured `command_shell`, else `sh` when the login shell recorded
+    /// in metadata isn't POSIX, else the login shell.
+    #[must_use]
+    pub fn command_shell(&self) -> CommandShell {
+        self.command_shell.unwrap_or_else(|| {
+            let login = self.metadata.as_ref().and_then(|m| m.shell.as_deref());
+            match login.map(|path| path.rsplit('/').next().unwrap_or(path)) {
+                Some(name) if NON_POSIX_LOGIN_SHELLS.contains(&name) => CommandShell::Sh,
+                _ => CommandShell::Login,
+            }
+        })
+    }
+
+    /// Whether the server carries every tag in `tags`. Tags are matched
+    /// whole, so `key=value` tags such as `env=staging` work as-is.
+    #[must_use]
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        tags.iter().all(|tag| self.tags.contains(tag))
+    }
+
+    /// Address to probe or connect to: the pinned `resolve` IP if set,
+    /// otherwise `host`.
+    #[must_use]
+    pub fn connect_host(&self) -> &str {
+        self.resolve.as_deref().unwrap_or(&self.host)
+    }
+
+    /// Record an address found by `discovery`: in `resolve` when the server
+    /// has a pinned IP (so `host` still names it for host key checks),
+    /// otherwise in `host`. Returns whether anything changed.
+    pub fn set_discovered_address(&mut self, address: &str) -> bool {
+        let field = self.resolve.as_mut().unwrap_or(&mut self.host);
+        if field == address {
+            return false;
+        }
+        address.clone_into(field);
+        true
+    }
+
     /// Compare fields that affect SSH connectivity or the command execution
     /// context — metadata-only changes don't warrant a reconnection.
     ///
@@ -170,5 +963,13 @@ impl ServerEntry {
             || self.remote_path != other.remote_path
             || self.identity != other.identity
             || self.auth != other.auth
+            || self.resolve != other.resolve
+            || self.remote_shell() != other.remote_shell()
+            || self.max_channels != other.max_channels
+            || self.address_family != other.address_family
+            || self.bind_address != other.bind_address
+            || self.bind_interface != other.bind_interface
+            || self.agent_socket != other.agent_socket
+            || self.strict_host_key != other.strict_host_key
     }
 }
@@ -0,0 +1,114 @@
+//! Timeouts for commands that tools run on a server.
+//!
+//! ```toml
+//! [servers.ci]
+//! default_timeout_ms = 300000  # calls that don't pass `timeout`
+//! max_timeout_ms = 7200000     # cap for `long_running` remote_bash calls
+//! no_timeout = true            # allow `timeout: 0`, i.e. no timeout
+//! ```
+//!
+//! `remote_bash`, `run_saved_command`, command templates, `remote_rollout`,
+//! and provisioning recipe commands all resolve their timeout with
+//! [`TimeoutPolicy::resolve`]: the call's own timeout, else the server's
+//! default, else 2 minutes, capped at 10 minutes. `0` means no timeout and
+//! is only accepted on servers marked `no_timeout`, since a hung command
+//! then holds its channel until the connection drops.
+//!
+//! Work a tool does on its own, whose length grows with the data — transfers,
+//! manifests and hashes, `du`, service actions, installs, snapshots, backups,
+//! `remote_audit`, and benchmarks — resolves with [`TimeoutPolicy::operation`] instead: the
+//! tool's own limit, raised by a longer server default and lifted entirely
+//! on `no_timeout` servers.
+//!
+//! A few fixed timeouts stay outside the policy, because they bound quick
+//! probes whose only failure mode is a stuck connection:
+//!
+//! - `remote_whoami`, `remote_df`, `remote_process`, `remote_stat` metadata,
+//!   `remote_service` status, and `remote_tail`'s read: one short command each.
+//! - `remote_watch`: one `find` over a glob, answered from metadata only.
+//! - Remote listing, `mkdir`, and attribute helpers of the sync tools, the
+//!   `sync_pull` probe, and the change journal: bookkeeping around a transfer.
+//! - Background `remote_bash` launches and `remote_write` attribute changes:
+//!   they return as soon as the command is started or the mode is set.
+//! - Check commands in `remote_bootstrap` and `provision`: `command -v`-style
+//!   probes that decide whether to install.
+//! - Workspaces and session directories: one `mktemp` or `rm -rf` of a
+//!   scratch directory, also run at shutdown when no server config is at hand.
+//! - `remote_wait_for`: its `timeout_seconds` is how long to wait for the
+//!   condition, and each check is a single connect or request.
+//! - `list_servers` probes: a DNS lookup and TCP connect, not a command.
+
+use crate::server_registry::ServerEntry;
+
+/// Timeout when neither the call nor the server sets one (2 minutes).
+pub const DEFAULT_TIMEOUT_MS: u64 = 120_000;
+
+/// Longest timeout a call may ask for (10 minutes), unless it sets
+/// `long_running` and the server's `max_timeout_ms` allows more.
+pub const MAX_TIMEOUT_MS: u64 = 600_000;
+
+/// One server's timeout settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeoutPolicy {
+    /// `default_timeout_ms`: used when a call doesn't pass a timeout.
+    pub default_ms: Option<u64>,
+    /// `max_timeout_ms`: the cap for `long_running` calls. Values below
+    /// [`MAX_TIMEOUT_MS`] cap every call.
+    pub max_ms: Option<u64>,
+    /// `no_timeout`: a timeout of `0` runs without one.
+    pub unlimited: bool,
+}
+
+impl TimeoutPolicy {
+    #[must_use]
+    pub fn for_server(entry: &ServerEntry) -> Self {
+        Self {
+            default_ms: entry.default_timeout_ms,
+            max_ms: entry.max_timeout_ms,
+            unlimited: entry.no_timeout,
+        }
+    }
+
+    /// Timeout for a call that asked for `requested` milliseconds, or
+    /// `None` for no timeout. `long_running` raises the cap from 10 minutes
+    /// to the server's `max_timeout_ms`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a user-facing message for a timeout of `0` on a server
+    /// without `no_timeout`.
+    pub fn resolve(
+        &self,
+        requested: Option<u64>,
+        long_running: bool,
+    ) -> Result<Option<u64>, String> {
+        let server_cap = self.max_ms.unwrap_or(MAX_TIMEOUT_MS);
+        let cap = if long_running {
+            server_cap
+        } else {
+            server_cap.min(MAX_TIMEOUT_MS)
+        };
+        match requested.or(self.default_ms) {
+            Some(0) if self.unlimited => Ok(None),
+            Some(0) => Err(
+                "timeout 0 (no timeout) is only allowed on servers with no_timeout = true"
+                    .to_string(),
+            ),
+            requested => Ok(Some(requested.unwrap_or(DEFAULT_TIMEOUT_MS).min(cap))),
+        }
+    }
+
+    /// Timeout for long work a tool does on its own, such as a transfer or
+    /// hashing a tree, whose built-in limit is `builtin_ms`, or `None` for
+    /// no timeout. A longer `default_timeout_ms` raises it, up to the cap
+    /// for `long_running` calls, and `no_timeout` lifts it.
+    #[must_use]
+    pub fn operation(&self, builtin_ms: u64) -> Option<u64> {
+        if self.unlimited {
+            return None;
+        }
+        let cap = self.max_ms.unwrap_or(MAX_TIMEOUT_MS);
+        let default = self.default_ms.unwrap_or(0).min(cap);
+        Some(builtin_ms.max(default))
+    }
+}
@@ -0,0 +1,173 @@
+//! `benchmark_server` and `ssh-hub bench`: latency and throughput of the
+//! SSH connection to a server.
+//!
+//! Latency is sampled `rounds` times, both for opening a channel and for a
+//! no-op command. Throughput moves one payload each way through a command's
+//! stdin and stdout, the path `remote_bash`, `remote_write`, and exec-based
+//! sync transfers take.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+
+use super::schema::{BenchmarkReport, BenchmarkServerInput, Throughput, Timing};
+use crate::connection::SshConnection;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::remote_error;
+
+/// Default number of latency samples.
+pub const DEFAULT_ROUNDS: u32 = 5;
+
+/// Most latency samples one call takes.
+pub const MAX_ROUNDS: u32 = 20;
+
+/// Default payload size (1 MiB).
+pub const DEFAULT_PAYLOAD_KB: u64 = 1024;
+
+/// Largest payload (16 MiB), held in memory for each direction.
+pub const MAX_PAYLOAD_KB: u64 = 16 * 1024;
+
+/// Timeout for each command, including the transfers (2 minutes), unless
+/// the server's [`TimeoutPolicy`] raises it.
+const COMMAND_TIMEOUT_MS: u64 = 120_000;
+
+/// Sample count and payload size `input` asks for, within the limits.
+#[must_use]
+pub fn limits(input: &BenchmarkServerInput) -> (u32, u64) {
+    let rounds = input.rounds.unwrap_or(DEFAULT_ROUNDS).clamp(1, MAX_ROUNDS);
+    let payload_kb = input
+        .payload_kb
+        .unwrap_or(DEFAULT_PAYLOAD_KB)
+        .clamp(1, MAX_PAYLOAD_KB);
+    (rounds, payload_kb * 1024)
+}
+
+impl Timing {
+    /// Summary of `samples`; all zero when there are none.
+    #[must_use]
+    pub fn of(samples: &[Duration]) -> Self {
+        let mut ms: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        ms.sort_by(f64::total_cmp);
+        let median = if ms.is_empty() {
+            0.0
+        } else if ms.len() % 2 == 1 {
+            ms[ms.len() / 2]
+        } else {
+            f64::midpoint(ms[ms.len() / 2 - 1], ms[ms.len() / 2])
+        };
+        Self {
+            min: round2(ms.first().copied().unwrap_or_default()),
+            median: round2(median),
+            max: round2(ms.last().copied().unwrap_or_default()),
+        }
+    }
+}
+
+impl Throughput {
+    #[must_use]
+    pub fn of(bytes: u64, elapsed: Duration) -> Self {
+        let secs = elapsed.as_secs_f64();
+        // Payloads are at most 16 MiB, well within u32
+        let mib = f64::from(u32::try_from(bytes).unwrap_or(u32::MAX)) / (1024.0 * 1024.0);
+        Self {
+            bytes,
+            elapsed_ms: round2(secs * 1000.0),
+            mib_per_sec: if secs > 0.0 { round2(mib / secs) } else { 0.0 },
+        }
+    }
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// `len` bytes that don't compress, so SSH compression can't inflate the
+/// upload rate.
+#[must_use]
+pub fn payload(len: usize) -> Vec<u8> {
+    // xorshift64: cheap, and random enough for a compressor
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut out = Vec::with_capacity(len + 8);
+    while out.len() < len {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        out.extend_from_slice(&state.to_le_bytes());
+    }
+    out.truncate(len);
+    out
+}
+
+/// Take the measurements on `conn`.
+///
+/// # Errors
+///
+/// Returns an error if the remote has no POSIX shell, or a channel,
+/// command, or transfer fails.
+pub async fn measure(
+    conn: &SshConnection,
+    server: &str,
+    rounds: u32,
+    payload_bytes: u64,
+    timeouts: TimeoutPolicy,
+) -> Result<BenchmarkReport> {
+    let timeout = timeouts.operation(COMMAND_TIMEOUT_MS);
+    if let Some(error) = conn.posix_required("benchmark_server") {
+        bail!(error);
+    }
+    let mut opens = Vec::new();
+    let mut commands = Vec::new();
+    for _ in 0..rounds {
+        opens.push(conn.time_channel_open().await?);
+        let started = Instant::now();
+        conn.exec("true", timeout).await?;
+        commands.push(started.elapsed());
+    }
+
+    let data = payload(usize::try_from(payload_bytes)?);
+    let started = Instant::now();
+    let sent = conn
+        .exec_raw("cat > /dev/null", Some(&data), timeout)
+        .await?;
+    let upload = Throughput::of(payload_bytes, started.elapsed());
+    if sent.exit_code != 0 {
+        bail!("Upload test failed: {}", sent.stderr.trim());
+    }
+
+    let started = Instant::now();
+    let received = conn
+        .exec_raw(
+            &format!("head -c {payload_bytes} /dev/urandom"),
+            None,
+            timeout,
+        )
+        .await?;
+    let download = Throughput::of(received.stdout.len() as u64, started.elapsed());
+    if received.exit_code != 0 {
+        bail!("Download test failed: {}", received.stderr.trim());
+    }
+
+    Ok(BenchmarkReport {
+        server: server.to_string(),
+        rounds,
+        channel_open_ms: Timing::of(&opens),
+        command_ms: Timing::of(&commands),
+        upload,
+        download,
+    })
+}
+
+/// MCP entry point.
+pub async fn handle(
+    conn: Arc<SshConnection>,
+    input: BenchmarkServerInput,
+    timeouts: TimeoutPolicy,
+) -> String {
+    let (rounds, payload_bytes) = limits(&input);
+    match measure(&conn, &input.server, rounds, payload_bytes, timeouts).await {
+        Ok(report) => serde_json::to_string_pretty(&report)
+            .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#)),
+        Err(e) => remote_error(&e, format!("benchmark failed: {e:#}")),
+    }
+}
@@ -0,0 +1,46 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct BenchmarkServerInput {
+    #[schemars(description = "Name of the connected server to measure (e.g., 'staging')")]
+    pub server: String,
+
+    #[schemars(
+        description = "Latency samples to take (default: 5, max: 20). The median is the number to compare"
+    )]
+    pub rounds: Option<u32>,
+
+    #[schemars(
+        description = "Size of the upload and download test payload in KiB (default: 1024, max: 16384)"
+    )]
+    pub payload_kb: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkReport {
+    pub server: String,
+    pub rounds: u32,
+    /// Opening an SSH channel: one request and reply, the connection's
+    /// round-trip time.
+    pub channel_open_ms: Timing,
+    /// Running a no-op command, as every tool call does at least once.
+    pub command_ms: Timing,
+    pub upload: Throughput,
+    pub download: Throughput,
+}
+
+/// Minimum, median, and maximum of the samples, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Timing {
+    pub min: f64,
+    pub median: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Throughput {
+    pub bytes: u64,
+    pub elapsed_ms: f64,
+    pub mib_per_sec: f64,
+}
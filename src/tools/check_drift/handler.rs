@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::schema::{CheckDriftInput, CheckDriftOutput, RecipeDrift};
+use crate::connection::SshConnection;
+use crate::server_registry::ServerRegistry;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::provision::handler::{apply, find_recipe};
+use crate::tools::provision::history::AppliedRecipe;
+use crate::tools::provision::{ProvisionReport, Recipe, StepKind, StepStatus};
+use crate::tools::{tool_error, ErrorCode, ToolError};
+
+/// A recipe selected for a drift check.
+#[derive(Debug, Clone)]
+pub struct DriftCheck {
+    pub name: String,
+    pub recipe: Recipe,
+    pub applied_at: Option<u64>,
+}
+
+/// Recipes to check on `server`, plus applied recipes missing from the config.
+#[derive(Debug, Clone, Default)]
+pub struct DriftPlan {
+    pub checks: Vec<DriftCheck>,
+    pub missing: Vec<String>,
+    /// The server's timeout settings, for recipe `unless` checks.
+    pub timeouts: TimeoutPolicy,
+}
+
+/// Pick the recipes to check: `requested` if given, otherwise every recipe
+/// in `applied` (from the provision history) that is still configured.
+///
+/// # Errors
+///
+/// Returns the tool error to report if nothing can be checked, or if a
+/// selected recipe is unknown, restricted, or invalid.
+pub fn plan_checks(
+    config: &ServerRegistry,
+    applied: &BTreeMap<String, AppliedRecipe>,
+    server: &str,
+    requested: Option<&str>,
+) -> Result<DriftPlan, ToolError> {
+    let timeouts = config
+        .get(server)
+        .map(TimeoutPolicy::for_server)
+        .unwrap_or_default();
+    if let Some(name) = requested {
+        return Ok(DriftPlan {
+            checks: vec![DriftCheck {
+                name: name.to_string(),
+                recipe: find_recipe(config, name, server)?,
+                applied_at: applied.get(name).map(|a| a.applied_at),
+            }],
+            missing: Vec::new(),
+            timeouts,
+        });
+    }
+
+    if applied.is_empty() {
+        return Err(ToolError::new(
+            ErrorCode::InvalidInput,
+            format!(
+                "no recipes have been applied to '{server}' with provision. Pass 'recipe' to check one explicitly."
+            ),
+        ));
+    }
+    let mut plan = DriftPlan {
+        timeouts,
+        ..DriftPlan::default()
+    };
+    for (name, record) in applied {
+        if config.recipes.contains_key(name) {
+            plan.checks.push(DriftCheck {
+                name: name.clone(),
+                recipe: find_recipe(config, name, server)?,
+                applied_at: Some(record.applied_at),
+            });
+        } else {
+            plan.missing.push(name.clone());
+        }
+    }
+    Ok(plan)
+}
+
+/// MCP entry point: run the planned checks and return the drift report as JSON.
+pub async fn handle(
+    conn: Arc<SshConnection>,
+    input: CheckDriftInput,
+    plan: DriftPlan,
+    config_dir: &Path,
+) -> String {
+    if let Some(error) = conn.posix_required("check_drift") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    let output = check(&conn, &input.server, plan, config_dir).await;
+    serde_json::to_string_pretty(&output)
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
+
+/// Evaluate every planned recipe in check-only mode. Nothing on the remote
+/// is changed.
+pub async fn check(
+    conn: &SshConnection,
+    server: &str,
+    plan: DriftPlan,
+    config_dir: &Path,
+) -> CheckDriftOutput {
+    let mut recipes = Vec::with_capacity(plan.checks.len());
+    for check in plan.checks {
+        let report = apply(
+            conn,
+            server,
+            &check.name,
+            &check.recipe,
+            config_dir,
+            true,
+            plan.timeouts,
+        )
+        .await;
+        recipes.push(summarize(&check, report));
+    }
+    CheckDriftOutput {
+        server: server.to_string(),
+        drifted: recipes.iter().map(|r| r.drifted.len()).sum(),
+        recipes,
+        missing_recipes: plan.missing,
+    }
+}
+
+/// Sort a dry-run report's steps into in-sync, drifted, unchecked, and errors.
+/// Commands without an `unless` check always report a pending change, so
+/// they are listed as unchecked rather than drifted.
+#[must_use]
+pub fn summarize(check: &DriftCheck, report: ProvisionReport) -> RecipeDrift {
+    let mut drift = RecipeDrift {
+        recipe: check.name.clone(),
+        applied_at: check.applied_at,
+        in_sync: 0,
+        drifted: Vec::new(),
+        unchecked: Vec::new(),
+        errors: Vec::new(),
+    };
+    for step in report.steps {
+        let unguarded = step.kind == StepKind::Command
+            && check
+                .recipe
+                .commands
+                .iter()
+                .any(|c| c.run == step.target && c.unless.is_none());
+        match step.status {
+            StepStatus::Ok | StepStatus::Changed => drift.in_sync += 1,
+            StepStatus::WouldChange if unguarded => drift.unchecked.push(step.target),
+            StepStatus::WouldChange => drift.drifted.push(step),
+            StepStatus::Failed | StepStatus::Skipped => drift.errors.push(step),
+        }
+    }
+    drift
+}
@@ -0,0 +1,45 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+use crate::tools::provision::StepReport;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CheckDriftInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+
+    #[schemars(
+        description = "Recipe to check. Defaults to every recipe previously applied to this server with provision"
+    )]
+    pub recipe: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckDriftOutput {
+    pub server: String,
+    pub recipes: Vec<RecipeDrift>,
+    /// Drifted steps across all recipes.
+    pub drifted: usize,
+    /// Recipes applied earlier that are no longer declared in `servers.toml`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing_recipes: Vec<String>,
+}
+
+/// Check-only evaluation of one recipe.
+#[derive(Debug, Serialize)]
+pub struct RecipeDrift {
+    pub recipe: String,
+    /// Unix timestamp of the last successful `provision`, if recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub applied_at: Option<u64>,
+    /// Steps that still match the recipe.
+    pub in_sync: usize,
+    /// Steps whose state differs from the recipe; `detail` says how.
+    pub drifted: Vec<StepReport>,
+    /// Commands without an `unless` check, whose effect can't be verified.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unchecked: Vec<String>,
+    /// Steps whose check failed to run (later steps are skipped).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<StepReport>,
+}
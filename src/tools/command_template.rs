@@ -0,0 +1,494 @@
+//! Typed remote command templates, exposed as dynamically registered tools.
+//!
+//! Templates are declared in `servers.toml`:
+//!
+//! ```toml
+//! [templates.restart_service]
+//! description = "Restart a systemd service"
+//! command = "sudo systemctl restart {name}"
+//! servers = ["prod", "staging"]
+//!
+//! [templates.restart_service.params.name]
+//! type = "string"
+//! description = "Unit name"
+//! pattern = "[a-z0-9@._-]+"
+//! ```
+//!
+//! Each template becomes an MCP tool taking `server` plus its declared
+//! parameters. Arguments are validated against the parameter schema and
+//! always shell-escaped before substitution, so agents get a curated verb set
+//! instead of raw bash.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use regex::Regex;
+use rmcp::model::{JsonObject, Tool};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::connection::SshConnection;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::remote_bash::RemoteBashOutput;
+use crate::tools::remote_error;
+use crate::utils::output::fit_output;
+use crate::utils::path::shell_escape;
+
+/// Arguments every template tool takes besides its declared params.
+const RESERVED_ARGS: [&str; 2] = ["server", "override_window"];
+
+/// A command template with a parameter schema.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommandTemplate {
+    pub description: String,
+    /// Command with `{param}` placeholders. `{{` and `}}` are literal braces.
+    pub command: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, TemplateParam>,
+    /// Servers this template may run on. Empty means all configured servers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub servers: Vec<String>,
+    /// Timeout in milliseconds (capped at 10 minutes). `0` means none, on
+    /// servers with `no_timeout`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+/// Schema for a single template parameter.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TemplateParam {
+    #[serde(rename = "type", default)]
+    pub kind: ParamType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Regex the whole value must match (strings only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Allowed values (strings only).
+    #[serde(default, rename = "enum", skip_serializing_if = "Vec::is_empty")]
+    pub allowed: Vec<String>,
+    /// Inclusive bounds (integers only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<i64>,
+    /// Value used when the argument is omitted. Parameters without a default
+    /// are required.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamType {
+    #[default]
+    String,
+    Integer,
+    Boolean,
+}
+
+impl CommandTemplate {
+    /// Check the template itself: placeholders must reference declared
+    /// parameters and patterns must compile.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        for placeholder in placeholders(&self.command)? {
+            if !self.params.contains_key(&placeholder) {
+                return Err(format!(
+                    "placeholder '{{{placeholder}}}' has no matching param"
+                ));
+            }
+        }
+        for (name, param) in &self.params {
+            if RESERVED_ARGS.contains(&name.as_str()) {
+                return Err(format!("param name '{name}' is reserved"));
+            }
+            if let Some(pattern) = &param.pattern {
+                full_match_regex(pattern)
+                    .map_err(|e| format!("param '{name}' has an invalid pattern: {e}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// A server's saved command as a template: each `{param}` placeholder
+    /// becomes a required string parameter. Saved commands are ordinary
+    /// shell, so only an unquoted `{name}` is a placeholder: braces inside
+    /// quotes, in `${...}`, and around anything but a name, such as
+    /// `awk '{print $1}'` or `{ a; b; }`, stay as written.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem when a placeholder is
+    /// unterminated or uses a reserved name.
+    pub fn saved(command: &str) -> Result<Self, String> {
+        let command = escape_shell_braces(command)?;
+        let command = command.as_str();
+        let params = placeholders(command)?
+            .into_iter()
+            .map(|name| (name, TemplateParam::default()))
+            .collect();
+        let template = Self {
+            description: String::new(),
+            command: command.to_string(),
+            params,
+            servers: Vec::new(),
+            timeout: None,
+        };
+        template.validate()?;
+        Ok(template)
+    }
+
+    /// Whether this template may run on `server`.
+    #[must_use]
+    pub fn allows_server(&self, server: &str) -> bool {
+        self.servers.is_empty() || self.servers.iter().any(|s| s == server)
+    }
+
+    /// Build the MCP tool definition for this template.
+    #[must_use]
+    pub fn to_tool(&self, name: &str) -> Tool {
+        let mut properties = Map::new();
+        let server_desc = if self.servers.is_empty() {
+            "Name of the configured server to run on".to_string()
+        } else {
+            format!(
+                "Name of the server to run on (one of: {})",
+                self.servers.join(", ")
+            )
+        };
+        let mut server_schema = json!({ "type": "string", "description": server_desc });
+        if !self.servers.is_empty() {
+            server_schema["enum"] = json!(self.servers);
+        }
+        properties.insert("server".to_string(), server_schema);
+        properties.insert(
+            "override_window".to_string(),
+            json!({
+                "type": "string",
+                "description": "Reason for running this outside the server's maintenance window. Only needed after a call was refused for that reason; the reason is logged",
+            }),
+        );
+
+        let mut required = vec![Value::from("server")];
+        for (param_name, param) in &self.params {
+            properties.insert(param_name.clone(), param.json_schema());
+            if param.default.is_none() {
+                required.push(Value::from(param_name.as_str()));
+            }
+        }
+
+        let mut schema = JsonObject::new();
+        schema.insert("type".to_string(), json!("object"));
+        schema.insert("properties".to_string(), Value::Object(properties));
+        schema.insert("required".to_string(), Value::Array(required));
+
+        Tool::new(
+            name.to_string(),
+            format!(
+                "{} (command template: `{}`)",
+                self.description, self.command
+            ),
+            Arc::new(schema),
+        )
+    }
+
+    /// Validate `args` against the parameter schema and substitute them,
+    /// shell-escaped, into the command.
+    ///
+    /// # Errors
+    ///
+    /// Returns a user-facing message for missing, unknown, or invalid arguments.
+    pub fn render(&self, args: &Map<String, Value>) -> Result<String, String> {
+        if let Some(unknown) = args
+            .keys()
+            .find(|k| !RESERVED_ARGS.contains(&k.as_str()) && !self.params.contains_key(*k))
+        {
+            return Err(format!("unknown argument '{unknown}'"));
+        }
+
+        let mut values = BTreeMap::new();
+        for (name, param) in &self.params {
+            let value = match args.get(name).filter(|v| !v.is_null()) {
+                Some(v) => v,
+                None => param
+                    .default
+                    .as_ref()
+                    .ok_or_else(|| format!("missing required argument '{name}'"))?,
+            };
+            values.insert(name.as_str(), param.check(name, value)?);
+        }
+
+        let mut out = String::with_capacity(self.command.len());
+        let mut chars = self.command.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    out.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    out.push('}');
+                }
+                '{' => {
+                    let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                    let value = values
+                        .get(name.as_str())
+                        .ok_or_else(|| format!("placeholder '{{{name}}}' has no matching param"))?;
+                    out.push_str(&shell_escape(value));
+                }
+                _ => out.push(c),
+            }
+        }
+        Ok(out)
+    }
+
+    /// Effective timeout for this template on a server with `timeouts`,
+    /// or `None` for no timeout.
+    ///
+    /// # Errors
+    ///
+    /// Returns a user-facing message when [`TimeoutPolicy::resolve`] does.
+    pub fn timeout_ms(&self, timeouts: TimeoutPolicy) -> Result<Option<u64>, String> {
+        timeouts.resolve(self.timeout, false)
+    }
+}
+
+impl TemplateParam {
+    fn json_schema(&self) -> Value {
+        let mut schema = json!({
+            "type": match self.kind {
+                ParamType::String => "string",
+                ParamType::Integer => "integer",
+                ParamType::Boolean => "boolean",
+            }
+        });
+        if let Some(desc) = &self.description {
+            schema["description"] = json!(desc);
+        }
+        if let Some(pattern) = &self.pattern {
+            schema["pattern"] = json!(format!("^(?:{pattern})$"));
+        }
+        if !self.allowed.is_empty() {
+            schema["enum"] = json!(self.allowed);
+        }
+        if let Some(min) = self.min {
+            schema["minimum"] = json!(min);
+        }
+        if let Some(max) = self.max {
+            schema["maximum"] = json!(max);
+        }
+        if let Some(default) = &self.default {
+            schema["default"] = default.clone();
+        }
+        schema
+    }
+
+    /// Validate a single argument and return its string form for substitution.
+    fn check(&self, name: &str, value: &Value) -> Result<String, String> {
+        match self.kind {
+            ParamType::String => {
+                let s = value
+                    .as_str()
+                    .ok_or_else(|| format!("argument '{name}' must be a string"))?;
+                if !self.allowed.is_empty() && !self.allowed.iter().any(|a| a == s) {
+                    return Err(format!(
+                        "argument '{name}' must be one of: {}",
+                        self.allowed.join(", ")
+                    ));
+                }
+                if let Some(pattern) = &self.pattern {
+                    let re = full_match_regex(pattern).map_err(|e| e.to_string())?;
+                    if !re.is_match(s) {
+                        return Err(format!("argument '{name}' must match pattern '{pattern}'"));
+                    }
+                }
+                Ok(s.to_string())
+            }
+            ParamType::Integer => {
+                let n = value
+                    .as_i64()
+                    .ok_or_else(|| format!("argument '{name}' must be an integer"))?;
+                if self.min.is_some_and(|min| n < min) || self.max.is_some_and(|max| n > max) {
+                    return Err(format!(
+                        "argument '{name}' must be between {} and {}",
+                        self.min
+                            .map_or_else(|| "-inf".to_string(), |v| v.to_string()),
+                        self.max
+                            .map_or_else(|| "inf".to_string(), |v| v.to_string()),
+                    ));
+                }
+                Ok(n.to_string())
+            }
+            ParamType::Boolean => value
+                .as_bool()
+                .map(|b| b.to_string())
+                .ok_or_else(|| format!("argument '{name}' must be a boolean")),
+        }
+    }
+}
+
+/// Compile `pattern` so it must match the whole value.
+fn full_match_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{pattern})$"))
+}
+
+/// Rewrite a saved command in template syntax by doubling every brace that
+/// isn't part of a `{name}` placeholder or a `{{`/`}}` escape. Braces are
+/// literal inside quotes, after a backslash, and in `${...}`.
+fn escape_shell_braces(command: &str) -> Result<String, String> {
+    let chars: Vec<char> = command.chars().collect();
+    let mut out = String::with_capacity(command.len());
+    let mut double_quoted = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        match c {
+            '\'' if !double_quoted => {
+                out.push(c);
+                while let Some(&c) = chars.get(i) {
+                    i += 1;
+                    push_literal(&mut out, c);
+                    if c == '\'' {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                double_quoted = !double_quoted;
+                out.push(c);
+            }
+            '\\' => {
+                out.push(c);
+                if let Some(&c) = chars.get(i) {
+                    i += 1;
+                    push_literal(&mut out, c);
+                }
+            }
+            '$' if chars.get(i) == Some(&'{') => {
+                out.push(c);
+                let mut depth = 0;
+                while let Some(&c) = chars.get(i) {
+                    i += 1;
+                    push_literal(&mut out, c);
+                    match c {
+                        '{' => depth += 1,
+                        '}' => depth -= 1,
+                        _ => {}
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            '{' | '}' if double_quoted => push_literal(&mut out, c),
+            '{' | '}' if chars.get(i) == Some(&c) => {
+                i += 1;
+                out.push(c);
+                out.push(c);
+            }
+            '{' => {
+                let name_len = chars[i..]
+                    .iter()
+                    .enumerate()
+                    .take_while(|&(n, &c)| {
+                        c == '_' || c.is_ascii_alphabetic() || (n > 0 && c.is_ascii_digit())
+                    })
+                    .count();
+                match chars.get(i + name_len) {
+                    Some('}') if name_len > 0 => {
+                        out.extend(&chars[i - 1..=i + name_len]);
+                        i += name_len + 1;
+                    }
+                    None if name_len > 0 => {
+                        return Err("unterminated '{' in command".to_string());
+                    }
+                    _ => out.push_str("{{"),
+                }
+            }
+            '}' => out.push_str("}}"),
+            _ => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+/// Push `c` so template rendering gives it back unchanged.
+fn push_literal(out: &mut String, c: char) {
+    if c == '{' || c == '}' {
+        out.push(c);
+    }
+    out.push(c);
+}
+
+/// Placeholder names in a command, honoring `{{`/`}}` escapes.
+fn placeholders(command: &str) -> Result<Vec<String>, String> {
+    let mut names = Vec::new();
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                if !closed {
+                    return Err("unterminated '{' in command".to_string());
+                }
+                names.push(name);
+            }
+            _ => {}
+        }
+    }
+    Ok(names)
+}
+
+/// Tool names must be usable as MCP tool identifiers.
+#[must_use]
+pub fn is_valid_tool_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Run a rendered template command and return the same JSON shape as
+/// `remote_bash`, with large stdout saved to a temp file named after `tool`.
+pub async fn handle(
+    conn: Arc<SshConnection>,
+    command: String,
+    timeout_ms: Option<u64>,
+    tool: &str,
+) -> String {
+    match conn.exec(&command, timeout_ms).await {
+        Ok(result) => {
+            let output = RemoteBashOutput {
+                stdout: fit_output(result.stdout, tool).await,
+                stderr: result.stderr,
+                exit_code: result.exit_code,
+                compressed_bytes: None,
+                cancelled: false,
+            };
+            serde_json::to_string_pretty(&output)
+                .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+        }
+        Err(e) => remote_error(&e, e.to_string()),
+    }
+}
@@ -0,0 +1,304 @@
+//! Tarball snapshots of remote directories, taken by `remote_snapshot` and
+//! put back by `remote_restore`.
+//!
+//! A snapshot of `/srv/app` taken at 2026-10-16 15:29:40.123 UTC lives on the
+//! same server at `~/.ssh-hub-snapshots/20261016T152940.123Z/srv/app.tar.gz`
+//! (or under the server's `snapshot_dir`), so the directory can be read back
+//! from the snapshot path alone. A label goes after the timestamp:
+//! `20261016T152940.123Z-before-upgrade/srv/app.tar.gz`.
+
+use anyhow::{anyhow, Result};
+
+use crate::connection::RemoteExecutor;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::file_backup::backup_stamp;
+use crate::utils::path::{shell_escape, shell_escape_remote_path};
+
+/// Snapshot directory used when the server config doesn't set `snapshot_dir`.
+pub const DEFAULT_SNAPSHOT_DIR: &str = "~/.ssh-hub-snapshots";
+
+/// Suffix of every snapshot file.
+const SNAPSHOT_SUFFIX: &str = ".tar.gz";
+
+/// Longest label accepted for a snapshot.
+const MAX_LABEL_LEN: usize = 64;
+
+/// Timeout for archiving or extracting a directory (10 minutes), unless the
+/// server's [`TimeoutPolicy`] raises it.
+pub const SNAPSHOT_TIMEOUT_MS: u64 = 600_000;
+
+/// Exit code of [`snapshot_command`] when the path is not a directory.
+const EXIT_NOT_A_DIRECTORY: i32 = 2;
+
+/// Label added to the snapshot `remote_restore` takes of the directory it
+/// replaces.
+pub const BEFORE_RESTORE_LABEL: &str = "before-restore";
+
+/// A snapshot that was taken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Absolute path of the tarball on the server.
+    pub path: String,
+    /// Size of the tarball in bytes.
+    pub bytes: u64,
+}
+
+/// Name of the snapshot subdirectory: the timestamp, then `-<label>` if
+/// given.
+///
+/// # Errors
+///
+/// Returns an error if the label is invalid; see [`check_label`].
+pub fn snapshot_name(stamp: &str, label: Option<&str>) -> Result<String> {
+    let Some(label) = label else {
+        return Ok(stamp.to_string());
+    };
+    check_label(label)?;
+    Ok(format!("{stamp}-{label}"))
+}
+
+/// Check a snapshot label before anything is archived.
+///
+/// # Errors
+///
+/// Returns an error if the label is empty, too long, or has characters
+/// other than ASCII letters, digits, `.`, `_`, and `-`.
+pub fn check_label(label: &str) -> Result<()> {
+    let valid = !label.is_empty()
+        && label.len() <= MAX_LABEL_LEN
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if !valid {
+        return Err(anyhow!(
+            "invalid snapshot label '{label}': use up to {MAX_LABEL_LEN} letters, digits, '.', '_', or '-'"
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `name` starts with a timestamp from [`backup_stamp`], such as
+/// `20261016T152940.123Z`.
+fn is_snapshot_name(name: &str) -> bool {
+    let stamp = name.as_bytes();
+    let digits = |range: std::ops::Range<usize>| stamp[range].iter().all(u8::is_ascii_digit);
+    stamp.len() >= 20
+        && digits(0..8)
+        && stamp[8] == b'T'
+        && digits(9..15)
+        && stamp[15] == b'.'
+        && digits(16..19)
+        && stamp[19] == b'Z'
+        && (stamp.len() == 20 || stamp[20] == b'-')
+}
+
+/// The directory a snapshot was taken of: the part of `snapshot_path`
+/// after the `<timestamp>[-<label>]` component, without `.tar.gz`.
+///
+/// # Errors
+///
+/// Returns an error if `snapshot_path` has no timestamp component, doesn't
+/// end in `.tar.gz`, or contains `..` components.
+pub fn snapshot_source(snapshot_path: &str) -> Result<String> {
+    let not_a_snapshot = || {
+        anyhow!(
+            "'{snapshot_path}' is not a snapshot path (expected .../<timestamp>/<directory>{SNAPSHOT_SUFFIX})"
+        )
+    };
+    if snapshot_path.split('/').any(|part| part == "..") {
+        return Err(not_a_snapshot());
+    }
+    let archive = snapshot_path
+        .strip_suffix(SNAPSHOT_SUFFIX)
+        .ok_or_else(not_a_snapshot)?;
+    let mut parts = archive.split('/');
+    parts
+        .by_ref()
+        .find(|part| is_snapshot_name(part))
+        .ok_or_else(not_a_snapshot)?;
+    let source: Vec<&str> = parts.collect();
+    if source.is_empty() || source.iter().any(|part| part.is_empty()) {
+        return Err(not_a_snapshot());
+    }
+    Ok(format!("/{}", source.join("/")))
+}
+
+/// Shell script that archives the directory `path` to
+/// `<snapshot_dir>/<name>/<absolute path>.tar.gz`, then prints the
+/// tarball's path and size on two lines. When `snapshot_dir` is inside the
+/// directory, it is left out of the archive. Exits 2 when `path` is not a
+/// directory.
+#[must_use]
+pub fn snapshot_command(path: &str, snapshot_dir: &str, name: &str) -> String {
+    format!(
+        "{}; \
+         [ -d \"$src\" ] || {{ echo \"Not a directory: $src\" >&2; exit {EXIT_NOT_A_DIRECTORY}; }}; \
+         src=$(cd \"$src\" && pwd -P) || exit 1; \
+         {}; \
+         dest=\"$dir/\"{}\"$src{SNAPSHOT_SUFFIX}\"; \
+         set --; case $dir/ in \"$src\"/*) set -- \"--exclude=./${{dir#\"$src\"/}}\" ;; esac; \
+         mkdir -p \"$(dirname \"$dest\")\" || exit 1; \
+         tar -czf \"$dest\" \"$@\" -C \"$src\" . || {{ rm -f \"$dest\"; exit 1; }}; \
+         printf '%s\\n' \"$dest\"; wc -c < \"$dest\"",
+        absolute("src", path),
+        absolute("dir", snapshot_dir),
+        shell_escape(name),
+    )
+}
+
+/// Shell script that prints the size and path of every snapshot of the
+/// directory `path`, one tab-separated line each.
+#[must_use]
+pub fn list_command(path: &str, snapshot_dir: &str) -> String {
+    format!(
+        "{}; src=$(cd \"$src\" 2>/dev/null && pwd -P || printf '%s' \"${{src%/}}\"); \
+         {}; \
+         for f in \"$dir\"/*\"$src{SNAPSHOT_SUFFIX}\"; do \
+         [ -f \"$f\" ] && printf '%s\\t%s\\n' \"$(wc -c < \"$f\" | tr -d ' ')\" \"$f\"; \
+         done; true",
+        absolute("src", path),
+        absolute("dir", snapshot_dir),
+    )
+}
+
+/// Shell script that replaces the directory `dest` with the contents of
+/// the snapshot `snapshot_path`. The snapshot is extracted beside `dest`
+/// first, so a failed extraction leaves `dest` as it was.
+#[must_use]
+pub fn restore_command(snapshot_path: &str, dest: &str) -> String {
+    format!(
+        "{}; dest={}; \
+         [ -f \"$snap\" ] || {{ echo \"No such snapshot: $snap\" >&2; exit 1; }}; \
+         case $snap in \"$dest\"/*) echo \"The snapshot is inside $dest, so it can't replace it\" >&2; exit 1 ;; esac; \
+         parent=$(dirname \"$dest\"); mkdir -p \"$parent\" || exit 1; \
+         stage=$(mktemp -d \"$parent/.ssh-hub-restore.XXXXXXXX\") || exit 1; \
+         tar -xpzf \"$snap\" -C \"$stage\" || {{ rm -rf \"$stage\"; exit 1; }}; \
+         old=; if [ -e \"$dest\" ]; then old=$stage.old; mv \"$dest\" \"$old\" || {{ rm -rf \"$stage\"; exit 1; }}; fi; \
+         mv \"$stage\" \"$dest\" || {{ [ -n \"$old\" ] && mv \"$old\" \"$dest\"; exit 1; }}; \
+         [ -z \"$old\" ] || rm -rf \"$old\"",
+        absolute("snap", snapshot_path),
+        shell_escape(dest),
+    )
+}
+
+/// Assign `path` to the shell variable `var`, made absolute against the
+/// working directory.
+fn absolute(var: &str, path: &str) -> String {
+    format!(
+        "{var}={}; case ${var} in /*) ;; *) {var}=$PWD/${var} ;; esac",
+        shell_escape_remote_path(path)
+    )
+}
+
+/// Archive the directory `path` into `snapshot_dir`, labelled `label`.
+/// Returns `None` if `path` is not a directory.
+///
+/// # Errors
+///
+/// Returns an error if the remote has no POSIX shell, the label is
+/// invalid, or archiving fails.
+pub async fn take_snapshot<C: RemoteExecutor + ?Sized>(
+    conn: &C,
+    path: &str,
+    snapshot_dir: &str,
+    label: Option<&str>,
+    timeouts: TimeoutPolicy,
+) -> Result<Option<Snapshot>> {
+    if let Some(error) = conn.posix_required("snapshots") {
+        return Err(anyhow!(error));
+    }
+    let since_epoch = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let name = snapshot_name(&backup_stamp(since_epoch), label)?;
+    let command = snapshot_command(path, snapshot_dir, &name);
+    let result = conn
+        .exec(&command, timeouts.operation(SNAPSHOT_TIMEOUT_MS))
+        .await?;
+    if result.exit_code == EXIT_NOT_A_DIRECTORY {
+        return Ok(None);
+    }
+    if result.exit_code != 0 {
+        return Err(anyhow!(
+            "snapshot of {path} failed: {}",
+            result.stderr.trim()
+        ));
+    }
+    let mut lines = result.stdout.lines();
+    let (Some(path), Some(bytes)) = (lines.next(), lines.next()) else {
+        return Err(anyhow!(
+            "snapshot of {path} printed no path: {}",
+            result.stdout.trim()
+        ));
+    };
+    Ok(Some(Snapshot {
+        path: path.to_string(),
+        bytes: bytes.trim().parse().unwrap_or(0),
+    }))
+}
+
+/// Snapshots of the directory `path` in `snapshot_dir`, oldest first.
+///
+/// # Errors
+///
+/// Returns an error if the remote has no POSIX shell or the listing fails.
+pub async fn list_snapshots<C: RemoteExecutor + ?Sized>(
+    conn: &C,
+    path: &str,
+    snapshot_dir: &str,
+    timeouts: TimeoutPolicy,
+) -> Result<Vec<Snapshot>> {
+    if let Some(error) = conn.posix_required("snapshots") {
+        return Err(anyhow!(error));
+    }
+    let result = conn
+        .exec(
+            &list_command(path, snapshot_dir),
+            timeouts.operation(SNAPSHOT_TIMEOUT_MS),
+        )
+        .await?;
+    if result.exit_code != 0 {
+        return Err(anyhow!(
+            "listing snapshots of {path} failed: {}",
+            result.stderr.trim()
+        ));
+    }
+    let mut snapshots: Vec<Snapshot> = result
+        .stdout
+        .lines()
+        .filter_map(|line| {
+            let (bytes, path) = line.split_once('\t')?;
+            Some(Snapshot {
+                path: path.to_string(),
+                bytes: bytes.trim().parse().unwrap_or(0),
+            })
+        })
+        .collect();
+    snapshots.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(snapshots)
+}
+
+/// Replace the directory a snapshot was taken of with the snapshot's
+/// contents. Returns the restored directory.
+///
+/// # Errors
+///
+/// Returns an error if `snapshot_path` is not a snapshot path or the
+/// extraction fails.
+pub async fn restore_snapshot<C: RemoteExecutor + ?Sized>(
+    conn: &C,
+    snapshot_path: &str,
+    timeouts: TimeoutPolicy,
+) -> Result<String> {
+    let dest = snapshot_source(snapshot_path)?;
+    let result = conn
+        .exec(
+            &restore_command(snapshot_path, &dest),
+            timeouts.operation(SNAPSHOT_TIMEOUT_MS),
+        )
+        .await?;
+    if result.exit_code != 0 {
+        return Err(anyhow!("restoring {dest} failed: {}", result.stderr.trim()));
+    }
+    Ok(dest)
+}
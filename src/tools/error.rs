@@ -0,0 +1,226 @@
+//! Structured errors for tool responses.
+//!
+//! Handlers report a failure by returning a [`ToolError`] as JSON, built
+//! with [`tool_error`] where the code is known, or [`remote_error`] where it
+//! follows from the type of a connection error. The result goes back to the
+//! client marked as an error, so agents can branch on `code` instead of the
+//! wording:
+//!
+//! ```json
+//! {
+//!   "error": {
+//!     "code": "CONNECTION_DEAD",
+//!     "message": "Connection unusable — retry to auto-reconnect: ...",
+//!     "retriable": true,
+//!     "suggestion": "Repeat the call; ssh-hub reconnects on its own."
+//!   }
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::connection::resolve::Unreachable;
+use crate::connection::{AuthFailed, ConnectionLost, EncryptedKey, HostKeyRejected, TimedOut};
+
+/// What kind of failure a tool call ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// No server by that name in the config.
+    ServerNotFound,
+    /// The SSH connection couldn't be made.
+    ConnectionFailed,
+    /// The SSH connection dropped during the call.
+    ConnectionDead,
+    /// The server refused every credential.
+    AuthFailed,
+    /// The key file is encrypted and ssh-hub has no passphrase for it.
+    KeyEncrypted,
+    /// The host key is unknown under `strict_host_key`, changed, or unconfirmed.
+    HostKeyRejected,
+    /// A file or directory doesn't exist.
+    FileNotFound,
+    /// Something else the call names doesn't exist: a process, a service,
+    /// a workspace, a token.
+    NotFound,
+    /// The remote (or sudo) refused access.
+    PermissionDenied,
+    /// The server is `readonly` and the call would change it.
+    Readonly,
+    /// The server is outside its maintenance window.
+    OutsideWindow,
+    /// The tool family's per-minute context budget is spent.
+    BudgetExceeded,
+    /// The server's rate limit for this kind of tool is reached.
+    Throttled,
+    /// The command or transfer ran out of time.
+    Timeout,
+    /// The client cancelled the call.
+    Cancelled,
+    /// The arguments don't make sense for this tool.
+    InvalidInput,
+    /// The tool isn't available on this server or in this configuration.
+    NotSupported,
+    /// Written content didn't match what was sent.
+    ChecksumMismatch,
+    /// Anything else; `message` has the details.
+    Failed,
+}
+
+impl ErrorCode {
+    /// The code for a failure of the connection or the remote, decided by
+    /// the error types in `error`'s chain. Errors without a known type are
+    /// [`Failed`](Self::Failed).
+    #[must_use]
+    pub fn of(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            let code = if cause.is::<EncryptedKey>() {
+                Self::KeyEncrypted
+            } else if cause.is::<HostKeyRejected>() {
+                Self::HostKeyRejected
+            } else if cause.is::<AuthFailed>() {
+                Self::AuthFailed
+            } else if cause.is::<Unreachable>() {
+                Self::ConnectionFailed
+            } else if cause.is::<ConnectionLost>() {
+                Self::ConnectionDead
+            } else if cause.is::<TimedOut>() {
+                Self::Timeout
+            } else if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+                match e.kind() {
+                    std::io::ErrorKind::NotFound => Self::FileNotFound,
+                    std::io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+                    std::io::ErrorKind::TimedOut => Self::Timeout,
+                    _ => continue,
+                }
+            } else {
+                continue;
+            };
+            return code;
+        }
+        Self::Failed
+    }
+
+    /// Whether repeating the same call unchanged may succeed.
+    #[must_use]
+    pub fn retriable(self) -> bool {
+        matches!(
+            self,
+            Self::ConnectionFailed
+                | Self::ConnectionDead
+                | Self::BudgetExceeded
+                | Self::Throttled
+                | Self::Timeout
+                | Self::ChecksumMismatch
+        )
+    }
+
+    /// What the agent can do about it, when there is a general answer.
+    #[must_use]
+    pub fn suggestion(self) -> Option<&'static str> {
+        Some(match self {
+            Self::ServerNotFound => "Call list_servers to see the configured servers.",
+            Self::ConnectionFailed => {
+                "Check that the host is up and reachable, then retry. Use reconnect_server after fixing the config."
+            }
+            Self::ConnectionDead => "Repeat the call; ssh-hub reconnects on its own.",
+            Self::AuthFailed => {
+                "The user needs to fix the credentials, e.g. load the key with ssh-add or run 'ssh-hub add' again."
+            }
+            Self::KeyEncrypted => {
+                "Ask the user to load the key with ssh-add, or to store its passphrase with 'ssh-hub key-passphrase <server>'."
+            }
+            Self::HostKeyRejected => {
+                "Ask the user to verify the server's host key; don't retry until they have."
+            }
+            Self::FileNotFound => "Check the path, e.g. with remote_glob.",
+            Self::PermissionDenied => {
+                "Use a path the login user can access, or sudo where the tool supports it."
+            }
+            Self::Readonly => "Use another server, or ask the user to change it.",
+            Self::OutsideWindow => {
+                "Wait for the maintenance window, or pass override_window with a reason if the config allows it."
+            }
+            Self::BudgetExceeded => "Wait for the time given in the message, then narrow the query.",
+            Self::Throttled => {
+                "Wait for the time given in the message, and do the work in fewer, larger calls."
+            }
+            Self::Timeout => {
+                "Raise the timeout, narrow the work, or run it in the background."
+            }
+            Self::InvalidInput => "Fix the arguments as the message describes.",
+            Self::NotSupported => "Use another tool, such as remote_bash.",
+            Self::ChecksumMismatch => "Repeat the call to write the file again.",
+            Self::NotFound | Self::Cancelled | Self::Failed => return None,
+        })
+    }
+}
+
+/// A failed tool call, as sent to the client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub retriable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    error: ToolError,
+}
+
+impl ToolError {
+    #[must_use]
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            retriable: code.retriable(),
+            suggestion: code.suggestion().map(str::to_string),
+        }
+    }
+
+    /// The error as the JSON sent to the client.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&Envelope {
+            error: self.clone(),
+        })
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+    }
+
+    /// Parse JSON made by [`to_json`](Self::to_json), or `None` for any
+    /// other output.
+    #[must_use]
+    pub fn from_json(json: &str) -> Option<Self> {
+        if !json.starts_with(ENVELOPE_START) {
+            return None;
+        }
+        serde_json::from_str::<Envelope>(json).ok().map(|e| e.error)
+    }
+
+    /// Whether a handler's output reports a failed call.
+    #[must_use]
+    pub fn is_error(output: &str) -> bool {
+        Self::from_json(output).is_some()
+    }
+}
+
+/// How every [`ToolError::to_json`] starts, checked before parsing so large
+/// successful outputs aren't parsed at all.
+const ENVELOPE_START: &str = "{\n  \"error\": {";
+
+/// A failed call as the text a handler returns.
+#[must_use]
+pub fn tool_error(code: ErrorCode, message: impl Into<String>) -> String {
+    ToolError::new(code, message).to_json()
+}
+
+/// A failed call whose code follows from the type of `error`, as the text a
+/// handler returns; see [`ErrorCode::of`].
+#[must_use]
+pub fn remote_error(error: &anyhow::Error, message: impl Into<String>) -> String {
+    tool_error(ErrorCode::of(error), message)
+}
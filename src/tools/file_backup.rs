@@ -0,0 +1,138 @@
+//! Copies of remote files taken before `remote_write` or `remote_edit`
+//! changes them, restored with `restore_backup`.
+//!
+//! A backup of `/etc/app.conf` taken at 2026-10-16 15:29:40.123 UTC lives on
+//! the same server at `~/.ssh-hub-backups/20261016T152940.123Z/etc/app.conf`,
+//! so the original path can be read back from the backup path alone.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+
+use crate::connection::RemoteExecutor;
+use crate::timeout::TimeoutPolicy;
+use crate::utils::log_file::civil_date;
+use crate::utils::path::{shell_escape, shell_escape_remote_path};
+
+/// Directory under the remote home that holds one subdirectory per backup.
+pub const BACKUP_DIR: &str = ".ssh-hub-backups";
+
+/// Timeout for copying a file into or out of the backup directory (1 minute),
+/// unless the server's [`TimeoutPolicy`] raises it.
+const BACKUP_TIMEOUT_MS: u64 = 60_000;
+
+/// Name of the directory for a backup taken `since_epoch` after the Unix
+/// epoch, e.g. `20261016T152940.123Z`. Sorts chronologically.
+#[must_use]
+pub fn backup_stamp(since_epoch: Duration) -> String {
+    let secs = since_epoch.as_secs();
+    let (y, mo, d) = civil_date(secs / 86_400);
+    let rem = secs % 86_400;
+    format!(
+        "{y:04}{mo:02}{d:02}T{:02}{:02}{:02}.{:03}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60,
+        since_epoch.subsec_millis(),
+    )
+}
+
+/// Shell script that copies `path` into the backup directory `stamp`,
+/// keeping its mode and times, and prints the backup's absolute path.
+/// Prints nothing when `path` doesn't exist, since there is nothing to undo.
+#[must_use]
+pub fn backup_command(path: &str, stamp: &str) -> String {
+    format!(
+        "src={}; case $src in /*) ;; *) src=$PWD/$src ;; esac; \
+         [ -e \"$src\" ] || exit 0; \
+         dest=\"$HOME/{BACKUP_DIR}/\"{}\"$src\"; \
+         mkdir -p \"$(dirname \"$dest\")\" && cp -p -- \"$src\" \"$dest\" && printf '%s' \"$dest\"",
+        shell_escape_remote_path(path),
+        shell_escape(stamp),
+    )
+}
+
+/// The file a backup was taken from: the part of `backup_path` after
+/// `.ssh-hub-backups/<stamp>`.
+///
+/// # Errors
+///
+/// Returns an error if `backup_path` is not inside a backup directory or
+/// contains `..` components.
+pub fn original_path(backup_path: &str) -> Result<String> {
+    let not_a_backup = || {
+        anyhow!(
+            "'{backup_path}' is not a backup path (expected .../{BACKUP_DIR}/<timestamp>/<file>)"
+        )
+    };
+    if backup_path.split('/').any(|part| part == "..") {
+        return Err(not_a_backup());
+    }
+    let marker = format!("{BACKUP_DIR}/");
+    let (_, rest) = backup_path.split_once(&marker).ok_or_else(not_a_backup)?;
+    let (stamp, original) = rest.split_once('/').ok_or_else(not_a_backup)?;
+    if stamp.is_empty() || original.is_empty() {
+        return Err(not_a_backup());
+    }
+    Ok(format!("/{original}"))
+}
+
+/// Copy `path` into a new backup directory on the remote. Returns the
+/// backup path, or `None` if the file doesn't exist yet.
+///
+/// # Errors
+///
+/// Returns an error if the remote has no POSIX shell or the copy fails.
+pub async fn backup_file<C: RemoteExecutor + ?Sized>(
+    conn: &C,
+    path: &str,
+    timeouts: TimeoutPolicy,
+) -> Result<Option<String>> {
+    if !conn.shell().is_posix() {
+        return Err(anyhow!(
+            "backups require a POSIX shell on the remote (this server uses {})",
+            conn.shell()
+        ));
+    }
+    let since_epoch = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let command = backup_command(path, &backup_stamp(since_epoch));
+    let result = conn
+        .exec(&command, timeouts.operation(BACKUP_TIMEOUT_MS))
+        .await?;
+    if result.exit_code != 0 {
+        return Err(anyhow!("backup of {path} failed: {}", result.stderr.trim()));
+    }
+    let backup = result.stdout.trim();
+    Ok((!backup.is_empty()).then(|| backup.to_string()))
+}
+
+/// Copy a backup back over the file it was taken from, keeping its mode
+/// and times. Returns the restored path.
+///
+/// # Errors
+///
+/// Returns an error if `backup_path` is not a backup path or the copy fails.
+pub async fn restore_file<C: RemoteExecutor + ?Sized>(
+    conn: &C,
+    backup_path: &str,
+    timeouts: TimeoutPolicy,
+) -> Result<String> {
+    let original = original_path(backup_path)?;
+    let command = format!(
+        "cp -p -- {} {}",
+        shell_escape_remote_path(backup_path),
+        shell_escape(&original),
+    );
+    let result = conn
+        .exec(&command, timeouts.operation(BACKUP_TIMEOUT_MS))
+        .await?;
+    if result.exit_code != 0 {
+        return Err(anyhow!(
+            "restoring {original} failed: {}",
+            result.stderr.trim()
+        ));
+    }
+    Ok(original)
+}
@@ -0,0 +1,232 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use futures::stream::{self, StreamExt};
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+
+use super::schema::{
+    ConnectionState, LastError, ListServersInput, ListServersOutput, ProbeResult, ProbeStatus,
+    ServerListing,
+};
+use crate::connection::resolve::{connect_any, resolve_addrs};
+use crate::connection::ConnectionPool;
+use crate::metadata::SystemMetadata;
+use crate::server_registry::{ServerChanges, ServerEntry};
+use crate::utils::gitignore::glob_match;
+use crate::utils::output::fit_json;
+
+/// How long a probe result is reused before the server is probed again.
+pub const PROBE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Time allowed for the name lookup, kept apart from the connect timeout so
+/// a black-holed resolver can't use up the whole probe.
+const DNS_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Time allowed for the TCP connect once the name has resolved.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Delay before racing the next address of a multi-address host.
+const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Probes in flight at once.
+const PROBE_CONCURRENCY: usize = 16;
+
+/// Cache key: address probed and port.
+type CacheKey = (String, u16);
+
+/// Recent probe results keyed by address, shared across `list_servers` calls.
+#[derive(Default)]
+pub struct ProbeCache {
+    entries: Mutex<HashMap<CacheKey, (Instant, ProbeResult)>>,
+}
+
+impl ProbeCache {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The result of probing `host:port` within [`PROBE_CACHE_TTL`], with
+    /// `cached_secs` filled in.
+    pub async fn lookup(&self, host: &str, port: u16) -> Option<ProbeResult> {
+        let entries = self.entries.lock().await;
+        let (probed_at, result) = entries.get(&(host.to_string(), port))?;
+        let age = probed_at.elapsed();
+        (age < PROBE_CACHE_TTL).then_some(ProbeResult {
+            cached_secs: Some(age.as_secs()),
+            ..*result
+        })
+    }
+
+    pub async fn record(&self, host: &str, port: u16, result: ProbeResult) {
+        let mut entries = self.entries.lock().await;
+        entries.retain(|_, (probed_at, _)| probed_at.elapsed() < PROBE_CACHE_TTL);
+        entries.insert((host.to_string(), port), (Instant::now(), result));
+    }
+}
+
+/// Resolve `host` and open a TCP connection to its SSH port, each step
+/// under its own timeout.
+pub async fn probe(host: &str, port: u16) -> ProbeResult {
+    let failed = |status| ProbeResult {
+        status,
+        latency_ms: None,
+        cached_secs: None,
+    };
+    let addrs = match timeout(DNS_TIMEOUT, resolve_addrs(host, port, None)).await {
+        Ok(Ok(addrs)) => addrs,
+        Ok(Err(_)) => return failed(ProbeStatus::DnsFailed),
+        Err(_) => return failed(ProbeStatus::DnsTimeout),
+    };
+    let start = Instant::now();
+    match timeout(CONNECT_TIMEOUT, connect_any(&addrs, ATTEMPT_DELAY)).await {
+        Ok(Ok(_)) => ProbeResult {
+            status: ProbeStatus::Reachable,
+            latency_ms: Some(u32::try_from(start.elapsed().as_millis()).unwrap_or(u32::MAX)),
+            cached_secs: None,
+        },
+        _ => failed(ProbeStatus::Unreachable),
+    }
+}
+
+/// The cached result for `host:port`, or a new probe unless `skip_probe`.
+async fn cached_probe(
+    cache: &ProbeCache,
+    host: String,
+    port: u16,
+    skip_probe: bool,
+) -> Option<ProbeResult> {
+    if let Some(result) = cache.lookup(&host, port).await {
+        return Some(result);
+    }
+    if skip_probe {
+        return None;
+    }
+    let result = probe(&host, port).await;
+    cache.record(&host, port, result).await;
+    Some(result)
+}
+
+/// Message for a tag filter that matched nothing, listing the tags in use.
+fn no_tagged_servers(tags: &[String], in_use: &BTreeSet<String>) -> String {
+    let wanted = tags.join(", ");
+    if in_use.is_empty() {
+        format!("No server is tagged {wanted}. No configured server has tags.")
+    } else {
+        let in_use: Vec<&str> = in_use.iter().map(String::as_str).collect();
+        format!(
+            "No server is tagged {wanted}. Tags in use: {}.",
+            in_use.join(", ")
+        )
+    }
+}
+
+/// List `servers` as JSON, keeping those that carry every tag in `tags`,
+/// match `name_pattern`, and are connected when `connected_only` is set,
+/// and probing those without a fresh cached result unless `skip_probe` is
+/// set. `changes` are the config changes since the previous call, reported
+/// alongside the listing.
+pub async fn handle(
+    mut servers: Vec<(String, ServerEntry)>,
+    pool: &ConnectionPool,
+    cache: &ProbeCache,
+    input: ListServersInput,
+    changes: ServerChanges,
+) -> String {
+    if servers.is_empty() {
+        let removed = if changes.removed.is_empty() {
+            String::new()
+        } else {
+            format!(
+                " Removed since the last call: {}.",
+                changes.removed.join(", ")
+            )
+        };
+        return format!(
+            "No servers are configured. Add servers via 'ssh-hub add <name> <connection>'.{removed}"
+        );
+    }
+    let tags = input.tags.unwrap_or_default();
+    if !tags.is_empty() {
+        let in_use: BTreeSet<String> = servers
+            .iter()
+            .flat_map(|(_, entry)| entry.tags.iter().cloned())
+            .collect();
+        servers.retain(|(_, entry)| entry.has_tags(&tags));
+        if servers.is_empty() {
+            return no_tagged_servers(&tags, &in_use);
+        }
+    }
+    if let Some(pattern) = &input.name_pattern {
+        servers.retain(|(name, _)| name == pattern || glob_match(pattern, name));
+        if servers.is_empty() {
+            return format!("No configured server matches '{pattern}'.");
+        }
+    }
+    let connected: HashSet<String> = pool.list().await.into_iter().collect();
+    if input.connected_only.unwrap_or(false) {
+        servers.retain(|(name, _)| connected.contains(name));
+        if servers.is_empty() {
+            return "No listed server is connected. Tools connect on first use.".to_string();
+        }
+    }
+    servers.sort_by(|a, b| a.0.cmp(&b.0));
+    let skip_probe = input.skip_probe.unwrap_or(false);
+
+    let targets: Vec<(String, u16)> = servers
+        .iter()
+        .map(|(_, entry)| (entry.connect_host().to_string(), entry.port))
+        .collect();
+    let results: Vec<Option<ProbeResult>> = stream::iter(targets)
+        .map(|(host, port)| cached_probe(cache, host, port, skip_probe))
+        .buffered(PROBE_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut states: HashMap<String, ConnectionState> = pool
+        .stats()
+        .await
+        .into_iter()
+        .map(|entry| {
+            let state = ConnectionState {
+                health: entry.health,
+                age_secs: entry.age_secs,
+                idle_secs: entry.idle_secs,
+                channels_in_use: entry.channels_in_use,
+            };
+            (entry.server, state)
+        })
+        .collect();
+    let mut listings = Vec::with_capacity(servers.len());
+    for ((name, entry), probe) in servers.into_iter().zip(results) {
+        let last_error = pool.last_error(&name).await.map(|e| LastError {
+            age_secs: e.age_secs(),
+            message: e.message,
+        });
+        listings.push(ServerListing {
+            connected: connected.contains(&name),
+            connection: states.remove(&name),
+            target: format!("{}@{}:{}", entry.user, entry.host, entry.port),
+            system: entry
+                .metadata
+                .as_ref()
+                .and_then(SystemMetadata::summary_line),
+            metadata: entry.metadata,
+            auth: entry.auth,
+            name,
+            remote_path: entry.remote_path,
+            tags: entry.tags,
+            readonly: entry.readonly,
+            saved_commands: entry.commands.into_keys().collect(),
+            last_error,
+            probe,
+        });
+    }
+    let config_changes = (!changes.is_empty()).then_some(changes);
+    let output = ListServersOutput {
+        servers: listings,
+        config_changes,
+    };
+    fit_json(&output, &["/servers"], "list_servers").await
+}
@@ -0,0 +1,103 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+use crate::connection::Health;
+use crate::metadata::SystemMetadata;
+use crate::server_registry::{AuthMethod, ServerChanges};
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct ListServersInput {
+    #[schemars(
+        description = "Skip the reachability probes and list only the configured servers, with any probe results still cached from a recent call (default: false)"
+    )]
+    pub skip_probe: Option<bool>,
+    #[schemars(
+        description = "Only list servers carrying every one of these tags (e.g. ['gpu'] or ['env=staging'])"
+    )]
+    pub tags: Option<Vec<String>>,
+    #[schemars(
+        description = "Only list servers whose name matches this glob (e.g. 'web-*'), or equals it"
+    )]
+    pub name_pattern: Option<String>,
+    #[schemars(description = "Only list servers with an open pooled connection (default: false)")]
+    pub connected_only: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListServersOutput {
+    pub servers: Vec<ServerListing>,
+    /// Servers added, removed, or changed in `servers.toml` since the
+    /// previous `list_servers` call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_changes: Option<ServerChanges>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServerListing {
+    pub name: String,
+    /// `user@host:port`.
+    pub target: String,
+    pub remote_path: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub readonly: bool,
+    /// Names of the commands `run_saved_command` can run on this server.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub saved_commands: Vec<String>,
+    pub auth: AuthMethod,
+    /// Distro, architecture, and package manager from collected metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    /// Everything collected about the server on its last connection.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<SystemMetadata>,
+    /// Whether the pool holds a connection to this server.
+    pub connected: bool,
+    /// State of the pooled connection, when there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connection: Option<ConnectionState>,
+    /// Why the last attempt to connect failed, unless one has succeeded since.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<LastError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub probe: Option<ProbeResult>,
+}
+
+/// A pooled connection, as in `pool_stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ConnectionState {
+    pub health: Health,
+    pub age_secs: u64,
+    pub idle_secs: u64,
+    pub channels_in_use: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LastError {
+    pub message: String,
+    pub age_secs: u64,
+}
+
+/// Outcome of a TCP probe of a server's SSH port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ProbeResult {
+    pub status: ProbeStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u32>,
+    /// Seconds since the probe ran, when the result came from the cache.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cached_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeStatus {
+    Reachable,
+    /// The address resolved but the SSH port didn't accept a connection.
+    Unreachable,
+    /// The name lookup failed.
+    DnsFailed,
+    /// The name lookup didn't finish within its own timeout.
+    DnsTimeout,
+}
@@ -0,0 +1,93 @@
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::process::Command;
+
+use super::schema::LocalBashInput;
+use crate::timeout::{TimeoutPolicy, DEFAULT_TIMEOUT_MS};
+use crate::tools::remote_bash::RemoteBashOutput;
+use crate::tools::{remote_error, tool_error, ErrorCode};
+use crate::utils::output::fit_output;
+
+/// Run a command on the machine the MCP server runs on, when the config
+/// sets `allow_local = true`.
+///
+/// Output is returned like `remote_bash` output: JSON with stdout, stderr,
+/// and exit code, with stdout over 128 KB saved to a local temp file and
+/// summarized.
+pub async fn handle(input: LocalBashInput, allow_local: bool) -> String {
+    if !allow_local {
+        return tool_error(
+            ErrorCode::NotSupported,
+            "local_bash is disabled. Set allow_local = true in servers.toml to enable it",
+        );
+    }
+    let timeout = match TimeoutPolicy::default().resolve(input.timeout, false) {
+        Ok(timeout) => timeout.unwrap_or(DEFAULT_TIMEOUT_MS),
+        Err(e) => return tool_error(ErrorCode::InvalidInput, e),
+    };
+
+    let mut command = Command::new("sh");
+    command
+        .args(["-c", &input.command])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(cwd) = &input.cwd {
+        command.current_dir(cwd);
+    }
+    let child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            let message = format!("failed to start the command: {e}");
+            return remote_error(&e.into(), message);
+        }
+    };
+
+    let output = match tokio::time::timeout(
+        Duration::from_millis(timeout),
+        child.wait_with_output(),
+    )
+    .await
+    {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => {
+            let message = e.to_string();
+            return remote_error(&e.into(), message);
+        }
+        Err(_) => {
+            let message = format!("Command timed out after {timeout}ms");
+            return tool_error(ErrorCode::Timeout, message);
+        }
+    };
+
+    let output = RemoteBashOutput {
+        stdout: fit_output(
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+            "local_bash",
+        )
+        .await,
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        // Killed by a signal: report it the way a shell would (128 + signal).
+        exit_code: output
+            .status
+            .code()
+            .unwrap_or_else(|| signal_exit_code(output.status)),
+        compressed_bytes: None,
+        cancelled: false,
+    };
+    serde_json::to_string_pretty(&output)
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
+
+#[cfg(unix)]
+fn signal_exit_code(status: std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal().map_or(-1, |signal| 128 + signal)
+}
+
+#[cfg(not(unix))]
+fn signal_exit_code(_status: std::process::ExitStatus) -> i32 {
+    -1
+}
@@ -0,0 +1,21 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LocalBashInput {
+    #[schemars(description = "The command to execute, run with 'sh -c'")]
+    pub command: String,
+
+    #[schemars(
+        description = "Directory to run the command in. Defaults to the MCP server's working directory"
+    )]
+    pub cwd: Option<String>,
+
+    #[schemars(
+        description = "Timeout in milliseconds. Defaults to 120000 (2 min), max 600000 (10 min)"
+    )]
+    pub timeout: Option<u64>,
+
+    #[schemars(description = "Clear, concise description of what this command does")]
+    pub description: Option<String>,
+}
@@ -0,0 +1,18 @@
+use super::schema::{MetricsFormat, MetricsInput};
+use crate::metrics::Metrics;
+
+/// Report `metrics` in the requested format, or how to turn them on.
+#[must_use]
+pub fn handle(metrics: Option<&Metrics>, input: &MetricsInput) -> String {
+    let Some(metrics) = metrics else {
+        return "Metrics are off. Set `enabled = true` under [metrics] in servers.toml and \
+                restart the MCP server to start counting."
+            .to_string();
+    };
+    let snapshot = metrics.snapshot();
+    match input.format.unwrap_or_default() {
+        MetricsFormat::Json => serde_json::to_string_pretty(&snapshot)
+            .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#)),
+        MetricsFormat::Prometheus => snapshot.to_prometheus(),
+    }
+}
@@ -0,0 +1,18 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct MetricsInput {
+    #[schemars(
+        description = "Output format: 'json' (default) or 'prometheus' for the Prometheus text exposition format"
+    )]
+    pub format: Option<MetricsFormat>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricsFormat {
+    #[default]
+    Json,
+    Prometheus,
+}
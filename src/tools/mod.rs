@@ -1,16 +1,78 @@
+pub mod benchmark_server;
+pub mod check_drift;
+pub mod command_template;
+pub mod dir_snapshot;
+pub mod error;
+pub mod file_backup;
+pub mod list_servers;
+pub mod local_bash;
+pub mod metrics;
+pub mod provision;
+pub mod remote_audit;
 pub mod remote_bash;
+pub mod remote_bootstrap;
+pub mod remote_disk;
 pub mod remote_edit;
 pub mod remote_glob;
+pub mod remote_patch;
+pub mod remote_process;
 pub mod remote_read;
+pub mod remote_restore;
+pub mod remote_rollout;
+pub mod remote_service;
+pub mod remote_snapshot;
+pub mod remote_stat;
+pub mod remote_tail;
+pub mod remote_wait_for;
+pub mod remote_watch;
+pub mod remote_whoami;
 pub mod remote_write;
+pub mod restore_backup;
+pub mod run_saved_command;
+pub mod server_connection;
+pub mod socks_proxy;
+pub mod sync_chunked;
+pub mod sync_journal;
+pub mod sync_manifest;
 pub mod sync_pull;
 pub mod sync_push;
+pub mod sync_status;
+pub mod sync_transfer;
 pub mod sync_types;
+pub mod sync_verify;
+pub mod workspace;
 // Re-export input types for convenient use in server.rs
+pub use benchmark_server::BenchmarkServerInput;
+pub use check_drift::CheckDriftInput;
+pub use error::{remote_error, tool_error, ErrorCode, ToolError};
+pub use list_servers::ListServersInput;
+pub use local_bash::LocalBashInput;
+pub use metrics::MetricsInput;
+pub use provision::ProvisionInput;
+pub use remote_audit::RemoteAuditInput;
 pub use remote_bash::RemoteBashInput;
+pub use remote_bootstrap::RemoteBootstrapInput;
+pub use remote_disk::{RemoteDfInput, RemoteDuInput};
 pub use remote_edit::RemoteEditInput;
 pub use remote_glob::RemoteGlobInput;
+pub use remote_patch::RemotePatchInput;
+pub use remote_process::{RemoteKillInput, RemotePsInput};
 pub use remote_read::RemoteReadInput;
+pub use remote_restore::RemoteRestoreInput;
+pub use remote_rollout::RemoteRolloutInput;
+pub use remote_service::RemoteServiceInput;
+pub use remote_snapshot::RemoteSnapshotInput;
+pub use remote_stat::RemoteStatInput;
+pub use remote_tail::RemoteTailInput;
+pub use remote_wait_for::RemoteWaitForInput;
+pub use remote_watch::RemoteWatchInput;
+pub use remote_whoami::RemoteWhoamiInput;
 pub use remote_write::RemoteWriteInput;
+pub use restore_backup::RestoreBackupInput;
+pub use run_saved_command::RunSavedCommandInput;
+pub use server_connection::{DisconnectServerInput, ReconnectServerInput};
+pub use socks_proxy::SocksProxyInput;
 pub use sync_pull::SyncPullInput;
 pub use sync_push::SyncPushInput;
+pub use sync_status::SyncStatusInput;
+pub use workspace::{RemoteTmpdirInput, WorkspaceCleanupInput, WorkspaceCreateInput};
@@ -0,0 +1,516 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+
+use super::history::record_applied;
+use super::package::{PackageManager, DETECT_PACKAGE_MANAGER};
+use super::recipe::{Recipe, RecipeCommand, RecipeFile};
+use super::schema::{ProvisionInput, ProvisionReport, StepKind, StepReport, StepStatus};
+use crate::connection::SshConnection;
+use crate::server_registry::ServerRegistry;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::{tool_error, ErrorCode, ToolError};
+use crate::utils::command::CommandBuilder;
+use crate::utils::path::{normalize_remote_path, shell_escape, shell_escape_remote_path};
+
+/// Timeout for state checks and file writes (60 seconds).
+const CHECK_TIMEOUT_MS: u64 = 60_000;
+
+/// Timeout for package installs (10 minutes), unless the server's
+/// [`TimeoutPolicy`] raises it.
+const INSTALL_TIMEOUT_MS: u64 = 600_000;
+
+/// Output lines kept in a failed step's detail.
+const DETAIL_LINES: usize = 5;
+
+/// Look up `recipe` in the config and check it may run on `server`.
+///
+/// # Errors
+///
+/// Returns the tool error to report for unknown, restricted, or invalid
+/// recipes.
+pub fn find_recipe(
+    config: &ServerRegistry,
+    recipe: &str,
+    server: &str,
+) -> Result<Recipe, ToolError> {
+    let Some(found) = config.recipes.get(recipe) else {
+        let message = if config.recipes.is_empty() {
+            format!("recipe '{recipe}' not found. No recipes are configured under [recipes] in servers.toml.")
+        } else {
+            format!(
+                "recipe '{recipe}' not found. Configured recipes: {}.",
+                config
+                    .recipes
+                    .keys()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+        return Err(ToolError::new(ErrorCode::NotFound, message));
+    };
+    if !found.allows_server(server) {
+        return Err(ToolError::new(
+            ErrorCode::InvalidInput,
+            format!(
+                "recipe '{recipe}' is not enabled for server '{server}'. Allowed: {}.",
+                found.servers.join(", ")
+            ),
+        ));
+    }
+    found.validate().map_err(|e| {
+        ToolError::new(
+            ErrorCode::InvalidInput,
+            format!("recipe '{recipe}' is invalid: {e}"),
+        )
+    })?;
+    Ok(found.clone())
+}
+
+/// MCP entry point: apply an already-resolved recipe and return the report as JSON.
+pub async fn handle(
+    conn: Arc<SshConnection>,
+    input: ProvisionInput,
+    recipe: Recipe,
+    config_dir: &Path,
+    timeouts: TimeoutPolicy,
+) -> String {
+    if let Some(error) = conn.posix_required("provision") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    let report = apply(
+        &conn,
+        &input.server,
+        &input.recipe,
+        &recipe,
+        config_dir,
+        input.dry_run.unwrap_or(false),
+        timeouts,
+    )
+    .await;
+    serde_json::to_string_pretty(&report)
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
+
+/// Apply `recipe` step by step, with `timeouts` deciding how long its
+/// commands may run. After the first failure the remaining steps
+/// are reported as skipped. A successful real run is recorded in the
+/// [`ProvisionHistory`](super::history::ProvisionHistory) for `check_drift`.
+pub async fn apply(
+    conn: &SshConnection,
+    server: &str,
+    name: &str,
+    recipe: &Recipe,
+    config_dir: &Path,
+    dry_run: bool,
+    timeouts: TimeoutPolicy,
+) -> ProvisionReport {
+    let mut applier = Applier {
+        conn,
+        sudo: recipe.sudo,
+        dry_run,
+        timeouts,
+        steps: Vec::new(),
+        failed: false,
+    };
+    applier.packages(&recipe.packages).await;
+    for file in &recipe.files {
+        applier.file(file, config_dir).await;
+    }
+    for command in &recipe.commands {
+        applier.command(command).await;
+    }
+    for service in &recipe.services {
+        applier.service(service).await;
+    }
+
+    let changed = applier
+        .steps
+        .iter()
+        .filter(|s| matches!(s.status, StepStatus::Changed | StepStatus::WouldChange))
+        .count();
+    if !dry_run && !applier.failed {
+        record_applied(server, name);
+    }
+    ProvisionReport {
+        recipe: name.to_string(),
+        server: server.to_string(),
+        dry_run,
+        steps: applier.steps,
+        changed,
+        failed: applier.failed,
+    }
+}
+
+struct Applier<'a> {
+    conn: &'a SshConnection,
+    sudo: bool,
+    dry_run: bool,
+    timeouts: TimeoutPolicy,
+    steps: Vec<StepReport>,
+    failed: bool,
+}
+
+impl Applier<'_> {
+    fn record(&mut self, kind: StepKind, target: &str, status: StepStatus, detail: Option<String>) {
+        if status == StepStatus::Failed {
+            self.failed = true;
+        }
+        self.steps.push(StepReport {
+            kind,
+            target: target.to_string(),
+            status,
+            detail,
+        });
+    }
+
+    /// Record the step as skipped if an earlier step failed.
+    fn skip_after_failure(&mut self, kind: StepKind, target: &str) -> bool {
+        if self.failed {
+            self.record(kind, target, StepStatus::Skipped, None);
+        }
+        self.failed
+    }
+
+    /// Wrap `command` in `sudo -n sh -c` when the recipe asks for sudo.
+    fn privileged(&self, command: &str) -> String {
+        if self.sudo {
+            CommandBuilder::new("sudo")
+                .words(["-n", "sh", "-c"])
+                .arg(command)
+                .build()
+        } else {
+            command.to_string()
+        }
+    }
+
+    /// Run `command`, mapping SSH errors and non-zero exits to a failure detail.
+    async fn exec(&self, command: &str, timeout_ms: Option<u64>) -> Result<String, String> {
+        match self.conn.exec(command, timeout_ms).await {
+            Ok(r) if r.exit_code == 0 => Ok(r.stdout),
+            Ok(r) => Err(failure_detail(r.exit_code, &r.stdout, &r.stderr)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    async fn packages(&mut self, packages: &[String]) {
+        if packages.is_empty() {
+            return;
+        }
+        if self.failed {
+            for package in packages {
+                self.record(StepKind::Package, package, StepStatus::Skipped, None);
+            }
+            return;
+        }
+
+        let manager = match self
+            .exec(DETECT_PACKAGE_MANAGER, Some(CHECK_TIMEOUT_MS))
+            .await
+        {
+            Ok(out) => PackageManager::from_command(out.trim()),
+            Err(e) => {
+                for package in packages {
+                    self.record(
+                        StepKind::Package,
+                        package,
+                        StepStatus::Failed,
+                        Some(e.clone()),
+                    );
+                }
+                return;
+            }
+        };
+        let Some(manager) = manager else {
+            for package in packages {
+                self.record(
+                    StepKind::Package,
+                    package,
+                    StepStatus::Failed,
+                    Some("no supported package manager found".to_string()),
+                );
+            }
+            return;
+        };
+
+        let list = packages
+            .iter()
+            .map(|p| shell_escape(p))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let check = format!(
+            "for p in {list}; do {} || echo \"$p\"; done; true",
+            manager.installed_check()
+        );
+        let missing: Vec<String> = match self.exec(&check, Some(CHECK_TIMEOUT_MS)).await {
+            Ok(out) => out.lines().map(|l| l.trim().to_string()).collect(),
+            Err(e) => {
+                for package in packages {
+                    self.record(
+                        StepKind::Package,
+                        package,
+                        StepStatus::Failed,
+                        Some(e.clone()),
+                    );
+                }
+                return;
+            }
+        };
+
+        let (status, detail) = if missing.is_empty() {
+            (StepStatus::Ok, None)
+        } else if self.dry_run {
+            (StepStatus::WouldChange, Some("not installed".to_string()))
+        } else {
+            let to_install = missing
+                .iter()
+                .map(|p| shell_escape(p))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let install = manager.install_command(&to_install);
+            // Homebrew refuses to run as root.
+            let install = if manager == PackageManager::Brew {
+                install
+            } else {
+                self.privileged(&install)
+            };
+            match self
+                .exec(&install, self.timeouts.operation(INSTALL_TIMEOUT_MS))
+                .await
+            {
+                Ok(_) => (StepStatus::Changed, Some("installed".to_string())),
+                Err(e) => (StepStatus::Failed, Some(e)),
+            }
+        };
+        for package in packages {
+            if missing.contains(package) {
+                self.record(StepKind::Package, package, status, detail.clone());
+            } else {
+                self.record(StepKind::Package, package, StepStatus::Ok, None);
+            }
+        }
+    }
+
+    async fn file(&mut self, file: &RecipeFile, config_dir: &Path) {
+        let dest = normalize_remote_path(&file.dest, self.conn.remote_path());
+        if self.skip_after_failure(StepKind::File, &dest) {
+            return;
+        }
+        let (status, detail) = self.sync_file(file, &dest, config_dir).await;
+        self.record(StepKind::File, &dest, status, detail);
+    }
+
+    async fn sync_file(
+        &self,
+        file: &RecipeFile,
+        dest: &str,
+        config_dir: &Path,
+    ) -> (StepStatus, Option<String>) {
+        let local = file.local_path(config_dir);
+        let content = match tokio::fs::read(&local).await {
+            Ok(c) => c,
+            Err(e) => {
+                return (
+                    StepStatus::Failed,
+                    Some(format!("cannot read {}: {e}", local.display())),
+                );
+            }
+        };
+        let local_hash = hex::encode(Sha256::digest(&content));
+
+        let escaped = shell_escape_remote_path(dest);
+        let probe = self.privileged(&format!(
+            "if [ -f {escaped} ]; then \
+               (sha256sum -- {escaped} 2>/dev/null || shasum -a 256 -- {escaped}) | cut -d' ' -f1; \
+               stat -c %a {escaped} 2>/dev/null || stat -f %Lp {escaped}; \
+             fi"
+        ));
+        let state = match self.exec(&probe, Some(CHECK_TIMEOUT_MS)).await {
+            Ok(out) => out,
+            Err(e) => return (StepStatus::Failed, Some(e)),
+        };
+        let mut lines = state.lines().map(str::trim);
+        let remote_hash = lines.next().filter(|h| !h.is_empty());
+        let remote_mode = lines.next().and_then(|m| u32::from_str_radix(m, 8).ok());
+        let wanted_mode = file
+            .mode
+            .as_deref()
+            .and_then(|m| u32::from_str_radix(m, 8).ok());
+
+        let content_ok = remote_hash == Some(local_hash.as_str());
+        let mode_ok = wanted_mode.is_none() || wanted_mode == remote_mode;
+        if content_ok && mode_ok {
+            return (StepStatus::Ok, None);
+        }
+
+        let mut changes = Vec::new();
+        if !content_ok {
+            changes.push(if remote_hash.is_none() {
+                "created"
+            } else {
+                "content updated"
+            });
+        }
+        if wanted_mode.is_some() && (!mode_ok || !content_ok) {
+            changes.push("mode set");
+        }
+        let detail = Some(changes.join(", "));
+        if self.dry_run {
+            return (StepStatus::WouldChange, detail);
+        }
+
+        if !content_ok {
+            let mkdir = dest
+                .rsplit_once('/')
+                .filter(|(parent, _)| !parent.is_empty())
+                .map(|(parent, _)| format!("mkdir -p {} && ", shell_escape_remote_path(parent)))
+                .unwrap_or_default();
+            let write = self.privileged(&format!("{mkdir}cat > {escaped}"));
+            match self
+                .conn
+                .exec_raw(&write, Some(&content), Some(CHECK_TIMEOUT_MS))
+                .await
+            {
+                Ok(r) if r.exit_code == 0 => {}
+                Ok(r) => {
+                    let stdout = String::from_utf8_lossy(&r.stdout);
+                    return (
+                        StepStatus::Failed,
+                        Some(failure_detail(r.exit_code, &stdout, &r.stderr)),
+                    );
+                }
+                Err(e) => return (StepStatus::Failed, Some(e.to_string())),
+            }
+        }
+        if let Some(mode) = &file.mode {
+            let chmod = self.privileged(&format!("chmod {mode} {escaped}"));
+            if let Err(e) = self.exec(&chmod, Some(CHECK_TIMEOUT_MS)).await {
+                return (StepStatus::Failed, Some(e));
+            }
+        }
+        (StepStatus::Changed, detail)
+    }
+
+    async fn command(&mut self, command: &RecipeCommand) {
+        if self.skip_after_failure(StepKind::Command, &command.run) {
+            return;
+        }
+        let timeout = match command.timeout_ms(self.timeouts) {
+            Ok(timeout) => timeout,
+            Err(e) => {
+                self.record(StepKind::Command, &command.run, StepStatus::Failed, Some(e));
+                return;
+            }
+        };
+        if let Some(unless) = &command.unless {
+            match self.conn.exec(&self.privileged(unless), timeout).await {
+                Ok(r) if r.exit_code == 0 => {
+                    self.record(
+                        StepKind::Command,
+                        &command.run,
+                        StepStatus::Ok,
+                        Some("'unless' check passed".to_string()),
+                    );
+                    return;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    self.record(
+                        StepKind::Command,
+                        &command.run,
+                        StepStatus::Failed,
+                        Some(e.to_string()),
+                    );
+                    return;
+                }
+            }
+        }
+        if self.dry_run {
+            self.record(
+                StepKind::Command,
+                &command.run,
+                StepStatus::WouldChange,
+                None,
+            );
+            return;
+        }
+        let (status, detail) = match self.exec(&self.privileged(&command.run), timeout).await {
+            Ok(_) => (StepStatus::Changed, None),
+            Err(e) => (StepStatus::Failed, Some(e)),
+        };
+        self.record(StepKind::Command, &command.run, status, detail);
+    }
+
+    async fn service(&mut self, service: &str) {
+        if self.skip_after_failure(StepKind::Service, service) {
+            return;
+        }
+        let (status, detail) = self.ensure_service(service).await;
+        self.record(StepKind::Service, service, status, detail);
+    }
+
+    async fn ensure_service(&self, service: &str) -> (StepStatus, Option<String>) {
+        let unit = shell_escape(service);
+        let probe = format!(
+            "command -v systemctl >/dev/null 2>&1 || {{ echo no-systemd; exit 0; }}; \
+             echo \"$(systemctl is-enabled {unit} 2>/dev/null) $(systemctl is-active {unit} 2>/dev/null)\""
+        );
+        let state = match self.exec(&probe, Some(CHECK_TIMEOUT_MS)).await {
+            Ok(out) => out.trim().to_string(),
+            Err(e) => return (StepStatus::Failed, Some(e)),
+        };
+        if state == "no-systemd" {
+            return (
+                StepStatus::Failed,
+                Some("systemctl not found on the remote".to_string()),
+            );
+        }
+        let (enabled, active) = state.split_once(' ').unwrap_or((&state, ""));
+        let enabled_ok = matches!(
+            enabled,
+            "enabled" | "enabled-runtime" | "static" | "alias" | "indirect" | "generated"
+        );
+        let active_ok = active == "active";
+        if enabled_ok && active_ok {
+            return (StepStatus::Ok, None);
+        }
+
+        let (action, detail) = if enabled_ok {
+            (format!("systemctl start {unit}"), "started")
+        } else {
+            (
+                format!("systemctl enable --now {unit}"),
+                "enabled and started",
+            )
+        };
+        if self.dry_run {
+            return (StepStatus::WouldChange, Some(detail.to_string()));
+        }
+        match self
+            .exec(&self.privileged(&action), Some(CHECK_TIMEOUT_MS))
+            .await
+        {
+            Ok(_) => (StepStatus::Changed, Some(detail.to_string())),
+            Err(e) => (StepStatus::Failed, Some(e)),
+        }
+    }
+}
+
+/// Exit code plus the last few lines of stderr (or stdout when stderr is empty).
+#[must_use]
+pub fn failure_detail(exit_code: i32, stdout: &str, stderr: &str) -> String {
+    let output = if stderr.trim().is_empty() {
+        stdout
+    } else {
+        stderr
+    };
+    let lines: Vec<&str> = output.lines().filter(|l| !l.trim().is_empty()).collect();
+    let tail = lines[lines.len().saturating_sub(DETAIL_LINES)..].join("\n");
+    if tail.is_empty() {
+        format!("exit {exit_code}")
+    } else {
+        format!("exit {exit_code}: {tail}")
+    }
+}
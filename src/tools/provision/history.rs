@@ -0,0 +1,96 @@
+//! Record of which recipes were applied to which servers, so `check_drift`
+//! knows what to re-check. Persisted as JSON in the user data dir rather than
+//! `servers.toml`, which the MCP server never writes.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// A successful, non-dry-run application of a recipe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppliedRecipe {
+    /// Unix timestamp (seconds) of the last successful apply.
+    pub applied_at: u64,
+}
+
+/// Server name → recipe name → last successful apply.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvisionHistory {
+    #[serde(default)]
+    pub servers: BTreeMap<String, BTreeMap<String, AppliedRecipe>>,
+}
+
+impl ProvisionHistory {
+    pub fn record(&mut self, server: &str, recipe: &str, now: u64) {
+        self.servers
+            .entry(server.to_string())
+            .or_default()
+            .insert(recipe.to_string(), AppliedRecipe { applied_at: now });
+    }
+
+    /// Drop the record of `server`. Returns whether there was one.
+    pub fn forget(&mut self, server: &str) -> bool {
+        self.servers.remove(server).is_some()
+    }
+
+    /// Recipes applied to `server`, by name.
+    #[must_use]
+    pub fn applied(&self, server: &str) -> BTreeMap<String, AppliedRecipe> {
+        self.servers.get(server).cloned().unwrap_or_default()
+    }
+
+    fn path() -> Option<PathBuf> {
+        Some(dirs::data_dir()?.join("ssh-hub").join("provisioned.json"))
+    }
+
+    #[must_use]
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        let Some(path) = Self::path() else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                tracing::debug!("Cannot create provision history dir: {e}");
+                return;
+            }
+        }
+        match serde_json::to_string_pretty(self) {
+            Ok(json) => {
+                if let Err(e) = std::fs::write(&path, json) {
+                    tracing::debug!("Cannot write provision history: {e}");
+                }
+            }
+            Err(e) => tracing::debug!("Cannot serialize provision history: {e}"),
+        }
+    }
+}
+
+/// Record that `recipe` was applied to `server` just now.
+pub fn record_applied(server: &str, recipe: &str) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let mut history = ProvisionHistory::load();
+    history.record(server, recipe, now);
+    history.save();
+}
+
+/// Drop what was recorded about `server`. Returns whether there was anything.
+#[must_use]
+pub fn forget_server(server: &str) -> bool {
+    let mut history = ProvisionHistory::load();
+    let forgotten = history.forget(server);
+    if forgotten {
+        history.save();
+    }
+    forgotten
+}
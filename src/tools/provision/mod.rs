@@ -0,0 +1,8 @@
+pub mod handler;
+pub mod history;
+pub mod package;
+pub mod recipe;
+pub mod schema;
+
+pub use recipe::*;
+pub use schema::*;
@@ -0,0 +1,100 @@
+//! Package managers the remote may have, for installing packages.
+
+/// Prints the first package manager found on the remote.
+pub const DETECT_PACKAGE_MANAGER: &str = "for m in apt-get dnf yum zypper apk pacman brew; do \
+     command -v $m >/dev/null 2>&1 && { echo $m; break; }; done; true";
+
+/// Supported package managers, by the command that installs packages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Apt,
+    Dnf,
+    Yum,
+    Zypper,
+    Apk,
+    Pacman,
+    Brew,
+}
+
+impl PackageManager {
+    /// The package manager's command, as detected on the remote.
+    #[must_use]
+    pub fn command(self) -> &'static str {
+        match self {
+            Self::Apt => "apt-get",
+            Self::Dnf => "dnf",
+            Self::Yum => "yum",
+            Self::Zypper => "zypper",
+            Self::Apk => "apk",
+            Self::Pacman => "pacman",
+            Self::Brew => "brew",
+        }
+    }
+
+    /// The package that provides `command`, for the common commands whose
+    /// package is named differently. Other commands are their own package.
+    #[must_use]
+    pub fn package_for(self, command: &str) -> String {
+        let package = match (command, self) {
+            ("rg", _) => "ripgrep",
+            ("ag", Self::Apt) => "silversearcher-ag",
+            ("ag", _) => "the_silver_searcher",
+            ("python3", Self::Pacman | Self::Brew) | ("pip3", Self::Brew) => "python",
+            ("pip3", Self::Apk) => "py3-pip",
+            ("pip3", Self::Pacman) => "python-pip",
+            ("pip3", _) => "python3-pip",
+            ("node", Self::Brew) => "node",
+            ("node", _) => "nodejs",
+            ("dig", Self::Apt) => "dnsutils",
+            ("dig", Self::Dnf | Self::Yum | Self::Zypper) => "bind-utils",
+            ("dig", Self::Apk) => "bind-tools",
+            ("dig", Self::Pacman | Self::Brew) => "bind",
+            ("http", _) => "httpie",
+            _ => command,
+        };
+        package.to_string()
+    }
+
+    #[must_use]
+    pub fn from_command(command: &str) -> Option<Self> {
+        Some(match command {
+            "apt-get" => Self::Apt,
+            "dnf" => Self::Dnf,
+            "yum" => Self::Yum,
+            "zypper" => Self::Zypper,
+            "apk" => Self::Apk,
+            "pacman" => Self::Pacman,
+            "brew" => Self::Brew,
+            _ => return None,
+        })
+    }
+
+    /// Shell test that succeeds when the package named by `$p` is installed.
+    #[must_use]
+    pub fn installed_check(self) -> &'static str {
+        match self {
+            Self::Apt => {
+                "dpkg-query -W -f='${Status}' \"$p\" 2>/dev/null | grep -q 'install ok installed'"
+            }
+            Self::Dnf | Self::Yum | Self::Zypper => "rpm -q \"$p\" >/dev/null 2>&1",
+            Self::Apk => "apk info -e \"$p\" >/dev/null 2>&1",
+            Self::Pacman => "pacman -Q \"$p\" >/dev/null 2>&1",
+            Self::Brew => "brew list \"$p\" >/dev/null 2>&1",
+        }
+    }
+
+    #[must_use]
+    pub fn install_command(self, packages: &str) -> String {
+        match self {
+            Self::Apt => {
+                format!("env DEBIAN_FRONTEND=noninteractive apt-get install -y {packages}")
+            }
+            Self::Dnf => format!("dnf install -y {packages}"),
+            Self::Yum => format!("yum install -y {packages}"),
+            Self::Zypper => format!("zypper --non-interactive install {packages}"),
+            Self::Apk => format!("apk add {packages}"),
+            Self::Pacman => format!("pacman -S --noconfirm --needed {packages}"),
+            Self::Brew => format!("brew install {packages}"),
+        }
+    }
+}
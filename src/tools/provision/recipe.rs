@@ -0,0 +1,147 @@
+//! Declarative provisioning recipes.
+//!
+//! Recipes are declared in `servers.toml` next to command templates:
+//!
+//! ```toml
+//! [recipes.web]
+//! description = "nginx with the app's site config"
+//! sudo = true
+//! packages = ["nginx", "curl"]
+//! services = ["nginx"]
+//!
+//! [[recipes.web.files]]
+//! src = "~/deploy/nginx.conf"
+//! dest = "/etc/nginx/sites-enabled/app.conf"
+//! mode = "644"
+//!
+//! [[recipes.web.commands]]
+//! run = "nginx -t && systemctl reload nginx"
+//! unless = "test -f /var/lib/app/.configured"
+//! ```
+//!
+//! Steps are applied in a fixed order: packages, files, commands, services.
+//! Every step checks the current state first and only changes what differs,
+//! so applying a recipe twice makes no changes the second time.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::timeout::TimeoutPolicy;
+use crate::utils::validate::{is_valid_mode, is_valid_name};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Recipe {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// Run package installs, file writes, commands, and service changes
+    /// through `sudo -n` (never prompts; fails if a password is needed).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sudo: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<RecipeFile>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<RecipeCommand>,
+    /// systemd units to enable and start.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub services: Vec<String>,
+    /// Servers this recipe may be applied to. Empty means all configured servers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub servers: Vec<String>,
+}
+
+/// A local file to place on the remote.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RecipeFile {
+    /// Local path. `~/` expands to the home directory; relative paths resolve
+    /// against the directory holding `servers.toml`.
+    pub src: String,
+    /// Remote path, relative to the server's base path unless absolute.
+    pub dest: String,
+    /// Octal permissions, e.g. `644`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+}
+
+/// A shell command, optionally guarded by a check.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RecipeCommand {
+    pub run: String,
+    /// Skip `run` when this command exits 0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unless: Option<String>,
+    /// Timeout in milliseconds (capped at 10 minutes). `0` means none, on
+    /// servers with `no_timeout`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+impl Recipe {
+    /// Check names and modes before anything runs on the remote.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        for package in &self.packages {
+            if !is_valid_name(package) {
+                return Err(format!("invalid package name '{package}'"));
+            }
+        }
+        for service in &self.services {
+            if !is_valid_name(service) {
+                return Err(format!("invalid service name '{service}'"));
+            }
+        }
+        for file in &self.files {
+            if file.src.is_empty() || file.dest.is_empty() {
+                return Err("files need both 'src' and 'dest'".to_string());
+            }
+            if let Some(mode) = &file.mode {
+                if !is_valid_mode(mode) {
+                    return Err(format!(
+                        "invalid mode '{mode}' for '{}': expected 3 or 4 octal digits",
+                        file.dest
+                    ));
+                }
+            }
+        }
+        if self.commands.iter().any(|c| c.run.trim().is_empty()) {
+            return Err("commands need a non-empty 'run'".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether this recipe may be applied to `server`.
+    #[must_use]
+    pub fn allows_server(&self, server: &str) -> bool {
+        self.servers.is_empty() || self.servers.iter().any(|s| s == server)
+    }
+}
+
+impl RecipeFile {
+    /// Local path of `src`, resolved against `config_dir`.
+    #[must_use]
+    pub fn local_path(&self, config_dir: &Path) -> PathBuf {
+        if let Some(rest) = self.src.strip_prefix("~/") {
+            if let Some(home) = dirs::home_dir() {
+                return home.join(rest);
+            }
+        }
+        config_dir.join(&self.src)
+    }
+}
+
+impl RecipeCommand {
+    /// Effective timeout for this command on a server with `timeouts`, or
+    /// `None` for no timeout.
+    ///
+    /// # Errors
+    ///
+    /// Returns a user-facing message when [`TimeoutPolicy::resolve`] does.
+    pub fn timeout_ms(&self, timeouts: TimeoutPolicy) -> Result<Option<u64>, String> {
+        timeouts.resolve(self.timeout, false)
+    }
+}
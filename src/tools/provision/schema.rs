@@ -0,0 +1,89 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ProvisionInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+
+    #[schemars(description = "Name of a recipe declared under [recipes] in servers.toml")]
+    pub recipe: String,
+
+    #[schemars(
+        description = "Check every step and report what would change without changing anything. Defaults to false"
+    )]
+    pub dry_run: Option<bool>,
+
+    #[schemars(
+        description = "Reason for making this change outside the server's maintenance window. Only needed after a call was refused for that reason; the reason is logged"
+    )]
+    pub override_window: Option<String>,
+}
+
+/// Result of applying (or dry-running) a recipe.
+#[derive(Debug, Serialize)]
+pub struct ProvisionReport {
+    pub recipe: String,
+    pub server: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+    pub steps: Vec<StepReport>,
+    /// Steps that changed (or would change, in a dry run).
+    pub changed: usize,
+    pub failed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepReport {
+    pub kind: StepKind,
+    /// Package, remote path, command, or unit name.
+    pub target: String,
+    pub status: StepStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepKind {
+    Package,
+    File,
+    Command,
+    Service,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    /// Already in the desired state.
+    Ok,
+    Changed,
+    /// Dry run: the step would change something.
+    WouldChange,
+    Failed,
+    /// Not attempted because an earlier step failed.
+    Skipped,
+}
+
+impl std::fmt::Display for StepKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Package => "package",
+            Self::File => "file",
+            Self::Command => "command",
+            Self::Service => "service",
+        })
+    }
+}
+
+impl std::fmt::Display for StepStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Ok => "ok",
+            Self::Changed => "changed",
+            Self::WouldChange => "would change",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+        })
+    }
+}
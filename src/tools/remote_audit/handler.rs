@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use super::schema::{
+    ListeningSocket, LoginRecord, PendingUpdates, RemoteAuditInput, RemoteAuditOutput, SshdSettings,
+};
+use crate::connection::RemoteExecutor;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::{remote_error, tool_error, ErrorCode};
+
+/// Default number of recent logins returned.
+const DEFAULT_LOGINS: usize = 10;
+
+/// Upper bound on `logins`.
+const MAX_LOGINS: usize = 100;
+
+/// Timeout for the audit command (60 seconds — package simulations are slow),
+/// unless the server's [`TimeoutPolicy`] raises it.
+const AUDIT_TIMEOUT_MS: u64 = 60_000;
+
+/// Prefix of the lines separating sections in the audit command's output.
+const SECTION_MARKER: &str = "@@ssh-hub-audit:";
+
+/// sshd settings reported in the audit; everything else is dropped.
+const SSHD_KEYS: &[&str] = &[
+    "port",
+    "listenaddress",
+    "permitrootlogin",
+    "passwordauthentication",
+    "permitemptypasswords",
+    "pubkeyauthentication",
+    "kbdinteractiveauthentication",
+    "challengeresponseauthentication",
+    "maxauthtries",
+    "x11forwarding",
+    "allowtcpforwarding",
+    "allowusers",
+    "allowgroups",
+];
+
+/// Collect pending updates, listening sockets, sshd settings, and recent
+/// logins in a single remote command.
+///
+/// Everything runs unprivileged: `sshd -T` falls back to reading the config
+/// files, and update counts come from the package manager's local index.
+pub async fn handle<C: RemoteExecutor>(
+    conn: Arc<C>,
+    input: RemoteAuditInput,
+    timeouts: TimeoutPolicy,
+) -> String {
+    if let Some(error) = conn.posix_required("remote_audit") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    let logins = input.logins.unwrap_or(DEFAULT_LOGINS).clamp(1, MAX_LOGINS);
+
+    match conn
+        .exec(&audit_command(logins), timeouts.operation(AUDIT_TIMEOUT_MS))
+        .await
+    {
+        Ok(result) => {
+            let output = parse_audit(&result.stdout);
+            serde_json::to_string_pretty(&output)
+                .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+        }
+        Err(e) => remote_error(&e, format!("Error running audit: {e}")),
+    }
+}
+
+/// Shell script printing each section after a [`SECTION_MARKER`] line.
+#[must_use]
+pub fn audit_command(logins: usize) -> String {
+    format!(
+        "echo '{SECTION_MARKER}updates'; \
+         if command -v apt-get >/dev/null 2>&1; then echo pkg=apt; \
+           apt-get -s -o Debug::NoLocking=1 upgrade 2>/dev/null | grep '^Inst ' \
+             | awk '{{n++}} /[Ss]ecurity/{{s++}} END{{print \"pending=\" n+0; print \"security=\" s+0}}'; \
+         elif command -v dnf >/dev/null 2>&1; then echo pkg=dnf; \
+           echo \"pending=$(dnf -q check-update 2>/dev/null | awk 'NF==3 && $1 ~ /\\./' | wc -l)\"; \
+           echo \"security=$(dnf -q updateinfo list --security 2>/dev/null | wc -l)\"; \
+         elif command -v yum >/dev/null 2>&1; then echo pkg=yum; \
+           echo \"pending=$(yum -q check-update 2>/dev/null | awk 'NF==3 && $1 ~ /\\./' | wc -l)\"; \
+         elif command -v apk >/dev/null 2>&1; then echo pkg=apk; \
+           echo \"pending=$(apk -u list 2>/dev/null | wc -l)\"; \
+         fi; \
+         echo '{SECTION_MARKER}listening'; \
+         if command -v ss >/dev/null 2>&1; then echo tool=ss; ss -Htuln 2>/dev/null; \
+         elif command -v netstat >/dev/null 2>&1; then echo tool=netstat; netstat -an 2>/dev/null; \
+         fi; \
+         echo '{SECTION_MARKER}sshd'; \
+         if o=$(sshd -T 2>/dev/null || /usr/sbin/sshd -T 2>/dev/null); then \
+           echo 'source=sshd -T'; echo \"$o\"; \
+         elif [ -r /etc/ssh/sshd_config ]; then \
+           echo source=/etc/ssh/sshd_config; \
+           cat /etc/ssh/sshd_config.d/*.conf /etc/ssh/sshd_config 2>/dev/null; \
+         fi; \
+         echo '{SECTION_MARKER}logins'; \
+         last -n {logins} -w -i 2>/dev/null || last -n {logins} 2>/dev/null; \
+         true"
+    )
+}
+
+/// Parse the output of [`audit_command`].
+#[must_use]
+pub fn parse_audit(stdout: &str) -> RemoteAuditOutput {
+    let mut sections: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    let mut current = None;
+    for line in stdout.lines() {
+        if let Some(name) = line.strip_prefix(SECTION_MARKER) {
+            current = Some(name.trim());
+        } else if let Some(name) = current {
+            sections.entry(name).or_default().push(line);
+        }
+    }
+    let section = |name: &str| sections.get(name).map_or(&[][..], Vec::as_slice);
+
+    let mut output = RemoteAuditOutput {
+        updates: parse_updates(section("updates")),
+        listening: parse_listening(section("listening")),
+        sshd: parse_sshd(section("sshd")),
+        last_logins: parse_logins(section("logins")),
+        warnings: Vec::new(),
+    };
+    output.warnings = warnings(&output);
+    output
+}
+
+fn parse_updates(lines: &[&str]) -> Option<PendingUpdates> {
+    let mut package_manager = None;
+    let mut pending = 0;
+    let mut security = None;
+    for line in lines {
+        match line.trim().split_once('=') {
+            Some(("pkg", v)) => package_manager = Some(v.to_string()),
+            Some(("pending", v)) => pending = v.trim().parse().unwrap_or(0),
+            Some(("security", v)) => security = v.trim().parse().ok(),
+            _ => {}
+        }
+    }
+    Some(PendingUpdates {
+        package_manager: package_manager?,
+        pending,
+        security,
+    })
+}
+
+/// Parse `ss -Htuln` or `netstat -an` output into listening sockets.
+#[must_use]
+pub fn parse_listening(lines: &[&str]) -> Vec<ListeningSocket> {
+    let mut lines = lines.iter();
+    let netstat = match lines.next().map(|l| l.trim()) {
+        Some("tool=ss") => false,
+        Some("tool=netstat") => true,
+        _ => return Vec::new(),
+    };
+
+    let mut sockets: Vec<ListeningSocket> = lines
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let proto = fields.first()?.to_ascii_lowercase();
+            let protocol = if proto.starts_with("tcp") {
+                "tcp"
+            } else if proto.starts_with("udp") {
+                "udp"
+            } else {
+                return None;
+            };
+            let local = if netstat {
+                // netstat -an also lists established connections.
+                if protocol == "tcp" && !fields.last()?.eq_ignore_ascii_case("listen") {
+                    return None;
+                }
+                fields.get(3)?
+            } else {
+                fields.get(4)?
+            };
+            let (address, port) = split_host_port(local)?;
+            Some(ListeningSocket {
+                port,
+                protocol: protocol.to_string(),
+                address,
+            })
+        })
+        .collect();
+    sockets.sort();
+    sockets.dedup();
+    sockets
+}
+
+/// Split `addr:port`, `[v6]:port`, `addr%iface:port`, or BSD-style `addr.port`.
+fn split_host_port(local: &str) -> Option<(String, u16)> {
+    let (host, port) = local.rsplit_once(':').or_else(|| local.rsplit_once('.'))?;
+    let port = port.parse().ok()?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let host = host.split('%').next().unwrap_or(host);
+    let host = if host.is_empty() { "*" } else { host };
+    Some((host.to_string(), port))
+}
+
+/// Parse `sshd -T` output or raw config files. The first value of a setting
+/// wins, matching sshd; `Match` blocks are ignored.
+#[must_use]
+pub fn parse_sshd(lines: &[&str]) -> Option<SshdSettings> {
+    let mut lines = lines.iter();
+    let source = lines.next()?.trim().strip_prefix("source=")?.to_string();
+    let mut settings = BTreeMap::new();
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or((line, ""));
+        let key = key.to_ascii_lowercase();
+        if key == "match" {
+            break;
+        }
+        if SSHD_KEYS.contains(&key.as_str()) {
+            settings.entry(key).or_insert_with(|| {
+                value
+                    .trim_start_matches([' ', '\t', '='])
+                    .trim()
+                    .to_string()
+            });
+        }
+    }
+    Some(SshdSettings { source, settings })
+}
+
+/// Parse `last -w -i` output. Reboot records and the trailing `wtmp begins`
+/// line are skipped; a `0.0.0.0` source (local login) is reported as absent.
+#[must_use]
+pub fn parse_logins(lines: &[&str]) -> Vec<LoginRecord> {
+    lines
+        .iter()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let user = fields.next()?;
+            if matches!(user, "reboot" | "shutdown" | "wtmp" | "btmp" | "utx") {
+                return None;
+            }
+            let tty = fields.next()?;
+            let rest: Vec<&str> = fields.collect();
+            let (from, when) = match rest.first().map(|f| f.parse::<IpAddr>()) {
+                Some(Ok(ip)) if ip.is_unspecified() => (None, &rest[1..]),
+                Some(Ok(ip)) => (Some(ip.to_string()), &rest[1..]),
+                _ => (None, &rest[..]),
+            };
+            if when.is_empty() {
+                return None;
+            }
+            Some(LoginRecord {
+                user: user.to_string(),
+                tty: tty.to_string(),
+                from,
+                when: when.join(" "),
+            })
+        })
+        .collect()
+}
+
+fn warnings(output: &RemoteAuditOutput) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if let Some(security) = output.updates.as_ref().and_then(|u| u.security) {
+        if security > 0 {
+            warnings.push(format!("{security} pending security updates"));
+        }
+    }
+    if let Some(sshd) = &output.sshd {
+        let get = |key: &str| sshd.settings.get(key).map(String::as_str);
+        match get("passwordauthentication") {
+            Some("yes") => warnings.push("sshd: PasswordAuthentication is enabled".to_string()),
+            None => warnings
+                .push("sshd: PasswordAuthentication is not set (sshd defaults to yes)".to_string()),
+            Some(_) => {}
+        }
+        if get("permitrootlogin") == Some("yes") {
+            warnings.push("sshd: PermitRootLogin allows root password login".to_string());
+        }
+        if get("permitemptypasswords") == Some("yes") {
+            warnings.push("sshd: PermitEmptyPasswords is enabled".to_string());
+        }
+    }
+    warnings
+}
@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RemoteAuditInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+
+    #[schemars(description = "Number of recent logins to include. Defaults to 10, max 100")]
+    pub logins: Option<usize>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RemoteAuditOutput {
+    /// Pending package updates. Absent when no supported package manager was found.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updates: Option<PendingUpdates>,
+    /// Listening TCP sockets and bound UDP sockets, sorted by port.
+    pub listening: Vec<ListeningSocket>,
+    /// Security-relevant sshd settings. Absent when neither `sshd -T` nor the
+    /// config file could be read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sshd: Option<SshdSettings>,
+    /// Most recent logins, newest first.
+    pub last_logins: Vec<LoginRecord>,
+    /// Findings worth a closer look, derived from the sections above.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct PendingUpdates {
+    pub package_manager: String,
+    pub pending: u64,
+    /// Security updates. Absent when the package manager can't tell them apart.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub security: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct ListeningSocket {
+    pub port: u16,
+    pub protocol: String,
+    pub address: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct SshdSettings {
+    /// `sshd -T` (effective config, needs root) or the config file path.
+    pub source: String,
+    /// Lowercased setting name → value. Settings missing from a config file
+    /// use sshd's built-in defaults.
+    pub settings: BTreeMap<String, String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct LoginRecord {
+    pub user: String,
+    pub tty: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    /// Login time and session duration as printed by `last`.
+    pub when: String,
+}
@@ -1,40 +1,80 @@
-use std::fmt::Write;
-use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rmcp::model::ProgressNotificationParam;
+use rmcp::service::RequestContext;
+use rmcp::RoleServer;
+use tokio::sync::Mutex;
 
 use super::schema::{RemoteBashBackgroundOutput, RemoteBashInput, RemoteBashOutput};
-use crate::connection::SshConnection;
+use crate::connection::{
+    compress_command, decode_compressed, decode_compressed_partial, ExecResult, RemoteExecutor,
+};
+use crate::keychain;
+use crate::server_registry::CommandShell;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::{remote_error, tool_error, ErrorCode};
+use crate::utils::command::CommandBuilder;
+use crate::utils::output::fit_output;
 use crate::utils::path::shell_escape;
 
-/// Default timeout for bash commands (2 minutes).
-const DEFAULT_TIMEOUT_MS: u64 = 120_000;
+/// Time between keepalive progress notifications for `long_running` commands.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
 
-/// Maximum allowed timeout for bash commands (10 minutes).
-const MAX_TIMEOUT_MS: u64 = 600_000;
+/// Timeout for the background wrapper command itself (get PID back).
+const BACKGROUND_TIMEOUT_MS: u64 = 10_000;
 
-/// Stdout larger than this is saved to disk instead of returned inline.
-/// 128 KB keeps the LLM context window manageable while still showing
-/// a meaningful amount of output for most commands.
-const MAX_INLINE_OUTPUT: usize = 128 * 1024;
+/// Background jobs write their output to `<prefix><job_id>.log`.
+pub const BACKGROUND_LOG_PREFIX: &str = "/tmp/ssh-hub-bg-";
 
-/// Number of lines from the start included in the disk-save summary.
-const SUMMARY_HEAD_LINES: usize = 150;
+/// Stdout above this size makes the next run of the same command compressed,
+/// unless the call sets `compress`.
+pub const COMPRESS_THRESHOLD: usize = 1024 * 1024;
 
-/// Number of lines from the end included in the disk-save summary.
-const SUMMARY_TAIL_LINES: usize = 50;
+/// Commands whose output size is remembered before the store starts over.
+const MAX_TRACKED_COMMANDS: usize = 1024;
 
-/// Timeout for the background wrapper command itself (get PID back).
-const BACKGROUND_TIMEOUT_MS: u64 = 10_000;
+/// Stdout size of the last run of each command, per server, to decide
+/// whether the next run should be compressed.
+#[derive(Default)]
+pub struct OutputSizes {
+    sizes: Mutex<HashMap<(String, String), usize>>,
+}
 
-/// SI kilobyte (1000 bytes), used for human-readable size display.
-const BYTES_PER_KB: usize = 1_000;
-/// SI megabyte (1,000,000 bytes), used for human-readable size display.
-const BYTES_PER_MB: usize = 1_000_000;
-/// Divisor to extract tenths-of-megabytes for human-readable size formatting.
-const TENTHS_MB_DIVISOR: usize = 100_000;
+impl OutputSizes {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-/// Pre-allocated capacity for the output summary string (32 KB).
-const SUMMARY_BUFFER_CAPACITY: usize = 32 * 1024;
+    /// Whether to compress `command` on `server`: as `requested`, or when
+    /// its last run printed more than [`COMPRESS_THRESHOLD`].
+    pub async fn should_compress(
+        &self,
+        server: &str,
+        command: &str,
+        requested: Option<bool>,
+    ) -> bool {
+        if let Some(requested) = requested {
+            return requested;
+        }
+        self.sizes
+            .lock()
+            .await
+            .get(&(server.to_string(), command.to_string()))
+            .is_some_and(|&bytes| bytes > COMPRESS_THRESHOLD)
+    }
+
+    pub async fn record(&self, server: &str, command: &str, bytes: usize) {
+        let mut sizes = self.sizes.lock().await;
+        if sizes.len() >= MAX_TRACKED_COMMANDS {
+            sizes.clear();
+        }
+        sizes.insert((server.to_string(), command.to_string()), bytes);
+    }
+}
 
 /// Execute a bash command on the remote server.
 ///
@@ -42,31 +82,81 @@ const SUMMARY_BUFFER_CAPACITY: usize = 32 * 1024;
 /// Rejects commands that attempt shell-level backgrounding without using the
 /// `run_in_background` flag, since those break the SSH channel.
 ///
+/// `timeouts` are the server's foreground timeout settings, and
+/// `default_shell` the interpreter used when the call doesn't pick one.
+/// `sizes` remembers output sizes so large output gets compressed.
+///
 /// Returns a JSON-serialized [`RemoteBashOutput`] or [`RemoteBashBackgroundOutput`],
 /// or a plain-text error message if the command fails to launch.
-pub async fn handle(conn: Arc<SshConnection>, input: RemoteBashInput) -> String {
+pub async fn handle<C: RemoteExecutor>(
+    conn: Arc<C>,
+    mut input: RemoteBashInput,
+    timeouts: TimeoutPolicy,
+    default_shell: CommandShell,
+    sizes: Arc<OutputSizes>,
+    ctx: RequestContext<RoleServer>,
+) -> String {
     let run_in_background = input.run_in_background.unwrap_or(false);
 
     if !run_in_background {
         if let Some(reason) = detect_background_pattern(&input.command) {
-            return format!(
-                "Error: command appears to use shell-level backgrounding ({reason}). \
-                 This will hang the SSH channel. Use the `run_in_background` parameter \
-                 instead and pass the raw command without nohup/setsid/& wrappers."
+            return tool_error(
+                ErrorCode::InvalidInput,
+                format!(
+                    "command appears to use shell-level backgrounding ({reason}). \
+                     This will hang the SSH channel. Use the `run_in_background` parameter \
+                     instead and pass the raw command without nohup/setsid/& wrappers."
+                ),
             );
         }
     }
 
+    if input.sudo.unwrap_or(false) {
+        if run_in_background {
+            return tool_error(
+                ErrorCode::InvalidInput,
+                "sudo cannot be combined with run_in_background",
+            );
+        }
+        if let Some(error) = conn.posix_required("sudo") {
+            return tool_error(ErrorCode::NotSupported, error);
+        }
+    }
+
+    // Keyed on the command as given, before any shell wrapping
+    let command = input.command.clone();
+    let shell = input.shell.unwrap_or(default_shell);
+    if shell != CommandShell::Login {
+        if let Some(error) = conn.posix_required("shell") {
+            return tool_error(ErrorCode::NotSupported, error);
+        }
+        input.command = shell.wrap(&input.command);
+    }
+
     if run_in_background {
         handle_background(conn, input).await
     } else {
-        handle_foreground(conn, input).await
+        let compress = !input.sudo.unwrap_or(false)
+            && conn.posix_required("compress").is_none()
+            && sizes
+                .should_compress(&input.server, &command, input.compress)
+                .await;
+        let server = input.server.clone();
+        let (output, stdout_bytes) = handle_foreground(conn, input, timeouts, compress, ctx).await;
+        if let Some(bytes) = stdout_bytes {
+            sizes.record(&server, &command, bytes).await;
+        }
+        output
     }
 }
 
 /// Run the command detached on the remote server and return immediately with PID and log path.
-async fn handle_background(conn: Arc<SshConnection>, input: RemoteBashInput) -> String {
-    let log_file = format!("/tmp/ssh-hub-bg-{}.log", timestamp_suffix());
+async fn handle_background<C: RemoteExecutor>(conn: Arc<C>, input: RemoteBashInput) -> String {
+    if let Some(error) = conn.posix_required("run_in_background") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    let job_id = timestamp_suffix();
+    let log_file = background_log_file(&job_id).unwrap_or_default();
 
     // Detach the background process from the SSH session so the channel
     // closes immediately after echoing the PID.
@@ -101,21 +191,28 @@ async fn handle_background(conn: Arc<SshConnection>, input: RemoteBashInput) ->
 
     let result = match conn.exec(&wrapped, Some(BACKGROUND_TIMEOUT_MS)).await {
         Ok(result) => result,
-        Err(e) => return format!("Error launching background command: {e}"),
+        Err(e) => return remote_error(&e, format!("Error launching background command: {e}")),
     };
 
     let pid = result.stdout.trim().to_string();
     if pid.is_empty() || pid.parse::<u32>().is_err() {
-        return format!(
-            "Error: background launch did not return a valid PID. Output: {}",
-            result.stdout.trim(),
+        return tool_error(
+            ErrorCode::Failed,
+            format!(
+                "background launch did not return a valid PID. Output: {}",
+                result.stdout.trim(),
+            ),
         );
     }
 
     let output = RemoteBashBackgroundOutput {
         pid,
         log_file,
-        message: "Command launched in background.".to_string(),
+        message: format!(
+            "Command launched in background. Watch it from a terminal with: ssh-hub logs {} {job_id} --follow",
+            input.server
+        ),
+        job_id,
     };
     serde_json::to_string_pretty(&output)
         .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
@@ -123,113 +220,249 @@ async fn handle_background(conn: Arc<SshConnection>, input: RemoteBashInput) ->
 
 /// Run the command in the foreground and return stdout, stderr, and exit code as JSON.
 ///
-/// Stdout larger than [`MAX_INLINE_OUTPUT`] is saved to a local temp file; the response
-/// includes a head/tail summary with the file path.
-async fn handle_foreground(conn: Arc<SshConnection>, input: RemoteBashInput) -> String {
-    let timeout = input
-        .timeout
-        .unwrap_or(DEFAULT_TIMEOUT_MS)
-        .min(MAX_TIMEOUT_MS);
-
-    match conn.exec(&input.command, Some(timeout)).await {
-        Ok(result) => {
-            let stdout = if result.stdout.len() > MAX_INLINE_OUTPUT {
-                match save_output_to_disk(&result.stdout).await {
-                    Ok(path) => build_output_summary(&result.stdout, &path),
-                    Err(e) => {
-                        tracing::warn!("Failed to save large output to disk: {}", e);
-                        truncate_inline(&result.stdout)
-                    }
-                }
-            } else {
-                result.stdout
-            };
+/// Stdout larger than [`fit_output`] allows is saved to a local temp file; the
+/// response includes a head/tail summary with the file path.
+///
+/// `long_running` calls send a progress notification every
+/// [`KEEPALIVE_INTERVAL`]. If the client cancels the call, the remote
+/// command is stopped and the output so far is returned, marked `cancelled`.
+///
+/// With `compress`, stdout is gzipped on the remote. Also returns the size
+/// of stdout when the command ran to completion.
+async fn handle_foreground<C: RemoteExecutor>(
+    conn: Arc<C>,
+    input: RemoteBashInput,
+    timeouts: TimeoutPolicy,
+    compress: bool,
+    ctx: RequestContext<RoleServer>,
+) -> (String, Option<usize>) {
+    let long_running = input.long_running.unwrap_or(false);
+    let timeout = match timeouts.resolve(input.timeout, long_running) {
+        Ok(timeout) => timeout,
+        Err(e) => return (tool_error(ErrorCode::InvalidInput, e), None),
+    };
+    let cancelled = ctx.ct.cancelled();
+
+    let executed = if input.sudo.unwrap_or(false) {
+        let password = match keychain::sudo_password(&input.server).await {
+            Ok(Some(password)) => password,
+            Ok(None) => {
+                let error = format!(
+                    "no sudo password stored for '{0}'. Store one with 'ssh-hub sudo-password {0}'",
+                    input.server
+                );
+                return (tool_error(ErrorCode::PermissionDenied, error), None);
+            }
+            Err(e) => {
+                let error = format!("Error reading the sudo password: {e}");
+                return (tool_error(ErrorCode::Failed, error), None);
+            }
+        };
+        with_keepalive(
+            &ctx,
+            long_running,
+            exec_sudo(conn.as_ref(), &input.command, &password, timeout, cancelled),
+        )
+        .await
+    } else if compress {
+        with_keepalive(
+            &ctx,
+            long_running,
+            exec_compressed(conn.as_ref(), &input.command, timeout, cancelled),
+        )
+        .await
+    } else {
+        with_keepalive(
+            &ctx,
+            long_running,
+            exec_plain(conn.as_ref(), &input.command, timeout, cancelled),
+        )
+        .await
+    };
 
+    match executed {
+        Ok(executed) => {
+            let stdout_bytes = (!executed.cancelled).then_some(executed.result.stdout.len());
             let output = RemoteBashOutput {
-                stdout,
-                stderr: result.stderr,
-                exit_code: result.exit_code,
+                stdout: fit_output(executed.result.stdout, "remote_bash").await,
+                stderr: executed.result.stderr,
+                exit_code: executed.result.exit_code,
+                compressed_bytes: executed.compressed_bytes,
+                cancelled: executed.cancelled,
             };
-            serde_json::to_string_pretty(&output)
-                .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+            let output = serde_json::to_string_pretty(&output)
+                .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#));
+            (output, stdout_bytes)
         }
-        Err(e) => format!("Error: {e}"),
+        Err(e) => (remote_error(&e, e.to_string()), None),
     }
 }
 
-/// Generate a millisecond-precision timestamp suffix for unique file names.
-fn timestamp_suffix() -> String {
-    let ts = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default();
-    format!("{}.{:03}", ts.as_secs(), ts.subsec_millis())
+/// A foreground command's result.
+struct Executed {
+    result: ExecResult,
+    /// Bytes of gzip'd stdout that came over the connection, when compressed.
+    compressed_bytes: Option<usize>,
+    /// The client cancelled the call; `result` holds the output until then.
+    cancelled: bool,
 }
 
-/// Save stdout to a temp file and return the path.
-async fn save_output_to_disk(stdout: &str) -> Result<PathBuf, std::io::Error> {
-    let dir = std::env::temp_dir().join("ssh-hub");
-    tokio::fs::create_dir_all(&dir).await?;
-
-    let path = dir.join(format!("output-{}.log", timestamp_suffix()));
-    tokio::fs::write(&path, stdout).await?;
-    Ok(path)
+/// Run `command` until it finishes or `cancelled` completes.
+async fn exec_plain<C: RemoteExecutor>(
+    conn: &C,
+    command: &str,
+    timeout: Option<u64>,
+    cancelled: impl Future<Output = ()> + Send,
+) -> anyhow::Result<Executed> {
+    let (raw, cancelled) = conn
+        .exec_raw_cancellable(command, None, timeout, cancelled)
+        .await?
+        .into_parts();
+    Ok(Executed {
+        result: ExecResult {
+            stdout: String::from_utf8_lossy(&raw.stdout).into_owned(),
+            stderr: raw.stderr,
+            exit_code: raw.exit_code,
+        },
+        compressed_bytes: None,
+        cancelled,
+    })
 }
 
-/// Build a head/tail summary with a pointer to the full output on disk.
-fn build_output_summary(stdout: &str, file_path: &Path) -> String {
-    let lines: Vec<&str> = stdout.lines().collect();
-    let total_lines = lines.len();
-    let total_bytes = stdout.len();
-
-    let size_str = format_byte_size(total_bytes);
-    let mut out = String::with_capacity(SUMMARY_BUFFER_CAPACITY);
+/// Run `command` with its stdout gzipped on the remote. Also returns the
+/// number of bytes that came over the connection.
+async fn exec_compressed<C: RemoteExecutor>(
+    conn: &C,
+    command: &str,
+    timeout: Option<u64>,
+    cancelled: impl Future<Output = ()> + Send,
+) -> anyhow::Result<Executed> {
+    let (raw, cancelled) = conn
+        .exec_raw_cancellable(&compress_command(command), None, timeout, cancelled)
+        .await?
+        .into_parts();
+    let compressed_bytes = Some(raw.stdout.len());
+    let result = if cancelled {
+        decode_compressed_partial(raw)
+    } else {
+        decode_compressed(raw)?
+    };
+    Ok(Executed {
+        result,
+        compressed_bytes,
+        cancelled,
+    })
+}
 
-    let _ = writeln!(
-        out,
-        "[Output too large for context ({size_str}, {total_lines} lines)]",
+/// Await `command`, sending a progress notification with the elapsed time
+/// every [`KEEPALIVE_INTERVAL`] when `enabled` and the client asked for
+/// progress.
+async fn with_keepalive<T>(
+    ctx: &RequestContext<RoleServer>,
+    enabled: bool,
+    command: impl Future<Output = T>,
+) -> T {
+    let token = ctx.meta.get_progress_token().filter(|_| enabled);
+    let started = Instant::now();
+    let mut ticks = tokio::time::interval_at(
+        tokio::time::Instant::now() + KEEPALIVE_INTERVAL,
+        KEEPALIVE_INTERVAL,
     );
-    let _ = writeln!(out, "Full output saved to: {}", file_path.display());
-
-    if total_lines > SUMMARY_HEAD_LINES + SUMMARY_TAIL_LINES {
-        let _ = writeln!(out, "\n--- First {SUMMARY_HEAD_LINES} lines ---");
-        for line in &lines[..SUMMARY_HEAD_LINES] {
-            let _ = writeln!(out, "{line}");
+    tokio::pin!(command);
+    loop {
+        tokio::select! {
+            result = &mut command => return result,
+            _ = ticks.tick(), if token.is_some() => {
+                let elapsed = u32::try_from(started.elapsed().as_secs()).unwrap_or(u32::MAX);
+                let Some(token) = &token else { continue };
+                let param = ProgressNotificationParam {
+                    progress_token: token.clone(),
+                    progress: f64::from(elapsed),
+                    total: None,
+                    message: Some(format!("Still running ({elapsed}s elapsed)")),
+                };
+                if let Err(e) = ctx.peer.notify_progress(param).await {
+                    tracing::debug!("Failed to send keepalive progress notification: {e}");
+                }
+            }
         }
+    }
+}
 
-        let omitted = total_lines - SUMMARY_HEAD_LINES - SUMMARY_TAIL_LINES;
-        let _ = writeln!(out, "\n... ({omitted} lines omitted) ...");
+/// Run `command` under `sudo -S`, feeding `password` on stdin. Anything that
+/// echoes the password back has it masked in the result.
+async fn exec_sudo<C: RemoteExecutor>(
+    conn: &C,
+    command: &str,
+    password: &str,
+    timeout: Option<u64>,
+    cancelled: impl Future<Output = ()> + Send,
+) -> anyhow::Result<Executed> {
+    let stdin = format!("{password}\n");
+    let (raw, cancelled) = conn
+        .exec_raw_cancellable(
+            &sudo_command(command),
+            Some(stdin.as_bytes()),
+            timeout,
+            cancelled,
+        )
+        .await?
+        .into_parts();
+    Ok(Executed {
+        result: ExecResult {
+            stdout: redact(&String::from_utf8_lossy(&raw.stdout), password),
+            stderr: redact(&raw.stderr, password),
+            exit_code: raw.exit_code,
+        },
+        compressed_bytes: None,
+        cancelled,
+    })
+}
 
-        let _ = writeln!(out, "\n--- Last {SUMMARY_TAIL_LINES} lines ---");
-        for line in &lines[total_lines - SUMMARY_TAIL_LINES..] {
-            let _ = writeln!(out, "{line}");
-        }
-    } else {
-        // Few lines but large bytes (very long lines) — include all
-        let _ = write!(out, "\n{stdout}");
+/// Wrap `command` so sudo reads the password from stdin without printing a
+/// prompt, then runs the whole command line as root.
+#[must_use]
+pub fn sudo_command(command: &str) -> String {
+    CommandBuilder::new("sudo")
+        .words(["-S", "-p"])
+        .arg("")
+        .words(["--", "sh", "-c"])
+        .arg(command)
+        .build()
+}
+
+/// Mask every occurrence of `secret` in `text`.
+#[must_use]
+pub fn redact(text: &str, secret: &str) -> String {
+    if secret.is_empty() {
+        return text.to_string();
     }
+    text.replace(secret, "********")
+}
 
-    out
+/// Log file of the background job `job_id`, or `None` when `job_id` isn't
+/// one (job IDs are the `<seconds>.<millis>` launch time).
+#[must_use]
+pub fn background_log_file(job_id: &str) -> Option<String> {
+    let valid = !job_id.is_empty() && job_id.chars().all(|c| c.is_ascii_digit() || c == '.');
+    valid.then(|| format!("{BACKGROUND_LOG_PREFIX}{job_id}.log"))
 }
 
-/// Format a byte count as a human-readable size string (e.g., "1.3 MB", "450 KB").
-fn format_byte_size(bytes: usize) -> String {
-    if bytes >= BYTES_PER_MB {
-        let tenths_mb = bytes / TENTHS_MB_DIVISOR;
-        format!("{}.{} MB", tenths_mb / 10, tenths_mb % 10)
-    } else {
-        format!("{} KB", bytes / BYTES_PER_KB)
-    }
+/// Job ID of a background job's log file.
+#[must_use]
+pub fn background_job_id(log_file: &str) -> Option<&str> {
+    log_file
+        .strip_prefix(BACKGROUND_LOG_PREFIX)?
+        .strip_suffix(".log")
+        .filter(|id| background_log_file(id).is_some())
 }
 
-/// Fallback: truncate stdout at a char boundary when disk write fails.
-fn truncate_inline(stdout: &str) -> String {
-    let mut end = MAX_INLINE_OUTPUT;
-    while !stdout.is_char_boundary(end) && end > 0 {
-        end -= 1;
-    }
-    let mut s = stdout[..end].to_string();
-    s.push_str("\n\n[Output truncated — failed to save to disk]");
-    s
+/// Generate a millisecond-precision timestamp suffix for unique file names.
+fn timestamp_suffix() -> String {
+    let ts = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}.{:03}", ts.as_secs(), ts.subsec_millis())
 }
 
 /// Detect shell-level backgrounding patterns that would hang the SSH channel.
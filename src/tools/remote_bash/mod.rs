@@ -1,4 +1,5 @@
 pub mod handler;
+pub mod mutation;
 pub mod schema;
 
 pub use schema::*;
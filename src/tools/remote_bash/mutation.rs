@@ -0,0 +1,513 @@
+//! Best-effort detection of shell commands that change the remote system,
+//! used to keep `readonly` servers read-only.
+//!
+//! Commands are split on shell operators, leading assignments and wrappers
+//! such as `sudo` or `env` are skipped, and the command word is compared
+//! against known mutating programs and subcommands. Output redirection to
+//! anything but `/dev/null` also counts. Quoted text is not inspected except
+//! for `sh -c` / `bash -c` scripts and `eval`, so this is a guard rail
+//! against accidents, not a sandbox.
+
+/// Programs that change state whatever their arguments.
+const ALWAYS: &[&str] = &[
+    "rm",
+    "rmdir",
+    "mv",
+    "cp",
+    "dd",
+    "mkdir",
+    "touch",
+    "chmod",
+    "chown",
+    "chgrp",
+    "ln",
+    "truncate",
+    "fallocate",
+    "shred",
+    "install",
+    "kill",
+    "pkill",
+    "killall",
+    "reboot",
+    "shutdown",
+    "halt",
+    "poweroff",
+    "useradd",
+    "userdel",
+    "usermod",
+    "groupadd",
+    "groupdel",
+    "groupmod",
+    "passwd",
+    "chpasswd",
+    "mount",
+    "umount",
+    "swapon",
+    "swapoff",
+    "fdisk",
+    "parted",
+    "wipefs",
+    "modprobe",
+    "rmmod",
+    "insmod",
+    "chattr",
+    "setfacl",
+    "rsync",
+    "scp",
+    "unlink",
+    "patch",
+];
+
+/// Programs whose first non-flag argument selects a mutating subcommand.
+const SUBCOMMANDS: &[(&str, &[&str])] = &[
+    (
+        "systemctl",
+        &[
+            "start",
+            "stop",
+            "restart",
+            "reload",
+            "try-restart",
+            "reload-or-restart",
+            "enable",
+            "disable",
+            "mask",
+            "unmask",
+            "kill",
+            "isolate",
+            "daemon-reload",
+            "daemon-reexec",
+            "set-property",
+            "edit",
+            "reboot",
+            "poweroff",
+            "halt",
+            "reset-failed",
+        ],
+    ),
+    (
+        "git",
+        &[
+            "commit",
+            "push",
+            "pull",
+            "checkout",
+            "switch",
+            "reset",
+            "merge",
+            "rebase",
+            "clean",
+            "stash",
+            "add",
+            "rm",
+            "mv",
+            "restore",
+            "cherry-pick",
+            "revert",
+            "apply",
+            "am",
+            "init",
+            "clone",
+        ],
+    ),
+    (
+        "kubectl",
+        &[
+            "apply",
+            "create",
+            "delete",
+            "edit",
+            "patch",
+            "replace",
+            "scale",
+            "annotate",
+            "label",
+            "cordon",
+            "uncordon",
+            "drain",
+            "taint",
+            "set",
+            "expose",
+            "autoscale",
+            "exec",
+            "cp",
+            "run",
+        ],
+    ),
+];
+
+/// Package managers and their mutating verbs.
+const PACKAGE_VERBS: &[&str] = &[
+    "install",
+    "remove",
+    "uninstall",
+    "purge",
+    "upgrade",
+    "update",
+    "autoremove",
+    "dist-upgrade",
+    "full-upgrade",
+    "erase",
+    "reinstall",
+    "downgrade",
+    "add",
+    "del",
+];
+const PACKAGE_MANAGERS: &[&str] = &[
+    "apt", "apt-get", "aptitude", "yum", "dnf", "zypper", "apk", "brew", "snap", "flatpak", "pip",
+    "pip3", "npm", "yarn", "pnpm", "gem", "cargo",
+];
+
+/// Container CLI verbs that change containers, images, or volumes; checked
+/// against the first two non-flag arguments (`docker image rm`, `docker compose up`).
+const CONTAINER_VERBS: &[&str] = &[
+    "run", "rm", "rmi", "stop", "start", "restart", "kill", "exec", "pull", "push", "build",
+    "create", "commit", "cp", "rename", "pause", "unpause", "update", "prune", "up", "down",
+    "load", "import", "tag",
+];
+
+/// `iptables` options that add, change, or remove rules and chains.
+const IPTABLES_CHANGES: &[&str] = &[
+    "-A",
+    "-I",
+    "-D",
+    "-R",
+    "-F",
+    "-X",
+    "-P",
+    "-N",
+    "-Z",
+    "-E",
+    "--append",
+    "--insert",
+    "--delete",
+    "--replace",
+    "--flush",
+    "--policy",
+];
+
+/// Wrappers whose own arguments come before the real command.
+const WRAPPERS: &[&str] = &[
+    "sudo", "doas", "env", "nice", "ionice", "nohup", "time", "command", "exec", "timeout",
+    "xargs", "stdbuf",
+];
+
+/// Return a short description of why `command` looks like it changes the
+/// remote system, or `None` if it looks read-only.
+#[must_use]
+pub fn detect_mutation(command: &str) -> Option<String> {
+    let (commands, redirects) = split_commands(command);
+    if let Some(target) = redirects
+        .iter()
+        .find(|t| !matches!(t.as_str(), "/dev/null" | "/dev/stdout" | "/dev/stderr"))
+    {
+        return Some(format!("output redirection to '{target}'"));
+    }
+    commands
+        .iter()
+        .find_map(|words| check_simple_command(words))
+}
+
+fn check_simple_command(words: &[String]) -> Option<String> {
+    let words = strip_wrappers(words);
+    let (program, args) = words.split_first()?;
+    let name = program.rsplit('/').next().unwrap_or(program);
+    let positional = positional_args(args, value_flags(name));
+
+    if ALWAYS.contains(&name) || name.starts_with("mkfs") {
+        return Some(format!("`{name}`"));
+    }
+    if let Some((_, verbs)) = SUBCOMMANDS.iter().find(|(p, _)| *p == name) {
+        if let Some(verb) = positional.first().filter(|v| verbs.contains(v)) {
+            return Some(format!("`{name} {verb}`"));
+        }
+        if name == "kubectl"
+            && positional.first() == Some(&"rollout")
+            && positional
+                .get(1)
+                .is_some_and(|v| ["restart", "undo", "pause", "resume"].contains(v))
+        {
+            return Some(format!("`kubectl rollout {}`", positional[1]));
+        }
+        return None;
+    }
+    if PACKAGE_MANAGERS.contains(&name) {
+        return positional
+            .first()
+            .filter(|v| PACKAGE_VERBS.contains(v))
+            .map(|verb| format!("`{name} {verb}`"));
+    }
+    program_mutation(name, args, &positional)
+}
+
+/// Rules for programs whose arguments decide whether they write.
+fn program_mutation(name: &str, args: &[String], positional: &[&str]) -> Option<String> {
+    let has_arg = |wanted: &[&str]| args.iter().any(|a| wanted.contains(&a.as_str()));
+    match name {
+        "docker" | "podman" | "nerdctl" => positional
+            .iter()
+            .take(2)
+            .find(|v| CONTAINER_VERBS.contains(v))
+            .map(|verb| format!("`{name} {verb}`")),
+        "service" => positional
+            .get(1)
+            .filter(|v| ["start", "stop", "restart", "reload", "force-reload"].contains(v))
+            .map(|verb| format!("`service {verb}`")),
+        "pacman" => args
+            .iter()
+            .find(|a| {
+                (a.starts_with("-S") && !["-Ss", "-Si", "-Sl", "-Sg"].contains(&a.as_str()))
+                    || a.starts_with("-R")
+                    || a.starts_with("-U")
+            })
+            .map(|flag| format!("`pacman {flag}`")),
+        "sed" => args
+            .iter()
+            .any(|a| a.starts_with("-i") || a.starts_with("--in-place"))
+            .then(|| "`sed -i`".to_string()),
+        "perl" => args
+            .iter()
+            .any(|a| a.starts_with('-') && !a.starts_with("--") && a.contains('i'))
+            .then(|| "`perl -i`".to_string()),
+        "tee" => positional
+            .iter()
+            .any(|a| *a != "/dev/null")
+            .then(|| "`tee` to a file".to_string()),
+        "crontab" => (!has_arg(&["-l"])).then(|| "`crontab`".to_string()),
+        "sysctl" => (has_arg(&["-w", "--write", "-p", "--load"])
+            || positional.iter().any(|a| a.contains('=')))
+        .then(|| "`sysctl` write".to_string()),
+        "iptables" | "ip6tables" => {
+            has_arg(IPTABLES_CHANGES).then(|| format!("`{name}` rule change"))
+        }
+        "ip" => has_arg(&["add", "del", "delete", "set", "replace", "change", "flush"])
+            .then(|| "`ip` configuration change".to_string()),
+        "hostnamectl" | "timedatectl" | "localectl" => positional
+            .first()
+            .filter(|v| v.starts_with("set-"))
+            .map(|verb| format!("`{name} {verb}`")),
+        "wget" => (!args.windows(2).any(|w| w[0] == "-O" && w[1] == "-")
+            && !has_arg(&["-O-", "-qO-", "--spider"]))
+        .then(|| "`wget` download to a file".to_string()),
+        "curl" => has_arg(&["-o", "-O", "--output", "--remote-name", "--remote-name-all"])
+            .then(|| "`curl` download to a file".to_string()),
+        "find" => find_mutation(args),
+        "sh" | "bash" | "zsh" | "dash" | "ksh" => args
+            .iter()
+            .position(|a| a == "-c")
+            .and_then(|i| args.get(i + 1))
+            .and_then(|script| detect_mutation(script)),
+        "eval" => detect_mutation(&args.join(" ")),
+        _ => None,
+    }
+}
+
+fn find_mutation(args: &[String]) -> Option<String> {
+    if args.iter().any(|a| a == "-delete") {
+        return Some("`find -delete`".to_string());
+    }
+    let start = args
+        .iter()
+        .position(|a| matches!(a.as_str(), "-exec" | "-execdir" | "-ok" | "-okdir"))?;
+    let rest = &args[start + 1..];
+    let end = rest
+        .iter()
+        .position(|a| a == ";" || a == "+")
+        .unwrap_or(rest.len());
+    check_simple_command(&rest[..end]).map(|reason| format!("{reason} via `find -exec`"))
+}
+
+/// Global flags that take a separate value, so the value isn't mistaken
+/// for the subcommand.
+fn value_flags(program: &str) -> &'static [&'static str] {
+    match program {
+        "git" => &["-C", "-c", "--git-dir", "--work-tree", "--namespace"],
+        "kubectl" => &[
+            "-n",
+            "--namespace",
+            "--context",
+            "--cluster",
+            "--kubeconfig",
+            "-s",
+            "--server",
+            "--user",
+        ],
+        "systemctl" => &["-H", "--host", "-M", "--machine"],
+        "docker" | "podman" | "nerdctl" => &[
+            "-H",
+            "--host",
+            "--context",
+            "-c",
+            "--config",
+            "-f",
+            "--file",
+            "-p",
+            "--project-name",
+            "--project-directory",
+            "--env-file",
+            "--profile",
+        ],
+        _ => &[],
+    }
+}
+
+/// Arguments that aren't flags or the values of `value_flags`.
+fn positional_args<'a>(args: &'a [String], value_flags: &[&str]) -> Vec<&'a str> {
+    let mut positional = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if value_flags.contains(&arg.as_str()) {
+            iter.next();
+        } else if !arg.starts_with('-') {
+            positional.push(arg.as_str());
+        }
+    }
+    positional
+}
+
+/// Skip leading `VAR=value` assignments and wrapper commands with their flags.
+fn strip_wrappers(mut words: &[String]) -> &[String] {
+    loop {
+        let Some(first) = words.first() else {
+            return words;
+        };
+        if is_assignment(first) {
+            words = &words[1..];
+            continue;
+        }
+        if !WRAPPERS.contains(&first.as_str()) {
+            return words;
+        }
+        let wrapper = first.as_str();
+        words = &words[1..];
+        while let Some(arg) = words.first() {
+            if arg.starts_with('-') {
+                // Flags of sudo/doas that take a separate value.
+                let takes_value = matches!(wrapper, "sudo" | "doas")
+                    && matches!(
+                        arg.as_str(),
+                        "-u" | "-g" | "-C" | "-D" | "-h" | "-p" | "-r" | "-t"
+                    );
+                words = &words[if takes_value { 2 } else { 1 }.min(words.len())..];
+            } else if wrapper == "env" && is_assignment(arg) {
+                words = &words[1..];
+            } else if wrapper == "timeout" && arg.starts_with(|c: char| c.is_ascii_digit()) {
+                words = &words[1..];
+                break;
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty()
+            && name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// Split `command` into simple commands (lists of unquoted words) and collect
+/// output redirection targets. File descriptor duplication (`2>&1`) is not a
+/// target.
+fn split_commands(command: &str) -> (Vec<Vec<String>>, Vec<String>) {
+    let mut splitter = Splitter {
+        commands: vec![Vec::new()],
+        redirects: Vec::new(),
+        word: None,
+        redirect_next: false,
+    };
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                let word = splitter.word();
+                word.extend(chars.by_ref().take_while(|&q| q != '\''));
+            }
+            '"' => {
+                let word = splitter.word();
+                while let Some(q) = chars.next() {
+                    match q {
+                        '"' => break,
+                        '\\' => word.extend(chars.next()),
+                        q => word.push(q),
+                    }
+                }
+            }
+            '\\' => {
+                let next = chars.next();
+                splitter.word().extend(next);
+            }
+            '>' => {
+                // A word of digits right before `>` is a file descriptor.
+                if splitter
+                    .word
+                    .as_ref()
+                    .is_some_and(|w| w.chars().all(|d| d.is_ascii_digit()))
+                {
+                    splitter.word = None;
+                } else {
+                    splitter.finish_word();
+                }
+                if matches!(chars.peek(), Some('>' | '|')) {
+                    chars.next();
+                }
+                if chars.next_if_eq(&'&').is_some() {
+                    // `>&2` duplicates a descriptor.
+                    while chars.next_if(|d| d.is_ascii_digit() || *d == '-').is_some() {}
+                } else {
+                    splitter.redirect_next = true;
+                }
+            }
+            '&' if chars.peek() == Some(&'>') => {
+                splitter.finish_word();
+                chars.next();
+                chars.next_if_eq(&'>');
+                splitter.redirect_next = true;
+            }
+            ';' | '&' | '|' | '\n' | '(' | ')' | '`' => splitter.finish_command(),
+            '$' if chars.peek() == Some(&'(') => {
+                chars.next();
+                splitter.finish_command();
+            }
+            c if c.is_whitespace() => splitter.finish_word(),
+            c => splitter.word().push(c),
+        }
+    }
+    splitter.finish_command();
+    splitter.commands.retain(|c| !c.is_empty());
+    (splitter.commands, splitter.redirects)
+}
+
+struct Splitter {
+    commands: Vec<Vec<String>>,
+    redirects: Vec<String>,
+    /// The word being read, if any (it may be empty: `''`).
+    word: Option<String>,
+    /// The next word is the target of an output redirection.
+    redirect_next: bool,
+}
+
+impl Splitter {
+    fn word(&mut self) -> &mut String {
+        self.word.get_or_insert_with(String::new)
+    }
+
+    fn finish_word(&mut self) {
+        let Some(word) = self.word.take() else {
+            return;
+        };
+        if std::mem::take(&mut self.redirect_next) {
+            self.redirects.push(word);
+        } else if let Some(current) = self.commands.last_mut() {
+            current.push(word);
+        }
+    }
+
+    fn finish_command(&mut self) {
+        self.finish_word();
+        self.commands.push(Vec::new());
+    }
+}
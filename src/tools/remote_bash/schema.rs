@@ -1,6 +1,8 @@
 use rmcp::schemars::{self, JsonSchema};
 use serde::{Deserialize, Serialize};
 
+use crate::server_registry::CommandShell;
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct RemoteBashInput {
     #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
@@ -10,10 +12,20 @@ pub struct RemoteBashInput {
     pub command: String,
 
     #[schemars(
-        description = "Timeout in milliseconds. Defaults to 120000 (2 min), max 600000 (10 min). Ignored when run_in_background is true."
+        description = "Interpreter to run the command with: 'bash' (runs 'bash -lc', for bashisms such as [[ ]] and arrays), 'zsh', 'fish', 'sh', or 'login' to hand the command to the login shell unchanged. Defaults to the server's command_shell setting, which is 'login' unless the login shell isn't POSIX (fish, nushell), where it is 'sh'. POSIX remotes only"
+    )]
+    pub shell: Option<CommandShell>,
+
+    #[schemars(
+        description = "Timeout in milliseconds. Defaults to the server's default_timeout_ms, else 120000 (2 min); max 600000 (10 min) unless long_running is set. 0 runs without a timeout on servers configured with no_timeout = true. Ignored when run_in_background is true."
     )]
     pub timeout: Option<u64>,
 
+    #[schemars(
+        description = "Allow a foreground timeout above 10 minutes, up to the server's max_timeout_ms, for builds and test runs that need the full output and exit code. Progress notifications are sent every 30 seconds while the command runs so the client doesn't give up on the call"
+    )]
+    pub long_running: Option<bool>,
+
     #[schemars(description = "Clear, concise description of what this command does")]
     pub description: Option<String>,
 
@@ -21,6 +33,21 @@ pub struct RemoteBashInput {
         description = "Set to true to run this command in the background. Returns a PID and log file path immediately. The 'timeout' parameter is ignored for background commands."
     )]
     pub run_in_background: Option<bool>,
+
+    #[schemars(
+        description = "Run the command with sudo, using the password stored for this server with 'ssh-hub sudo-password'. The password goes to sudo on stdin and never appears in the output. Not available with run_in_background"
+    )]
+    pub sudo: Option<bool>,
+
+    #[schemars(
+        description = "Reason for making this change outside the server's maintenance window. Only needed after a call was refused for that reason; the reason is logged"
+    )]
+    pub override_window: Option<String>,
+
+    #[schemars(
+        description = "Gzip stdout on the remote and decompress it here, for commands that print a lot (log dumps, large files) over slow links. Defaults to on when the previous run of the same command on this server printed more than 1 MB. Not used with sudo or on non-POSIX remotes"
+    )]
+    pub compress: Option<bool>,
 }
 
 #[derive(Debug, Serialize)]
@@ -28,6 +55,13 @@ pub struct RemoteBashOutput {
     pub stdout: String,
     pub stderr: String,
     pub exit_code: i32,
+    /// Bytes of gzip'd stdout sent over the connection, when compressed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compressed_bytes: Option<usize>,
+    /// The client cancelled the call: the command was stopped, and the
+    /// output is what it printed until then.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
 }
 
 /// Output returned when a command is launched in background mode.
@@ -40,6 +74,8 @@ pub struct RemoteBashBackgroundOutput {
     pub pid: String,
     /// Path to the log file capturing stdout and stderr on the remote server.
     pub log_file: String,
+    /// ID for `ssh-hub logs <server> <job_id>`.
+    pub job_id: String,
     /// Human-readable status message.
     pub message: String,
 }
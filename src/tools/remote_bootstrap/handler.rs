@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::schema::{NeedReport, NeedStatus, RemoteBootstrapInput, RemoteBootstrapOutput};
+use crate::connection::RemoteExecutor;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::provision::handler::failure_detail;
+use crate::tools::provision::package::{PackageManager, DETECT_PACKAGE_MANAGER};
+use crate::tools::{tool_error, ErrorCode, ToolError};
+use crate::utils::command::CommandBuilder;
+use crate::utils::path::shell_escape;
+use crate::utils::validate::is_valid_name;
+
+/// Timeout for finding commands and the package manager (30 seconds).
+const CHECK_TIMEOUT_MS: u64 = 30_000;
+
+/// Timeout for the install (10 minutes, as for provisioning), unless the
+/// server's [`TimeoutPolicy`] raises it.
+const INSTALL_TIMEOUT_MS: u64 = 600_000;
+
+/// A needed command and, when set, the package that provides it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Need {
+    pub command: String,
+    pub package: Option<String>,
+}
+
+impl Need {
+    /// Parse `command` or `command=package`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message for names that aren't plain command or package names.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (command, package) = match spec.split_once('=') {
+            Some((command, package)) => (command.trim(), Some(package.trim())),
+            None => (spec.trim(), None),
+        };
+        if !is_valid_name(command) {
+            return Err(format!("invalid command name '{command}'"));
+        }
+        if let Some(package) = package.filter(|p| !is_valid_name(p)) {
+            return Err(format!("invalid package name '{package}' for '{command}'"));
+        }
+        Ok(Self {
+            command: command.to_string(),
+            package: package.map(str::to_string),
+        })
+    }
+
+    fn package(&self, manager: PackageManager) -> String {
+        self.package
+            .clone()
+            .unwrap_or_else(|| manager.package_for(&self.command))
+    }
+}
+
+/// Script printing each command and its path (empty when missing), one
+/// tab-separated pair per line.
+#[must_use]
+pub fn find_commands_script(needs: &[Need]) -> String {
+    let list = needs
+        .iter()
+        .map(|n| shell_escape(&n.command))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "for c in {list}; do printf '%s\\t%s\\n' \"$c\" \"$(command -v \"$c\" 2>/dev/null)\"; done"
+    )
+}
+
+/// Paths of the commands found by [`find_commands_script`].
+#[must_use]
+pub fn parse_found(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter(|(_, path)| !path.trim().is_empty())
+        .map(|(command, path)| (command.to_string(), path.trim().to_string()))
+        .collect()
+}
+
+/// MCP entry point. `configured` are the server's `needs` from the config,
+/// used when the call names none.
+pub async fn handle<C: RemoteExecutor>(
+    conn: Arc<C>,
+    input: RemoteBootstrapInput,
+    configured: Vec<String>,
+    timeouts: TimeoutPolicy,
+) -> String {
+    if let Some(error) = conn.posix_required("remote_bootstrap") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    let specs = match &input.needs {
+        Some(needs) if !needs.is_empty() => needs.clone(),
+        _ => configured,
+    };
+    if specs.is_empty() {
+        return tool_error(
+            ErrorCode::InvalidInput,
+            "needs must list at least one command, or the server must set needs in servers.toml",
+        );
+    }
+    let needs = match specs
+        .iter()
+        .map(|s| Need::parse(s))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(needs) => needs,
+        Err(e) => return tool_error(ErrorCode::InvalidInput, e),
+    };
+    match bootstrap(&*conn, &input, &needs, timeouts).await {
+        Ok(output) => serde_json::to_string_pretty(&output)
+            .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#)),
+        Err(e) => e.to_json(),
+    }
+}
+
+async fn bootstrap<C: RemoteExecutor>(
+    conn: &C,
+    input: &RemoteBootstrapInput,
+    needs: &[Need],
+    timeouts: TimeoutPolicy,
+) -> Result<RemoteBootstrapOutput, ToolError> {
+    let script = find_commands_script(needs);
+    let found = parse_found(&run(conn, &script, Some(CHECK_TIMEOUT_MS)).await?);
+    let missing: Vec<&Need> = needs
+        .iter()
+        .filter(|n| !found.contains_key(&n.command))
+        .collect();
+    let mut output = RemoteBootstrapOutput {
+        server: input.server.clone(),
+        package_manager: None,
+        tools: needs
+            .iter()
+            .filter_map(|n| {
+                found.get(&n.command).map(|path| NeedReport {
+                    command: n.command.clone(),
+                    status: NeedStatus::Available,
+                    path: Some(path.clone()),
+                    package: None,
+                    detail: None,
+                })
+            })
+            .collect(),
+        install_command: None,
+        dry_run: input.install.unwrap_or(false) && input.dry_run.unwrap_or(false),
+        all_available: missing.is_empty(),
+    };
+    if missing.is_empty() {
+        return Ok(output);
+    }
+
+    let manager = PackageManager::from_command(
+        run(conn, DETECT_PACKAGE_MANAGER, Some(CHECK_TIMEOUT_MS))
+            .await?
+            .trim(),
+    );
+    let Some(manager) = manager else {
+        for need in missing {
+            output.tools.push(NeedReport {
+                command: need.command.clone(),
+                status: NeedStatus::Missing,
+                path: None,
+                package: need.package.clone(),
+                detail: Some("no supported package manager found".to_string()),
+            });
+        }
+        return Ok(output);
+    };
+    output.package_manager = Some(manager.command().to_string());
+
+    let install = install_command(manager, &missing, input.sudo.unwrap_or(false));
+    output.install_command = Some(install.clone());
+
+    if !input.install.unwrap_or(false) || output.dry_run {
+        let status = if output.dry_run {
+            NeedStatus::WouldInstall
+        } else {
+            NeedStatus::Missing
+        };
+        for need in missing {
+            output.tools.push(NeedReport {
+                command: need.command.clone(),
+                status,
+                path: None,
+                package: Some(need.package(manager)),
+                detail: None,
+            });
+        }
+        return Ok(output);
+    }
+
+    let install_error = run(conn, &install, timeouts.operation(INSTALL_TIMEOUT_MS))
+        .await
+        .err()
+        .map(|e| e.message);
+    let found = parse_found(&run(conn, &script, Some(CHECK_TIMEOUT_MS)).await?);
+    for need in missing {
+        let path = found.get(&need.command).cloned();
+        let (status, detail) = match (&path, &install_error) {
+            (Some(_), _) => (NeedStatus::Installed, None),
+            (None, Some(e)) => (NeedStatus::Failed, Some(e.clone())),
+            (None, None) => (
+                NeedStatus::Failed,
+                Some(format!(
+                    "the package installed, but '{}' is still not on the PATH",
+                    need.command
+                )),
+            ),
+        };
+        output.tools.push(NeedReport {
+            command: need.command.clone(),
+            status,
+            path,
+            package: Some(need.package(manager)),
+            detail,
+        });
+    }
+    output.all_available = output.tools.iter().all(|t| t.path.is_some());
+    Ok(output)
+}
+
+/// Command installing the packages of `missing` with `manager`, through
+/// `sudo -n` when asked.
+#[must_use]
+pub fn install_command(manager: PackageManager, missing: &[&Need], sudo: bool) -> String {
+    let mut packages: Vec<String> = Vec::new();
+    for need in missing {
+        let package = need.package(manager);
+        if !packages.contains(&package) {
+            packages.push(package);
+        }
+    }
+    let list = packages
+        .iter()
+        .map(|p| shell_escape(p))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let install = manager.install_command(&list);
+    // Homebrew refuses to run as root.
+    if sudo && manager != PackageManager::Brew {
+        CommandBuilder::new("sudo")
+            .words(["-n", "sh", "-c"])
+            .arg(&install)
+            .build()
+    } else {
+        install
+    }
+}
+
+/// Run `command`, mapping SSH errors and non-zero exits to a tool error
+/// whose message is the failure detail.
+async fn run<C: RemoteExecutor>(
+    conn: &C,
+    command: &str,
+    timeout_ms: Option<u64>,
+) -> Result<String, ToolError> {
+    match conn.exec(command, timeout_ms).await {
+        Ok(r) if r.exit_code == 0 => Ok(r.stdout),
+        Ok(r) => Err(ToolError::new(
+            ErrorCode::Failed,
+            failure_detail(r.exit_code, &r.stdout, &r.stderr),
+        )),
+        Err(e) => Err(ToolError::new(ErrorCode::of(&e), e.to_string())),
+    }
+}
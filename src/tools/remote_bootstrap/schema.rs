@@ -0,0 +1,83 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RemoteBootstrapInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+
+    #[schemars(
+        description = "Commands the task needs (e.g. ['rg', 'jq', 'python3']). Write 'command=package' when the package is named differently. Defaults to the server's needs in servers.toml"
+    )]
+    pub needs: Option<Vec<String>>,
+
+    #[schemars(
+        description = "Install missing commands with the server's package manager. Defaults to false: only check"
+    )]
+    pub install: Option<bool>,
+
+    #[schemars(
+        description = "With install, report the install command without running it. Defaults to false"
+    )]
+    pub dry_run: Option<bool>,
+
+    #[schemars(
+        description = "Run the install through sudo -n (needs passwordless sudo; never prompts). Not used with Homebrew"
+    )]
+    pub sudo: Option<bool>,
+
+    #[schemars(
+        description = "Reason for installing outside the server's maintenance window. Only needed after a call was refused for that reason; the reason is logged"
+    )]
+    pub override_window: Option<String>,
+}
+
+impl RemoteBootstrapInput {
+    /// Whether the call may change the server.
+    #[must_use]
+    pub fn installs(&self) -> bool {
+        self.install.unwrap_or(false) && !self.dry_run.unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemoteBootstrapOutput {
+    pub server: String,
+    /// Package manager found on the server, when something is missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package_manager: Option<String>,
+    pub tools: Vec<NeedReport>,
+    /// Command that installs the missing packages: run, or to be run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub install_command: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+    /// Every needed command is on the `PATH` after the call.
+    pub all_available: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NeedReport {
+    pub command: String,
+    pub status: NeedStatus,
+    /// Where the command was found.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Package that provides the command, for missing ones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NeedStatus {
+    Available,
+    Missing,
+    /// Dry run: the package would be installed.
+    WouldInstall,
+    Installed,
+    /// The install failed, or didn't put the command on the `PATH`.
+    Failed,
+}
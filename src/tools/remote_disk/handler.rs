@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use super::schema::{
+    EntryUsage, FilesystemUsage, RemoteDfInput, RemoteDfOutput, RemoteDuInput, RemoteDuOutput,
+};
+use crate::connection::RemoteExecutor;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::{remote_error, tool_error, ErrorCode};
+use crate::utils::command::CommandBuilder;
+use crate::utils::path::{normalize_remote_path, shell_escape_remote_path};
+
+/// Timeout for listing filesystems (15 seconds).
+const DF_TIMEOUT_MS: u64 = 15_000;
+
+/// Timeout for measuring a directory, which walks everything below it
+/// (2 minutes), unless the server's [`TimeoutPolicy`] raises it.
+const DU_TIMEOUT_MS: u64 = 120_000;
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 200;
+
+/// `df` and `du` report sizes in 1024-byte blocks with `-k`.
+const BLOCK_SIZE: u64 = 1024;
+
+/// Report mounted filesystems as JSON.
+pub async fn df<C: RemoteExecutor>(conn: Arc<C>, input: RemoteDfInput) -> String {
+    if let Some(error) = conn.posix_required("remote_df") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    // -P keeps each filesystem on one line in the same columns on GNU and BSD
+    let mut command = CommandBuilder::new("df")
+        .env("LC_ALL", "C")
+        .words(["-P", "-k"]);
+    if let Some(path) = &input.path {
+        command = command
+            .word("--")
+            .path(normalize_remote_path(path, conn.remote_path()));
+    }
+    let command = command.build();
+    let result = match conn.exec(&command, Some(DF_TIMEOUT_MS)).await {
+        Ok(result) => result,
+        Err(e) => return remote_error(&e, format!("Error listing filesystems: {e}")),
+    };
+    let mut filesystems = parse_df(&result.stdout);
+    if filesystems.is_empty() {
+        let message = format!("Error listing filesystems: {}", result.stderr.trim());
+        return tool_error(ErrorCode::Failed, message);
+    }
+    if input.path.is_none() {
+        filesystems.retain(|fs| fs.size_bytes > 0);
+    }
+    serde_json::to_string_pretty(&RemoteDfOutput { filesystems })
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
+
+/// Measure each entry of a directory as JSON, largest first.
+pub async fn du<C: RemoteExecutor>(
+    conn: Arc<C>,
+    input: RemoteDuInput,
+    timeouts: TimeoutPolicy,
+) -> String {
+    if let Some(error) = conn.posix_required("remote_du") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    let path = normalize_remote_path(input.path.as_deref().unwrap_or("."), conn.remote_path());
+    let result = match conn
+        .exec(&du_command(&path), timeouts.operation(DU_TIMEOUT_MS))
+        .await
+    {
+        Ok(result) => result,
+        Err(e) => return remote_error(&e, format!("Error measuring {path}: {e}")),
+    };
+    // Exit code 2 comes from the `cd`; du itself exits 1 when it couldn't
+    // read some of the tree
+    if result.exit_code == 2 {
+        let message = format!("Error measuring {path}: {}", result.stderr.trim());
+        return tool_error(ErrorCode::Failed, message);
+    }
+    let limit = input.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let output = summarize_du(path, parse_du(&result.stdout), limit, result.exit_code != 0);
+    serde_json::to_string_pretty(&output)
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
+
+/// Shell script that prints `<KiB>\t<name>` for every entry of `path`,
+/// hidden ones included, without crossing into other filesystems.
+/// Unmatched glob patterns are dropped rather than passed to `du`.
+#[must_use]
+pub fn du_command(path: &str) -> String {
+    format!(
+        "cd {} || exit 2; set --; \
+         for f in * .[!.]* ..?*; do \
+         if [ -e \"$f\" ] || [ -L \"$f\" ]; then set -- \"$@\" \"$f\"; fi; \
+         done; \
+         [ $# -eq 0 ] || LC_ALL=C du -skx -- \"$@\" || exit 1",
+        shell_escape_remote_path(path),
+    )
+}
+
+/// Parse `df -P -k` output. Device names and mount points may contain
+/// spaces, so the four numeric columns are found first and the text around
+/// them split off.
+#[must_use]
+pub fn parse_df(stdout: &str) -> Vec<FilesystemUsage> {
+    stdout.lines().skip(1).filter_map(parse_df_line).collect()
+}
+
+fn parse_df_line(line: &str) -> Option<FilesystemUsage> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let start = (1..fields.len().checked_sub(4)?).find(|&i| {
+        fields[i..i + 3].iter().all(|f| f.parse::<u64>().is_ok())
+            && (fields[i + 3].ends_with('%') || fields[i + 3] == "-")
+    })?;
+    let blocks = |i: usize| fields[i].parse::<u64>().ok().map(|n| n * BLOCK_SIZE);
+    Some(FilesystemUsage {
+        filesystem: fields[..start].join(" "),
+        size_bytes: blocks(start)?,
+        used_bytes: blocks(start + 1)?,
+        available_bytes: blocks(start + 2)?,
+        // `-` for filesystems without a meaningful percentage
+        use_percent: fields[start + 3].trim_end_matches('%').parse().unwrap_or(0),
+        mount: fields[start + 4..].join(" "),
+    })
+}
+
+/// Parse the `<KiB>\t<name>` lines printed by [`du_command`].
+#[must_use]
+pub fn parse_du(stdout: &str) -> Vec<EntryUsage> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let (size, name) = line.split_once('\t')?;
+            Some(EntryUsage {
+                name: name.to_string(),
+                bytes: size.trim().parse::<u64>().ok()? * BLOCK_SIZE,
+            })
+        })
+        .collect()
+}
+
+/// Sort entries largest first and keep the top `limit`.
+#[must_use]
+pub fn summarize_du(
+    path: String,
+    mut entries: Vec<EntryUsage>,
+    limit: usize,
+    partial: bool,
+) -> RemoteDuOutput {
+    let total_bytes = entries.iter().map(|e| e.bytes).sum();
+    entries.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+    let omitted = entries.len().saturating_sub(limit);
+    entries.truncate(limit);
+    RemoteDuOutput {
+        path,
+        total_bytes,
+        entries,
+        omitted,
+        partial,
+    }
+}
@@ -0,0 +1,65 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RemoteDfInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+    #[schemars(
+        description = "Only report the filesystem holding this path (relative paths resolve from the connection's base path). If omitted, lists every mounted filesystem with a nonzero size"
+    )]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RemoteDuInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+    #[schemars(
+        description = "Directory whose entries to measure (relative paths resolve from the connection's base path). Defaults to the base path"
+    )]
+    pub path: Option<String>,
+    #[schemars(description = "Maximum entries to return, largest first (default: 20, max: 200)")]
+    pub limit: Option<usize>,
+}
+
+/// One mounted filesystem, as reported by `df -P -k`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FilesystemUsage {
+    /// Device or source, e.g. `/dev/sda1` or `tmpfs`.
+    pub filesystem: String,
+    pub size_bytes: u64,
+    pub used_bytes: u64,
+    pub available_bytes: u64,
+    /// Share of the space usable by ordinary users that is taken.
+    pub use_percent: u8,
+    pub mount: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemoteDfOutput {
+    pub filesystems: Vec<FilesystemUsage>,
+}
+
+/// Disk usage of one entry of the measured directory, subdirectories
+/// included.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EntryUsage {
+    /// Name within the measured directory.
+    pub name: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemoteDuOutput {
+    pub path: String,
+    /// Sum over all entries, including those left out by `limit`.
+    pub total_bytes: u64,
+    /// Largest entries first.
+    pub entries: Vec<EntryUsage>,
+    /// Entries left out by `limit`.
+    pub omitted: usize,
+    /// Some files or directories couldn't be read, so sizes are too low.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+}
@@ -1,32 +1,73 @@
 use std::sync::Arc;
 
 use super::schema::RemoteEditInput;
-use crate::connection::SshConnection;
+use crate::connection::RemoteExecutor;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::{file_backup, remote_error, tool_error, ErrorCode};
+use crate::utils::diff::{truncate_diff, unified_diff};
 use crate::utils::path::normalize_remote_path;
 
-pub async fn handle(conn: Arc<SshConnection>, input: RemoteEditInput) -> String {
+/// Diff lines included in the success message after an applied edit.
+const MAX_DIFF_LINES: usize = 40;
+
+pub async fn handle<C: RemoteExecutor>(
+    conn: Arc<C>,
+    input: RemoteEditInput,
+    timeouts: TimeoutPolicy,
+) -> String {
     let base_path = conn.remote_path().to_string();
     let path = normalize_remote_path(&input.file_path, &base_path);
 
     let content = match conn.read_file(&path).await {
         Ok(c) => c,
-        Err(e) => return format!("Error reading file: {e}"),
+        Err(e) => return remote_error(&e, format!("Error reading file: {e}")),
     };
 
+    let matches = if input.old_string.is_empty() {
+        0
+    } else {
+        content.matches(&input.old_string).count()
+    };
+    if matches == 0 {
+        let message = format!("String '{}' not found in file", input.old_string);
+        return tool_error(ErrorCode::InvalidInput, message);
+    }
+
     let replace_all = input.replace_all.unwrap_or(false);
+    if matches > 1 && !replace_all {
+        let message = format!(
+            "old_string matches {matches} times in {path}. Include more surrounding \
+             context to make it unique, or set replace_all=true to change every occurrence."
+        );
+        return tool_error(ErrorCode::InvalidInput, message);
+    }
+
     let new_content = if replace_all {
         content.replace(&input.old_string, &input.new_string)
     } else {
         content.replacen(&input.old_string, &input.new_string, 1)
     };
+    let diff = unified_diff(&content, &new_content, &path);
 
-    // If unchanged, the old_string was never found
-    if new_content == content {
-        return format!("String '{}' not found in file", input.old_string);
+    if input.preview.unwrap_or(false) {
+        return format!("Preview of edit to {path} (not written):\n\n{diff}");
     }
 
+    let backup_note = if input.backup.unwrap_or(false) {
+        match file_backup::backup_file(conn.as_ref(), &path, timeouts).await {
+            Ok(Some(backup)) => format!("\nBacked up the previous version to {backup}"),
+            Ok(None) => String::new(),
+            Err(e) => return remote_error(&e, format!("{e}; the file was not edited")),
+        }
+    } else {
+        String::new()
+    };
+
     match conn.write_file(&path, &new_content).await {
-        Ok(()) => format!("Successfully edited {path}"),
-        Err(e) => format!("Error writing file: {e}"),
+        Ok(()) => format!(
+            "Successfully edited {path}{backup_note}\n\n{}",
+            truncate_diff(&diff, MAX_DIFF_LINES)
+        ),
+        Err(e) => remote_error(&e, format!("Error writing file: {e}")),
     }
 }
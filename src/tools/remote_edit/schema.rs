@@ -17,4 +17,19 @@ pub struct RemoteEditInput {
 
     #[schemars(description = "Replace all occurrences of old_string (default false)")]
     pub replace_all: Option<bool>,
+
+    #[schemars(
+        description = "Return a unified diff of the change without writing the file (default false)"
+    )]
+    pub preview: Option<bool>,
+
+    #[schemars(
+        description = "Before editing, copy the existing file to ~/.ssh-hub-backups/<timestamp>/ on the server and report the backup path; undo with restore_backup (default false)"
+    )]
+    pub backup: Option<bool>,
+
+    #[schemars(
+        description = "Reason for making this change outside the server's maintenance window. Only needed after a call was refused for that reason; the reason is logged"
+    )]
+    pub override_window: Option<String>,
 }
@@ -3,18 +3,83 @@ use std::sync::Arc;
 use serde_json::json;
 
 use super::schema::RemoteGlobInput;
-use crate::connection::SshConnection;
+use crate::connection::RemoteExecutor;
+use crate::tools::{remote_error, tool_error, ErrorCode};
+use crate::utils::glob::{GlobPage, GlobPattern};
+use crate::utils::output::MAX_INLINE_OUTPUT;
 
-pub async fn handle(conn: Arc<SshConnection>, input: RemoteGlobInput) -> String {
+/// Matches returned when `limit` is not set.
+const DEFAULT_LIMIT: usize = 1000;
+
+/// Upper bound on `limit`.
+const MAX_LIMIT: usize = 10_000;
+
+/// Bytes of paths in one page, leaving room for the other fields. A page
+/// that would be larger ends early, and `next_offset` picks up from there.
+const MAX_PAGE_BYTES: usize = MAX_INLINE_OUTPUT - 4 * 1024;
+
+/// Indentation, comma, and newline around each path in the pretty JSON.
+const PATH_OVERHEAD: usize = 6;
+
+pub async fn handle<C: RemoteExecutor>(conn: Arc<C>, input: RemoteGlobInput) -> String {
+    if let Err(e) = GlobPattern::new(&input.pattern) {
+        return tool_error(
+            ErrorCode::InvalidInput,
+            format!("Invalid glob pattern: {e}"),
+        );
+    }
     let base_path = conn.remote_path().to_string();
     let path = input.path.as_deref().unwrap_or(&base_path);
+    let page = GlobPage {
+        offset: input.offset.unwrap_or(0),
+        limit: input.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        sort: input.sort.unwrap_or_default(),
+    };
 
-    match conn.glob(&input.pattern, Some(path)).await {
-        Ok(files) => {
-            let result = json!({ "files": files });
+    match conn
+        .glob(
+            &input.pattern,
+            Some(path),
+            input.kind.unwrap_or_default(),
+            page,
+        )
+        .await
+    {
+        Ok(mut matches) => {
+            let mut bytes = 0;
+            let fits = matches
+                .paths
+                .iter()
+                .take_while(|path| {
+                    bytes += serde_json::to_string(path).map_or(0, |s| s.len()) + PATH_OVERHEAD;
+                    bytes <= MAX_PAGE_BYTES
+                })
+                .count();
+            matches.paths.truncate(fits);
+            let mut result = json!({
+                "files": matches.paths,
+                "total_matches": matches.total,
+                "truncated": matches.truncated(),
+            });
+            if let Some(next) = matches.next_offset() {
+                result["next_offset"] = json!(next);
+            }
+            if matches.incomplete {
+                result["note"] = json!(
+                    "The directory tree was too large to list completely; showing matches from the first part only. Narrow 'path' or start the pattern with a literal directory."
+                );
+            } else if matches.truncated() {
+                result["note"] = json!(format!(
+                    "Showing matches {}-{} of {}. Pass offset={} for the next page, or narrow the pattern.",
+                    matches.offset + 1,
+                    matches.offset + matches.paths.len(),
+                    matches.total,
+                    matches.offset + matches.paths.len(),
+                ));
+            }
             serde_json::to_string_pretty(&result)
                 .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
         }
-        Err(e) => format!("Error searching files: {e}"),
+        Err(e) => remote_error(&e, format!("Error searching files: {e}")),
     }
 }
@@ -1,16 +1,38 @@
 use rmcp::schemars::{self, JsonSchema};
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize, JsonSchema)]
+use crate::utils::glob::{GlobKind, GlobSort};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct RemoteGlobInput {
     #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
     pub server: String,
 
-    #[schemars(description = "The glob pattern to match files against")]
+    #[schemars(
+        description = "The glob pattern to match. Supports *, ?, ** (any number of directories), [abc], [!abc] and {a,b}. A pattern without '/' matches file names at any depth"
+    )]
     pub pattern: String,
 
     #[schemars(
         description = "The directory to search in. If not specified, uses the connection's base path"
     )]
     pub path: Option<String>,
+
+    #[schemars(
+        description = "What to return: 'files' (default), 'dirs', or 'all'. Directories are listed with a trailing '/'"
+    )]
+    pub kind: Option<GlobKind>,
+
+    #[schemars(description = "Maximum number of matches to return (default: 1000, max: 10000)")]
+    pub limit: Option<usize>,
+
+    #[schemars(
+        description = "Number of matches to skip, for fetching the page after a truncated result (default: 0). Use the next_offset of the previous call"
+    )]
+    pub offset: Option<usize>,
+
+    #[schemars(
+        description = "Order of the matches: 'name' (default) or 'mtime' for the most recently modified first, e.g. to find the newest log matching *.log"
+    )]
+    pub sort: Option<GlobSort>,
 }
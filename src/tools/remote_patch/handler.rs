@@ -0,0 +1,200 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use super::schema::RemotePatchInput;
+use crate::connection::RemoteExecutor;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::{file_backup, remote_error, tool_error, ErrorCode, ToolError};
+use crate::utils::diff::{truncate_diff, unified_diff};
+use crate::utils::patch::{apply_hunks, describe_offsets, parse_patch, FilePatch};
+use crate::utils::path::normalize_remote_path;
+
+/// Diff lines included in the success message after an applied patch.
+const MAX_DIFF_LINES: usize = 80;
+
+/// One file's patch, checked against the current content.
+struct Patched {
+    path: String,
+    old: String,
+    new: String,
+    /// The patch creates the file.
+    created: bool,
+    hunks: usize,
+    offsets: String,
+}
+
+pub async fn handle<C: RemoteExecutor>(
+    conn: Arc<C>,
+    input: RemotePatchInput,
+    timeouts: TimeoutPolicy,
+) -> String {
+    let patches = match parse_patch(&input.patch) {
+        Ok(p) => p,
+        Err(e) => return tool_error(ErrorCode::InvalidInput, format!("invalid patch: {e}")),
+    };
+    if input.file_path.is_some() && patches.len() > 1 {
+        let message = format!(
+            "file_path must be left out for a patch of {} files; the ---/+++ headers name them",
+            patches.len()
+        );
+        return tool_error(ErrorCode::InvalidInput, message);
+    }
+
+    let checked = match check(conn.as_ref(), &patches, input.file_path.as_deref()).await {
+        Ok(checked) => checked,
+        Err(e) => return e.to_json(),
+    };
+
+    let diff: String = checked
+        .iter()
+        .map(|p| unified_diff(&p.old, &p.new, &p.path))
+        .collect();
+    if input.preview.unwrap_or(false) {
+        return format!(
+            "Preview of patch to {} (not written):\n\n{diff}",
+            files(checked.len())
+        );
+    }
+
+    let mut notes = String::new();
+    if input.backup.unwrap_or(false) {
+        for p in checked.iter().filter(|p| !p.created) {
+            match file_backup::backup_file(conn.as_ref(), &p.path, timeouts).await {
+                Ok(Some(backup)) => {
+                    let _ = write!(notes, "\nBacked up the previous {} to {backup}", p.path);
+                }
+                Ok(None) => {}
+                Err(e) => return remote_error(&e, format!("{e}; no file was patched")),
+            }
+        }
+    }
+
+    let mut summary = String::new();
+    for (n, p) in checked.iter().enumerate() {
+        if let Err(e) = conn.write_file(&p.path, &p.new).await {
+            let written: Vec<&str> = checked[..n].iter().map(|p| p.path.as_str()).collect();
+            let already = if written.is_empty() {
+                String::new()
+            } else {
+                format!("; already patched: {}", written.join(", "))
+            };
+            return remote_error(&e, format!("Error writing {}: {e}{already}", p.path));
+        }
+        let moved = if p.offsets.is_empty() {
+            String::new()
+        } else {
+            format!(", {}", p.offsets)
+        };
+        let verb = if p.created { "Created" } else { "Patched" };
+        let _ = writeln!(
+            summary,
+            "{verb} {} ({} hunk{}{moved})",
+            p.path,
+            p.hunks,
+            if p.hunks == 1 { "" } else { "s" },
+        );
+    }
+    format!(
+        "{}{notes}\n\n{}",
+        summary.trim_end(),
+        truncate_diff(&diff, MAX_DIFF_LINES)
+    )
+}
+
+/// Read each file the patch changes and apply its hunks in memory.
+/// Returns the tool error to report when a file can't be read or any hunk
+/// fails.
+async fn check<C: RemoteExecutor>(
+    conn: &C,
+    patches: &[FilePatch],
+    file_path: Option<&str>,
+) -> Result<Vec<Patched>, ToolError> {
+    let base_path = conn.remote_path().to_string();
+    let mut checked = Vec::with_capacity(patches.len());
+    let mut failures = Vec::new();
+    let mut total_hunks = 0;
+    for patch in patches {
+        total_hunks += patch.hunks.len();
+        let path = match target_path(patch, file_path) {
+            Ok(path) => normalize_remote_path(path, &base_path),
+            Err(e) => return Err(e),
+        };
+        if checked.iter().any(|p: &Patched| p.path == path) {
+            return Err(ToolError::new(
+                ErrorCode::InvalidInput,
+                format!("invalid patch: {path} appears more than once"),
+            ));
+        }
+        let created = patch.old_path.is_none() && file_path.is_none();
+        let old = if created {
+            if conn.read_file(&path).await.is_ok() {
+                return Err(ToolError::new(
+                    ErrorCode::InvalidInput,
+                    format!("the patch creates {path}, but it already exists"),
+                ));
+            }
+            String::new()
+        } else {
+            match conn.read_file(&path).await {
+                Ok(c) => c,
+                Err(e) => {
+                    let message = format!("Error reading {path}: {e}");
+                    return Err(ToolError::new(ErrorCode::of(&e), message));
+                }
+            }
+        };
+        match apply_hunks(&old, &patch.hunks) {
+            Ok(applied) => checked.push(Patched {
+                path,
+                old,
+                new: applied.content,
+                created,
+                hunks: patch.hunks.len(),
+                offsets: describe_offsets(&applied.offsets),
+            }),
+            Err(failed) => {
+                failures.extend(failed.into_iter().map(|f| format!("{path}: {f}")));
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(ToolError::new(
+            ErrorCode::InvalidInput,
+            format!(
+                "patch did not apply, so no file was changed. {} of {total_hunks} hunks \
+                 failed:\n{}\nRead the file again and regenerate the patch against its \
+                 current content.",
+                failures.len(),
+                failures.join("\n"),
+            ),
+        ));
+    }
+    Ok(checked)
+}
+
+/// The path `patch` changes: `file_path` if given, else the diff's header.
+fn target_path<'a>(patch: &'a FilePatch, file_path: Option<&'a str>) -> Result<&'a str, ToolError> {
+    if let Some(path) = file_path {
+        return Ok(path);
+    }
+    match (&patch.old_path, &patch.new_path) {
+        (Some(old), None) => Err(ToolError::new(
+            ErrorCode::NotSupported,
+            format!("the patch deletes {old}; deleting files is not supported by remote_patch"),
+        )),
+        (_, Some(new)) => Ok(new),
+        (None, None) => Err(ToolError::new(
+            ErrorCode::InvalidInput,
+            "invalid patch: hunks without ---/+++ headers need file_path",
+        )),
+    }
+}
+
+fn files(count: usize) -> String {
+    if count == 1 {
+        "1 file".to_string()
+    } else {
+        format!("{count} files")
+    }
+}
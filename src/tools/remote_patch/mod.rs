@@ -0,0 +1,4 @@
+pub mod handler;
+pub mod schema;
+
+pub use schema::*;
@@ -0,0 +1,33 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RemotePatchInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+
+    #[schemars(
+        description = "Unified diff to apply, as written by `diff -u` or `git diff`. May cover several files; relative paths in the ---/+++ headers (a/ and b/ prefixes are dropped) resolve against the server's base path. --- /dev/null creates a file"
+    )]
+    pub patch: String,
+
+    #[schemars(
+        description = "File to patch. Required when the diff has only @@ hunks without ---/+++ headers; overrides the header path of a single-file diff"
+    )]
+    pub file_path: Option<String>,
+
+    #[schemars(
+        description = "Check that every hunk applies and return the resulting unified diff without writing (default false)"
+    )]
+    pub preview: Option<bool>,
+
+    #[schemars(
+        description = "Before patching, copy each existing file to ~/.ssh-hub-backups/<timestamp>/ on the server and report the backup paths; undo with restore_backup (default false)"
+    )]
+    pub backup: Option<bool>,
+
+    #[schemars(
+        description = "Reason for making this change outside the server's maintenance window. Only needed after a call was refused for that reason; the reason is logged"
+    )]
+    pub override_window: Option<String>,
+}
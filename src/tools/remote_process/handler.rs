@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use super::schema::{
+    ProcessInfo, PsSort, RemoteKillInput, RemoteKillOutput, RemotePsInput, RemotePsOutput,
+};
+use crate::connection::RemoteExecutor;
+use crate::tools::{remote_error, tool_error, ErrorCode};
+use crate::utils::path::shell_escape;
+
+/// Timeout for listing processes (30 seconds).
+const PS_TIMEOUT_MS: u64 = 30_000;
+
+/// Timeout for checking and signalling a process (15 seconds).
+const KILL_TIMEOUT_MS: u64 = 15_000;
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+/// Signals `remote_kill` accepts, and the numbers that mean the same on
+/// every platform.
+const SIGNALS: [(&str, Option<u32>); 9] = [
+    ("TERM", Some(15)),
+    ("KILL", Some(9)),
+    ("INT", Some(2)),
+    ("HUP", Some(1)),
+    ("QUIT", Some(3)),
+    ("USR1", None),
+    ("USR2", None),
+    ("STOP", None),
+    ("CONT", None),
+];
+
+/// Prints the shell's own PID, so the `ps` it runs can be left out, then one
+/// line per process. `lstart` is always five fields in the C locale.
+const PS_COMMAND: &str =
+    r#"echo "SELF=$$"; LC_ALL=C ps -eo pid=,ppid=,user=,pcpu=,pmem=,lstart=,args="#;
+
+/// List processes on the server as JSON.
+pub async fn ps<C: RemoteExecutor>(conn: Arc<C>, input: RemotePsInput) -> String {
+    if let Some(error) = conn.posix_required("remote_ps") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    let result = match conn.exec(PS_COMMAND, Some(PS_TIMEOUT_MS)).await {
+        Ok(result) if result.exit_code == 0 => result,
+        Ok(result) => {
+            let message = format!("Error listing processes: {}", result.stderr.trim());
+            return tool_error(ErrorCode::Failed, message);
+        }
+        Err(e) => return remote_error(&e, format!("Error listing processes: {e}")),
+    };
+    let output = select_processes(parse_ps(&result.stdout), &input);
+    serde_json::to_string_pretty(&output)
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
+
+/// Send a signal to a process after checking its command line.
+pub async fn kill<C: RemoteExecutor>(conn: Arc<C>, input: RemoteKillInput) -> String {
+    if let Some(error) = conn.posix_required("remote_kill") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    if input.pid <= 1 {
+        let message = format!("refusing to signal PID {}", input.pid);
+        return tool_error(ErrorCode::InvalidInput, message);
+    }
+    if input.match_command.trim().is_empty() {
+        return tool_error(ErrorCode::InvalidInput, "match_command must not be empty");
+    }
+    let signal = match parse_signal(input.signal.as_deref().unwrap_or("TERM")) {
+        Ok(signal) => signal,
+        Err(e) => return tool_error(ErrorCode::InvalidInput, e),
+    };
+
+    let command = kill_command(input.pid, &input.match_command, signal);
+    let result = match conn.exec(&command, Some(KILL_TIMEOUT_MS)).await {
+        Ok(result) => result,
+        Err(e) => return remote_error(&e, format!("Error signalling process: {e}")),
+    };
+    match parse_kill(&result.stdout) {
+        KillResult::Missing => tool_error(
+            ErrorCode::NotFound,
+            format!("no process with PID {}", input.pid),
+        ),
+        KillResult::Mismatch(cmd) => tool_error(
+            ErrorCode::InvalidInput,
+            format!(
+                "PID {} is running '{cmd}', which does not contain '{}'. No signal was sent.",
+                input.pid, input.match_command
+            ),
+        ),
+        KillResult::Failed(_) => tool_error(
+            ErrorCode::Failed,
+            format!("Error signalling process: {}", result.stderr.trim()),
+        ),
+        KillResult::Sent {
+            command,
+            still_running,
+        } => {
+            let output = RemoteKillOutput {
+                pid: input.pid,
+                signal: signal.to_string(),
+                command,
+                still_running,
+            };
+            serde_json::to_string_pretty(&output)
+                .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+        }
+    }
+}
+
+/// Parse the output of [`PS_COMMAND`], leaving out the probe itself.
+#[must_use]
+pub fn parse_ps(stdout: &str) -> Vec<ProcessInfo> {
+    let own_pid = stdout
+        .lines()
+        .find_map(|l| l.strip_prefix("SELF="))
+        .and_then(|pid| pid.trim().parse::<u32>().ok());
+    stdout
+        .lines()
+        .filter_map(parse_ps_line)
+        .filter(|p| own_pid.is_none_or(|own| p.pid != own && p.ppid != own))
+        .collect()
+}
+
+fn parse_ps_line(line: &str) -> Option<ProcessInfo> {
+    let (fields, command) = split_fields(line, 10)?;
+    Some(ProcessInfo {
+        pid: fields[0].parse().ok()?,
+        ppid: fields[1].parse().ok()?,
+        user: fields[2].to_string(),
+        cpu: fields[3].parse().ok()?,
+        mem: fields[4].parse().ok()?,
+        started: fields[5..10].join(" "),
+        command: command.to_string(),
+    })
+}
+
+/// Split `n` whitespace-separated fields off the front of `line`, returning
+/// them and the untouched rest.
+fn split_fields(line: &str, n: usize) -> Option<(Vec<&str>, &str)> {
+    let mut fields = Vec::with_capacity(n);
+    let mut rest = line.trim_start();
+    for _ in 0..n {
+        let end = rest.find(char::is_whitespace)?;
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    Some((fields, rest.trim_end()))
+}
+
+/// Apply the filters, sort, and limit of `input`.
+#[must_use]
+pub fn select_processes(processes: Vec<ProcessInfo>, input: &RemotePsInput) -> RemotePsOutput {
+    let mut processes: Vec<ProcessInfo> = processes
+        .into_iter()
+        .filter(|p| input.user.as_ref().is_none_or(|u| &p.user == u))
+        .filter(|p| {
+            input
+                .filter
+                .as_ref()
+                .is_none_or(|f| p.command.contains(f.as_str()))
+        })
+        .collect();
+    match input.sort.unwrap_or_default() {
+        PsSort::Cpu => processes.sort_by(|a, b| b.cpu.total_cmp(&a.cpu)),
+        PsSort::Mem => processes.sort_by(|a, b| b.mem.total_cmp(&a.mem)),
+        PsSort::Pid => processes.sort_by_key(|p| p.pid),
+    }
+    let matched = processes.len();
+    let limit = input.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    processes.truncate(limit);
+    RemotePsOutput {
+        truncated: matched > processes.len(),
+        processes,
+        matched,
+    }
+}
+
+/// Normalize a signal given by name (`TERM`, `sigterm`) or portable number.
+///
+/// # Errors
+///
+/// Returns a message listing the accepted signals.
+pub fn parse_signal(signal: &str) -> Result<&'static str, String> {
+    let upper = signal.trim().to_ascii_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    let number = name.parse::<u32>().ok();
+    SIGNALS
+        .iter()
+        .find(|(n, num)| *n == name || (number.is_some() && *num == number))
+        .map(|(n, _)| *n)
+        .ok_or_else(|| {
+            let names: Vec<&str> = SIGNALS.iter().map(|(n, _)| *n).collect();
+            format!(
+                "unsupported signal '{signal}'. Use one of {} (or 1, 2, 3, 9, 15)",
+                names.join(", ")
+            )
+        })
+}
+
+/// Shell command that signals `pid` only if its command line contains
+/// `pattern`, checked in the same shell to keep the window for PID reuse
+/// small. Prints `MISSING`, or `CMD=<command line>` followed by `MISMATCH`
+/// or `SENT` (with `ALIVE` if the process is still there a second later).
+#[must_use]
+pub fn kill_command(pid: u32, pattern: &str, signal: &str) -> String {
+    format!(
+        "cmd=$(ps -o args= -p {pid} 2>/dev/null); \
+         if [ -z \"$cmd\" ]; then echo MISSING; exit 0; fi; \
+         printf 'CMD=%s\\n' \"$cmd\"; \
+         case \"$cmd\" in *{pattern}*) ;; *) echo MISMATCH; exit 0;; esac; \
+         kill -s {signal} {pid} || exit 1; \
+         sleep 1; if kill -0 {pid} 2>/dev/null; then echo ALIVE; fi; echo SENT",
+        pattern = shell_escape(pattern),
+    )
+}
+
+/// Outcome of [`kill_command`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum KillResult {
+    Missing,
+    Mismatch(String),
+    Sent {
+        command: String,
+        still_running: bool,
+    },
+    /// The process matched but `kill` failed (e.g. permission denied).
+    Failed(String),
+}
+
+/// Parse the output of [`kill_command`].
+#[must_use]
+pub fn parse_kill(stdout: &str) -> KillResult {
+    let command = stdout
+        .lines()
+        .find_map(|l| l.strip_prefix("CMD="))
+        .unwrap_or_default()
+        .to_string();
+    let has = |marker: &str| stdout.lines().any(|l| l.trim() == marker);
+    if has("MISSING") {
+        KillResult::Missing
+    } else if has("MISMATCH") {
+        KillResult::Mismatch(command)
+    } else if has("SENT") {
+        KillResult::Sent {
+            command,
+            still_running: has("ALIVE"),
+        }
+    } else {
+        KillResult::Failed(command)
+    }
+}
@@ -0,0 +1,78 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RemotePsInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+    #[schemars(
+        description = "Only list processes whose command line contains this text (case-sensitive)"
+    )]
+    pub filter: Option<String>,
+    #[schemars(description = "Only list processes owned by this user")]
+    pub user: Option<String>,
+    #[schemars(description = "Sort order: 'cpu' (default), 'mem', or 'pid'")]
+    pub sort: Option<PsSort>,
+    #[schemars(description = "Maximum processes to return (default: 50, max: 500)")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PsSort {
+    #[default]
+    Cpu,
+    Mem,
+    Pid,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RemoteKillInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+    #[schemars(description = "PID of the process to signal, as listed by remote_ps")]
+    pub pid: u32,
+    #[schemars(
+        description = "Text that must appear in the process's command line. The signal is only sent if it does, so a PID reused by another process is left alone"
+    )]
+    pub match_command: String,
+    #[schemars(
+        description = "Signal name or number: TERM (default), KILL, INT, HUP, QUIT, USR1, USR2, STOP, CONT"
+    )]
+    pub signal: Option<String>,
+    #[schemars(
+        description = "Reason for making this change outside the server's maintenance window. Only needed after a call was refused for that reason; the reason is logged"
+    )]
+    pub override_window: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub ppid: u32,
+    pub user: String,
+    /// CPU usage in percent, as reported by `ps`.
+    pub cpu: f32,
+    /// Share of physical memory in percent.
+    pub mem: f32,
+    /// Start time in the server's local time, e.g. `Thu Oct 16 14:57:01 2026`.
+    pub started: String,
+    pub command: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemotePsOutput {
+    pub processes: Vec<ProcessInfo>,
+    /// Processes that matched the filters, before `limit`.
+    pub matched: usize,
+    pub truncated: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct RemoteKillOutput {
+    pub pid: u32,
+    pub signal: String,
+    pub command: String,
+    /// Whether the process was still there a second after the signal.
+    pub still_running: bool,
+}
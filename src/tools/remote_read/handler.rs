@@ -1,42 +1,69 @@
 use std::sync::Arc;
 
 use super::schema::RemoteReadInput;
-use crate::connection::SshConnection;
-use crate::utils::path::{
-    format_with_line_numbers, normalize_remote_path, shell_escape_remote_path,
-};
+use crate::connection::{read_failure, read_failure_check, RemoteExecutor};
+use crate::tools::remote_error;
+use crate::utils::command::CommandBuilder;
+use crate::utils::output::fit_output;
+use crate::utils::path::{format_with_line_numbers, normalize_remote_path};
 
-pub async fn handle(conn: Arc<SshConnection>, input: RemoteReadInput) -> String {
+pub async fn handle<C: RemoteExecutor>(conn: Arc<C>, input: RemoteReadInput) -> String {
     let base_path = conn.remote_path().to_string();
     let path = normalize_remote_path(&input.file_path, &base_path);
 
     let offset = input.offset.unwrap_or(0);
     let has_slicing = offset > 0 || input.limit.is_some();
 
-    if has_slicing {
+    if has_slicing && conn.shell().is_posix() {
         // Server-side slicing with sed — transfers only the requested lines
         let start = offset + 1; // sed is 1-indexed
         let end = match input.limit {
             Some(limit) => format!("{}", offset + limit),
             None => "$".to_string(),
         };
-        let command = format!(
-            "sed -n '{start},{end}p' {}",
-            shell_escape_remote_path(&path),
-        );
+        let command = CommandBuilder::new("sed")
+            .word("-n")
+            .arg(format!("{start},{end}p"))
+            .path(&path)
+            .build();
+        let command = format!("{command} || {}", read_failure_check(&path));
         let line_offset = usize::try_from(offset).unwrap_or(usize::MAX);
         match conn.exec(&command, Some(60_000)).await {
             Ok(result) if result.exit_code == 0 => {
-                format_with_line_numbers(&result.stdout, line_offset)
+                fit_output(
+                    format_with_line_numbers(&result.stdout, line_offset),
+                    "remote_read",
+                )
+                .await
             }
-            Ok(result) => format!("Error reading file: {}", result.stderr),
-            Err(e) => format!("Error reading file: {e}"),
+            Ok(result) => {
+                let error = read_failure(result.exit_code, &result.stderr);
+                remote_error(&error, format!("Error reading file: {}", result.stderr))
+            }
+            Err(e) => remote_error(&e, format!("Error reading file: {e}")),
+        }
+    } else if has_slicing {
+        // No sed on non-POSIX remotes — read the whole file and slice locally
+        let start = usize::try_from(offset).unwrap_or(usize::MAX);
+        let limit = input
+            .limit
+            .map_or(usize::MAX, |l| usize::try_from(l).unwrap_or(usize::MAX));
+        match conn.read_file(&path).await {
+            Ok(content) => {
+                let sliced: Vec<&str> = content.lines().skip(start).take(limit).collect();
+                fit_output(
+                    format_with_line_numbers(&sliced.join("\n"), start),
+                    "remote_read",
+                )
+                .await
+            }
+            Err(e) => remote_error(&e, format!("Error reading file: {e}")),
         }
     } else {
         // Full file read — pass directly to formatter
         match conn.read_file(&path).await {
-            Ok(content) => format_with_line_numbers(&content, 0),
-            Err(e) => format!("Error reading file: {e}"),
+            Ok(content) => fit_output(format_with_line_numbers(&content, 0), "remote_read").await,
+            Err(e) => remote_error(&e, format!("Error reading file: {e}")),
         }
     }
 }
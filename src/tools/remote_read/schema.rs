@@ -1,7 +1,7 @@
 use rmcp::schemars::{self, JsonSchema};
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct RemoteReadInput {
     #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
     pub server: String,
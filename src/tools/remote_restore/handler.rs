@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use super::schema::RemoteRestoreInput;
+use crate::connection::RemoteExecutor;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::{dir_snapshot, remote_error, tool_error, ErrorCode};
+
+/// Replace a directory with a snapshot of it. The current directory is
+/// snapshotted first, so a restore can itself be undone.
+pub async fn handle<C: RemoteExecutor>(
+    conn: Arc<C>,
+    input: RemoteRestoreInput,
+    snapshot_dir: &str,
+    timeouts: TimeoutPolicy,
+) -> String {
+    let dest = match dir_snapshot::snapshot_source(&input.snapshot) {
+        Ok(path) => path,
+        Err(e) => return tool_error(ErrorCode::InvalidInput, e.to_string()),
+    };
+    let replaced = match dir_snapshot::take_snapshot(
+        conn.as_ref(),
+        &dest,
+        snapshot_dir,
+        Some(dir_snapshot::BEFORE_RESTORE_LABEL),
+        timeouts,
+    )
+    .await
+    {
+        Ok(snapshot) => snapshot,
+        Err(e) => return remote_error(&e, format!("{e}; nothing was restored")),
+    };
+    if let Err(e) = dir_snapshot::restore_snapshot(conn.as_ref(), &input.snapshot, timeouts).await {
+        return remote_error(&e, e.to_string());
+    }
+    match replaced {
+        Some(snapshot) => format!(
+            "Restored {dest} from {}. The replaced directory was snapshotted to {}",
+            input.snapshot, snapshot.path
+        ),
+        None => format!("Restored {dest} from {}", input.snapshot),
+    }
+}
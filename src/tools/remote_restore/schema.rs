@@ -0,0 +1,18 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RemoteRestoreInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+
+    #[schemars(
+        description = "Snapshot path reported by remote_snapshot (a .tar.gz under the snapshot directory). The directory it was taken of is replaced with its contents"
+    )]
+    pub snapshot: String,
+
+    #[schemars(
+        description = "Reason for making this change outside the server's maintenance window. Only needed after a call was refused for that reason; the reason is logged"
+    )]
+    pub override_window: Option<String>,
+}
@@ -0,0 +1,264 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+
+use futures::future::join_all;
+use regex::Regex;
+
+use super::schema::{OutcomeStatus, RemoteRolloutInput, RolloutReport, ServerOutcome};
+use crate::connection::SshConnection;
+use crate::timeout::{TimeoutPolicy, DEFAULT_TIMEOUT_MS};
+use crate::tools::remote_bash::handler::detect_background_pattern;
+use crate::tools::ToolError;
+
+/// Lines of stdout and of stderr kept per server in the report.
+const OUTPUT_TAIL_LINES: usize = 20;
+
+/// What a server's result must satisfy to pass.
+#[derive(Debug, Clone)]
+pub struct Assertion {
+    pub exit_code: i32,
+    pub output: Option<Regex>,
+}
+
+impl Assertion {
+    /// # Errors
+    ///
+    /// Returns why the result doesn't satisfy the assertion.
+    pub fn check(&self, exit_code: i32, stdout: &str) -> Result<(), String> {
+        if exit_code != self.exit_code {
+            return Err(format!(
+                "exit code {exit_code}, expected {}",
+                self.exit_code
+            ));
+        }
+        if let Some(re) = &self.output {
+            if !re.is_match(stdout) {
+                return Err(format!("stdout does not match /{}/", re.as_str()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Validated rollout: the canary, then the remaining servers in batches.
+#[derive(Debug, Clone)]
+pub struct RolloutPlan {
+    pub command: String,
+    pub canary: String,
+    pub batches: Vec<Vec<String>>,
+    pub batch_size: usize,
+    pub assertion: Assertion,
+    pub max_failures: usize,
+    /// Each server's timeout, `None` for no timeout.
+    pub timeouts: HashMap<String, Option<u64>>,
+}
+
+/// Check the input and split the servers into canary and batches.
+///
+/// # Errors
+///
+/// Returns a user-facing message for an empty or duplicated server list, an
+/// unknown canary, a backgrounded command, an invalid regex, or a timeout
+/// one of the servers' `timeouts` rejects.
+pub fn plan(
+    input: RemoteRolloutInput,
+    timeouts: impl Fn(&str) -> TimeoutPolicy,
+) -> Result<RolloutPlan, String> {
+    if input.servers.is_empty() {
+        return Err("'servers' must list at least one server".to_string());
+    }
+    let mut seen = HashSet::new();
+    if let Some(dup) = input.servers.iter().find(|s| !seen.insert(s.as_str())) {
+        return Err(format!("server '{dup}' is listed more than once"));
+    }
+    if input.command.trim().is_empty() {
+        return Err("'command' must not be empty".to_string());
+    }
+    if let Some(reason) = detect_background_pattern(&input.command) {
+        return Err(format!(
+            "command appears to use shell-level backgrounding ({reason}), which hangs the SSH channel"
+        ));
+    }
+
+    let canary = input.canary.unwrap_or_else(|| input.servers[0].clone());
+    if !input.servers.contains(&canary) {
+        return Err(format!("canary '{canary}' must be one of 'servers'"));
+    }
+    let output = input
+        .expect_output
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .map_err(|e| format!("invalid expect_output regex: {e}"))?;
+
+    let timeouts = input
+        .servers
+        .iter()
+        .map(|server| {
+            let timeout = timeouts(server)
+                .resolve(input.timeout, false)
+                .map_err(|e| format!("server '{server}': {e}"))?;
+            Ok((server.clone(), timeout))
+        })
+        .collect::<Result<_, String>>()?;
+
+    let batch_size = input.batch_size.unwrap_or(1).max(1);
+    let rest: Vec<String> = input.servers.into_iter().filter(|s| *s != canary).collect();
+    Ok(RolloutPlan {
+        command: input.command,
+        canary,
+        batches: rest.chunks(batch_size).map(<[String]>::to_vec).collect(),
+        batch_size,
+        assertion: Assertion {
+            exit_code: input.expect_exit_code.unwrap_or(0),
+            output,
+        },
+        max_failures: input.max_failures.unwrap_or(0),
+        timeouts,
+    })
+}
+
+/// Run the plan: the canary alone, then each batch concurrently. Stops when
+/// the canary fails or failures after it exceed `max_failures`; servers not
+/// reached are reported as skipped.
+///
+/// `connect` returns a pooled connection for a server name, or the error
+/// connecting ran into.
+pub async fn rollout<F, Fut>(plan: &RolloutPlan, connect: F) -> RolloutReport
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<Arc<SshConnection>, ToolError>>,
+{
+    let mut servers = Vec::new();
+    let mut aborted = None;
+
+    let canary = run_server(plan, &plan.canary, 0, &connect).await;
+    if canary.status == OutcomeStatus::Failed {
+        aborted = Some(format!(
+            "canary '{}' failed: {}",
+            plan.canary,
+            canary.reason.as_deref().unwrap_or("unknown error")
+        ));
+    }
+    servers.push(canary);
+
+    let mut failures = 0;
+    for (i, batch) in plan.batches.iter().enumerate() {
+        if aborted.is_some() {
+            servers.extend(batch.iter().map(|s| skipped(s, i + 1)));
+            continue;
+        }
+        let results = join_all(batch.iter().map(|s| run_server(plan, s, i + 1, &connect))).await;
+        failures += results
+            .iter()
+            .filter(|r| r.status == OutcomeStatus::Failed)
+            .count();
+        servers.extend(results);
+        if failures > plan.max_failures {
+            aborted = Some(format!(
+                "{failures} server(s) failed after the canary (max_failures = {})",
+                plan.max_failures
+            ));
+        }
+    }
+
+    let count = |status| servers.iter().filter(|s| s.status == status).count();
+    RolloutReport {
+        command: plan.command.clone(),
+        canary: plan.canary.clone(),
+        batch_size: plan.batch_size,
+        aborted,
+        passed: count(OutcomeStatus::Passed),
+        failed: count(OutcomeStatus::Failed),
+        skipped: count(OutcomeStatus::Skipped),
+        servers,
+    }
+}
+
+/// MCP entry point: run the rollout and return the report as JSON.
+pub async fn handle<F, Fut>(plan: RolloutPlan, connect: F) -> String
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<Arc<SshConnection>, ToolError>>,
+{
+    let report = rollout(&plan, connect).await;
+    serde_json::to_string_pretty(&report)
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
+
+async fn run_server<F, Fut>(
+    plan: &RolloutPlan,
+    server: &str,
+    batch: usize,
+    connect: &F,
+) -> ServerOutcome
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<Arc<SshConnection>, ToolError>>,
+{
+    let start = Instant::now();
+    let mut outcome = ServerOutcome {
+        server: server.to_string(),
+        batch,
+        status: OutcomeStatus::Failed,
+        exit_code: None,
+        duration_ms: None,
+        reason: None,
+        output: String::new(),
+    };
+    let conn = match connect(server.to_string()).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            outcome.reason = Some(e.message);
+            return outcome;
+        }
+    };
+    let timeout = plan
+        .timeouts
+        .get(server)
+        .copied()
+        .unwrap_or(Some(DEFAULT_TIMEOUT_MS));
+    let result = conn.exec(&plan.command, timeout).await;
+    outcome.duration_ms = Some(u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX));
+    match result {
+        Ok(r) => {
+            outcome.exit_code = Some(r.exit_code);
+            outcome.output = output_tail(&r.stdout, &r.stderr);
+            match plan.assertion.check(r.exit_code, &r.stdout) {
+                Ok(()) => outcome.status = OutcomeStatus::Passed,
+                Err(reason) => outcome.reason = Some(reason),
+            }
+        }
+        Err(e) => outcome.reason = Some(e.to_string()),
+    }
+    outcome
+}
+
+fn skipped(server: &str, batch: usize) -> ServerOutcome {
+    ServerOutcome {
+        server: server.to_string(),
+        batch,
+        status: OutcomeStatus::Skipped,
+        exit_code: None,
+        duration_ms: None,
+        reason: Some("rollout aborted".to_string()),
+        output: String::new(),
+    }
+}
+
+/// Last [`OUTPUT_TAIL_LINES`] of stdout, then of stderr under a `[stderr]` marker.
+#[must_use]
+pub fn output_tail(stdout: &str, stderr: &str) -> String {
+    let tail = |s: &str| {
+        let lines: Vec<&str> = s.trim_end().lines().collect();
+        lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n")
+    };
+    let (out, err) = (tail(stdout), tail(stderr));
+    match (out.is_empty(), err.is_empty()) {
+        (_, true) => out,
+        (true, false) => format!("[stderr]\n{err}"),
+        (false, false) => format!("{out}\n[stderr]\n{err}"),
+    }
+}
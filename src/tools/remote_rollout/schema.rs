@@ -0,0 +1,86 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RemoteRolloutInput {
+    #[schemars(description = "Servers to run the command on, in rollout order")]
+    pub servers: Vec<String>,
+
+    #[schemars(description = "The command to execute on each server")]
+    pub command: String,
+
+    #[schemars(
+        description = "Server to run first, alone. Defaults to the first entry in 'servers'. If it fails the assertion, the rollout stops"
+    )]
+    pub canary: Option<String>,
+
+    #[schemars(description = "Servers run concurrently per batch after the canary. Defaults to 1")]
+    pub batch_size: Option<usize>,
+
+    #[schemars(description = "Exit code that counts as success. Defaults to 0")]
+    pub expect_exit_code: Option<i32>,
+
+    #[schemars(
+        description = "Regex that must match somewhere in stdout for a server to pass (in addition to the exit code)"
+    )]
+    pub expect_output: Option<String>,
+
+    #[schemars(
+        description = "Failures tolerated after the canary before the rollout aborts. Defaults to 0 (abort on the first failure)"
+    )]
+    pub max_failures: Option<usize>,
+
+    #[schemars(
+        description = "Timeout per server in milliseconds. Defaults to each server's default_timeout_ms, else 120000 (2 min); max 600000 (10 min). 0 runs without a timeout, if every server is configured with no_timeout = true"
+    )]
+    pub timeout: Option<u64>,
+
+    #[schemars(
+        description = "Reason for making this change outside the servers' maintenance windows. Only needed after a call was refused for that reason; the reason is logged"
+    )]
+    pub override_window: Option<String>,
+}
+
+/// Final per-server report of a rollout.
+#[derive(Debug, Serialize)]
+pub struct RolloutReport {
+    pub command: String,
+    pub canary: String,
+    pub batch_size: usize,
+    /// Why the rollout stopped early, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aborted: Option<String>,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub servers: Vec<ServerOutcome>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerOutcome {
+    pub server: String,
+    /// 0 for the canary, then 1, 2, ... for each batch.
+    pub batch: usize,
+    pub status: OutcomeStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Why the server failed or was skipped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Last lines of stdout and stderr.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutcomeStatus {
+    /// Exit code and output matched the assertion.
+    Passed,
+    /// The command ran but failed the assertion, or could not run.
+    Failed,
+    /// Not attempted because the rollout aborted first.
+    Skipped,
+}
@@ -0,0 +1,369 @@
+use std::sync::Arc;
+
+use super::schema::{
+    RemoteServiceInput, RemoteServiceOutput, ServiceAction, ServiceBackend, ServiceState,
+};
+use crate::connection::RemoteExecutor;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::{remote_error, tool_error, ErrorCode, ToolError};
+use crate::utils::command::CommandBuilder;
+use crate::utils::path::shell_escape_remote_path;
+use crate::utils::validate::is_valid_name;
+
+/// Timeout for detecting the backend, reading state, and reading logs (30 seconds).
+const STATUS_TIMEOUT_MS: u64 = 30_000;
+
+/// Timeout for starting, stopping, or restarting a service (2 minutes), long
+/// enough for `docker compose up` to pull a small image, unless the server's
+/// [`TimeoutPolicy`] raises it.
+const ACTION_TIMEOUT_MS: u64 = 120_000;
+
+const DEFAULT_LINES: usize = 100;
+const MAX_LINES: usize = 1000;
+
+/// Lines of stderr kept in the error for a failed action.
+const DETAIL_LINES: usize = 10;
+
+/// Runs docker compose as the `docker compose` plugin, or the standalone
+/// `docker-compose` where the plugin is missing.
+const COMPOSE_FN: &str = "dc() { if docker compose version >/dev/null 2>&1; \
+     then docker compose \"$@\"; else docker-compose \"$@\"; fi; }";
+
+/// Prints the service manager to use: systemd when it is running as init,
+/// then init scripts, then docker compose, else `none`.
+const DETECT_COMMAND: &str = "if [ -d /run/systemd/system ] && command -v systemctl >/dev/null 2>&1; then echo systemd; \
+     elif command -v service >/dev/null 2>&1; then echo service; \
+     elif docker compose version >/dev/null 2>&1 || command -v docker-compose >/dev/null 2>&1; then echo compose; \
+     else echo none; fi";
+
+/// Report a service's state, optionally after starting, stopping, or
+/// restarting it, or with its recent logs.
+pub async fn handle<C: RemoteExecutor>(
+    conn: Arc<C>,
+    input: RemoteServiceInput,
+    timeouts: TimeoutPolicy,
+) -> String {
+    if let Some(error) = conn.posix_required("remote_service") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    if !is_valid_name(&input.service) {
+        let message = format!("invalid service name '{}'", input.service);
+        return tool_error(ErrorCode::InvalidInput, message);
+    }
+    let backend = match resolve_backend(&*conn, &input).await {
+        Ok(backend) => backend,
+        Err(e) => return e.to_json(),
+    };
+    let sudo = input.sudo.unwrap_or(false);
+    let project_dir = input.project_dir.as_deref();
+    let run = |body: String| service_script(backend, project_dir, &body, sudo);
+
+    if input.action.is_mutation() {
+        let command = run(action_command(backend, input.action, &input.service));
+        match conn
+            .exec(&command, timeouts.operation(ACTION_TIMEOUT_MS))
+            .await
+        {
+            Ok(result) if result.exit_code == 0 => {}
+            Ok(result) => {
+                let message = format!(
+                    "{} {} failed: {}",
+                    input.action,
+                    input.service,
+                    failure_detail(result.exit_code, &result.stdout, &result.stderr)
+                );
+                return tool_error(ErrorCode::Failed, message);
+            }
+            Err(e) => {
+                let message = format!("{} {} failed: {e}", input.action, input.service);
+                return remote_error(&e, message);
+            }
+        }
+    }
+
+    let logs = if input.action == ServiceAction::Logs {
+        let lines = input.lines.unwrap_or(DEFAULT_LINES).clamp(1, MAX_LINES);
+        let Some(body) = logs_command(backend, &input.service, lines) else {
+            let message = format!(
+                "the {backend} backend keeps no logs of its own. Use remote_tail on the service's log file"
+            );
+            return tool_error(ErrorCode::NotSupported, message);
+        };
+        match conn.exec(&run(body), Some(STATUS_TIMEOUT_MS)).await {
+            Ok(result) if result.exit_code == 0 => Some(result.stdout),
+            Ok(result) => {
+                let message = format!("Error reading logs: {}", result.stderr.trim());
+                return tool_error(ErrorCode::Failed, message);
+            }
+            Err(e) => return remote_error(&e, format!("Error reading logs: {e}")),
+        }
+    } else {
+        None
+    };
+
+    let command = run(status_command(backend, &input.service));
+    let result = match conn.exec(&command, Some(STATUS_TIMEOUT_MS)).await {
+        Ok(result) => result,
+        Err(e) => return remote_error(&e, format!("Error reading service state: {e}")),
+    };
+    let state = match backend {
+        ServiceBackend::Systemd => parse_systemd_show(&result.stdout),
+        ServiceBackend::Service => parse_service_status(&result.stdout),
+        ServiceBackend::Compose if result.exit_code == 0 => Ok(parse_compose_state(&result.stdout)),
+        ServiceBackend::Compose => Err(result.stderr.trim().to_string()),
+    };
+    let state = match state {
+        Ok(state) => state,
+        Err(e) => {
+            let message = format!("Error reading state of {}: {e}", input.service);
+            return tool_error(ErrorCode::Failed, message);
+        }
+    };
+
+    let output = RemoteServiceOutput {
+        service: input.service,
+        backend,
+        action: input.action,
+        state,
+        logs,
+    };
+    serde_json::to_string_pretty(&output)
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
+
+/// The backend `input` asks for, compose when it names a project
+/// directory, or otherwise the one found on the remote.
+async fn resolve_backend<C: RemoteExecutor + ?Sized>(
+    conn: &C,
+    input: &RemoteServiceInput,
+) -> Result<ServiceBackend, ToolError> {
+    if let Some(backend) = input.backend {
+        return Ok(backend);
+    }
+    if input.project_dir.is_some() {
+        return Ok(ServiceBackend::Compose);
+    }
+    let result = conn
+        .exec(DETECT_COMMAND, Some(STATUS_TIMEOUT_MS))
+        .await
+        .map_err(|e| {
+            let message = format!("detecting the service manager failed: {e}");
+            ToolError::new(ErrorCode::of(&e), message)
+        })?;
+    parse_backend(&result.stdout).ok_or_else(|| {
+        ToolError::new(
+            ErrorCode::NotSupported,
+            "no service manager found on the remote (looked for systemctl, service, and docker compose)",
+        )
+    })
+}
+
+/// Parse the output of the backend detection.
+#[must_use]
+pub fn parse_backend(stdout: &str) -> Option<ServiceBackend> {
+    match stdout.trim() {
+        "systemd" => Some(ServiceBackend::Systemd),
+        "service" => Some(ServiceBackend::Service),
+        "compose" => Some(ServiceBackend::Compose),
+        _ => None,
+    }
+}
+
+/// Wrap a backend command: compose commands get the `dc` helper and run in
+/// `project_dir`, and everything runs through `sudo -n` when asked.
+#[must_use]
+pub fn service_script(
+    backend: ServiceBackend,
+    project_dir: Option<&str>,
+    body: &str,
+    sudo: bool,
+) -> String {
+    let script = match (backend, project_dir) {
+        (ServiceBackend::Compose, Some(dir)) => format!(
+            "{COMPOSE_FN}; cd {} && {body}",
+            shell_escape_remote_path(dir)
+        ),
+        (ServiceBackend::Compose, None) => format!("{COMPOSE_FN}; {body}"),
+        _ => body.to_string(),
+    };
+    if sudo {
+        CommandBuilder::new("sudo")
+            .words(["-n", "sh", "-c"])
+            .arg(&script)
+            .build()
+    } else {
+        script
+    }
+}
+
+/// Command that starts, stops, or restarts `service`. Compose starts with
+/// `up -d`, which also creates the container when there is none yet.
+#[must_use]
+pub fn action_command(backend: ServiceBackend, action: ServiceAction, service: &str) -> String {
+    let word = action.to_string();
+    match (backend, action) {
+        (ServiceBackend::Systemd, _) => CommandBuilder::new("systemctl").word(word).arg(service),
+        (ServiceBackend::Service, _) => CommandBuilder::new("service").arg(service).word(word),
+        (ServiceBackend::Compose, ServiceAction::Start) => {
+            CommandBuilder::new("dc").words(["up", "-d"]).arg(service)
+        }
+        (ServiceBackend::Compose, _) => CommandBuilder::new("dc").word(word).arg(service),
+    }
+    .build()
+}
+
+/// Command that prints the last `lines` log lines of `service`, or `None`
+/// for init scripts, which have no log of their own.
+#[must_use]
+pub fn logs_command(backend: ServiceBackend, service: &str, lines: usize) -> Option<String> {
+    let lines = lines.to_string();
+    let command = match backend {
+        ServiceBackend::Systemd => CommandBuilder::new("journalctl")
+            .word("-u")
+            .arg(service)
+            .words(["-n", &lines, "--no-pager", "-o", "short-iso"]),
+        ServiceBackend::Service => return None,
+        ServiceBackend::Compose => CommandBuilder::new("dc")
+            .words(["logs", "--no-color", "--tail", &lines])
+            .arg(service),
+    };
+    Some(command.build())
+}
+
+/// Command that prints the state of `service` for the matching parser.
+#[must_use]
+pub fn status_command(backend: ServiceBackend, service: &str) -> String {
+    match backend {
+        ServiceBackend::Systemd => CommandBuilder::new("systemctl")
+            .word("show")
+            .arg(service)
+            .words(["--no-pager", "-p", "LoadState", "-p", "ActiveState"])
+            .words(["-p", "SubState", "-p", "UnitFileState"])
+            .words(["-p", "MainPID", "-p", "ActiveEnterTimestamp"])
+            .build(),
+        ServiceBackend::Service => format!(
+            "{}; echo \"EXIT=$?\"",
+            CommandBuilder::new("service")
+                .arg(service)
+                .word("status")
+                .stdout_to("/dev/null")
+                .stderr_to_stdout()
+        ),
+        ServiceBackend::Compose => format!(
+            "ids=$({}) || exit 1; for id in $ids; do \
+             docker inspect -f '{{{{.State.Status}}}} {{{{.State.Pid}}}} \
+             {{{{if .State.Health}}}}{{{{.State.Health.Status}}}}{{{{else}}}}-{{{{end}}}} \
+             {{{{.State.StartedAt}}}}' \"$id\"; done",
+            CommandBuilder::new("dc")
+                .words(["ps", "-a", "-q"])
+                .arg(service)
+        ),
+    }
+}
+
+/// Parse `systemctl show` output.
+///
+/// # Errors
+///
+/// Returns an error if systemd doesn't know the unit.
+pub fn parse_systemd_show(stdout: &str) -> Result<ServiceState, String> {
+    let property = |key: &str| {
+        stdout
+            .lines()
+            .find_map(|l| l.strip_prefix(key)?.strip_prefix('='))
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    };
+    if property("LoadState") == Some("not-found") {
+        return Err("no such systemd unit".to_string());
+    }
+    let state = property("ActiveState").unwrap_or("unknown").to_string();
+    Ok(ServiceState {
+        running: state == "active",
+        state,
+        detail: property("SubState").map(str::to_string),
+        enabled: property("UnitFileState").map(str::to_string),
+        pid: property("MainPID")
+            .and_then(|pid| pid.parse().ok())
+            .filter(|&pid| pid != 0),
+        since: property("ActiveEnterTimestamp").map(str::to_string),
+        containers: None,
+    })
+}
+
+/// Parse the exit code of `service <name> status`, which init scripts set
+/// per the LSB: 0 running, 3 stopped, 4 unknown service.
+///
+/// # Errors
+///
+/// Returns an error if the service doesn't exist.
+pub fn parse_service_status(stdout: &str) -> Result<ServiceState, String> {
+    let code = stdout
+        .lines()
+        .find_map(|l| l.strip_prefix("EXIT="))
+        .and_then(|code| code.trim().parse::<i32>().ok());
+    let (state, detail) = match code {
+        Some(0) => ("running", None),
+        Some(3) => ("stopped", None),
+        Some(4) => return Err("no such service".to_string()),
+        Some(code) => ("unknown", Some(format!("status exited with {code}"))),
+        None => ("unknown", None),
+    };
+    Ok(ServiceState {
+        state: state.to_string(),
+        running: state == "running",
+        detail,
+        ..ServiceState::default()
+    })
+}
+
+/// Parse one `docker inspect` line per container of a compose service:
+/// status, PID, health (`-` without a health check), and start time.
+#[must_use]
+pub fn parse_compose_state(stdout: &str) -> ServiceState {
+    let containers: Vec<Vec<&str>> = stdout
+        .lines()
+        .map(|l| l.split_whitespace().collect::<Vec<_>>())
+        .filter(|fields| fields.len() == 4)
+        .collect();
+    let Some(first) = containers.first() else {
+        return ServiceState {
+            state: "missing".to_string(),
+            containers: Some(0),
+            ..ServiceState::default()
+        };
+    };
+    let state = if containers.iter().all(|c| c[0] == first[0]) {
+        first[0].to_string()
+    } else {
+        "mixed".to_string()
+    };
+    ServiceState {
+        running: containers.iter().all(|c| c[0] == "running"),
+        state,
+        detail: (first[2] != "-").then(|| first[2].to_string()),
+        enabled: None,
+        pid: (containers.len() == 1)
+            .then(|| first[1].parse().ok())
+            .flatten()
+            .filter(|&pid| pid != 0),
+        since: Some(first[3].to_string()),
+        containers: Some(containers.len()),
+    }
+}
+
+/// Exit code plus the last few lines of stderr (or stdout when stderr is empty).
+fn failure_detail(exit_code: i32, stdout: &str, stderr: &str) -> String {
+    let output = if stderr.trim().is_empty() {
+        stdout
+    } else {
+        stderr
+    };
+    let lines: Vec<&str> = output.lines().filter(|l| !l.trim().is_empty()).collect();
+    let tail = lines[lines.len().saturating_sub(DETAIL_LINES)..].join("\n");
+    if tail.is_empty() {
+        format!("exit {exit_code}")
+    } else {
+        format!("exit {exit_code}: {tail}")
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RemoteServiceInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+    #[schemars(
+        description = "Service to act on: a systemd unit or init script name (e.g., 'nginx'), or a docker compose service"
+    )]
+    pub service: String,
+    #[schemars(description = "What to do: 'status', 'start', 'stop', 'restart', or 'logs'")]
+    pub action: ServiceAction,
+    #[schemars(
+        description = "Service manager: 'systemd', 'service' (init scripts), or 'compose' (docker compose). Detected on the remote when omitted"
+    )]
+    pub backend: Option<ServiceBackend>,
+    #[schemars(
+        description = "Directory holding the docker-compose.yml, for the compose backend. Setting it selects compose; defaults to the server's base path"
+    )]
+    pub project_dir: Option<String>,
+    #[schemars(description = "Log lines to return with action 'logs' (default: 100, max: 1000)")]
+    pub lines: Option<usize>,
+    #[schemars(
+        description = "Run the service manager through sudo -n (needs passwordless sudo; never prompts)"
+    )]
+    pub sudo: Option<bool>,
+    #[schemars(
+        description = "Reason for making this change outside the server's maintenance window. Only needed after a call was refused for that reason; the reason is logged"
+    )]
+    pub override_window: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceAction {
+    Status,
+    Start,
+    Stop,
+    Restart,
+    Logs,
+}
+
+impl ServiceAction {
+    /// Whether the action changes the service, rather than only reading it.
+    #[must_use]
+    pub fn is_mutation(self) -> bool {
+        matches!(self, Self::Start | Self::Stop | Self::Restart)
+    }
+}
+
+impl fmt::Display for ServiceAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Status => "status",
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Restart => "restart",
+            Self::Logs => "logs",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceBackend {
+    Systemd,
+    Service,
+    Compose,
+}
+
+impl fmt::Display for ServiceBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Systemd => "systemd",
+            Self::Service => "service",
+            Self::Compose => "compose",
+        })
+    }
+}
+
+/// State of a service as its manager reports it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ServiceState {
+    /// The manager's own word for the state: systemd's `ActiveState`
+    /// (`active`, `inactive`, `failed`, ...), `running` or `stopped` for init
+    /// scripts, or the container status for compose (`running`, `exited`,
+    /// `missing` when no container was created).
+    pub state: String,
+    pub running: bool,
+    /// systemd's `SubState` (e.g. `running`, `dead`) or the container's
+    /// health check status.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Whether the unit starts at boot, e.g. `enabled` or `disabled` (systemd only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// When the service entered its current state, as the manager prints it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    /// Containers of a compose service, which may be scaled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub containers: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemoteServiceOutput {
+    pub service: String,
+    pub backend: ServiceBackend,
+    pub action: ServiceAction,
+    /// State after the action.
+    pub state: ServiceState,
+    /// Recent log lines, oldest first, for action `logs`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logs: Option<String>,
+}
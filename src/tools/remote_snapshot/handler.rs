@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use super::schema::{RemoteSnapshotInput, RemoteSnapshotOutput, SnapshotEntry, SnapshotListOutput};
+use crate::connection::RemoteExecutor;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::{dir_snapshot, remote_error, tool_error, ErrorCode};
+
+/// Take a snapshot of `input.path` in `snapshot_dir`, or with `list`,
+/// list the snapshots already there.
+pub async fn handle<C: RemoteExecutor>(
+    conn: Arc<C>,
+    input: RemoteSnapshotInput,
+    snapshot_dir: &str,
+    timeouts: TimeoutPolicy,
+) -> String {
+    if input.path.trim_end_matches('/').is_empty() {
+        return tool_error(
+            ErrorCode::InvalidInput,
+            "refusing to snapshot the root directory; pick a directory below it",
+        );
+    }
+    if let Some(Err(e)) = input.label.as_deref().map(dir_snapshot::check_label) {
+        return tool_error(ErrorCode::InvalidInput, e.to_string());
+    }
+    let output = if input.list.unwrap_or(false) {
+        let snapshots =
+            match dir_snapshot::list_snapshots(conn.as_ref(), &input.path, snapshot_dir, timeouts)
+                .await
+            {
+                Ok(snapshots) => snapshots,
+                Err(e) => return remote_error(&e, e.to_string()),
+            };
+        serde_json::to_string_pretty(&SnapshotListOutput {
+            path: input.path,
+            snapshots: snapshots
+                .into_iter()
+                .map(|s| SnapshotEntry {
+                    snapshot: s.path,
+                    bytes: s.bytes,
+                })
+                .collect(),
+        })
+    } else {
+        let snapshot = match dir_snapshot::take_snapshot(
+            conn.as_ref(),
+            &input.path,
+            snapshot_dir,
+            input.label.as_deref(),
+            timeouts,
+        )
+        .await
+        {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => {
+                let message = format!("{} is not a directory", input.path);
+                return tool_error(ErrorCode::FileNotFound, message);
+            }
+            Err(e) => return remote_error(&e, e.to_string()),
+        };
+        serde_json::to_string_pretty(&RemoteSnapshotOutput {
+            path: input.path,
+            snapshot: snapshot.path,
+            bytes: snapshot.bytes,
+        })
+    };
+    output.unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
@@ -0,0 +1,50 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RemoteSnapshotInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+
+    #[schemars(
+        description = "Directory to snapshot (relative paths resolve from the connection's base path)"
+    )]
+    pub path: String,
+
+    #[schemars(
+        description = "Short label added to the snapshot's name, e.g. 'before-upgrade' (letters, digits, '.', '_', '-')"
+    )]
+    pub label: Option<String>,
+
+    #[schemars(
+        description = "List the directory's existing snapshots instead of taking one (default: false)"
+    )]
+    pub list: Option<bool>,
+
+    #[schemars(
+        description = "Reason for making this change outside the server's maintenance window. Only needed after a call was refused for that reason; the reason is logged"
+    )]
+    pub override_window: Option<String>,
+}
+
+/// One snapshot tarball.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SnapshotEntry {
+    /// Path of the tarball on the server, for `remote_restore`.
+    pub snapshot: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemoteSnapshotOutput {
+    pub path: String,
+    pub snapshot: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SnapshotListOutput {
+    pub path: String,
+    /// Oldest first.
+    pub snapshots: Vec<SnapshotEntry>,
+}
@@ -0,0 +1,209 @@
+//! Attributes and optional sha256 of many remote paths in one command.
+//!
+//! The paths go to the remote on stdin, one per line, so a long list can't
+//! overflow the command line. For each path that exists the script prints
+//! tagged lines keyed by the path's index: `S` with the attributes, `L` with
+//! a symbolic link's target, and `H` with a regular file's hash.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::schema::{EntryType, PathStat, RemoteStatInput, RemoteStatOutput};
+use crate::connection::{FileStat, RemoteCapabilities, RemoteExecutor};
+use crate::timeout::TimeoutPolicy;
+use crate::tools::{remote_error, tool_error, ErrorCode};
+use crate::utils::path::normalize_remote_path;
+
+/// Timeout for inspecting the paths (30 seconds).
+const STAT_TIMEOUT_MS: u64 = 30_000;
+
+/// Timeout when hashing, which reads every file in full (5 minutes), unless
+/// the server's [`TimeoutPolicy`] raises it.
+const HASH_TIMEOUT_MS: u64 = 300_000;
+
+/// Paths accepted per call.
+pub const MAX_PATHS: usize = 1000;
+
+/// Report the attributes of `input.paths` as JSON, using the stat variant
+/// and sha256 tool in `caps`.
+pub async fn handle<C: RemoteExecutor>(
+    conn: Arc<C>,
+    input: RemoteStatInput,
+    caps: RemoteCapabilities,
+    timeouts: TimeoutPolicy,
+) -> String {
+    if let Some(error) = conn.posix_required("remote_stat") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    if input.paths.is_empty() {
+        return tool_error(ErrorCode::InvalidInput, "paths must name at least one path");
+    }
+    if input.paths.len() > MAX_PATHS {
+        let message = format!(
+            "paths must list at most {MAX_PATHS} entries, got {}",
+            input.paths.len()
+        );
+        return tool_error(ErrorCode::InvalidInput, message);
+    }
+    if let Some(path) = input
+        .paths
+        .iter()
+        .find(|p| p.is_empty() || p.contains('\n'))
+    {
+        let message = format!("invalid path {path:?}: paths must be non-empty single lines");
+        return tool_error(ErrorCode::InvalidInput, message);
+    }
+
+    let hash = input.sha256.unwrap_or(false);
+    let mut stdin = String::new();
+    for path in &input.paths {
+        stdin.push_str(&normalize_remote_path(path, conn.remote_path()));
+        stdin.push('\n');
+    }
+    let command = stat_command(caps.file_stat, hash.then(|| caps.sha256_command()));
+    let timeout = if hash {
+        timeouts.operation(HASH_TIMEOUT_MS)
+    } else {
+        Some(STAT_TIMEOUT_MS)
+    };
+    let result = match conn
+        .exec_raw(&command, Some(stdin.as_bytes()), timeout)
+        .await
+    {
+        Ok(result) => result,
+        Err(e) => return remote_error(&e, format!("Error inspecting paths: {e}")),
+    };
+    if result.exit_code != 0 {
+        let message = format!("Error inspecting paths: {}", result.stderr.trim());
+        return tool_error(ErrorCode::Failed, message);
+    }
+    let paths = parse_stat(&String::from_utf8_lossy(&result.stdout), input.paths, hash);
+    serde_json::to_string_pretty(&RemoteStatOutput { paths })
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
+
+/// Shell script that reads one path per line from stdin and prints the
+/// tagged lines described in the module docs. `sha256` is the command that
+/// hashes stdin, if hashes are wanted. Symbolic links are not followed.
+#[must_use]
+pub fn stat_command(file_stat: FileStat, sha256: Option<&str>) -> String {
+    let stat = match file_stat {
+        FileStat::FindPrintf => "find \"$p\" -maxdepth 0 -printf '%s %T@ %m %u %g %y\\n'",
+        FileStat::StatC => "stat -c '%s %Y %a %U %G %F' -- \"$p\"",
+        FileStat::StatF => "stat -f '%z %m %Mp%Lp %Su %Sg %HT' -- \"$p\"",
+    };
+    let hash = sha256.map_or_else(String::new, |sha256| {
+        format!(
+            " elif [ -f \"$p\" ]; then printf 'H%s\\t' \"$i\"; \
+             {{ {sha256} < \"$p\" 2>/dev/null || echo; }} | cut -d' ' -f1;"
+        )
+    });
+    format!(
+        "i=0; while IFS= read -r p; do \
+         case $p in \"~\") p=$HOME ;; \"~/\"*) p=$HOME/${{p#\"~/\"}} ;; esac; \
+         if [ -e \"$p\" ] || [ -L \"$p\" ]; then \
+         printf 'S%s\\t' \"$i\"; {stat} 2>/dev/null || echo; \
+         if [ -L \"$p\" ]; then printf 'L%s\\t' \"$i\"; readlink -- \"$p\" || echo;{hash} fi; \
+         fi; i=$((i + 1)); done"
+    )
+}
+
+/// Match the output of [`stat_command`] to `paths`, in their order. Paths
+/// without an `S` line don't exist; with `hash`, regular files without a
+/// usable `H` line get an error instead of a hash.
+#[must_use]
+pub fn parse_stat(stdout: &str, paths: Vec<String>, hash: bool) -> Vec<PathStat> {
+    let mut lines: HashMap<(char, usize), &str> = HashMap::new();
+    for line in stdout.lines() {
+        let mut chars = line.chars();
+        let Some(tag) = chars.next() else { continue };
+        let Some((index, value)) = chars.as_str().split_once('\t') else {
+            continue;
+        };
+        if let Ok(index) = index.parse() {
+            lines.insert((tag, index), value);
+        }
+    }
+
+    paths
+        .into_iter()
+        .enumerate()
+        .map(|(i, path)| {
+            let Some(attrs) = lines.get(&('S', i)) else {
+                return PathStat {
+                    path,
+                    exists: false,
+                    ..PathStat::default()
+                };
+            };
+            let mut stat = parse_attributes(attrs).unwrap_or_else(|| PathStat {
+                error: Some("could not read its attributes".to_string()),
+                ..PathStat::default()
+            });
+            stat.path = path;
+            stat.exists = true;
+            stat.link_target = lines.get(&('L', i)).map(|target| (*target).to_string());
+            if hash && stat.kind == Some(EntryType::File) {
+                match lines.get(&('H', i)) {
+                    Some(hex) if is_sha256(hex) => stat.sha256 = Some((*hex).to_string()),
+                    _ => stat.error = Some("could not read it to hash".to_string()),
+                }
+            }
+            stat
+        })
+        .collect()
+}
+
+/// Parse `<size> <mtime> <mode> <owner> <group> <type>`. The type is last
+/// because `stat` spells it in words.
+fn parse_attributes(line: &str) -> Option<PathStat> {
+    let mut fields = line.splitn(6, ' ');
+    let size = fields.next()?.parse().ok()?;
+    // `find -printf %T@` adds a fraction
+    let mtime = fields.next()?.split('.').next()?.parse().ok()?;
+    let mode = u32::from_str_radix(fields.next()?, 8).ok()?;
+    let owner = fields.next()?.to_string();
+    let group = fields.next()?.to_string();
+    let kind = entry_type(fields.next()?);
+    Some(PathStat {
+        kind: Some(kind),
+        size: Some(size),
+        mtime: Some(mtime),
+        mode: Some(format!("{mode:03o}")),
+        owner: Some(owner),
+        group: Some(group),
+        ..PathStat::default()
+    })
+}
+
+/// Entry type from a `find %y` letter or the words printed by GNU, busybox,
+/// and BSD `stat`.
+fn entry_type(text: &str) -> EntryType {
+    let text = text.trim().to_lowercase();
+    match text.as_str() {
+        "f" => return EntryType::File,
+        "d" => return EntryType::Dir,
+        "l" => return EntryType::Symlink,
+        "p" => return EntryType::Fifo,
+        "s" => return EntryType::Socket,
+        "c" => return EntryType::CharDevice,
+        "b" => return EntryType::BlockDevice,
+        _ => {}
+    }
+    [
+        ("regular", EntryType::File),
+        ("directory", EntryType::Dir),
+        ("symbolic", EntryType::Symlink),
+        ("fifo", EntryType::Fifo),
+        ("socket", EntryType::Socket),
+        ("character", EntryType::CharDevice),
+        ("block", EntryType::BlockDevice),
+    ]
+    .into_iter()
+    .find(|(word, _)| text.contains(word))
+    .map_or(EntryType::Other, |(_, kind)| kind)
+}
+
+fn is_sha256(text: &str) -> bool {
+    text.len() == 64 && text.bytes().all(|b| b.is_ascii_hexdigit())
+}
@@ -0,0 +1,68 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RemoteStatInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+    #[schemars(
+        description = "Paths to inspect (relative paths resolve from the connection's base path). At most 1000 per call"
+    )]
+    pub paths: Vec<String>,
+    #[schemars(
+        description = "Also hash each regular file with sha256 (default: false). Reads every file in full, so leave it off for large files unless the content matters"
+    )]
+    pub sha256: Option<bool>,
+}
+
+/// Kind of filesystem entry. Symbolic links are reported as themselves,
+/// not followed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryType {
+    File,
+    Dir,
+    Symlink,
+    Fifo,
+    Socket,
+    CharDevice,
+    BlockDevice,
+    Other,
+}
+
+/// What the remote reported for one requested path.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PathStat {
+    /// The path as requested.
+    pub path: String,
+    pub exists: bool,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<EntryType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Modification time in whole seconds since the epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<u64>,
+    /// Permission bits in octal, e.g. `644` or `4755`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Where a symbolic link points.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<String>,
+    /// Hex sha256 of a regular file's content, when requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Why the path exists but couldn't be inspected or hashed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemoteStatOutput {
+    /// One entry per requested path, in request order.
+    pub paths: Vec<PathStat>,
+}
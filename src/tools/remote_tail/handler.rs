@@ -0,0 +1,210 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rmcp::model::{ProgressNotificationParam, ProgressToken};
+use rmcp::service::RequestContext;
+use rmcp::RoleServer;
+
+use super::schema::{RemoteTailInput, RemoteTailOutput};
+use crate::connection::{read_failure, read_failure_check, RemoteExecutor};
+use crate::tools::{remote_error, tool_error, ErrorCode};
+use crate::utils::output::{fit_output, MAX_INLINE_OUTPUT};
+use crate::utils::path::{normalize_remote_path, shell_escape_remote_path};
+
+/// Default number of trailing lines returned.
+const DEFAULT_LINES: u64 = 50;
+
+/// Maximum follow duration (2 minutes).
+const MAX_FOLLOW_SECS: u64 = 120;
+
+/// Interval between polls while following.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Timeout for each tail/poll command (30 seconds).
+const TAIL_TIMEOUT_MS: u64 = 30_000;
+
+/// Maximum bytes read per poll.
+pub const MAX_CHUNK_BYTES: u64 = 64 * 1024;
+
+/// Stop following once this many bytes have been collected, the same cap
+/// as any inline output.
+const MAX_FOLLOW_OUTPUT: usize = MAX_INLINE_OUTPUT;
+
+/// Return the last N lines of a remote file, optionally following it.
+///
+/// Following polls the file size and reads only the bytes appended since the
+/// previous poll, so it works for any file — including the background-job
+/// logs `remote_bash` creates — without holding a long-lived channel open.
+/// Each batch of new lines is sent as a progress notification when the client
+/// supplied a progress token. Cancellation stops following early.
+pub async fn handle<C: RemoteExecutor>(
+    conn: Arc<C>,
+    input: RemoteTailInput,
+    ctx: RequestContext<RoleServer>,
+) -> String {
+    if let Some(error) = conn.posix_required("remote_tail") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    let base_path = conn.remote_path().to_string();
+    let file_path = normalize_remote_path(&input.file_path, &base_path);
+    let path = shell_escape_remote_path(&file_path);
+    let lines = input.lines.unwrap_or(DEFAULT_LINES);
+
+    // Print the size first, then tail exactly that many bytes so the follow
+    // offset lines up with what was returned.
+    let command = format!(
+        "{{ s=$(wc -c < {path}) && echo \"$s\" && head -c \"$s\" {path} | tail -n {lines}; }} || {}",
+        read_failure_check(&file_path)
+    );
+    let (mut offset, content) = match conn.exec(&command, Some(TAIL_TIMEOUT_MS)).await {
+        Ok(result) if result.exit_code == 0 => {
+            let Some((size, rest)) = split_size_line(&result.stdout) else {
+                let message = format!("Error tailing file: unexpected output: {}", result.stdout);
+                return tool_error(ErrorCode::Failed, message);
+            };
+            (size, rest.to_string())
+        }
+        Ok(result) => {
+            let error = read_failure(result.exit_code, &result.stderr);
+            let message = format!("Error tailing file: {}", result.stderr.trim());
+            return remote_error(&error, message);
+        }
+        Err(e) => return remote_error(&e, format!("Error tailing file: {e}")),
+    };
+
+    let follow_secs = input.follow_seconds.unwrap_or(0).min(MAX_FOLLOW_SECS);
+    if follow_secs == 0 {
+        return to_json(RemoteTailOutput {
+            content,
+            followed: None,
+            truncated: false,
+            output_capped: false,
+        })
+        .await;
+    }
+
+    let progress_token = ctx.meta.get_progress_token();
+    let deadline = Instant::now() + Duration::from_secs(follow_secs);
+    let mut followed = String::new();
+    let mut truncated = false;
+    let mut output_capped = false;
+    let mut batches: u32 = 0;
+
+    while Instant::now() < deadline {
+        tokio::select! {
+            () = ctx.ct.cancelled() => break,
+            () = tokio::time::sleep(POLL_INTERVAL) => {}
+        }
+
+        let command = format!(
+            "s=$(wc -c < {path}) && echo \"$s\" && \
+             if [ \"$s\" -ge {offset} ]; then tail -c +{start} {path} | head -c {MAX_CHUNK_BYTES}; fi",
+            start = offset + 1,
+        );
+        // Raw bytes, so the offset counts what the file holds even where
+        // it isn't valid UTF-8
+        let result = match conn.exec_raw(&command, None, Some(TAIL_TIMEOUT_MS)).await {
+            Ok(result) if result.exit_code == 0 => result,
+            Ok(result) => {
+                let message = format!("Error following file: {}", result.stderr.trim());
+                return tool_error(ErrorCode::Failed, message);
+            }
+            Err(e) => return remote_error(&e, format!("Error following file: {e}")),
+        };
+        let Some((size, chunk)) = split_size_bytes(&result.stdout) else {
+            continue;
+        };
+
+        if size < offset {
+            // Rotated or truncated — start over from the top of the new file.
+            truncated = true;
+            offset = 0;
+            continue;
+        }
+
+        let end = follow_chunk_end(chunk);
+        if end == 0 {
+            continue;
+        }
+        offset += end as u64;
+        let complete = String::from_utf8_lossy(&chunk[..end]);
+        followed.push_str(&complete);
+
+        if let Some(token) = &progress_token {
+            batches += 1;
+            notify_lines(&ctx, token, batches, &complete).await;
+        }
+
+        if followed.len() >= MAX_FOLLOW_OUTPUT {
+            output_capped = true;
+            break;
+        }
+    }
+
+    to_json(RemoteTailOutput {
+        content,
+        followed: Some(followed),
+        truncated,
+        output_capped,
+    })
+    .await
+}
+
+/// Split `"<size>\n<rest>"` into the size and the remaining output.
+fn split_size_line(stdout: &str) -> Option<(u64, &str)> {
+    let (size, rest) = stdout.split_once('\n').unwrap_or((stdout, ""));
+    Some((size.trim().parse().ok()?, rest))
+}
+
+/// [`split_size_line`] for raw output.
+fn split_size_bytes(stdout: &[u8]) -> Option<(u64, &[u8])> {
+    let newline = stdout.iter().position(|&b| b == b'\n');
+    let (size, rest) = newline.map_or((stdout, &[][..]), |i| (&stdout[..i], &stdout[i + 1..]));
+    Some((std::str::from_utf8(size).ok()?.trim().parse().ok()?, rest))
+}
+
+/// How many bytes of a follow poll's `chunk` to consume: through its last
+/// newline, leaving a partial line for the next poll. A full chunk without
+/// a newline is part of an over-long line and is taken whole, up to its
+/// last complete UTF-8 character, so following doesn't stall on it.
+#[must_use]
+pub fn follow_chunk_end(chunk: &[u8]) -> usize {
+    if let Some(newline) = chunk.iter().rposition(|&b| b == b'\n') {
+        return newline + 1;
+    }
+    if (chunk.len() as u64) < MAX_CHUNK_BYTES {
+        return 0;
+    }
+    match std::str::from_utf8(chunk) {
+        Err(e) if e.error_len().is_none() && e.valid_up_to() > 0 => e.valid_up_to(),
+        _ => chunk.len(),
+    }
+}
+
+async fn notify_lines(
+    ctx: &RequestContext<RoleServer>,
+    token: &ProgressToken,
+    batch: u32,
+    lines: &str,
+) {
+    let param = ProgressNotificationParam {
+        progress_token: token.clone(),
+        progress: f64::from(batch),
+        total: None,
+        message: Some(lines.trim_end().to_string()),
+    };
+    if let Err(e) = ctx.peer.notify_progress(param).await {
+        tracing::debug!("Failed to send tail progress notification: {e}");
+    }
+}
+
+/// Serialize `output`, saving `content` or `followed` to disk and
+/// summarizing it when it is too large to return inline.
+async fn to_json(mut output: RemoteTailOutput) -> String {
+    output.content = fit_output(output.content, "remote_tail").await;
+    if let Some(followed) = output.followed.take() {
+        output.followed = Some(fit_output(followed, "remote_tail").await);
+    }
+    serde_json::to_string_pretty(&output)
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
@@ -0,0 +1,37 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RemoteTailInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+
+    #[schemars(
+        description = "Path to the file to tail, e.g. a build log or the log_file returned by a background remote_bash command"
+    )]
+    pub file_path: String,
+
+    #[schemars(description = "Number of lines to return from the end of the file. Defaults to 50")]
+    pub lines: Option<u64>,
+
+    #[schemars(
+        description = "Keep following the file for this many seconds and return lines appended during that time. New lines are also streamed as progress notifications when the client supports them. Defaults to 0 (no follow), max 120"
+    )]
+    pub follow_seconds: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemoteTailOutput {
+    /// The last `lines` lines of the file at the time of the call.
+    pub content: String,
+    /// Lines appended while following. Absent when not following.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub followed: Option<String>,
+    /// Set when the file shrank while following (rotated or truncated);
+    /// reading restarted from the beginning of the new file.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Set when following stopped early because the output cap was reached.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub output_capped: bool,
+}
@@ -0,0 +1,205 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rmcp::model::ProgressNotificationParam;
+use rmcp::service::RequestContext;
+use rmcp::RoleServer;
+
+use super::schema::{RemoteWaitForInput, RemoteWaitForOutput};
+use crate::connection::RemoteExecutor;
+use crate::tools::{remote_error, tool_error, ErrorCode};
+use crate::utils::path::{normalize_remote_path, shell_escape, shell_escape_remote_path};
+
+/// Default time to wait (1 minute).
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// Longest allowed wait (10 minutes, like a foreground `remote_bash`).
+const MAX_TIMEOUT_SECS: u64 = 600;
+
+/// Default time between checks.
+const DEFAULT_INTERVAL_SECS: u64 = 1;
+
+/// Timeout for one check. Port and URL checks give up on their own after
+/// [`CONNECT_TIMEOUT_SECS`]; this only catches a check that hangs anyway.
+const CHECK_TIMEOUT_MS: u64 = 15_000;
+
+/// How long a single connection attempt may take.
+const CONNECT_TIMEOUT_SECS: u64 = 5;
+
+/// Exit code of a check when the remote lacks the tools to run it.
+const EXIT_MISSING_TOOL: i32 = 127;
+
+/// What to wait for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// A TCP port accepting connections.
+    Port { host: String, port: u16 },
+    /// A URL answering with a 2xx or 3xx status.
+    Url(String),
+    /// A file or directory existing.
+    Path(String),
+}
+
+impl Condition {
+    /// The condition `input` asks for, with `path` resolved from `base_path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message when not exactly one of `port`, `url`, and `path`
+    /// is set, or `host` comes without `port`.
+    pub fn from_input(input: &RemoteWaitForInput, base_path: &str) -> Result<Self, String> {
+        let set = [
+            input.port.is_some(),
+            input.url.is_some(),
+            input.path.is_some(),
+        ];
+        if set.iter().filter(|&&s| s).count() != 1 {
+            return Err("pass exactly one of port, url, and path".to_string());
+        }
+        if input.host.is_some() && input.port.is_none() {
+            return Err("host must come with port".to_string());
+        }
+        if let Some(port) = input.port {
+            let host = input.host.as_deref().unwrap_or("localhost").to_string();
+            return Ok(Self::Port { host, port });
+        }
+        if let Some(url) = &input.url {
+            return Ok(Self::Url(url.clone()));
+        }
+        let path = input.path.as_deref().unwrap_or_default();
+        Ok(Self::Path(normalize_remote_path(path, base_path)))
+    }
+
+    /// Shell command that exits 0 once the condition holds, and
+    /// [`EXIT_MISSING_TOOL`] when the remote can't check it.
+    #[must_use]
+    pub fn check_command(&self) -> String {
+        match self {
+            Self::Port { host, port } => format!(
+                "h={}; p={port}; t=; \
+                 command -v timeout >/dev/null 2>&1 && t='timeout {CONNECT_TIMEOUT_SECS}'; \
+                 if command -v bash >/dev/null 2>&1; then \
+                 exec $t bash -c 'exec 3<>\"/dev/tcp/$0/$1\"' \"$h\" \"$p\"; \
+                 elif command -v nc >/dev/null 2>&1; then \
+                 exec nc -z -w {CONNECT_TIMEOUT_SECS} \"$h\" \"$p\"; \
+                 else echo 'neither bash nor nc is installed' >&2; exit {EXIT_MISSING_TOOL}; fi",
+                shell_escape(host)
+            ),
+            Self::Url(url) => format!(
+                "u={}; \
+                 if command -v curl >/dev/null 2>&1; then \
+                 exec curl -fsS -o /dev/null --max-time {CONNECT_TIMEOUT_SECS} -- \"$u\"; \
+                 elif command -v wget >/dev/null 2>&1; then \
+                 exec wget -q -O /dev/null -T {CONNECT_TIMEOUT_SECS} -- \"$u\"; \
+                 else echo 'neither curl nor wget is installed' >&2; exit {EXIT_MISSING_TOOL}; fi",
+                shell_escape(url)
+            ),
+            Self::Path(path) => format!("[ -e {} ]", shell_escape_remote_path(path)),
+        }
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Port { host, port } => write!(f, "port {host}:{port}"),
+            Self::Url(url) => write!(f, "url {url}"),
+            Self::Path(path) => write!(f, "path {path}"),
+        }
+    }
+}
+
+/// Check a condition on the remote every `interval_seconds` until it holds
+/// or `timeout_seconds` pass. When the client supplied a progress token,
+/// each failed check sends a progress notification, which also keeps the
+/// client from giving up on a long wait. Cancellation stops waiting.
+///
+/// Returns a JSON-serialized [`RemoteWaitForOutput`], or an error when the
+/// wait times out.
+pub async fn handle<C: RemoteExecutor>(
+    conn: Arc<C>,
+    input: RemoteWaitForInput,
+    ctx: RequestContext<RoleServer>,
+) -> String {
+    if let Some(error) = conn.posix_required("remote_wait_for") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    let condition = match Condition::from_input(&input, conn.remote_path()) {
+        Ok(condition) => condition,
+        Err(e) => return tool_error(ErrorCode::InvalidInput, e),
+    };
+    let timeout = Duration::from_secs(
+        input
+            .timeout_seconds
+            .unwrap_or(DEFAULT_TIMEOUT_SECS)
+            .min(MAX_TIMEOUT_SECS),
+    );
+    let interval = Duration::from_secs(
+        input
+            .interval_seconds
+            .unwrap_or(DEFAULT_INTERVAL_SECS)
+            .max(1),
+    );
+    let command = condition.check_command();
+    let token = ctx.meta.get_progress_token();
+
+    let started = Instant::now();
+    let mut attempts: u32 = 0;
+    loop {
+        attempts += 1;
+        let last = match conn.exec(&command, Some(CHECK_TIMEOUT_MS)).await {
+            Ok(result) if result.exit_code == 0 => break,
+            Ok(result) if result.exit_code == EXIT_MISSING_TOOL => {
+                let message = format!(
+                    "cannot check {condition} on this server: {}",
+                    result.stderr.trim()
+                );
+                return tool_error(ErrorCode::NotSupported, message);
+            }
+            Ok(result) => result.stderr.trim().to_string(),
+            Err(e) => return remote_error(&e, format!("Error checking {condition}: {e}")),
+        };
+        let elapsed = started.elapsed();
+        if elapsed + interval > timeout {
+            let last = if last.is_empty() {
+                String::new()
+            } else {
+                format!(". Last check: {last}")
+            };
+            let message = format!(
+                "timed out after {}s waiting for {condition} ({attempts} checks){last}",
+                elapsed.as_secs()
+            );
+            return tool_error(ErrorCode::Timeout, message);
+        }
+        if let Some(token) = &token {
+            let param = ProgressNotificationParam {
+                progress_token: token.clone(),
+                progress: f64::from(attempts),
+                total: None,
+                message: Some(format!(
+                    "Waiting for {condition} ({}s elapsed)",
+                    elapsed.as_secs()
+                )),
+            };
+            if let Err(e) = ctx.peer.notify_progress(param).await {
+                tracing::debug!("Failed to send wait progress notification: {e}");
+            }
+        }
+        tokio::select! {
+            () = ctx.ct.cancelled() => {
+                let message = format!("waiting for {condition} was cancelled by the client");
+                return tool_error(ErrorCode::Cancelled, message);
+            }
+            () = tokio::time::sleep(interval) => {}
+        }
+    }
+
+    let output = RemoteWaitForOutput {
+        condition: condition.to_string(),
+        attempts,
+        elapsed_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+    };
+    serde_json::to_string_pretty(&output)
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
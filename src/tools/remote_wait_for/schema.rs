@@ -0,0 +1,43 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RemoteWaitForInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+
+    #[schemars(
+        description = "Wait until this TCP port accepts connections. Checked from the server itself, against host"
+    )]
+    pub port: Option<u16>,
+
+    #[schemars(
+        description = "Host to check port on, as seen from the server. Defaults to localhost"
+    )]
+    pub host: Option<String>,
+
+    #[schemars(
+        description = "Wait until this URL answers with a 2xx or 3xx status, fetched from the server with curl (or wget)"
+    )]
+    pub url: Option<String>,
+
+    #[schemars(
+        description = "Wait until this file or directory exists (relative paths resolve from the connection's base path)"
+    )]
+    pub path: Option<String>,
+
+    #[schemars(description = "Give up after this many seconds. Defaults to 60, max 600")]
+    pub timeout_seconds: Option<u64>,
+
+    #[schemars(description = "Seconds between checks. Defaults to 1")]
+    pub interval_seconds: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemoteWaitForOutput {
+    /// What was waited for, e.g. `port localhost:8080`.
+    pub condition: String,
+    /// Checks run, the successful one included.
+    pub attempts: u32,
+    pub elapsed_ms: u64,
+}
@@ -0,0 +1,258 @@
+//! Snapshots of the files matching a glob, kept in memory so a later call
+//! can report what changed without syncing or re-reading anything.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+use super::schema::{RemoteWatchInput, RemoteWatchOutput};
+use crate::connection::SshConnection;
+use crate::tools::{remote_error, tool_error, ErrorCode};
+use crate::utils::glob::GlobPattern;
+use crate::utils::path::{shell_escape, shell_escape_remote_path};
+
+/// Timeout for listing the watched files (30 seconds).
+const WATCH_TIMEOUT_MS: u64 = 30_000;
+
+/// Files listed per snapshot at most, as for `remote_glob`.
+const SCAN_LIMIT: usize = 200_000;
+
+/// Snapshots kept; the oldest tokens stop working beyond this.
+const MAX_SNAPSHOTS: usize = 64;
+
+/// Paths reported per change list.
+const MAX_CHANGES: usize = 500;
+
+/// Hex characters in a token.
+const TOKEN_LEN: usize = 16;
+
+/// Size and modification time of a file, as the remote reported them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileVersion {
+    pub size: u64,
+    /// Seconds since the epoch, as printed by `find -printf %T@` or `stat`.
+    pub mtime: String,
+}
+
+/// The matching files of one `remote_watch` call.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    server: String,
+    path: String,
+    pattern: String,
+    files: BTreeMap<String, FileVersion>,
+}
+
+/// Paths added, removed, and modified between two snapshots, in path order.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+/// Recent snapshots by token, oldest first.
+#[derive(Debug, Default)]
+pub struct WatchStore {
+    snapshots: Mutex<VecDeque<(String, Arc<Snapshot>)>>,
+    issued: AtomicU64,
+}
+
+impl WatchStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn get(&self, token: &str) -> Option<Arc<Snapshot>> {
+        self.snapshots
+            .lock()
+            .await
+            .iter()
+            .find(|(t, _)| t == token)
+            .map(|(_, snapshot)| Arc::clone(snapshot))
+    }
+
+    /// Keep `snapshot` and return its new token.
+    async fn insert(&self, snapshot: Snapshot) -> String {
+        let issued = self.issued.fetch_add(1, Ordering::Relaxed);
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let seed = format!(
+            "{}\0{}\0{}\0{nanos}\0{issued}",
+            snapshot.server, snapshot.path, snapshot.pattern
+        );
+        let token = hex::encode(Sha256::digest(seed.as_bytes()))[..TOKEN_LEN].to_string();
+
+        let mut snapshots = self.snapshots.lock().await;
+        if snapshots.len() >= MAX_SNAPSHOTS {
+            snapshots.pop_front();
+        }
+        snapshots.push_back((token.clone(), Arc::new(snapshot)));
+        token
+    }
+}
+
+/// Snapshot the files matching `input.pattern` and, with `since`, report
+/// what changed after that earlier snapshot.
+pub async fn handle(
+    conn: Arc<SshConnection>,
+    store: &WatchStore,
+    input: RemoteWatchInput,
+) -> String {
+    if let Some(error) = conn.posix_required("remote_watch") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    let glob = match GlobPattern::new(&input.pattern) {
+        Ok(glob) => glob,
+        Err(e) => {
+            return tool_error(
+                ErrorCode::InvalidInput,
+                format!("Invalid glob pattern: {e}"),
+            )
+        }
+    };
+    let path = input.path.unwrap_or_else(|| conn.remote_path().to_string());
+
+    let previous = match &input.since {
+        Some(token) => match store.get(token).await {
+            Some(snapshot) if snapshot.server == input.server
+                && snapshot.path == path
+                && snapshot.pattern == input.pattern =>
+            {
+                Some(snapshot)
+            }
+            Some(_) => {
+                return tool_error(
+                    ErrorCode::InvalidInput,
+                    "the 'since' token was issued for a different server, path, or pattern",
+                )
+            }
+            None => {
+                return tool_error(
+                    ErrorCode::NotFound,
+                    "unknown or expired 'since' token. Call remote_watch without 'since' to take a new snapshot",
+                )
+            }
+        },
+        None => None,
+    };
+
+    let stat_action = conn.capabilities().await.stat_action(true);
+    let command = watch_command(&path, glob.base_dir(), stat_action);
+    let result = match conn.exec(&command, Some(WATCH_TIMEOUT_MS)).await {
+        Ok(result) => result,
+        Err(e) => return remote_error(&e, format!("Error listing files: {e}")),
+    };
+    // find piped through head can exit non-zero on success (SIGPIPE)
+    if result.exit_code != 0 && !result.stderr.is_empty() {
+        let message = format!("Error listing files: {}", result.stderr.trim());
+        return tool_error(ErrorCode::Failed, message);
+    }
+    let listed = result.stdout.lines().count();
+    let files = parse_watch_listing(&result.stdout, &glob);
+
+    let mut output = RemoteWatchOutput {
+        token: String::new(),
+        files: files.len(),
+        changed: None,
+        added: Vec::new(),
+        removed: Vec::new(),
+        modified: Vec::new(),
+        truncated: false,
+        note: (listed >= SCAN_LIMIT).then(|| {
+            "The directory tree was too large to list completely. Narrow 'path' or start the pattern with a literal directory.".to_string()
+        }),
+    };
+    if let Some(previous) = previous {
+        let changes = diff_snapshots(&previous.files, &files);
+        output.changed = Some(!changes.is_empty());
+        let mut cut = |list: Vec<String>| {
+            output.truncated |= list.len() > MAX_CHANGES;
+            list.into_iter().take(MAX_CHANGES).collect::<Vec<_>>()
+        };
+        let (added, removed, modified) = (
+            cut(changes.added),
+            cut(changes.removed),
+            cut(changes.modified),
+        );
+        output.added = added;
+        output.removed = removed;
+        output.modified = modified;
+    }
+    output.token = store
+        .insert(Snapshot {
+            server: input.server,
+            path,
+            pattern: input.pattern,
+            files,
+        })
+        .await;
+    serde_json::to_string_pretty(&output)
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
+
+/// Command listing `<size> <mtime> <path>` for every file below `start`
+/// (relative to `path`), using the remote's `stat_action`.
+#[must_use]
+pub fn watch_command(path: &str, start: &str, stat_action: &str) -> String {
+    let start = if start.is_empty() {
+        ".".to_string()
+    } else {
+        shell_escape(start)
+    };
+    format!(
+        "cd {} && find {start} -type f {stat_action} 2>/dev/null | head -n {SCAN_LIMIT}",
+        shell_escape_remote_path(path),
+    )
+}
+
+/// Parse `<size> <mtime> <path>` lines, keeping the paths matching `glob`.
+#[must_use]
+pub fn parse_watch_listing(stdout: &str, glob: &GlobPattern) -> BTreeMap<String, FileVersion> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ' ');
+            let size = fields.next()?.parse().ok()?;
+            let mtime = fields.next()?.to_string();
+            let path = fields.next()?.trim_start_matches("./");
+            (!path.is_empty() && glob.is_match(path))
+                .then(|| (path.to_string(), FileVersion { size, mtime }))
+        })
+        .collect()
+}
+
+/// What changed from `old` to `new`.
+#[must_use]
+pub fn diff_snapshots(
+    old: &BTreeMap<String, FileVersion>,
+    new: &BTreeMap<String, FileVersion>,
+) -> Changes {
+    let mut changes = Changes::default();
+    for (path, version) in new {
+        match old.get(path) {
+            None => changes.added.push(path.clone()),
+            Some(before) if before != version => changes.modified.push(path.clone()),
+            Some(_) => {}
+        }
+    }
+    changes.removed = old
+        .keys()
+        .filter(|path| !new.contains_key(*path))
+        .cloned()
+        .collect();
+    changes
+}
+
+impl Changes {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
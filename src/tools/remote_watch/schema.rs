@@ -0,0 +1,47 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RemoteWatchInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+
+    #[schemars(
+        description = "Glob pattern of the files to watch, as for remote_glob: *, ?, ** (any number of directories), [abc], {a,b}. A pattern without '/' matches file names at any depth"
+    )]
+    pub pattern: String,
+
+    #[schemars(
+        description = "The directory to watch in. If not specified, uses the connection's base path"
+    )]
+    pub path: Option<String>,
+
+    #[schemars(
+        description = "Token from an earlier remote_watch call with the same server, pattern, and path. Reports what changed since then. Omit to take the first snapshot"
+    )]
+    pub since: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemoteWatchOutput {
+    /// Pass as `since` on the next call to see what changed after this one.
+    pub token: String,
+    /// Files matching the pattern now.
+    pub files: usize,
+    /// Whether anything changed since the `since` snapshot. Absent on the
+    /// first call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub added: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String>,
+    /// Files whose size or modification time changed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub modified: Vec<String>,
+    /// Whether the change lists were cut to their first entries.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use super::schema::{RemoteWhoamiInput, RemoteWhoamiOutput, SudoAccess};
+use crate::connection::RemoteExecutor;
+use crate::tools::{remote_error, tool_error, ErrorCode};
+
+/// Timeout for the identity probe (15 seconds).
+const WHOAMI_TIMEOUT_MS: u64 = 15_000;
+
+/// Prints `KEY=VALUE` lines. `sudo -n` never prompts: it fails immediately
+/// when a password would be needed, and its message tells the cases apart.
+const WHOAMI_COMMAND: &str = concat!(
+    r#"echo "USER=$(id -un)"; "#,
+    r#"echo "UID=$(id -u)"; "#,
+    r#"echo "GID=$(id -g)"; "#,
+    r#"echo "GROUPS=$(id -Gn)"; "#,
+    r#"echo "HOME=$HOME"; "#,
+    r#"echo "UMASK=$(umask)"; "#,
+    r#"if ! command -v sudo >/dev/null 2>&1; then echo "SUDO=missing"; "#,
+    r#"elif out=$(sudo -n true 2>&1); then echo "SUDO=ok"; "#,
+    r#"else echo "SUDO=$(printf '%s\n' "$out" | head -n 1)"; fi"#,
+);
+
+/// Report the login user's identity, groups, umask, and sudo access.
+pub async fn handle<C: RemoteExecutor>(conn: Arc<C>, _input: RemoteWhoamiInput) -> String {
+    if let Some(error) = conn.posix_required("remote_whoami") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    match conn.exec(WHOAMI_COMMAND, Some(WHOAMI_TIMEOUT_MS)).await {
+        Ok(result) => serde_json::to_string_pretty(&parse_whoami(&result.stdout))
+            .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#)),
+        Err(e) => remote_error(&e, format!("Error running identity probe: {e}")),
+    }
+}
+
+/// Parse the `KEY=VALUE` output of the identity probe.
+#[must_use]
+pub fn parse_whoami(stdout: &str) -> RemoteWhoamiOutput {
+    let mut output = RemoteWhoamiOutput::default();
+    for line in stdout.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "USER" => output.user = value.to_string(),
+            "UID" => output.uid = value.parse().ok(),
+            "GID" => output.gid = value.parse().ok(),
+            "GROUPS" => output.groups = value.split_whitespace().map(String::from).collect(),
+            "HOME" => output.home = value.to_string(),
+            "UMASK" => output.umask = value.to_string(),
+            "SUDO" => output.sudo = parse_sudo(value),
+            _ => {}
+        }
+    }
+    output.root = output.uid == Some(0);
+    output
+}
+
+fn parse_sudo(value: &str) -> SudoAccess {
+    let lower = value.to_ascii_lowercase();
+    if value == "ok" {
+        SudoAccess::Passwordless
+    } else if value == "missing" {
+        SudoAccess::NotInstalled
+    } else if lower.contains("password is required") {
+        SudoAccess::PasswordRequired
+    } else if lower.contains("not in the sudoers")
+        || lower.contains("may not run sudo")
+        || lower.contains("not allowed")
+    {
+        SudoAccess::Denied
+    } else {
+        SudoAccess::Unknown
+    }
+}
@@ -0,0 +1,39 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RemoteWhoamiInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct RemoteWhoamiOutput {
+    pub user: String,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub groups: Vec<String>,
+    pub home: String,
+    /// Octal file creation mask, e.g. `0022`.
+    pub umask: String,
+    /// Whether the login user is root (uid 0); sudo is irrelevant then.
+    pub root: bool,
+    pub sudo: SudoAccess,
+}
+
+/// Result of the `sudo -n true` probe.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SudoAccess {
+    /// `sudo` works without a password prompt.
+    Passwordless,
+    /// sudo asked for a password, which agents can't provide. Whether the
+    /// user is allowed at all is only known after authenticating.
+    PasswordRequired,
+    /// `sudo` is installed but the user is not allowed to use it.
+    Denied,
+    /// `sudo` is not installed.
+    NotInstalled,
+    #[default]
+    Unknown,
+}
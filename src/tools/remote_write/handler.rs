@@ -1,15 +1,108 @@
 use std::sync::Arc;
 
 use super::schema::RemoteWriteInput;
-use crate::connection::SshConnection;
+use crate::connection::RemoteExecutor;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::file_backup;
+use crate::tools::sync_verify::{self, VerifyStatus};
+use crate::tools::{remote_error, tool_error, ErrorCode};
+use crate::utils::command::CommandBuilder;
 use crate::utils::path::normalize_remote_path;
+use crate::utils::validate::{is_valid_mode, is_valid_name};
 
-pub async fn handle(conn: Arc<SshConnection>, input: RemoteWriteInput) -> String {
+/// Timeout for the `chmod` and `chown` after a write.
+const ATTRIBUTE_TIMEOUT_MS: u64 = 10_000;
+
+pub async fn handle<C: RemoteExecutor>(
+    conn: Arc<C>,
+    input: RemoteWriteInput,
+    timeouts: TimeoutPolicy,
+) -> String {
     let base_path = conn.remote_path().to_string();
     let path = normalize_remote_path(&input.file_path, &base_path);
 
-    match conn.write_file(&path, &input.content).await {
-        Ok(()) => format!("Successfully wrote to {path}"),
-        Err(e) => format!("Error writing file: {e}"),
+    if let Some(mode) = &input.mode {
+        if !is_valid_mode(mode) {
+            let message = format!("invalid mode '{mode}': expected 3 or 4 octal digits");
+            return tool_error(ErrorCode::InvalidInput, message);
+        }
+    }
+    if let Some(owner) = &input.owner {
+        if !is_valid_name(owner) {
+            let message = format!("invalid owner '{owner}': expected 'user' or 'user:group'");
+            return tool_error(ErrorCode::InvalidInput, message);
+        }
+    }
+    if input.mode.is_some() || input.owner.is_some() {
+        if let Some(error) = conn.posix_required("mode and owner") {
+            return tool_error(ErrorCode::NotSupported, error);
+        }
+    }
+
+    let backup = if input.backup.unwrap_or(false) {
+        match file_backup::backup_file(conn.as_ref(), &path, timeouts).await {
+            Ok(backup) => backup,
+            Err(e) => return remote_error(&e, format!("{e}; the file was not written")),
+        }
+    } else {
+        None
+    };
+    let backup_note = backup.map_or_else(String::new, |b| {
+        format!("\nBacked up the previous version to {b}")
+    });
+
+    if let Err(e) = conn.write_file(&path, &input.content).await {
+        return remote_error(&e, format!("Error writing file: {e}"));
+    }
+
+    if let Some(mode) = &input.mode {
+        let chmod = CommandBuilder::new("chmod").word(mode).path(&path).build();
+        if let Err(e) = set_attribute(conn.as_ref(), &chmod).await {
+            let message = format!("wrote {path} but could not set mode {mode}: {e}");
+            return tool_error(ErrorCode::Failed, message);
+        }
+    }
+    let owner_note = match &input.owner {
+        Some(owner) => {
+            let chown = CommandBuilder::new("chown").arg(owner).path(&path).build();
+            match set_attribute(conn.as_ref(), &chown).await {
+                Ok(()) => String::new(),
+                Err(e) => format!("\nCould not change the owner to {owner}: {e}"),
+            }
+        }
+        None => String::new(),
+    };
+    let notes = format!("{owner_note}{backup_note}");
+
+    if !input.verify.unwrap_or(false) {
+        return format!("Successfully wrote to {path}{notes}");
+    }
+    match sync_verify::verify_content(conn.as_ref(), input.content.as_bytes(), &path, timeouts)
+        .await
+    {
+        Ok(VerifyStatus::Ok) => {
+            format!("Successfully wrote to {path} (sha256 verified){notes}")
+        }
+        Ok(VerifyStatus::Mismatch) => tool_error(
+            ErrorCode::ChecksumMismatch,
+            format!("checksum mismatch after writing {path} — remote content differs"),
+        ),
+        Ok(VerifyStatus::Missing) => tool_error(
+            ErrorCode::Failed,
+            format!("wrote {path} but could not hash it on the remote to verify"),
+        ),
+        Err(e) => remote_error(
+            &e,
+            format!("wrote {path} but checksum verification failed: {e}"),
+        ),
+    }
+}
+
+/// Run a `chmod` or `chown`, turning a non-zero exit into its stderr.
+async fn set_attribute<C: RemoteExecutor>(conn: &C, command: &str) -> Result<(), String> {
+    match conn.exec(command, Some(ATTRIBUTE_TIMEOUT_MS)).await {
+        Ok(r) if r.exit_code == 0 => Ok(()),
+        Ok(r) => Err(r.stderr.trim().to_string()),
+        Err(e) => Err(e.to_string()),
     }
 }
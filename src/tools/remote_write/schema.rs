@@ -13,4 +13,29 @@ pub struct RemoteWriteInput {
 
     #[schemars(description = "The content to write to the file")]
     pub content: String,
+
+    #[schemars(
+        description = "After writing, compare the sha256 of the content with the remote file and report an error on mismatch"
+    )]
+    pub verify: Option<bool>,
+
+    #[schemars(
+        description = "Before overwriting, copy the existing file to ~/.ssh-hub-backups/<timestamp>/ on the server and report the backup path; undo with restore_backup (default false)"
+    )]
+    pub backup: Option<bool>,
+
+    #[schemars(
+        description = "Permissions to set after writing, as 3 or 4 octal digits (e.g. '755' for a script, '644' for a unit file). Without it, an existing file keeps its mode and a new one gets the umask default"
+    )]
+    pub mode: Option<String>,
+
+    #[schemars(
+        description = "Owner to set after writing, as 'user' or 'user:group'. Best effort: usually needs root, and a failed chown is reported without failing the write"
+    )]
+    pub owner: Option<String>,
+
+    #[schemars(
+        description = "Reason for making this change outside the server's maintenance window. Only needed after a call was refused for that reason; the reason is logged"
+    )]
+    pub override_window: Option<String>,
 }
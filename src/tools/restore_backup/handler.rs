@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use super::schema::RestoreBackupInput;
+use crate::connection::RemoteExecutor;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::{file_backup, remote_error, tool_error, ErrorCode};
+
+/// Copy a backup back into place. The current file is backed up first, so
+/// a restore can itself be undone.
+pub async fn handle<C: RemoteExecutor>(
+    conn: Arc<C>,
+    input: RestoreBackupInput,
+    timeouts: TimeoutPolicy,
+) -> String {
+    let original = match file_backup::original_path(&input.backup_path) {
+        Ok(path) => path,
+        Err(e) => return tool_error(ErrorCode::InvalidInput, e.to_string()),
+    };
+    let replaced = match file_backup::backup_file(conn.as_ref(), &original, timeouts).await {
+        Ok(backup) => backup,
+        Err(e) => return remote_error(&e, format!("{e}; nothing was restored")),
+    };
+    if let Err(e) = file_backup::restore_file(conn.as_ref(), &input.backup_path, timeouts).await {
+        return remote_error(&e, e.to_string());
+    }
+    match replaced {
+        Some(backup) => format!(
+            "Restored {original} from {}. The replaced version was backed up to {backup}",
+            input.backup_path
+        ),
+        None => format!("Restored {original} from {}", input.backup_path),
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+
+use super::schema::RunSavedCommandInput;
+use crate::tools::command_template::CommandTemplate;
+use crate::tools::{ErrorCode, ToolError};
+
+/// The shell command a call asks for: the saved command `input.command`
+/// from `commands`, with `input.args` shell-escaped into its placeholders.
+///
+/// # Errors
+///
+/// Returns the tool error to report when the server has no such command,
+/// the saved command is malformed, or the arguments don't fit its
+/// placeholders.
+pub fn render(
+    commands: &BTreeMap<String, String>,
+    input: &RunSavedCommandInput,
+) -> Result<String, ToolError> {
+    let Some(command) = commands.get(&input.command) else {
+        if commands.is_empty() {
+            return Err(ToolError::new(
+                ErrorCode::NotFound,
+                format!(
+                    "server '{}' has no saved commands. Define them under [servers.{}.commands] in servers.toml",
+                    input.server, input.server
+                ),
+            ));
+        }
+        let names: Vec<&str> = commands.keys().map(String::as_str).collect();
+        return Err(ToolError::new(
+            ErrorCode::NotFound,
+            format!(
+                "server '{}' has no saved command '{}'. Saved commands: {}",
+                input.server,
+                input.command,
+                names.join(", ")
+            ),
+        ));
+    };
+    let template = CommandTemplate::saved(command).map_err(|e| {
+        let message = format!("saved command '{}' is malformed: {e}", input.command);
+        ToolError::new(ErrorCode::Failed, message)
+    })?;
+    let args: Map<String, Value> = input
+        .args
+        .iter()
+        .flatten()
+        .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+        .collect();
+    template.render(&args).map_err(|e| {
+        let message = format!("{e} for saved command '{}'", input.command);
+        ToolError::new(ErrorCode::InvalidInput, message)
+    })
+}
+
+/// Parse `name=value` words from the command line into `args`.
+///
+/// # Errors
+///
+/// Returns a message naming the first word without `=` or with an empty name.
+pub fn parse_args(words: &[String]) -> Result<BTreeMap<String, String>, String> {
+    words
+        .iter()
+        .map(|word| match word.split_once('=') {
+            Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+            _ => Err(format!("expected name=value, got '{word}'")),
+        })
+        .collect()
+}
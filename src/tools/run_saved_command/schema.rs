@@ -0,0 +1,30 @@
+use std::collections::BTreeMap;
+
+use rmcp::schemars::{self, JsonSchema};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RunSavedCommandInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+
+    #[schemars(
+        description = "Name of the saved command (list_servers shows each server's saved_commands)"
+    )]
+    pub command: String,
+
+    #[schemars(
+        description = "Values for the command's {param} placeholders, keyed by name (e.g. {\"branch\": \"main\"}). Each value is shell-escaped"
+    )]
+    pub args: Option<BTreeMap<String, String>>,
+
+    #[schemars(
+        description = "Timeout in milliseconds. Defaults to the server's default_timeout_ms, else 120000 (2 min); max 600000 (10 min). 0 runs without a timeout on servers configured with no_timeout = true"
+    )]
+    pub timeout: Option<u64>,
+
+    #[schemars(
+        description = "Reason for running this outside the server's maintenance window. Only needed after a call was refused for that reason; the reason is logged"
+    )]
+    pub override_window: Option<String>,
+}
@@ -0,0 +1,24 @@
+use crate::connection::{ConnectionPool, PoolStats};
+
+/// Remove `server` from the pool and close its SSH session, along with any
+/// other server sharing that session.
+///
+/// Operations still running on the old connection fail once it closes; new
+/// tool calls open a fresh one. Returns `false` if nothing was pooled.
+pub async fn disconnect(pool: &ConnectionPool, server: &str) -> bool {
+    let Some(conn) = pool.remove_with_sharers(server).await else {
+        return false;
+    };
+    tracing::info!("Disconnecting '{server}' on request");
+    conn.disconnect().await;
+    true
+}
+
+/// JSON snapshot of every pooled connection for `pool_stats`.
+pub async fn stats(pool: &ConnectionPool) -> String {
+    let output = PoolStats {
+        connections: pool.stats().await,
+    };
+    serde_json::to_string_pretty(&output)
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
@@ -0,0 +1,14 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DisconnectServerInput {
+    #[schemars(description = "Name of the connected server to disconnect (e.g., 'staging')")]
+    pub server: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReconnectServerInput {
+    #[schemars(description = "Name of the configured server to reconnect (e.g., 'staging')")]
+    pub server: String,
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::{Ipv4Addr, SocketAddr};
+
+use anyhow::Result;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+use crate::connection::socks;
+use crate::tools::{tool_error, ErrorCode};
+
+use super::schema::{SocksProxyInput, SocksProxyOutput};
+
+struct RunningProxy {
+    address: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+/// Running SOCKS proxies, at most one per server.
+#[derive(Default)]
+pub struct ProxyStore {
+    proxies: Mutex<HashMap<String, RunningProxy>>,
+}
+
+impl ProxyStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Start a SOCKS5 proxy for `input.server`, whose client connections are
+/// opened with `open`, or report the one already running.
+pub async fn start<F, Fut, T>(store: &ProxyStore, input: SocksProxyInput, open: F) -> String
+where
+    F: Fn(String, u16, SocketAddr) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<T>> + Send + 'static,
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let mut proxies = store.proxies.lock().await;
+    proxies.retain(|_, proxy| !proxy.task.is_finished());
+
+    if let Some(proxy) = proxies.get(&input.server) {
+        if input.port.is_some_and(|port| port != proxy.address.port()) {
+            let message = format!(
+                "a SOCKS proxy for '{}' is already running on {}. Stop it first to use another port",
+                input.server, proxy.address
+            );
+            return tool_error(ErrorCode::InvalidInput, message);
+        }
+        return render(&input.server, Some(proxy.address), true);
+    }
+
+    let port = input.port.unwrap_or(0);
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            let message = format!("cannot listen on 127.0.0.1:{port}: {e}");
+            return tool_error(ErrorCode::Failed, message);
+        }
+    };
+    let address = match listener.local_addr() {
+        Ok(address) => address,
+        Err(e) => {
+            let message = format!("cannot listen on 127.0.0.1:{port}: {e}");
+            return tool_error(ErrorCode::Failed, message);
+        }
+    };
+    tracing::info!("SOCKS proxy for '{}' listening on {address}", input.server);
+    let task = tokio::spawn(socks::serve(listener, open));
+    proxies.insert(input.server.clone(), RunningProxy { address, task });
+    render(&input.server, Some(address), false)
+}
+
+/// Stop the SOCKS proxy for `server`, if one is running.
+pub async fn stop(store: &ProxyStore, server: &str) -> String {
+    if let Some(proxy) = store.proxies.lock().await.remove(server) {
+        proxy.task.abort();
+        tracing::info!("SOCKS proxy for '{server}' on {} stopped", proxy.address);
+    }
+    render(server, None, false)
+}
+
+fn render(server: &str, address: Option<SocketAddr>, already_running: bool) -> String {
+    let output = SocksProxyOutput {
+        server: server.to_string(),
+        running: address.is_some(),
+        address: address.map(|a| a.to_string()),
+        proxy_url: address.map(|a| format!("socks5h://{a}")),
+        already_running,
+    };
+    serde_json::to_string_pretty(&output)
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
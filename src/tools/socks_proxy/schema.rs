@@ -0,0 +1,30 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SocksProxyInput {
+    #[schemars(description = "Name of the configured server to tunnel through (e.g., 'bastion')")]
+    pub server: String,
+
+    #[schemars(description = "Local port to listen on (127.0.0.1 only). Defaults to a free port")]
+    pub port: Option<u16>,
+
+    #[schemars(description = "Stop the server's proxy instead of starting one")]
+    pub stop: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SocksProxyOutput {
+    pub server: String,
+    /// Whether a proxy for the server is running after the call.
+    pub running: bool,
+    /// Local address of the proxy, e.g. `127.0.0.1:1080`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// Proxy URL for clients, with host names resolved by the server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_url: Option<String>,
+    /// The proxy was started by an earlier call.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub already_running: bool,
+}
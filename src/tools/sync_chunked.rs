@@ -0,0 +1,427 @@
+//! Chunked transfer of large single files over SFTP, resumable after a
+//! dropped connection.
+//!
+//! Files at or above [`CHUNKED_BYTES`] are copied a [`CHUNK_BYTES`] range at
+//! a time into a partial file beside the destination
+//! (`<dest>.ssh-hub-partial`), which is renamed into place once complete.
+//! The size and mtime of the source the partial file was copied from are
+//! recorded under `~/.cache/ssh-hub/transfers/`, so the next `sync_push` or
+//! `sync_pull` of the same file picks up where the last one stopped, as long
+//! as the source hasn't changed since.
+
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use rmcp::model::{ProgressNotificationParam, ProgressToken};
+use rmcp::service::RequestContext;
+use rmcp::{Peer, RoleServer};
+use russh_sftp::protocol::OpenFlags;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+
+use crate::connection::{ConnectionParams, SshConnection};
+use crate::tools::sync_pull::handler::format_size;
+use crate::tools::sync_transfer::{sftp_attrs, FileAttrs, TransferResult};
+use crate::utils::path::home_relative;
+
+/// Single files at or above this size are transferred in chunks.
+pub const CHUNKED_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Bytes copied between progress reports.
+pub const CHUNK_BYTES: usize = 4 * 1024 * 1024;
+
+/// Appended to the destination path to name the partial file.
+pub const PARTIAL_SUFFIX: &str = ".ssh-hub-partial";
+
+/// Hex characters of the hash naming a transfer's state file.
+const STATE_KEY_LEN: usize = 16;
+
+/// Size and mtime of a transfer's source, which must match for a partial
+/// file to be resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceVersion {
+    pub size: u64,
+    /// Seconds since the Unix epoch.
+    pub mtime: u64,
+}
+
+/// Which way a chunked transfer goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Push,
+    Pull,
+}
+
+/// Name of the state file for copying between `remote` on the server of
+/// `params` and `local`, in the given direction.
+#[must_use]
+pub fn state_key(
+    direction: Direction,
+    params: &ConnectionParams,
+    remote: &str,
+    local: &Path,
+) -> String {
+    let local = std::path::absolute(local).unwrap_or_else(|_| local.to_path_buf());
+    let id = format!(
+        "{direction:?}\0{}@{}:{}\0{remote}\0{}",
+        params.user,
+        params.host,
+        params.port,
+        local.display()
+    );
+    hex::encode(Sha256::digest(id.as_bytes()))[..STATE_KEY_LEN].to_string()
+}
+
+/// Where the state file for `key` lives.
+#[must_use]
+pub fn state_path(key: &str) -> Option<PathBuf> {
+    Some(
+        dirs::cache_dir()?
+            .join("ssh-hub")
+            .join("transfers")
+            .join(format!("{key}.json")),
+    )
+}
+
+/// The source version recorded in a state file, if it can be read.
+#[must_use]
+pub fn read_state(path: &Path) -> Option<SourceVersion> {
+    let json = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Record the source version a partial file is copied from.
+///
+/// # Errors
+///
+/// Returns an error if the state file can't be written.
+pub fn write_state(path: &Path, source: SourceVersion) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string(&source)?)?;
+    Ok(())
+}
+
+/// Bytes of a partial file that can be kept: all of it (up to the source's
+/// size) when it was copied from this same version of the source, else none.
+#[must_use]
+pub fn resume_offset(
+    recorded: Option<SourceVersion>,
+    source: SourceVersion,
+    partial_len: Option<u64>,
+) -> u64 {
+    match (recorded, partial_len) {
+        (Some(recorded), Some(len)) if recorded == source => len.min(source.size),
+        _ => 0,
+    }
+}
+
+/// Progress notifications for the tool call a transfer runs in, sent only
+/// when the client asked for them with a progress token.
+#[derive(Clone, Default)]
+pub struct Progress {
+    target: Option<(Peer<RoleServer>, ProgressToken)>,
+}
+
+impl Progress {
+    #[must_use]
+    pub fn from_context(ctx: &RequestContext<RoleServer>) -> Self {
+        Self {
+            target: ctx
+                .meta
+                .get_progress_token()
+                .map(|token| (ctx.peer.clone(), token)),
+        }
+    }
+
+    async fn report(&self, done: u64, total: u64) {
+        let Some((peer, token)) = &self.target else {
+            return;
+        };
+        let param = ProgressNotificationParam {
+            progress_token: token.clone(),
+            progress: kib(done),
+            total: Some(kib(total)),
+            message: Some(progress_message(done, total)),
+        };
+        if let Err(e) = peer.notify_progress(param).await {
+            tracing::debug!("Failed to send transfer progress notification: {e}");
+        }
+    }
+}
+
+/// Progress counts are sent in KiB, which stay exact as `f64` through any
+/// file size `u32` can count.
+fn kib(bytes: u64) -> f64 {
+    f64::from(u32::try_from(bytes / 1024).unwrap_or(u32::MAX))
+}
+
+/// Progress line for a chunked transfer, e.g. `12.0 MiB of 500.0 MiB`.
+#[must_use]
+pub fn progress_message(done: u64, total: u64) -> String {
+    format!("{} of {}", format_size(done), format_size(total))
+}
+
+/// Why a chunked transfer stopped, and how to pick it up again.
+fn interrupted(
+    e: impl std::fmt::Display,
+    done: u64,
+    total: u64,
+    partial: &str,
+    tool: &str,
+) -> String {
+    format!(
+        "{e}; {} is kept in {partial}, so running the same {tool} again resumes from there",
+        progress_message(done, total)
+    )
+}
+
+/// Copy `from` into `to` from the current positions on, reporting progress
+/// after each chunk. Returns the bytes copied, or the error and the bytes
+/// copied before it.
+async fn copy_chunks<R, W>(
+    from: &mut R,
+    to: &mut W,
+    mut done: u64,
+    total: u64,
+    progress: &Progress,
+) -> Result<u64, (anyhow::Error, u64)>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0u8; CHUNK_BYTES];
+    while done < total {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match from.read(&mut buf[filled..]).await {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) => return Err((anyhow!("read failed: {e}"), done)),
+            }
+        }
+        if filled == 0 {
+            break;
+        }
+        if let Err(e) = to.write_all(&buf[..filled]).await {
+            return Err((anyhow!("write failed: {e}"), done));
+        }
+        done += filled as u64;
+        progress.report(done, total).await;
+    }
+    to.flush()
+        .await
+        .map_err(|e| (anyhow!("write failed: {e}"), done))?;
+    Ok(done)
+}
+
+/// Push a large file over SFTP in chunks, resuming a partial copy left by
+/// an earlier push of the same file.
+///
+/// # Errors
+///
+/// Returns an error, before anything is written, if no SFTP session can be
+/// opened, so the caller can fall back to another strategy.
+pub async fn push_file_chunked(
+    conn: &SshConnection,
+    local: &Path,
+    remote_dest: &str,
+    preserve: bool,
+    progress: &Progress,
+) -> Result<TransferResult> {
+    let mut result = TransferResult::default();
+    let path_str = local.display().to_string();
+    let Some(attrs) = FileAttrs::of(local) else {
+        result.fail(path_str, "Error reading local file metadata");
+        return Ok(result);
+    };
+    let size = std::fs::metadata(local).map_or(0, |m| m.len());
+    let source = SourceVersion {
+        size,
+        mtime: attrs.mtime,
+    };
+
+    let sftp = conn.open_sftp().await?;
+    let remote = home_relative(remote_dest);
+    let partial = format!("{remote}{PARTIAL_SUFFIX}");
+    let state = state_path(&state_key(Direction::Push, conn.params(), &remote, local));
+    let partial_len = sftp
+        .metadata(partial.as_str())
+        .await
+        .ok()
+        .and_then(|m| m.size);
+    let offset = resume_offset(state.as_deref().and_then(read_state), source, partial_len);
+    if let Some(state) = &state {
+        if let Err(e) = write_state(state, source) {
+            tracing::debug!("Failed to record transfer state: {e}");
+        }
+    }
+
+    let mut flags = OpenFlags::CREATE | OpenFlags::WRITE;
+    if offset == 0 {
+        flags |= OpenFlags::TRUNCATE;
+    }
+    let mut to = match sftp.open_with_flags(partial.as_str(), flags).await {
+        Ok(f) => f,
+        Err(e) => {
+            result.fail(path_str, format!("Error creating {partial}: {e}"));
+            return Ok(result);
+        }
+    };
+    let mut from = match tokio::fs::File::open(local).await {
+        Ok(f) => f,
+        Err(e) => {
+            result.fail(path_str, format!("Error reading local file: {e}"));
+            return Ok(result);
+        }
+    };
+    if offset > 0 {
+        tracing::info!("Resuming push of {path_str} at {offset} of {size} bytes");
+        let seeked = match from.seek(SeekFrom::Start(offset)).await {
+            Ok(_) => to.seek(SeekFrom::Start(offset)).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = seeked {
+            result.fail(path_str, format!("Error resuming transfer: {e}"));
+            return Ok(result);
+        }
+    }
+
+    match copy_chunks(&mut from, &mut to, offset, size, progress).await {
+        Ok(done) => result.bytes = done - offset,
+        Err((e, done)) => {
+            result.fail(path_str, interrupted(e, done, size, &partial, "sync_push"));
+            return Ok(result);
+        }
+    }
+    let _ = to.shutdown().await;
+    drop(to);
+
+    // SFTP renames don't replace an existing file
+    let _ = sftp.remove_file(remote.as_str()).await;
+    if let Err(e) = sftp.rename(partial.as_str(), remote.as_str()).await {
+        result.fail(path_str, format!("Error moving {partial} into place: {e}"));
+        return Ok(result);
+    }
+    if let Some(state) = &state {
+        let _ = std::fs::remove_file(state);
+    }
+    result.transferred.push(path_str.clone());
+    result.resumed_from = (offset > 0).then_some(offset);
+    if preserve {
+        let meta = sftp_attrs(attrs, conn.shell().is_posix());
+        if let Err(e) = sftp.set_metadata(remote, meta).await {
+            result.fail(
+                path_str,
+                format!("copied, but setting mode and mtime failed: {e}"),
+            );
+        }
+    }
+    let _ = sftp.close().await;
+    Ok(result)
+}
+
+/// Pull a large file over SFTP in chunks, resuming a partial copy left by
+/// an earlier pull of the same file.
+///
+/// # Errors
+///
+/// Returns an error, before anything is written, if no SFTP session can be
+/// opened or the remote file can't be read, so the caller can fall back to
+/// another strategy.
+pub async fn pull_file_chunked(
+    conn: &SshConnection,
+    remote_path: &str,
+    local_dest: &str,
+    progress: &Progress,
+) -> Result<TransferResult> {
+    let mut result = TransferResult::default();
+    let sftp = conn.open_sftp().await?;
+    let remote = home_relative(remote_path);
+    let meta = sftp.metadata(remote.as_str()).await?;
+    let source = SourceVersion {
+        size: meta.size.unwrap_or(0),
+        mtime: meta.mtime.map_or(0, u64::from),
+    };
+    let mut from = sftp.open(remote.as_str()).await?;
+
+    let dest = Path::new(local_dest);
+    if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+        if let Err(e) = tokio::fs::create_dir_all(parent).await {
+            result.fail(local_dest, format!("Error creating local directory: {e}"));
+            return Ok(result);
+        }
+    }
+    let partial = format!("{local_dest}{PARTIAL_SUFFIX}");
+    let state = state_path(&state_key(Direction::Pull, conn.params(), &remote, dest));
+    let partial_len = std::fs::metadata(&partial).ok().map(|m| m.len());
+    let offset = resume_offset(state.as_deref().and_then(read_state), source, partial_len);
+    if let Some(state) = &state {
+        if let Err(e) = write_state(state, source) {
+            tracing::debug!("Failed to record transfer state: {e}");
+        }
+    }
+
+    let opened = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&partial)
+        .await;
+    let mut to = match opened {
+        Ok(f) => f,
+        Err(e) => {
+            result.fail(local_dest, format!("Error creating {partial}: {e}"));
+            return Ok(result);
+        }
+    };
+    // Drop anything past the point being resumed from
+    let resumed = match to.set_len(offset).await {
+        Ok(()) if offset > 0 => {
+            tracing::info!(
+                "Resuming pull of {remote_path} at {offset} of {} bytes",
+                source.size
+            );
+            match to.seek(SeekFrom::Start(offset)).await {
+                Ok(_) => from.seek(SeekFrom::Start(offset)).await.map(|_| ()),
+                Err(e) => Err(e),
+            }
+        }
+        other => other,
+    };
+    if let Err(e) = resumed {
+        result.fail(local_dest, format!("Error resuming transfer: {e}"));
+        return Ok(result);
+    }
+
+    match copy_chunks(&mut from, &mut to, offset, source.size, progress).await {
+        Ok(done) => result.bytes = done - offset,
+        Err((e, done)) => {
+            result.fail(
+                remote_path,
+                interrupted(e, done, source.size, &partial, "sync_pull"),
+            );
+            return Ok(result);
+        }
+    }
+    drop(to);
+    drop(from);
+    let _ = sftp.close().await;
+
+    if let Err(e) = tokio::fs::rename(&partial, dest).await {
+        result.fail(
+            local_dest,
+            format!("Error moving {partial} into place: {e}"),
+        );
+        return Ok(result);
+    }
+    if let Some(state) = &state {
+        let _ = std::fs::remove_file(state);
+    }
+    result.transferred.push(local_dest.to_string());
+    result.resumed_from = (offset > 0).then_some(offset);
+    Ok(result)
+}
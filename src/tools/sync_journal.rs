@@ -0,0 +1,209 @@
+//! Remote file change journal.
+//!
+//! On first use for a directory, a detached watcher (`inotifywait` on Linux,
+//! `fswatch` on macOS/BSD) is started on the remote and appends changed paths
+//! to `~/.cache/ssh-hub/journal/<key>/log`. A journal token is
+//! `<watcher pid>:<byte offset>`; asking "what changed since token X" reads
+//! only the journal tail instead of re-hashing the whole tree.
+//!
+//! Tokens are invalidated when the watcher restarts (pid mismatch) or the log
+//! is truncated — callers fall back to a full manifest scan in that case.
+//!
+//! Watchers don't outlive their use: one is stopped when the connection that
+//! started it disconnects, and stops itself after going unqueried for a while.
+
+use std::collections::BTreeSet;
+
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+
+use crate::connection::SshConnection;
+use crate::utils::path::{shell_escape, shell_escape_remote_path};
+
+/// Timeout for journal setup and queries (15 seconds).
+const JOURNAL_TIMEOUT_MS: u64 = 15_000;
+
+/// Hex characters of the directory hash used as the journal key.
+const JOURNAL_KEY_LEN: usize = 16;
+
+/// A watcher stops itself after going this long without a query (30 minutes).
+const JOURNAL_IDLE_MINUTES: u32 = 30;
+
+/// How often a watcher checks whether it went idle (1 minute).
+const IDLE_CHECK_SECS: u32 = 60;
+
+/// Output line emitted when no watcher tool is installed on the remote.
+const NO_WATCHER: &str = "NO_WATCHER";
+
+/// A running journal and the current end of its log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalToken {
+    pub pid: u32,
+    pub offset: u64,
+}
+
+impl JournalToken {
+    /// Parse a `<pid>:<offset>` token string.
+    #[must_use]
+    pub fn parse(token: &str) -> Option<Self> {
+        let (pid, offset) = token.split_once(':')?;
+        Some(Self {
+            pid: pid.parse().ok()?,
+            offset: offset.parse().ok()?,
+        })
+    }
+}
+
+impl std::fmt::Display for JournalToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.pid, self.offset)
+    }
+}
+
+/// Remote directory holding the journal for `remote_dir`.
+fn journal_dir(remote_dir: &str) -> String {
+    let hash = hex::encode(Sha256::digest(remote_dir.as_bytes()));
+    format!("~/.cache/ssh-hub/journal/{}", &hash[..JOURNAL_KEY_LEN])
+}
+
+/// Start the watcher for `remote_dir` if it isn't already running and return
+/// a token pointing at the current end of the log. Returns `None` when neither
+/// `inotifywait` nor `fswatch` is available on the remote.
+///
+/// The watcher is stopped when `conn` disconnects, and stops itself once no
+/// one has queried it for [`JOURNAL_IDLE_MINUTES`].
+///
+/// # Errors
+///
+/// Returns an error if the SSH command fails or its output is malformed.
+pub async fn ensure_journal(
+    conn: &SshConnection,
+    remote_dir: &str,
+) -> Result<Option<JournalToken>> {
+    let result = conn
+        .exec(&ensure_command(remote_dir), Some(JOURNAL_TIMEOUT_MS))
+        .await?;
+    let line = result.stdout.trim();
+    if line == NO_WATCHER {
+        return Ok(None);
+    }
+    let token =
+        JournalToken::parse(line).ok_or_else(|| anyhow!("Unexpected journal output: {line}"))?;
+    conn.on_disconnect(stop_command(remote_dir));
+    Ok(Some(token))
+}
+
+/// Shell command that starts the watcher for `remote_dir` unless one is
+/// running, then prints `<pid>:<log size>` (or `NO_WATCHER`).
+#[must_use]
+pub fn ensure_command(remote_dir: &str) -> String {
+    let jdir = shell_escape_remote_path(&journal_dir(remote_dir));
+    let dir = shell_escape_remote_path(remote_dir);
+
+    // The watcher runs under a small supervisor that kills it once
+    // `touched` is older than the idle limit, and takes it down when stopped.
+    let supervise = |watch: String| {
+        shell_escape(&format!(
+            "{watch} >> {jdir}/log 2>/dev/null & p=$!; \
+             trap 'kill $p $s 2>/dev/null; exit' TERM HUP; \
+             while kill -0 $p 2>/dev/null; do \
+               sleep {IDLE_CHECK_SECS} & s=$!; wait $s; \
+               if [ -n \"$(find {jdir}/touched -mmin +{JOURNAL_IDLE_MINUTES} 2>/dev/null)\" ]; then kill $p; fi; \
+             done; \
+             [ \"$(cat {jdir}/pid 2>/dev/null)\" = $$ ] && rm -f {jdir}/pid"
+        ))
+    };
+    let inotify = supervise(format!(
+        "inotifywait -m -r -q -e close_write,create,delete,moved_to,moved_from \
+         --format '%w%f' {dir}"
+    ));
+    let fswatch = supervise(format!("fswatch -r {dir}"));
+
+    // Supervisors run inside `sh -c` under setsid/nohup (same detaching
+    // strategy as remote_bash background jobs) so they survive the channel.
+    format!(
+        "mkdir -p {jdir} && touch {jdir}/touched && \
+         if [ -f {jdir}/pid ] && kill -0 \"$(cat {jdir}/pid)\" 2>/dev/null; then :; \
+         else \
+           if command -v inotifywait >/dev/null 2>&1; then w={inotify}; \
+           elif command -v fswatch >/dev/null 2>&1; then w={fswatch}; \
+           else echo {NO_WATCHER}; exit 0; fi; \
+           : > {jdir}/log; \
+           if command -v setsid >/dev/null 2>&1; then \
+             setsid sh -c \"$w\" < /dev/null > /dev/null 2>&1 & \
+           else \
+             nohup sh -c \"$w\" < /dev/null > /dev/null 2>&1 & \
+           fi; \
+           echo $! > {jdir}/pid; \
+         fi; \
+         echo \"$(cat {jdir}/pid):$(wc -c < {jdir}/log | tr -d ' ')\""
+    )
+}
+
+/// Shell command that stops the watcher for `remote_dir`, if running.
+#[must_use]
+pub fn stop_command(remote_dir: &str) -> String {
+    let jdir = shell_escape_remote_path(&journal_dir(remote_dir));
+    format!(
+        "if [ -f {jdir}/pid ]; then kill \"$(cat {jdir}/pid)\" 2>/dev/null; rm -f {jdir}/pid; fi"
+    )
+}
+
+/// Read paths changed since `since`. Returns the changed paths (relative to
+/// `remote_dir`) and a new token, or `None` if the token is stale (watcher
+/// restarted or log truncated).
+///
+/// # Errors
+///
+/// Returns an error if the SSH command fails.
+pub async fn changes_since(
+    conn: &SshConnection,
+    remote_dir: &str,
+    since: &JournalToken,
+) -> Result<Option<(Vec<String>, JournalToken)>> {
+    let jdir = shell_escape_remote_path(&journal_dir(remote_dir));
+    let dir = shell_escape_remote_path(remote_dir);
+    // First output line is the absolute directory the watcher reports paths
+    // under. Querying counts as use, keeping the watcher from going idle.
+    let command = format!(
+        "touch {jdir}/touched 2>/dev/null; (cd {dir} && pwd) && [ -f {jdir}/pid ] && [ \"$(cat {jdir}/pid)\" = {pid} ] && kill -0 {pid} 2>/dev/null \
+         && [ \"$(wc -c < {jdir}/log | tr -d ' ')\" -ge {offset} ] \
+         && tail -c +{start} {jdir}/log",
+        pid = since.pid,
+        offset = since.offset,
+        start = since.offset + 1,
+    );
+
+    let result = conn.exec(&command, Some(JOURNAL_TIMEOUT_MS)).await?;
+    if result.exit_code != 0 {
+        return Ok(None);
+    }
+
+    let (base, log) = result
+        .stdout
+        .split_once('\n')
+        .unwrap_or((&result.stdout, ""));
+
+    // Only consume complete lines — a partial trailing line is re-read next time.
+    let complete = log.rfind('\n').map_or("", |i| &log[..=i]);
+    let token = JournalToken {
+        pid: since.pid,
+        offset: since.offset + complete.len() as u64,
+    };
+
+    Ok(Some((relativize_changes(complete, base), token)))
+}
+
+/// Convert absolute watcher paths to paths relative to `base`, deduplicated
+/// and sorted. Paths outside `base` and `.git/` internals are dropped.
+#[must_use]
+pub fn relativize_changes(log: &str, base: &str) -> Vec<String> {
+    let base = base.trim_end_matches('/');
+    let changed: BTreeSet<String> = log
+        .lines()
+        .filter_map(|line| line.strip_prefix(base)?.strip_prefix('/'))
+        .filter(|rel| !rel.is_empty() && *rel != ".git" && !rel.starts_with(".git/"))
+        .map(ToString::to_string)
+        .collect();
+    changed.into_iter().collect()
+}
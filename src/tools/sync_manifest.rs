@@ -0,0 +1,289 @@
+//! File manifests (path → size, mtime, sha256) for comparing a local
+//! directory against its remote counterpart.
+//!
+//! Remote manifests are expensive — every file is hashed on the server — so
+//! they are cached per server + directory for [`MANIFEST_TTL`]. A
+//! `sync_status` followed by a `sync_push` with `changed_only` in the same
+//! agent turn reuses one manifest instead of hashing the tree twice. When the
+//! directory has a [change journal](crate::tools::sync_journal), a cached
+//! manifest is only reused while the journal shows nothing changed since it
+//! was taken.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+use crate::connection::SshConnection;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::sync_journal::{self, JournalToken};
+use crate::utils::path::shell_escape_remote_path;
+
+/// How long a cached remote manifest stays valid.
+const MANIFEST_TTL: Duration = Duration::from_secs(30);
+
+/// Timeout for building a remote manifest (2 minutes — hashes every file),
+/// unless the server's [`TimeoutPolicy`] raises it.
+const MANIFEST_TIMEOUT_MS: u64 = 120_000;
+
+/// Separates the file stat section from the hash section.
+const HASH_SECTION_MARKER: &str = "--ssh-hub-sha256--";
+
+/// Read buffer size for local file hashing.
+const HASH_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Metadata for a single file in a manifest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ManifestEntry {
+    pub size: u64,
+    /// Modification time in whole seconds since the Unix epoch.
+    pub mtime: u64,
+    pub sha256: String,
+}
+
+/// Relative path → entry, sorted for stable output.
+pub type Manifest = BTreeMap<String, ManifestEntry>;
+
+/// Result of comparing a local manifest against a remote one.
+#[derive(Debug, Default, Serialize)]
+pub struct ManifestDiff {
+    pub local_only: Vec<String>,
+    pub remote_only: Vec<String>,
+    pub modified: Vec<String>,
+    pub unchanged: usize,
+}
+
+/// Cache key: server name and remote directory.
+type CacheKey = (String, String);
+
+/// A cached manifest, when it was fetched, and the journal token taken just
+/// before fetching it, if the directory has a journal.
+type CacheEntry = (Instant, Arc<Manifest>, Option<JournalToken>);
+
+/// Short-lived cache of remote manifests keyed by server name and directory.
+#[derive(Default)]
+pub struct ManifestCache {
+    entries: Mutex<HashMap<CacheKey, CacheEntry>>,
+}
+
+impl ManifestCache {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the cached manifest for `server` + `remote_dir` if still fresh,
+    /// otherwise fetch a new one. The boolean is `true` on a cache hit.
+    ///
+    /// `journal` is a token the caller took before this call and will hand
+    /// out with the manifest. A manifest cached with a token is reused only
+    /// while the journal shows no change since that token; one cached without
+    /// a token is never paired with the caller's token, so it is fetched again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the remote manifest command fails.
+    pub async fn get_or_fetch(
+        &self,
+        server: &str,
+        conn: &SshConnection,
+        remote_dir: &str,
+        mut journal: Option<JournalToken>,
+        timeouts: TimeoutPolicy,
+    ) -> Result<(Arc<Manifest>, bool)> {
+        let key = (server.to_string(), remote_dir.to_string());
+        let cached = {
+            let entries = self.entries.lock().await;
+            entries
+                .get(&key)
+                .filter(|(fetched_at, _, _)| fetched_at.elapsed() < MANIFEST_TTL)
+                .map(|(_, manifest, token)| (Arc::clone(manifest), token.clone()))
+        };
+
+        match cached {
+            Some((manifest, None)) if journal.is_none() => return Ok((manifest, true)),
+            Some((manifest, Some(token))) => {
+                match sync_journal::changes_since(conn, remote_dir, &token).await {
+                    Ok(Some((changed, _))) if changed.is_empty() => return Ok((manifest, true)),
+                    // Taken before the fetch below, so it can stand for it
+                    Ok(Some((_, next))) => journal = journal.or(Some(next)),
+                    Ok(None) => tracing::debug!("Journal token {token} is stale, refetching"),
+                    Err(e) => tracing::debug!("Journal query failed, refetching: {e}"),
+                }
+            }
+            _ => {}
+        }
+
+        let manifest = Arc::new(fetch_remote_manifest(conn, remote_dir, timeouts).await?);
+        let mut entries = self.entries.lock().await;
+        entries.insert(key, (Instant::now(), Arc::clone(&manifest), journal));
+        Ok((manifest, false))
+    }
+
+    /// Drop every cached manifest for a server. Called after any tool that
+    /// may have modified the remote filesystem.
+    pub async fn invalidate_server(&self, server: &str) {
+        let mut entries = self.entries.lock().await;
+        entries.retain(|(name, _), _| name != server);
+    }
+}
+
+/// Build a manifest of every regular file under `remote_dir` (excluding `.git/`).
+/// A missing directory yields an empty manifest.
+///
+/// # Errors
+///
+/// Returns an error if the SSH command fails or exits non-zero.
+pub async fn fetch_remote_manifest(
+    conn: &SshConnection,
+    remote_dir: &str,
+    timeouts: TimeoutPolicy,
+) -> Result<Manifest> {
+    let caps = conn.capabilities().await;
+    let dir = shell_escape_remote_path(remote_dir);
+    let command = format!(
+        "if [ -d {dir} ]; then cd {dir} && \
+         find . -type f ! -path './.git/*' {} && \
+         echo '{HASH_SECTION_MARKER}' && \
+         find . -type f ! -path './.git/*' -exec {} {{}} +; fi",
+        caps.stat_action(true),
+        caps.sha256_command(),
+    );
+    let result = conn
+        .exec(&command, timeouts.operation(MANIFEST_TIMEOUT_MS))
+        .await?;
+    if result.exit_code != 0 {
+        return Err(anyhow!(
+            "Remote manifest failed (exit {}): {}",
+            result.exit_code,
+            result.stderr.trim()
+        ));
+    }
+    Ok(parse_remote_manifest(&result.stdout))
+}
+
+/// Parse the output of the remote manifest command.
+///
+/// The first section holds `<size> <mtime> ./<path>` lines from `find -printf`
+/// or `stat`, the second `<sha256>  ./<path>` lines from `sha256sum` or
+/// `shasum`. Malformed lines are skipped.
+#[must_use]
+pub fn parse_remote_manifest(output: &str) -> Manifest {
+    let mut manifest = Manifest::new();
+    let (stat_section, hash_section) = output
+        .split_once(HASH_SECTION_MARKER)
+        .unwrap_or((output, ""));
+
+    for line in stat_section.lines() {
+        let mut parts = line.splitn(3, ' ');
+        let (Some(size), Some(mtime), Some(path)) = (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        let Ok(size) = size.parse() else {
+            continue;
+        };
+        let mtime = mtime
+            .split('.')
+            .next()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default();
+        manifest.insert(
+            strip_dot_slash(path).to_string(),
+            ManifestEntry {
+                size,
+                mtime,
+                sha256: String::new(),
+            },
+        );
+    }
+
+    for line in hash_section.lines() {
+        let Some((hash, path)) = line.split_once("  ") else {
+            continue;
+        };
+        if let Some(entry) = manifest.get_mut(strip_dot_slash(path)) {
+            entry.sha256 = hash.to_string();
+        }
+    }
+
+    manifest
+}
+
+fn strip_dot_slash(path: &str) -> &str {
+    path.strip_prefix("./").unwrap_or(path)
+}
+
+/// Hash the given files (relative to `base_dir`) into a manifest.
+/// Blocking I/O — call from `spawn_blocking`.
+///
+/// # Errors
+///
+/// Returns an error if any file cannot be read.
+pub fn build_local_manifest(base_dir: &Path, files: &[String]) -> Result<Manifest> {
+    let mut manifest = Manifest::new();
+    let mut buf = vec![0u8; HASH_BUFFER_SIZE];
+
+    for file in files {
+        let path = base_dir.join(file);
+        let meta = std::fs::metadata(&path)
+            .map_err(|e| anyhow!("Cannot stat '{}': {e}", path.display()))?;
+        let mut reader = std::fs::File::open(&path)
+            .map_err(|e| anyhow!("Cannot open '{}': {e}", path.display()))?;
+
+        let mut hasher = Sha256::new();
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+
+        let mtime = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+
+        manifest.insert(
+            file.clone(),
+            ManifestEntry {
+                size: meta.len(),
+                mtime,
+                sha256: hex::encode(hasher.finalize()),
+            },
+        );
+    }
+
+    Ok(manifest)
+}
+
+/// Compare manifests by content hash. Paths present on both sides with equal
+/// hashes count as unchanged regardless of mtime.
+#[must_use]
+pub fn diff_manifests(local: &Manifest, remote: &Manifest) -> ManifestDiff {
+    let mut diff = ManifestDiff::default();
+
+    for (path, entry) in local {
+        match remote.get(path) {
+            None => diff.local_only.push(path.clone()),
+            Some(r) if r.sha256 != entry.sha256 => diff.modified.push(path.clone()),
+            Some(_) => diff.unchanged += 1,
+        }
+    }
+
+    diff.remote_only = remote
+        .keys()
+        .filter(|path| !local.contains_key(*path))
+        .cloned()
+        .collect();
+
+    diff
+}
@@ -1,37 +1,121 @@
-use std::io::Cursor;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Instant;
 
 use flate2::read::GzDecoder;
+use tokio::io::BufWriter;
 
 use super::schema::SyncPullInput;
-use crate::connection::SshConnection;
+use crate::connection::{RemoteCapabilities, SshConnection};
+use crate::timeout::TimeoutPolicy;
+use crate::tools::sync_chunked::{self, Progress, CHUNKED_BYTES};
+use crate::tools::sync_transfer::{
+    self, select_strategy, TransferResult, TransferShape, TransferStrategy,
+};
 use crate::tools::sync_types::SyncOutput;
-use crate::utils::path::{normalize_remote_path, shell_escape, shell_escape_remote_path};
+use crate::tools::sync_verify::{self, FileVerification};
+use crate::utils::gitignore::{retain_included, GitIgnore};
+use crate::utils::path::{home_relative, normalize_remote_path, shell_escape_remote_path};
 
 /// Timeout for the remote `test -d` probe (10 seconds).
 const PROBE_TIMEOUT_MS: u64 = 10_000;
 
-/// Timeout for tar-based directory sync operations (2 minutes).
+/// Timeout for tar-based directory sync operations (2 minutes), unless the
+/// server's [`TimeoutPolicy`] raises it.
 const SYNC_TIMEOUT_MS: u64 = 120_000;
 
-pub async fn handle(conn: Arc<SshConnection>, input: SyncPullInput) -> String {
+/// Timeout for listing a remote directory before pulling it (1 minute),
+/// unless the server's [`TimeoutPolicy`] raises it.
+const LIST_TIMEOUT_MS: u64 = 60_000;
+
+/// Separates the remote `.gitignore` from the file listing.
+const LISTING_MARKER: &str = "--ssh-hub-files--";
+
+/// Directory pulls over this many bytes are refused unless the call or the
+/// config raises the cap.
+pub const DEFAULT_MAX_PULL_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Largest entries named when a pull is over the cap.
+const LARGEST_SHOWN: usize = 5;
+
+/// `max_pull_bytes` is the `[sync]` setting from the config, used when the
+/// call doesn't pass `max_bytes`.
+pub async fn handle(
+    conn: Arc<SshConnection>,
+    input: SyncPullInput,
+    max_pull_bytes: Option<u64>,
+    progress: Progress,
+    timeouts: TimeoutPolicy,
+) -> String {
     let base_path = conn.remote_path().to_string();
     let remote_path = normalize_remote_path(&input.remote_path, &base_path);
 
-    // Determine if remote path is file or directory
-    let probe_cmd = format!(
-        "test -d {} && echo dir || echo file",
-        shell_escape_remote_path(&remote_path),
-    );
-    let is_dir = match conn.exec(&probe_cmd, Some(PROBE_TIMEOUT_MS)).await {
-        Ok(result) => result.stdout.trim() == "dir",
-        Err(_) => false,
+    // Determine if remote path is file or directory (and the file's size,
+    // which feeds strategy selection)
+    let (is_dir, size) = if conn.shell().is_posix() {
+        let escaped = shell_escape_remote_path(&remote_path);
+        let probe_cmd = format!(
+            "if [ -d {escaped} ]; then echo dir; else echo \"file $(wc -c < {escaped} 2>/dev/null)\"; fi",
+        );
+        match conn.exec(&probe_cmd, Some(PROBE_TIMEOUT_MS)).await {
+            Ok(result) => parse_probe(&result.stdout),
+            Err(_) => (false, 0),
+        }
+    } else {
+        probe_sftp(&conn, &remote_path).await
     };
 
+    let verify = input.verify.unwrap_or(false);
+    if let Some(error) = verify.then(|| conn.posix_required("verify")).flatten() {
+        return SyncOutput::failure(remote_path, error)
+            .to_json("sync_pull")
+            .await;
+    }
+
     if is_dir || input.files.is_some() {
         let local_dest = input.local_path.unwrap_or_else(|| ".".to_string());
-        return pull_directory(&conn, &remote_path, &local_dest, input.files.as_deref()).await;
+        let (files, total_bytes) = if let Some(files) = input.files {
+            (files, 0)
+        } else {
+            let limit = input
+                .max_bytes
+                .or(max_pull_bytes)
+                .unwrap_or(DEFAULT_MAX_PULL_BYTES);
+            let exclude = input.exclude.unwrap_or_default();
+            let include = input.include.unwrap_or_default();
+            match plan_pull(&conn, &remote_path, &exclude, &include, limit, timeouts).await {
+                Ok(plan) => plan,
+                Err(e) => {
+                    return SyncOutput::failure(remote_path, e)
+                        .to_json("sync_pull")
+                        .await
+                }
+            }
+        };
+        let mut output = pull_directory(
+            &conn,
+            &remote_path,
+            &local_dest,
+            &files,
+            total_bytes,
+            input.strategy,
+            timeouts,
+        )
+        .await;
+        if verify && !output.transferred.is_empty() {
+            let files = pulled_files(Path::new(&local_dest), &output.transferred);
+            let result = sync_verify::verify_files(
+                conn.as_ref(),
+                Path::new(&local_dest),
+                &remote_path,
+                &files,
+                timeouts,
+            )
+            .await;
+            sync_verify::attach(&mut output, &remote_path, result);
+        }
+        return output.to_json("sync_pull").await;
     }
 
     // Single file
@@ -41,15 +125,150 @@ pub async fn handle(conn: Arc<SshConnection>, input: SyncPullInput) -> String {
             |n| n.to_string_lossy().to_string(),
         )
     });
-    pull_single_file(&conn, &remote_path, &local_dest).await
+    let mut output = pull_single_file(
+        &conn,
+        &remote_path,
+        &local_dest,
+        size,
+        input.strategy,
+        &progress,
+        timeouts,
+    )
+    .await;
+    if verify && !output.transferred.is_empty() {
+        let local = Path::new(&local_dest);
+        let result = sync_verify::verify_file(conn.as_ref(), local, &remote_path, timeouts)
+            .await
+            .map(|status| {
+                vec![FileVerification {
+                    path: local_dest.clone(),
+                    status,
+                }]
+            });
+        sync_verify::attach(&mut output, &remote_path, result);
+    }
+    output.to_json("sync_pull").await
 }
 
-async fn pull_single_file(conn: &SshConnection, remote_path: &str, local_dest: &str) -> String {
+/// Regular files among pulled entries, as relative paths. Tar listings
+/// include `./` prefixes and directory entries, which are dropped here.
+fn pulled_files(local_dest: &Path, transferred: &[String]) -> Vec<String> {
+    transferred
+        .iter()
+        .map(|p| p.strip_prefix("./").unwrap_or(p))
+        .filter(|p| !p.is_empty() && local_dest.join(p).is_file())
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Parse the probe output: `dir`, or `file <size>`.
+fn parse_probe(stdout: &str) -> (bool, u64) {
+    let line = stdout.trim();
+    if line == "dir" {
+        return (true, 0);
+    }
+    let size = line
+        .strip_prefix("file")
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0);
+    (false, size)
+}
+
+/// The probe for remotes without a POSIX shell: SFTP `stat`.
+async fn probe_sftp(conn: &SshConnection, remote_path: &str) -> (bool, u64) {
+    let Ok(sftp) = conn.open_sftp().await else {
+        return (false, 0);
+    };
+    match sftp.metadata(home_relative(remote_path)).await {
+        Ok(meta) if meta.is_dir() => (true, 0),
+        Ok(meta) => (false, meta.size.unwrap_or(0)),
+        Err(_) => (false, 0),
+    }
+}
+
+async fn pull_single_file(
+    conn: &SshConnection,
+    remote_path: &str,
+    local_dest: &str,
+    size: u64,
+    requested: Option<TransferStrategy>,
+    progress: &Progress,
+    timeouts: TimeoutPolicy,
+) -> SyncOutput {
+    let shape = TransferShape {
+        is_dir: false,
+        file_count: Some(1),
+        total_bytes: size,
+    };
+    let caps = if requested.is_some() || !conn.shell().is_posix() {
+        conn.capabilities().await
+    } else {
+        RemoteCapabilities::default()
+    };
+    let strategy = match select_strategy(&shape, caps, requested) {
+        Ok(s) => s,
+        Err(e) => return SyncOutput::failure(remote_path, e),
+    };
+
+    let started = Instant::now();
+    let result = match strategy {
+        TransferStrategy::Sftp if size >= CHUNKED_BYTES => {
+            match sync_chunked::pull_file_chunked(conn, remote_path, local_dest, progress).await {
+                Ok(result) => result,
+                Err(e) if requested.is_none() && !caps.sftp_only => {
+                    tracing::debug!("SFTP pull failed, falling back to cat: {e}");
+                    let started = Instant::now();
+                    return pull_file_cat(conn, remote_path, local_dest)
+                        .await
+                        .into_output(TransferStrategy::Cat, started);
+                }
+                Err(e) => {
+                    let mut result = TransferResult::default();
+                    result.fail(remote_path, e);
+                    result
+                }
+            }
+        }
+        TransferStrategy::Sftp => {
+            let result = sync_transfer::pull_file_sftp(conn, remote_path, local_dest).await;
+            // Auto-selected SFTP falls back to a cat pipe when the remote has no
+            // sftp subsystem.
+            if requested.is_none() && !caps.sftp_only && result.transferred.is_empty() {
+                tracing::debug!("SFTP pull failed, falling back to cat: {:?}", result.failed);
+                let started = Instant::now();
+                return pull_file_cat(conn, remote_path, local_dest)
+                    .await
+                    .into_output(TransferStrategy::Cat, started);
+            }
+            result
+        }
+        TransferStrategy::Rsync => {
+            let src = sync_transfer::rsync_remote_spec(conn.params(), remote_path);
+            let mut result =
+                sync_transfer::rsync_single_file(conn, &src, local_dest, true, timeouts).await;
+            if result.failed.is_empty() {
+                result.transferred = vec![local_dest.to_string()];
+            }
+            result
+        }
+        TransferStrategy::Cat | TransferStrategy::Tar => {
+            pull_file_cat(conn, remote_path, local_dest).await
+        }
+    };
+    result.into_output(strategy, started)
+}
+
+async fn pull_file_cat(
+    conn: &SshConnection,
+    remote_path: &str,
+    local_dest: &str,
+) -> TransferResult {
+    let mut result = TransferResult::default();
     let content = match conn.read_file_raw(remote_path).await {
         Ok(c) => c,
         Err(e) => {
-            return SyncOutput::failure(remote_path, format!("Error reading remote file: {e}"))
-                .to_json();
+            result.fail(remote_path, format!("Error reading remote file: {e}"));
+            return result;
         }
     };
 
@@ -57,76 +276,288 @@ async fn pull_single_file(conn: &SshConnection, remote_path: &str, local_dest: &
     if let Some(parent) = Path::new(local_dest).parent() {
         if !parent.as_os_str().is_empty() {
             if let Err(e) = tokio::fs::create_dir_all(parent).await {
-                return SyncOutput::failure(
-                    local_dest,
-                    format!("Error creating local directory: {e}"),
-                )
-                .to_json();
+                result.fail(local_dest, format!("Error creating local directory: {e}"));
+                return result;
             }
         }
     }
 
     match tokio::fs::write(local_dest, &content).await {
-        Ok(()) => SyncOutput::success(vec![local_dest.to_string()]).to_json(),
-        Err(e) => SyncOutput::failure(local_dest, e.to_string()).to_json(),
+        Ok(()) => {
+            result.bytes = content.len() as u64;
+            result.transferred.push(local_dest.to_string());
+        }
+        Err(e) => result.fail(local_dest, e),
     }
+    result
 }
 
 async fn pull_directory(
     conn: &SshConnection,
     remote_path: &str,
     local_dest: &str,
-    files_filter: Option<&[String]>,
-) -> String {
-    // Build tar command
-    let files_arg = match files_filter {
-        Some(files) => files
-            .iter()
-            .map(|f| shell_escape(f))
-            .collect::<Vec<_>>()
-            .join(" "),
-        None => ".".to_string(),
+    files: &[String],
+    total_bytes: u64,
+    requested: Option<TransferStrategy>,
+    timeouts: TimeoutPolicy,
+) -> SyncOutput {
+    if files.is_empty() {
+        return SyncOutput::success(Vec::new());
+    }
+    let shape = TransferShape {
+        is_dir: true,
+        file_count: Some(files.len()),
+        total_bytes,
+    };
+    let strategy = match select_strategy(&shape, conn.capabilities().await, requested) {
+        Ok(s) => s,
+        Err(e) => return SyncOutput::failure(remote_path, e),
     };
+
+    let started = Instant::now();
+    let dest = Path::new(local_dest);
+    let result = match strategy {
+        TransferStrategy::Tar => pull_tar(conn, remote_path, local_dest, files, timeouts).await,
+        TransferStrategy::Rsync => {
+            sync_transfer::pull_files_rsync(conn, remote_path, Some(files), dest, timeouts).await
+        }
+        TransferStrategy::Cat => {
+            sync_transfer::pull_files_cat(conn, remote_path, files, dest).await
+        }
+        TransferStrategy::Sftp => {
+            sync_transfer::pull_files_sftp(conn, remote_path, files, dest).await
+        }
+    };
+    result.into_output(strategy, started)
+}
+
+/// The files a directory pull transfers and their total size, or why the
+/// pull is refused.
+async fn plan_pull(
+    conn: &SshConnection,
+    remote_path: &str,
+    exclude: &[String],
+    include: &[String],
+    limit: u64,
+    timeouts: TimeoutPolicy,
+) -> Result<(Vec<String>, u64), String> {
+    let mut listed = list_pull_files(conn, remote_path, exclude, timeouts)
+        .await
+        .map_err(|e| e.to_string())?;
+    retain_included(&mut listed, include, |(path, _)| path);
+    check_pull_size(&listed, limit)?;
+    let total = listed.iter().map(|(_, size)| size).sum();
+    Ok((listed.into_iter().map(|(path, _)| path).collect(), total))
+}
+
+/// Files under `remote_path` with their sizes, minus `.git/`, whatever the
+/// remote `.gitignore` ignores, and `exclude`.
+async fn list_pull_files(
+    conn: &SshConnection,
+    remote_path: &str,
+    exclude: &[String],
+    timeouts: TimeoutPolicy,
+) -> anyhow::Result<Vec<(String, u64)>> {
+    let caps = conn.capabilities().await;
+    if caps.sftp_only {
+        let gitignore = conn
+            .read_file(&format!("{remote_path}/.gitignore"))
+            .await
+            .unwrap_or_default();
+        let ignore = pull_ignore(&gitignore, exclude);
+        let files = conn
+            .sftp_walk_sizes(remote_path, &|dir| {
+                dir == ".git" || ignore.is_ignored(dir, true)
+            })
+            .await?;
+        return Ok(select_pull_files(files, &ignore));
+    }
+
     let command = format!(
-        "tar czf - -C {} {}",
+        "cd {} && {{ cat .gitignore 2>/dev/null; echo; echo '{LISTING_MARKER}'; \
+         find . -path ./.git -prune -o -type f {}; }}",
         shell_escape_remote_path(remote_path),
-        files_arg
+        caps.stat_action(false),
     );
+    let result = conn
+        .exec(&command, timeouts.operation(LIST_TIMEOUT_MS))
+        .await?;
+    if result.exit_code != 0 {
+        return Err(anyhow::anyhow!(
+            "Remote listing failed: {}",
+            result.stderr.trim()
+        ));
+    }
+    let (gitignore, files) = parse_listing(&result.stdout);
+    Ok(select_pull_files(files, &pull_ignore(&gitignore, exclude)))
+}
 
-    // Get raw tar bytes from remote
-    let raw_result = match conn.exec_raw(&command, None, Some(SYNC_TIMEOUT_MS)).await {
-        Ok(r) => r,
-        Err(e) => {
-            return SyncOutput::failure(remote_path, format!("Error running remote tar: {e}"))
-                .to_json();
-        }
+/// Parse the listing command's output: the remote `.gitignore`, then
+/// `<size> ./<path>` lines. Malformed lines are skipped.
+#[must_use]
+pub fn parse_listing(stdout: &str) -> (String, Vec<(String, u64)>) {
+    let Some((gitignore, listing)) = stdout.split_once(LISTING_MARKER) else {
+        return (String::new(), Vec::new());
     };
+    let files = listing
+        .lines()
+        .filter_map(|line| {
+            let (size, path) = line.split_once(' ')?;
+            let path = path.strip_prefix("./").unwrap_or(path);
+            Some((path.to_string(), size.parse().ok()?))
+        })
+        .filter(|(path, _)| !path.is_empty())
+        .collect();
+    (gitignore.to_string(), files)
+}
 
-    if raw_result.exit_code != 0 {
-        return SyncOutput::failure(
-            remote_path,
-            format!(
-                "Remote tar failed (exit {}): {}",
-                raw_result.exit_code, raw_result.stderr
-            ),
-        )
-        .to_json();
+/// Rules for a directory pull: the remote `.gitignore` plus `exclude`.
+#[must_use]
+pub fn pull_ignore(gitignore: &str, exclude: &[String]) -> GitIgnore {
+    let mut ignore = GitIgnore::parse(gitignore);
+    ignore.extend_patterns(exclude);
+    ignore
+}
+
+/// Drop `.git/` and ignored files from a listing, sorted by path.
+#[must_use]
+pub fn select_pull_files(files: Vec<(String, u64)>, ignore: &GitIgnore) -> Vec<(String, u64)> {
+    let mut files: Vec<(String, u64)> = files
+        .into_iter()
+        .filter(|(path, _)| !path.starts_with(".git/") && !ignore.is_path_ignored(path))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Refuse a pull whose files add up to more than `limit` bytes (0 disables
+/// the cap), naming the largest top-level entries so the caller knows what
+/// to exclude.
+///
+/// # Errors
+///
+/// Returns the message to report when the pull is over the cap.
+pub fn check_pull_size(files: &[(String, u64)], limit: u64) -> Result<(), String> {
+    let total: u64 = files.iter().map(|(_, size)| size).sum();
+    if limit == 0 || total <= limit {
+        return Ok(());
     }
 
+    let mut by_entry: HashMap<String, u64> = HashMap::new();
+    for (path, size) in files {
+        let entry = match path.split_once('/') {
+            Some((dir, _)) => format!("{dir}/"),
+            None => path.clone(),
+        };
+        *by_entry.entry(entry).or_default() += size;
+    }
+    let mut largest: Vec<(String, u64)> = by_entry.into_iter().collect();
+    largest.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let largest: Vec<String> = largest
+        .iter()
+        .take(LARGEST_SHOWN)
+        .map(|(entry, size)| format!("{entry} ({})", format_size(*size)))
+        .collect();
+
+    Err(format!(
+        "Pull refused: {} files totalling {} exceed the {} limit. Largest: {}. \
+         Add 'exclude' patterns, pull specific 'files', or raise 'max_bytes'.",
+        files.len(),
+        format_size(total),
+        format_size(limit),
+        largest.join(", "),
+    ))
+}
+
+/// Human-readable byte count with one decimal, e.g. `1.5 GiB`.
+#[must_use]
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut divisor: u64 = 1024;
+    let mut unit = 0;
+    while unit < UNITS.len() - 1 && bytes / divisor >= 1024 {
+        divisor *= 1024;
+        unit += 1;
+    }
+    let tenths = u128::from(bytes) * 10 / u128::from(divisor);
+    format!("{}.{} {}", tenths / 10, tenths % 10, UNITS[unit])
+}
+
+/// Pull `files` within a directory as one gzip'd tar stream. The file list
+/// goes to tar on stdin, `./`-prefixed so no name reads as an option. The
+/// archive is written to a temporary file in `local_dest` as it arrives and
+/// only extracted once the remote tar succeeded, so a failed pull leaves
+/// nothing half-written and memory use doesn't grow with its size.
+async fn pull_tar(
+    conn: &SshConnection,
+    remote_path: &str,
+    local_dest: &str,
+    files: &[String],
+    timeouts: TimeoutPolicy,
+) -> TransferResult {
+    let mut result = TransferResult::default();
+
+    let command = format!(
+        "tar czf - -C {} -T -",
+        shell_escape_remote_path(remote_path)
+    );
+    let list: String = files
+        .iter()
+        .flat_map(|f| ["./", f.as_str(), "\n"])
+        .collect();
+
     // Create local destination
     let dest = Path::new(local_dest);
     if let Err(e) = tokio::fs::create_dir_all(dest).await {
-        return SyncOutput::failure(local_dest, format!("Error creating local directory: {e}"))
-            .to_json();
+        result.fail(local_dest, format!("Error creating local directory: {e}"));
+        return result;
     }
+    let archive_path = dest.join(format!(".ssh-hub-pull-{}.tar.gz", std::process::id()));
+    let archive_file = match tokio::fs::File::create(&archive_path).await {
+        Ok(f) => f,
+        Err(e) => {
+            result.fail(local_dest, format!("Error creating temporary archive: {e}"));
+            return result;
+        }
+    };
 
-    // Extract tar.gz locally (synchronous I/O — run off the tokio runtime)
-    let dest_owned = dest.to_path_buf();
-    let tar_data = raw_result.stdout;
-    let local_dest_str = local_dest.to_string();
+    let streamed = conn
+        .exec_stream_stdout(
+            &command,
+            Some(list.as_bytes()),
+            BufWriter::new(archive_file),
+            timeouts.operation(SYNC_TIMEOUT_MS),
+        )
+        .await;
+    match streamed {
+        Ok(output) if output.exit_code == 0 => {
+            result.bytes = output.bytes;
+            extract_archive(&mut result, &archive_path, local_dest).await;
+        }
+        Ok(output) => result.fail(
+            remote_path,
+            format!(
+                "Remote tar failed (exit {}): {}",
+                output.exit_code, output.stderr
+            ),
+        ),
+        Err(e) => result.fail(remote_path, format!("Error running remote tar: {e}")),
+    }
+    let _ = tokio::fs::remove_file(&archive_path).await;
+    result
+}
 
+/// Extract a tar.gz file into `local_dest` (synchronous I/O — run off the
+/// tokio runtime), recording the extracted paths.
+async fn extract_archive(result: &mut TransferResult, archive_path: &Path, local_dest: &str) {
+    let archive_path = archive_path.to_path_buf();
+    let dest_owned = PathBuf::from(local_dest);
     match tokio::task::spawn_blocking(move || {
-        let decoder = GzDecoder::new(Cursor::new(&tar_data));
+        let file = std::fs::File::open(&archive_path).map_err(|e| e.to_string())?;
+        let decoder = GzDecoder::new(std::io::BufReader::new(file));
         let mut archive = tar::Archive::new(decoder);
 
         let entries = archive.entries().map_err(|e| e.to_string())?;
@@ -144,12 +575,8 @@ async fn pull_directory(
     })
     .await
     {
-        Ok(Ok(pulled)) => SyncOutput::success(pulled).to_json(),
-        Ok(Err(e)) => {
-            SyncOutput::failure(&local_dest_str, format!("Error extracting archive: {e}")).to_json()
-        }
-        Err(e) => {
-            SyncOutput::failure(&local_dest_str, format!("Extraction task panicked: {e}")).to_json()
-        }
+        Ok(Ok(pulled)) => result.transferred = pulled,
+        Ok(Err(e)) => result.fail(local_dest, format!("Error extracting archive: {e}")),
+        Err(e) => result.fail(local_dest, format!("Extraction task panicked: {e}")),
     }
 }
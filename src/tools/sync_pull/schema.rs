@@ -1,7 +1,9 @@
 use rmcp::schemars::{self, JsonSchema};
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize, JsonSchema)]
+use crate::tools::sync_transfer::TransferStrategy;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct SyncPullInput {
     #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
     pub server: String,
@@ -12,7 +14,7 @@ pub struct SyncPullInput {
     pub remote_path: String,
 
     #[schemars(
-        description = "Local destination path. For files: defaults to the filename in the current directory. For directories: defaults to the current directory"
+        description = "Local destination path. If omitted and remote_path lies inside the remote root of a project mapped to this server, the same path within the local project. Otherwise, for files: the filename in the current directory; for directories: the current directory"
     )]
     pub local_path: Option<String>,
 
@@ -20,4 +22,29 @@ pub struct SyncPullInput {
         description = "Specific files to pull, as relative paths within remote_path. Only used when remote_path is a directory. If omitted, pulls all files"
     )]
     pub files: Option<Vec<String>>,
+
+    #[schemars(
+        description = "Extra exclusion patterns (gitignore syntax) for directory pulls. Applied on top of the remote .gitignore. Ignored when 'files' is given. Example: [\"*.log\", \"tmp/\", \"dist\"]"
+    )]
+    pub exclude: Option<Vec<String>>,
+
+    #[schemars(
+        description = "Only pull files matching one of these patterns (gitignore syntax; a directory pattern such as \"logs/\" takes everything under it). The remote .gitignore and 'exclude' still apply. Ignored when 'files' is given. Example: [\"*.csv\", \"results/\"]"
+    )]
+    pub include: Option<Vec<String>>,
+
+    #[schemars(
+        description = "Refuse a directory pull whose files add up to more than this many bytes (0 disables the cap). Defaults to [sync] max_pull_bytes from the config, or 512 MiB"
+    )]
+    pub max_bytes: Option<u64>,
+
+    #[schemars(
+        description = "Override the transfer strategy: 'cat' (one pipe per file), 'tar' (gzip'd tar stream, directories only), 'sftp', or 'rsync' (local rsync over system ssh). If omitted, chosen from file count, size, and remote capabilities"
+    )]
+    pub strategy: Option<TransferStrategy>,
+
+    #[schemars(
+        description = "After transferring, compare sha256 of local and remote content and report a per-file status (ok, mismatch, missing) in 'verification'"
+    )]
+    pub verify: Option<bool>,
 }
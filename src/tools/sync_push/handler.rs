@@ -1,22 +1,42 @@
+use std::io::Write;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Instant;
 
 use flate2::write::GzEncoder;
 use flate2::Compression;
+use tokio_util::io::SyncIoBridge;
 
 use super::schema::SyncPushInput;
-use crate::connection::SshConnection;
-use crate::tools::sync_types::SyncOutput;
-use crate::utils::gitignore::GitIgnore;
-use crate::utils::path::{normalize_remote_path, shell_escape_remote_path, validate_path_within};
+use crate::connection::{atomic_extract_command, RemoteCapabilities, SshConnection};
+use crate::timeout::TimeoutPolicy;
+use crate::tools::sync_chunked::{self, Progress, CHUNKED_BYTES};
+use crate::tools::sync_manifest::{build_local_manifest, ManifestCache};
+use crate::tools::sync_transfer::{
+    self, select_strategy, FileAttrs, TransferResult, TransferShape, TransferStrategy,
+};
+use crate::tools::sync_types::{FailedTransfer, ScanSummary, SyncOutput};
+use crate::tools::sync_verify::{self, FileVerification};
+use crate::utils::gitignore::retain_included;
+use crate::utils::path::{normalize_remote_path, validate_path_within};
+use crate::utils::walk::{load_gitignore, walk_dir_with_sizes};
 
-/// Timeout for tar-based directory sync operations (2 minutes).
+/// Timeout for tar-based directory sync operations (2 minutes), unless the
+/// server's [`TimeoutPolicy`] raises it.
 const SYNC_TIMEOUT_MS: u64 = 120_000;
 
-/// Build a tar.gz archive in memory from files under `base_dir`.
+/// Bytes of archive buffered between the compressing task and the SSH
+/// channel. Bounds memory use however large the pushed tree is.
+const PIPE_BUFFER_BYTES: usize = 256 * 1024;
+
+/// Write a tar.gz archive of files under `base_dir` to `out`.
 /// `files` are relative paths within `base_dir`.
-fn build_tar_gz(base_dir: &Path, files: &[String]) -> anyhow::Result<Vec<u8>> {
-    let enc = GzEncoder::new(Vec::new(), Compression::default());
+///
+/// # Errors
+///
+/// Returns an error if a file can't be read or `out` can't be written.
+pub fn write_tar_gz(out: impl Write, base_dir: &Path, files: &[String]) -> anyhow::Result<()> {
+    let enc = GzEncoder::new(out, Compression::default());
     let mut tar = tar::Builder::new(enc);
 
     for file in files {
@@ -25,65 +45,17 @@ fn build_tar_gz(base_dir: &Path, files: &[String]) -> anyhow::Result<Vec<u8>> {
             .map_err(|e| anyhow::anyhow!("Failed to add '{file}' to archive: {e}"))?;
     }
 
-    let enc = tar.into_inner()?;
-    let bytes = enc.finish()?;
-    Ok(bytes)
-}
-
-/// Recursively collect files under `dir`, respecting .gitignore and exclude patterns.
-/// Skips symlinks, `.git/`, and gitignored entries.
-fn walk_dir(dir: &Path, gitignore: &GitIgnore) -> anyhow::Result<Vec<String>> {
-    let mut files = Vec::new();
-    walk_dir_inner(dir, dir, gitignore, &mut files)?;
-    Ok(files)
-}
-
-fn walk_dir_inner(
-    base: &Path,
-    current: &Path,
-    gitignore: &GitIgnore,
-    files: &mut Vec<String>,
-) -> anyhow::Result<()> {
-    for entry in std::fs::read_dir(current)? {
-        let entry = entry?;
-        let file_type = entry.file_type()?;
-
-        // Skip symlinks — file_type() uses lstat, doesn't follow
-        if file_type.is_symlink() {
-            continue;
-        }
-
-        let path = entry.path();
-        let relative = path
-            .strip_prefix(base)
-            .map_err(|e| anyhow::anyhow!("Path prefix error: {e}"))?
-            .to_string_lossy()
-            .to_string();
-
-        if file_type.is_dir() {
-            // Always skip .git
-            if entry.file_name().to_str() == Some(".git") {
-                continue;
-            }
-
-            // Check gitignore for this directory — skips the entire subtree
-            if gitignore.is_ignored(&relative, true) {
-                continue;
-            }
-
-            walk_dir_inner(base, &path, gitignore, files)?;
-        } else if file_type.is_file() {
-            if gitignore.is_ignored(&relative, false) {
-                continue;
-            }
-
-            files.push(relative);
-        }
-    }
+    tar.into_inner()?.finish()?.flush()?;
     Ok(())
 }
 
-pub async fn handle(conn: Arc<SshConnection>, input: SyncPushInput) -> String {
+pub async fn handle(
+    conn: Arc<SshConnection>,
+    input: SyncPushInput,
+    cache: Arc<ManifestCache>,
+    progress: Progress,
+    timeouts: TimeoutPolicy,
+) -> String {
     let base_path = conn.remote_path().to_string();
     let local = Path::new(&input.local_path);
 
@@ -91,101 +63,456 @@ pub async fn handle(conn: Arc<SshConnection>, input: SyncPushInput) -> String {
         .remote_path
         .unwrap_or_else(|| normalize_remote_path(&input.local_path, &base_path));
 
+    let verify = input.verify.unwrap_or(false);
+    let options = PushOptions {
+        requested: input.strategy,
+        preserve: input.preserve.unwrap_or(true),
+        timeouts,
+    };
+    let feature = if verify {
+        Some("verify")
+    } else if input.changed_only == Some(true) {
+        Some("changed_only")
+    } else {
+        None
+    };
+    if let Some(error) = feature.and_then(|f| conn.posix_required(f)) {
+        return SyncOutput::failure(input.local_path, error)
+            .to_json("sync_push")
+            .await;
+    }
+
     if local.is_file() {
-        return push_single_file(&conn, local, &remote_dest).await;
+        let mut output = push_single_file(&conn, local, &remote_dest, options, &progress).await;
+        if verify && !output.transferred.is_empty() {
+            let result = sync_verify::verify_file(conn.as_ref(), local, &remote_dest, timeouts)
+                .await
+                .map(|status| {
+                    vec![FileVerification {
+                        path: input.local_path.clone(),
+                        status,
+                    }]
+                });
+            sync_verify::attach(&mut output, &input.local_path, result);
+        }
+        return output.to_json("sync_push").await;
     }
 
     if local.is_dir() {
-        return push_directory(&conn, local, &remote_dest, input.exclude.as_deref()).await;
+        let changed_only = input
+            .changed_only
+            .unwrap_or(false)
+            .then_some((cache.as_ref(), input.server.as_str()));
+        let selection = match input.files {
+            Some(files) => FileSelection::Listed(files),
+            None => FileSelection::Walk {
+                exclude: input.exclude,
+                include: input.include.unwrap_or_default(),
+            },
+        };
+        let mut output =
+            push_directory(&conn, local, &remote_dest, selection, changed_only, options).await;
+        if verify && !output.transferred.is_empty() {
+            let result = sync_verify::verify_files(
+                conn.as_ref(),
+                local,
+                &remote_dest,
+                &output.transferred,
+                timeouts,
+            )
+            .await;
+            sync_verify::attach(&mut output, &input.local_path, result);
+        }
+        return output.to_json("sync_push").await;
     }
 
-    SyncOutput::failure(input.local_path, "Path is neither a file nor a directory").to_json()
+    SyncOutput::failure(input.local_path, "Path is neither a file nor a directory")
+        .to_json("sync_push")
+        .await
+}
+
+/// How a push moves files, whichever path it takes.
+#[derive(Clone, Copy)]
+struct PushOptions {
+    /// The strategy the call asked for, or `None` to pick one.
+    requested: Option<TransferStrategy>,
+    /// Keep the local mode and mtime.
+    preserve: bool,
+    timeouts: TimeoutPolicy,
 }
 
-async fn push_single_file(conn: &SshConnection, local: &Path, remote_dest: &str) -> String {
+async fn push_single_file(
+    conn: &SshConnection,
+    local: &Path,
+    remote_dest: &str,
+    options: PushOptions,
+    progress: &Progress,
+) -> SyncOutput {
+    let PushOptions {
+        requested,
+        preserve,
+        timeouts,
+    } = options;
+    let path_str = local.display().to_string();
+    let shape = TransferShape {
+        is_dir: false,
+        file_count: Some(1),
+        total_bytes: std::fs::metadata(local).map_or(0, |m| m.len()),
+    };
+    let caps = if requested.is_some() || !conn.shell().is_posix() {
+        conn.capabilities().await
+    } else {
+        RemoteCapabilities::default()
+    };
+    let strategy = match select_strategy(&shape, caps, requested) {
+        Ok(s) => s,
+        Err(e) => return SyncOutput::failure(path_str, e),
+    };
+
+    let started = Instant::now();
+    let result = match strategy {
+        TransferStrategy::Sftp if shape.total_bytes >= CHUNKED_BYTES => {
+            match sync_chunked::push_file_chunked(conn, local, remote_dest, preserve, progress)
+                .await
+            {
+                Ok(result) => result,
+                Err(e) if requested.is_none() && !caps.sftp_only => {
+                    tracing::debug!("SFTP push failed, falling back to cat: {e}");
+                    let started = Instant::now();
+                    return push_file_cat(conn, local, remote_dest, preserve)
+                        .await
+                        .into_output(TransferStrategy::Cat, started);
+                }
+                Err(e) => {
+                    let mut result = TransferResult::default();
+                    result.fail(path_str, e);
+                    result
+                }
+            }
+        }
+        TransferStrategy::Sftp => {
+            let result = sync_transfer::push_file_sftp(conn, local, remote_dest, preserve).await;
+            // Auto-selected SFTP falls back to a cat pipe when the remote has no
+            // sftp subsystem.
+            if requested.is_none() && !caps.sftp_only && result.transferred.is_empty() {
+                tracing::debug!("SFTP push failed, falling back to cat: {:?}", result.failed);
+                let started = Instant::now();
+                return push_file_cat(conn, local, remote_dest, preserve)
+                    .await
+                    .into_output(TransferStrategy::Cat, started);
+            }
+            result
+        }
+        TransferStrategy::Rsync => {
+            let dst = sync_transfer::rsync_remote_spec(conn.params(), remote_dest);
+            sync_transfer::rsync_single_file(conn, &path_str, &dst, preserve, timeouts).await
+        }
+        TransferStrategy::Cat | TransferStrategy::Tar => {
+            push_file_cat(conn, local, remote_dest, preserve).await
+        }
+    };
+    result.into_output(strategy, started)
+}
+
+async fn push_file_cat(
+    conn: &SshConnection,
+    local: &Path,
+    remote_dest: &str,
+    preserve: bool,
+) -> TransferResult {
+    let mut result = TransferResult::default();
     let path_str = local.display().to_string();
 
     let content = match tokio::fs::read(local).await {
         Ok(c) => c,
         Err(e) => {
-            return SyncOutput::failure(&path_str, format!("Error reading local file: {e}"))
-                .to_json();
+            result.failed.push(FailedTransfer {
+                path: path_str,
+                error: format!("Error reading local file: {e}"),
+            });
+            return result;
         }
     };
 
     match conn.write_file_raw(remote_dest, &content).await {
-        Ok(()) => SyncOutput::success(vec![path_str]).to_json(),
-        Err(e) => SyncOutput::failure(path_str, e.to_string()).to_json(),
+        Ok(()) => {
+            result.bytes = content.len() as u64;
+            result.transferred.push(path_str.clone());
+            // `cat >` creates files with the umask's mode and keeps the mode
+            // of a file it replaces, so the local one is applied afterwards
+            if let Some(attrs) = FileAttrs::of(local).filter(|_| preserve) {
+                let files = [(remote_dest.to_string(), attrs)];
+                if let Err(e) = sync_transfer::apply_attrs(conn, &files).await {
+                    result.fail(path_str, e);
+                }
+            }
+        }
+        Err(e) => result.failed.push(FailedTransfer {
+            path: path_str,
+            error: e.to_string(),
+        }),
     }
+    result
+}
+
+/// Which files of a directory to push.
+enum FileSelection {
+    /// Walk the directory, skipping .gitignore'd files and `exclude`, and
+    /// keeping only files matching `include` when it isn't empty.
+    Walk {
+        exclude: Option<Vec<String>>,
+        include: Vec<String>,
+    },
+    /// Exactly these relative paths.
+    Listed(Vec<String>),
+}
+
+/// Check requested relative paths against `base_dir`: each must stay inside
+/// it and name a regular file. Returns the accepted paths, normalized
+/// relative to `base_dir`, and a failure for each rejected one.
+#[must_use]
+pub fn select_push_files(base_dir: &Path, files: &[String]) -> (Vec<String>, Vec<FailedTransfer>) {
+    let canon_base = base_dir
+        .canonicalize()
+        .unwrap_or_else(|_| base_dir.to_path_buf());
+    let mut accepted = Vec::new();
+    let mut failed = Vec::new();
+    for file in files {
+        let relative = validate_path_within(base_dir, file).and_then(|full| {
+            if !full.is_file() {
+                anyhow::bail!("'{file}' is not a regular file");
+            }
+            Ok(full
+                .strip_prefix(&canon_base)
+                .map(|p| p.to_string_lossy().into_owned())?)
+        });
+        match relative {
+            Ok(path) if !accepted.contains(&path) => accepted.push(path),
+            Ok(_) => {}
+            Err(e) => failed.push(FailedTransfer {
+                path: file.clone(),
+                error: e.to_string(),
+            }),
+        }
+    }
+    (accepted, failed)
 }
 
 async fn push_directory(
     conn: &SshConnection,
     local_dir: &Path,
     remote_dest: &str,
-    exclude: Option<&[String]>,
-) -> String {
+    selection: FileSelection,
+    changed_only: Option<(&ManifestCache, &str)>,
+    options: PushOptions,
+) -> SyncOutput {
     let dir_str = local_dir.display().to_string();
 
-    // Collect file list — gitignore-aware, symlink-safe
+    // Collect file list — gitignore-aware, symlink-safe — or check the listed one
     let dir_owned = local_dir.to_path_buf();
-    let exclude_owned = exclude.map(ToOwned::to_owned);
-    let files = match tokio::task::spawn_blocking(move || {
-        let mut gitignore = GitIgnore::from_file(&dir_owned.join(".gitignore"));
-        if let Some(patterns) = &exclude_owned {
-            gitignore.extend_patterns(patterns);
+    let (files, rejected, scanned) = match tokio::task::spawn_blocking(move || match selection {
+        FileSelection::Walk { exclude, include } => {
+            let started = Instant::now();
+            let gitignore = load_gitignore(&dir_owned, exclude.as_deref());
+            walk_dir_with_sizes(&dir_owned, &gitignore).map(|mut files| {
+                retain_included(&mut files, &include, |file| file.path.as_str());
+                let scanned = ScanSummary {
+                    files: files.len(),
+                    bytes: files.iter().map(|file| file.size).sum(),
+                    elapsed_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+                };
+                let files = files.into_iter().map(|file| file.path).collect();
+                (files, Vec::new(), Some(scanned))
+            })
+        }
+        FileSelection::Listed(files) => {
+            let (files, rejected) = select_push_files(&dir_owned, &files);
+            Ok((files, rejected, None))
         }
-        walk_dir(&dir_owned, &gitignore)
     })
     .await
     {
         Ok(Ok(f)) => f,
         Ok(Err(e)) => {
-            return SyncOutput::failure(&dir_str, format!("Error walking directory: {e}"))
-                .to_json();
+            return SyncOutput::failure(&dir_str, format!("Error walking directory: {e}"));
         }
         Err(e) => {
-            return SyncOutput::failure(&dir_str, format!("Directory walk task panicked: {e}"))
-                .to_json();
+            return SyncOutput::failure(&dir_str, format!("Directory walk task panicked: {e}"));
+        }
+    };
+    let mut output = if files.is_empty() && !rejected.is_empty() {
+        SyncOutput::success(vec![])
+    } else {
+        push_files(conn, local_dir, remote_dest, files, changed_only, options).await
+    };
+    output.failed.extend(rejected);
+    output.scanned = scanned;
+    output
+}
+
+async fn push_files(
+    conn: &SshConnection,
+    local_dir: &Path,
+    remote_dest: &str,
+    files: Vec<String>,
+    changed_only: Option<(&ManifestCache, &str)>,
+    options: PushOptions,
+) -> SyncOutput {
+    let dir_str = local_dir.display().to_string();
+    let PushOptions {
+        requested,
+        preserve,
+        timeouts,
+    } = options;
+
+    let files = match changed_only {
+        Some((cache, server)) => {
+            match filter_changed(conn, cache, server, local_dir, remote_dest, files, timeouts).await
+            {
+                Ok(changed) if changed.is_empty() => return SyncOutput::success(vec![]),
+                Ok(changed) => changed,
+                Err(e) => {
+                    return SyncOutput::failure(
+                        &dir_str,
+                        format!("Error comparing manifests: {e}"),
+                    );
+                }
+            }
         }
+        None => files,
     };
 
     if files.is_empty() {
-        return SyncOutput::failure(&dir_str, "No files to push").to_json();
+        return SyncOutput::failure(&dir_str, "No files to push");
     }
 
-    // Build tar.gz in memory (CPU-bound gzip compression)
+    let shape = TransferShape {
+        is_dir: true,
+        file_count: Some(files.len()),
+        total_bytes: files
+            .iter()
+            .filter_map(|f| std::fs::metadata(local_dir.join(f)).ok())
+            .map(|m| m.len())
+            .sum(),
+    };
+    let strategy = match select_strategy(&shape, conn.capabilities().await, requested) {
+        Ok(s) => s,
+        Err(e) => return SyncOutput::failure(&dir_str, e),
+    };
+
+    let started = Instant::now();
+    let result = match strategy {
+        TransferStrategy::Tar => {
+            push_tar(conn, local_dir, remote_dest, files, preserve, timeouts).await
+        }
+        TransferStrategy::Cat => {
+            sync_transfer::push_files_cat(conn, local_dir, remote_dest, &files, preserve).await
+        }
+        TransferStrategy::Sftp => {
+            sync_transfer::push_files_sftp(conn, local_dir, remote_dest, &files, preserve).await
+        }
+        TransferStrategy::Rsync => {
+            sync_transfer::push_files_rsync(
+                conn,
+                local_dir,
+                remote_dest,
+                &files,
+                preserve,
+                timeouts,
+            )
+            .await
+        }
+    };
+    result.into_output(strategy, started)
+}
+
+/// Push `files` as a single gzip'd tar stream extracted on the remote. The
+/// archive is compressed on a blocking thread while it is sent, through a
+/// pipe of [`PIPE_BUFFER_BYTES`].
+async fn push_tar(
+    conn: &SshConnection,
+    local_dir: &Path,
+    remote_dest: &str,
+    files: Vec<String>,
+    preserve: bool,
+    timeouts: TimeoutPolicy,
+) -> TransferResult {
+    let dir_str = local_dir.display().to_string();
+    let mut result = TransferResult::default();
+
+    let (reader, writer) = tokio::io::duplex(PIPE_BUFFER_BYTES);
     let dir_owned = local_dir.to_path_buf();
     let file_list = files.clone(); // kept for the success response
-    let tar_bytes = match tokio::task::spawn_blocking(move || build_tar_gz(&dir_owned, &files))
-        .await
-    {
-        Ok(Ok(b)) => b,
+                                   // If building fails, dropping the writer ends the stream early and the
+                                   // remote discards the truncated archive
+    let compress = tokio::task::spawn_blocking(move || {
+        write_tar_gz(SyncIoBridge::new(writer), &dir_owned, &files)
+    });
+    let command = atomic_extract_command(remote_dest, preserve);
+    let upload = conn.exec_stream_stdin(&command, reader, timeouts.operation(SYNC_TIMEOUT_MS));
+    let (built, sent) = tokio::join!(compress, upload);
+
+    // A failed upload closes the pipe, which fails the build too; report
+    // the cause
+    let output = match sent {
+        Ok(output) => output,
+        Err(e) => {
+            result.fail(&dir_str, e);
+            return result;
+        }
+    };
+    match built {
+        Ok(Ok(())) => {}
         Ok(Err(e)) => {
-            return SyncOutput::failure(&dir_str, format!("Error building archive: {e}")).to_json();
+            result.fail(&dir_str, format!("Error building archive: {e}"));
+            return result;
         }
         Err(e) => {
-            return SyncOutput::failure(&dir_str, format!("Archive build task panicked: {e}"))
-                .to_json();
+            result.fail(&dir_str, format!("Archive build task panicked: {e}"));
+            return result;
         }
-    };
-
-    // Stream to remote via stdin
-    let escaped = shell_escape_remote_path(remote_dest);
-    let command = format!("mkdir -p {escaped} && tar xzf - -C {escaped}");
-    match conn
-        .exec_raw(&command, Some(&tar_bytes), Some(SYNC_TIMEOUT_MS))
-        .await
-    {
-        Ok(result) if result.exit_code == 0 => SyncOutput::success(file_list).to_json(),
-        Ok(result) => SyncOutput::failure(
+    }
+    if output.exit_code == 0 {
+        result.transferred = file_list;
+        result.bytes = output.bytes;
+    } else {
+        result.fail(
             &dir_str,
             format!(
                 "Remote tar extraction failed (exit {}): {}",
-                result.exit_code, result.stderr
+                output.exit_code, output.stderr
             ),
-        )
-        .to_json(),
-        Err(e) => SyncOutput::failure(dir_str, e.to_string()).to_json(),
+        );
     }
+    result
+}
+
+/// Keep only files that are missing on the remote or whose sha256 differs.
+/// The remote manifest comes from the shared cache when fresh and, if the
+/// directory has a change journal, nothing changed there since it was taken.
+async fn filter_changed(
+    conn: &SshConnection,
+    cache: &ManifestCache,
+    server: &str,
+    local_dir: &Path,
+    remote_dest: &str,
+    files: Vec<String>,
+    timeouts: TimeoutPolicy,
+) -> anyhow::Result<Vec<String>> {
+    let dir_owned = local_dir.to_path_buf();
+    let local_manifest =
+        tokio::task::spawn_blocking(move || build_local_manifest(&dir_owned, &files)).await??;
+    let (remote_manifest, _) = cache
+        .get_or_fetch(server, conn, remote_dest, None, timeouts)
+        .await?;
+
+    Ok(local_manifest
+        .into_iter()
+        .filter(|(path, entry)| {
+            remote_manifest
+                .get(path)
+                .is_none_or(|remote| remote.sha256 != entry.sha256)
+        })
+        .map(|(path, _)| path)
+        .collect())
 }
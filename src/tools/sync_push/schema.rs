@@ -1,21 +1,60 @@
 use rmcp::schemars::{self, JsonSchema};
 use serde::Deserialize;
 
+use crate::tools::sync_transfer::TransferStrategy;
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct SyncPushInput {
     #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
     pub server: String,
 
-    #[schemars(description = "Absolute path to a local file or directory to push")]
+    #[schemars(
+        description = "Path to a local file or directory to push. Relative paths resolve from the current directory (the project root)"
+    )]
     pub local_path: String,
 
     #[schemars(
-        description = "Remote destination path. If omitted, mirrors the local path relative to the connection's base path"
+        description = "Remote destination path. If omitted and local_path lies inside a project mapped to this server, the same path within the project's remote root; otherwise mirrors the local path relative to the connection's base path"
     )]
     pub remote_path: Option<String>,
 
     #[schemars(
-        description = "Extra exclusion patterns (gitignore syntax). Applied on top of .gitignore rules. Example: [\"*.log\", \"tmp/\", \"dist\"]"
+        description = "Specific files to push, as relative paths within local_path. Only used when local_path is a directory. Listed files are pushed even if .gitignore or 'exclude' would skip them; paths that don't exist or lead outside local_path are reported in 'failed'. If omitted, pushes the whole directory"
+    )]
+    pub files: Option<Vec<String>>,
+
+    #[schemars(
+        description = "Extra exclusion patterns (gitignore syntax). Applied on top of .gitignore rules. Ignored when 'files' is given. Example: [\"*.log\", \"tmp/\", \"dist\"]"
     )]
     pub exclude: Option<Vec<String>>,
+
+    #[schemars(
+        description = "Only push files matching one of these patterns (gitignore syntax; a directory pattern such as \"src/\" takes everything under it). .gitignore and 'exclude' still apply. Ignored when 'files' is given. Example: [\"*.py\", \"config/\"]"
+    )]
+    pub include: Option<Vec<String>>,
+
+    #[schemars(
+        description = "Only push files whose content differs from the remote (compared by sha256). Reuses the manifest from a recent sync_status call. Directories only"
+    )]
+    pub changed_only: Option<bool>,
+
+    #[schemars(
+        description = "Override the transfer strategy: 'cat' (one pipe per file), 'tar' (gzip'd tar stream, directories only), 'sftp', or 'rsync' (local rsync over system ssh). If omitted, chosen from file count, size, and remote capabilities"
+    )]
+    pub strategy: Option<TransferStrategy>,
+
+    #[schemars(
+        description = "Give remote files the mode (including the executable bit) and modification time of the local files. Default: true. With false, new files get the remote umask's mode and the time of the transfer, and replaced files keep their mode"
+    )]
+    pub preserve: Option<bool>,
+
+    #[schemars(
+        description = "After transferring, compare sha256 of local and remote content and report a per-file status (ok, mismatch, missing) in 'verification'"
+    )]
+    pub verify: Option<bool>,
+
+    #[schemars(
+        description = "Reason for making this change outside the server's maintenance window. Only needed after a call was refused for that reason; the reason is logged"
+    )]
+    pub override_window: Option<String>,
 }
@@ -0,0 +1,126 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use super::schema::{SyncStatusInput, SyncStatusOutput};
+use crate::connection::SshConnection;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::sync_journal::{self, JournalToken};
+use crate::tools::sync_manifest::{build_local_manifest, diff_manifests, ManifestCache};
+use crate::tools::{remote_error, tool_error, ErrorCode};
+use crate::utils::output::fit_json;
+use crate::utils::path::normalize_remote_path;
+use crate::utils::walk::{load_gitignore, walk_dir};
+
+/// Compare a local directory against the remote one by content hash.
+///
+/// Remote paths excluded by the local `.gitignore` (or `exclude` patterns) are
+/// dropped before comparison so ignored build artifacts don't show up as
+/// `remote_only`.
+///
+/// With a valid `since` token, only the remote change journal is consulted.
+/// Every response carries a fresh `journal_token` when a watcher is available,
+/// taken before the remote manifest so no change falls between the two.
+pub async fn handle(
+    conn: Arc<SshConnection>,
+    input: SyncStatusInput,
+    cache: Arc<ManifestCache>,
+    timeouts: TimeoutPolicy,
+) -> String {
+    if let Some(error) = conn.posix_required("sync_status") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    let local = Path::new(&input.local_path);
+    if !local.is_dir() {
+        let message = format!("'{}' is not a local directory", input.local_path);
+        return tool_error(ErrorCode::FileNotFound, message);
+    }
+
+    let remote_dir = input
+        .remote_path
+        .clone()
+        .unwrap_or_else(|| normalize_remote_path(&input.local_path, conn.remote_path()));
+
+    if let Some(since) = input.since.as_deref().and_then(JournalToken::parse) {
+        match sync_journal::changes_since(&conn, &remote_dir, &since).await {
+            Ok(Some((changed, token))) => {
+                let gitignore = load_gitignore(local, input.exclude.as_deref());
+                let output = SyncStatusOutput {
+                    diff: None,
+                    changed_since: Some(
+                        changed
+                            .into_iter()
+                            .filter(|p| !gitignore.is_path_ignored(p))
+                            .collect(),
+                    ),
+                    cached: false,
+                    journal_token: Some(token.to_string()),
+                };
+                return to_json(&output).await;
+            }
+            Ok(None) => tracing::debug!("Journal token {since} is stale, doing full scan"),
+            Err(e) => tracing::debug!("Journal query failed, doing full scan: {e}"),
+        }
+    }
+
+    let dir_owned = local.to_path_buf();
+    let exclude = input.exclude.clone();
+    let local_result = tokio::task::spawn_blocking(move || {
+        let gitignore = load_gitignore(&dir_owned, exclude.as_deref());
+        let files = walk_dir(&dir_owned, &gitignore)?;
+        let manifest = build_local_manifest(&dir_owned, &files)?;
+        Ok::<_, anyhow::Error>((manifest, gitignore))
+    })
+    .await;
+
+    let (local_manifest, gitignore) = match local_result {
+        Ok(Ok(m)) => m,
+        Ok(Err(e)) => return remote_error(&e, format!("Error scanning local directory: {e}")),
+        Err(e) => return tool_error(ErrorCode::Failed, format!("local scan task panicked: {e}")),
+    };
+
+    // Start (or reuse) the change journal before taking the manifest, so the
+    // token covers every change the manifest might miss. Failures are
+    // non-fatal — the full comparison is still valid.
+    let journal_token = match sync_journal::ensure_journal(&conn, &remote_dir).await {
+        Ok(token) => token,
+        Err(e) => {
+            tracing::debug!("Could not start change journal: {e}");
+            None
+        }
+    };
+
+    let (remote_manifest, cached) = match cache
+        .get_or_fetch(
+            &input.server,
+            &conn,
+            &remote_dir,
+            journal_token.clone(),
+            timeouts,
+        )
+        .await
+    {
+        Ok(m) => m,
+        Err(e) => return remote_error(&e, format!("Error building remote manifest: {e}")),
+    };
+
+    let mut remote_filtered = (*remote_manifest).clone();
+    remote_filtered.retain(|path, _| !gitignore.is_path_ignored(path));
+
+    let output = SyncStatusOutput {
+        diff: Some(diff_manifests(&local_manifest, &remote_filtered)),
+        changed_since: None,
+        cached,
+        journal_token: journal_token.map(|t| t.to_string()),
+    };
+    to_json(&output).await
+}
+
+async fn to_json(output: &SyncStatusOutput) -> String {
+    let lists = [
+        "/changed_since",
+        "/diff/local_only",
+        "/diff/remote_only",
+        "/diff/modified",
+    ];
+    fit_json(output, &lists, "sync_status").await
+}
@@ -0,0 +1,49 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+use crate::tools::sync_manifest::ManifestDiff;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct SyncStatusInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+
+    #[schemars(
+        description = "Path to a local directory to compare. Relative paths resolve from the current directory (the project root)"
+    )]
+    pub local_path: String,
+
+    #[schemars(
+        description = "Remote directory to compare against. If omitted and local_path lies inside a project mapped to this server, the same path within the project's remote root; otherwise mirrors the local path relative to the connection's base path"
+    )]
+    pub remote_path: Option<String>,
+
+    #[schemars(
+        description = "Extra exclusion patterns (gitignore syntax). Applied on top of .gitignore rules to both sides"
+    )]
+    pub exclude: Option<Vec<String>>,
+
+    #[schemars(
+        description = "Journal token from a previous sync_status call. When valid, returns only remote paths changed since then (fast, no hashing). Falls back to a full comparison if the token is stale"
+    )]
+    pub since: Option<String>,
+}
+
+/// Differences between a local directory and its remote counterpart.
+///
+/// A full comparison fills `diff`; an incremental query with a valid `since`
+/// token fills `changed_since` instead.
+#[derive(Debug, Serialize)]
+pub struct SyncStatusOutput {
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub diff: Option<ManifestDiff>,
+    /// Remote paths changed since the `since` token (incremental mode only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed_since: Option<Vec<String>>,
+    /// Whether the remote manifest came from the short-lived cache.
+    pub cached: bool,
+    /// Pass as `since` on the next call. Absent when the remote has no
+    /// `inotifywait`/`fswatch` to run a change journal.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub journal_token: Option<String>,
+}
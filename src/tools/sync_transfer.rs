@@ -0,0 +1,814 @@
+//! Transfer strategy selection for `sync_push` / `sync_pull`.
+//!
+//! Four strategies move bytes between the machines:
+//!
+//! - **cat** — one `cat` channel per file. Lowest overhead for a single small file.
+//! - **tar** — one gzip'd tar stream for a whole directory. Default for directories.
+//! - **sftp** — the SSH `sftp` subsystem. Used for large single files and for
+//!   directories on remotes without `tar`.
+//! - **rsync** — the local `rsync` binary over the system `ssh`. Never picked
+//!   automatically: it authenticates through `~/.ssh/config` and the system
+//!   agent rather than this connection, so it must be requested explicitly.
+//!
+//! The strategy actually used and the measured throughput are reported in the
+//! sync result so users can see — and override — what happened.
+
+use std::collections::BTreeSet;
+use std::net::SocketAddr;
+use std::path::{Component, Path};
+use std::process::Stdio;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::connection::{ConnectionParams, RemoteCapabilities, Sftp, SshConnection};
+use crate::timeout::TimeoutPolicy;
+use crate::tools::sync_types::{FailedTransfer, SyncOutput};
+use crate::utils::command::CommandBuilder;
+use crate::utils::log_file::civil_date;
+use crate::utils::path::{shell_escape, shell_escape_remote_path};
+
+pub use crate::utils::path::home_relative;
+
+/// Single files at or above this size go over SFTP instead of a `cat` pipe,
+/// and from [`CHUNKED_BYTES`](crate::tools::sync_chunked::CHUNKED_BYTES) on
+/// in resumable chunks.
+pub const LARGE_FILE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Timeout for remote listing and `mkdir` helpers (30 seconds).
+const HELPER_TIMEOUT_MS: u64 = 30_000;
+
+/// Longest a local `rsync` may run before it is killed, as long as a tar
+/// transfer may take (2 minutes), unless the server's [`TimeoutPolicy`]
+/// raises it.
+const RSYNC_TIMEOUT_MS: u64 = 120_000;
+
+/// Files whose mode and mtime are set by one remote command.
+const ATTRS_BATCH: usize = 200;
+
+/// How bytes are moved between the local and remote machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStrategy {
+    Cat,
+    Tar,
+    Rsync,
+    Sftp,
+}
+
+impl std::fmt::Display for TransferStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Cat => "cat",
+            Self::Tar => "tar",
+            Self::Rsync => "rsync",
+            Self::Sftp => "sftp",
+        })
+    }
+}
+
+/// What is being transferred, as far as it is known up front.
+#[derive(Debug, Clone, Copy)]
+pub struct TransferShape {
+    pub is_dir: bool,
+    /// Number of files, when known (`None` for an unlisted remote directory).
+    pub file_count: Option<usize>,
+    pub total_bytes: u64,
+}
+
+/// Strategy used and measured throughput, reported alongside sync results.
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferStats {
+    pub strategy: TransferStrategy,
+    /// Bytes sent over the wire (compressed size for `tar`).
+    pub bytes: u64,
+    pub elapsed_ms: u64,
+    pub bytes_per_sec: u64,
+    /// Bytes of a partial copy from an earlier, interrupted transfer that
+    /// this one continued from; they are not counted in `bytes`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resumed_from: Option<u64>,
+}
+
+impl TransferStats {
+    #[must_use]
+    pub fn new(strategy: TransferStrategy, bytes: u64, elapsed: Duration) -> Self {
+        let elapsed_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+        let bytes_per_sec = bytes.saturating_mul(1000) / elapsed_ms.max(1);
+        Self {
+            strategy,
+            bytes,
+            elapsed_ms,
+            bytes_per_sec,
+            resumed_from: None,
+        }
+    }
+}
+
+/// Files moved by a per-file strategy, plus per-file failures.
+#[derive(Debug, Default)]
+pub struct TransferResult {
+    pub transferred: Vec<String>,
+    pub failed: Vec<FailedTransfer>,
+    pub bytes: u64,
+    /// Where a chunked transfer picked up a partial copy.
+    pub resumed_from: Option<u64>,
+}
+
+impl TransferResult {
+    /// Convert into a [`SyncOutput`] carrying [`TransferStats`].
+    #[must_use]
+    pub fn into_output(self, strategy: TransferStrategy, started: Instant) -> SyncOutput {
+        SyncOutput {
+            transferred: self.transferred,
+            failed: self.failed,
+            scanned: None,
+            transfer: Some(TransferStats {
+                resumed_from: self.resumed_from,
+                ..TransferStats::new(strategy, self.bytes, started.elapsed())
+            }),
+            verification: None,
+        }
+    }
+
+    /// Record a failure for `path`.
+    pub fn fail(&mut self, path: impl Into<String>, error: impl std::fmt::Display) {
+        self.failed.push(FailedTransfer {
+            path: path.into(),
+            error: error.to_string(),
+        });
+    }
+}
+
+/// Mode and modification time of a local file, carried over to its remote
+/// copy by `sync_push` with `preserve`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileAttrs {
+    /// Permission bits; `None` where the local OS has no Unix modes.
+    pub mode: Option<u32>,
+    /// Seconds since the Unix epoch.
+    pub mtime: u64,
+}
+
+impl FileAttrs {
+    /// Attributes of a local file, or `None` if its metadata can't be read.
+    #[must_use]
+    pub fn of(path: &Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        let mtime = meta
+            .modified()
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_secs();
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(meta.permissions().mode() & 0o7777)
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+        Some(Self { mode, mtime })
+    }
+}
+
+/// Shell command that gives each remote file the mode and mtime of its
+/// local original. `touch -t` under `TZ=UTC0` reads the same on GNU, BSD,
+/// and busybox, unlike `touch -d @<epoch>`.
+#[must_use]
+pub fn set_attrs_command(files: &[(String, FileAttrs)]) -> String {
+    files
+        .iter()
+        .map(|(path, attrs)| {
+            let path = shell_escape_remote_path(path);
+            let touch = format!("TZ=UTC0 touch -c -t {} {path}", touch_time(attrs.mtime));
+            match attrs.mode {
+                Some(mode) => format!("chmod {mode:o} {path} && {touch}"),
+                None => touch,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" && ")
+}
+
+/// `CCYYMMDDhhmm.SS` in UTC, the `touch -t` format.
+fn touch_time(mtime: u64) -> String {
+    let (y, mo, d) = civil_date(mtime / 86_400);
+    let rem = mtime % 86_400;
+    format!(
+        "{y:04}{mo:02}{d:02}{:02}{:02}.{:02}",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60,
+    )
+}
+
+/// Set the mode and mtime of pushed files on the remote, a batch per command.
+///
+/// # Errors
+///
+/// Returns an error if a `chmod` or `touch` fails.
+pub async fn apply_attrs(conn: &SshConnection, files: &[(String, FileAttrs)]) -> Result<()> {
+    for batch in files.chunks(ATTRS_BATCH) {
+        let result = conn
+            .exec(&set_attrs_command(batch), Some(HELPER_TIMEOUT_MS))
+            .await?;
+        if result.exit_code != 0 {
+            return Err(anyhow!(
+                "copied, but setting mode and mtime failed: {}",
+                result.stderr.trim()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// SFTP attributes for `attrs`. Modes are left alone on remotes without a
+/// POSIX shell, which generally don't honor them.
+#[must_use]
+pub fn sftp_attrs(attrs: FileAttrs, with_mode: bool) -> russh_sftp::protocol::FileAttributes {
+    let mut meta = russh_sftp::protocol::FileAttributes::empty();
+    if with_mode {
+        meta.permissions = attrs.mode;
+    }
+    let mtime = u32::try_from(attrs.mtime).ok();
+    meta.mtime = mtime;
+    meta.atime = mtime;
+    meta
+}
+
+/// Pick a strategy for `shape`, honoring an explicit `requested` strategy when
+/// the remote supports it.
+///
+/// # Errors
+///
+/// Returns a user-facing message when the requested strategy cannot be used.
+pub fn select_strategy(
+    shape: &TransferShape,
+    caps: RemoteCapabilities,
+    requested: Option<TransferStrategy>,
+) -> Result<TransferStrategy, String> {
+    if caps.sftp_only {
+        return match requested {
+            None | Some(TransferStrategy::Sftp) => Ok(TransferStrategy::Sftp),
+            Some(strategy) => Err(format!(
+                "Strategy '{strategy}' needs a POSIX shell on the remote; only 'sftp' is available"
+            )),
+        };
+    }
+
+    if let Some(strategy) = requested {
+        return match strategy {
+            TransferStrategy::Tar if !shape.is_dir => {
+                Err("Strategy 'tar' applies to directories only".to_string())
+            }
+            TransferStrategy::Tar if !caps.tar => {
+                Err("Strategy 'tar' requested but tar is not installed on the remote".to_string())
+            }
+            TransferStrategy::Rsync if !caps.rsync => Err(
+                "Strategy 'rsync' requested but rsync is not installed on the remote".to_string(),
+            ),
+            _ => Ok(strategy),
+        };
+    }
+
+    if !shape.is_dir {
+        return Ok(if shape.total_bytes >= LARGE_FILE_BYTES {
+            TransferStrategy::Sftp
+        } else {
+            TransferStrategy::Cat
+        });
+    }
+
+    Ok(match shape.file_count {
+        Some(1) => TransferStrategy::Cat,
+        _ if caps.tar => TransferStrategy::Tar,
+        _ => TransferStrategy::Sftp,
+    })
+}
+
+/// Reject relative paths that could escape the destination directory.
+fn is_safe_relative(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Unique parent directories of `files`, relative to their common base.
+fn parent_dirs(files: &[String]) -> BTreeSet<String> {
+    files
+        .iter()
+        .filter_map(|f| Path::new(f).parent())
+        .filter(|p| !p.as_os_str().is_empty())
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// List regular files under a remote directory (relative paths, `.git/` excluded).
+///
+/// # Errors
+///
+/// Returns an error if the remote `find` fails.
+pub async fn list_remote_files(conn: &SshConnection, remote_dir: &str) -> Result<Vec<String>> {
+    if conn.capabilities().await.sftp_only {
+        let files = conn.sftp_walk(remote_dir, usize::MAX).await?;
+        return Ok(files
+            .into_iter()
+            .filter(|f| f != ".git" && !f.starts_with(".git/"))
+            .collect());
+    }
+    let command = format!(
+        "cd {} && find . -type f ! -path './.git/*'",
+        shell_escape_remote_path(remote_dir)
+    );
+    let result = conn.exec(&command, Some(HELPER_TIMEOUT_MS)).await?;
+    if result.exit_code != 0 {
+        return Err(anyhow!("Remote listing failed: {}", result.stderr.trim()));
+    }
+    Ok(result
+        .stdout
+        .lines()
+        .map(|l| l.strip_prefix("./").unwrap_or(l).to_string())
+        .filter(|l| !l.is_empty())
+        .collect())
+}
+
+// ── cat ─────────────────────────────────────────────────────────────
+
+/// Push files one `cat` channel at a time, creating parent directories first.
+pub async fn push_files_cat(
+    conn: &SshConnection,
+    local_dir: &Path,
+    remote_dest: &str,
+    files: &[String],
+    preserve: bool,
+) -> TransferResult {
+    let mut result = TransferResult::default();
+
+    let mut dirs = vec![remote_dest.to_string()];
+    dirs.extend(
+        parent_dirs(files)
+            .iter()
+            .map(|d| format!("{remote_dest}/{d}")),
+    );
+    if let Err(e) = mkdir_remote(conn, &dirs).await {
+        result.fail(remote_dest, e);
+        return result;
+    }
+
+    let mut attrs = Vec::new();
+    for file in files {
+        let local = local_dir.join(file);
+        let content = match tokio::fs::read(&local).await {
+            Ok(c) => c,
+            Err(e) => {
+                result.fail(file, format!("Error reading local file: {e}"));
+                continue;
+            }
+        };
+        let remote = format!("{remote_dest}/{file}");
+        match conn.write_file_raw(&remote, &content).await {
+            Ok(()) => {
+                result.bytes += content.len() as u64;
+                result.transferred.push(file.clone());
+                if let Some(a) = FileAttrs::of(&local).filter(|_| preserve) {
+                    attrs.push((remote, a));
+                }
+            }
+            Err(e) => result.fail(file, e),
+        }
+    }
+    if let Err(e) = apply_attrs(conn, &attrs).await {
+        result.fail(remote_dest, e);
+    }
+    result
+}
+
+/// Pull files one `cat` channel at a time into `local_dest`.
+pub async fn pull_files_cat(
+    conn: &SshConnection,
+    remote_dir: &str,
+    files: &[String],
+    local_dest: &Path,
+) -> TransferResult {
+    let mut result = TransferResult::default();
+    for file in files {
+        if !is_safe_relative(file) {
+            result.fail(file, "Path escapes the destination directory");
+            continue;
+        }
+        match conn.read_file_raw(&format!("{remote_dir}/{file}")).await {
+            Ok(content) => {
+                write_local(&mut result, local_dest, file, &content).await;
+            }
+            Err(e) => result.fail(file, e),
+        }
+    }
+    result
+}
+
+// ── sftp ────────────────────────────────────────────────────────────
+
+/// Push files over a single SFTP session.
+pub async fn push_files_sftp(
+    conn: &SshConnection,
+    local_dir: &Path,
+    remote_dest: &str,
+    files: &[String],
+    preserve: bool,
+) -> TransferResult {
+    let mut result = TransferResult::default();
+    let sftp = match conn.open_sftp().await {
+        Ok(s) => s,
+        Err(e) => {
+            result.fail(remote_dest, e);
+            return result;
+        }
+    };
+    let base = home_relative(remote_dest);
+
+    // Create the destination and every parent directory, shallowest first.
+    let mut dirs: BTreeSet<String> = BTreeSet::new();
+    dirs.insert(base.clone());
+    for dir in parent_dirs(files) {
+        let mut prefix = base.clone();
+        for part in dir.split('/') {
+            prefix = format!("{prefix}/{part}");
+            dirs.insert(prefix.clone());
+        }
+    }
+    let mut ordered: Vec<String> = dirs.into_iter().collect();
+    ordered.sort_by_key(|d| d.matches('/').count());
+    for dir in ordered {
+        if !sftp.try_exists(dir.as_str()).await.unwrap_or(false) {
+            if let Err(e) = sftp.create_dir(dir.as_str()).await {
+                result.fail(dir, format!("Error creating remote directory: {e}"));
+                return result;
+            }
+        }
+    }
+
+    let with_mode = conn.shell().is_posix();
+    for file in files {
+        let local = local_dir.join(file);
+        let remote = format!("{base}/{file}");
+        match upload_sftp(&sftp, &local, &remote).await {
+            Ok(bytes) => {
+                result.bytes += bytes;
+                result.transferred.push(file.clone());
+            }
+            Err(e) => {
+                result.fail(file, e);
+                continue;
+            }
+        }
+        if let Some(attrs) = FileAttrs::of(&local).filter(|_| preserve) {
+            if let Err(e) = sftp
+                .set_metadata(remote, sftp_attrs(attrs, with_mode))
+                .await
+            {
+                result.fail(
+                    file,
+                    format!("copied, but setting mode and mtime failed: {e}"),
+                );
+            }
+        }
+    }
+    let _ = sftp.close().await;
+    result
+}
+
+/// Push a single file over SFTP.
+pub async fn push_file_sftp(
+    conn: &SshConnection,
+    local: &Path,
+    remote_dest: &str,
+    preserve: bool,
+) -> TransferResult {
+    let mut result = TransferResult::default();
+    let path_str = local.display().to_string();
+    let sftp = match conn.open_sftp().await {
+        Ok(s) => s,
+        Err(e) => {
+            result.fail(path_str, e);
+            return result;
+        }
+    };
+    let remote = home_relative(remote_dest);
+    match upload_sftp(&sftp, local, &remote).await {
+        Ok(bytes) => {
+            result.bytes = bytes;
+            result.transferred.push(path_str.clone());
+            if let Some(attrs) = FileAttrs::of(local).filter(|_| preserve) {
+                let meta = sftp_attrs(attrs, conn.shell().is_posix());
+                if let Err(e) = sftp.set_metadata(remote, meta).await {
+                    result.fail(
+                        path_str,
+                        format!("copied, but setting mode and mtime failed: {e}"),
+                    );
+                }
+            }
+        }
+        Err(e) => result.fail(path_str, e),
+    }
+    let _ = sftp.close().await;
+    result
+}
+
+/// Stream `local` into `remote` (created or truncated) in chunks, so a
+/// large file is never held in memory whole. Returns the bytes sent.
+async fn upload_sftp(sftp: &Sftp, local: &Path, remote: &str) -> Result<u64, String> {
+    let mut from = tokio::fs::File::open(local)
+        .await
+        .map_err(|e| format!("Error reading local file: {e}"))?;
+    let mut to = sftp.create(remote).await.map_err(|e| e.to_string())?;
+    let bytes = tokio::io::copy(&mut from, &mut to)
+        .await
+        .map_err(|e| e.to_string())?;
+    to.shutdown().await.map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+/// Pull files over a single SFTP session into `local_dest`.
+pub async fn pull_files_sftp(
+    conn: &SshConnection,
+    remote_dir: &str,
+    files: &[String],
+    local_dest: &Path,
+) -> TransferResult {
+    let mut result = TransferResult::default();
+    let sftp = match conn.open_sftp().await {
+        Ok(s) => s,
+        Err(e) => {
+            result.fail(remote_dir, e);
+            return result;
+        }
+    };
+    let base = home_relative(remote_dir);
+    for file in files {
+        if !is_safe_relative(file) {
+            result.fail(file, "Path escapes the destination directory");
+            continue;
+        }
+        match sftp.read(format!("{base}/{file}")).await {
+            Ok(content) => write_local(&mut result, local_dest, file, &content).await,
+            Err(e) => result.fail(file, e),
+        }
+    }
+    let _ = sftp.close().await;
+    result
+}
+
+/// Pull a single file over SFTP to `local_dest`.
+pub async fn pull_file_sftp(
+    conn: &SshConnection,
+    remote_path: &str,
+    local_dest: &str,
+) -> TransferResult {
+    let mut result = TransferResult::default();
+    let sftp = match conn.open_sftp().await {
+        Ok(s) => s,
+        Err(e) => {
+            result.fail(remote_path, e);
+            return result;
+        }
+    };
+    match sftp.read(home_relative(remote_path)).await {
+        Ok(content) => {
+            let dest = Path::new(local_dest);
+            let parent = dest.parent().unwrap_or_else(|| Path::new(""));
+            let name = dest
+                .file_name()
+                .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+            write_local(&mut result, parent, &name, &content).await;
+            if let Some(last) = result.transferred.last_mut() {
+                *last = local_dest.to_string();
+            }
+        }
+        Err(e) => result.fail(remote_path, e),
+    }
+    let _ = sftp.close().await;
+    result
+}
+
+// ── rsync ───────────────────────────────────────────────────────────
+
+/// Push `files` (relative to `local_dir`) with the local `rsync` binary.
+pub async fn push_files_rsync(
+    conn: &SshConnection,
+    local_dir: &Path,
+    remote_dest: &str,
+    files: &[String],
+    preserve: bool,
+    timeouts: TimeoutPolicy,
+) -> TransferResult {
+    let mut result = TransferResult::default();
+    if let Err(e) = mkdir_remote(conn, &[remote_dest.to_string()]).await {
+        result.fail(remote_dest, e);
+        return result;
+    }
+    let src = format!("{}/", local_dir.display());
+    let dst = rsync_remote_spec(conn.params(), remote_dest);
+    run_rsync(
+        &mut result,
+        conn,
+        &src,
+        &dst,
+        Some(files),
+        preserve,
+        timeouts,
+    )
+    .await;
+    result
+}
+
+/// Pull a remote directory (or only `files` within it) with the local `rsync` binary.
+pub async fn pull_files_rsync(
+    conn: &SshConnection,
+    remote_dir: &str,
+    files: Option<&[String]>,
+    local_dest: &Path,
+    timeouts: TimeoutPolicy,
+) -> TransferResult {
+    let mut result = TransferResult::default();
+    if let Err(e) = tokio::fs::create_dir_all(local_dest).await {
+        result.fail(
+            local_dest.display().to_string(),
+            format!("Error creating local directory: {e}"),
+        );
+        return result;
+    }
+    let src = format!("{}/", rsync_remote_spec(conn.params(), remote_dir));
+    let dst = format!("{}/", local_dest.display());
+    run_rsync(&mut result, conn, &src, &dst, files, true, timeouts).await;
+    result
+}
+
+/// Transfer a single file with rsync in either direction. `preserve` keeps
+/// its mode and mtime.
+pub async fn rsync_single_file(
+    conn: &SshConnection,
+    src: &str,
+    dst: &str,
+    preserve: bool,
+    timeouts: TimeoutPolicy,
+) -> TransferResult {
+    let mut result = TransferResult::default();
+    run_rsync(&mut result, conn, src, dst, None, preserve, timeouts).await;
+    result
+}
+
+/// `user@host:path` for rsync, bracketing IPv6 literals.
+#[must_use]
+pub fn rsync_remote_spec(params: &ConnectionParams, path: &str) -> String {
+    let host = if params.host.contains(':') {
+        format!("[{}]", params.host)
+    } else {
+        params.host.clone()
+    };
+    format!("{}@{host}:{}", params.user, home_relative(path))
+}
+
+/// The `ssh` command rsync runs. It goes to `peer`, the address the pooled
+/// session reached, rather than resolving `host` again, and checks the host
+/// key recorded for `host`, so both transfers land on the same server.
+#[must_use]
+pub fn rsync_ssh_command(params: &ConnectionParams, peer: SocketAddr) -> String {
+    let alias = if params.port == 22 {
+        params.host.clone()
+    } else {
+        format!("[{}]:{}", params.host, params.port)
+    };
+    let mut ssh = format!(
+        "ssh -p {} -o BatchMode=yes -o HostName={} -o HostKeyAlias={}",
+        peer.port(),
+        peer.ip(),
+        shell_escape(&alias),
+    );
+    if let Some(identity) = &params.identity {
+        ssh.push_str(" -i ");
+        ssh.push_str(&shell_escape(&identity.to_string_lossy()));
+    }
+    ssh
+}
+
+async fn run_rsync(
+    result: &mut TransferResult,
+    conn: &SshConnection,
+    src: &str,
+    dst: &str,
+    files: Option<&[String]>,
+    preserve: bool,
+    timeouts: TimeoutPolicy,
+) {
+    let ssh = rsync_ssh_command(conn.params(), conn.peer_addr());
+    let mut cmd = tokio::process::Command::new("rsync");
+    cmd.args(["-a", "-s", "--out-format=%l %n", "-e", &ssh]);
+    if !preserve {
+        cmd.args(["--no-perms", "--no-times"]);
+    }
+    if files.is_some() {
+        cmd.arg("--files-from=-");
+    }
+    cmd.arg(src).arg(dst);
+    cmd.stdin(if files.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    })
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .kill_on_drop(true);
+
+    let mut child = match cmd.spawn() {
+        Ok(c) => c,
+        Err(e) => {
+            result.fail(src, format!("Failed to run local rsync: {e}"));
+            return;
+        }
+    };
+    if let (Some(files), Some(mut stdin)) = (files, child.stdin.take()) {
+        let list = files.join("\n");
+        if let Err(e) = stdin.write_all(list.as_bytes()).await {
+            result.fail(src, format!("Failed to send file list to rsync: {e}"));
+            return;
+        }
+    }
+
+    let output = match timeouts.operation(RSYNC_TIMEOUT_MS) {
+        Some(ms) => tokio::time::timeout(Duration::from_millis(ms), child.wait_with_output())
+            .await
+            .map_err(|_| ms),
+        None => Ok(child.wait_with_output().await),
+    };
+    let output = match output {
+        Ok(Ok(o)) => o,
+        Ok(Err(e)) => {
+            result.fail(src, format!("rsync failed: {e}"));
+            return;
+        }
+        Err(ms) => {
+            result.fail(src, format!("rsync timed out after {}s", ms / 1000));
+            return;
+        }
+    };
+    if !output.status.success() {
+        result.fail(
+            src,
+            format!(
+                "rsync failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        );
+        return;
+    }
+
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some((size, name)) = line.split_once(' ') else {
+            continue;
+        };
+        if name.ends_with('/') {
+            continue;
+        }
+        result.bytes += size.parse::<u64>().unwrap_or(0);
+        result.transferred.push(name.to_string());
+    }
+}
+
+// ── helpers ─────────────────────────────────────────────────────────
+
+/// `mkdir -p` a list of remote directories in one command.
+async fn mkdir_remote(conn: &SshConnection, dirs: &[String]) -> Result<()> {
+    let command = CommandBuilder::new("mkdir").word("-p").paths(dirs).build();
+    let result = conn.exec(&command, Some(HELPER_TIMEOUT_MS)).await?;
+    if result.exit_code != 0 {
+        return Err(anyhow!(
+            "Error creating remote directory: {}",
+            result.stderr.trim()
+        ));
+    }
+    Ok(())
+}
+
+async fn write_local(result: &mut TransferResult, base: &Path, file: &str, content: &[u8]) {
+    let path = base.join(file);
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            if let Err(e) = tokio::fs::create_dir_all(parent).await {
+                result.fail(file, format!("Error creating local directory: {e}"));
+                return;
+            }
+        }
+    }
+    match tokio::fs::write(&path, content).await {
+        Ok(()) => {
+            result.bytes += content.len() as u64;
+            result.transferred.push(file.to_string());
+        }
+        Err(e) => result.fail(file, e),
+    }
+}
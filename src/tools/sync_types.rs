@@ -1,5 +1,9 @@
 use serde::Serialize;
 
+use crate::tools::sync_transfer::TransferStats;
+use crate::tools::sync_verify::FileVerification;
+use crate::utils::output::fit_json;
+
 /// A single file that failed during a sync operation.
 #[derive(Debug, Serialize)]
 pub struct FailedTransfer {
@@ -7,14 +11,32 @@ pub struct FailedTransfer {
     pub error: String,
 }
 
+/// Files a directory push found to send, after ignore rules and `include`
+/// but before `changed_only` narrows them down.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ScanSummary {
+    pub files: usize,
+    pub bytes: u64,
+    pub elapsed_ms: u64,
+}
+
 /// Unified output for sync push/pull operations.
 ///
 /// The `transferred` field lists successfully synced files,
-/// and `failed` lists any files that encountered errors.
+/// and `failed` lists any files that encountered errors. `scanned`
+/// summarizes the local walk of a directory push, `transfer` records the
+/// strategy used and its throughput when bytes were moved, and
+/// `verification` holds per-file sha256 results when `verify` was requested.
 #[derive(Debug, Serialize)]
 pub struct SyncOutput {
     pub transferred: Vec<String>,
     pub failed: Vec<FailedTransfer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scanned: Option<ScanSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transfer: Option<TransferStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<Vec<FileVerification>>,
 }
 
 impl SyncOutput {
@@ -24,6 +46,9 @@ impl SyncOutput {
         Self {
             transferred,
             failed: vec![],
+            scanned: None,
+            transfer: None,
+            verification: None,
         }
     }
 
@@ -35,16 +60,15 @@ impl SyncOutput {
                 path: path.into(),
                 error: error.into(),
             }],
+            scanned: None,
+            transfer: None,
+            verification: None,
         }
     }
 
-    /// Serialize to compact JSON.
-    ///
-    /// Falls back to a minimal error JSON if serialization fails, which
-    /// should never happen since both fields are trivially serializable.
-    #[must_use]
-    pub fn to_json(&self) -> String {
-        serde_json::to_string_pretty(self)
-            .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+    /// Serialize to pretty JSON for `tool`'s response, cutting the file
+    /// lists when they are too large to return inline (see [`fit_json`]).
+    pub async fn to_json(&self, tool: &str) -> String {
+        fit_json(self, &["/transferred", "/verification", "/failed"], tool).await
     }
 }
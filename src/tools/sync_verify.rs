@@ -0,0 +1,238 @@
+//! Post-transfer integrity checks: compare sha256 of local and remote content.
+//!
+//! Remote hashes for a batch come from a single `sha256sum` invocation (file
+//! names are fed over stdin to `xargs -0`, so batch size isn't limited by the
+//! command line). Falls back to `shasum -a 256` on remotes without coreutils.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::connection::RemoteExecutor;
+use crate::timeout::TimeoutPolicy;
+use crate::tools::sync_manifest::build_local_manifest;
+use crate::tools::sync_types::{FailedTransfer, SyncOutput};
+use crate::utils::path::shell_escape_remote_path;
+
+/// Timeout for the remote hashing command (2 minutes), unless the server's
+/// [`TimeoutPolicy`] raises it.
+const VERIFY_TIMEOUT_MS: u64 = 120_000;
+
+/// Outcome of verifying one file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyStatus {
+    /// Local and remote sha256 match.
+    Ok,
+    /// Both sides exist but the content differs.
+    Mismatch,
+    /// The file could not be hashed on one side.
+    Missing,
+}
+
+/// Verification result for a single transferred file.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileVerification {
+    pub path: String,
+    pub status: VerifyStatus,
+}
+
+/// Hash `files` (relative to `remote_dir`) on the remote in one command.
+/// Files that cannot be hashed are absent from the result.
+///
+/// # Errors
+///
+/// Returns an error if the SSH command cannot be run.
+pub async fn remote_sha256<C: RemoteExecutor + ?Sized>(
+    conn: &C,
+    remote_dir: &str,
+    files: &[String],
+    timeouts: TimeoutPolicy,
+) -> Result<HashMap<String, String>> {
+    if files.is_empty() {
+        return Ok(HashMap::new());
+    }
+    if !conn.shell().is_posix() {
+        return Err(anyhow!(
+            "verification requires a POSIX shell on the remote (this server uses {})",
+            conn.shell()
+        ));
+    }
+    let command = format!(
+        "cd {} && if command -v sha256sum >/dev/null 2>&1; then h=sha256sum; else h='shasum -a 256'; fi; \
+         xargs -0 $h --",
+        shell_escape_remote_path(remote_dir)
+    );
+    let mut stdin = Vec::new();
+    for file in files {
+        stdin.extend_from_slice(file.as_bytes());
+        stdin.push(0);
+    }
+    let result = conn
+        .exec_raw(
+            &command,
+            Some(&stdin),
+            timeouts.operation(VERIFY_TIMEOUT_MS),
+        )
+        .await?;
+    // Non-zero exit just means some files were missing — parse what we got.
+    let stdout = String::from_utf8_lossy(&result.stdout);
+    if stdout.trim().is_empty() && result.exit_code != 0 {
+        return Err(anyhow!("Remote hashing failed: {}", result.stderr.trim()));
+    }
+    Ok(parse_sha256sum(&stdout))
+}
+
+/// Parse `sha256sum` output into path → hash. Handles the escaped form
+/// (leading `\`) used for names containing backslashes or newlines.
+#[must_use]
+pub fn parse_sha256sum(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (escaped, line) = match line.strip_prefix('\\') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let (hash, path) = line.split_once("  ").or_else(|| line.split_once(" *"))?;
+            let path = if escaped {
+                unescape(path)
+            } else {
+                path.to_string()
+            };
+            Some((path, hash.to_string()))
+        })
+        .collect()
+}
+
+/// Undo `sha256sum` name escaping (`\\` and `\n`) in a single pass.
+fn unescape(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Hash a single remote file. Returns `None` if it cannot be hashed.
+///
+/// # Errors
+///
+/// Returns an error if the SSH command cannot be run.
+pub async fn remote_file_sha256<C: RemoteExecutor + ?Sized>(
+    conn: &C,
+    remote_path: &str,
+    timeouts: TimeoutPolicy,
+) -> Result<Option<String>> {
+    let (dir, name) = remote_path.rsplit_once('/').unwrap_or((".", remote_path));
+    let dir = if dir.is_empty() { "/" } else { dir };
+    let name = name.to_string();
+    let mut hashes = remote_sha256(conn, dir, std::slice::from_ref(&name), timeouts).await?;
+    Ok(hashes.remove(&name))
+}
+
+/// Compare in-memory `content` with the remote file at `remote_path`.
+///
+/// # Errors
+///
+/// Returns an error if the SSH command cannot be run.
+pub async fn verify_content<C: RemoteExecutor + ?Sized>(
+    conn: &C,
+    content: &[u8],
+    remote_path: &str,
+    timeouts: TimeoutPolicy,
+) -> Result<VerifyStatus> {
+    let local = hex::encode(Sha256::digest(content));
+    Ok(
+        match remote_file_sha256(conn, remote_path, timeouts).await? {
+            Some(remote) if remote == local => VerifyStatus::Ok,
+            Some(_) => VerifyStatus::Mismatch,
+            None => VerifyStatus::Missing,
+        },
+    )
+}
+
+/// Compare a local file with the remote file at `remote_path`.
+///
+/// # Errors
+///
+/// Returns an error if the local file cannot be read or the SSH command fails.
+pub async fn verify_file<C: RemoteExecutor + ?Sized>(
+    conn: &C,
+    local: &Path,
+    remote_path: &str,
+    timeouts: TimeoutPolicy,
+) -> Result<VerifyStatus> {
+    let content = tokio::fs::read(local)
+        .await
+        .map_err(|e| anyhow!("Cannot read '{}': {e}", local.display()))?;
+    verify_content(conn, &content, remote_path, timeouts).await
+}
+
+/// Compare local files under `local_dir` with remote files under
+/// `remote_dir`. `files` are relative paths valid on both sides.
+///
+/// # Errors
+///
+/// Returns an error if remote hashing fails outright.
+pub async fn verify_files<C: RemoteExecutor + ?Sized>(
+    conn: &C,
+    local_dir: &Path,
+    remote_dir: &str,
+    files: &[String],
+    timeouts: TimeoutPolicy,
+) -> Result<Vec<FileVerification>> {
+    let remote = remote_sha256(conn, remote_dir, files, timeouts).await?;
+
+    let dir_owned = local_dir.to_path_buf();
+    let files_owned = files.to_vec();
+    let local = tokio::task::spawn_blocking(move || {
+        files_owned
+            .into_iter()
+            .map(|f| {
+                let hash = build_local_manifest(&dir_owned, std::slice::from_ref(&f))
+                    .ok()
+                    .and_then(|m| m.into_values().next())
+                    .map(|e| e.sha256);
+                (f, hash)
+            })
+            .collect::<Vec<_>>()
+    })
+    .await?;
+
+    Ok(local
+        .into_iter()
+        .map(|(path, local_hash)| {
+            let status = match (local_hash, remote.get(&path)) {
+                (Some(l), Some(r)) if &l == r => VerifyStatus::Ok,
+                (Some(_), Some(_)) => VerifyStatus::Mismatch,
+                _ => VerifyStatus::Missing,
+            };
+            FileVerification { path, status }
+        })
+        .collect())
+}
+
+/// Attach verification results to `output`. A verification error is recorded
+/// as a failure against `path` rather than discarding the transfer result.
+pub fn attach(output: &mut SyncOutput, path: &str, result: Result<Vec<FileVerification>>) {
+    match result {
+        Ok(verification) => output.verification = Some(verification),
+        Err(e) => output.failed.push(FailedTransfer {
+            path: path.to_string(),
+            error: format!("Checksum verification failed: {e}"),
+        }),
+    }
+}
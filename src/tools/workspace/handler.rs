@@ -0,0 +1,197 @@
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::registry::{
+    is_workspace_path, sanitize_label, Workspace, WorkspaceRegistry, WORKSPACE_PREFIX,
+};
+use super::schema::{
+    RemoteTmpdirOutput, WorkspaceCleanupInput, WorkspaceCleanupOutput, WorkspaceCreateInput,
+    WorkspaceCreateOutput, WorkspaceFailure,
+};
+use super::session::SessionDirs;
+use crate::connection::SshConnection;
+use crate::tools::{remote_error, tool_error, ErrorCode};
+use crate::utils::command::CommandBuilder;
+use crate::utils::path::shell_escape_remote_path;
+
+/// Scratch root used when the server config doesn't set `scratch_root`.
+pub const DEFAULT_SCRATCH_ROOT: &str = "/tmp";
+
+/// Timeout for creating or removing a workspace (60 seconds).
+const WORKSPACE_TIMEOUT_MS: u64 = 60_000;
+
+const DEFAULT_TTL_HOURS: u64 = 24;
+const MAX_TTL_HOURS: u64 = 168;
+
+/// Make a unique directory under `scratch_root` and track it.
+pub async fn create(
+    conn: Arc<SshConnection>,
+    server: &str,
+    scratch_root: &str,
+    input: WorkspaceCreateInput,
+) -> String {
+    if let Some(error) = conn.posix_required("workspace_create") {
+        return tool_error(ErrorCode::NotSupported, error);
+    }
+    let root = scratch_root.trim_end_matches('/');
+    if !(root.starts_with('/') || root == "~" || root.starts_with("~/")) {
+        let message =
+            format!("scratch_root '{scratch_root}' for '{server}' must be an absolute or ~/ path");
+        return tool_error(ErrorCode::Failed, message);
+    }
+
+    let now = unix_now();
+    let expired = WorkspaceRegistry::load().expired(server, now);
+    let expired_removed = remove_workspaces(&conn, server, expired).await.removed;
+
+    let name = match input
+        .label
+        .as_deref()
+        .map(sanitize_label)
+        .filter(|l| !l.is_empty())
+    {
+        Some(label) => format!("{WORKSPACE_PREFIX}{label}.XXXXXXXX"),
+        None => format!("{WORKSPACE_PREFIX}XXXXXXXX"),
+    };
+    let command = format!(
+        "mkdir -p {root} && mktemp -d {template}",
+        root = shell_escape_remote_path(if root.is_empty() { "/" } else { root }),
+        template = shell_escape_remote_path(&format!("{root}/{name}")),
+    );
+    let path = match conn.exec(&command, Some(WORKSPACE_TIMEOUT_MS)).await {
+        Ok(result) if result.exit_code == 0 => result.stdout.trim().to_string(),
+        Ok(result) => {
+            let message = format!(
+                "Error creating workspace under {scratch_root}: {}",
+                result.stderr.trim()
+            );
+            return tool_error(ErrorCode::Failed, message);
+        }
+        Err(e) => return remote_error(&e, format!("Error creating workspace: {e}")),
+    };
+    if !is_workspace_path(&path) {
+        let message = format!("mktemp returned an unexpected path '{path}'");
+        return tool_error(ErrorCode::Failed, message);
+    }
+
+    let ttl_hours = input
+        .ttl_hours
+        .unwrap_or(DEFAULT_TTL_HOURS)
+        .clamp(1, MAX_TTL_HOURS);
+    let workspace = Workspace {
+        server: server.to_string(),
+        path: path.clone(),
+        created_at: now,
+        expires_at: now + ttl_hours * 3_600,
+        pid: std::process::id(),
+    };
+    let expires_at = workspace.expires_at;
+    WorkspaceRegistry::update(|registry| registry.add(workspace));
+
+    let output = WorkspaceCreateOutput {
+        path,
+        expires_at,
+        expired_removed,
+    };
+    serde_json::to_string_pretty(&output)
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
+
+/// Remove one tracked workspace, or every workspace tracked for the server.
+pub async fn cleanup(
+    conn: Arc<SshConnection>,
+    server: &str,
+    input: WorkspaceCleanupInput,
+) -> String {
+    let registry = WorkspaceRegistry::load();
+    let targets = match input.path.as_deref() {
+        Some(path) => {
+            let Some(workspace) = registry.find(server, path) else {
+                let message = format!(
+                    "'{path}' is not a workspace tracked for '{server}'. \
+                     Only directories made by workspace_create can be removed."
+                );
+                return tool_error(ErrorCode::NotFound, message);
+            };
+            vec![workspace.clone()]
+        }
+        None => registry.on_server(server),
+    };
+    let output = remove_workspaces(&conn, server, targets).await;
+    serde_json::to_string_pretty(&output)
+        .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#))
+}
+
+/// This session's scratch directory on `server`, made on first use.
+pub async fn tmpdir(
+    conn: Arc<SshConnection>,
+    dirs: &SessionDirs,
+    server: &str,
+    scratch_root: &str,
+) -> String {
+    match dirs.get_or_create(&conn, server, scratch_root).await {
+        Ok((path, created)) => serde_json::to_string_pretty(&RemoteTmpdirOutput { path, created })
+            .unwrap_or_else(|e| format!(r#"{{"error": "serialization failed: {e}"}}"#)),
+        Err(e) => remote_error(&e, format!("Error creating session directory: {e}")),
+    }
+}
+
+/// Remove the workspaces on `server` created by this process. Run when the
+/// MCP server shuts down.
+pub async fn cleanup_owned(conn: Arc<SshConnection>, server: &str) -> String {
+    let owned: Vec<Workspace> = WorkspaceRegistry::load()
+        .owned_by(std::process::id())
+        .into_iter()
+        .filter(|w| w.server == server)
+        .collect();
+    let output = remove_workspaces(&conn, server, owned).await;
+    serde_json::to_string(&output).unwrap_or_default()
+}
+
+/// `rm -rf` each workspace and stop tracking the ones that are gone.
+async fn remove_workspaces(
+    conn: &SshConnection,
+    server: &str,
+    workspaces: Vec<Workspace>,
+) -> WorkspaceCleanupOutput {
+    let mut output = WorkspaceCleanupOutput::default();
+    for workspace in workspaces {
+        let path = workspace.path;
+        if !is_workspace_path(&path) {
+            output.failed.push(WorkspaceFailure {
+                path,
+                error: "not a workspace path; refusing to remove it".to_string(),
+            });
+            continue;
+        }
+        let command = CommandBuilder::new("rm")
+            .words(["-rf", "--"])
+            .path(&path)
+            .build();
+        match conn.exec(&command, Some(WORKSPACE_TIMEOUT_MS)).await {
+            Ok(result) if result.exit_code == 0 => output.removed.push(path),
+            Ok(result) => output.failed.push(WorkspaceFailure {
+                path,
+                error: result.stderr.trim().to_string(),
+            }),
+            Err(e) => output.failed.push(WorkspaceFailure {
+                path,
+                error: e.to_string(),
+            }),
+        }
+    }
+    if !output.removed.is_empty() {
+        WorkspaceRegistry::update(|registry| {
+            for path in &output.removed {
+                registry.remove(server, path);
+            }
+        });
+    }
+    output
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
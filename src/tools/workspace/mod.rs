@@ -0,0 +1,6 @@
+pub mod handler;
+pub mod registry;
+pub mod schema;
+pub mod session;
+
+pub use schema::*;
@@ -0,0 +1,162 @@
+//! Record of the remote scratch directories made by `workspace_create`, so
+//! they can be removed later: by `workspace_cleanup`, when they expire, or
+//! when the MCP server that made them exits. Persisted as JSON in the user
+//! data dir, like the provision history.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// Prefix of every workspace directory name. Cleanup refuses any path whose
+/// last component doesn't start with it.
+pub const WORKSPACE_PREFIX: &str = "ssh-hub-";
+
+/// Longest label kept in a directory name.
+const MAX_LABEL_LEN: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Workspace {
+    pub server: String,
+    pub path: String,
+    /// Unix timestamps (seconds).
+    pub created_at: u64,
+    pub expires_at: u64,
+    /// Process that created it; it removes the workspace when it exits.
+    pub pid: u32,
+}
+
+impl Workspace {
+    #[must_use]
+    pub fn expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceRegistry {
+    #[serde(default)]
+    pub workspaces: Vec<Workspace>,
+}
+
+impl WorkspaceRegistry {
+    pub fn add(&mut self, workspace: Workspace) {
+        self.workspaces.push(workspace);
+    }
+
+    /// Stop tracking `path` on `server`. Returns whether it was tracked.
+    pub fn remove(&mut self, server: &str, path: &str) -> bool {
+        let before = self.workspaces.len();
+        self.workspaces
+            .retain(|w| !(w.server == server && w.path == path));
+        self.workspaces.len() != before
+    }
+
+    #[must_use]
+    pub fn find(&self, server: &str, path: &str) -> Option<&Workspace> {
+        self.workspaces
+            .iter()
+            .find(|w| w.server == server && w.path == path)
+    }
+
+    /// Workspaces tracked for `server`.
+    #[must_use]
+    pub fn on_server(&self, server: &str) -> Vec<Workspace> {
+        self.workspaces
+            .iter()
+            .filter(|w| w.server == server)
+            .cloned()
+            .collect()
+    }
+
+    /// Workspaces on `server` whose time is up at `now`.
+    #[must_use]
+    pub fn expired(&self, server: &str, now: u64) -> Vec<Workspace> {
+        self.workspaces
+            .iter()
+            .filter(|w| w.server == server && w.expired(now))
+            .cloned()
+            .collect()
+    }
+
+    /// Workspaces created by the process `pid`.
+    #[must_use]
+    pub fn owned_by(&self, pid: u32) -> Vec<Workspace> {
+        self.workspaces
+            .iter()
+            .filter(|w| w.pid == pid)
+            .cloned()
+            .collect()
+    }
+
+    fn path() -> Option<PathBuf> {
+        Some(dirs::data_dir()?.join("ssh-hub").join("workspaces.json"))
+    }
+
+    #[must_use]
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::path() else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                tracing::debug!("Cannot create workspace registry dir: {e}");
+                return;
+            }
+        }
+        match serde_json::to_string_pretty(self) {
+            Ok(json) => {
+                if let Err(e) = std::fs::write(&path, json) {
+                    tracing::debug!("Cannot write workspace registry: {e}");
+                }
+            }
+            Err(e) => tracing::debug!("Cannot serialize workspace registry: {e}"),
+        }
+    }
+
+    /// Load the registry, apply `f`, and save it.
+    pub fn update<T>(f: impl FnOnce(&mut Self) -> T) -> T {
+        let mut registry = Self::load();
+        let result = f(&mut registry);
+        registry.save();
+        result
+    }
+}
+
+/// Reduce `label` to characters safe in a directory name.
+#[must_use]
+pub fn sanitize_label(label: &str) -> String {
+    let cleaned: String = label
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .take(MAX_LABEL_LEN)
+        .collect();
+    cleaned.trim_matches('-').to_string()
+}
+
+/// Whether `path` looks like a directory `workspace_create` made: absolute or
+/// home-relative, no `..`, and a last component starting with
+/// [`WORKSPACE_PREFIX`]. A safety net before `rm -rf`.
+#[must_use]
+pub fn is_workspace_path(path: &str) -> bool {
+    let rooted = path.starts_with('/') || path.starts_with("~/");
+    let segments: Vec<&str> = path.split('/').collect();
+    rooted
+        && !segments.contains(&"..")
+        && segments.last().is_some_and(|name| {
+            name.len() > WORKSPACE_PREFIX.len() && name.starts_with(WORKSPACE_PREFIX)
+        })
+}
@@ -0,0 +1,62 @@
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct WorkspaceCreateInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+    #[schemars(
+        description = "Short label for the task, used in the directory name (letters, digits, '-' and '_'; other characters are replaced)"
+    )]
+    pub label: Option<String>,
+    #[schemars(
+        description = "Hours before the workspace counts as abandoned and is removed by a later workspace_create or workspace_cleanup (default: 24, max: 168)"
+    )]
+    pub ttl_hours: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct WorkspaceCleanupInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+    #[schemars(
+        description = "Workspace path returned by workspace_create. Omit to remove every workspace tracked for this server."
+    )]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RemoteTmpdirInput {
+    #[schemars(description = "Name of the connected server to target (e.g., 'staging')")]
+    pub server: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkspaceCreateOutput {
+    pub path: String,
+    /// Unix timestamp (seconds) after which the workspace may be removed.
+    pub expires_at: u64,
+    /// Expired workspaces removed before creating this one.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub expired_removed: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct WorkspaceCleanupOutput {
+    pub removed: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<WorkspaceFailure>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkspaceFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemoteTmpdirOutput {
+    pub path: String,
+    /// Whether this call made the directory (later calls return the same one).
+    pub created: bool,
+}
@@ -0,0 +1,154 @@
+//! One scratch directory per server for the lifetime of this MCP server
+//! process, returned by `remote_tmpdir` and removed when the process shuts
+//! down. Unlike workspaces it is not tracked on disk, so a killed process
+//! leaves it behind under the scratch root.
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use tokio::sync::Mutex;
+
+use super::registry::is_workspace_path;
+use crate::connection::SshConnection;
+use crate::utils::command::CommandBuilder;
+use crate::utils::path::shell_escape_remote_path;
+
+/// Directory name prefix, followed by the session ID.
+pub const SESSION_PREFIX: &str = "ssh-hub-session-";
+
+/// Timeout for creating or removing a session directory (60 seconds).
+const SESSION_DIR_TIMEOUT_MS: u64 = 60_000;
+
+/// Session ID for a process started `since_epoch` after the Unix epoch,
+/// e.g. `4242-19a3f0c2b10`.
+#[must_use]
+pub fn session_id(pid: u32, since_epoch: Duration) -> String {
+    format!("{pid}-{:x}", since_epoch.as_millis())
+}
+
+/// Path of the session directory under `scratch_root`.
+#[must_use]
+pub fn session_dir(scratch_root: &str, id: &str) -> String {
+    format!(
+        "{}/{SESSION_PREFIX}{id}",
+        scratch_root.trim_end_matches('/')
+    )
+}
+
+/// Shell script that makes `path` private to the login user and prints it
+/// expanded. An existing directory is reused only if it is ours and not a
+/// symlink, since the name under `/tmp` is guessable.
+#[must_use]
+pub fn create_command(path: &str) -> String {
+    format!(
+        "d={}; mkdir -p \"$(dirname \"$d\")\" && \
+         {{ mkdir -m 700 \"$d\" 2>/dev/null || {{ [ -d \"$d\" ] && [ ! -L \"$d\" ] && [ -O \"$d\" ]; }}; }} && \
+         printf '%s' \"$d\"",
+        shell_escape_remote_path(path),
+    )
+}
+
+/// Session directories made so far, by server name.
+pub struct SessionDirs {
+    id: String,
+    dirs: Mutex<HashMap<String, String>>,
+}
+
+impl Default for SessionDirs {
+    fn default() -> Self {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            id: session_id(std::process::id(), since_epoch),
+            dirs: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl SessionDirs {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The session directory on `server`, made under `scratch_root` on
+    /// first use. The boolean is `true` when this call created it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the remote has no POSIX shell or the directory
+    /// can't be made.
+    pub async fn get_or_create(
+        &self,
+        conn: &SshConnection,
+        server: &str,
+        scratch_root: &str,
+    ) -> Result<(String, bool)> {
+        if let Some(path) = self.dirs.lock().await.get(server) {
+            return Ok((path.clone(), false));
+        }
+        if let Some(error) = conn.posix_required("remote_tmpdir") {
+            return Err(anyhow!(error));
+        }
+        let path = session_dir(scratch_root, &self.id);
+        let result = conn
+            .exec(&create_command(&path), Some(SESSION_DIR_TIMEOUT_MS))
+            .await?;
+        let created = result.stdout.trim();
+        if result.exit_code != 0 || created.is_empty() {
+            return Err(anyhow!(
+                "cannot create {path}: {}",
+                if result.stderr.trim().is_empty() {
+                    "it exists and belongs to another user"
+                } else {
+                    result.stderr.trim()
+                }
+            ));
+        }
+        let mut dirs = self.dirs.lock().await;
+        let path = dirs
+            .entry(server.to_string())
+            .or_insert_with(|| created.to_string());
+        Ok((path.clone(), true))
+    }
+
+    /// Forget every session directory, returning `(server, path)` pairs.
+    pub async fn take_all(&self) -> Vec<(String, String)> {
+        let mut dirs: Vec<_> = self.dirs.lock().await.drain().collect();
+        dirs.sort();
+        dirs
+    }
+}
+
+/// `rm -rf` a session directory.
+///
+/// # Errors
+///
+/// Returns an error if `path` isn't a session directory or removing it fails.
+pub async fn remove(conn: &SshConnection, path: &str) -> Result<()> {
+    let is_session_dir = path
+        .rsplit('/')
+        .next()
+        .is_some_and(|name| name.starts_with(SESSION_PREFIX));
+    if !is_session_dir || !is_workspace_path(path) {
+        return Err(anyhow!(
+            "'{path}' is not a session directory; refusing to remove it"
+        ));
+    }
+    let command = CommandBuilder::new("rm")
+        .words(["-rf", "--"])
+        .path(path)
+        .build();
+    let result = conn.exec(&command, Some(SESSION_DIR_TIMEOUT_MS)).await?;
+    if result.exit_code != 0 {
+        return Err(anyhow!("{}", result.stderr.trim()));
+    }
+    Ok(())
+}
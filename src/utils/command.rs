@@ -0,0 +1,218 @@
+//! Build one remote shell command line without hand-quoting.
+//!
+//! Values — arguments, paths, environment values, and redirection targets —
+//! are always quoted, so a path with `$`, quotes, or spaces reaches the
+//! program as one literal word. Literal words such as subcommands, options,
+//! and numbers are written as-is when they only contain shell-safe
+//! characters, and quoted otherwise.
+//!
+//! ```
+//! use ssh_hub::utils::command::CommandBuilder;
+//!
+//! let command = CommandBuilder::new("tar")
+//!     .word("-czf")
+//!     .path("~/backups/$today.tgz")
+//!     .arg("it's here")
+//!     .cwd("/srv/app")
+//!     .env("LC_ALL", "C")
+//!     .stderr_to_stdout()
+//!     .build();
+//! assert_eq!(
+//!     command,
+//!     "cd '/srv/app' && LC_ALL='C' tar -czf $HOME/'backups/$today.tgz' 'it'\\''s here' 2>&1"
+//! );
+//! ```
+
+use std::fmt;
+
+use super::path::{shell_escape, shell_escape_remote_path};
+
+/// A program, its arguments, and how to run it, rendered as a POSIX shell
+/// command line by [`CommandBuilder::build`] or `Display`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandBuilder {
+    cwd: Option<String>,
+    env: Vec<(String, String)>,
+    words: Vec<String>,
+    redirects: Vec<String>,
+}
+
+impl CommandBuilder {
+    /// Start a command running `program`. A name the shell would read as an
+    /// assignment or expand is quoted.
+    #[must_use]
+    pub fn new(program: &str) -> Self {
+        let program = if program.contains('=') {
+            shell_escape(program)
+        } else {
+            literal(program)
+        };
+        Self {
+            cwd: None,
+            env: Vec::new(),
+            words: vec![program],
+            redirects: Vec::new(),
+        }
+    }
+
+    /// Append a literal word such as a subcommand, option, or number. It is
+    /// left unquoted when made only of shell-safe characters.
+    #[must_use]
+    pub fn word(mut self, word: impl AsRef<str>) -> Self {
+        self.words.push(literal(word.as_ref()));
+        self
+    }
+
+    /// Append several literal words; see [`CommandBuilder::word`].
+    #[must_use]
+    pub fn words<I, S>(self, words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        words.into_iter().fold(self, Self::word)
+    }
+
+    /// Append a value, always single-quoted.
+    #[must_use]
+    pub fn arg(mut self, value: impl AsRef<str>) -> Self {
+        self.words.push(shell_escape(value.as_ref()));
+        self
+    }
+
+    /// Append several values; see [`CommandBuilder::arg`].
+    #[must_use]
+    pub fn args<I, S>(self, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        values.into_iter().fold(self, Self::arg)
+    }
+
+    /// Append a remote path, quoted except for a leading `~`, which expands
+    /// to `$HOME`.
+    #[must_use]
+    pub fn path(mut self, path: impl AsRef<str>) -> Self {
+        self.words.push(shell_escape_remote_path(path.as_ref()));
+        self
+    }
+
+    /// Append several remote paths; see [`CommandBuilder::path`].
+    #[must_use]
+    pub fn paths<I, S>(self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        paths.into_iter().fold(self, Self::path)
+    }
+
+    /// Set an environment variable for the program only.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not a valid shell variable name, since it can't be
+    /// quoted.
+    #[must_use]
+    pub fn env(mut self, key: &str, value: impl AsRef<str>) -> Self {
+        assert!(
+            is_var_name(key),
+            "invalid environment variable name {key:?}"
+        );
+        self.env
+            .push((key.to_string(), shell_escape(value.as_ref())));
+        self
+    }
+
+    /// Run the program in the remote directory `dir`, and not at all when
+    /// `cd` fails.
+    #[must_use]
+    pub fn cwd(mut self, dir: impl AsRef<str>) -> Self {
+        self.cwd = Some(shell_escape_remote_path(dir.as_ref()));
+        self
+    }
+
+    /// Read stdin from the remote file `path`.
+    #[must_use]
+    pub fn stdin_from(self, path: impl AsRef<str>) -> Self {
+        self.redirect("<", path.as_ref())
+    }
+
+    /// Write stdout to the remote file `path`, replacing it.
+    #[must_use]
+    pub fn stdout_to(self, path: impl AsRef<str>) -> Self {
+        self.redirect(">", path.as_ref())
+    }
+
+    /// Append stdout to the remote file `path`.
+    #[must_use]
+    pub fn stdout_append(self, path: impl AsRef<str>) -> Self {
+        self.redirect(">>", path.as_ref())
+    }
+
+    /// Write stderr to the remote file `path`, replacing it.
+    #[must_use]
+    pub fn stderr_to(self, path: impl AsRef<str>) -> Self {
+        self.redirect("2>", path.as_ref())
+    }
+
+    /// Send stderr wherever stdout goes at this point, so call it after
+    /// redirecting stdout to capture both in one file.
+    #[must_use]
+    pub fn stderr_to_stdout(mut self) -> Self {
+        self.redirects.push("2>&1".to_string());
+        self
+    }
+
+    fn redirect(mut self, operator: &str, path: &str) -> Self {
+        self.redirects
+            .push(format!("{operator}{}", shell_escape_remote_path(path)));
+        self
+    }
+
+    /// The command line, ready for `sh -c` or [`RemoteExecutor::exec`].
+    ///
+    /// [`RemoteExecutor::exec`]: crate::connection::RemoteExecutor::exec
+    #[must_use]
+    pub fn build(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for CommandBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(dir) = &self.cwd {
+            write!(f, "cd {dir} && ")?;
+        }
+        for (key, value) in &self.env {
+            write!(f, "{key}={value} ")?;
+        }
+        f.write_str(&self.words.join(" "))?;
+        for redirect in &self.redirects {
+            write!(f, " {redirect}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Characters that never mean anything to the shell inside a word.
+fn is_safe(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c)
+}
+
+fn literal(word: &str) -> String {
+    if !word.is_empty() && word.chars().all(is_safe) {
+        word.to_string()
+    } else {
+        shell_escape(word)
+    }
+}
+
+fn is_var_name(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -0,0 +1,33 @@
+use std::fmt::Write;
+
+use similar::TextDiff;
+
+/// Lines of unchanged context around each hunk.
+const CONTEXT_LINES: usize = 3;
+
+/// Unified diff of `old` → `new`, with `path` in the `---`/`+++` headers.
+/// Returns an empty string when the contents are identical.
+#[must_use]
+pub fn unified_diff(old: &str, new: &str, path: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .header(path, path)
+        .to_string()
+}
+
+/// Keep the first `max_lines` lines of a diff, noting how many were dropped.
+#[must_use]
+pub fn truncate_diff(diff: &str, max_lines: usize) -> String {
+    let total = diff.lines().count();
+    if total <= max_lines {
+        return diff.to_string();
+    }
+    let mut out: String = diff
+        .lines()
+        .take(max_lines)
+        .flat_map(|line| [line, "\n"])
+        .collect();
+    let _ = writeln!(out, "... ({} more diff lines)", total - max_lines);
+    out
+}
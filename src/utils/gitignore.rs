@@ -2,9 +2,12 @@ use std::path::Path;
 
 /// Lightweight .gitignore pattern matcher.
 ///
-/// Supports: `*`, `**`, `?`, comments (`#`), negation (`!`), dir-only trailing `/`,
-/// anchored patterns (containing `/`). Only reads a single .gitignore file
-/// (no nested .gitignore support).
+/// Supports: `*`, `**`, `?`, character classes (`[abc]`, `[0-9]`, `[!a-z]`),
+/// comments (`#`), negation (`!`), dir-only trailing `/`, anchored patterns
+/// (containing `/`), and backslash escapes (`\#`, `\!`, `\*`, and `\ ` to keep a
+/// trailing space). Reads whole files such as `.gitignore` or
+/// `.git/info/exclude` (no nested .gitignore support). Later rules win, so
+/// files are added lowest precedence first.
 #[derive(Default)]
 pub struct GitIgnore {
     rules: Vec<IgnoreRule>,
@@ -15,19 +18,45 @@ struct IgnoreRule {
     negated: bool,
     dir_only: bool,
     anchored: bool,
+    /// Path of the matched directory from the one the rule is relative to,
+    /// e.g. `web` for a repository-wide rule while walking `<repo>/web`.
+    /// Empty when they are the same.
+    base: String,
 }
 
 impl GitIgnore {
     /// Parse a .gitignore file. Returns empty ruleset if the file doesn't exist or is unreadable.
+    #[must_use]
     pub fn from_file(path: &Path) -> Self {
         let Ok(content) = std::fs::read_to_string(path) else {
             return Self::default();
         };
+        Self::parse(&content)
+    }
 
+    /// Parse .gitignore content, e.g. a file read from a remote server.
+    #[must_use]
+    pub fn parse(content: &str) -> Self {
         let rules = content.lines().filter_map(parse_line).collect();
         Self { rules }
     }
 
+    /// Append the rules of a gitignore-format file, written relative to a
+    /// parent of the matched directory; `base` is the matched directory's
+    /// path from that parent (`""` when they are the same). A missing or
+    /// unreadable file adds nothing.
+    pub fn extend_from_file(&mut self, path: &Path, base: &str) {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return;
+        };
+        let base = base.trim_matches('/');
+        self.rules.extend(content.lines().filter_map(|line| {
+            let mut rule = parse_line(line)?;
+            rule.base = base.to_string();
+            Some(rule)
+        }));
+    }
+
     /// Append extra exclusion patterns (same syntax as .gitignore lines).
     pub fn extend_patterns(&mut self, patterns: &[String]) {
         for line in patterns {
@@ -48,7 +77,9 @@ impl GitIgnore {
                 continue;
             }
 
-            let matches = if rule.anchored {
+            let matches = if rule.anchored && !rule.base.is_empty() {
+                glob_match(&rule.pattern, &format!("{}/{relative_path}", rule.base))
+            } else if rule.anchored {
                 glob_match(&rule.pattern, relative_path)
             } else {
                 // Non-anchored: match against the last path component
@@ -63,6 +94,34 @@ impl GitIgnore {
 
         ignored
     }
+
+    /// Check whether a file path is ignored, either directly or because one of
+    /// its parent directories is. Use this for paths that did not come from a
+    /// gitignore-aware walk (e.g. remote listings).
+    #[must_use]
+    pub fn is_path_ignored(&self, relative_path: &str) -> bool {
+        let mut end = 0;
+        while let Some(pos) = relative_path[end..].find('/') {
+            end += pos;
+            if self.is_ignored(&relative_path[..end], true) {
+                return true;
+            }
+            end += 1;
+        }
+        self.is_ignored(relative_path, false)
+    }
+}
+
+/// Keep only the items whose path matches one of the `include` patterns
+/// (gitignore syntax, so `src/` takes everything under `src`). With no
+/// patterns, everything is kept.
+pub fn retain_included<T>(items: &mut Vec<T>, include: &[String], path: impl Fn(&T) -> &str) {
+    if include.is_empty() {
+        return;
+    }
+    let mut rules = GitIgnore::default();
+    rules.extend_patterns(include);
+    items.retain(|item| rules.is_path_ignored(path(item)));
 }
 
 /// Parse a single .gitignore line into an `IgnoreRule`.
@@ -70,7 +129,7 @@ impl GitIgnore {
 /// Works entirely on `&str` slices to avoid intermediate allocations,
 /// creating a single owned `String` only for the final pattern.
 fn parse_line(line: &str) -> Option<IgnoreRule> {
-    let mut s = line.trim_end();
+    let mut s = trim_trailing_spaces(line);
     if s.is_empty() || s.starts_with('#') {
         return None;
     }
@@ -105,15 +164,35 @@ fn parse_line(line: &str) -> Option<IgnoreRule> {
         negated,
         dir_only,
         anchored,
+        base: String::new(),
     })
 }
 
+/// Strip the line ending and trailing spaces, keeping a space escaped with a
+/// backslash (`foo\ ` ends in a space).
+fn trim_trailing_spaces(line: &str) -> &str {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let mut end = 0;
+    let mut chars = line.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => end = chars.next().map_or(i + 1, |(j, next)| j + next.len_utf8()),
+            ' ' => {}
+            _ => end = i + c.len_utf8(),
+        }
+    }
+    &line[..end]
+}
+
 /// Match a gitignore-style glob pattern against text.
 ///
 /// - `*` matches any sequence of characters except `/`
 /// - `**` matches any sequence of characters including `/`
 /// - `?` matches any single character except `/`
-fn glob_match(pattern: &str, text: &str) -> bool {
+/// - `[...]` matches one character from a set (`[abc]`, `[a-z]`, `[!a-z]`)
+/// - `\` makes the next character literal
+#[must_use]
+pub fn glob_match(pattern: &str, text: &str) -> bool {
     glob_match_bytes(pattern.as_bytes(), text.as_bytes())
 }
 
@@ -135,6 +214,16 @@ fn glob_match_bytes(p: &[u8], t: &[u8]) -> bool {
         // ? — matches single char except /
         (Some(b'?'), Some(&c)) if c != b'/' => glob_match_bytes(&p[1..], &t[1..]),
 
+        // [...] — character class; an unclosed `[` is literal
+        (Some(b'['), Some(&c)) if c != b'/' => match match_class(p, c) {
+            Some((matched, len)) => matched && glob_match_bytes(&p[len..], &t[1..]),
+            None => c == b'[' && glob_match_bytes(&p[1..], &t[1..]),
+        },
+
+        // \x — literal x; a trailing backslash matches nothing
+        (Some(b'\\'), Some(&tc)) if p.get(1) == Some(&tc) => glob_match_bytes(&p[2..], &t[1..]),
+        (Some(b'\\'), _) => false,
+
         // Literal match
         (Some(&pc), Some(&tc)) if pc == tc => glob_match_bytes(&p[1..], &t[1..]),
 
@@ -142,6 +231,42 @@ fn glob_match_bytes(p: &[u8], t: &[u8]) -> bool {
     }
 }
 
+/// Match the `[...]` class at the start of `p` against `c`. Returns whether
+/// it matched and the class length in bytes, or `None` if it is unclosed.
+fn match_class(p: &[u8], c: u8) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(p.get(i), Some(b'!' | b'^'));
+    if negated {
+        i += 1;
+    }
+    let start = i;
+    let mut matched = false;
+    loop {
+        let mut lo = *p.get(i)?;
+        // A `]` right after the opening bracket is a member, not the end.
+        if lo == b']' && i > start {
+            break;
+        }
+        if lo == b'\\' {
+            i += 1;
+            lo = *p.get(i)?;
+        }
+        i += 1;
+        let mut hi = lo;
+        if p.get(i) == Some(&b'-') && p.get(i + 1).is_some_and(|&b| b != b']') {
+            i += 1;
+            hi = p[i];
+            if hi == b'\\' {
+                i += 1;
+                hi = *p.get(i)?;
+            }
+            i += 1;
+        }
+        matched |= (lo..=hi).contains(&c);
+    }
+    Some((matched != negated, i + 1))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -201,6 +326,47 @@ mod tests {
         assert!(!glob_match("fo?", "fo/"));
     }
 
+    #[test]
+    fn character_classes() {
+        assert!(glob_match("*.py[cod]", "foo.pyc"));
+        assert!(glob_match("*.py[cod]", "foo.pyd"));
+        assert!(!glob_match("*.py[cod]", "foo.py"));
+        assert!(!glob_match("*.py[cod]", "foo.pyx"));
+        assert!(glob_match("log[0-9]", "log7"));
+        assert!(!glob_match("log[0-9]", "logx"));
+        assert!(glob_match("[a-cx]", "x"));
+        assert!(!glob_match("[a-cx]", "d"));
+    }
+
+    #[test]
+    fn negated_character_classes() {
+        assert!(glob_match("[!a-z]*", "Makefile"));
+        assert!(!glob_match("[!a-z]*", "makefile"));
+        assert!(glob_match("[^0-9]", "a"));
+        assert!(!glob_match("[!x]", "/"));
+    }
+
+    #[test]
+    fn class_edge_cases() {
+        // `]` first is a member; `-` at the end is literal.
+        assert!(glob_match("[]a]", "]"));
+        assert!(glob_match("[a-]", "-"));
+        assert!(glob_match("[\\]]", "]"));
+        // Unclosed `[` is a literal bracket.
+        assert!(glob_match("foo[", "foo["));
+        assert!(!glob_match("foo[", "foo"));
+    }
+
+    #[test]
+    fn backslash_escapes() {
+        assert!(glob_match("\\*.txt", "*.txt"));
+        assert!(!glob_match("\\*.txt", "a.txt"));
+        assert!(glob_match("what\\?", "what?"));
+        assert!(!glob_match("what\\?", "whatx"));
+        assert!(glob_match("\\[abc]", "[abc]"));
+        assert!(!glob_match("foo\\", "foo\\"));
+    }
+
     #[test]
     fn trailing_stars_match_empty() {
         assert!(glob_match("foo*", "foo"));
@@ -216,6 +382,28 @@ mod tests {
         assert!(parse_line("# comment").is_none());
     }
 
+    #[test]
+    fn parse_trailing_spaces() {
+        assert_eq!(parse_line("build   ").unwrap().pattern, "build");
+        assert_eq!(parse_line("foo\\ ").unwrap().pattern, "foo\\ ");
+        assert_eq!(parse_line("foo\\  ").unwrap().pattern, "foo\\ ");
+        assert_eq!(parse_line("foo\\\\ ").unwrap().pattern, "foo\\\\");
+        assert_eq!(parse_line("dist\r").unwrap().pattern, "dist");
+        assert_eq!(parse_line("caf\\é ").unwrap().pattern, "caf\\é");
+    }
+
+    #[test]
+    fn parse_escaped_hash_and_bang() {
+        let rule = parse_line("\\#notes").unwrap();
+        assert!(!rule.negated);
+        assert!(glob_match(&rule.pattern, "#notes"));
+
+        let rule = parse_line("\\!important").unwrap();
+        assert!(!rule.negated);
+        assert!(glob_match(&rule.pattern, "!important"));
+        assert!(!glob_match(&rule.pattern, "important"));
+    }
+
     #[test]
     fn parse_simple_pattern() {
         let rule = parse_line("node_modules").unwrap();
@@ -325,6 +513,30 @@ mod tests {
         assert!(!gi.is_ignored("other/src/generated", true));
     }
 
+    #[test]
+    fn path_ignored_via_parent_dir() {
+        let gi = GitIgnore {
+            rules: vec![parse_line("node_modules/").unwrap()],
+        };
+        assert!(gi.is_path_ignored("node_modules/pkg/index.js"));
+        assert!(gi.is_path_ignored("web/node_modules/pkg/index.js"));
+        assert!(!gi.is_path_ignored("src/node_modules.rs"));
+    }
+
+    #[test]
+    fn escaped_trailing_space_in_gitignore() {
+        let gi = GitIgnore {
+            rules: vec![
+                parse_line("notes\\ ").unwrap(),
+                parse_line("*.py[co]").unwrap(),
+            ],
+        };
+        assert!(gi.is_ignored("notes ", false));
+        assert!(!gi.is_ignored("notes", false));
+        assert!(gi.is_ignored("pkg/mod.pyc", false));
+        assert!(!gi.is_ignored("pkg/mod.py", false));
+    }
+
     #[test]
     fn from_file_missing() {
         let gi = GitIgnore::from_file(Path::new("/nonexistent/.gitignore"));
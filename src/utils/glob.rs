@@ -0,0 +1,297 @@
+//! Glob patterns for `remote_glob`, matched against `/`-separated relative paths.
+//!
+//! - `*` matches within one path segment, `?` one character of it
+//! - `**` as a whole segment matches any number of directories, including none
+//! - `[abc]`, `[a-z]`, `[!abc]` match one character from a set
+//! - `{a,b}` matches either alternative (alternatives may nest and hold wildcards)
+//! - `\` escapes the next character
+//!
+//! A pattern without `/` matches the file name at any depth, so `*.rs` finds
+//! every Rust file below the search directory.
+
+use regex::Regex;
+use rmcp::schemars::{self, JsonSchema};
+use serde::Deserialize;
+
+#[derive(Debug, Clone)]
+pub struct GlobPattern {
+    regex: Regex,
+    base_dir: String,
+}
+
+impl GlobPattern {
+    /// Compile `pattern`. A leading `./` is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of an unbalanced brace or an empty pattern.
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let pattern = pattern.trim_start_matches("./");
+        if pattern.is_empty() {
+            return Err("pattern is empty".to_string());
+        }
+        let body = translate(pattern)?;
+        let source = if pattern.contains('/') {
+            format!("^{body}$")
+        } else {
+            format!("^(?:.*/)?{body}$")
+        };
+        let regex = Regex::new(&source).map_err(|e| format!("invalid pattern: {e}"))?;
+        Ok(Self {
+            regex,
+            base_dir: literal_base_dir(pattern),
+        })
+    }
+
+    /// Whether `path` (relative, `/`-separated, no leading `./`) matches.
+    #[must_use]
+    pub fn is_match(&self, path: &str) -> bool {
+        self.regex.is_match(path)
+    }
+
+    /// Leading directory of the pattern that contains no wildcards, e.g.
+    /// `src/lib` for `src/lib/**/*.rs`. Empty when the pattern starts with a
+    /// wildcard or has no `/`. Only paths under it can match.
+    #[must_use]
+    pub fn base_dir(&self) -> &str {
+        &self.base_dir
+    }
+}
+
+fn is_special(c: char) -> bool {
+    matches!(c, '*' | '?' | '[' | '{' | '\\')
+}
+
+fn literal_base_dir(pattern: &str) -> String {
+    let Some((dirs, _)) = pattern.rsplit_once('/') else {
+        return String::new();
+    };
+    dirs.split('/')
+        .take_while(|segment| !segment.contains(is_special))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn translate(pattern: &str) -> Result<String, String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut re = String::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let at_start = i == 0 || chars[i - 1] == '/';
+                let next = chars.get(i + 2);
+                if at_start && next == Some(&'/') {
+                    re.push_str("(?:.*/)?");
+                    i += 3;
+                    continue;
+                }
+                if at_start && next.is_none() {
+                    re.push_str(".*");
+                } else {
+                    re.push_str("[^/]*");
+                }
+                i += 2;
+                continue;
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            '[' => {
+                if let Some((class, end)) = char_class(&chars, i) {
+                    re.push_str(&class);
+                    i = end + 1;
+                    continue;
+                }
+                re.push_str(r"\[");
+            }
+            '{' => {
+                depth += 1;
+                re.push_str("(?:");
+            }
+            ',' if depth > 0 => re.push('|'),
+            '}' if depth > 0 => {
+                depth -= 1;
+                re.push(')');
+            }
+            '\\' => {
+                i += 1;
+                let escaped = chars.get(i).ok_or("pattern ends with '\\'")?;
+                re.push_str(&regex::escape(&escaped.to_string()));
+            }
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    if depth > 0 {
+        return Err("unclosed '{' in pattern".to_string());
+    }
+    Ok(re)
+}
+
+/// Translate the `[...]` class starting at `start`. Returns the regex class
+/// and the index of the closing `]`, or `None` if the class is unclosed.
+fn char_class(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let mut i = start + 1;
+    let negated = matches!(chars.get(i), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+    let first = i;
+    let mut class = String::from(if negated { "[^/" } else { "[" });
+    while let Some(&c) = chars.get(i) {
+        if c == ']' && i > first {
+            class.push(']');
+            return Some((class, i));
+        }
+        if matches!(c, '\\' | '[' | ']' | '^' | '&' | '~') {
+            class.push('\\');
+        }
+        class.push(c);
+        i += 1;
+    }
+    None
+}
+
+/// Which entry types a glob returns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GlobKind {
+    #[default]
+    Files,
+    Dirs,
+    All,
+}
+
+impl GlobKind {
+    #[must_use]
+    pub fn files(self) -> bool {
+        self != Self::Dirs
+    }
+
+    #[must_use]
+    pub fn dirs(self) -> bool {
+        self != Self::Files
+    }
+}
+
+/// Order of glob matches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GlobSort {
+    /// By path.
+    #[default]
+    Name,
+    /// Most recently modified first.
+    Mtime,
+}
+
+/// Which slice of the sorted matches to keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlobPage {
+    pub offset: usize,
+    pub limit: usize,
+    pub sort: GlobSort,
+}
+
+/// Result of filtering a directory listing against a [`GlobPattern`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GlobMatches {
+    /// Sorted matches, at most `limit` starting at `offset`. Directories
+    /// end with `/`.
+    pub paths: Vec<String>,
+    /// Matches found before applying the offset and limit.
+    pub total: usize,
+    /// Matches skipped before `paths`.
+    pub offset: usize,
+    /// The listing itself was cut short, so `total` is a lower bound.
+    pub incomplete: bool,
+}
+
+impl GlobMatches {
+    /// Whether matches exist beyond `paths`, or may exist beyond the listing.
+    #[must_use]
+    pub fn truncated(&self) -> bool {
+        self.incomplete || self.next_offset().is_some()
+    }
+
+    /// Offset of the next page, if any matches follow `paths`.
+    #[must_use]
+    pub fn next_offset(&self) -> Option<usize> {
+        let next = self.offset + self.paths.len();
+        (next < self.total).then_some(next)
+    }
+}
+
+/// Filter `entries` (relative paths; directories end with `/`) by `pattern`
+/// and `kind`, keeping the first `limit` in sorted order.
+#[must_use]
+pub fn filter_matches<'a>(
+    pattern: &GlobPattern,
+    entries: impl IntoIterator<Item = &'a str>,
+    kind: GlobKind,
+    limit: usize,
+) -> GlobMatches {
+    let page = GlobPage {
+        offset: 0,
+        limit,
+        sort: GlobSort::Name,
+    };
+    page_matches(
+        pattern,
+        entries.into_iter().map(|entry| (entry, None)),
+        kind,
+        page,
+    )
+}
+
+/// Filter `entries` (relative paths with their mtimes in seconds) like
+/// [`filter_matches`], sort them by `page.sort`, and keep one page. Entries
+/// without an mtime sort after the rest when sorting by mtime.
+#[must_use]
+pub fn page_matches<'a>(
+    pattern: &GlobPattern,
+    entries: impl IntoIterator<Item = (&'a str, Option<f64>)>,
+    kind: GlobKind,
+    page: GlobPage,
+) -> GlobMatches {
+    let mut matches: Vec<(String, Option<f64>)> = entries
+        .into_iter()
+        .map(|(e, mtime)| (e.trim_start_matches("./"), mtime))
+        .filter(|(e, _)| !e.is_empty())
+        .filter(|(e, _)| {
+            let (path, is_dir) = match e.strip_suffix('/') {
+                Some(dir) => (dir, true),
+                None => (*e, false),
+            };
+            let wanted = if is_dir { kind.dirs() } else { kind.files() };
+            wanted && pattern.is_match(path)
+        })
+        .map(|(e, mtime)| (e.to_string(), mtime))
+        .collect();
+    matches.sort_by(|a, b| a.0.cmp(&b.0));
+    matches.dedup_by(|a, b| a.0 == b.0);
+    if page.sort == GlobSort::Mtime {
+        // Stable, so equal mtimes stay in path order
+        matches.sort_by(|a, b| match (a.1, b.1) {
+            (Some(a), Some(b)) => b.total_cmp(&a),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+    }
+    let total = matches.len();
+    let paths = matches
+        .into_iter()
+        .skip(page.offset)
+        .take(page.limit)
+        .map(|(path, _)| path)
+        .collect();
+    GlobMatches {
+        paths,
+        total,
+        offset: page.offset,
+        incomplete: false,
+    }
+}
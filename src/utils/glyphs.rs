@@ -0,0 +1,81 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Environment variable that forces ASCII (`1`) or Unicode (`0`) output.
+pub const ASCII_ENV: &str = "SSH_HUB_ASCII";
+
+/// Non-ASCII glyphs used in CLI and tool output, with plain-ASCII fallbacks
+/// for terminals and screen readers that garble Unicode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Glyphs {
+    /// Separator between line number and content in `format_with_line_numbers`.
+    pub arrow: &'static str,
+    /// Spinner animation frames (last frame is blank).
+    pub spinner: &'static [&'static str],
+}
+
+impl Glyphs {
+    pub const UNICODE: Self = Self {
+        arrow: "\u{2192}",
+        spinner: &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏", " "],
+    };
+
+    pub const ASCII: Self = Self {
+        arrow: "->",
+        spinner: &["|", "/", "-", "\\", " "],
+    };
+
+    /// Glyph set for the current process (see [`ascii_mode`]).
+    #[must_use]
+    pub fn current() -> Self {
+        if ascii_mode() {
+            Self::ASCII
+        } else {
+            Self::UNICODE
+        }
+    }
+}
+
+/// 0 = not yet decided, 1 = ASCII, 2 = Unicode.
+static MODE: AtomicU8 = AtomicU8::new(0);
+
+/// Force ASCII output on or off for the rest of the process (`--ascii`).
+pub fn set_ascii_mode(ascii: bool) {
+    MODE.store(if ascii { 1 } else { 2 }, Ordering::Relaxed);
+}
+
+/// Whether output should be plain ASCII. Forced by [`set_ascii_mode`] or
+/// `SSH_HUB_ASCII`, otherwise detected from `TERM` and the locale.
+#[must_use]
+pub fn ascii_mode() -> bool {
+    match MODE.load(Ordering::Relaxed) {
+        1 => return true,
+        2 => return false,
+        _ => {}
+    }
+    let ascii = match std::env::var(ASCII_ENV).ok().as_deref() {
+        Some("1" | "true") => true,
+        Some("0" | "false") => false,
+        _ => {
+            let term = std::env::var("TERM").ok();
+            let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+                .iter()
+                .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()));
+            detect_ascii(term.as_deref(), locale.as_deref())
+        }
+    };
+    set_ascii_mode(ascii);
+    ascii
+}
+
+/// ASCII is needed on dumb terminals and under an explicit non-UTF-8 locale.
+/// An unset locale is treated as UTF-8-capable so defaults don't change.
+#[must_use]
+pub fn detect_ascii(term: Option<&str>, locale: Option<&str>) -> bool {
+    if term == Some("dumb") {
+        return true;
+    }
+    locale.is_some_and(|l| {
+        let l = l.to_ascii_lowercase();
+        !l.contains("utf-8") && !l.contains("utf8")
+    })
+}
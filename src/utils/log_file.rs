@@ -0,0 +1,169 @@
+//! Daily-rotated log file.
+//!
+//! Logs for `base` = `.../ssh-hub.log` go to `.../ssh-hub.log.2026-10-16`,
+//! one file per UTC day. When a new day's file is opened, the oldest dated
+//! files beyond the retention limit are deleted.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Daily log files kept by default.
+pub const DEFAULT_KEEP_DAYS: usize = 14;
+
+const SECS_PER_DAY: u64 = 86_400;
+
+/// Default log file base path: `~/.local/share/ssh-hub/logs/ssh-hub.log`
+/// (macOS: `~/Library/Application Support/ssh-hub/logs/ssh-hub.log`).
+#[must_use]
+pub fn default_log_path() -> Option<PathBuf> {
+    Some(
+        dirs::data_dir()?
+            .join("ssh-hub")
+            .join("logs")
+            .join("ssh-hub.log"),
+    )
+}
+
+/// Append-only writer that switches to a new dated file when the UTC day changes.
+pub struct RollingFile {
+    base: PathBuf,
+    keep: usize,
+    day: u64,
+    file: File,
+}
+
+impl RollingFile {
+    /// Create the log directory if needed, open today's file, and prune old ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or file cannot be created.
+    pub fn open(base: PathBuf, keep: usize) -> io::Result<Self> {
+        if let Some(dir) = base.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let day = today();
+        let file = open_append(&dated_path(&base, day))?;
+        let rolling = Self {
+            base,
+            keep: keep.max(1),
+            day,
+            file,
+        };
+        rolling.prune();
+        Ok(rolling)
+    }
+
+    /// Path of the file currently written to.
+    #[must_use]
+    pub fn current_path(&self) -> PathBuf {
+        dated_path(&self.base, self.day)
+    }
+
+    fn rotate_if_needed(&mut self) -> io::Result<()> {
+        let day = today();
+        if day != self.day {
+            self.file = open_append(&dated_path(&self.base, day))?;
+            self.day = day;
+            self.prune();
+        }
+        Ok(())
+    }
+
+    /// Best effort: a failed delete is retried at the next rotation.
+    fn prune(&self) {
+        let (Some(dir), Some(name)) = (self.base.parent(), self.base.file_name()) else {
+            return;
+        };
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let names: Vec<String> = entries
+            .filter_map(Result::ok)
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        for expired in expired_logs(&names, &name.to_string_lossy(), self.keep) {
+            let _ = std::fs::remove_file(dir.join(expired));
+        }
+    }
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.rotate_if_needed()?;
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// `base` with a `.YYYY-MM-DD` suffix for `day` (days since the Unix epoch).
+#[must_use]
+pub fn dated_path(base: &Path, day: u64) -> PathBuf {
+    let mut name = base.as_os_str().to_os_string();
+    name.push(".");
+    name.push(format_day(day));
+    PathBuf::from(name)
+}
+
+/// Dated log files for `base_name` beyond the newest `keep`, oldest first.
+/// Names that don't end in a `YYYY-MM-DD` date are left alone.
+#[must_use]
+pub fn expired_logs(names: &[String], base_name: &str, keep: usize) -> Vec<String> {
+    let prefix = format!("{base_name}.");
+    let mut dated: Vec<&String> = names
+        .iter()
+        .filter(|n| n.strip_prefix(&prefix).is_some_and(is_date))
+        .collect();
+    // ISO dates sort chronologically as strings.
+    dated.sort();
+    let expired = dated.len().saturating_sub(keep);
+    dated.into_iter().take(expired).cloned().collect()
+}
+
+/// Format days since the Unix epoch as a `YYYY-MM-DD` UTC date.
+#[must_use]
+pub fn format_day(day: u64) -> String {
+    let (y, m, d) = civil_date(day);
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+/// Year, month (1-12) and day of month for days since the Unix epoch.
+#[must_use]
+pub fn civil_date(day: u64) -> (u64, u64, u64) {
+    // Civil-from-days (H. Hinnant), shifted so eras start on 0000-03-01.
+    let z = day + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + u64::from(m <= 2);
+    (y, m, d)
+}
+
+fn is_date(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() == 10
+        && b[4] == b'-'
+        && b[7] == b'-'
+        && b.iter()
+            .enumerate()
+            .all(|(i, c)| i == 4 || i == 7 || c.is_ascii_digit())
+}
+
+fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() / SECS_PER_DAY)
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
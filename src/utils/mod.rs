@@ -1,2 +1,11 @@
+pub mod command;
+pub mod diff;
 pub mod gitignore;
+pub mod glob;
+pub mod glyphs;
+pub mod log_file;
+pub mod output;
+pub mod patch;
 pub mod path;
+pub mod validate;
+pub mod walk;
@@ -0,0 +1,259 @@
+//! Output budget: caps the text any tool returns inline.
+//!
+//! Text over [`MAX_INLINE_OUTPUT`] is written in full to
+//! `<temp dir>/ssh-hub/<tool>-<timestamp>.log` and replaced by a summary
+//! naming that file: the first [`SUMMARY_HEAD_LINES`] and last
+//! [`SUMMARY_TAIL_LINES`] lines, each part cut to a byte cap so a handful of
+//! very long lines can't fill the context either. If the file can't be
+//! written, the text is cut at [`MAX_INLINE_OUTPUT`] instead.
+//!
+//! Tools that return JSON cap their unbounded fields themselves so the JSON
+//! stays valid: `remote_bash`, `local_bash`, command templates, and
+//! `remote_tail` run their text fields through [`fit_output`], and tools that
+//! return lists use [`fit_json`], which cuts the lists and says where the full
+//! result was saved. Plain-text results go through [`fit_output`] whole.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Text larger than this is saved to disk instead of returned inline.
+/// 128 KB keeps the LLM context window manageable while still showing
+/// a meaningful amount of output for most commands.
+pub const MAX_INLINE_OUTPUT: usize = 128 * 1024;
+
+/// Number of lines from the start included in the disk-save summary.
+pub const SUMMARY_HEAD_LINES: usize = 150;
+
+/// Number of lines from the end included in the disk-save summary.
+pub const SUMMARY_TAIL_LINES: usize = 50;
+
+/// Most bytes of the summary's head section.
+const SUMMARY_HEAD_BYTES: usize = 48 * 1024;
+
+/// Most bytes of the summary's tail section.
+const SUMMARY_TAIL_BYTES: usize = 16 * 1024;
+
+/// SI kilobyte (1000 bytes), used for human-readable size display.
+const BYTES_PER_KB: usize = 1_000;
+/// SI megabyte (1,000,000 bytes), used for human-readable size display.
+const BYTES_PER_MB: usize = 1_000_000;
+/// Divisor to extract tenths-of-megabytes for human-readable size formatting.
+const TENTHS_MB_DIVISOR: usize = 100_000;
+
+/// Pre-allocated capacity for the output summary string (32 KB).
+const SUMMARY_BUFFER_CAPACITY: usize = 32 * 1024;
+
+/// Tells apart files saved within the same millisecond.
+static SAVED_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// `output` as returned inline: unchanged up to [`MAX_INLINE_OUTPUT`], and
+/// otherwise saved to a local temp file named after `tool` and replaced by
+/// a head/tail summary naming it.
+pub async fn fit_output(output: String, tool: &str) -> String {
+    if output.len() <= MAX_INLINE_OUTPUT {
+        return output;
+    }
+    match save_output_to_disk(&output, tool).await {
+        Ok(path) => build_output_summary(&output, &path),
+        Err(e) => {
+            tracing::warn!("Failed to save large output to disk: {}", e);
+            truncate_inline(&output)
+        }
+    }
+}
+
+/// `value` as pretty JSON: unchanged up to [`MAX_INLINE_OUTPUT`], and
+/// otherwise saved in full to a local temp file named after `tool`, with
+/// entries dropped from the end of the arrays at `lists` (JSON pointers such
+/// as `/transferred`), in order, until it fits.
+///
+/// A cut result stays valid JSON and gains `truncated: true`, `saved_to`
+/// naming the file (absent if it couldn't be written), and `omitted`
+/// counting the entries dropped from each list.
+pub async fn fit_json<T: Serialize>(value: &T, lists: &[&str], tool: &str) -> String {
+    let mut json = match serde_json::to_value(value) {
+        Ok(json) => json,
+        Err(e) => return format!(r#"{{"error": "serialization failed: {e}"}}"#),
+    };
+    let full = pretty(&json);
+    if full.len() <= MAX_INLINE_OUTPUT {
+        return full;
+    }
+    let saved_to = match save_output_to_disk(&full, tool).await {
+        Ok(path) => Some(path.display().to_string()),
+        Err(e) => {
+            tracing::warn!("Failed to save large output to disk: {}", e);
+            None
+        }
+    };
+    let Some(fields) = json.as_object_mut() else {
+        return too_large(saved_to);
+    };
+    fields.insert("truncated".to_string(), Value::Bool(true));
+    if let Some(path) = &saved_to {
+        fields.insert("saved_to".to_string(), json!(path));
+    }
+
+    let mut omitted: BTreeMap<&str, usize> = BTreeMap::new();
+    for pointer in lists {
+        let name = pointer.rsplit('/').next().unwrap_or(pointer);
+        loop {
+            let over = pretty(&json).len().saturating_sub(MAX_INLINE_OUTPUT);
+            if over == 0 {
+                break;
+            }
+            let Some(Value::Array(items)) = json.pointer_mut(pointer) else {
+                break;
+            };
+            if items.is_empty() {
+                break;
+            }
+            // Nested entries are indented further than they are here, so
+            // this may free too little; the loop measures again.
+            let mut freed = 0;
+            while freed < over {
+                let Some(item) = items.pop() else { break };
+                freed += pretty(&item).len() + 2;
+                *omitted.entry(name).or_default() += 1;
+            }
+            json["omitted"] = json!(omitted);
+        }
+    }
+    let fitted = pretty(&json);
+    if fitted.len() <= MAX_INLINE_OUTPUT {
+        fitted
+    } else {
+        too_large(saved_to)
+    }
+}
+
+/// Pretty JSON of a value that came from `serde_json::to_value`.
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+/// Stand-in for JSON that is still too large after cutting its lists.
+fn too_large(saved_to: Option<String>) -> String {
+    let mut result = json!({
+        "truncated": true,
+        "note": "The result was too large to return inline.",
+    });
+    if let Some(path) = saved_to {
+        result["saved_to"] = json!(path);
+    }
+    pretty(&result)
+}
+
+/// Save `output` to a temp file and return the path.
+async fn save_output_to_disk(output: &str, tool: &str) -> Result<PathBuf, std::io::Error> {
+    let dir = std::env::temp_dir().join("ssh-hub");
+    tokio::fs::create_dir_all(&dir).await?;
+
+    let ts = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let seq = SAVED_FILES.fetch_add(1, Ordering::Relaxed);
+    let path = dir.join(format!(
+        "{tool}-{}.{:03}-{seq}.log",
+        ts.as_secs(),
+        ts.subsec_millis()
+    ));
+    tokio::fs::write(&path, output).await?;
+    Ok(path)
+}
+
+/// Build a head/tail summary with a pointer to the full output on disk.
+#[must_use]
+pub fn build_output_summary(output: &str, file_path: &Path) -> String {
+    let lines: Vec<&str> = output.lines().collect();
+    let total_lines = lines.len();
+
+    let size_str = format_byte_size(output.len());
+    let mut out = String::with_capacity(SUMMARY_BUFFER_CAPACITY);
+
+    let _ = writeln!(
+        out,
+        "[Output too large for context ({size_str}, {total_lines} lines)]",
+    );
+    let _ = writeln!(out, "Full output saved to: {}", file_path.display());
+
+    let head_lines = SUMMARY_HEAD_LINES.min(total_lines);
+    let tail_lines = SUMMARY_TAIL_LINES.min(total_lines - head_lines);
+    let _ = writeln!(out, "\n--- First {head_lines} lines ---");
+    out.push_str(&head(&lines[..head_lines], SUMMARY_HEAD_BYTES));
+
+    let omitted = total_lines - head_lines - tail_lines;
+    if omitted > 0 {
+        let _ = writeln!(out, "\n... ({omitted} lines omitted) ...");
+    }
+    if tail_lines > 0 {
+        let _ = writeln!(out, "\n--- Last {tail_lines} lines ---");
+        out.push_str(&tail(
+            &lines[total_lines - tail_lines..],
+            SUMMARY_TAIL_BYTES,
+        ));
+    }
+    out
+}
+
+/// `lines` joined with newlines, cut after `max_bytes`.
+fn head(lines: &[&str], max_bytes: usize) -> String {
+    let mut text = lines.join("\n");
+    text.push('\n');
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let cut = text.len() - end;
+    text.truncate(end);
+    let _ = writeln!(text, "\n[... {} cut ...]", format_byte_size(cut));
+    text
+}
+
+/// `lines` joined with newlines, keeping only the last `max_bytes`.
+fn tail(lines: &[&str], max_bytes: usize) -> String {
+    let mut text = lines.join("\n");
+    text.push('\n');
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut start = text.len() - max_bytes;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    format!(
+        "[... {} cut ...]\n{}",
+        format_byte_size(start),
+        &text[start..]
+    )
+}
+
+/// Format a byte count as a human-readable size string (e.g., "1.3 MB", "450 KB").
+#[must_use]
+pub fn format_byte_size(bytes: usize) -> String {
+    if bytes >= BYTES_PER_MB {
+        let tenths_mb = bytes / TENTHS_MB_DIVISOR;
+        format!("{}.{} MB", tenths_mb / 10, tenths_mb % 10)
+    } else {
+        format!("{} KB", bytes / BYTES_PER_KB)
+    }
+}
+
+/// Fallback: truncate `output` at a char boundary when the disk write fails.
+fn truncate_inline(output: &str) -> String {
+    let mut end = MAX_INLINE_OUTPUT;
+    while !output.is_char_boundary(end) && end > 0 {
+        end -= 1;
+    }
+    let mut s = output[..end].to_string();
+    s.push_str("\n\n[Output truncated — failed to save to disk]");
+    s
+}
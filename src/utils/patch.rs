@@ -0,0 +1,433 @@
+//! Parsing unified diffs and applying them hunk by hunk, for `remote_patch`.
+//!
+//! A hunk applies where its context and removed lines match the file: at
+//! the line its header names, shifted by how far earlier hunks moved, or
+//! else at the nearest place the same lines appear, like `patch` does
+//! without fuzz. Lines compare without their line ending, so a patch made
+//! with `\n` applies to a file with `\r\n`, and added lines take the file's
+//! line ending.
+
+use std::fmt::{self, Write};
+
+/// One file's part of a unified diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePatch {
+    /// Path from the `---` header, `None` for `/dev/null` (a new file).
+    pub old_path: Option<String>,
+    /// Path from the `+++` header, `None` for `/dev/null` (a deleted file).
+    pub new_path: Option<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    /// The path the patch changes, preferring the new name.
+    #[must_use]
+    pub fn path(&self) -> Option<&str> {
+        self.new_path.as_deref().or(self.old_path.as_deref())
+    }
+}
+
+/// One `@@ -a,b +c,d @@` section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// 1-based first line of the old side (the line after which to insert
+    /// when `old_len` is 0).
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub lines: Vec<HunkLine>,
+    /// The old side's last line has no newline (`\ No newline at end of file`).
+    pub old_missing_newline: bool,
+    /// The new side's last line has no newline.
+    pub new_missing_newline: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+impl Hunk {
+    /// The `@@ -a,b +c,d @@` header.
+    #[must_use]
+    pub fn header(&self) -> String {
+        format!(
+            "@@ -{},{} +{},{} @@",
+            self.old_start, self.old_len, self.new_start, self.new_len
+        )
+    }
+
+    fn old_lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(text) | HunkLine::Remove(text) => Some(text.as_str()),
+            HunkLine::Add(_) => None,
+        })
+    }
+}
+
+/// Why a hunk didn't apply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HunkFailure {
+    /// 1-based position of the hunk in its file's patch.
+    pub index: usize,
+    pub header: String,
+    pub reason: String,
+}
+
+impl fmt::Display for HunkFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "hunk {} ({}): {}", self.index, self.header, self.reason)
+    }
+}
+
+/// Where each hunk landed, relative to the line its header names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Applied {
+    pub content: String,
+    /// Line offset of each hunk; 0 when it applied where the header says.
+    pub offsets: Vec<isize>,
+}
+
+/// Parse a unified diff, as `diff -u` or `git diff` write it. Lines before
+/// the first `---` header, such as `diff --git` and `index` lines, are
+/// skipped. A diff of hunks without headers is returned as one `FilePatch`
+/// with no paths, for the caller to name.
+///
+/// # Errors
+///
+/// Returns a message naming the line for a malformed header or a hunk whose
+/// line counts don't match its header.
+pub fn parse_patch(text: &str) -> Result<Vec<FilePatch>, String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut patches = Vec::new();
+    let mut current: Option<FilePatch> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if let Some(old) = line.strip_prefix("--- ") {
+            let Some(new) = lines.get(i + 1).and_then(|l| l.strip_prefix("+++ ")) else {
+                return Err(format!("line {}: '---' header without '+++'", i + 1));
+            };
+            patches.extend(current.take());
+            current = Some(FilePatch {
+                old_path: header_path(old, "a/"),
+                new_path: header_path(new, "b/"),
+                hunks: Vec::new(),
+            });
+            i += 2;
+        } else if line.starts_with("@@") {
+            let (hunk, next) = parse_hunk(&lines, i)?;
+            current
+                .get_or_insert_with(|| FilePatch {
+                    old_path: None,
+                    new_path: None,
+                    hunks: Vec::new(),
+                })
+                .hunks
+                .push(hunk);
+            i = next;
+        } else {
+            i += 1;
+        }
+    }
+    patches.extend(current);
+    if patches.is_empty() {
+        return Err("no hunks found; expected a unified diff with @@ headers".to_string());
+    }
+    if let Some(empty) = patches.iter().find(|p| p.hunks.is_empty()) {
+        return Err(format!(
+            "no hunks for {}",
+            empty.path().unwrap_or("/dev/null")
+        ));
+    }
+    Ok(patches)
+}
+
+/// The path in a `---`/`+++` header: without a trailing timestamp, the
+/// `a/`/`b/` prefix `git diff` adds, or `None` for `/dev/null`.
+fn header_path(header: &str, git_prefix: &str) -> Option<String> {
+    let path = header.split('\t').next().unwrap_or(header).trim_end();
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(git_prefix).unwrap_or(path).to_string())
+}
+
+/// Parse the hunk whose header is `lines[start]`, returning it and the
+/// index of the line after it.
+fn parse_hunk(lines: &[&str], start: usize) -> Result<(Hunk, usize), String> {
+    let header = lines[start];
+    let invalid = || format!("line {}: invalid hunk header '{header}'", start + 1);
+    let ranges = header
+        .strip_prefix("@@ ")
+        .and_then(|rest| rest.split(" @@").next())
+        .ok_or_else(invalid)?;
+    let (old, new) = ranges.split_once(' ').ok_or_else(invalid)?;
+    let (old_start, old_len) = old
+        .strip_prefix('-')
+        .and_then(parse_range)
+        .ok_or_else(invalid)?;
+    let (new_start, new_len) = new
+        .strip_prefix('+')
+        .and_then(parse_range)
+        .ok_or_else(invalid)?;
+
+    let mut hunk = Hunk {
+        old_start,
+        old_len,
+        new_start,
+        new_len,
+        lines: Vec::new(),
+        old_missing_newline: false,
+        new_missing_newline: false,
+    };
+    let (mut old_seen, mut new_seen) = (0, 0);
+    let mut i = start + 1;
+    while i < lines.len() {
+        let line = lines[i];
+        if line.starts_with('\\') {
+            // "\ No newline at end of file" for the line before it
+            match hunk.lines.last() {
+                Some(HunkLine::Remove(_)) => hunk.old_missing_newline = true,
+                Some(HunkLine::Add(_)) => hunk.new_missing_newline = true,
+                Some(HunkLine::Context(_)) => {
+                    hunk.old_missing_newline = true;
+                    hunk.new_missing_newline = true;
+                }
+                None => return Err(format!("line {}: misplaced '{line}'", i + 1)),
+            }
+            i += 1;
+            continue;
+        }
+        if old_seen == old_len && new_seen == new_len {
+            break;
+        }
+        // Editors and mail clients drop the space of an empty context line
+        let (kind, text) = match line.chars().next() {
+            Some(c @ (' ' | '-' | '+')) => (c, &line[1..]),
+            None => (' ', ""),
+            Some(_) => break,
+        };
+        match kind {
+            ' ' => {
+                old_seen += 1;
+                new_seen += 1;
+                hunk.lines.push(HunkLine::Context(text.to_string()));
+            }
+            '-' => {
+                old_seen += 1;
+                hunk.lines.push(HunkLine::Remove(text.to_string()));
+            }
+            _ => {
+                new_seen += 1;
+                hunk.lines.push(HunkLine::Add(text.to_string()));
+            }
+        }
+        i += 1;
+    }
+    if old_seen != old_len || new_seen != new_len {
+        return Err(format!(
+            "line {}: hunk {} has {old_seen} old and {new_seen} new lines, \
+             but its header says {old_len} and {new_len}",
+            start + 1,
+            hunk.header(),
+        ));
+    }
+    Ok((hunk, i))
+}
+
+/// `start,len` or `start` (a length of 1).
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+/// Apply `hunks` to `content`. Every hunk is tried, so a failure reports
+/// all the hunks that don't match rather than just the first.
+///
+/// # Errors
+///
+/// Returns one [`HunkFailure`] per hunk whose lines aren't in the file, or
+/// that would overlap an earlier hunk.
+pub fn apply_hunks(content: &str, hunks: &[Hunk]) -> Result<Applied, Vec<HunkFailure>> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let eol = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut out = String::with_capacity(content.len());
+    let mut offsets = Vec::with_capacity(hunks.len());
+    let mut failures = Vec::new();
+    // Next line of the file not yet copied to `out`
+    let mut copied = 0;
+    let mut shift: isize = 0;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        let old: Vec<&str> = hunk.old_lines().collect();
+        // With no old lines, old_start is the line to insert after
+        let named = if old.is_empty() {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let expected = named.saturating_add_signed(shift);
+        let fail = |reason: String| HunkFailure {
+            index: index + 1,
+            header: hunk.header(),
+            reason,
+        };
+
+        let Some(at) = find_hunk(&lines, &old, expected, copied) else {
+            failures.push(fail(mismatch(&lines, &old, expected, copied)));
+            continue;
+        };
+        let offset = signed_distance(named, at);
+        shift = offset;
+        offsets.push(offset);
+
+        out.extend(lines[copied..at].iter().copied());
+        let mut file_lines = lines[at..at + old.len()].iter();
+        // The new side's last line, where a missing newline applies
+        let last = hunk
+            .lines
+            .iter()
+            .rposition(|line| !matches!(line, HunkLine::Remove(_)));
+        for (n, line) in hunk.lines.iter().enumerate() {
+            let ending = if Some(n) == last && hunk.new_missing_newline {
+                ""
+            } else {
+                eol
+            };
+            match line {
+                // Context keeps the file's own line, ending included
+                HunkLine::Context(_) => {
+                    let original = file_lines.next().copied().unwrap_or_default();
+                    if ending.is_empty() {
+                        out.push_str(strip_eol(original));
+                    } else {
+                        out.push_str(original);
+                    }
+                }
+                HunkLine::Remove(_) => {
+                    file_lines.next();
+                }
+                HunkLine::Add(text) => {
+                    out.push_str(text);
+                    out.push_str(ending);
+                }
+            }
+        }
+        copied = at + old.len();
+    }
+
+    if !failures.is_empty() {
+        return Err(failures);
+    }
+    out.extend(lines[copied..].iter().copied());
+    Ok(Applied {
+        content: out,
+        offsets,
+    })
+}
+
+/// The line where `old` matches at or after `from`: `expected` when it
+/// matches there, else the nearest match.
+fn find_hunk(lines: &[&str], old: &[&str], expected: usize, from: usize) -> Option<usize> {
+    let fits = |at: usize| {
+        at >= from
+            && at + old.len() <= lines.len()
+            && old
+                .iter()
+                .zip(&lines[at..])
+                .all(|(want, have)| same_line(want, have))
+    };
+    if fits(expected) {
+        return Some(expected);
+    }
+    // Without lines to match, only the named position is trusted
+    if old.is_empty() {
+        return None;
+    }
+    (1..=lines.len()).find_map(|distance| {
+        let before = expected.checked_sub(distance).filter(|&at| fits(at));
+        before.or_else(|| Some(expected + distance).filter(|&at| fits(at)))
+    })
+}
+
+/// `to - from` as a signed line offset, saturating for headers that name
+/// lines far past the end of the file.
+fn signed_distance(from: usize, to: usize) -> isize {
+    if to >= from {
+        isize::try_from(to - from).unwrap_or(isize::MAX)
+    } else {
+        isize::try_from(from - to).map_or(isize::MIN, |n| -n)
+    }
+}
+
+/// Why `old` doesn't match at `expected`: the first line that differs.
+fn mismatch(lines: &[&str], old: &[&str], expected: usize, from: usize) -> String {
+    if expected < from {
+        return format!(
+            "overlaps the previous hunk, which ends at line {from}, and its lines \
+             are not found further on"
+        );
+    }
+    for (n, want) in old.iter().enumerate() {
+        let line_no = expected + n + 1;
+        match lines.get(expected + n) {
+            None => {
+                return format!(
+                    "expected \"{want}\" at line {line_no}, but the file has only {} lines, \
+                     and the hunk's lines are not found elsewhere",
+                    lines.len()
+                );
+            }
+            Some(have) if !same_line(want, have) => {
+                return format!(
+                    "expected \"{want}\" at line {line_no}, found \"{}\", \
+                     and the hunk's lines are not found elsewhere",
+                    strip_eol(have)
+                );
+            }
+            Some(_) => {}
+        }
+    }
+    format!(
+        "cannot insert after line {expected}, the file has {} lines",
+        lines.len()
+    )
+}
+
+fn same_line(want: &str, have: &str) -> bool {
+    want.strip_suffix('\r').unwrap_or(want) == strip_eol(have)
+}
+
+fn strip_eol(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+/// "hunk 2 at +3 lines, hunk 4 at -1 line" for hunks that moved, or an
+/// empty string.
+#[must_use]
+pub fn describe_offsets(offsets: &[isize]) -> String {
+    let mut out = String::new();
+    for (n, &offset) in offsets.iter().enumerate().filter(|(_, o)| **o != 0) {
+        if !out.is_empty() {
+            out.push_str(", ");
+        }
+        let unit = if offset.unsigned_abs() == 1 {
+            "line"
+        } else {
+            "lines"
+        };
+        let _ = write!(out, "hunk {} at {offset:+} {unit}", n + 1);
+    }
+    out
+}
@@ -2,6 +2,8 @@ use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Result};
 
+use super::glyphs::Glyphs;
+
 /// Escape a string for safe interpolation into a POSIX shell command.
 /// Wraps in single quotes with internal `'` escaped as `'\''`.
 #[must_use]
@@ -23,6 +25,19 @@ pub fn shell_escape_remote_path(path: &str) -> String {
     }
 }
 
+/// Convert a remote path to the form SFTP and rsync expect: `~` and `~/x`
+/// become paths relative to the login directory, since neither shell-expands.
+#[must_use]
+pub fn home_relative(path: &str) -> String {
+    if path == "~" {
+        ".".to_string()
+    } else if let Some(rest) = path.strip_prefix("~/") {
+        rest.to_string()
+    } else {
+        path.to_string()
+    }
+}
+
 /// Validate that a relative path stays within the base directory after resolution.
 /// Canonicalizes both paths to catch `..` traversal and symlink escapes.
 /// Returns the canonical path on success.
@@ -67,6 +82,12 @@ pub fn normalize_remote_path(path: &str, base_path: &str) -> String {
 /// Uses a single pre-allocated `String` instead of collecting into a `Vec` and joining.
 #[must_use]
 pub fn format_with_line_numbers(content: &str, offset: usize) -> String {
+    format_with_line_numbers_using(content, offset, Glyphs::current())
+}
+
+/// [`format_with_line_numbers`] with an explicit glyph set.
+#[must_use]
+pub fn format_with_line_numbers_using(content: &str, offset: usize, glyphs: Glyphs) -> String {
     use std::fmt::Write;
 
     // Estimate: ~8 chars prefix + average line length
@@ -77,7 +98,7 @@ pub fn format_with_line_numbers(content: &str, offset: usize) -> String {
         if i > 0 {
             result.push('\n');
         }
-        let _ = write!(result, "{:>6}\u{2192}{}", offset + i + 1, line);
+        let _ = write!(result, "{:>6}{}{}", offset + i + 1, glyphs.arrow, line);
     }
 
     result
@@ -0,0 +1,18 @@
+//! Checks for names and modes that tools pass to remote commands.
+
+/// Package, unit, and user names: letters, digits, and `@._+:-`, not
+/// starting with `-`.
+#[must_use]
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "@._+:-".contains(c))
+}
+
+/// Whether `mode` is a `chmod` mode of 3 or 4 octal digits, like `644`.
+#[must_use]
+pub fn is_valid_mode(mode: &str) -> bool {
+    (3..=4).contains(&mode.len()) && mode.chars().all(|c| ('0'..='7').contains(&c))
+}
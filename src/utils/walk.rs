@@ -0,0 +1,348 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use super::gitignore::GitIgnore;
+
+/// Build the ignore ruleset for a local directory, as `git status` would
+/// apply it, lowest precedence first: the user's global excludes
+/// (`core.excludesFile`), the enclosing repository's `.git/info/exclude`,
+/// the directory's `.gitignore`, then any extra exclusion patterns.
+#[must_use]
+pub fn load_gitignore(dir: &Path, exclude: Option<&[String]>) -> GitIgnore {
+    load_gitignore_for(dir, exclude, GitUserConfig::from_env().as_ref())
+}
+
+/// [`load_gitignore`] with the user's git configuration in `user`, or
+/// without global excludes when `None`.
+#[must_use]
+pub fn load_gitignore_for(
+    dir: &Path,
+    exclude: Option<&[String]>,
+    user: Option<&GitUserConfig>,
+) -> GitIgnore {
+    let mut gitignore = GitIgnore::default();
+    let repo = GitRepo::find(dir);
+    let base = repo.as_ref().map_or("", |repo| repo.prefix.as_str());
+    if let Some(user) = user {
+        let excludes = user.excludes_file(repo.as_ref());
+        gitignore.extend_from_file(&excludes, base);
+    }
+    if let Some(repo) = &repo {
+        gitignore.extend_from_file(&repo.common_dir.join("info").join("exclude"), base);
+    }
+    gitignore.extend_from_file(&dir.join(".gitignore"), "");
+    if let Some(patterns) = exclude {
+        gitignore.extend_patterns(patterns);
+    }
+    gitignore
+}
+
+/// Where the user's git configuration lives.
+#[derive(Debug, Clone)]
+pub struct GitUserConfig {
+    pub home: PathBuf,
+    /// `$XDG_CONFIG_HOME`, or `~/.config`.
+    pub config_home: PathBuf,
+}
+
+impl GitUserConfig {
+    /// From `HOME` and `XDG_CONFIG_HOME`, or `None` without a home directory.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        let home = dirs::home_dir()?;
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .unwrap_or_else(|| home.join(".config"));
+        Some(Self { home, config_home })
+    }
+
+    /// The global excludes file: `core.excludesFile` from the user's or
+    /// `repo`'s config (the last one set wins, as in git), or git's default
+    /// `<config home>/git/ignore`.
+    #[must_use]
+    pub fn excludes_file(&self, repo: Option<&GitRepo>) -> PathBuf {
+        let mut configs = vec![
+            self.config_home.join("git").join("config"),
+            self.home.join(".gitconfig"),
+        ];
+        if let Some(repo) = repo {
+            configs.push(repo.common_dir.join("config"));
+        }
+        configs
+            .iter()
+            .rev()
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .find_map(|content| config_excludes_file(&content))
+            .map_or_else(
+                || self.config_home.join("git").join("ignore"),
+                |path| match path.strip_prefix("~/") {
+                    Some(rest) => self.home.join(rest),
+                    None => PathBuf::from(path),
+                },
+            )
+    }
+}
+
+/// The git repository a directory is in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitRepo {
+    /// The repository's shared git directory, which holds `info/exclude`
+    /// and `config` (the main `.git` for linked worktrees).
+    pub common_dir: PathBuf,
+    /// Path of the directory from the top of the work tree, `/`-separated;
+    /// empty at the top.
+    pub prefix: String,
+}
+
+impl GitRepo {
+    /// The repository `dir` or one of its parents holds, found by its
+    /// `.git` directory or `.git` file (`gitdir: <path>`, in worktrees and
+    /// submodules).
+    #[must_use]
+    pub fn find(dir: &Path) -> Option<Self> {
+        let dir = dir.canonicalize().ok()?;
+        for top in dir.ancestors() {
+            let dot_git = top.join(".git");
+            let git_dir = if dot_git.is_dir() {
+                dot_git
+            } else if let Ok(content) = std::fs::read_to_string(&dot_git) {
+                top.join(content.strip_prefix("gitdir:")?.trim())
+            } else {
+                continue;
+            };
+            let common_dir = std::fs::read_to_string(git_dir.join("commondir"))
+                .map_or_else(|_| git_dir.clone(), |common| git_dir.join(common.trim()));
+            let prefix = dir
+                .strip_prefix(top)
+                .ok()?
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            return Some(Self { common_dir, prefix });
+        }
+        None
+    }
+}
+
+/// `core.excludesFile` from the git config file `content`, if set.
+/// Handles the plain `[core]` section and quoted values; `include`
+/// directives are not followed.
+#[must_use]
+pub fn config_excludes_file(content: &str) -> Option<String> {
+    let mut in_core = false;
+    let mut value = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(section) = line.strip_prefix('[') {
+            let name = section.split(']').next().unwrap_or_default().trim();
+            in_core = name.eq_ignore_ascii_case("core");
+            continue;
+        }
+        if !in_core {
+            continue;
+        }
+        let Some((key, raw)) = line.split_once('=') else {
+            continue;
+        };
+        if key.trim().eq_ignore_ascii_case("excludesfile") {
+            value = Some(config_value(raw));
+        }
+    }
+    value.filter(|v| !v.is_empty())
+}
+
+/// A git config value: quotes removed, and everything after an unquoted
+/// `#` or `;` dropped as a comment.
+fn config_value(raw: &str) -> String {
+    let mut value = String::new();
+    let mut quoted = false;
+    let mut chars = raw.trim().chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' => value.extend(chars.next()),
+            '#' | ';' if !quoted => break,
+            _ => value.push(c),
+        }
+    }
+    value.trim_end().to_string()
+}
+
+/// Most threads a directory walk uses; beyond this, reading directories
+/// is bound by the disk rather than by the walk.
+const MAX_WALK_THREADS: usize = 8;
+
+/// A file found by [`walk_dir_with_sizes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkedFile {
+    /// Path relative to the walked directory.
+    pub path: String,
+    pub size: u64,
+}
+
+/// Recursively collect files under `dir`, respecting the rules from [`load_gitignore`].
+/// Skips symlinks, `.git/`, and gitignored entries. Returned paths are relative to `dir`
+/// and sorted.
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be read.
+pub fn walk_dir(dir: &Path, gitignore: &GitIgnore) -> anyhow::Result<Vec<String>> {
+    let files = Walker::new(dir, gitignore, false).run()?;
+    Ok(files.into_iter().map(|file| file.path).collect())
+}
+
+/// [`walk_dir`], with each file's size.
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be read or a file's metadata
+/// cannot be read.
+pub fn walk_dir_with_sizes(dir: &Path, gitignore: &GitIgnore) -> anyhow::Result<Vec<WalkedFile>> {
+    Walker::new(dir, gitignore, true).run()
+}
+
+/// A parallel directory walk. Each thread reads directories from the back
+/// of its own queue and queues the subdirectories it finds there; an idle
+/// thread steals from the front of another's queue, which holds the
+/// shallowest, and so largest, subtrees. Ignore rules only depend on the
+/// path, so the order directories are read in doesn't change the result.
+struct Walker<'a> {
+    base: &'a Path,
+    gitignore: &'a GitIgnore,
+    sizes: bool,
+    queues: Vec<Mutex<VecDeque<PathBuf>>>,
+    /// Directories queued or being read; the walk is done at zero.
+    pending: AtomicUsize,
+    error: Mutex<Option<anyhow::Error>>,
+    failed: AtomicBool,
+}
+
+impl<'a> Walker<'a> {
+    fn new(base: &'a Path, gitignore: &'a GitIgnore, sizes: bool) -> Self {
+        let threads = std::thread::available_parallelism()
+            .map_or(1, std::num::NonZeroUsize::get)
+            .min(MAX_WALK_THREADS);
+        let queues: Vec<Mutex<VecDeque<PathBuf>>> =
+            (0..threads).map(|_| Mutex::default()).collect();
+        lock(&queues[0]).push_back(base.to_path_buf());
+        Self {
+            base,
+            gitignore,
+            sizes,
+            queues,
+            pending: AtomicUsize::new(1),
+            error: Mutex::new(None),
+            failed: AtomicBool::new(false),
+        }
+    }
+
+    fn run(self) -> anyhow::Result<Vec<WalkedFile>> {
+        let mut files: Vec<WalkedFile> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.queues.len())
+                .map(|index| {
+                    let walker = &self;
+                    scope.spawn(move || walker.work(index))
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
+        });
+        if let Some(e) = lock(&self.error).take() {
+            return Err(e);
+        }
+        files.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+
+    fn work(&self, index: usize) -> Vec<WalkedFile> {
+        let mut files = Vec::new();
+        while self.pending.load(Ordering::Acquire) > 0 && !self.failed.load(Ordering::Acquire) {
+            let Some(dir) = self.next(index) else {
+                std::thread::yield_now();
+                continue;
+            };
+            if let Err(e) = self.read(index, &dir, &mut files) {
+                lock(&self.error).get_or_insert(e);
+                self.failed.store(true, Ordering::Release);
+            }
+            self.pending.fetch_sub(1, Ordering::AcqRel);
+        }
+        files
+    }
+
+    /// The next directory for thread `index`: its own newest, or the oldest
+    /// of the first other thread with work.
+    fn next(&self, index: usize) -> Option<PathBuf> {
+        if let Some(dir) = lock(&self.queues[index]).pop_back() {
+            return Some(dir);
+        }
+        let count = self.queues.len();
+        (1..count).find_map(|offset| lock(&self.queues[(index + offset) % count]).pop_front())
+    }
+
+    fn read(&self, index: usize, dir: &Path, files: &mut Vec<WalkedFile>) -> anyhow::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+
+            // Skip symlinks — file_type() uses lstat, doesn't follow
+            if file_type.is_symlink() {
+                continue;
+            }
+
+            let path = entry.path();
+            let relative = path
+                .strip_prefix(self.base)
+                .map_err(|e| anyhow::anyhow!("Path prefix error: {e}"))?
+                .to_string_lossy()
+                .to_string();
+
+            if file_type.is_dir() {
+                // Always skip .git
+                if entry.file_name().to_str() == Some(".git") {
+                    continue;
+                }
+
+                // Check gitignore for this directory — skips the entire subtree
+                if self.gitignore.is_ignored(&relative, true) {
+                    continue;
+                }
+
+                self.pending.fetch_add(1, Ordering::AcqRel);
+                lock(&self.queues[index]).push_back(path);
+            } else if file_type.is_file() {
+                if self.gitignore.is_ignored(&relative, false) {
+                    continue;
+                }
+
+                let size = if self.sizes {
+                    entry.metadata()?.len()
+                } else {
+                    0
+                };
+                files.push(WalkedFile {
+                    path: relative,
+                    size,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lock a walk mutex. A thread that panicked holding one is re-raised by
+/// [`Walker::run`], so a poisoned lock's data is still usable until then.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,222 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use ssh_hub::connection::{atomic_extract_command, atomic_write_command, TMP_SUFFIX};
+
+fn run(script: &str, stdin: &[u8]) -> Output {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The script may exit before reading stdin, e.g. for a directory
+    if let Err(e) = child.stdin.take().unwrap().write_all(stdin) {
+        assert_eq!(e.kind(), std::io::ErrorKind::BrokenPipe);
+    }
+    child.wait_with_output().unwrap()
+}
+
+fn tar_gz(files: &[(&str, &str)]) -> Vec<u8> {
+    tar_gz_with_mode(files, 0o644)
+}
+
+fn tar_gz_with_mode(files: &[(&str, &str)], mode: u32) -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+    for (path, content) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(mode);
+        header.set_mtime(1_000_000_000);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, path, content.as_bytes())
+            .unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+fn leftovers(dir: &Path) -> Vec<String> {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.contains(TMP_SUFFIX))
+        .collect()
+}
+
+#[test]
+fn test_write_creates_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("it's new.txt");
+    let output = run(&atomic_write_command(path.to_str().unwrap()), b"hello\n");
+    assert!(output.status.success());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello\n");
+    assert!(leftovers(dir.path()).is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_write_replaces_file_keeping_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.conf");
+    std::fs::write(&path, "old contents that are longer\n").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+    let output = run(&atomic_write_command(path.to_str().unwrap()), b"new\n");
+    assert!(output.status.success());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "new\n");
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o640);
+    assert!(leftovers(dir.path()).is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_write_follows_symlink() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("real.conf");
+    let link = dir.path().join("link.conf");
+    std::fs::write(&target, "old\n").unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    let output = run(&atomic_write_command(link.to_str().unwrap()), b"new\n");
+    assert!(output.status.success());
+    assert!(std::fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "new\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_write_new_file_follows_umask() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("new.conf");
+    let script = format!(
+        "umask 027; {}",
+        atomic_write_command(path.to_str().unwrap())
+    );
+    assert!(run(&script, b"x\n").status.success());
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o640);
+}
+
+#[test]
+fn test_concurrent_writes_use_separate_temp_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shared.txt");
+    let command = atomic_write_command(path.to_str().unwrap());
+    let contents: Vec<Vec<u8>> = (0..8u8).map(|n| vec![b'a' + n; 256 * 1024]).collect();
+    std::thread::scope(|scope| {
+        for content in &contents {
+            let command = &command;
+            scope.spawn(move || assert!(run(command, content).status.success()));
+        }
+    });
+    // The file is one writer's content in full, never a mix
+    let written = std::fs::read(&path).unwrap();
+    assert!(contents.contains(&written));
+    assert!(leftovers(dir.path()).is_empty());
+}
+
+#[test]
+fn test_write_refuses_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = run(&atomic_write_command(dir.path().to_str().unwrap()), b"x");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Is a directory"));
+    assert!(leftovers(dir.path().parent().unwrap()).is_empty());
+}
+
+#[test]
+fn test_extract_moves_files_into_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("app");
+    std::fs::create_dir_all(dest.join("src")).unwrap();
+    std::fs::write(dest.join("src/main.rs"), "old\n").unwrap();
+    std::fs::write(dest.join("keep.txt"), "untouched\n").unwrap();
+
+    let archive = tar_gz(&[("./src/main.rs", "new\n"), ("./docs/a b.md", "doc\n")]);
+    let output = run(
+        &atomic_extract_command(dest.to_str().unwrap(), true),
+        &archive,
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        std::fs::read_to_string(dest.join("src/main.rs")).unwrap(),
+        "new\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dest.join("docs/a b.md")).unwrap(),
+        "doc\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dest.join("keep.txt")).unwrap(),
+        "untouched\n"
+    );
+    assert!(leftovers(&dest).is_empty());
+}
+
+#[test]
+fn test_extract_truncated_archive_changes_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("app");
+    std::fs::create_dir_all(&dest).unwrap();
+    std::fs::write(dest.join("main.rs"), "old\n").unwrap();
+
+    let archive = tar_gz(&[("./main.rs", &"new\n".repeat(10_000))]);
+    let output = run(
+        &atomic_extract_command(dest.to_str().unwrap(), true),
+        &archive[..archive.len() / 2],
+    );
+    assert!(!output.status.success());
+    assert_eq!(
+        std::fs::read_to_string(dest.join("main.rs")).unwrap(),
+        "old\n"
+    );
+    assert!(leftovers(&dest).is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_preserve_keeps_mode_and_mtime() {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let dir = tempfile::tempdir().unwrap();
+    let archive = tar_gz_with_mode(&[("./run.sh", "#!/bin/sh\n")], 0o755);
+
+    // A umask that would strip group and other bits from extracted files
+    let dest = dir.path().join("kept");
+    let script = format!(
+        "umask 077; {}",
+        atomic_extract_command(dest.to_str().unwrap(), true)
+    );
+    assert!(run(&script, &archive).status.success());
+    let meta = std::fs::metadata(dest.join("run.sh")).unwrap();
+    assert_eq!(meta.permissions().mode() & 0o777, 0o755);
+    assert_eq!(meta.mtime(), 1_000_000_000);
+
+    let dest = dir.path().join("fresh");
+    let script = format!(
+        "umask 077; {}",
+        atomic_extract_command(dest.to_str().unwrap(), false)
+    );
+    assert!(run(&script, &archive).status.success());
+    // tar run as root keeps archived modes regardless, so only the time differs
+    let meta = std::fs::metadata(dest.join("run.sh")).unwrap();
+    assert_ne!(meta.mtime(), 1_000_000_000);
+}
@@ -0,0 +1,123 @@
+use std::fs;
+
+use russh::keys::ssh_key::rand_core::OsRng;
+use russh::keys::ssh_key::LineEnding;
+use russh::keys::{Algorithm, PrivateKey};
+use ssh_hub::connection::{
+    agent_location, decrypt_key, is_encrypted_key_error, is_security_key, security_key_public,
+    AgentLocation, EncryptedKey, WINDOWS_AGENT_PIPE,
+};
+use ssh_hub::tools::ErrorCode;
+
+const SK_ED25519_PUB: &str = "sk-ssh-ed25519@openssh.com AAAAGnNrLXNzaC1lZDI1NTE5QG9wZW5zc2guY29tAAAAIAABAgMEBQYHCAkKCwwNDg8QERITFBUWFxgZGhscHR4fAAAABHNzaDo= yubikey";
+
+#[test]
+fn test_is_security_key() {
+    assert!(is_security_key(&Algorithm::SkEd25519));
+    assert!(is_security_key(&Algorithm::SkEcdsaSha2NistP256));
+    assert!(!is_security_key(&Algorithm::Ed25519));
+    assert!(!is_security_key(&Algorithm::Rsa { hash: None }));
+}
+
+#[test]
+fn test_security_key_public_reads_pub_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let key = dir.path().join("id_ed25519_sk");
+    // The private half is a handle for the authenticator; only the .pub is read
+    fs::write(&key, "not a key").unwrap();
+    fs::write(dir.path().join("id_ed25519_sk.pub"), SK_ED25519_PUB).unwrap();
+
+    let public = security_key_public(&key).expect("sk key detected");
+    assert_eq!(public.algorithm(), Algorithm::SkEd25519);
+}
+
+#[test]
+fn test_security_key_public_ignores_other_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let key = dir.path().join("id_ed25519");
+    PrivateKey::random(&mut OsRng, Algorithm::Ed25519)
+        .unwrap()
+        .write_openssh_file(&key, LineEnding::LF)
+        .unwrap();
+
+    assert!(security_key_public(&key).is_none());
+    assert!(security_key_public(&dir.path().join("missing")).is_none());
+}
+
+#[test]
+fn test_agent_location_prefers_configured_socket() {
+    assert_eq!(
+        agent_location(Some("/run/agent.sock"), Some("/tmp/ssh-X/agent.1")),
+        Some(AgentLocation::Socket("/run/agent.sock".to_string()))
+    );
+    assert_eq!(
+        agent_location(None, Some("/tmp/ssh-X/agent.1")),
+        Some(AgentLocation::Socket("/tmp/ssh-X/agent.1".to_string()))
+    );
+    // An empty setting falls through, like an empty SSH_AUTH_SOCK
+    assert_eq!(
+        agent_location(Some(""), Some("/tmp/ssh-X/agent.1")),
+        Some(AgentLocation::Socket("/tmp/ssh-X/agent.1".to_string()))
+    );
+}
+
+#[test]
+fn test_agent_location_windows_agents() {
+    assert_eq!(
+        agent_location(Some("Pageant"), None),
+        Some(AgentLocation::Pageant)
+    );
+    assert_eq!(
+        agent_location(Some(r"\\.\pipe\my-agent"), None),
+        Some(AgentLocation::NamedPipe(r"\\.\pipe\my-agent".to_string()))
+    );
+    assert_eq!(
+        agent_location(Some("//./pipe/my-agent"), None),
+        Some(AgentLocation::NamedPipe("//./pipe/my-agent".to_string()))
+    );
+}
+
+#[test]
+fn test_agent_location_default() {
+    let expected = cfg!(windows).then(|| AgentLocation::NamedPipe(WINDOWS_AGENT_PIPE.to_string()));
+    assert_eq!(agent_location(None, None), expected);
+}
+
+#[tokio::test]
+async fn test_encrypted_keys_need_their_passphrase() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("id_ed25519");
+    let key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+    key.encrypt(&mut OsRng, "correct horse")
+        .unwrap()
+        .write_openssh_file(&path, LineEnding::LF)
+        .unwrap();
+
+    let err = decrypt_key(&path, None).await.unwrap_err();
+    assert!(is_encrypted_key_error(&err), "{err:#}");
+
+    let wrong = decrypt_key(&path, Some("battery staple".to_string()))
+        .await
+        .unwrap_err();
+    assert!(!is_encrypted_key_error(&wrong), "{wrong:#}");
+
+    let decrypted = decrypt_key(&path, Some("correct horse".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(decrypted.public_key(), key.public_key());
+}
+
+#[test]
+fn test_encrypted_key_error_is_its_own_code() {
+    let error = anyhow::Error::new(EncryptedKey {
+        path: "/home/me/.ssh/id_ed25519".into(),
+    })
+    .context("Failed to connect to SSH server");
+    assert!(
+        format!("{error:#}").contains("ssh-add /home/me/.ssh/id_ed25519"),
+        "{error:#}"
+    );
+    let code = ErrorCode::of(&error);
+    assert_eq!(code, ErrorCode::KeyEncrypted);
+    assert!(!code.retriable());
+}
@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use serde_json::json;
+use ssh_hub::tools::benchmark_server::handler::{limits, payload, MAX_PAYLOAD_KB, MAX_ROUNDS};
+use ssh_hub::tools::benchmark_server::{BenchmarkServerInput, Throughput, Timing};
+
+fn input(value: serde_json::Value) -> BenchmarkServerInput {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn test_limits_default_and_clamp() {
+    assert_eq!(limits(&input(json!({"server": "s"}))), (5, 1024 * 1024));
+    assert_eq!(
+        limits(&input(
+            json!({"server": "s", "rounds": 500, "payload_kb": 1_000_000})
+        )),
+        (MAX_ROUNDS, MAX_PAYLOAD_KB * 1024)
+    );
+    assert_eq!(
+        limits(&input(json!({"server": "s", "rounds": 0, "payload_kb": 0}))),
+        (1, 1024)
+    );
+}
+
+#[test]
+fn test_timing_summary() {
+    let ms = |v: &[u64]| {
+        v.iter()
+            .map(|&m| Duration::from_millis(m))
+            .collect::<Vec<_>>()
+    };
+    let odd = Timing::of(&ms(&[30, 10, 20]));
+    assert_eq!(
+        odd,
+        Timing {
+            min: 10.0,
+            median: 20.0,
+            max: 30.0
+        }
+    );
+    assert!((Timing::of(&ms(&[10, 40, 20, 30])).median - 25.0).abs() < f64::EPSILON);
+    assert!((Timing::of(&[]).max).abs() < f64::EPSILON);
+}
+
+#[test]
+fn test_throughput_in_mib_per_second() {
+    let t = Throughput::of(4 * 1024 * 1024, Duration::from_millis(500));
+    assert!((t.mib_per_sec - 8.0).abs() < f64::EPSILON);
+    assert!((t.elapsed_ms - 500.0).abs() < f64::EPSILON);
+    assert!((Throughput::of(1024, Duration::ZERO).mib_per_sec).abs() < f64::EPSILON);
+}
+
+#[test]
+fn test_payload_has_the_size_and_does_not_compress() {
+    use std::io::Write;
+
+    let data = payload(100_003);
+    assert_eq!(data.len(), 100_003);
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&data).unwrap();
+    let compressed = encoder.finish().unwrap();
+    assert!(
+        compressed.len() > data.len() * 9 / 10,
+        "{}",
+        compressed.len()
+    );
+}
@@ -0,0 +1,116 @@
+#![cfg(unix)]
+
+use std::path::Path;
+
+use ssh_hub::broker::{call_tool, claim, socket_name, Role};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+#[tokio::test]
+async fn test_first_claim_owns_later_claims_connect() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("ssh-hub").join("broker.sock");
+
+    let Role::Owner(listener) = claim(&path).await.unwrap() else {
+        panic!("first claim should own the socket");
+    };
+    let Role::Client(mut client) = claim(&path).await.unwrap() else {
+        panic!("second claim should connect to the owner");
+    };
+
+    let (mut accepted, _) = listener.accept().await.unwrap();
+    client.write_all(b"ping").await.unwrap();
+    let mut buf = [0u8; 4];
+    accepted.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"ping");
+}
+
+#[tokio::test]
+async fn test_stale_socket_is_replaced() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("broker.sock");
+
+    let Role::Owner(listener) = claim(&path).await.unwrap() else {
+        panic!("first claim should own the socket");
+    };
+    drop(listener);
+    assert!(path.exists(), "socket file outlives its listener");
+
+    assert!(matches!(claim(&path).await.unwrap(), Role::Owner(_)));
+}
+
+#[tokio::test]
+async fn test_call_tool_handshakes_and_returns_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("broker.sock");
+    let Role::Owner(listener) = claim(&path).await.unwrap() else {
+        panic!("first claim should own the socket");
+    };
+
+    let owner = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        let mut methods = Vec::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            let message: serde_json::Value = serde_json::from_str(&line).unwrap();
+            methods.push(message["method"].as_str().unwrap().to_string());
+            let reply = match message["method"].as_str() {
+                Some("initialize") => serde_json::json!({
+                    "jsonrpc": "2.0", "id": message["id"], "result": {}
+                }),
+                Some("tools/call") => serde_json::json!({
+                    "jsonrpc": "2.0", "id": message["id"],
+                    "result": { "content": [{ "type": "text", "text": message["params"]["name"] }] }
+                }),
+                _ => continue,
+            };
+            // A notification in between must be skipped by the client.
+            let note = r#"{"jsonrpc":"2.0","method":"notifications/message","params":{}}"#;
+            write
+                .write_all(format!("{note}\n{reply}\n").as_bytes())
+                .await
+                .unwrap();
+        }
+        methods
+    });
+
+    let text = call_tool(&path, "pool_stats", serde_json::json!({}))
+        .await
+        .unwrap();
+    assert_eq!(text, "pool_stats");
+    assert_eq!(
+        owner.await.unwrap(),
+        ["initialize", "notifications/initialized", "tools/call"]
+    );
+}
+
+#[tokio::test]
+async fn test_call_tool_without_broker_is_not_found() {
+    let dir = tempfile::tempdir().unwrap();
+    let err = call_tool(
+        &dir.path().join("broker.sock"),
+        "pool_stats",
+        serde_json::json!({}),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_socket_name_depends_on_config() {
+    let default = Path::new("/home/me/.config/ssh-hub/servers.toml");
+    assert_eq!(socket_name(Some(default), Some(default)), "broker.sock");
+    assert_eq!(socket_name(None, Some(default)), "broker.sock");
+
+    let ci = socket_name(Some(Path::new("/tmp/ci.toml")), Some(default));
+    let staging = socket_name(Some(Path::new("/tmp/staging.toml")), Some(default));
+    let hash = ci
+        .strip_prefix("broker-")
+        .unwrap()
+        .strip_suffix(".sock")
+        .unwrap();
+    assert_eq!(hash.len(), 16, "{ci}");
+    assert_ne!(ci, staging);
+    assert_eq!(ci, socket_name(Some(Path::new("/tmp/ci.toml")), None));
+}
@@ -0,0 +1,123 @@
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use ssh_hub::budget::{BudgetUsage, Limits, ToolFamily};
+use ssh_hub::server_registry::ServerRegistry;
+use ssh_hub::tools::ErrorCode;
+
+const LIMITS: Limits = Limits {
+    per_call: 100,
+    per_minute: 250,
+};
+
+fn lines(count: usize) -> String {
+    (0..count).fold(String::new(), |mut out, i| {
+        let _ = writeln!(out, "line {i:04}");
+        out
+    })
+}
+
+fn notice(output: &str) -> serde_json::Value {
+    let json = output.lines().last().unwrap();
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn test_limits_from_config() {
+    let cfg: ServerRegistry = toml::from_str(
+        r"
+        [budget.read]
+        per_call = 1000
+
+        [budget.bash]
+        per_call = 0
+        per_minute = 0
+        ",
+    )
+    .unwrap();
+    let read = Limits::for_family(ToolFamily::Read, cfg.budget.as_ref());
+    assert_eq!(read.per_call, 1000);
+    assert_eq!(
+        read.per_minute,
+        Limits::for_family(ToolFamily::Read, None).per_minute
+    );
+    let bash = Limits::for_family(ToolFamily::Bash, cfg.budget.as_ref());
+    assert_eq!((bash.per_call, bash.per_minute), (0, 0));
+    assert_eq!(
+        Limits::for_family(ToolFamily::Search, cfg.budget.as_ref()),
+        Limits::for_family(ToolFamily::Search, None)
+    );
+}
+
+#[test]
+fn test_small_output_passes_through() {
+    let mut usage = BudgetUsage::new();
+    let now = Instant::now();
+    let output = usage.charge(ToolFamily::Read, LIMITS, lines(3), now);
+    assert_eq!(output, lines(3));
+    assert_eq!(usage.spent(ToolFamily::Read, now), 30);
+    assert_eq!(usage.spent(ToolFamily::Bash, now), 0);
+}
+
+#[test]
+fn test_per_call_truncates_at_line_boundary() {
+    let mut usage = BudgetUsage::new();
+    let output = usage.charge(ToolFamily::Read, LIMITS, lines(20), Instant::now());
+    assert!(output.starts_with(&lines(10)));
+    assert!(!output.contains("line 0010"));
+    assert!(output.contains("refine your query"));
+    let notice = notice(&output);
+    assert_eq!(notice["truncated"], "budget");
+    assert_eq!(notice["family"], "read");
+    assert_eq!(notice["limit"], "per_call");
+    assert_eq!(notice["returned_bytes"], 100);
+    assert_eq!(notice["total_bytes"], 200);
+}
+
+#[test]
+fn test_per_minute_budget_runs_out_and_recovers() {
+    let mut usage = BudgetUsage::new();
+    let start = Instant::now();
+    usage.charge(ToolFamily::Bash, LIMITS, lines(10), start);
+    usage.charge(ToolFamily::Bash, LIMITS, lines(10), start);
+
+    // 50 bytes left this minute.
+    let output = usage.charge(ToolFamily::Bash, LIMITS, lines(10), start);
+    assert_eq!(notice(&output)["limit"], "per_minute");
+    assert_eq!(notice(&output)["returned_bytes"], 50);
+
+    let err = usage.check(ToolFamily::Bash, LIMITS, start).unwrap_err();
+    assert_eq!(err.code, ErrorCode::BudgetExceeded);
+    assert!(err.message.starts_with("the bash budget"));
+    assert!(err.message.contains("Retry in 60s"));
+    assert!(usage.check(ToolFamily::Read, LIMITS, start).is_ok());
+
+    let later = start + Duration::from_secs(61);
+    assert!(usage.check(ToolFamily::Bash, LIMITS, later).is_ok());
+    assert_eq!(usage.spent(ToolFamily::Bash, later), 0);
+}
+
+#[test]
+fn test_zero_disables_limits() {
+    let mut usage = BudgetUsage::new();
+    let unlimited = Limits {
+        per_call: 0,
+        per_minute: 0,
+    };
+    let now = Instant::now();
+    for _ in 0..10 {
+        assert_eq!(
+            usage.charge(ToolFamily::Search, unlimited, lines(100), now),
+            lines(100)
+        );
+    }
+    assert!(usage.check(ToolFamily::Search, unlimited, now).is_ok());
+}
+
+#[test]
+fn test_truncation_respects_char_boundaries() {
+    let mut usage = BudgetUsage::new();
+    let output = usage.charge(ToolFamily::Read, LIMITS, "é".repeat(80), Instant::now());
+    assert!(output.starts_with(&"é".repeat(50)));
+    assert_eq!(notice(&output)["returned_bytes"], 100);
+}
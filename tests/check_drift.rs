@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+
+use ssh_hub::server_registry::ServerRegistry;
+use ssh_hub::tools::check_drift::handler::{plan_checks, summarize, DriftCheck};
+use ssh_hub::tools::provision::history::{AppliedRecipe, ProvisionHistory};
+use ssh_hub::tools::provision::{ProvisionReport, StepKind, StepReport, StepStatus};
+
+const CONFIG: &str = r#"
+[servers.web1]
+host = "web1.example.com"
+user = "deploy"
+
+[recipes.nginx]
+packages = ["nginx"]
+services = ["nginx"]
+servers = ["web1"]
+
+[[recipes.nginx.commands]]
+run = "touch /var/lib/app/.configured"
+unless = "test -f /var/lib/app/.configured"
+
+[[recipes.nginx.commands]]
+run = "systemctl reload nginx"
+
+[recipes.tools]
+packages = ["htop"]
+servers = ["web2"]
+"#;
+
+fn config() -> ServerRegistry {
+    toml::from_str(CONFIG).unwrap()
+}
+
+fn applied(names: &[&str]) -> BTreeMap<String, AppliedRecipe> {
+    names
+        .iter()
+        .map(|n| {
+            (
+                n.to_string(),
+                AppliedRecipe {
+                    applied_at: 1_700_000_000,
+                },
+            )
+        })
+        .collect()
+}
+
+fn step(kind: StepKind, target: &str, status: StepStatus) -> StepReport {
+    StepReport {
+        kind,
+        target: target.to_string(),
+        status,
+        detail: None,
+    }
+}
+
+#[test]
+fn test_history_record_and_roundtrip() {
+    let mut history = ProvisionHistory::default();
+    history.record("web1", "nginx", 100);
+    history.record("web1", "nginx", 200);
+    history.record("web1", "tools", 150);
+
+    let web1 = history.applied("web1");
+    assert_eq!(web1.len(), 2);
+    assert_eq!(web1["nginx"].applied_at, 200);
+    assert!(history.applied("db1").is_empty());
+
+    let json = serde_json::to_string(&history).unwrap();
+    let back: ProvisionHistory = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, history);
+}
+
+#[test]
+fn test_plan_uses_applied_recipes() {
+    let plan = plan_checks(&config(), &applied(&["nginx", "gone"]), "web1", None).unwrap();
+    assert_eq!(plan.checks.len(), 1);
+    assert_eq!(plan.checks[0].name, "nginx");
+    assert_eq!(plan.checks[0].applied_at, Some(1_700_000_000));
+    assert_eq!(plan.missing, ["gone"]);
+}
+
+#[test]
+fn test_plan_requires_history_or_recipe() {
+    let err = plan_checks(&config(), &BTreeMap::new(), "web1", None).unwrap_err();
+    assert!(err.message.contains("no recipes have been applied"));
+
+    let plan = plan_checks(&config(), &BTreeMap::new(), "web1", Some("nginx")).unwrap();
+    assert_eq!(plan.checks.len(), 1);
+    assert_eq!(plan.checks[0].applied_at, None);
+}
+
+#[test]
+fn test_plan_rejects_restricted_recipe() {
+    let err = plan_checks(&config(), &applied(&["tools"]), "web1", None).unwrap_err();
+    assert!(err.message.contains("not enabled"));
+    assert!(plan_checks(&config(), &BTreeMap::new(), "web1", Some("missing")).is_err());
+}
+
+#[test]
+fn test_summarize_classifies_steps() {
+    let check = DriftCheck {
+        name: "nginx".to_string(),
+        recipe: config().recipes["nginx"].clone(),
+        applied_at: None,
+    };
+    let report = ProvisionReport {
+        recipe: "nginx".to_string(),
+        server: "web1".to_string(),
+        dry_run: true,
+        steps: vec![
+            step(StepKind::Package, "nginx", StepStatus::Ok),
+            step(
+                StepKind::Command,
+                "touch /var/lib/app/.configured",
+                StepStatus::WouldChange,
+            ),
+            step(
+                StepKind::Command,
+                "systemctl reload nginx",
+                StepStatus::WouldChange,
+            ),
+            step(StepKind::Service, "nginx", StepStatus::Failed),
+        ],
+        changed: 2,
+        failed: true,
+    };
+
+    let drift = summarize(&check, report);
+    assert_eq!(drift.in_sync, 1);
+    assert_eq!(drift.drifted.len(), 1);
+    assert_eq!(drift.drifted[0].target, "touch /var/lib/app/.configured");
+    assert_eq!(drift.unchecked, ["systemctl reload nginx"]);
+    assert_eq!(drift.errors.len(), 1);
+    assert_eq!(drift.errors[0].kind, StepKind::Service);
+}
@@ -0,0 +1,110 @@
+use std::process::Command;
+
+use ssh_hub::utils::command::CommandBuilder;
+
+/// Run `command` with `sh -c`, returning stdout.
+fn sh(command: &str, home: &str) -> String {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("HOME", home)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{command}: {output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_words_args_and_paths() {
+    assert_eq!(
+        CommandBuilder::new("systemctl")
+            .word("restart")
+            .arg("nginx")
+            .build(),
+        "systemctl restart 'nginx'"
+    );
+    assert_eq!(
+        CommandBuilder::new("rm")
+            .words(["-rf", "--"])
+            .paths(["~", "~/a b", "/tmp/x"])
+            .build(),
+        "rm -rf -- $HOME $HOME/'a b' '/tmp/x'"
+    );
+    // Words with shell syntax are quoted rather than trusted
+    assert_eq!(
+        CommandBuilder::new("FOO=1").word("$(id)").word("").build(),
+        "'FOO=1' '$(id)' ''"
+    );
+}
+
+#[test]
+fn test_env_cwd_and_redirections() {
+    let command = CommandBuilder::new("df")
+        .env("LC_ALL", "C")
+        .words(["-P", "-k"])
+        .cwd("~/app")
+        .stdin_from("/dev/null")
+        .stdout_append("log file")
+        .stderr_to_stdout();
+    assert_eq!(
+        command.to_string(),
+        "cd $HOME/'app' && LC_ALL='C' df -P -k <'/dev/null' >>'log file' 2>&1"
+    );
+}
+
+#[test]
+#[should_panic(expected = "invalid environment variable name")]
+fn test_env_rejects_bad_names() {
+    let _ = CommandBuilder::new("env").env("A;rm", "x");
+}
+
+#[test]
+fn test_hostile_values_reach_the_program_intact() {
+    let dir = tempfile::tempdir().unwrap();
+    let home = dir.path().to_str().unwrap();
+    let nasty = [
+        "$HOME",
+        "it's",
+        "a \"quoted\" `cmd` $(id) \\ path",
+        "*",
+        "",
+        "line\nbreak",
+    ];
+    let output = sh(
+        &CommandBuilder::new("printf")
+            .arg("<%s>\n")
+            .args(nasty)
+            .env("X", "$(id)'")
+            .build(),
+        home,
+    );
+    let expected = nasty
+        .iter()
+        .map(|v| format!("<{v}>\n"))
+        .collect::<Vec<_>>()
+        .concat();
+    assert_eq!(output, expected);
+
+    // Paths with `$` and quotes, relative to home and as redirect targets
+    let name = "$dir 'q'";
+    std::fs::create_dir(dir.path().join(name)).unwrap();
+    let file = format!("~/{name}/out $x");
+    sh(
+        &CommandBuilder::new("echo")
+            .arg("hello")
+            .cwd(format!("~/{name}"))
+            .stdout_to(&file)
+            .build(),
+        home,
+    );
+    let written = dir.path().join(name).join("out $x");
+    assert_eq!(std::fs::read_to_string(&written).unwrap(), "hello\n");
+    let output = sh(
+        &CommandBuilder::new("cat")
+            .path(&file)
+            .stderr_to("/dev/null")
+            .build(),
+        home,
+    );
+    assert_eq!(output, "hello\n");
+}
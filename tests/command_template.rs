@@ -0,0 +1,189 @@
+use serde_json::{json, Map, Value};
+use ssh_hub::server_registry::ServerRegistry;
+use ssh_hub::timeout::TimeoutPolicy;
+use ssh_hub::tools::command_template::{is_valid_tool_name, CommandTemplate};
+
+const CONFIG: &str = r#"
+[servers.prod]
+host = "prod.example.com"
+user = "deploy"
+
+[templates.restart_service]
+description = "Restart a systemd service"
+command = "sudo systemctl restart {name}"
+servers = ["prod"]
+
+[templates.restart_service.params.name]
+type = "string"
+description = "Unit name"
+pattern = "[a-z0-9@._-]+"
+
+[templates.tail_log]
+description = "Show recent journal lines"
+command = "journalctl -u {unit} -n {lines} --no-pager{{}}"
+timeout = 900000
+
+[templates.tail_log.params.unit]
+enum = ["api", "worker"]
+
+[templates.tail_log.params.lines]
+type = "integer"
+min = 1
+max = 500
+default = 50
+"#;
+
+fn templates() -> ServerRegistry {
+    toml::from_str(CONFIG).unwrap()
+}
+
+fn template(name: &str) -> CommandTemplate {
+    templates().templates.get(name).unwrap().clone()
+}
+
+fn args(value: Value) -> Map<String, Value> {
+    match value {
+        Value::Object(map) => map,
+        other => panic!("expected an object, got {other}"),
+    }
+}
+
+#[test]
+fn test_parse_templates() {
+    let cfg = templates();
+    assert_eq!(cfg.templates.len(), 2);
+    assert_eq!(cfg.servers.len(), 1);
+    for t in cfg.templates.values() {
+        assert!(t.validate().is_ok());
+    }
+}
+
+#[test]
+fn test_render_escapes_arguments() {
+    let t = template("restart_service");
+    let cmd = t
+        .render(&args(json!({"server": "prod", "name": "nginx"})))
+        .unwrap();
+    assert_eq!(cmd, "sudo systemctl restart 'nginx'");
+}
+
+#[test]
+fn test_render_rejects_pattern_mismatch() {
+    let t = template("restart_service");
+    let err = t
+        .render(&args(json!({"server": "prod", "name": "nginx; rm -rf /"})))
+        .unwrap_err();
+    assert!(err.contains("pattern"));
+}
+
+#[test]
+fn test_render_missing_and_unknown_arguments() {
+    let t = template("restart_service");
+    assert!(t
+        .render(&args(json!({"server": "prod"})))
+        .unwrap_err()
+        .contains("missing"));
+    assert!(t
+        .render(&args(json!({"server": "prod", "name": "x", "extra": 1})))
+        .unwrap_err()
+        .contains("unknown"));
+}
+
+#[test]
+fn test_render_defaults_enum_and_bounds() {
+    let t = template("tail_log");
+    assert_eq!(
+        t.render(&args(json!({"server": "prod", "unit": "api"})))
+            .unwrap(),
+        "journalctl -u 'api' -n '50' --no-pager{}"
+    );
+    assert!(t
+        .render(&args(json!({"server": "prod", "unit": "db"})))
+        .is_err());
+    assert!(t
+        .render(&args(
+            json!({"server": "prod", "unit": "api", "lines": 501})
+        ))
+        .is_err());
+    assert!(t
+        .render(&args(
+            json!({"server": "prod", "unit": "api", "lines": "5"})
+        ))
+        .is_err());
+}
+
+#[test]
+fn test_timeout_capped() {
+    let timeouts = TimeoutPolicy::default();
+    assert_eq!(template("tail_log").timeout_ms(timeouts), Ok(Some(600_000)));
+    assert_eq!(
+        template("restart_service").timeout_ms(timeouts),
+        Ok(Some(120_000))
+    );
+}
+
+#[test]
+fn test_allows_server() {
+    assert!(template("restart_service").allows_server("prod"));
+    assert!(!template("restart_service").allows_server("staging"));
+    assert!(template("tail_log").allows_server("anything"));
+}
+
+#[test]
+fn test_validate_rejects_undeclared_placeholder() {
+    let t: CommandTemplate = toml::from_str(
+        r#"
+description = "bad"
+command = "echo {missing}"
+"#,
+    )
+    .unwrap();
+    assert!(t.validate().unwrap_err().contains("missing"));
+}
+
+#[test]
+fn test_validate_rejects_reserved_param() {
+    let t: CommandTemplate = toml::from_str(
+        r#"
+description = "bad"
+command = "echo {override_window}"
+
+[params.override_window]
+"#,
+    )
+    .unwrap();
+    assert!(t.validate().unwrap_err().contains("reserved"));
+}
+
+#[test]
+fn test_render_accepts_override_window() {
+    let rendered = template("restart_service")
+        .render(&args(
+            json!({"server": "prod", "name": "nginx", "override_window": "hotfix"}),
+        ))
+        .unwrap();
+    assert_eq!(rendered, "sudo systemctl restart 'nginx'");
+}
+
+#[test]
+fn test_tool_schema() {
+    let tool = template("tail_log").to_tool("tail_log");
+    assert_eq!(tool.name, "tail_log");
+    let schema = Value::Object((*tool.input_schema).clone());
+    assert_eq!(schema["required"], json!(["server", "unit"]));
+    assert_eq!(schema["properties"]["lines"]["type"], "integer");
+    assert_eq!(schema["properties"]["override_window"]["type"], "string");
+    assert_eq!(
+        schema["properties"]["unit"]["enum"],
+        json!(["api", "worker"])
+    );
+}
+
+#[test]
+fn test_tool_name_validation() {
+    assert!(is_valid_tool_name("restart_service"));
+    assert!(is_valid_tool_name("db-backup2"));
+    assert!(!is_valid_tool_name(""));
+    assert!(!is_valid_tool_name("has space"));
+    assert!(!is_valid_tool_name("dots.not.ok"));
+}
@@ -0,0 +1,101 @@
+use std::path::Path;
+use std::process::Command;
+
+use ssh_hub::cli::{launchd_plist, systemd_unit};
+
+fn args(words: &[&str]) -> Vec<String> {
+    words.iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn test_systemd_unit() {
+    let unit = systemd_unit(
+        Path::new("/home/me/.cargo/bin/ssh-hub"),
+        &args(&["--profile", "work", "daemon", "run"]),
+    );
+    assert!(unit.contains("\nExecStart=/home/me/.cargo/bin/ssh-hub --profile work daemon run\n"));
+    assert!(unit.contains("\nRestart=on-failure\n"));
+    assert!(unit.contains("\n[Install]\nWantedBy=default.target\n"));
+}
+
+#[test]
+fn test_systemd_unit_quotes_and_escapes() {
+    let unit = systemd_unit(
+        Path::new("/opt/my tools/ssh-hub"),
+        &args(&["--config", "/srv/100%/$HOME/\"x\".toml", "daemon", "run"]),
+    );
+    let exec = unit
+        .lines()
+        .find_map(|line| line.strip_prefix("ExecStart="))
+        .unwrap();
+    assert_eq!(
+        exec,
+        r#""/opt/my tools/ssh-hub" --config "/srv/100%%/$$HOME/\"x\".toml" daemon run"#
+    );
+}
+
+#[test]
+fn test_launchd_plist() {
+    let plist = launchd_plist(
+        "com.perceptron-studios.ssh-hub",
+        Path::new("/usr/local/bin/ssh-hub"),
+        &args(&["--config", "/Users/me/a&b.toml", "daemon", "run"]),
+        Path::new("/Users/me/Library/Logs/ssh-hub.log"),
+    );
+    assert!(plist.contains("<string>com.perceptron-studios.ssh-hub</string>"));
+    let arguments: Vec<&str> = plist
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("<string>"))
+        .filter_map(|line| line.strip_suffix("</string>"))
+        .collect();
+    assert_eq!(
+        arguments,
+        [
+            "com.perceptron-studios.ssh-hub",
+            "/usr/local/bin/ssh-hub",
+            "--config",
+            "/Users/me/a&amp;b.toml",
+            "daemon",
+            "run",
+            "/Users/me/Library/Logs/ssh-hub.log",
+        ]
+    );
+    assert!(plist.contains("<key>RunAtLoad</key>\n    <true/>"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_install_writes_unit_and_enables_broker() {
+    let home = tempfile::tempdir().unwrap();
+    let config = home.path().join(".config");
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_ssh-hub"))
+            .args(args)
+            .env("HOME", home.path())
+            .env("XDG_CONFIG_HOME", &config)
+            .env_remove("SSH_HUB_CONFIG")
+            .env_remove("SSH_HUB_CONFIG_DIR")
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+    };
+
+    run(&["--profile", "work", "daemon", "install", "--no-start"]);
+    let unit = std::fs::read_to_string(config.join("systemd/user/ssh-hub-work.service")).unwrap();
+    assert!(unit.contains(" --profile work daemon run\n"), "{unit}");
+    assert!(!unit.contains("--config"), "{unit}");
+    let servers = std::fs::read_to_string(config.join("ssh-hub/servers-work.toml")).unwrap();
+    assert!(servers.contains("[broker]\nenabled = true"), "{servers}");
+
+    // Uninstalling without systemd still removes the file
+    let output = Command::new(env!("CARGO_BIN_EXE_ssh-hub"))
+        .args(["--profile", "work", "daemon", "uninstall"])
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", &config)
+        .env("PATH", "")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert!(!config.join("systemd/user/ssh-hub-work.service").exists());
+}
@@ -0,0 +1,26 @@
+use ssh_hub::utils::diff::{truncate_diff, unified_diff};
+
+#[test]
+fn test_unified_diff_single_change() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+    let new = "a\nb\nc\nd\nE\nf\ng\nh\n";
+    let diff = unified_diff(old, new, "/srv/app/config.txt");
+    assert!(diff.starts_with("--- /srv/app/config.txt\n+++ /srv/app/config.txt\n"));
+    assert!(diff.contains("@@ -2,7 +2,7 @@"));
+    assert!(diff.contains("\n-e\n+E\n"));
+    // Three lines of context on each side, nothing further.
+    assert!(!diff.contains("\n a\n"));
+    assert!(diff.contains("\n h\n"));
+}
+
+#[test]
+fn test_unified_diff_identical_is_empty() {
+    assert_eq!(unified_diff("same\n", "same\n", "f"), "");
+}
+
+#[test]
+fn test_truncate_diff() {
+    let diff = "l1\nl2\nl3\nl4\nl5\n";
+    assert_eq!(truncate_diff(diff, 10), diff);
+    assert_eq!(truncate_diff(diff, 2), "l1\nl2\n... (3 more diff lines)\n");
+}
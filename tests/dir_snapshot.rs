@@ -0,0 +1,336 @@
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use ssh_hub::connection::{ExecResult, FakeExecutor};
+use ssh_hub::server_registry::RemoteShell;
+use ssh_hub::timeout::TimeoutPolicy;
+use ssh_hub::tools::dir_snapshot::{
+    list_command, restore_command, snapshot_command, snapshot_name, snapshot_source,
+};
+use ssh_hub::tools::{remote_restore, remote_snapshot, ErrorCode, ToolError};
+
+#[test]
+fn test_snapshot_names_and_labels() {
+    let stamp = "20261016T152940.123Z";
+    assert_eq!(snapshot_name(stamp, None).unwrap(), stamp);
+    assert_eq!(
+        snapshot_name(stamp, Some("before-v2.1_upgrade")).unwrap(),
+        "20261016T152940.123Z-before-v2.1_upgrade"
+    );
+    for label in ["", "has space", "../up", "a/b", &"x".repeat(65)] {
+        assert!(snapshot_name(stamp, Some(label)).is_err(), "{label:?}");
+    }
+}
+
+#[test]
+fn test_snapshot_source_from_snapshot_path() {
+    assert_eq!(
+        snapshot_source("/home/me/.ssh-hub-snapshots/20261016T152940.123Z/srv/app.tar.gz").unwrap(),
+        "/srv/app"
+    );
+    assert_eq!(
+        snapshot_source("/var/backups/20261016T152940.123Z-before-upgrade/srv/my app.tar.gz")
+            .unwrap(),
+        "/srv/my app"
+    );
+    assert_eq!(
+        snapshot_source(
+            "~/.ssh-hub-snapshots/20261016T152940.123Z/home/me/20261016T000000.000Z.tar.gz"
+        )
+        .unwrap(),
+        "/home/me/20261016T000000.000Z"
+    );
+    for path in [
+        "/srv/app.tar.gz",
+        "/snaps/20261016T152940.123Z/srv/app.tgz",
+        "/snaps/20261016T152940.123Z.tar.gz",
+        "/snaps/20261016T152940.123Zlabel/srv/app.tar.gz",
+        "/snaps/20261016T152940.123Z/srv//app.tar.gz",
+        "/snaps/20261016T152940.123Z/../../etc.tar.gz",
+    ] {
+        assert!(snapshot_source(path).is_err(), "{path} should be rejected");
+    }
+}
+
+fn sh(script: &str, home: &Path, cwd: &Path) -> (i32, String, String) {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .env("HOME", home)
+        .current_dir(cwd)
+        .output()
+        .unwrap();
+    (
+        output.status.code().unwrap_or(-1),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[cfg(unix)]
+#[test]
+fn test_snapshot_list_and_restore_scripts() {
+    let home = tempfile::tempdir().unwrap();
+    let home = home.path().canonicalize().unwrap();
+    let app = home.join("it's app");
+    std::fs::create_dir_all(app.join("conf")).unwrap();
+    std::fs::write(app.join("conf/app.toml"), "v1").unwrap();
+
+    let (code, stdout, stderr) = sh(
+        &snapshot_command("it's app", "~/.ssh-hub-snapshots", "20261016T152940.123Z"),
+        &home,
+        &home,
+    );
+    assert_eq!(code, 0, "{stderr}");
+    let mut lines = stdout.lines();
+    let snapshot = lines.next().unwrap().to_string();
+    assert_eq!(
+        snapshot,
+        format!(
+            "{}/.ssh-hub-snapshots/20261016T152940.123Z{}.tar.gz",
+            home.display(),
+            app.display()
+        )
+    );
+    let bytes: u64 = lines.next().unwrap().trim().parse().unwrap();
+    assert_eq!(bytes, std::fs::metadata(&snapshot).unwrap().len());
+    assert_eq!(snapshot_source(&snapshot).unwrap(), app.to_str().unwrap());
+
+    let (_, listed, _) = sh(
+        &list_command(app.to_str().unwrap(), "~/.ssh-hub-snapshots"),
+        &home,
+        &home,
+    );
+    assert_eq!(listed, format!("{bytes}\t{snapshot}\n"));
+
+    std::fs::write(app.join("conf/app.toml"), "v2").unwrap();
+    std::fs::write(app.join("new.txt"), "added").unwrap();
+    let (code, _, stderr) = sh(
+        &restore_command(&snapshot, app.to_str().unwrap()),
+        &home,
+        &home,
+    );
+    assert_eq!(code, 0, "{stderr}");
+    assert_eq!(
+        std::fs::read_to_string(app.join("conf/app.toml")).unwrap(),
+        "v1"
+    );
+    assert!(!app.join("new.txt").exists());
+    let leftovers: Vec<_> = std::fs::read_dir(&home)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with(".ssh-hub-restore"))
+        .collect();
+    assert!(leftovers.is_empty(), "{leftovers:?}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_snapshot_leaves_out_a_snapshot_dir_inside_the_directory() {
+    let home = tempfile::tempdir().unwrap();
+    let home = home.path().canonicalize().unwrap();
+    std::fs::write(home.join("notes.txt"), "hi").unwrap();
+    let script = snapshot_command("~", "~/.ssh-hub-snapshots", "20261016T152940.123Z");
+    let (code, stdout, stderr) = sh(&script, &home, &home);
+    assert_eq!(code, 0, "{stderr}");
+
+    let snapshot = stdout.lines().next().unwrap();
+    let (_, members, _) = sh(&format!("tar -tzf '{snapshot}'"), &home, &home);
+    assert!(members.contains("notes.txt"), "{members}");
+    assert!(!members.contains(".ssh-hub-snapshots"), "{members}");
+
+    // Restoring would delete the snapshots along with the directory
+    let (code, _, stderr) = sh(
+        &restore_command(snapshot, home.to_str().unwrap()),
+        &home,
+        &home,
+    );
+    assert_eq!(code, 1);
+    assert!(stderr.contains("inside"), "{stderr}");
+    assert!(home.join("notes.txt").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_snapshot_and_restore_failures() {
+    let home = tempfile::tempdir().unwrap();
+    let home = home.path().canonicalize().unwrap();
+    let (code, stdout, _) = sh(
+        &snapshot_command("missing", "~/.ssh-hub-snapshots", "20261016T152940.123Z"),
+        &home,
+        &home,
+    );
+    assert_eq!((code, stdout.as_str()), (2, ""));
+
+    // A corrupt snapshot leaves the directory alone
+    let app = home.join("app");
+    std::fs::create_dir(&app).unwrap();
+    std::fs::write(app.join("keep"), "x").unwrap();
+    let snaps = home
+        .join("snaps/20261016T152940.123Z")
+        .join(app.strip_prefix("/").unwrap());
+    std::fs::create_dir_all(snaps.parent().unwrap()).unwrap();
+    let corrupt = format!("{}.tar.gz", snaps.display());
+    std::fs::write(&corrupt, "not gzip").unwrap();
+    let (code, _, _) = sh(
+        &restore_command(&corrupt, app.to_str().unwrap()),
+        &home,
+        &home,
+    );
+    assert_eq!(code, 1);
+    assert!(app.join("keep").exists());
+    assert_eq!(
+        std::fs::read_dir(&home).unwrap().count(),
+        2,
+        "staging left behind"
+    );
+}
+
+fn snapshot_input(value: Value) -> remote_snapshot::RemoteSnapshotInput {
+    serde_json::from_value(value).unwrap()
+}
+
+#[tokio::test]
+async fn test_snapshot_tool_output() {
+    let conn = Arc::new(FakeExecutor::new("/srv").on_command(
+        "tar -czf",
+        "/home/me/.ssh-hub-snapshots/20261016T152940.123Z-pre/srv/app.tar.gz\n 2048\n",
+    ));
+    let output = remote_snapshot::handler::handle(
+        Arc::clone(&conn),
+        snapshot_input(json!({"server": "s", "path": "/srv/app", "label": "pre"})),
+        "~/.ssh-hub-snapshots",
+        TimeoutPolicy::default(),
+    )
+    .await;
+    let value: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(
+        value,
+        json!({
+            "path": "/srv/app",
+            "snapshot": "/home/me/.ssh-hub-snapshots/20261016T152940.123Z-pre/srv/app.tar.gz",
+            "bytes": 2048,
+        })
+    );
+    assert!(
+        conn.commands()[0].contains("-pre'"),
+        "{:?}",
+        conn.commands()
+    );
+}
+
+#[tokio::test]
+async fn test_snapshot_tool_lists_oldest_first() {
+    let conn = Arc::new(FakeExecutor::new("/srv").on_command(
+        "for f in",
+        "20\t/s/20261016T152940.123Z/srv/app.tar.gz\n10\t/s/20261015T080000.000Z-a/srv/app.tar.gz\n",
+    ));
+    let output = remote_snapshot::handler::handle(
+        conn,
+        snapshot_input(json!({"server": "s", "path": "/srv/app", "list": true})),
+        "/s",
+        TimeoutPolicy::default(),
+    )
+    .await;
+    let value: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(
+        value["snapshots"],
+        json!([
+            {"snapshot": "/s/20261015T080000.000Z-a/srv/app.tar.gz", "bytes": 10},
+            {"snapshot": "/s/20261016T152940.123Z/srv/app.tar.gz", "bytes": 20},
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_snapshot_tool_errors() {
+    let run = |conn: FakeExecutor, input: Value| async move {
+        remote_snapshot::handler::handle(
+            Arc::new(conn),
+            snapshot_input(input),
+            "/s",
+            TimeoutPolicy::default(),
+        )
+        .await
+    };
+    let missing = FakeExecutor::new("/srv").on_command_result(
+        "tar -czf",
+        ExecResult {
+            stdout: String::new(),
+            stderr: "Not a directory: /srv/gone".to_string(),
+            exit_code: 2,
+        },
+    );
+    let output = run(missing, json!({"server": "s", "path": "gone"})).await;
+    assert_eq!(
+        ToolError::from_json(&output),
+        Some(ToolError::new(
+            ErrorCode::FileNotFound,
+            "gone is not a directory"
+        ))
+    );
+    let root = run(
+        FakeExecutor::new("/srv"),
+        json!({"server": "s", "path": "/"}),
+    )
+    .await;
+    let root = ToolError::from_json(&root).unwrap();
+    assert_eq!(root.code, ErrorCode::InvalidInput);
+    assert!(root.message.starts_with("refusing to snapshot the root"));
+    let label = run(
+        FakeExecutor::new("/srv"),
+        json!({"server": "s", "path": "app", "label": "a b"}),
+    )
+    .await;
+    let label = ToolError::from_json(&label).unwrap();
+    assert_eq!(label.code, ErrorCode::InvalidInput);
+    assert!(label.message.starts_with("invalid snapshot label"));
+    let windows = run(
+        FakeExecutor::new("C:/app").with_shell(RemoteShell::Powershell),
+        json!({"server": "s", "path": "app"}),
+    )
+    .await;
+    assert!(windows.contains("requires a POSIX shell"), "{windows}");
+}
+
+#[tokio::test]
+async fn test_restore_tool_snapshots_the_replaced_directory() {
+    let conn = Arc::new(
+        FakeExecutor::new("/srv")
+            .on_command(
+                "tar -czf",
+                "/s/20261016T160000.000Z-before-restore/srv/app.tar.gz\n512\n",
+            )
+            .on_command("tar -xpzf", ""),
+    );
+    let input = serde_json::from_value(json!({
+        "server": "s",
+        "snapshot": "/s/20261016T152940.123Z/srv/app.tar.gz",
+    }))
+    .unwrap();
+    let output =
+        remote_restore::handler::handle(Arc::clone(&conn), input, "/s", TimeoutPolicy::default())
+            .await;
+    assert_eq!(
+        output,
+        "Restored /srv/app from /s/20261016T152940.123Z/srv/app.tar.gz. \
+         The replaced directory was snapshotted to /s/20261016T160000.000Z-before-restore/srv/app.tar.gz"
+    );
+    let commands = conn.commands();
+    assert!(commands[0].contains("before-restore"));
+    assert!(commands[1].contains("tar -xpzf"));
+
+    let input =
+        serde_json::from_value(json!({"server": "s", "snapshot": "/srv/app.tar.gz"})).unwrap();
+    let output = remote_restore::handler::handle(conn, input, "/s", TimeoutPolicy::default()).await;
+    let error = ToolError::from_json(&output).unwrap();
+    assert_eq!(error.code, ErrorCode::InvalidInput);
+    assert!(
+        error
+            .message
+            .starts_with("'/srv/app.tar.gz' is not a snapshot path"),
+        "{output}"
+    );
+}
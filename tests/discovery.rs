@@ -0,0 +1,205 @@
+use std::time::Duration;
+
+use ssh_hub::discovery::{
+    aws_default_user, parse_aws_instance, parse_gcp_instance, run_lookup, DiscoveryConfig,
+};
+use ssh_hub::server_registry::{ServerEntry, ServerRegistry};
+
+#[test]
+fn test_parse_discovery_config() {
+    let config: ServerRegistry = toml::from_str(
+        r#"
+[servers.build]
+host = "203.0.113.7"
+user = "ci"
+discovery = { aws_instance = "i-0abc123", region = "eu-west-1" }
+"#,
+    )
+    .unwrap();
+    let discovery = config.get("build").unwrap().discovery.as_ref().unwrap();
+    assert_eq!(discovery.aws_instance.as_deref(), Some("i-0abc123"));
+    assert_eq!(discovery.region.as_deref(), Some("eu-west-1"));
+
+    let out = toml::to_string_pretty(&config).unwrap();
+    assert!(out.contains("aws_instance = \"i-0abc123\""), "{out}");
+    assert!(!out.contains("gcp_instance"), "{out}");
+}
+
+#[test]
+fn test_lookup_command_per_source() {
+    let command = DiscoveryConfig {
+        command: Some("terraform output -raw ip".to_string()),
+        ..DiscoveryConfig::default()
+    };
+    assert_eq!(
+        command.lookup_command().unwrap(),
+        "terraform output -raw ip"
+    );
+
+    let aws = DiscoveryConfig {
+        aws_instance: Some("i-0abc123".to_string()),
+        region: Some("eu-west-1".to_string()),
+        ..DiscoveryConfig::default()
+    };
+    let aws = aws.lookup_command().unwrap();
+    assert!(
+        aws.starts_with("aws ec2 describe-instances --instance-ids 'i-0abc123'"),
+        "{aws}"
+    );
+    assert!(aws.contains("PublicIpAddress"), "{aws}");
+    assert!(aws.ends_with(" --region 'eu-west-1'"), "{aws}");
+
+    let gcp = DiscoveryConfig {
+        gcp_instance: Some("web-1".to_string()),
+        zone: Some("us-central1-a".to_string()),
+        project: Some("acme".to_string()),
+        ..DiscoveryConfig::default()
+    };
+    let gcp = gcp.lookup_command().unwrap();
+    assert!(
+        gcp.starts_with("gcloud compute instances describe 'web-1'"),
+        "{gcp}"
+    );
+    assert!(gcp.contains("natIP"), "{gcp}");
+    assert!(
+        gcp.ends_with(" --zone 'us-central1-a' --project 'acme'"),
+        "{gcp}"
+    );
+}
+
+#[test]
+fn test_lookup_command_needs_exactly_one_source() {
+    assert!(DiscoveryConfig::default().lookup_command().is_err());
+    let both = DiscoveryConfig {
+        command: Some("echo 10.0.0.1".to_string()),
+        gcp_instance: Some("web-1".to_string()),
+        ..DiscoveryConfig::default()
+    };
+    let err = both.lookup_command().unwrap_err();
+    assert!(err.to_string().contains("only one"), "{err}");
+}
+
+#[tokio::test]
+async fn test_run_lookup_takes_first_line() {
+    let address = run_lookup(
+        "discovery",
+        "printf '  10.0.0.9 \\nignored\\n'",
+        Duration::from_secs(10),
+    )
+    .await
+    .unwrap();
+    assert_eq!(address, "10.0.0.9");
+}
+
+#[tokio::test]
+async fn test_run_lookup_errors() {
+    let limit = Duration::from_secs(10);
+    let err = run_lookup("discovery", "echo denied >&2; exit 3", limit)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("denied"), "{err}");
+
+    let err = run_lookup("discovery", "true", limit).await.unwrap_err();
+    assert!(err.to_string().contains("empty output"), "{err}");
+
+    // What `aws` prints for an instance without a public IP
+    let err = run_lookup("discovery", "echo None", limit)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("empty output"), "{err}");
+
+    let err = run_lookup("discovery", "sleep 5", Duration::from_millis(100))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("timed out"), "{err}");
+}
+
+#[test]
+fn test_set_discovered_address() {
+    let mut entry = ServerEntry {
+        host: "203.0.113.7".to_string(),
+        ..ServerEntry::default()
+    };
+    assert!(!entry.set_discovered_address("203.0.113.7"));
+    assert!(entry.set_discovered_address("198.51.100.2"));
+    assert_eq!(entry.host, "198.51.100.2");
+
+    // A pinned IP is replaced instead, keeping the name for host key checks
+    let mut entry = ServerEntry {
+        host: "build.internal".to_string(),
+        resolve: Some("10.0.0.1".to_string()),
+        ..ServerEntry::default()
+    };
+    assert!(entry.set_discovered_address("10.0.0.2"));
+    assert_eq!(entry.host, "build.internal");
+    assert_eq!(entry.resolve.as_deref(), Some("10.0.0.2"));
+}
+
+#[test]
+fn test_parse_aws_instance() {
+    let instance = parse_aws_instance(
+        r#"{"Reservations": [{"Instances": [{
+            "InstanceId": "i-0abc123",
+            "PublicIpAddress": "203.0.113.7",
+            "KeyName": "deploy",
+            "ImageId": "ami-0def456"
+        }]}]}"#,
+    )
+    .unwrap();
+    assert_eq!(instance.host, "203.0.113.7");
+    assert_eq!(instance.key_name.as_deref(), Some("deploy"));
+    assert_eq!(instance.image_id.as_deref(), Some("ami-0def456"));
+
+    let stopped = r#"{"Reservations": [{"Instances": [{"InstanceId": "i-0abc123"}]}]}"#;
+    let err = parse_aws_instance(stopped).unwrap_err();
+    assert!(err.to_string().contains("no public IP"), "{err}");
+    assert!(parse_aws_instance(r#"{"Reservations": []}"#).is_err());
+}
+
+#[test]
+fn test_parse_gcp_instance() {
+    let json = |metadata: &str| {
+        format!(
+            r#"{{"name": "web-1",
+                "networkInterfaces": [{{"accessConfigs": [{{"natIP": "198.51.100.4"}}]}}],
+                "metadata": {{"items": [{metadata}]}}}}"#
+        )
+    };
+    let plain = parse_gcp_instance(&json(r#"{"key": "startup-script", "value": "true"}"#)).unwrap();
+    assert_eq!(plain.host, "198.51.100.4");
+    assert!(!plain.os_login);
+    let os_login =
+        parse_gcp_instance(&json(r#"{"key": "enable-oslogin", "value": "TRUE"}"#)).unwrap();
+    assert!(os_login.os_login);
+
+    let err =
+        parse_gcp_instance(r#"{"networkInterfaces": [{"networkIP": "10.0.0.2"}]}"#).unwrap_err();
+    assert!(err.to_string().contains("no external IP"), "{err}");
+}
+
+#[test]
+fn test_aws_default_user() {
+    let cases = [
+        (
+            "ubuntu/images/hvm-ssd/ubuntu-noble-24.04-amd64-server-20240801",
+            Some("ubuntu"),
+        ),
+        ("debian-12-amd64-20240717-1811", Some("admin")),
+        (
+            "al2023-ami-2023.5.20240805.0-kernel-6.1-x86_64",
+            Some("ec2-user"),
+        ),
+        (
+            "amzn2-ami-kernel-5.10-hvm-2.0.20240719.0-x86_64-gp2",
+            Some("ec2-user"),
+        ),
+        (
+            "RHEL-9.4.0_HVM-20240605-x86_64-82-Hourly2-GP3",
+            Some("ec2-user"),
+        ),
+        ("my-golden-image-2024", None),
+    ];
+    for (name, user) in cases {
+        assert_eq!(aws_default_user(name), user, "{name}");
+    }
+}
@@ -0,0 +1,288 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use ssh_hub::connection::resolve::{
+    address_family, connect_any, connect_any_from, filter_addrs, interleave_families,
+    is_unreachable, resolve_addr, resolve_addrs, DnsCache, LocalBind, DNS_CACHE_TTL_SECS,
+};
+use ssh_hub::server_registry::{AddressFamily, ServerEntry, ServerRegistry};
+
+const IP_A: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3));
+const IP_B: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 1, 2, 4));
+
+#[test]
+fn test_lookup_respects_ttl() {
+    let mut cache = DnsCache::default();
+    cache.record("db.internal", IP_A, 1_000);
+    assert_eq!(cache.lookup("db.internal", 1_000), Some(IP_A));
+    assert_eq!(
+        cache.lookup("db.internal", 1_000 + DNS_CACHE_TTL_SECS),
+        Some(IP_A)
+    );
+    assert_eq!(
+        cache.lookup("db.internal", 1_001 + DNS_CACHE_TTL_SECS),
+        None
+    );
+    assert_eq!(cache.lookup("other.internal", 1_000), None);
+}
+
+#[test]
+fn test_record_reports_when_save_is_needed() {
+    let mut cache = DnsCache::default();
+    assert!(cache.record("db.internal", IP_A, 1_000));
+    // Same address shortly after: nothing worth writing.
+    assert!(!cache.record("db.internal", IP_A, 1_060));
+    // Address changed.
+    assert!(cache.record("db.internal", IP_B, 1_120));
+    assert_eq!(cache.lookup("db.internal", 1_120), Some(IP_B));
+    // Same address but past half the TTL: refresh the timestamp.
+    assert!(cache.record("db.internal", IP_B, 1_121 + DNS_CACHE_TTL_SECS / 2));
+}
+
+#[test]
+fn test_forget_drops_host() {
+    let mut cache = DnsCache::default();
+    cache.record("db.internal", IP_A, 1_000);
+    assert!(cache.forget("db.internal"));
+    assert!(!cache.forget("db.internal"));
+    assert_eq!(cache.lookup("db.internal", 1_000), None);
+}
+
+#[test]
+fn test_cache_json_roundtrip() {
+    let mut cache = DnsCache::default();
+    cache.record("db.internal", IP_A, 1_000);
+    let json = serde_json::to_string(&cache).unwrap();
+    let back: DnsCache = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, cache);
+}
+
+#[tokio::test]
+async fn test_resolve_addr_prefers_pinned_ip() {
+    let addr = resolve_addr("unresolvable.invalid", 2222, Some("10.1.2.3"))
+        .await
+        .unwrap();
+    assert_eq!(addr.to_string(), "10.1.2.3:2222");
+}
+
+#[tokio::test]
+async fn test_resolve_addr_rejects_non_ip_pin() {
+    assert!(resolve_addr("host", 22, Some("not-an-ip")).await.is_err());
+}
+
+#[tokio::test]
+async fn test_resolve_addr_accepts_ip_literal_host() {
+    let addr = resolve_addr("::1", 22, None).await.unwrap();
+    assert_eq!(addr.to_string(), "[::1]:22");
+}
+
+#[test]
+fn test_connect_host_uses_pinned_ip() {
+    let mut entry = ServerEntry {
+        host: "db.internal".to_string(),
+        ..ServerEntry::default()
+    };
+    assert_eq!(entry.connect_host(), "db.internal");
+    entry.resolve = Some("10.1.2.3".to_string());
+    assert_eq!(entry.connect_host(), "10.1.2.3");
+}
+
+fn addrs(list: &[&str]) -> Vec<SocketAddr> {
+    list.iter().map(|a| a.parse().unwrap()).collect()
+}
+
+#[test]
+fn test_interleave_families_alternates_from_preferred() {
+    let ordered = interleave_families(addrs(&[
+        "[2001:db8::1]:22",
+        "[2001:db8::2]:22",
+        "[2001:db8::3]:22",
+        "192.0.2.1:22",
+        "192.0.2.2:22",
+    ]));
+    assert_eq!(
+        ordered,
+        addrs(&[
+            "[2001:db8::1]:22",
+            "192.0.2.1:22",
+            "[2001:db8::2]:22",
+            "192.0.2.2:22",
+            "[2001:db8::3]:22",
+        ])
+    );
+
+    let ordered = interleave_families(addrs(&["192.0.2.1:22", "192.0.2.1:22", "[2001:db8::1]:22"]));
+    assert_eq!(ordered, addrs(&["192.0.2.1:22", "[2001:db8::1]:22"]));
+    assert!(interleave_families(Vec::new()).is_empty());
+}
+
+#[tokio::test]
+async fn test_resolve_addrs_single_for_pins_and_literals() {
+    let pinned = resolve_addrs("unresolvable.invalid", 22, Some("::1"))
+        .await
+        .unwrap();
+    assert_eq!(pinned, addrs(&["[::1]:22"]));
+    let literal = resolve_addrs("10.1.2.3", 2222, None).await.unwrap();
+    assert_eq!(literal, addrs(&["10.1.2.3:2222"]));
+}
+
+#[tokio::test]
+async fn test_connect_any_falls_back_to_next_address() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let open = listener.local_addr().unwrap();
+    // Bind and drop to find a port nothing listens on.
+    let closed = {
+        let l = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        l.local_addr().unwrap()
+    };
+
+    let (_, addr) = connect_any(&[closed, open], Duration::from_secs(30))
+        .await
+        .unwrap();
+    assert_eq!(addr, open);
+
+    let err = connect_any(&[closed], Duration::from_millis(10))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains(&closed.to_string()));
+    assert!(is_unreachable(&err));
+}
+
+#[tokio::test]
+async fn test_unreachable_excludes_config_errors() {
+    let err = resolve_addrs("example.com", 22, Some("not-an-ip"))
+        .await
+        .unwrap_err();
+    assert!(!is_unreachable(&err));
+
+    let err = resolve_addrs("ssh-hub-test.invalid", 22, None)
+        .await
+        .unwrap_err();
+    assert!(is_unreachable(&err));
+}
+
+#[test]
+fn test_address_family_names() {
+    assert_eq!(address_family(&"10.0.0.1:22".parse().unwrap()), "ipv4");
+    assert_eq!(address_family(&"[::1]:22".parse().unwrap()), "ipv6");
+}
+
+#[test]
+fn test_filter_addrs_by_family_and_bind_address() {
+    let both = addrs(&["[2001:db8::1]:22", "10.0.0.1:22", "[2001:db8::2]:22"]);
+    let none = LocalBind::default();
+    assert_eq!(
+        filter_addrs("h", both.clone(), AddressFamily::Any, &none).unwrap(),
+        both
+    );
+    assert_eq!(
+        filter_addrs("h", both.clone(), AddressFamily::Inet, &none).unwrap(),
+        addrs(&["10.0.0.1:22"])
+    );
+    assert_eq!(
+        filter_addrs("h", both.clone(), AddressFamily::Inet6, &none).unwrap(),
+        addrs(&["[2001:db8::1]:22", "[2001:db8::2]:22"])
+    );
+
+    let from_v4 = LocalBind {
+        address: Some("192.168.1.5".parse().unwrap()),
+        interface: None,
+    };
+    assert_eq!(
+        filter_addrs("h", both, AddressFamily::Any, &from_v4).unwrap(),
+        addrs(&["10.0.0.1:22"])
+    );
+
+    let v6_only = addrs(&["[2001:db8::1]:22"]);
+    let err = filter_addrs("h", v6_only.clone(), AddressFamily::Inet, &none).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "None of the 1 addresses of 'h' can be used with address_family = \"inet\""
+    );
+    assert!(
+        !is_unreachable(&err),
+        "a config mismatch isn't worth rediscovery"
+    );
+    let err = filter_addrs("h", v6_only, AddressFamily::Any, &from_v4).unwrap_err();
+    assert!(
+        err.to_string().contains("bind_address = \"192.168.1.5\""),
+        "{err}"
+    );
+}
+
+#[tokio::test]
+async fn test_connect_any_from_bind_address() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let open = listener.local_addr().unwrap();
+    let bind = LocalBind {
+        address: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+        interface: None,
+    };
+    let (stream, addr) = connect_any_from(&[open], Duration::from_secs(30), &bind)
+        .await
+        .unwrap();
+    assert_eq!(addr, open);
+    assert_eq!(
+        stream.local_addr().unwrap().ip(),
+        IpAddr::V4(Ipv4Addr::LOCALHOST)
+    );
+
+    let unknown = LocalBind {
+        address: None,
+        interface: Some("ssh-hub-none0".to_string()),
+    };
+    let err = connect_any_from(&[open], Duration::from_secs(30), &unknown)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("ssh-hub-none0"), "{err}");
+}
+
+#[test]
+fn test_bind_settings_from_config() {
+    let cfg = ServerRegistry::parse(
+        r#"
+        [servers.vpn]
+        host = "db.internal"
+        user = "deploy"
+        address_family = "inet"
+        bind_address = "10.8.0.2"
+        bind_interface = "tun0"
+
+        [servers.plain]
+        host = "10.0.0.1"
+        user = "deploy"
+        "#,
+    )
+    .unwrap();
+    let vpn = ssh_hub::cli::params_from_config("vpn", cfg.get("vpn").unwrap());
+    assert_eq!(vpn.address_family, AddressFamily::Inet);
+    assert_eq!(
+        vpn.bind,
+        LocalBind {
+            address: Some("10.8.0.2".parse().unwrap()),
+            interface: Some("tun0".to_string()),
+        }
+    );
+    let plain = ssh_hub::cli::params_from_config("plain", cfg.get("plain").unwrap());
+    assert_eq!(plain.address_family, AddressFamily::Any);
+    assert_eq!(plain.bind, LocalBind::default());
+
+    assert!(ServerRegistry::parse(
+        r#"
+        [servers.bad]
+        host = "h"
+        user = "u"
+        bind_address = "eth0"
+        "#,
+    )
+    .is_err());
+    assert!(ServerRegistry::parse(
+        r#"
+        [servers.bad]
+        host = "h"
+        user = "u"
+        address_family = "ipv4"
+        "#,
+    )
+    .is_err());
+}
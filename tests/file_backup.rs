@@ -0,0 +1,72 @@
+use std::process::Command;
+use std::time::Duration;
+
+use ssh_hub::tools::file_backup::{backup_command, backup_stamp, original_path};
+
+#[test]
+fn test_backup_stamp_is_utc_with_millis() {
+    assert_eq!(
+        backup_stamp(Duration::from_millis(1_792_164_580_123)),
+        "20261016T152940.123Z"
+    );
+    assert_eq!(backup_stamp(Duration::ZERO), "19700101T000000.000Z");
+}
+
+#[test]
+fn test_original_path_from_backup_path() {
+    assert_eq!(
+        original_path("/home/me/.ssh-hub-backups/20261016T152940.123Z/etc/app.conf").unwrap(),
+        "/etc/app.conf"
+    );
+    assert_eq!(
+        original_path("~/.ssh-hub-backups/20261016T152940.123Z/srv/it's here").unwrap(),
+        "/srv/it's here"
+    );
+}
+
+#[test]
+fn test_original_path_rejects_other_paths() {
+    for path in [
+        "/etc/app.conf",
+        "/home/me/.ssh-hub-backups/20261016T152940.123Z",
+        "/home/me/.ssh-hub-backups//etc/app.conf",
+        "/home/me/.ssh-hub-backups/20261016T152940.123Z/../../etc/shadow",
+    ] {
+        assert!(original_path(path).is_err(), "{path} should be rejected");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_backup_command_copies_existing_files_only() {
+    let home = tempfile::tempdir().unwrap();
+    let work = tempfile::tempdir().unwrap();
+    let file = work.path().join("it's.conf");
+    std::fs::write(&file, "original").unwrap();
+
+    let run = |path: &str| {
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(backup_command(path, "20261016T152940.123Z"))
+            .env("HOME", home.path())
+            .current_dir(work.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let backup = run(file.to_str().unwrap());
+    assert_eq!(
+        backup,
+        format!(
+            "{}/.ssh-hub-backups/20261016T152940.123Z{}",
+            home.path().display(),
+            file.display()
+        )
+    );
+    assert_eq!(std::fs::read_to_string(&backup).unwrap(), "original");
+    assert_eq!(original_path(&backup).unwrap(), file.to_str().unwrap());
+
+    assert_eq!(run("missing.conf"), "");
+}
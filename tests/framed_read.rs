@@ -0,0 +1,130 @@
+use std::io;
+use std::process::Command;
+
+use ssh_hub::connection::{
+    framed_read_command, read_failure, read_sentinel, unframe_output, READ_EXIT_DENIED,
+    READ_EXIT_MISSING,
+};
+
+/// Run `command` the way a login shell with noisy startup files would: a
+/// MOTD and a `.bashrc` echo before it, a `.bash_logout` message after.
+fn noisy_shell(command: &str) -> Vec<u8> {
+    let script = format!(
+        "trap 'echo Goodbye from .bash_logout' EXIT; \
+         echo 'Welcome to Ubuntu 24.04 LTS'; printf 'Last login: Tue\\n\\n'; \
+         echo 'conda: base activated'; {command}"
+    );
+    let output = Command::new("sh").arg("-c").arg(script).output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    output.stdout
+}
+
+/// What a channel with a terminal in `onlcr` mode delivers.
+fn onlcr(stdout: &[u8]) -> Vec<u8> {
+    let mut translated = Vec::with_capacity(stdout.len());
+    for &b in stdout {
+        if b == b'\n' {
+            translated.push(b'\r');
+        }
+        translated.push(b);
+    }
+    translated
+}
+
+fn read_through_shell(content: &[u8], tty: bool) -> Vec<u8> {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file with 'quotes'.txt");
+    std::fs::write(&path, content).unwrap();
+    let sentinel = read_sentinel();
+    let mut stdout = noisy_shell(&framed_read_command(path.to_str().unwrap(), &sentinel));
+    if tty {
+        stdout = onlcr(&stdout);
+    }
+    unframe_output(&stdout, &sentinel).unwrap()
+}
+
+#[test]
+fn test_banners_are_stripped() {
+    let contents: [&[u8]; 5] = [
+        b"hello\n",
+        b"no trailing newline",
+        b"",
+        b"windows\r\nline endings\r\n",
+        b"\n\nbinary \x00\xff\r\x1b[0m\n\n",
+    ];
+    for content in contents {
+        assert_eq!(read_through_shell(content, false), content);
+    }
+}
+
+#[test]
+fn test_onlcr_translation_is_undone() {
+    let contents: [&[u8]; 4] = [
+        b"hello\nworld\n",
+        b"windows\r\nline endings\r\n",
+        b"lone\rcarriage return",
+        b"",
+    ];
+    for content in contents {
+        assert_eq!(read_through_shell(content, true), content);
+    }
+}
+
+#[test]
+fn test_content_that_looks_like_markers() {
+    let sentinel = "ssh-hub-0123456789abcdef";
+    let stdout = format!("motd\n{sentinel}:begin\n{sentinel}:end\nbody\n{sentinel}:end\nbye\n");
+    assert_eq!(
+        unframe_output(stdout.as_bytes(), sentinel).unwrap(),
+        format!("{sentinel}:end\nbody").as_bytes()
+    );
+}
+
+#[test]
+fn test_missing_markers_are_errors() {
+    let sentinel = read_sentinel();
+    assert_ne!(sentinel, read_sentinel());
+    let err = unframe_output(b"Welcome\nhello\n", &sentinel).unwrap_err();
+    assert!(err.to_string().contains("start marker"), "{err}");
+    let truncated = format!("{sentinel}:begin\nhalf a fi");
+    let err = unframe_output(truncated.as_bytes(), &sentinel).unwrap_err();
+    assert!(err.to_string().contains("end marker"), "{err}");
+
+    // A failed cat exits before the end marker
+    let stdout = noisy_shell(&format!(
+        "({}) || true",
+        framed_read_command("/nonexistent/file", &sentinel)
+    ));
+    assert!(unframe_output(&stdout, &sentinel).is_err());
+}
+
+#[test]
+fn test_failed_reads_say_why() {
+    let sentinel = read_sentinel();
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(framed_read_command("/nonexistent/file", &sentinel))
+        .output()
+        .unwrap()
+        .status;
+    assert_eq!(status.code(), Some(READ_EXIT_MISSING));
+
+    let dir = tempfile::tempdir().unwrap();
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(framed_read_command(dir.path().to_str().unwrap(), &sentinel))
+        .output()
+        .unwrap()
+        .status;
+    assert_eq!(status.code(), Some(1), "a directory exists and is readable");
+
+    let kind = |exit_code| {
+        read_failure(exit_code, "cat: x: No such file or directory\n")
+            .downcast::<io::Error>()
+            .unwrap()
+            .kind()
+    };
+    assert_eq!(kind(READ_EXIT_MISSING), io::ErrorKind::NotFound);
+    assert_eq!(kind(READ_EXIT_DENIED), io::ErrorKind::PermissionDenied);
+    assert_eq!(kind(1), io::ErrorKind::Other);
+}
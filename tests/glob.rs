@@ -0,0 +1,162 @@
+use ssh_hub::utils::glob::{
+    filter_matches, page_matches, GlobKind, GlobPage, GlobPattern, GlobSort,
+};
+
+fn matches(pattern: &str, path: &str) -> bool {
+    GlobPattern::new(pattern).unwrap().is_match(path)
+}
+
+#[test]
+fn test_star_stays_within_segment() {
+    assert!(matches("src/*.rs", "src/main.rs"));
+    assert!(!matches("src/*.rs", "src/tools/mod.rs"));
+    assert!(matches("src/?.rs", "src/a.rs"));
+    assert!(!matches("src/?.rs", "src/ab.rs"));
+}
+
+#[test]
+fn test_double_star_spans_directories() {
+    assert!(matches("src/**/*.rs", "src/main.rs"));
+    assert!(matches("src/**/*.rs", "src/tools/remote_glob/mod.rs"));
+    assert!(!matches("src/**/*.rs", "tests/glob.rs"));
+    assert!(matches("**/Cargo.toml", "Cargo.toml"));
+    assert!(matches("**/Cargo.toml", "crates/core/Cargo.toml"));
+    assert!(matches("logs/**", "logs/2024/app.log"));
+    assert!(!matches("a**b/x", "a/c/b/x"));
+}
+
+#[test]
+fn test_basename_pattern_matches_any_depth() {
+    assert!(matches("*.rs", "main.rs"));
+    assert!(matches("*.rs", "src/tools/mod.rs"));
+    assert!(!matches("*.rs", "src/main.rs.bak"));
+    assert!(matches("./src/*.rs", "src/lib.rs"));
+}
+
+#[test]
+fn test_braces_and_classes() {
+    assert!(matches("*.{rs,toml}", "Cargo.toml"));
+    assert!(matches("*.{rs,toml}", "src/lib.rs"));
+    assert!(!matches("*.{rs,toml}", "README.md"));
+    assert!(matches("{src/{a,b},docs}/*.md", "src/b/x.md"));
+    assert!(matches("{src/{a,b},docs}/*.md", "docs/x.md"));
+    assert!(matches("log[0-9].txt", "log7.txt"));
+    assert!(!matches("log[!0-9].txt", "log7.txt"));
+    assert!(matches("log[!0-9].txt", "logx.txt"));
+    assert!(matches("a,b", "a,b"));
+}
+
+#[test]
+fn test_escapes_and_errors() {
+    assert!(matches(r"\*.txt", "*.txt"));
+    assert!(!matches(r"\*.txt", "a.txt"));
+    assert!(matches("[.txt", "[.txt"));
+    assert!(GlobPattern::new("{a,b").is_err());
+    assert!(GlobPattern::new("a\\").is_err());
+    assert!(GlobPattern::new("").is_err());
+}
+
+#[test]
+fn test_base_dir() {
+    let base = |p: &str| GlobPattern::new(p).unwrap().base_dir().to_string();
+    assert_eq!(base("src/lib/**/*.rs"), "src/lib");
+    assert_eq!(base("src/*/mod.rs"), "src");
+    assert_eq!(base("**/*.rs"), "");
+    assert_eq!(base("*.rs"), "");
+    assert_eq!(base("{src,tests}/*.rs"), "");
+}
+
+#[test]
+fn test_filter_matches_kind_and_limit() {
+    let entries = [
+        "./src/",
+        "./src/main.rs",
+        "./src/tools/",
+        "./src/tools/mod.rs",
+        "./README.md",
+    ];
+    let pattern = GlobPattern::new("src/**").unwrap();
+
+    let files = filter_matches(&pattern, entries, GlobKind::Files, 10);
+    assert_eq!(files.paths, ["src/main.rs", "src/tools/mod.rs"]);
+    assert!(!files.truncated());
+
+    let dirs = filter_matches(&pattern, entries, GlobKind::Dirs, 10);
+    assert_eq!(dirs.paths, ["src/tools/"]);
+
+    let all = filter_matches(&pattern, entries, GlobKind::All, 2);
+    assert_eq!(all.paths, ["src/main.rs", "src/tools/"]);
+    assert_eq!(all.total, 3);
+    assert!(all.truncated());
+}
+
+#[test]
+fn test_page_matches_offset_and_next_offset() {
+    let entries = ["a.log", "b.log", "c.log", "d.log", "e.txt"];
+    let pattern = GlobPattern::new("*.log").unwrap();
+    let page = |offset| GlobPage {
+        offset,
+        limit: 3,
+        sort: GlobSort::Name,
+    };
+
+    let first = page_matches(
+        &pattern,
+        entries.map(|e| (e, None)),
+        GlobKind::Files,
+        page(0),
+    );
+    assert_eq!(first.paths, ["a.log", "b.log", "c.log"]);
+    assert_eq!(first.total, 4);
+    assert_eq!(first.next_offset(), Some(3));
+    assert!(first.truncated());
+
+    let last = page_matches(
+        &pattern,
+        entries.map(|e| (e, None)),
+        GlobKind::Files,
+        page(3),
+    );
+    assert_eq!(last.paths, ["d.log"]);
+    assert_eq!(last.next_offset(), None);
+    assert!(!last.truncated());
+
+    let past_end = page_matches(
+        &pattern,
+        entries.map(|e| (e, None)),
+        GlobKind::Files,
+        page(10),
+    );
+    assert!(past_end.paths.is_empty());
+    assert_eq!(past_end.total, 4);
+}
+
+#[test]
+fn test_page_matches_newest_first() {
+    let entries = [
+        ("./old.log", Some(1_700_000_000.0)),
+        ("./new.log", Some(1_700_000_500.25)),
+        ("./same-b.log", Some(1_700_000_100.0)),
+        ("./same-a.log", Some(1_700_000_100.0)),
+        ("./unknown.log", None),
+        ("./notes.txt", Some(1_800_000_000.0)),
+    ];
+    let pattern = GlobPattern::new("*.log").unwrap();
+    let page = GlobPage {
+        offset: 0,
+        limit: 10,
+        sort: GlobSort::Mtime,
+    };
+
+    let matches = page_matches(&pattern, entries, GlobKind::Files, page);
+    assert_eq!(
+        matches.paths,
+        [
+            "new.log",
+            "same-a.log",
+            "same-b.log",
+            "old.log",
+            "unknown.log"
+        ]
+    );
+}
@@ -0,0 +1,258 @@
+use std::sync::Arc;
+
+use serde_json::json;
+use ssh_hub::connection::{ExecResult, FakeExecutor, RemoteExecutor};
+use ssh_hub::server_registry::RemoteShell;
+use ssh_hub::timeout::TimeoutPolicy;
+use ssh_hub::tools::{
+    remote_disk, remote_edit, remote_glob, remote_process, remote_read, remote_whoami,
+    remote_write, ErrorCode, RemoteDfInput, RemoteEditInput, RemoteGlobInput, RemotePsInput,
+    RemoteReadInput, RemoteWhoamiInput, RemoteWriteInput, ToolError,
+};
+use ssh_hub::utils::output::MAX_INLINE_OUTPUT;
+
+/// The tool error a handler returned, failing the test for any other output.
+fn as_error(output: &str) -> ToolError {
+    ToolError::from_json(output).unwrap_or_else(|| panic!("not a tool error: {output}"))
+}
+
+fn input<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> T {
+    serde_json::from_value(value).unwrap()
+}
+
+#[tokio::test]
+async fn test_write_then_read_relative_path() {
+    let conn = Arc::new(FakeExecutor::new("/srv/app"));
+    let output = remote_write::handler::handle(
+        conn.clone(),
+        input::<RemoteWriteInput>(json!({
+            "server": "fake",
+            "file_path": "notes.txt",
+            "content": "one\ntwo\n",
+        })),
+        TimeoutPolicy::default(),
+    )
+    .await;
+    assert_eq!(output, "Successfully wrote to /srv/app/notes.txt");
+    assert_eq!(
+        conn.file("/srv/app/notes.txt").as_deref(),
+        Some(b"one\ntwo\n".as_slice())
+    );
+
+    let output = remote_read::handler::handle(
+        conn,
+        input::<RemoteReadInput>(json!({"server": "fake", "file_path": "notes.txt"})),
+    )
+    .await;
+    assert!(output.contains("one"));
+    assert!(output.contains("two"));
+}
+
+#[tokio::test]
+async fn test_write_sets_mode_and_owner() {
+    let conn = Arc::new(
+        FakeExecutor::new("/srv/app")
+            .on_command("chmod 755", "")
+            .on_command_result(
+                "chown",
+                ExecResult {
+                    stdout: String::new(),
+                    stderr: "chown: changing ownership: Operation not permitted\n".to_string(),
+                    exit_code: 1,
+                },
+            ),
+    );
+    let output = remote_write::handler::handle(
+        conn.clone(),
+        input::<RemoteWriteInput>(json!({
+            "server": "fake",
+            "file_path": "deploy.sh",
+            "content": "#!/bin/sh\n",
+            "mode": "755",
+            "owner": "deploy:www-data",
+        })),
+        TimeoutPolicy::default(),
+    )
+    .await;
+    assert_eq!(
+        output,
+        "Successfully wrote to /srv/app/deploy.sh\n\
+         Could not change the owner to deploy:www-data: chown: changing ownership: Operation not permitted"
+    );
+    assert_eq!(
+        conn.commands(),
+        [
+            "chmod 755 '/srv/app/deploy.sh'",
+            "chown 'deploy:www-data' '/srv/app/deploy.sh'"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_write_rejects_bad_mode_before_writing() {
+    let conn = Arc::new(FakeExecutor::new("/srv/app"));
+    for (field, value) in [("mode", "u+x"), ("mode", "75"), ("owner", "-R root")] {
+        let output = remote_write::handler::handle(
+            conn.clone(),
+            input::<RemoteWriteInput>(json!({
+                "server": "fake",
+                "file_path": "x",
+                "content": "",
+                field: value,
+            })),
+            TimeoutPolicy::default(),
+        )
+        .await;
+        let error = as_error(&output);
+        assert_eq!(error.code, ErrorCode::InvalidInput);
+        assert!(error.message.starts_with("invalid"), "{output}");
+    }
+    assert_eq!(conn.file("/srv/app/x"), None);
+}
+
+#[tokio::test]
+async fn test_read_missing_file() {
+    let conn = Arc::new(FakeExecutor::new("/srv/app"));
+    let output = remote_read::handler::handle(
+        conn,
+        input::<RemoteReadInput>(json!({"server": "fake", "file_path": "nope"})),
+    )
+    .await;
+    let error = as_error(&output);
+    assert_eq!(error.code, ErrorCode::FileNotFound);
+    assert!(error.message.starts_with("Error reading file:"));
+}
+
+#[tokio::test]
+async fn test_edit_requires_unique_match() {
+    let conn = Arc::new(FakeExecutor::new("/srv/app").with_file("/etc/app.conf", "a=1\nb=1\n"));
+    let edit = |old: &str, replace_all: bool| {
+        input::<RemoteEditInput>(json!({
+            "server": "fake",
+            "file_path": "/etc/app.conf",
+            "old_string": old,
+            "new_string": "=2",
+            "replace_all": replace_all,
+        }))
+    };
+
+    let output =
+        remote_edit::handler::handle(conn.clone(), edit("=1", false), TimeoutPolicy::default())
+            .await;
+    let error = as_error(&output);
+    assert_eq!(error.code, ErrorCode::InvalidInput);
+    assert!(error.message.starts_with("old_string matches 2 times"));
+    assert_eq!(
+        conn.file("/etc/app.conf").as_deref(),
+        Some(b"a=1\nb=1\n".as_slice())
+    );
+
+    // A failure whose message doesn't start with "Error" is still an error
+    let output =
+        remote_edit::handler::handle(conn.clone(), edit("c=1", true), TimeoutPolicy::default())
+            .await;
+    assert_eq!(
+        as_error(&output),
+        ToolError::new(ErrorCode::InvalidInput, "String 'c=1' not found in file")
+    );
+
+    let output =
+        remote_edit::handler::handle(conn.clone(), edit("=1", true), TimeoutPolicy::default())
+            .await;
+    assert!(output.starts_with("Successfully edited /etc/app.conf"));
+    assert_eq!(
+        conn.file("/etc/app.conf").as_deref(),
+        Some(b"a=2\nb=2\n".as_slice())
+    );
+}
+
+#[tokio::test]
+async fn test_glob_lists_files_below_base_path() {
+    let conn = Arc::new(
+        FakeExecutor::new("/srv/app")
+            .with_file("/srv/app/src/main.rs", "")
+            .with_file("/srv/app/src/tools/mod.rs", "")
+            .with_file("/srv/app/README.md", "")
+            .with_file("/srv/other/lib.rs", ""),
+    );
+    let output = remote_glob::handler::handle(
+        conn,
+        input::<RemoteGlobInput>(json!({"server": "fake", "pattern": "*.rs", "limit": 1})),
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["files"], json!(["src/main.rs"]));
+    assert_eq!(json["total_matches"], 2);
+    assert_eq!(json["truncated"], true);
+    assert_eq!(json["next_offset"], 1);
+}
+
+#[tokio::test]
+async fn test_glob_page_ends_early_when_paths_are_long() {
+    let dir = "d".repeat(200);
+    let conn = (0..1000).fold(FakeExecutor::new("/srv/app"), |conn, i| {
+        conn.with_file(&format!("/srv/app/{dir}/{i:04}.rs"), "")
+    });
+    let output = remote_glob::handler::handle(
+        Arc::new(conn),
+        input::<RemoteGlobInput>(json!({"server": "fake", "pattern": "*.rs"})),
+    )
+    .await;
+    assert!(output.len() <= MAX_INLINE_OUTPUT, "{}", output.len());
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let shown = json["files"].as_array().unwrap().len();
+    assert!(shown > 0 && shown < 1000, "{shown}");
+    assert_eq!(json["total_matches"], 1000);
+    assert_eq!(json["truncated"], true);
+    assert_eq!(json["next_offset"], shown);
+}
+
+#[tokio::test]
+async fn test_whoami_parses_canned_output() {
+    let conn = Arc::new(FakeExecutor::new("~").on_command(
+        "id -un",
+        "USER=deploy\nUID=1000\nGID=1000\nGROUPS=deploy docker\nHOME=/home/deploy\nUMASK=0022\nSUDO=ok\n",
+    ));
+    let output = remote_whoami::handler::handle(
+        conn.clone(),
+        input::<RemoteWhoamiInput>(json!({"server": "fake"})),
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["user"], "deploy");
+    assert_eq!(json["groups"], json!(["deploy", "docker"]));
+    assert_eq!(conn.commands().len(), 1);
+}
+
+#[tokio::test]
+async fn test_df_reports_command_failure() {
+    let conn = Arc::new(FakeExecutor::new("~"));
+    let output = remote_disk::handler::df(
+        conn.clone(),
+        input::<RemoteDfInput>(json!({"server": "fake"})),
+    )
+    .await;
+    let error = as_error(&output);
+    assert_eq!(error.code, ErrorCode::Failed);
+    assert!(error
+        .message
+        .starts_with("Error listing filesystems: fake: no response"));
+    assert!(conn.commands()[0].contains("df -P -k"));
+}
+
+#[tokio::test]
+async fn test_posix_tools_refused_on_powershell() {
+    let conn = Arc::new(FakeExecutor::new("C:/app").with_shell(RemoteShell::Powershell));
+    assert!(conn.posix_required("remote_ps").is_some());
+    let output = remote_process::handler::ps(
+        conn.clone(),
+        input::<RemotePsInput>(json!({"server": "fake"})),
+    )
+    .await;
+    let error = as_error(&output);
+    assert_eq!(error.code, ErrorCode::NotSupported);
+    assert!(error
+        .message
+        .starts_with("remote_ps requires a POSIX shell"));
+    assert!(conn.commands().is_empty());
+}
@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use ssh_hub::hooks::{attach_report, run_local, Hook, HookRun, HookStage, HookTarget};
+use ssh_hub::server_registry::ServerRegistry;
+use ssh_hub::tools::{ErrorCode, ToolError};
+
+#[test]
+fn test_hooks_from_config_and_defaults() {
+    let cfg = ServerRegistry::parse(
+        r#"
+        [[defaults.hooks.pre_exec]]
+        local = "true"
+
+        [servers.web]
+        host = "10.0.0.1"
+        user = "deploy"
+
+        [[servers.web.hooks.pre_sync_push]]
+        local = "npm run build"
+
+        [[servers.web.hooks.post_sync_push]]
+        remote = "sudo systemctl reload app"
+        timeout_secs = 30
+
+        [servers.db]
+        host = "10.0.0.2"
+        user = "deploy"
+        "#,
+    )
+    .unwrap();
+
+    let web = cfg.get("web").unwrap().hooks.clone().unwrap();
+    assert_eq!(
+        web.pre(HookStage::SyncPush)[0].target(),
+        Ok(HookTarget::Local("npm run build"))
+    );
+    let reload = &web.post(HookStage::SyncPush)[0];
+    assert_eq!(
+        reload.target(),
+        Ok(HookTarget::Remote("sudo systemctl reload app"))
+    );
+    assert_eq!(reload.timeout(), Duration::from_secs(30));
+    // A server's own hooks replace the defaults as a whole
+    assert!(web.pre(HookStage::Exec).is_empty());
+    assert!(web.post(HookStage::SyncPull).is_empty());
+
+    let db = cfg.get("db").unwrap().hooks.clone().unwrap();
+    assert_eq!(db.pre(HookStage::Exec).len(), 1);
+    assert_eq!(db.pre(HookStage::Exec)[0].timeout(), Duration::from_mins(5));
+}
+
+#[test]
+fn test_hook_needs_exactly_one_location() {
+    let both = Hook {
+        local: Some("make".to_string()),
+        remote: Some("make".to_string()),
+        timeout_secs: None,
+    };
+    assert!(both.target().unwrap_err().contains("misconfigured"));
+    assert!(Hook::default().target().is_err());
+
+    let run = HookRun::failed(
+        "pre_exec",
+        "unknown",
+        "",
+        ErrorCode::InvalidInput,
+        both.target().unwrap_err(),
+    );
+    let refusal = ToolError::from_json(&run.refusal(HookStage::Exec)).unwrap();
+    assert_eq!(refusal.code, ErrorCode::InvalidInput);
+    assert_eq!(
+        refusal.message,
+        "the pre_exec hook failed (misconfigured hook: set exactly one of local and remote), so remote_bash did not run"
+    );
+}
+
+#[tokio::test]
+async fn test_local_hooks_report_success_and_failure() {
+    let ok = run_local("pre_sync_push", "echo built", Duration::from_secs(10)).await;
+    assert!(ok.succeeded(), "{ok:?}");
+    assert_eq!(ok.exit_code, Some(0));
+    assert!(ok.output.is_empty(), "output is kept for failures only");
+
+    let failed = run_local(
+        "pre_sync_push",
+        "echo compiling; echo 'missing semicolon' >&2; exit 2",
+        Duration::from_secs(10),
+    )
+    .await;
+    assert!(!failed.succeeded());
+    assert_eq!(failed.exit_code, Some(2));
+    assert_eq!(failed.output, "compiling\nmissing semicolon");
+    let refusal = ToolError::from_json(&failed.refusal(HookStage::SyncPush)).unwrap();
+    assert_eq!(refusal.code, ErrorCode::Failed);
+    assert_eq!(
+        refusal.message,
+        "the pre_sync_push hook `echo compiling; echo 'missing semicolon' >&2; exit 2` \
+         failed (exit code 2), so sync_push did not run:\ncompiling\nmissing semicolon"
+    );
+}
+
+#[tokio::test]
+async fn test_local_hook_timeout() {
+    let run = run_local("pre_exec", "sleep 5", Duration::from_millis(200)).await;
+    assert!(!run.succeeded());
+    assert_eq!(run.exit_code, None);
+    let refusal = ToolError::from_json(&run.refusal(HookStage::Exec)).unwrap();
+    assert_eq!(refusal.code, ErrorCode::Timeout);
+    assert!(refusal.message.contains("timed out"), "{refusal:?}");
+}
+
+#[tokio::test]
+async fn test_failed_hook_output_is_trimmed_from_the_start() {
+    let run = run_local(
+        "post_exec",
+        "i=0; while [ $i -lt 1000 ]; do echo line$i; i=$((i+1)); done; exit 1",
+        Duration::from_secs(10),
+    )
+    .await;
+    assert!(run.output.starts_with("..."));
+    assert!(run.output.ends_with("line999"));
+    assert!(run.output.len() <= 2003);
+}
+
+#[tokio::test]
+async fn test_report_is_added_to_json_output() {
+    let runs = vec![
+        run_local("pre_sync_push", "true", Duration::from_secs(10)).await,
+        HookRun::failed(
+            "post_sync_push",
+            "remote",
+            "systemctl reload app",
+            ErrorCode::ConnectionFailed,
+            "connection refused".to_string(),
+        ),
+    ];
+    let output = attach_report(r#"{"files_pushed": 3}"#.to_string(), &runs);
+    let value: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(value["files_pushed"], 3);
+    let hooks = value["hooks"].as_array().unwrap();
+    assert_eq!(hooks.len(), 2);
+    assert_eq!(hooks[0]["hook"], "pre_sync_push");
+    assert_eq!(hooks[0]["location"], "local");
+    assert_eq!(hooks[0]["exit_code"], 0);
+    assert_eq!(
+        hooks[1],
+        json!({
+            "hook": "post_sync_push",
+            "location": "remote",
+            "command": "systemctl reload app",
+            "elapsed_ms": 0,
+            "error": "connection refused",
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_report_is_appended_to_text_output() {
+    let runs = vec![run_local("pre_exec", "exit 3", Duration::from_secs(10)).await];
+    let output = attach_report("Error: something".to_string(), &runs);
+    assert!(
+        output.starts_with("Error: something\n\nHooks:\n  pre_exec (local) `exit 3`: exit 3 in ")
+    );
+
+    assert_eq!(attach_report("plain".to_string(), &[]), "plain");
+}
@@ -0,0 +1,191 @@
+use russh::keys::PublicKey;
+use ssh_hub::connection::{forget_known_host_lines, new_key_action, NewHostKey, NewKeyAction};
+use ssh_hub::server_registry::{ServerRegistry, StrictHostKey};
+
+const KEY: &str =
+    "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIPcaQd2PbkodE6p2y/HlROBt5rKZUlMk5/tIUXa3bj6l";
+const FINGERPRINT: &str = "SHA256:GfqSSDBjZ9pGchi1UlNgT6ueHwPqdePuY5Xc+XRqZrI";
+
+fn new_key(server_name: Option<&str>) -> NewHostKey {
+    NewHostKey::new(
+        "build.example.com".to_string(),
+        2222,
+        server_name.map(str::to_string),
+        PublicKey::from_openssh(KEY).unwrap(),
+    )
+}
+
+// --- new_key_action ---
+
+#[test]
+fn ask_asks_when_the_client_can() {
+    assert_eq!(new_key_action(StrictHostKey::Ask, true), NewKeyAction::Ask);
+}
+
+#[test]
+fn ask_learns_when_no_one_can_be_asked() {
+    assert_eq!(
+        new_key_action(StrictHostKey::Ask, false),
+        NewKeyAction::Learn
+    );
+}
+
+#[test]
+fn accept_new_never_asks() {
+    assert_eq!(
+        new_key_action(StrictHostKey::AcceptNew, true),
+        NewKeyAction::Learn
+    );
+}
+
+#[test]
+fn yes_refuses_even_when_the_client_could_ask() {
+    assert_eq!(
+        new_key_action(StrictHostKey::Yes, true),
+        NewKeyAction::Refuse
+    );
+    assert_eq!(
+        new_key_action(StrictHostKey::Yes, false),
+        NewKeyAction::Refuse
+    );
+}
+
+// --- NewHostKey ---
+
+#[test]
+fn fingerprint_matches_ssh_keygen() {
+    let key = new_key(None);
+    assert_eq!(key.fingerprint(), FINGERPRINT);
+    assert_eq!(key.algorithm(), "ssh-ed25519");
+}
+
+#[test]
+fn prompt_names_server_address_and_fingerprint() {
+    let prompt = new_key(Some("build")).prompt();
+    assert!(prompt.contains("host 'build' (build.example.com:2222)"));
+    assert!(prompt.contains(&format!("ssh-ed25519 key fingerprint is {FINGERPRINT}.")));
+}
+
+#[test]
+fn prompt_without_server_name() {
+    let prompt = new_key(None).prompt();
+    assert!(prompt.contains("host (build.example.com:2222)"));
+}
+
+#[test]
+fn refusal_explains_how_to_add_the_key() {
+    let refusal = new_key(None).refusal();
+    assert!(refusal.contains(FINGERPRINT));
+    assert!(refusal.contains("ssh-keyscan -p 2222 build.example.com >> ~/.ssh/known_hosts"));
+}
+
+// --- config ---
+
+#[test]
+fn strict_host_key_defaults_to_ask() {
+    let config: ServerRegistry = toml::from_str(
+        r#"
+[servers.build]
+host = "build.example.com"
+user = "deploy"
+"#,
+    )
+    .unwrap();
+    let entry = config.get("build").unwrap();
+    assert_eq!(entry.strict_host_key, None);
+    assert_eq!(
+        entry.strict_host_key.unwrap_or_default(),
+        StrictHostKey::Ask
+    );
+}
+
+#[test]
+fn strict_host_key_parses_kebab_case() {
+    let config: ServerRegistry = toml::from_str(
+        r#"
+[servers.build]
+host = "build.example.com"
+user = "deploy"
+strict_host_key = "accept-new"
+
+[servers.prod]
+host = "prod.example.com"
+user = "deploy"
+strict_host_key = "yes"
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.get("build").unwrap().strict_host_key,
+        Some(StrictHostKey::AcceptNew)
+    );
+    assert_eq!(
+        config.get("prod").unwrap().strict_host_key,
+        Some(StrictHostKey::Yes)
+    );
+}
+
+#[test]
+fn params_from_config_carries_strict_host_key() {
+    let config: ServerRegistry = toml::from_str(
+        r#"
+[servers.prod]
+host = "prod.example.com"
+user = "deploy"
+strict_host_key = "yes"
+"#,
+    )
+    .unwrap();
+    let params = ssh_hub::cli::params_from_config("prod", config.get("prod").unwrap());
+    assert_eq!(params.strict_host_key, StrictHostKey::Yes);
+}
+
+#[test]
+fn params_from_config_inherits_agent_socket() {
+    let config = ServerRegistry::parse(
+        r#"
+[defaults]
+agent_socket = "~/.1password/agent.sock"
+
+[servers.prod]
+host = "prod.example.com"
+user = "deploy"
+
+[servers.win]
+host = "win.example.com"
+user = "admin"
+agent_socket = "pageant"
+"#,
+    )
+    .unwrap();
+    let prod = ssh_hub::cli::params_from_config("prod", config.get("prod").unwrap());
+    let socket = prod.agent_socket.unwrap();
+    assert!(!socket.starts_with('~'), "tilde expanded: {socket}");
+    assert!(socket.ends_with("/.1password/agent.sock"));
+    let win = ssh_hub::cli::params_from_config("win", config.get("win").unwrap());
+    assert_eq!(win.agent_socket.as_deref(), Some("pageant"));
+}
+
+#[test]
+fn forgets_only_plain_lines_for_the_host() {
+    let contents = format!(
+        "build.example.com {KEY}\n\
+         [build.example.com]:2222 {KEY}\n\
+         build.example.com,10.0.0.5 {KEY}\n\
+         |1|c2FsdA==|aGFzaA== {KEY}\n\
+         other.example.com {KEY}"
+    );
+    let (kept, dropped) = forget_known_host_lines(&contents, "build.example.com", 2222);
+    assert_eq!(dropped, 1);
+    assert!(!kept.contains("[build.example.com]:2222"));
+    assert!(kept.ends_with(&format!("other.example.com {KEY}")));
+
+    let (kept, dropped) = forget_known_host_lines(&kept, "build.example.com", 22);
+    assert_eq!(dropped, 1);
+    assert_eq!(
+        kept,
+        format!(
+            "build.example.com,10.0.0.5 {KEY}\n|1|c2FsdA==|aGFzaA== {KEY}\nother.example.com {KEY}"
+        )
+    );
+}
@@ -0,0 +1,114 @@
+use ssh_hub::keychain::{
+    delete_args, lookup_args, security_quote, store_invocation, Backend, Secret, KEY_SERVICE,
+    SERVICE,
+};
+
+#[test]
+fn test_secret_tool_store_passes_password_on_stdin() {
+    let (args, stdin) = store_invocation(
+        Backend::SecretTool,
+        Secret::SudoPassword,
+        "web1",
+        "s3cret pass",
+    );
+    assert_eq!(
+        args,
+        vec![
+            "store",
+            "--label",
+            "ssh-hub sudo password for web1",
+            "service",
+            SERVICE,
+            "server",
+            "web1"
+        ]
+    );
+    assert!(!args.iter().any(|a| a.contains("s3cret")));
+    assert_eq!(stdin, "s3cret pass");
+}
+
+#[test]
+fn test_security_store_uses_interactive_mode() {
+    let (args, stdin) = store_invocation(
+        Backend::Security,
+        Secret::SudoPassword,
+        "web1",
+        r#"pa"ss\word"#,
+    );
+    assert_eq!(args, vec!["-i"]);
+    assert_eq!(
+        stdin,
+        format!("add-generic-password -U -s \"{SERVICE}\" -a \"web1\" -w \"pa\\\"ss\\\\word\"\n")
+    );
+}
+
+#[test]
+fn test_lookup_and_delete_args() {
+    assert_eq!(
+        lookup_args(Backend::SecretTool, Secret::SudoPassword, "db"),
+        vec!["lookup", "service", SERVICE, "server", "db"]
+    );
+    assert_eq!(
+        lookup_args(Backend::Security, Secret::SudoPassword, "db"),
+        vec!["find-generic-password", "-s", SERVICE, "-a", "db", "-w"]
+    );
+    assert_eq!(
+        delete_args(Backend::SecretTool, Secret::SudoPassword, "db"),
+        vec!["clear", "service", SERVICE, "server", "db"]
+    );
+    assert_eq!(
+        delete_args(Backend::Security, Secret::SudoPassword, "db"),
+        vec!["delete-generic-password", "-s", SERVICE, "-a", "db"]
+    );
+}
+
+#[test]
+fn test_security_quote() {
+    assert_eq!(security_quote("plain"), "\"plain\"");
+    assert_eq!(security_quote("with space"), "\"with space\"");
+    assert_eq!(security_quote(r#"a"b\c"#), r#""a\"b\\c""#);
+}
+
+#[test]
+fn test_key_passphrases_are_stored_under_the_key_path() {
+    let (args, stdin) = store_invocation(
+        Backend::SecretTool,
+        Secret::KeyPassphrase,
+        "/home/me/.ssh/id_ed25519",
+        "correct horse",
+    );
+    assert_eq!(
+        args,
+        vec![
+            "store",
+            "--label",
+            "ssh-hub passphrase for /home/me/.ssh/id_ed25519",
+            "service",
+            KEY_SERVICE,
+            "key",
+            "/home/me/.ssh/id_ed25519"
+        ]
+    );
+    assert_eq!(stdin, "correct horse");
+    assert_eq!(
+        lookup_args(Backend::Security, Secret::KeyPassphrase, "/k"),
+        vec!["find-generic-password", "-s", KEY_SERVICE, "-a", "/k", "-w"]
+    );
+    assert_eq!(
+        delete_args(Backend::SecretTool, Secret::KeyPassphrase, "/k"),
+        vec!["clear", "service", KEY_SERVICE, "key", "/k"]
+    );
+}
+
+#[test]
+fn test_profiles_have_their_own_services() {
+    assert_eq!(Secret::SudoPassword.service_name(None), SERVICE);
+    assert_eq!(
+        Secret::SudoPassword.service_name(Some("work")),
+        "ssh-hub-sudo-work"
+    );
+    assert_eq!(
+        Secret::KeyPassphrase.service_name(Some("work")),
+        "ssh-hub-key-work"
+    );
+}
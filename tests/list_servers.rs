@@ -0,0 +1,377 @@
+use ssh_hub::connection::ConnectionPool;
+use ssh_hub::server_registry::{ServerChanges, ServerEntry};
+use ssh_hub::tools::list_servers::handler::{handle, probe, ProbeCache};
+use ssh_hub::tools::list_servers::{ListServersInput, ProbeResult, ProbeStatus};
+
+fn server(host: &str, port: u16) -> ServerEntry {
+    ServerEntry {
+        host: host.to_string(),
+        user: "deploy".to_string(),
+        port,
+        ..ServerEntry::default()
+    }
+}
+
+fn tagged(host: &str, tags: &[&str]) -> ServerEntry {
+    ServerEntry {
+        tags: tags.iter().map(ToString::to_string).collect(),
+        ..server(host, 22)
+    }
+}
+
+const REACHABLE: ProbeResult = ProbeResult {
+    status: ProbeStatus::Reachable,
+    latency_ms: Some(4),
+    cached_secs: None,
+};
+
+#[tokio::test]
+async fn test_probe_reachable() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let result = probe("127.0.0.1", port).await;
+    assert_eq!(result.status, ProbeStatus::Reachable);
+    assert!(result.latency_ms.is_some());
+    assert_eq!(result.cached_secs, None);
+}
+
+#[tokio::test]
+async fn test_probe_closed_port_is_unreachable() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+    let result = probe("127.0.0.1", port).await;
+    assert_eq!(result.status, ProbeStatus::Unreachable);
+    assert_eq!(result.latency_ms, None);
+}
+
+#[tokio::test]
+async fn test_probe_unresolvable_name() {
+    let result = probe("ssh-hub-test.invalid", 22).await;
+    assert!(matches!(
+        result.status,
+        ProbeStatus::DnsFailed | ProbeStatus::DnsTimeout
+    ));
+}
+
+#[tokio::test]
+async fn test_cache_hit_reports_age() {
+    let cache = ProbeCache::new();
+    assert_eq!(cache.lookup("10.0.0.1", 22).await, None);
+    cache.record("10.0.0.1", 22, REACHABLE).await;
+    let hit = cache.lookup("10.0.0.1", 22).await.unwrap();
+    assert_eq!(hit.status, ProbeStatus::Reachable);
+    assert_eq!(hit.latency_ms, Some(4));
+    assert_eq!(hit.cached_secs, Some(0));
+    assert_eq!(cache.lookup("10.0.0.1", 2222).await, None);
+}
+
+#[tokio::test]
+async fn test_skip_probe_lists_without_probing() {
+    let cache = ProbeCache::new();
+    cache.record("10.0.0.1", 22, REACHABLE).await;
+    let servers = vec![
+        ("web".to_string(), server("10.0.0.2", 22)),
+        ("api".to_string(), server("10.0.0.1", 22)),
+    ];
+    let input = ListServersInput {
+        skip_probe: Some(true),
+        tags: None,
+        ..ListServersInput::default()
+    };
+    let output = handle(
+        servers,
+        &ConnectionPool::new(),
+        &cache,
+        input,
+        ServerChanges::default(),
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let servers = json["servers"].as_array().unwrap();
+    assert_eq!(servers[0]["name"], "api");
+    assert_eq!(servers[0]["target"], "deploy@10.0.0.1:22");
+    assert_eq!(servers[0]["connected"], false);
+    // A fresh cached result is still shown
+    assert_eq!(servers[0]["probe"]["status"], "reachable");
+    assert_eq!(servers[0]["probe"]["cached_secs"], 0);
+    assert_eq!(servers[1]["name"], "web");
+    assert!(servers[1].get("probe").is_none());
+}
+
+#[tokio::test]
+async fn test_probe_results_are_cached() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let cache = ProbeCache::new();
+    let servers = vec![("local".to_string(), server("127.0.0.1", port))];
+    let output = handle(
+        servers.clone(),
+        &ConnectionPool::new(),
+        &cache,
+        ListServersInput::default(),
+        ServerChanges::default(),
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["servers"][0]["probe"]["status"], "reachable");
+    assert!(json["servers"][0]["probe"].get("cached_secs").is_none());
+
+    // The port is gone, but the second call is answered from the cache
+    drop(listener);
+    let output = handle(
+        servers,
+        &ConnectionPool::new(),
+        &cache,
+        ListServersInput::default(),
+        ServerChanges::default(),
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["servers"][0]["probe"]["status"], "reachable");
+    assert_eq!(json["servers"][0]["probe"]["cached_secs"], 0);
+}
+
+#[tokio::test]
+async fn test_no_servers_configured() {
+    let output = handle(
+        Vec::new(),
+        &ConnectionPool::new(),
+        &ProbeCache::new(),
+        ListServersInput::default(),
+        ServerChanges::default(),
+    )
+    .await;
+    assert!(output.starts_with("No servers are configured"));
+}
+
+#[tokio::test]
+async fn test_tag_filter_keeps_servers_with_every_tag() {
+    let servers = vec![
+        (
+            "gpu1".to_string(),
+            tagged("10.0.0.1", &["gpu", "env=staging"]),
+        ),
+        ("gpu2".to_string(), tagged("10.0.0.2", &["gpu", "env=prod"])),
+        ("web".to_string(), tagged("10.0.0.3", &["env=staging"])),
+    ];
+    let input = ListServersInput {
+        skip_probe: Some(true),
+        tags: Some(vec!["gpu".to_string(), "env=staging".to_string()]),
+        ..ListServersInput::default()
+    };
+    let output = handle(
+        servers,
+        &ConnectionPool::new(),
+        &ProbeCache::new(),
+        input,
+        ServerChanges::default(),
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let servers = json["servers"].as_array().unwrap();
+    assert_eq!(servers.len(), 1);
+    assert_eq!(servers[0]["name"], "gpu1");
+    assert_eq!(
+        servers[0]["tags"],
+        serde_json::json!(["gpu", "env=staging"])
+    );
+}
+
+#[tokio::test]
+async fn test_tag_filter_without_matches_lists_tags_in_use() {
+    let servers = vec![
+        ("a".to_string(), tagged("10.0.0.1", &["web", "env=prod"])),
+        ("b".to_string(), tagged("10.0.0.2", &["web"])),
+    ];
+    let input = ListServersInput {
+        skip_probe: Some(true),
+        tags: Some(vec!["gpu".to_string()]),
+        ..ListServersInput::default()
+    };
+    let output = handle(
+        servers,
+        &ConnectionPool::new(),
+        &ProbeCache::new(),
+        input,
+        ServerChanges::default(),
+    )
+    .await;
+    assert_eq!(
+        output,
+        "No server is tagged gpu. Tags in use: env=prod, web."
+    );
+
+    let servers = vec![("a".to_string(), server("10.0.0.1", 22))];
+    let input = ListServersInput {
+        skip_probe: Some(true),
+        tags: Some(vec!["gpu".to_string()]),
+        ..ListServersInput::default()
+    };
+    let output = handle(
+        servers,
+        &ConnectionPool::new(),
+        &ProbeCache::new(),
+        input,
+        ServerChanges::default(),
+    )
+    .await;
+    assert_eq!(
+        output,
+        "No server is tagged gpu. No configured server has tags."
+    );
+}
+
+#[tokio::test]
+async fn test_config_changes_reported_with_listing() {
+    let servers = vec![("new".to_string(), server("10.0.0.1", 22))];
+    let input = ListServersInput {
+        skip_probe: Some(true),
+        ..ListServersInput::default()
+    };
+    let changes = ServerChanges {
+        added: vec!["new".to_string()],
+        removed: vec!["old".to_string()],
+        changed: Vec::new(),
+    };
+    let output = handle(
+        servers.clone(),
+        &ConnectionPool::new(),
+        &ProbeCache::new(),
+        input,
+        changes,
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(
+        json["config_changes"],
+        serde_json::json!({"added": ["new"], "removed": ["old"]})
+    );
+
+    let input = ListServersInput {
+        skip_probe: Some(true),
+        ..ListServersInput::default()
+    };
+    let output = handle(
+        servers,
+        &ConnectionPool::new(),
+        &ProbeCache::new(),
+        input,
+        ServerChanges::default(),
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert!(json.get("config_changes").is_none());
+}
+
+#[tokio::test]
+async fn test_no_servers_configured_names_removed_servers() {
+    let changes = ServerChanges {
+        removed: vec!["web".to_string()],
+        ..ServerChanges::default()
+    };
+    let output = handle(
+        Vec::new(),
+        &ConnectionPool::new(),
+        &ProbeCache::new(),
+        ListServersInput::default(),
+        changes,
+    )
+    .await;
+    assert!(output.ends_with("Removed since the last call: web."));
+}
+
+#[tokio::test]
+async fn test_name_pattern_and_connected_only_filters() {
+    let servers = vec![
+        ("web-1".to_string(), server("10.0.0.1", 22)),
+        ("web-2".to_string(), server("10.0.0.2", 22)),
+        ("db".to_string(), server("10.0.0.3", 22)),
+    ];
+    let input = ListServersInput {
+        skip_probe: Some(true),
+        name_pattern: Some("web-*".to_string()),
+        ..ListServersInput::default()
+    };
+    let output = handle(
+        servers.clone(),
+        &ConnectionPool::new(),
+        &ProbeCache::new(),
+        input,
+        ServerChanges::default(),
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let names: Vec<&str> = json["servers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["web-1", "web-2"]);
+
+    let input = ListServersInput {
+        name_pattern: Some("cache-*".to_string()),
+        ..ListServersInput::default()
+    };
+    let output = handle(
+        servers.clone(),
+        &ConnectionPool::new(),
+        &ProbeCache::new(),
+        input,
+        ServerChanges::default(),
+    )
+    .await;
+    assert_eq!(output, "No configured server matches 'cache-*'.");
+
+    let input = ListServersInput {
+        connected_only: Some(true),
+        ..ListServersInput::default()
+    };
+    let output = handle(
+        servers,
+        &ConnectionPool::new(),
+        &ProbeCache::new(),
+        input,
+        ServerChanges::default(),
+    )
+    .await;
+    assert_eq!(
+        output,
+        "No listed server is connected. Tools connect on first use."
+    );
+}
+
+#[tokio::test]
+async fn test_listing_reports_auth_and_last_connect_error() {
+    let pool = ConnectionPool::new();
+    pool.record_error("web", "Failed to connect to SSH server: Connection refused")
+        .await;
+    let servers = vec![
+        ("web".to_string(), server("10.0.0.1", 22)),
+        ("db".to_string(), server("10.0.0.2", 22)),
+    ];
+    let input = ListServersInput {
+        skip_probe: Some(true),
+        ..ListServersInput::default()
+    };
+    let output = handle(
+        servers,
+        &pool,
+        &ProbeCache::new(),
+        input,
+        ServerChanges::default(),
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let db = &json["servers"][0];
+    assert_eq!(db["auth"], "auto");
+    assert!(db.get("last_error").is_none());
+    assert!(db.get("connection").is_none());
+    let web = &json["servers"][1];
+    assert_eq!(
+        web["last_error"]["message"],
+        "Failed to connect to SSH server: Connection refused"
+    );
+    assert_eq!(web["last_error"]["age_secs"], 0);
+}
@@ -0,0 +1,74 @@
+use serde_json::json;
+use ssh_hub::server_registry::ServerRegistry;
+use ssh_hub::tools::local_bash::handler::handle;
+use ssh_hub::tools::{ErrorCode, LocalBashInput, ToolError};
+
+fn input(value: serde_json::Value) -> LocalBashInput {
+    serde_json::from_value(value).unwrap()
+}
+
+async fn run(value: serde_json::Value) -> serde_json::Value {
+    let output = handle(input(value), true).await;
+    serde_json::from_str(&output).unwrap_or_else(|e| panic!("{e}: {output}"))
+}
+
+#[test]
+fn test_allow_local_is_off_by_default() {
+    let config = ServerRegistry::parse("").unwrap();
+    assert!(!config.allow_local);
+    let config = ServerRegistry::parse("allow_local = true\n").unwrap();
+    assert!(config.allow_local);
+    assert!(config.to_toml().unwrap().contains("allow_local = true"));
+}
+
+#[tokio::test]
+async fn test_refused_unless_allowed() {
+    let output = handle(input(json!({"command": "echo hi"})), false).await;
+    let error = ToolError::from_json(&output).unwrap();
+    assert_eq!(error.code, ErrorCode::NotSupported);
+    assert!(
+        error.message.starts_with("local_bash is disabled"),
+        "{output}"
+    );
+}
+
+#[tokio::test]
+async fn test_runs_command_in_cwd() {
+    let dir = tempfile::tempdir().unwrap();
+    let json = run(json!({
+        "command": "pwd; echo oops >&2; exit 3",
+        "cwd": dir.path().to_str().unwrap(),
+    }))
+    .await;
+    let pwd = std::fs::canonicalize(dir.path()).unwrap();
+    assert_eq!(
+        json["stdout"].as_str().unwrap().trim(),
+        pwd.to_str().unwrap()
+    );
+    assert_eq!(json["stderr"], "oops\n");
+    assert_eq!(json["exit_code"], 3);
+}
+
+#[tokio::test]
+async fn test_times_out() {
+    let output = handle(input(json!({"command": "sleep 5", "timeout": 100})), true).await;
+    assert_eq!(
+        ToolError::from_json(&output),
+        Some(ToolError::new(
+            ErrorCode::Timeout,
+            "Command timed out after 100ms"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn test_large_output_is_summarized() {
+    let json = run(json!({"command": "seq 1 100000"})).await;
+    let stdout = json["stdout"].as_str().unwrap();
+    assert!(
+        stdout.starts_with("[Output too large for context"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Full output saved to: "));
+    assert!(stdout.len() < 128 * 1024);
+}
@@ -0,0 +1,138 @@
+use std::io::Write;
+use std::path::Path;
+
+use ssh_hub::server_registry::{LoggingConfig, ServerRegistry};
+use ssh_hub::utils::log_file::{
+    dated_path, default_log_path, expired_logs, format_day, RollingFile, DEFAULT_KEEP_DAYS,
+};
+
+fn names(list: &[&str]) -> Vec<String> {
+    list.iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn test_format_day() {
+    assert_eq!(format_day(0), "1970-01-01");
+    assert_eq!(format_day(11_016), "2000-02-29");
+    assert_eq!(format_day(20_742), "2026-10-16");
+}
+
+#[test]
+fn test_dated_path() {
+    assert_eq!(
+        dated_path(Path::new("/var/log/ssh-hub.log"), 20_742),
+        Path::new("/var/log/ssh-hub.log.2026-10-16")
+    );
+}
+
+#[test]
+fn test_expired_logs_keeps_newest() {
+    let files = names(&[
+        "ssh-hub.log.2026-10-14",
+        "ssh-hub.log.2026-10-16",
+        "ssh-hub.log.2026-10-15",
+        "ssh-hub.log.2026-09-30",
+    ]);
+    assert_eq!(
+        expired_logs(&files, "ssh-hub.log", 2),
+        ["ssh-hub.log.2026-09-30", "ssh-hub.log.2026-10-14"]
+    );
+    assert!(expired_logs(&files, "ssh-hub.log", 10).is_empty());
+}
+
+#[test]
+fn test_expired_logs_ignores_other_files() {
+    let files = names(&[
+        "ssh-hub.log",
+        "ssh-hub.log.old",
+        "ssh-hub.log.2026-10-1x",
+        "other.log.2020-01-01",
+        "ssh-hub.log.2020-01-01",
+    ]);
+    assert_eq!(
+        expired_logs(&files, "ssh-hub.log", 0),
+        ["ssh-hub.log.2020-01-01"]
+    );
+}
+
+#[test]
+fn test_rolling_file_writes_and_prunes() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join("logs").join("ssh-hub.log");
+    std::fs::create_dir_all(base.parent().unwrap()).unwrap();
+    for old in [
+        "ssh-hub.log.2001-01-01",
+        "ssh-hub.log.2001-01-02",
+        "notes.txt",
+    ] {
+        std::fs::write(base.parent().unwrap().join(old), "old").unwrap();
+    }
+
+    let mut file = RollingFile::open(base.clone(), 2).unwrap();
+    writeln!(file, "hello").unwrap();
+    file.flush().unwrap();
+
+    let current = file.current_path();
+    assert!(current
+        .to_string_lossy()
+        .starts_with(&*base.to_string_lossy()));
+    assert_eq!(std::fs::read_to_string(&current).unwrap(), "hello\n");
+
+    let mut remaining: Vec<String> = std::fs::read_dir(base.parent().unwrap())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    remaining.sort();
+    assert_eq!(remaining.len(), 3);
+    assert_eq!(remaining[0], "notes.txt");
+    assert_eq!(remaining[1], "ssh-hub.log.2001-01-02");
+}
+
+#[test]
+fn test_logging_config_defaults() {
+    let config: ServerRegistry = toml::from_str("").unwrap();
+    assert!(config.logging.is_none());
+
+    let logging = LoggingConfig::default();
+    assert!(logging.enabled);
+    assert_eq!(logging.path(), default_log_path());
+    assert_eq!(logging.keep_days(), DEFAULT_KEEP_DAYS);
+}
+
+#[test]
+fn test_logging_config_parse() {
+    let config: ServerRegistry = toml::from_str(
+        r#"
+[logging]
+file = "/var/log/ssh-hub/mcp.log"
+keep_days = 0
+"#,
+    )
+    .unwrap();
+    let logging = config.logging.unwrap();
+    assert!(logging.enabled);
+    assert_eq!(
+        logging.path().as_deref(),
+        Some(Path::new("/var/log/ssh-hub/mcp.log"))
+    );
+    assert_eq!(logging.keep_days(), 1);
+
+    let disabled: ServerRegistry = toml::from_str("[logging]\nenabled = false\n").unwrap();
+    assert_eq!(disabled.logging.unwrap().path(), None);
+}
+
+#[test]
+fn test_logging_config_roundtrip() {
+    let config: ServerRegistry =
+        toml::from_str("[logging]\nfile = \"~/logs/ssh-hub.log\"\n").unwrap();
+    let serialized = toml::to_string_pretty(&config).unwrap();
+    assert!(serialized.contains("[logging]"));
+    let back: ServerRegistry = toml::from_str(&serialized).unwrap();
+    assert_eq!(back.logging, config.logging);
+    if let Some(home) = dirs::home_dir() {
+        assert_eq!(
+            back.logging.unwrap().path(),
+            Some(home.join("logs/ssh-hub.log"))
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use ssh_hub::maintenance::{
+    enforce, parse_duration, Cron, MaintenanceConfig, OutsideWindow, Schedule,
+};
+use ssh_hub::server_registry::ServerRegistry;
+use ssh_hub::tools::ErrorCode;
+
+const CONFIG: &str = r#"
+[servers.prod]
+host = "prod.example.com"
+user = "deploy"
+
+[servers.prod.maintenance]
+outside_window = "confirm"
+
+[[servers.prod.maintenance.windows]]
+start = "0 22 * * mon-fri"
+duration = "6h"
+"#;
+
+/// 2026-10-16 (a Friday) at `hh:mm` UTC.
+fn friday(hh: u64, mm: u64) -> u64 {
+    1_792_108_800 + hh * 3_600 + mm * 60
+}
+
+fn maintenance() -> MaintenanceConfig {
+    let cfg: ServerRegistry = toml::from_str(CONFIG).unwrap();
+    cfg.get("prod").unwrap().maintenance.clone().unwrap()
+}
+
+fn minute(unix: u64) -> u64 {
+    unix / 60
+}
+
+#[test]
+fn test_cron_fields() {
+    let cron = Cron::parse("*/15 9-17 * * 1-5").unwrap();
+    assert!(cron.matches(minute(friday(9, 45))));
+    assert!(!cron.matches(minute(friday(9, 50))));
+    assert!(!cron.matches(minute(friday(18, 0))));
+    // Saturday
+    assert!(!cron.matches(minute(friday(33, 0))));
+
+    let sunday = Cron::parse("30 23 * * 7").unwrap();
+    assert_eq!(sunday, Cron::parse("30 23 * * sun").unwrap());
+    assert!(sunday.matches(minute(friday(2 * 24 + 23, 30))));
+
+    let monthly = Cron::parse("0 0 16 oct *").unwrap();
+    assert!(monthly.matches(minute(friday(0, 0))));
+}
+
+#[test]
+fn test_cron_day_fields_are_ored() {
+    // The 1st of the month or any Friday.
+    let cron = Cron::parse("0 12 1 * fri").unwrap();
+    assert!(cron.matches(minute(friday(12, 0))));
+    assert!(!cron.matches(minute(friday(24 + 12, 0))));
+}
+
+#[test]
+fn test_cron_rejects_invalid() {
+    assert!(Cron::parse("* * * *")
+        .unwrap_err()
+        .contains("5 cron fields"));
+    assert!(Cron::parse("60 * * * *").unwrap_err().contains("outside"));
+    assert!(Cron::parse("* * * * funday").is_err());
+    assert!(Cron::parse("*/0 * * * *").is_err());
+    assert!(Cron::parse("5-1 * * * *").unwrap_err().contains("reversed"));
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("90m"), Ok(90));
+    assert_eq!(parse_duration("1h30m"), Ok(90));
+    assert_eq!(parse_duration("2d"), Ok(2_880));
+    assert!(parse_duration("6").is_err());
+    assert!(parse_duration("0h").is_err());
+    assert!(parse_duration("8d").is_err());
+    assert!(parse_duration("1w").is_err());
+}
+
+#[test]
+fn test_window_spans_midnight() {
+    let schedule = Schedule::parse(&maintenance()).unwrap();
+    assert!(!schedule.is_open(friday(21, 59)));
+    assert!(schedule.is_open(friday(22, 0)));
+    assert!(schedule.is_open(friday(24 + 3, 59)));
+    assert!(!schedule.is_open(friday(24 + 4, 0)));
+    assert_eq!(
+        schedule.next_opening(friday(12, 0)).as_deref(),
+        Some("2026-10-16 22:00 UTC")
+    );
+    // Saturday noon: the next weekday window is Monday night.
+    assert_eq!(
+        schedule.next_opening(friday(36, 0)).as_deref(),
+        Some("2026-10-19 22:00 UTC")
+    );
+}
+
+#[test]
+fn test_timezone_offset() {
+    let mut config = maintenance();
+    config.timezone = Some("+02:00".to_string());
+    let schedule = Schedule::parse(&config).unwrap();
+    // 22:00 at +02:00 is 20:00 UTC.
+    assert!(schedule.is_open(friday(20, 0)));
+    assert!(!schedule.is_open(friday(2, 0) + 24 * 3_600));
+    assert_eq!(
+        schedule.next_opening(friday(12, 0)).as_deref(),
+        Some("2026-10-16 22:00 +02:00")
+    );
+
+    config.timezone = Some("Europe/Berlin".to_string());
+    assert!(Schedule::parse(&config)
+        .unwrap_err()
+        .contains("unsupported timezone"));
+}
+
+#[test]
+fn test_enforce() {
+    let config = maintenance();
+    assert!(enforce("prod", None, None, friday(12, 0)).is_ok());
+    assert!(enforce("prod", Some(&config), None, friday(23, 0)).is_ok());
+
+    let err = enforce("prod", Some(&config), None, friday(12, 0)).unwrap_err();
+    assert_eq!(err.code, ErrorCode::OutsideWindow);
+    let err = err.message;
+    assert!(err.contains("outside its maintenance window"));
+    assert!(err.contains("2026-10-16 22:00 UTC"));
+    assert!(err.contains("override_window"));
+    assert!(enforce("prod", Some(&config), Some("  "), friday(12, 0)).is_err());
+    assert!(enforce("prod", Some(&config), Some("hotfix INC-42"), friday(12, 0)).is_ok());
+
+    let mut strict = config.clone();
+    strict.outside_window = OutsideWindow::Refuse;
+    let err = enforce("prod", Some(&strict), Some("hotfix"), friday(12, 0)).unwrap_err();
+    assert_eq!(err.code, ErrorCode::OutsideWindow);
+    assert!(err.message.contains("refused"));
+
+    let mut broken = config;
+    broken.windows[0].start = "nonsense".to_string();
+    let err = enforce("prod", Some(&broken), None, friday(23, 0)).unwrap_err();
+    assert_eq!(err.code, ErrorCode::Failed);
+    assert!(err.message.contains("invalid"));
+}
+
+#[test]
+fn test_no_windows_means_always_closed() {
+    let config = MaintenanceConfig::default();
+    let err = enforce("prod", Some(&config), None, friday(12, 0))
+        .unwrap_err()
+        .message;
+    assert!(err.contains("refused"));
+    assert!(!err.contains("next window"));
+}
@@ -229,3 +229,148 @@ fn errors_on_nonexistent_directory() {
 
     assert!(!output.status.success());
 }
+
+// ── Project mappings ────────────────────────────────────────────────
+
+/// Config dir with one server, for `--server` runs. `XDG_CONFIG_HOME`
+/// points the CLI at it on Linux.
+fn config_home() -> tempfile::TempDir {
+    let home = tempfile::tempdir().unwrap();
+    let dir = home.path().join("ssh-hub");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("servers.toml"),
+        "[servers.staging]\nhost = \"staging.example.com\"\nuser = \"deploy\"\nremote_path = \"/var/www\"\n",
+    )
+    .unwrap();
+    home
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn server_flag_records_project() {
+    let home = config_home();
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = ssh_hub_bin()
+        .env("XDG_CONFIG_HOME", home.path())
+        .args([
+            "mcp-install",
+            dir.path().to_str().unwrap(),
+            "--claude",
+            "--server",
+            "staging",
+            "--remote-path",
+            "/srv/app",
+        ])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let content = fs::read_to_string(home.path().join("ssh-hub/servers.toml")).unwrap();
+    let config: toml::Table = content.parse().unwrap();
+    let root = fs::canonicalize(dir.path()).unwrap();
+    let project = &config["projects"][root.to_str().unwrap()];
+    assert_eq!(project["server"].as_str(), Some("staging"));
+    assert_eq!(project["remote_path"].as_str(), Some("/srv/app"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn server_flag_defaults_to_server_remote_path() {
+    let home = config_home();
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = ssh_hub_bin()
+        .env("XDG_CONFIG_HOME", home.path())
+        .args([
+            "mcp-install",
+            dir.path().to_str().unwrap(),
+            "--codex",
+            "--server",
+            "staging",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let content = fs::read_to_string(home.path().join("ssh-hub/servers.toml")).unwrap();
+    assert!(content.contains("remote_path = \"/var/www\""), "{content}");
+    assert!(content.contains("[projects."), "{content}");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn errors_on_unknown_server() {
+    let home = config_home();
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = ssh_hub_bin()
+        .env("XDG_CONFIG_HOME", home.path())
+        .args([
+            "mcp-install",
+            dir.path().to_str().unwrap(),
+            "--server",
+            "nope",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("'nope' not found"));
+    assert!(!dir.path().join(".mcp.json").exists());
+}
+
+#[test]
+fn remote_path_requires_server() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = ssh_hub_bin()
+        .args([
+            "mcp-install",
+            dir.path().to_str().unwrap(),
+            "--remote-path",
+            "/srv",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+}
+
+#[test]
+fn pins_the_profile_in_server_args() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = ssh_hub_bin()
+        .args([
+            "--profile",
+            "work",
+            "mcp-install",
+            dir.path().to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let content = fs::read_to_string(dir.path().join(".mcp.json")).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(
+        parsed["mcpServers"]["ssh-hub"]["args"],
+        serde_json::json!(["--profile", "work"])
+    );
+
+    let content = fs::read_to_string(dir.path().join(".codex/config.toml")).unwrap();
+    let parsed: toml::Table = content.parse().unwrap();
+    let args = parsed["mcp_servers"]["ssh-hub"]["args"].as_array().unwrap();
+    assert_eq!(args.len(), 2);
+    assert_eq!(args[1].as_str(), Some("work"));
+}
@@ -36,6 +36,21 @@ PKG_MANAGER=brew\n";
     assert_eq!(meta.package_manager.as_deref(), Some("brew"));
 }
 
+#[test]
+fn test_parse_windows_crlf_output() {
+    let output = "OS=windows\r\nARCH=AMD64\r\nDISTRO=Microsoft Windows Server 2022 Standard\r\nSHELL=powershell\r\nPKG_MANAGER=winget\r\n";
+
+    let meta = parse_output(output).unwrap();
+    assert_eq!(meta.os.as_deref(), Some("windows"));
+    assert_eq!(meta.arch.as_deref(), Some("AMD64"));
+    assert_eq!(
+        meta.distro.as_deref(),
+        Some("Microsoft Windows Server 2022 Standard")
+    );
+    assert_eq!(meta.shell.as_deref(), Some("powershell"));
+    assert_eq!(meta.package_manager.as_deref(), Some("winget"));
+}
+
 #[test]
 fn test_parse_partial_output() {
     let output = "ARCH=x86_64\nOS=linux\n";
@@ -174,3 +189,80 @@ fn test_diff_multiple_fields_changed() {
     assert!(msg.contains("shell"));
     assert!(msg.contains("package_manager"));
 }
+
+#[test]
+fn test_diff_reports_address_family() {
+    let a = SystemMetadata {
+        address_family: Some("ipv6".into()),
+        ..Default::default()
+    };
+    let b = SystemMetadata {
+        address_family: Some("ipv4".into()),
+        ..Default::default()
+    };
+    assert_eq!(
+        diff(&a, &b).as_deref(),
+        Some("address_family: ipv6 -> ipv4")
+    );
+}
+
+#[test]
+fn test_parse_json_output() {
+    let output = r#"{"arch":"x86_64","os":"linux","distro":"Acme \"Edge\" OS\nbuild=7","hostname":"web-1","shell":"/bin/bash","shell_version":"GNU bash, version 5.2.15(1)-release","package_manager":"apt","uptime":"86400","umask":"0022"}
+"#;
+    let meta = parse_output(output).unwrap();
+    assert_eq!(meta.os.as_deref(), Some("linux"));
+    assert_eq!(meta.distro.as_deref(), Some("Acme \"Edge\" OS\nbuild=7"));
+    assert_eq!(meta.hostname.as_deref(), Some("web-1"));
+    assert_eq!(
+        meta.shell_version.as_deref(),
+        Some("GNU bash, version 5.2.15(1)-release")
+    );
+    assert_eq!(meta.uptime_seconds, Some(86_400));
+    assert_eq!(meta.umask.as_deref(), Some("0022"));
+    assert!(meta.collected_at.is_some());
+}
+
+#[test]
+fn test_parse_json_skips_empty_values() {
+    // PowerShell's ConvertTo-Json omits nothing, so unset fields come as ""
+    let output = "{\"os\":\"windows\",\"arch\":\"AMD64\",\"shell\":\"powershell\",\"shell_version\":\"7.4.1\",\"uptime\":\"\"}\r\n";
+    let meta = parse_output(output).unwrap();
+    assert_eq!(meta.os.as_deref(), Some("windows"));
+    assert_eq!(meta.shell_version.as_deref(), Some("7.4.1"));
+    assert!(meta.uptime_seconds.is_none());
+    assert!(meta.package_manager.is_none());
+}
+
+#[test]
+fn test_parse_key_value_fallback_keeps_equals_and_spaces() {
+    let output = "OS=linux\nDISTRO=Acme OS build=7 rev=2\nHOSTNAME=db 2\nUPTIME=120\nUMASK=0077\n";
+    let meta = parse_output(output).unwrap();
+    assert_eq!(meta.distro.as_deref(), Some("Acme OS build=7 rev=2"));
+    assert_eq!(meta.hostname.as_deref(), Some("db 2"));
+    assert_eq!(meta.uptime_seconds, Some(120));
+    assert_eq!(meta.umask.as_deref(), Some("0077"));
+}
+
+#[test]
+fn test_diff_ignores_uptime() {
+    let a = SystemMetadata {
+        hostname: Some("web-1".to_string()),
+        uptime_seconds: Some(100),
+        ..Default::default()
+    };
+    let b = SystemMetadata {
+        uptime_seconds: Some(200),
+        ..a.clone()
+    };
+    assert!(diff(&a, &b).is_none());
+
+    let renamed = SystemMetadata {
+        hostname: Some("web-2".to_string()),
+        ..a.clone()
+    };
+    assert_eq!(
+        diff(&a, &renamed).as_deref(),
+        Some("hostname: web-1 -> web-2")
+    );
+}
@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use ssh_hub::metrics::{synced_bytes, Metrics};
+use ssh_hub::server_registry::ServerRegistry;
+use ssh_hub::tools::metrics::handler::handle;
+use ssh_hub::tools::metrics::{MetricsFormat, MetricsInput};
+
+fn json(metrics: &Metrics) -> serde_json::Value {
+    serde_json::to_value(metrics.snapshot()).unwrap()
+}
+
+#[test]
+fn test_counts_calls_and_errors_per_tool() {
+    let mut metrics = Metrics::new();
+    metrics.record_call("remote_read", Duration::from_millis(5), false, false);
+    metrics.record_call("remote_read", Duration::from_millis(5), true, false);
+    metrics.record_call("list_servers", Duration::from_millis(1), false, false);
+    metrics.record_reconnect();
+
+    let snapshot = json(&metrics);
+    assert_eq!(snapshot["tools"]["remote_read"]["calls"], 2);
+    assert_eq!(snapshot["tools"]["remote_read"]["errors"], 1);
+    assert_eq!(snapshot["tools"]["list_servers"]["errors"], 0);
+    assert_eq!(snapshot["reconnects"], 1);
+}
+
+#[test]
+fn test_only_command_calls_are_timed() {
+    let mut metrics = Metrics::new();
+    metrics.record_call("remote_read", Duration::from_secs(3), false, false);
+    metrics.record_call("remote_bash", Duration::from_millis(50), false, true);
+    metrics.record_call("remote_bash", Duration::from_millis(700), false, true);
+    metrics.record_call("remote_bash", Duration::from_mins(10), false, true);
+
+    let histogram = &json(&metrics)["command_ms"];
+    assert_eq!(histogram["count"], 3);
+    assert_eq!(histogram["sum"], 600_750);
+    let buckets = histogram["buckets"].as_array().unwrap();
+    // Cumulative: 50ms in every bucket, 700ms from 1s up, 600s only in +Inf
+    assert_eq!(buckets[0]["le"], 100);
+    assert_eq!(buckets[0]["count"], 1);
+    assert_eq!(buckets[1]["count"], 1);
+    assert_eq!(buckets[2]["count"], 2);
+    let inf = buckets.last().unwrap();
+    assert!(inf["le"].is_null());
+    assert_eq!(inf["count"], 3);
+    assert_eq!(buckets[buckets.len() - 2]["count"], 2);
+}
+
+#[test]
+fn test_prometheus_text() {
+    let mut metrics = Metrics::new();
+    metrics.record_call("remote_bash", Duration::from_millis(2_500), true, true);
+    metrics.record_synced(2_000);
+
+    let text = metrics.snapshot().to_prometheus();
+    assert!(text.contains("# TYPE ssh_hub_tool_calls_total counter\n"));
+    assert!(text.contains("ssh_hub_tool_calls_total{tool=\"remote_bash\"} 1\n"));
+    assert!(text.contains("ssh_hub_tool_errors_total{tool=\"remote_bash\"} 1\n"));
+    assert!(text.contains("ssh_hub_reconnects_total 0\n"));
+    assert!(text.contains("# TYPE ssh_hub_command_duration_seconds histogram\n"));
+    assert!(text.contains("ssh_hub_command_duration_seconds_bucket{le=\"0.1\"} 0\n"));
+    assert!(text.contains("ssh_hub_command_duration_seconds_bucket{le=\"2.5\"} 1\n"));
+    assert!(text.contains("ssh_hub_command_duration_seconds_bucket{le=\"+Inf\"} 1\n"));
+    assert!(text.contains("ssh_hub_command_duration_seconds_sum 2.5\n"));
+    assert!(text.contains("ssh_hub_synced_bytes_bucket{le=\"1024\"} 0\n"));
+    assert!(text.contains("ssh_hub_synced_bytes_bucket{le=\"65536\"} 1\n"));
+    assert!(text.contains("ssh_hub_synced_bytes_sum 2000\n"));
+    assert!(text.contains("ssh_hub_synced_bytes_count 1\n"));
+}
+
+#[test]
+fn test_synced_bytes_from_sync_output() {
+    let output = r#"{"transferred": ["a"], "transfer": {"strategy": "cat", "bytes": 4096, "elapsed_ms": 3, "bytes_per_sec": 1}}"#;
+    assert_eq!(synced_bytes(output), Some(4096));
+    assert_eq!(synced_bytes(r#"{"transferred": []}"#), None);
+    assert_eq!(synced_bytes("Error: no such file"), None);
+}
+
+#[test]
+fn test_metrics_config() {
+    let cfg: ServerRegistry = toml::from_str("[metrics]\nenabled = true\n").unwrap();
+    assert!(cfg.metrics.unwrap().enabled);
+
+    let cfg: ServerRegistry = toml::from_str("").unwrap();
+    assert!(cfg.metrics.is_none());
+}
+
+#[test]
+fn test_tool_output() {
+    let mut metrics = Metrics::new();
+    metrics.record_call("pool_stats", Duration::ZERO, false, false);
+
+    let json = handle(Some(&metrics), &MetricsInput::default());
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["tools"]["pool_stats"]["calls"], 1);
+
+    let input = MetricsInput {
+        format: Some(MetricsFormat::Prometheus),
+    };
+    assert!(
+        handle(Some(&metrics), &input).contains("ssh_hub_tool_calls_total{tool=\"pool_stats\"} 1")
+    );
+
+    let off = handle(None, &MetricsInput::default());
+    assert!(off.starts_with("Metrics are off."));
+}
@@ -0,0 +1,109 @@
+use std::path::Path;
+
+use serde_json::{json, Value};
+use ssh_hub::utils::output::{
+    build_output_summary, fit_json, fit_output, format_byte_size, MAX_INLINE_OUTPUT,
+    SUMMARY_HEAD_LINES, SUMMARY_TAIL_LINES,
+};
+
+#[test]
+fn test_summary_keeps_head_and_tail_lines() {
+    let output: String = (1..=1000)
+        .map(|i| format!("line {i}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n";
+    let summary = build_output_summary(&output, Path::new("/tmp/ssh-hub/x.log"));
+    assert!(summary.starts_with("[Output too large for context (8 KB, 1000 lines)]\n"));
+    assert!(summary.contains("Full output saved to: /tmp/ssh-hub/x.log"));
+    assert!(summary.contains(&format!(
+        "--- First {SUMMARY_HEAD_LINES} lines ---\nline 1\n"
+    )));
+    assert!(summary.contains("line 150\n\n... (800 lines omitted) ..."));
+    assert!(summary.contains(&format!(
+        "--- Last {SUMMARY_TAIL_LINES} lines ---\nline 951\n"
+    )));
+    assert!(summary.ends_with("line 1000\n"));
+    assert!(!summary.contains("line 151\n"));
+}
+
+#[test]
+fn test_summary_of_few_long_lines_is_bounded() {
+    let line = "é".repeat(200_000);
+    let output = format!("{line}\n{line}\n");
+    let summary = build_output_summary(&output, Path::new("/tmp/ssh-hub/x.log"));
+    assert!(summary.len() < MAX_INLINE_OUTPUT / 2, "{}", summary.len());
+    assert!(summary.contains("--- First 2 lines ---"));
+    assert!(summary.contains(" cut ...]"));
+    assert!(!summary.contains("omitted"));
+    assert!(!summary.contains("--- Last"));
+}
+
+#[test]
+fn test_format_byte_size() {
+    assert_eq!(format_byte_size(450_000), "450 KB");
+    assert_eq!(format_byte_size(1_349_999), "1.3 MB");
+}
+
+#[tokio::test]
+async fn test_fit_output_spills_large_output_to_disk() {
+    let small = "ok\n".to_string();
+    assert_eq!(fit_output(small.clone(), "remote_read").await, small);
+
+    let large: String = (0..20_000)
+        .map(|i| format!("row {i:08}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let fitted = fit_output(large.clone(), "remote_read").await;
+    assert!(fitted.len() < MAX_INLINE_OUTPUT, "{}", fitted.len());
+    let path = fitted
+        .lines()
+        .find_map(|line| line.strip_prefix("Full output saved to: "))
+        .unwrap();
+    assert!(
+        Path::new(path)
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("remote_read-"),
+        "{path}"
+    );
+    assert_eq!(std::fs::read_to_string(path).unwrap(), large);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_fit_json_cuts_lists_and_stays_valid() {
+    let small = json!({"transferred": ["a"], "failed": []});
+    let fitted = fit_json(&small, &["/transferred"], "sync_push").await;
+    assert_eq!(fitted, serde_json::to_string_pretty(&small).unwrap());
+
+    let files: Vec<String> = (0..20_000).map(|i| format!("src/file_{i:08}.rs")).collect();
+    let large = json!({
+        "diff": {"local_only": files, "modified": files, "unchanged": 3},
+        "cached": false,
+    });
+    let fitted = fit_json(
+        &large,
+        &["/diff/local_only", "/diff/modified"],
+        "sync_status",
+    )
+    .await;
+    assert!(fitted.len() <= MAX_INLINE_OUTPUT, "{}", fitted.len());
+    let json: Value = serde_json::from_str(&fitted).unwrap();
+    assert_eq!(json["truncated"], true);
+    assert_eq!(json["cached"], false);
+    assert_eq!(json["diff"]["unchanged"], 3);
+    assert_eq!(json["diff"]["local_only"], json!([]));
+    assert_eq!(json["omitted"]["local_only"], 20_000);
+    let kept = json["diff"]["modified"].as_array().unwrap().len();
+    assert!(kept > 0, "{fitted}");
+    assert_eq!(json["omitted"]["modified"], 20_000 - kept);
+    assert_eq!(json["diff"]["modified"][0], "src/file_00000000.rs");
+
+    let path = json["saved_to"].as_str().unwrap();
+    let saved: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    assert_eq!(saved, large);
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,61 @@
+use std::fs;
+use std::process::Command;
+
+use ssh_hub::profile::{scoped, validate};
+
+#[test]
+fn test_profile_names() {
+    for name in ["work", "client-a", "home_2"] {
+        assert!(validate(name).is_ok(), "{name}");
+    }
+    for name in ["", "default", "a b", "../x", "work.toml", &"x".repeat(33)] {
+        assert!(validate(name).is_err(), "{name:?}");
+    }
+    assert_eq!(scoped("servers", None), "servers");
+    assert_eq!(scoped("servers", Some("work")), "servers-work");
+}
+
+#[test]
+fn test_profile_reads_its_own_config() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("servers.toml"),
+        "[servers.home]\nhost = \"10.0.0.1\"\nuser = \"me\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("servers-work.toml"),
+        "[servers.office]\nhost = \"10.0.0.2\"\nuser = \"me\"\n",
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_ssh-hub"))
+            .args(args)
+            .env("SSH_HUB_CONFIG_DIR", dir.path())
+            .env_remove("SSH_HUB_CONFIG")
+            .output()
+            .unwrap();
+        (
+            output.status.success(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        )
+    };
+
+    let (ok, stdout, _) = run(&["--profile", "work", "list", "--no-probe"]);
+    assert!(ok);
+    assert!(
+        stdout.contains("office") && !stdout.contains("home"),
+        "{stdout}"
+    );
+    let (ok, stdout, _) = run(&["list", "--no-probe"]);
+    assert!(ok);
+    assert!(
+        stdout.contains("home") && !stdout.contains("office"),
+        "{stdout}"
+    );
+
+    let (ok, _, stderr) = run(&["--profile", "no good", "list"]);
+    assert!(!ok);
+    assert!(stderr.contains("invalid profile name"), "{stderr}");
+}
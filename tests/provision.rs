@@ -0,0 +1,183 @@
+use std::path::Path;
+
+use ssh_hub::cli::sanitize_registry;
+use ssh_hub::server_registry::ServerRegistry;
+use ssh_hub::timeout::TimeoutPolicy;
+use ssh_hub::tools::provision::handler::find_recipe;
+use ssh_hub::tools::provision::{Recipe, StepKind, StepReport, StepStatus};
+use ssh_hub::tools::ErrorCode;
+
+const CONFIG: &str = r#"
+[servers.web1]
+host = "web1.example.com"
+user = "deploy"
+
+[servers.db1]
+host = "db1.example.com"
+user = "deploy"
+
+[recipes.nginx]
+description = "nginx with the app site"
+sudo = true
+packages = ["nginx", "curl"]
+services = ["nginx"]
+servers = ["web1"]
+
+[[recipes.nginx.files]]
+src = "files/app.conf"
+dest = "/etc/nginx/sites-enabled/app.conf"
+mode = "644"
+
+[[recipes.nginx.commands]]
+run = "nginx -t && systemctl reload nginx"
+unless = "test -f /var/lib/app/.configured"
+timeout = 900000
+
+[recipes.tools]
+packages = ["htop"]
+"#;
+
+fn config() -> ServerRegistry {
+    toml::from_str(CONFIG).unwrap()
+}
+
+fn recipe(name: &str) -> Recipe {
+    config().recipes.get(name).unwrap().clone()
+}
+
+#[test]
+fn test_parse_recipes() {
+    let cfg = config();
+    assert_eq!(cfg.recipes.len(), 2);
+
+    let nginx = recipe("nginx");
+    assert!(nginx.sudo);
+    assert_eq!(nginx.packages, ["nginx", "curl"]);
+    assert_eq!(nginx.files.len(), 1);
+    assert_eq!(nginx.files[0].mode.as_deref(), Some("644"));
+    assert_eq!(
+        nginx.commands[0].unless.as_deref(),
+        Some("test -f /var/lib/app/.configured")
+    );
+    assert!(nginx.validate().is_ok());
+
+    let tools = recipe("tools");
+    assert!(!tools.sudo);
+    assert!(tools.files.is_empty());
+}
+
+#[test]
+fn test_recipes_roundtrip() {
+    let cfg = config();
+    let serialized = toml::to_string_pretty(&cfg).unwrap();
+    let back: ServerRegistry = toml::from_str(&serialized).unwrap();
+    assert_eq!(back.recipes, cfg.recipes);
+    assert!(!toml::to_string_pretty(&ServerRegistry::default())
+        .unwrap()
+        .contains("recipes"));
+}
+
+#[test]
+fn test_command_timeout_capped() {
+    let nginx = recipe("nginx");
+    let timeouts = TimeoutPolicy::default();
+    assert_eq!(nginx.commands[0].timeout_ms(timeouts), Ok(Some(600_000)));
+
+    let mut command = nginx.commands[0].clone();
+    command.timeout = None;
+    assert_eq!(command.timeout_ms(timeouts), Ok(Some(120_000)));
+}
+
+#[test]
+fn test_allows_server() {
+    assert!(recipe("nginx").allows_server("web1"));
+    assert!(!recipe("nginx").allows_server("db1"));
+    assert!(recipe("tools").allows_server("db1"));
+}
+
+#[test]
+fn test_validate_rejects_bad_names_and_modes() {
+    let mut bad = recipe("tools");
+    bad.packages.push("--force".to_string());
+    assert!(bad.validate().unwrap_err().contains("--force"));
+
+    let mut bad = recipe("nginx");
+    bad.services = vec!["nginx; reboot".to_string()];
+    assert!(bad.validate().unwrap_err().contains("service"));
+
+    let mut bad = recipe("nginx");
+    bad.files[0].mode = Some("0999".to_string());
+    assert!(bad.validate().unwrap_err().contains("mode"));
+
+    let mut bad = recipe("nginx");
+    bad.commands[0].run = "  ".to_string();
+    assert!(bad.validate().is_err());
+}
+
+#[test]
+fn test_local_path_resolution() {
+    let file = &recipe("nginx").files[0];
+    assert_eq!(
+        file.local_path(Path::new("/cfg/ssh-hub")),
+        Path::new("/cfg/ssh-hub/files/app.conf")
+    );
+
+    let mut absolute = file.clone();
+    absolute.src = "/srv/app.conf".to_string();
+    assert_eq!(
+        absolute.local_path(Path::new("/cfg/ssh-hub")),
+        Path::new("/srv/app.conf")
+    );
+
+    if let Some(home) = dirs::home_dir() {
+        let mut tilde = file.clone();
+        tilde.src = "~/deploy/app.conf".to_string();
+        assert_eq!(
+            tilde.local_path(Path::new("/cfg")),
+            home.join("deploy/app.conf")
+        );
+    }
+}
+
+#[test]
+fn test_find_recipe_errors() {
+    let cfg = config();
+    assert!(find_recipe(&cfg, "nginx", "web1").is_ok());
+
+    let err = find_recipe(&cfg, "missing", "web1").unwrap_err();
+    assert_eq!(err.code, ErrorCode::NotFound);
+    assert!(err.message.contains("nginx, tools"));
+
+    let err = find_recipe(&cfg, "nginx", "db1").unwrap_err();
+    assert_eq!(err.code, ErrorCode::InvalidInput);
+    assert!(err.message.contains("not enabled"));
+
+    let err = find_recipe(&ServerRegistry::default(), "nginx", "web1").unwrap_err();
+    assert!(err.message.contains("No recipes"));
+}
+
+#[test]
+fn test_step_report_serialization() {
+    let step = StepReport {
+        kind: StepKind::Package,
+        target: "nginx".to_string(),
+        status: StepStatus::WouldChange,
+        detail: None,
+    };
+    let json = serde_json::to_value(&step).unwrap();
+    assert_eq!(json["kind"], "package");
+    assert_eq!(json["status"], "would_change");
+    assert_eq!(StepStatus::WouldChange.to_string(), "would change");
+}
+
+#[test]
+fn test_sanitize_redacts_recipe_commands_and_paths() {
+    let clean = sanitize_registry(&config());
+    let nginx = clean.recipes.get("nginx").unwrap();
+    assert_eq!(nginx.packages, ["nginx", "curl"]);
+    assert_eq!(nginx.files[0].src, "<redacted>");
+    assert_eq!(nginx.files[0].dest, "<redacted>");
+    assert_eq!(nginx.files[0].mode.as_deref(), Some("644"));
+    assert_eq!(nginx.commands[0].run, "<redacted>");
+    assert_eq!(nginx.commands[0].unless.as_deref(), Some("<redacted>"));
+}
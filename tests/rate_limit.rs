@@ -0,0 +1,152 @@
+use std::time::{Duration, Instant};
+
+use ssh_hub::rate_limit::{ClassLimit, RateUsage, ToolClass};
+use ssh_hub::server_registry::ServerRegistry;
+use ssh_hub::tools::ErrorCode;
+
+const THREE_PER_MINUTE: ClassLimit = ClassLimit {
+    calls_per_minute: Some(3),
+    bytes_per_hour: None,
+};
+
+#[test]
+fn test_limits_from_config_and_defaults() {
+    let cfg = ServerRegistry::parse(
+        r#"
+        [defaults.rate_limits.bash]
+        calls_per_minute = 30
+
+        [servers.prod]
+        host = "10.0.0.1"
+        user = "deploy"
+
+        [servers.prod.rate_limits.sync]
+        bytes_per_hour = 1000000000
+
+        [servers.web]
+        host = "10.0.0.3"
+        user = "deploy"
+
+        [servers.dev]
+        host = "10.0.0.2"
+        user = "deploy"
+        rate_limits.bash.calls_per_minute = 0
+        "#,
+    )
+    .unwrap();
+    let web = cfg.get("web").unwrap().rate_limits.clone().unwrap();
+    assert_eq!(
+        web.for_class(ToolClass::Bash).unwrap().calls_per_minute,
+        Some(30)
+    );
+    assert_eq!(web.for_class(ToolClass::Write), None);
+    // A server's own rate_limits replace the defaults as a whole
+    let prod = cfg.get("prod").unwrap().rate_limits.clone().unwrap();
+    assert_eq!(prod.for_class(ToolClass::Bash), None);
+    assert_eq!(
+        prod.for_class(ToolClass::Sync).unwrap().bytes_per_hour,
+        Some(1_000_000_000)
+    );
+    let dev = cfg.get("dev").unwrap().rate_limits.clone().unwrap();
+    assert_eq!(
+        dev.for_class(ToolClass::Bash).unwrap().calls_per_minute,
+        Some(0)
+    );
+}
+
+#[test]
+fn test_tool_classes() {
+    assert_eq!(
+        ToolClass::for_tool("remote_bash", false),
+        Some(ToolClass::Bash)
+    );
+    assert_eq!(
+        ToolClass::for_tool("restart_app", true),
+        Some(ToolClass::Bash)
+    );
+    assert_eq!(
+        ToolClass::for_tool("remote_edit", false),
+        Some(ToolClass::Write)
+    );
+    assert_eq!(
+        ToolClass::for_tool("sync_pull", false),
+        Some(ToolClass::Sync)
+    );
+    assert_eq!(ToolClass::for_tool("list_servers", false), None);
+}
+
+#[test]
+fn test_calls_per_minute_refuses_then_recovers() {
+    let mut usage = RateUsage::new();
+    let start = Instant::now();
+    for i in 0..3 {
+        let at = start + Duration::from_secs(i * 10);
+        assert!(usage
+            .check("prod", ToolClass::Bash, THREE_PER_MINUTE, at)
+            .is_ok());
+    }
+    let at = start + Duration::from_secs(45);
+    let err = usage
+        .check("prod", ToolClass::Bash, THREE_PER_MINUTE, at)
+        .unwrap_err();
+    assert_eq!(err.code, ErrorCode::Throttled);
+    assert!(err.retriable);
+    let err = err.message;
+    assert!(err.contains("3 calls per minute on 'prod'"), "{err}");
+    assert!(err.contains("Retry in 15s"), "{err}");
+
+    // Other servers and classes have their own counts
+    assert!(usage
+        .check("staging", ToolClass::Bash, THREE_PER_MINUTE, at)
+        .is_ok());
+    assert!(usage
+        .check("prod", ToolClass::Write, THREE_PER_MINUTE, at)
+        .is_ok());
+
+    // The refused call wasn't counted, and the first call ages out
+    let at = start + Duration::from_mins(1);
+    assert!(usage
+        .check("prod", ToolClass::Bash, THREE_PER_MINUTE, at)
+        .is_ok());
+    assert!(usage
+        .check("prod", ToolClass::Bash, THREE_PER_MINUTE, at)
+        .is_err());
+}
+
+#[test]
+fn test_bytes_per_hour() {
+    let limit = ClassLimit {
+        calls_per_minute: None,
+        bytes_per_hour: Some(1000),
+    };
+    let mut usage = RateUsage::new();
+    let start = Instant::now();
+    assert!(usage.check("prod", ToolClass::Sync, limit, start).is_ok());
+    usage.record_bytes("prod", ToolClass::Sync, 600, start);
+    let later = start + Duration::from_mins(10);
+    assert!(usage.check("prod", ToolClass::Sync, limit, later).is_ok());
+    usage.record_bytes("prod", ToolClass::Sync, 600, later);
+    let err = usage
+        .check("prod", ToolClass::Sync, limit, later)
+        .unwrap_err()
+        .message;
+    assert!(err.contains("1000 bytes per hour"), "{err}");
+    assert!(err.contains("Retry in 3000s"), "{err}");
+    let after_first = start + Duration::from_hours(1);
+    assert!(usage
+        .check("prod", ToolClass::Sync, limit, after_first)
+        .is_ok());
+}
+
+#[test]
+fn test_zero_disables_limit() {
+    let limit = ClassLimit {
+        calls_per_minute: Some(0),
+        bytes_per_hour: Some(0),
+    };
+    let mut usage = RateUsage::new();
+    let now = Instant::now();
+    for _ in 0..100 {
+        assert!(usage.check("prod", ToolClass::Read, limit, now).is_ok());
+    }
+}
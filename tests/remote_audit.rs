@@ -0,0 +1,189 @@
+use ssh_hub::tools::remote_audit::handler::{
+    audit_command, parse_audit, parse_listening, parse_logins, parse_sshd,
+};
+
+// --- listening sockets ---
+
+#[test]
+fn parses_ss_output() {
+    let lines = [
+        "tool=ss",
+        "udp   UNCONN 0      0      127.0.0.53%lo:53        0.0.0.0:*",
+        "tcp   LISTEN 0      4096         0.0.0.0:22        0.0.0.0:*",
+        "tcp   LISTEN 0      4096            [::]:22           [::]:*",
+        "tcp   LISTEN 0      511                *:80              *:*",
+    ];
+    let sockets = parse_listening(&lines);
+    let summary: Vec<(u16, &str, &str)> = sockets
+        .iter()
+        .map(|s| (s.port, s.protocol.as_str(), s.address.as_str()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (22, "tcp", "0.0.0.0"),
+            (22, "tcp", "::"),
+            (53, "udp", "127.0.0.53"),
+            (80, "tcp", "*"),
+        ]
+    );
+}
+
+#[test]
+fn netstat_skips_established_and_unix_sockets() {
+    let lines = [
+        "tool=netstat",
+        "Active Internet connections (servers and established)",
+        "Proto Recv-Q Send-Q Local Address           Foreign Address         State",
+        "tcp        0      0 0.0.0.0:22              0.0.0.0:*               LISTEN",
+        "tcp        0     36 10.0.0.5:22             203.0.113.9:51234       ESTABLISHED",
+        "tcp6       0      0 :::22                   :::*                    LISTEN",
+        "udp        0      0 0.0.0.0:68              0.0.0.0:*",
+        "unix  2      [ ACC ]     STREAM     LISTENING     12345    /run/systemd/private",
+    ];
+    let sockets = parse_listening(&lines);
+    assert_eq!(sockets.len(), 3);
+    assert!(sockets.iter().all(|s| s.port != 51234));
+    assert!(sockets.iter().any(|s| s.address == "::" && s.port == 22));
+}
+
+#[test]
+fn netstat_bsd_addresses() {
+    let lines = [
+        "tool=netstat",
+        "tcp4       0      0  *.22                   *.*                    LISTEN",
+        "tcp4       0      0  127.0.0.1.631          *.*                    LISTEN",
+    ];
+    let sockets = parse_listening(&lines);
+    assert_eq!(sockets[0].address, "*");
+    assert_eq!(sockets[0].port, 22);
+    assert_eq!(sockets[1].address, "127.0.0.1");
+    assert_eq!(sockets[1].port, 631);
+}
+
+#[test]
+fn listening_without_tool_is_empty() {
+    assert!(parse_listening(&[]).is_empty());
+}
+
+// --- sshd ---
+
+#[test]
+fn sshd_config_first_value_wins_and_match_blocks_are_ignored() {
+    let lines = [
+        "source=/etc/ssh/sshd_config",
+        "# drop-in",
+        "PasswordAuthentication no",
+        "Include /etc/ssh/sshd_config.d/*.conf",
+        "PasswordAuthentication yes",
+        "PermitRootLogin=prohibit-password",
+        "UsePAM yes",
+        "Match User deploy",
+        "    PermitRootLogin yes",
+    ];
+    let sshd = parse_sshd(&lines).unwrap();
+    assert_eq!(sshd.source, "/etc/ssh/sshd_config");
+    assert_eq!(sshd.settings["passwordauthentication"], "no");
+    assert_eq!(sshd.settings["permitrootlogin"], "prohibit-password");
+    assert!(!sshd.settings.contains_key("usepam"));
+}
+
+#[test]
+fn sshd_t_output() {
+    let lines = [
+        "source=sshd -T",
+        "port 22",
+        "permitrootlogin without-password",
+        "passwordauthentication yes",
+    ];
+    let sshd = parse_sshd(&lines).unwrap();
+    assert_eq!(sshd.source, "sshd -T");
+    assert_eq!(sshd.settings["port"], "22");
+    assert_eq!(sshd.settings["passwordauthentication"], "yes");
+}
+
+#[test]
+fn sshd_missing_source_is_none() {
+    assert!(parse_sshd(&[]).is_none());
+}
+
+// --- logins ---
+
+#[test]
+fn parses_last_output() {
+    let lines = [
+        "deploy   pts/0        203.0.113.5      Mon Oct 14 10:22   still logged in",
+        "root     tty1         0.0.0.0          Sun Oct 13 08:01 - 08:05  (00:04)",
+        "reboot   system boot  0.0.0.0          Sun Oct 13 07:59   still running",
+        "",
+        "wtmp begins Tue Oct  1 00:00:01 2024",
+    ];
+    let logins = parse_logins(&lines);
+    assert_eq!(logins.len(), 2);
+    assert_eq!(logins[0].user, "deploy");
+    assert_eq!(logins[0].from.as_deref(), Some("203.0.113.5"));
+    assert_eq!(logins[0].when, "Mon Oct 14 10:22 still logged in");
+    assert_eq!(logins[1].user, "root");
+    assert_eq!(logins[1].from, None);
+}
+
+#[test]
+fn last_without_ip_column() {
+    let logins =
+        parse_logins(&["alice    ttys000                   Mon Oct 14 10:22   still logged in"]);
+    assert_eq!(logins[0].tty, "ttys000");
+    assert_eq!(logins[0].from, None);
+    assert_eq!(logins[0].when, "Mon Oct 14 10:22 still logged in");
+}
+
+// --- full report ---
+
+#[test]
+fn full_report_with_warnings() {
+    let stdout = "\
+@@ssh-hub-audit:updates
+pkg=apt
+pending=12
+security=3
+@@ssh-hub-audit:listening
+tool=ss
+tcp   LISTEN 0      4096         0.0.0.0:22        0.0.0.0:*
+@@ssh-hub-audit:sshd
+source=/etc/ssh/sshd_config
+PermitRootLogin yes
+@@ssh-hub-audit:logins
+deploy   pts/0        203.0.113.5      Mon Oct 14 10:22   still logged in
+";
+    let report = parse_audit(stdout);
+    let updates = report.updates.as_ref().unwrap();
+    assert_eq!(updates.package_manager, "apt");
+    assert_eq!(updates.pending, 12);
+    assert_eq!(updates.security, Some(3));
+    assert_eq!(report.listening.len(), 1);
+    assert_eq!(report.last_logins.len(), 1);
+    assert_eq!(
+        report.warnings,
+        vec![
+            "3 pending security updates",
+            "sshd: PasswordAuthentication is not set (sshd defaults to yes)",
+            "sshd: PermitRootLogin allows root password login",
+        ]
+    );
+}
+
+#[test]
+fn empty_sections_produce_empty_report() {
+    let report = parse_audit(
+        "@@ssh-hub-audit:updates\n@@ssh-hub-audit:listening\n@@ssh-hub-audit:sshd\n@@ssh-hub-audit:logins\n",
+    );
+    assert!(report.updates.is_none());
+    assert!(report.listening.is_empty());
+    assert!(report.sshd.is_none());
+    assert!(report.last_logins.is_empty());
+    assert!(report.warnings.is_empty());
+}
+
+#[test]
+fn command_limits_logins() {
+    assert!(audit_command(5).contains("last -n 5 "));
+}
@@ -1,4 +1,17 @@
-use ssh_hub::tools::remote_bash::handler::detect_background_pattern;
+use std::io::{BufRead, BufReader};
+use std::os::unix::process::CommandExt;
+use std::process::Stdio;
+
+use ssh_hub::connection::{
+    compress_command, decode_compressed, decode_compressed_partial, kill_group_command,
+    split_pid_marker, ExecOutcome, ExecRawResult, FakeExecutor, RemoteExecutor,
+};
+use ssh_hub::server_registry::CommandShell;
+use ssh_hub::tools::remote_bash::handler::{
+    background_job_id, background_log_file, detect_background_pattern, redact, sudo_command,
+    OutputSizes, COMPRESS_THRESHOLD,
+};
+use ssh_hub::tools::remote_bash::mutation::detect_mutation;
 
 // --- nohup detection ---
 
@@ -94,3 +107,291 @@ fn allows_nohup_in_non_command_position() {
     assert_eq!(detect_background_pattern("cat /tmp/nohup.out"), None);
     assert_eq!(detect_background_pattern("grep nohup logfile"), None);
 }
+
+// --- mutation detection (readonly servers) ---
+
+fn mutates(command: &str) -> bool {
+    detect_mutation(command).is_some()
+}
+
+#[test]
+fn detects_mutating_programs() {
+    assert_eq!(detect_mutation("rm -rf /tmp/x").as_deref(), Some("`rm`"));
+    assert!(mutates("cd /srv && /bin/mv a b"));
+    assert!(mutates("sudo -u www-data touch /var/www/x"));
+    assert!(mutates("FOO=1 env BAR=2 chmod 600 key"));
+    assert!(mutates("mkfs.ext4 /dev/sdb1"));
+    assert!(mutates("ls; reboot"));
+}
+
+#[test]
+fn detects_mutating_subcommands() {
+    assert_eq!(
+        detect_mutation("sudo systemctl restart nginx").as_deref(),
+        Some("`systemctl restart`")
+    );
+    assert!(mutates("apt-get install -y htop"));
+    assert!(mutates("docker compose -f prod.yml up -d"));
+    assert!(mutates("git -C /srv/app pull"));
+    assert!(mutates("kubectl rollout restart deploy/api"));
+    assert!(mutates("service nginx reload"));
+    assert!(mutates("sed -i 's/a/b/' file"));
+    assert!(mutates("find /tmp -name '*.log' -delete"));
+    assert!(mutates("find . -exec rm {} +"));
+    assert!(mutates("sh -c 'rm -f /tmp/x'"));
+}
+
+#[test]
+fn detects_output_redirection() {
+    assert_eq!(
+        detect_mutation("echo hi > /etc/motd").as_deref(),
+        Some("output redirection to '/etc/motd'")
+    );
+    assert!(mutates("date >>log.txt"));
+    assert!(mutates("make &> build.log"));
+    assert!(mutates("ps aux | tee ps.txt"));
+}
+
+#[test]
+fn allows_read_only_commands() {
+    assert!(!mutates("ls -la /var/log"));
+    assert!(!mutates("cat /etc/os-release | grep -i version"));
+    assert!(!mutates("systemctl status nginx --no-pager"));
+    assert!(!mutates("journalctl -u api -n 100 2>&1"));
+    assert!(!mutates(
+        "grep -r error /var/log >/dev/null 2>&1 && echo found"
+    ));
+    assert!(!mutates("docker ps -a; docker logs --tail 50 api"));
+    assert!(!mutates("git -C /srv/app log --oneline -5 && git status"));
+    assert!(!mutates("kubectl -n prod get pods"));
+    assert!(!mutates("apt list --installed"));
+    assert!(!mutates("echo 'rm -rf / > x'"));
+    assert!(!mutates("find /srv -name '*.conf'"));
+    assert!(!mutates("crontab -l"));
+    assert!(!mutates("df -h; free -m; uptime"));
+}
+
+// --- sudo ---
+
+#[test]
+fn test_sudo_command_reads_password_from_stdin_without_prompt() {
+    assert_eq!(
+        sudo_command("apt-get update && apt-get -y upgrade"),
+        "sudo -S -p '' -- sh -c 'apt-get update && apt-get -y upgrade'"
+    );
+}
+
+#[test]
+fn test_sudo_command_quotes_single_quotes() {
+    let command = sudo_command("echo 'it''s'");
+    assert!(command.starts_with("sudo -S -p '' -- sh -c "));
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command.replace("sudo -S -p '' -- ", ""))
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "its\n");
+}
+
+#[test]
+fn test_redact_masks_every_occurrence() {
+    assert_eq!(
+        redact("pw=hunter2\nagain hunter2", "hunter2"),
+        "pw=********\nagain ********"
+    );
+    assert_eq!(redact("nothing here", ""), "nothing here");
+}
+
+// --- shell selection ---
+
+#[test]
+fn wraps_command_in_chosen_shell() {
+    let command = "[[ -n $HOME ]] && echo 'it works'";
+    assert_eq!(CommandShell::Login.wrap(command), command);
+    assert_eq!(
+        CommandShell::Bash.wrap(command),
+        r"bash -lc '[[ -n $HOME ]] && echo '\''it works'\'''"
+    );
+    assert_eq!(CommandShell::Sh.wrap("echo hi"), "sh -c 'echo hi'");
+    assert_eq!(CommandShell::Zsh.wrap("echo hi"), "zsh -lc 'echo hi'");
+    assert_eq!(CommandShell::Fish.wrap("echo hi"), "fish -lc 'echo hi'");
+}
+
+#[test]
+fn wrapped_command_runs_unchanged() {
+    let command = r#"printf '%s|' "$0" 'a b' "it's" $((1 + 2))"#;
+    let output = std::process::Command::new("sh")
+        .args(["-c", &CommandShell::Sh.wrap(command)])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "sh|a b|it's|3|");
+}
+
+#[test]
+fn shell_names_parse() {
+    let shell: CommandShell = serde_json::from_str(r#""bash""#).unwrap();
+    assert_eq!(shell, CommandShell::Bash);
+    assert_eq!(CommandShell::Login.to_string(), "login");
+}
+
+// --- background job IDs ---
+
+#[test]
+fn background_job_id_round_trips() {
+    let log = background_log_file("1760601234.512").unwrap();
+    assert_eq!(log, "/tmp/ssh-hub-bg-1760601234.512.log");
+    assert_eq!(background_job_id(&log), Some("1760601234.512"));
+}
+
+#[test]
+fn background_job_id_rejects_other_paths() {
+    assert_eq!(background_log_file(""), None);
+    assert_eq!(background_log_file("1; rm -rf /"), None);
+    assert_eq!(background_log_file("../etc/passwd"), None);
+    assert_eq!(background_job_id("/tmp/other.log"), None);
+    assert_eq!(background_job_id("/tmp/ssh-hub-bg-x.log"), None);
+}
+
+// --- compressed output ---
+
+fn run_sh(command: &str) -> ExecRawResult {
+    let output = std::process::Command::new("sh")
+        .args(["-c", command])
+        .output()
+        .unwrap();
+    ExecRawResult {
+        stdout: output.stdout,
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        exit_code: output.status.code().unwrap(),
+    }
+}
+
+#[test]
+fn compressed_output_round_trips() {
+    let raw = run_sh(&compress_command("seq 1 100000; echo oops >&2; exit 3"));
+    let expected = (1..=100_000)
+        .map(|n| n.to_string() + "\n")
+        .collect::<String>();
+    assert_eq!(raw.stdout[0], b'Z');
+    assert!(raw.stdout.len() < expected.len() / 2);
+    let result = decode_compressed(raw).unwrap();
+    assert_eq!(result.stdout, expected);
+    assert_eq!(result.stderr, "oops\n");
+    assert_eq!(result.exit_code, 3);
+}
+
+#[test]
+fn compressed_command_follows_cd() {
+    let raw = run_sh(&format!(
+        "cd /nonexistent-ssh-hub-dir 2>/dev/null && {}",
+        compress_command("echo ran")
+    ));
+    assert!(raw.stdout.is_empty());
+    assert_ne!(raw.exit_code, 0);
+}
+
+#[test]
+fn decodes_plain_and_unwrapped_output() {
+    let raw = |stdout: &[u8]| ExecRawResult {
+        stdout: stdout.to_vec(),
+        stderr: String::new(),
+        exit_code: 0,
+    };
+    assert_eq!(decode_compressed(raw(b"Phello")).unwrap().stdout, "hello");
+    assert_eq!(decode_compressed(raw(b"hello")).unwrap().stdout, "hello");
+    assert_eq!(decode_compressed(raw(b"")).unwrap().stdout, "");
+    assert!(decode_compressed(raw(b"Znot gzip")).is_err());
+}
+
+#[tokio::test]
+async fn compresses_after_large_output() {
+    let sizes = OutputSizes::new();
+    assert!(!sizes.should_compress("web", "cat log", None).await);
+    sizes.record("web", "cat log", COMPRESS_THRESHOLD + 1).await;
+    assert!(sizes.should_compress("web", "cat log", None).await);
+    assert!(!sizes.should_compress("web", "cat log", Some(false)).await);
+    assert!(!sizes.should_compress("db", "cat log", None).await);
+    sizes.record("web", "cat log", 10).await;
+    assert!(!sizes.should_compress("web", "cat log", None).await);
+    assert!(sizes.should_compress("web", "ls", Some(true)).await);
+}
+
+#[test]
+fn partial_compressed_output_keeps_what_decodes() {
+    let raw = run_sh(&compress_command("seq 1 100000"));
+    let cut = ExecRawResult {
+        stdout: raw.stdout[..raw.stdout.len() / 2].to_vec(),
+        stderr: String::new(),
+        exit_code: -1,
+    };
+    assert!(decode_compressed(cut.clone()).is_err());
+    let partial = decode_compressed_partial(cut);
+    assert!(partial.stdout.starts_with("1\n2\n3\n"));
+    assert!(partial.stdout.len() < 588_895);
+}
+
+// --- cancellation ---
+
+#[test]
+fn pid_marker_is_split_out_of_stderr() {
+    assert_eq!(
+        split_pid_marker(b"ssh-hub-pid:4242\nwarning: x\n"),
+        (Some(4242), b"warning: x\n".to_vec())
+    );
+    // Login scripts may print first
+    assert_eq!(
+        split_pid_marker(b"motd\nssh-hub-pid:7\nerr"),
+        (Some(7), b"motd\nerr".to_vec())
+    );
+    assert_eq!(split_pid_marker(b"ssh-hub-pid:7"), (Some(7), Vec::new()));
+    assert_eq!(
+        split_pid_marker(b"ssh-hub-pid:x\n"),
+        (None, b"ssh-hub-pid:x\n".to_vec())
+    );
+    assert_eq!(split_pid_marker(b""), (None, Vec::new()));
+}
+
+#[test]
+fn kill_group_command_stops_the_command_and_its_children() {
+    // Its own process group, as sshd starts each session
+    let mut command = std::process::Command::new("sh")
+        .args([
+            "-c",
+            "echo ssh-hub-pid:$$ >&2; sleep 30 & echo $! >&2; sleep 30",
+        ])
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()
+        .unwrap();
+    let mut lines = BufReader::new(command.stderr.take().unwrap()).lines();
+    let (pid, _) = split_pid_marker(lines.next().unwrap().unwrap().as_bytes());
+    let child: u32 = lines.next().unwrap().unwrap().parse().unwrap();
+
+    let killed = run_sh(&kill_group_command(pid.unwrap()));
+    assert_eq!(killed.exit_code, 0);
+    assert!(!command.wait().unwrap().success());
+    // Orphans may linger as zombies when nothing reaps them
+    let state = run_sh(&format!("ps -o stat= -p {child}"));
+    let state = String::from_utf8_lossy(&state.stdout);
+    assert!(
+        state.trim().is_empty() || state.starts_with('Z'),
+        "the background child is still running: {state}"
+    );
+}
+
+#[tokio::test]
+async fn cancelled_fake_commands_keep_their_output() {
+    let fake = FakeExecutor::new("/srv").on_command("make", "built\n");
+    let finished = fake
+        .exec_raw_cancellable("make", None, None, std::future::pending())
+        .await
+        .unwrap();
+    assert!(matches!(finished, ExecOutcome::Finished(_)));
+    let (raw, cancelled) = fake
+        .exec_raw_cancellable("make", None, None, std::future::ready(()))
+        .await
+        .unwrap()
+        .into_parts();
+    assert!(cancelled);
+    assert_eq!(raw.stdout, b"built\n");
+}
@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use ssh_hub::connection::{ExecResult, FakeExecutor};
+use ssh_hub::server_registry::ServerRegistry;
+use ssh_hub::timeout::TimeoutPolicy;
+use ssh_hub::tools::provision::package::PackageManager;
+use ssh_hub::tools::remote_bootstrap::handler::{handle, install_command, parse_found, Need};
+use ssh_hub::tools::{ErrorCode, RemoteBootstrapInput, ToolError};
+
+fn input(value: Value) -> RemoteBootstrapInput {
+    serde_json::from_value(value).unwrap()
+}
+
+async fn run(conn: FakeExecutor, input: RemoteBootstrapInput, configured: &[&str]) -> Value {
+    let configured = configured.iter().map(ToString::to_string).collect();
+    let output = handle(Arc::new(conn), input, configured, TimeoutPolicy::default()).await;
+    serde_json::from_str(&output).unwrap_or_else(|_| panic!("not JSON: {output}"))
+}
+
+#[test]
+fn test_needs_parse_command_and_package() {
+    assert_eq!(
+        Need::parse("rg").unwrap(),
+        Need {
+            command: "rg".to_string(),
+            package: None
+        }
+    );
+    assert_eq!(
+        Need::parse("fd = fd-find").unwrap().package.as_deref(),
+        Some("fd-find")
+    );
+    assert!(Need::parse("jq; rm -rf /").is_err());
+    assert!(Need::parse("-rf").is_err());
+}
+
+#[test]
+fn test_packages_for_renamed_commands() {
+    assert_eq!(PackageManager::Apt.package_for("rg"), "ripgrep");
+    assert_eq!(PackageManager::Apt.package_for("dig"), "dnsutils");
+    assert_eq!(PackageManager::Dnf.package_for("dig"), "bind-utils");
+    assert_eq!(PackageManager::Pacman.package_for("python3"), "python");
+    assert_eq!(PackageManager::Apk.package_for("jq"), "jq");
+
+    let needs = [Need::parse("rg").unwrap(), Need::parse("pip3").unwrap()];
+    let missing: Vec<&Need> = needs.iter().collect();
+    assert_eq!(
+        install_command(PackageManager::Apt, &missing, true),
+        "sudo -n sh -c 'env DEBIAN_FRONTEND=noninteractive apt-get install -y '\\''ripgrep'\\'' '\\''python3-pip'\\'''"
+    );
+    assert_eq!(
+        install_command(PackageManager::Brew, &missing[..1], true),
+        "brew install 'ripgrep'"
+    );
+}
+
+#[test]
+fn test_parse_found_skips_missing_commands() {
+    let found = parse_found("rg\t/usr/bin/rg\njq\t\npython3\t/usr/bin/python3\n");
+    assert_eq!(found.len(), 2);
+    assert_eq!(found["rg"], "/usr/bin/rg");
+    assert!(!found.contains_key("jq"));
+}
+
+#[tokio::test]
+async fn test_check_reports_missing_with_install_command() {
+    // Package manager detection runs `command -v` too, so it is matched first
+    let conn = FakeExecutor::new("/srv")
+        .on_command("for m in apt-get", "apt-get\n")
+        .on_command("command -v", "rg\t/usr/bin/rg\njq\t\n");
+    let output = run(conn, input(json!({"server": "web"})), &["rg", "jq"]).await;
+    assert_eq!(output["package_manager"], "apt-get");
+    assert_eq!(output["all_available"], false);
+    assert_eq!(
+        output["tools"],
+        json!([
+            {"command": "rg", "status": "available", "path": "/usr/bin/rg"},
+            {"command": "jq", "status": "missing", "package": "jq"},
+        ])
+    );
+    assert_eq!(
+        output["install_command"],
+        "env DEBIAN_FRONTEND=noninteractive apt-get install -y 'jq'"
+    );
+}
+
+#[tokio::test]
+async fn test_dry_run_installs_nothing() {
+    let conn = Arc::new(
+        FakeExecutor::new("/srv")
+            .on_command("for m in apt-get", "apk\n")
+            .on_command("command -v", "rg\t\n"),
+    );
+    let output = handle(
+        Arc::clone(&conn),
+        input(json!({"server": "web", "needs": ["rg"], "install": true, "dry_run": true})),
+        Vec::new(),
+        TimeoutPolicy::default(),
+    )
+    .await;
+    let output: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(output["dry_run"], true);
+    assert_eq!(output["tools"][0]["status"], "would_install");
+    assert_eq!(output["install_command"], "apk add 'ripgrep'");
+    assert!(!conn.commands().iter().any(|c| c.starts_with("apk add")));
+}
+
+#[tokio::test]
+async fn test_install_failure_is_reported_per_command() {
+    let conn = FakeExecutor::new("/srv")
+        .on_command("for m in apt-get", "dnf\n")
+        .on_command("command -v", "jq\t\n")
+        .on_command_result(
+            "dnf install",
+            ExecResult {
+                stdout: String::new(),
+                stderr: "Error: This command has to be run with superuser privileges".to_string(),
+                exit_code: 1,
+            },
+        );
+    let output = run(
+        conn,
+        input(json!({"server": "web", "needs": ["jq"], "install": true})),
+        &[],
+    )
+    .await;
+    assert_eq!(output["all_available"], false);
+    assert_eq!(output["tools"][0]["status"], "failed");
+    assert_eq!(
+        output["tools"][0]["detail"],
+        "exit 1: Error: This command has to be run with superuser privileges"
+    );
+}
+
+#[tokio::test]
+async fn test_nothing_to_check_is_an_input_error() {
+    let output = handle(
+        Arc::new(FakeExecutor::new("/srv")),
+        input(json!({"server": "web"})),
+        Vec::new(),
+        TimeoutPolicy::default(),
+    )
+    .await;
+    let error = ToolError::from_json(&output).unwrap();
+    assert_eq!(error.code, ErrorCode::InvalidInput);
+    assert!(error.message.starts_with("needs must list"), "{output}");
+}
+
+#[test]
+fn test_needs_from_config_and_defaults() {
+    let cfg = ServerRegistry::parse(
+        r#"
+        [defaults]
+        needs = ["jq"]
+
+        [servers.web]
+        host = "10.0.0.1"
+        user = "deploy"
+        needs = ["rg", "fd=fd-find"]
+
+        [servers.db]
+        host = "10.0.0.2"
+        user = "deploy"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(cfg.get("web").unwrap().needs, ["rg", "fd=fd-find"]);
+    assert_eq!(cfg.get("db").unwrap().needs, ["jq"]);
+}
@@ -0,0 +1,120 @@
+use std::process::Command;
+
+use ssh_hub::tools::remote_disk::handler::{du_command, parse_df, parse_du, summarize_du};
+use ssh_hub::tools::remote_disk::EntryUsage;
+
+const GNU_DF: &str = "\
+Filesystem     1024-blocks      Used Available Capacity Mounted on
+/dev/sda1         41152736  30146120   9393088      77% /
+tmpfs              8144932         0   8144932       0% /dev/shm
+proc                     0         0         0       -  /proc
+/dev/sdb1        103081248  51540624  46281952      53% /mnt/data disk
+";
+
+const MACOS_DF: &str = "\
+Filesystem    1024-blocks      Used Available Capacity  Mounted on
+/dev/disk3s1s1  482797652  10003716 169612404     6%    /
+map auto_home           0         0         0   100%    /System/Volumes/Data/home
+";
+
+#[test]
+fn parses_gnu_df() {
+    let filesystems = parse_df(GNU_DF);
+    assert_eq!(filesystems.len(), 4);
+    let root = &filesystems[0];
+    assert_eq!(root.filesystem, "/dev/sda1");
+    assert_eq!(root.size_bytes, 41_152_736 * 1024);
+    assert_eq!(root.used_bytes, 30_146_120 * 1024);
+    assert_eq!(root.available_bytes, 9_393_088 * 1024);
+    assert_eq!(root.use_percent, 77);
+    assert_eq!(root.mount, "/");
+
+    assert_eq!(filesystems[2].mount, "/proc");
+    assert_eq!(filesystems[2].use_percent, 0);
+    assert_eq!(filesystems[3].mount, "/mnt/data disk");
+}
+
+#[test]
+fn parses_bsd_df_with_spaces_in_device() {
+    let filesystems = parse_df(MACOS_DF);
+    assert_eq!(filesystems.len(), 2);
+    assert_eq!(filesystems[1].filesystem, "map auto_home");
+    assert_eq!(filesystems[1].use_percent, 100);
+    assert_eq!(filesystems[1].mount, "/System/Volumes/Data/home");
+}
+
+#[test]
+fn skips_df_header_and_noise() {
+    assert!(parse_df("Filesystem 1024-blocks Used Available Capacity Mounted on\n").is_empty());
+    assert!(parse_df("df: /nope: No such file or directory\n").is_empty());
+}
+
+#[test]
+fn parses_du_lines() {
+    let entries = parse_du("12\tsrc\n4\t.hidden dir\nbogus line\n");
+    assert_eq!(
+        entries,
+        vec![
+            EntryUsage {
+                name: "src".to_string(),
+                bytes: 12 * 1024,
+            },
+            EntryUsage {
+                name: ".hidden dir".to_string(),
+                bytes: 4 * 1024,
+            },
+        ]
+    );
+}
+
+#[test]
+fn summarize_du_sorts_and_limits() {
+    let entries = parse_du("4\ta\n400\tb\n40\tc\n40\td\n");
+    let output = summarize_du("/srv".to_string(), entries, 2, false);
+    assert_eq!(output.total_bytes, 484 * 1024);
+    let names: Vec<&str> = output.entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["b", "c"]);
+    assert_eq!(output.omitted, 2);
+}
+
+#[test]
+fn du_command_lists_every_entry() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("it's here");
+    std::fs::create_dir_all(root.join("big")).unwrap();
+    std::fs::write(root.join("big/blob"), vec![7u8; 64 * 1024]).unwrap();
+    std::fs::write(root.join(".env"), "x").unwrap();
+    std::fs::write(root.join("..odd"), "x").unwrap();
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(du_command(root.to_str().unwrap()))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let mut names: Vec<String> = parse_du(&String::from_utf8(output.stdout).unwrap())
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, ["..odd", ".env", "big"]);
+
+    // An empty directory prints nothing and succeeds
+    let empty = dir.path().join("empty");
+    std::fs::create_dir(&empty).unwrap();
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(du_command(empty.to_str().unwrap()))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    // A missing directory exits with 2
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(du_command(dir.path().join("nope").to_str().unwrap()))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,228 @@
+use std::sync::Arc;
+
+use serde_json::json;
+use ssh_hub::connection::FakeExecutor;
+use ssh_hub::timeout::TimeoutPolicy;
+use ssh_hub::tools::{remote_patch, ErrorCode, RemotePatchInput, ToolError};
+use ssh_hub::utils::diff::unified_diff;
+use ssh_hub::utils::patch::{apply_hunks, parse_patch, HunkLine};
+
+const MAIN: &str = "fn main() {\n    let a = 1;\n    let b = 2;\n    println!(\"{a}\");\n}\n";
+
+fn numbered(lines: usize) -> String {
+    (1..=lines)
+        .map(|n| format!("line {n}\n"))
+        .collect::<Vec<_>>()
+        .concat()
+}
+
+fn apply(content: &str, patch: &str) -> String {
+    let patches = parse_patch(patch).unwrap();
+    apply_hunks(content, &patches[0].hunks).unwrap().content
+}
+
+async fn run(conn: &Arc<FakeExecutor>, args: serde_json::Value) -> String {
+    let mut args = args;
+    args["server"] = json!("fake");
+    let input: RemotePatchInput = serde_json::from_value(args).unwrap();
+    remote_patch::handler::handle(Arc::clone(conn), input, TimeoutPolicy::default()).await
+}
+
+#[test]
+fn test_parse_git_diff() {
+    let patch = "diff --git a/src/main.rs b/src/main.rs\n\
+                 index 1234567..89abcde 100644\n\
+                 --- a/src/main.rs\n\
+                 +++ b/src/main.rs\n\
+                 @@ -1,3 +1,3 @@\n \
+                 fn main() {\n\
+                 -    let a = 1;\n\
+                 +    let a = 10;\n     \
+                 let b = 2;\n\
+                 --- /dev/null\n\
+                 +++ b/NOTES\n\
+                 @@ -0,0 +1 @@\n\
+                 +new\n";
+    let patches = parse_patch(patch).unwrap();
+    assert_eq!(patches.len(), 2);
+    assert_eq!(patches[0].path(), Some("src/main.rs"));
+    assert_eq!(patches[0].hunks[0].lines.len(), 4);
+    assert_eq!(
+        patches[0].hunks[0].lines[1],
+        HunkLine::Remove("    let a = 1;".to_string())
+    );
+    assert_eq!(patches[1].old_path, None);
+    assert_eq!(patches[1].path(), Some("NOTES"));
+    assert_eq!(patches[1].hunks[0].new_len, 1);
+}
+
+#[test]
+fn test_parse_errors_name_the_line() {
+    let err = parse_patch("@@ -1,2 +1,2 @@\n a\n-b\n").unwrap_err();
+    assert!(
+        err.contains("line 1") && err.contains("header says 2 and 2"),
+        "{err}"
+    );
+    let err = parse_patch("@@ -x +1 @@\n").unwrap_err();
+    assert!(err.contains("invalid hunk header"), "{err}");
+    assert!(parse_patch("just some text\n").is_err());
+}
+
+#[test]
+fn test_roundtrip_with_generated_diffs() {
+    let old = numbered(40);
+    let new = old
+        .replace("line 3\n", "line three\n")
+        .replace("line 20\n", "line 20\nline 20.5\n")
+        .replace("line 38\n", "");
+    let diff = unified_diff(&old, &new, "f");
+    assert_eq!(parse_patch(&diff).unwrap()[0].hunks.len(), 3);
+    assert_eq!(apply(&old, &diff), new);
+}
+
+#[test]
+fn test_hunks_apply_at_an_offset() {
+    let old = numbered(10);
+    let diff = unified_diff(&old, &old.replace("line 8\n", "line eight\n"), "f");
+    // Five lines were added at the top since the diff was made
+    let current = format!("{}{old}", "header\n".repeat(5));
+    let patches = parse_patch(&diff).unwrap();
+    let applied = apply_hunks(&current, &patches[0].hunks).unwrap();
+    assert_eq!(applied.offsets, vec![5]);
+    assert!(applied.content.contains("line 7\nline eight\nline 9\n"));
+}
+
+#[test]
+fn test_failures_report_every_hunk_and_the_differing_line() {
+    let old = numbered(30);
+    let new = old
+        .replace("line 2\n", "line two\n")
+        .replace("line 25\n", "line twenty-five\n");
+    let diff = unified_diff(&old, &new, "f");
+    let current = old
+        .replace("line 3\n", "line 3 edited\n")
+        .replace("line 24\n", "line 24 edited\n");
+    let patches = parse_patch(&diff).unwrap();
+    let failures = apply_hunks(&current, &patches[0].hunks).unwrap_err();
+    assert_eq!(failures.len(), 2);
+    assert_eq!(failures[0].index, 1);
+    assert_eq!(
+        failures[0].to_string(),
+        "hunk 1 (@@ -1,5 +1,5 @@): expected \"line 3\" at line 3, found \"line 3 edited\", \
+         and the hunk's lines are not found elsewhere"
+    );
+    assert!(failures[1].reason.contains("line 24"), "{}", failures[1]);
+}
+
+#[test]
+fn test_line_endings_and_missing_final_newline() {
+    let crlf = "a\r\nb\r\nc\r\n";
+    assert_eq!(
+        apply(crlf, "@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"),
+        "a\r\nB\r\nc\r\n"
+    );
+
+    let no_newline = "a\nb";
+    let diff = unified_diff(no_newline, "a\nB", "f");
+    assert!(diff.contains("\\ No newline at end of file"), "{diff}");
+    assert_eq!(apply(no_newline, &diff), "a\nB");
+    // Adding the final newline
+    assert_eq!(
+        apply(no_newline, &unified_diff(no_newline, "a\nb\n", "f")),
+        "a\nb\n"
+    );
+}
+
+#[tokio::test]
+async fn test_patch_several_files() {
+    let conn = Arc::new(
+        FakeExecutor::new("/srv/app")
+            .with_file("/srv/app/src/main.rs", MAIN)
+            .with_file("/srv/app/Cargo.toml", "[package]\nname = \"app\"\n"),
+    );
+    let patch = format!(
+        "{}{}--- /dev/null\n+++ b/CHANGES\n@@ -0,0 +1 @@\n+renamed a\n",
+        unified_diff(MAIN, &MAIN.replace('a', "x"), "a/src/main.rs").replace("+++ a/", "+++ b/"),
+        "--- a/Cargo.toml\n+++ b/Cargo.toml\n@@ -2 +2 @@\n-name = \"app\"\n+name = \"app2\"\n",
+    );
+
+    let preview = run(&conn, json!({"patch": patch, "preview": true})).await;
+    assert!(
+        preview.starts_with("Preview of patch to 3 files"),
+        "{preview}"
+    );
+    assert_eq!(conn.file("/srv/app/CHANGES"), None);
+
+    let output = run(&conn, json!({"patch": patch})).await;
+    assert!(
+        output.starts_with(
+            "Patched /srv/app/src/main.rs (1 hunk)\n\
+             Patched /srv/app/Cargo.toml (1 hunk)\n\
+             Created /srv/app/CHANGES (1 hunk)"
+        ),
+        "{output}"
+    );
+    assert_eq!(
+        conn.file("/srv/app/src/main.rs").unwrap(),
+        MAIN.replace('a', "x").as_bytes()
+    );
+    assert_eq!(
+        conn.file("/srv/app/Cargo.toml").unwrap(),
+        b"[package]\nname = \"app2\"\n"
+    );
+    assert_eq!(conn.file("/srv/app/CHANGES").unwrap(), b"renamed a\n");
+
+    // Creating it again fails
+    let output = run(
+        &conn,
+        json!({"patch": "--- /dev/null\n+++ CHANGES\n@@ -0,0 +1 @@\n+x\n"}),
+    )
+    .await;
+    assert!(output.contains("already exists"), "{output}");
+}
+
+#[tokio::test]
+async fn test_failed_hunk_writes_nothing() {
+    let conn = Arc::new(
+        FakeExecutor::new("/srv/app")
+            .with_file("/srv/app/a.txt", "one\ntwo\n")
+            .with_file("/srv/app/b.txt", "three\nfour\n"),
+    );
+    let patch = "--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,2 @@\n one\n-two\n+2\n\
+                 --- a/b.txt\n+++ b/b.txt\n@@ -1,2 +1,2 @@\n three\n-five\n+5\n";
+    let output = run(&conn, json!({"patch": patch})).await;
+    let error = ToolError::from_json(&output).unwrap();
+    assert_eq!(error.code, ErrorCode::InvalidInput);
+    assert!(
+        error
+            .message
+            .starts_with("patch did not apply, so no file was changed. 1 of 2 hunks"),
+        "{output}"
+    );
+    assert!(
+        error.message.contains(
+            "/srv/app/b.txt: hunk 1 (@@ -1,2 +1,2 @@): expected \"five\" at line 2, found \"four\""
+        ),
+        "{output}"
+    );
+    assert_eq!(conn.file("/srv/app/a.txt").unwrap(), b"one\ntwo\n");
+}
+
+#[tokio::test]
+async fn test_bare_hunks_need_file_path() {
+    let conn = Arc::new(FakeExecutor::new("/srv/app").with_file("/etc/app.conf", "a=1\nb=1\n"));
+    let patch = "@@ -1,2 +1,2 @@\n a=1\n-b=1\n+b=2\n";
+    let output = run(&conn, json!({"patch": patch})).await;
+    assert!(output.contains("need file_path"), "{output}");
+
+    let output = run(&conn, json!({"patch": patch, "file_path": "/etc/app.conf"})).await;
+    assert!(
+        output.starts_with("Patched /etc/app.conf (1 hunk)"),
+        "{output}"
+    );
+    assert_eq!(conn.file("/etc/app.conf").unwrap(), b"a=1\nb=2\n");
+
+    let deletion = "--- a/app.conf\n+++ /dev/null\n@@ -1,2 +0,0 @@\n-a=1\n-b=2\n";
+    let output = run(&conn, json!({"patch": deletion})).await;
+    assert!(output.contains("not supported"), "{output}");
+}
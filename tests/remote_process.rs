@@ -0,0 +1,143 @@
+use std::process::Command;
+
+use ssh_hub::tools::remote_process::handler::{
+    kill_command, parse_kill, parse_ps, parse_signal, select_processes, KillResult,
+};
+use ssh_hub::tools::remote_process::{PsSort, RemotePsInput};
+
+const PS_OUTPUT: &str = "\
+SELF=4174
+    1     0 root      0.2  0.1 Fri Oct 16 12:42:55 2026 /sbin/init splash
+  812     1 www-data 12.5  3.0 Fri Oct 16 12:43:10 2026 nginx: worker process
+  900     1 deploy   48.0  9.5 Fri Oct 16 13:01:02 2026 python3  train.py --epochs 10
+ 4175  4174 deploy    0.0  0.0 Fri Oct 16 14:57:01 2026 ps -eo pid=,ppid=,user=
+garbage
+";
+
+fn input() -> RemotePsInput {
+    RemotePsInput {
+        server: "web1".to_string(),
+        filter: None,
+        user: None,
+        sort: None,
+        limit: None,
+    }
+}
+
+#[test]
+fn parses_ps_output() {
+    let processes = parse_ps(PS_OUTPUT);
+    assert_eq!(processes.len(), 3, "the probe's own ps is left out");
+    let train = &processes[2];
+    assert_eq!(train.pid, 900);
+    assert_eq!(train.ppid, 1);
+    assert_eq!(train.user, "deploy");
+    assert!((train.cpu - 48.0).abs() < f32::EPSILON);
+    assert_eq!(train.started, "Fri Oct 16 13:01:02 2026");
+    assert_eq!(train.command, "python3  train.py --epochs 10");
+    assert_eq!(processes[1].command, "nginx: worker process");
+}
+
+#[test]
+fn filters_sorts_and_limits() {
+    let processes = parse_ps(PS_OUTPUT);
+
+    let out = select_processes(processes.clone(), &input());
+    let pids: Vec<u32> = out.processes.iter().map(|p| p.pid).collect();
+    assert_eq!(pids, vec![900, 812, 1]);
+    assert!(!out.truncated);
+
+    let out = select_processes(
+        processes.clone(),
+        &RemotePsInput {
+            filter: Some("nginx".to_string()),
+            ..input()
+        },
+    );
+    assert_eq!(out.processes.len(), 1);
+    assert_eq!(out.processes[0].user, "www-data");
+
+    let out = select_processes(
+        processes.clone(),
+        &RemotePsInput {
+            user: Some("root".to_string()),
+            ..input()
+        },
+    );
+    assert_eq!(out.processes[0].pid, 1);
+
+    let out = select_processes(
+        processes,
+        &RemotePsInput {
+            sort: Some(PsSort::Pid),
+            limit: Some(2),
+            ..input()
+        },
+    );
+    let pids: Vec<u32> = out.processes.iter().map(|p| p.pid).collect();
+    assert_eq!(pids, vec![1, 812]);
+    assert_eq!(out.matched, 3);
+    assert!(out.truncated);
+}
+
+#[test]
+fn normalizes_signals() {
+    assert_eq!(parse_signal("TERM"), Ok("TERM"));
+    assert_eq!(parse_signal("sigkill"), Ok("KILL"));
+    assert_eq!(parse_signal("9"), Ok("KILL"));
+    assert_eq!(parse_signal(" hup "), Ok("HUP"));
+    assert!(
+        parse_signal("10").is_err(),
+        "USR1's number differs by platform"
+    );
+    assert!(parse_signal("SEGV").is_err());
+}
+
+#[test]
+fn parses_kill_results() {
+    assert_eq!(parse_kill("MISSING\n"), KillResult::Missing);
+    assert_eq!(
+        parse_kill("CMD=sleep 100\nMISMATCH\n"),
+        KillResult::Mismatch("sleep 100".to_string())
+    );
+    assert_eq!(
+        parse_kill("CMD=sleep 100\nALIVE\nSENT\n"),
+        KillResult::Sent {
+            command: "sleep 100".to_string(),
+            still_running: true
+        }
+    );
+    assert_eq!(
+        parse_kill("CMD=sleep 100\n"),
+        KillResult::Failed("sleep 100".to_string())
+    );
+}
+
+fn run_sh(command: &str) -> String {
+    let output = Command::new("sh").arg("-c").arg(command).output().unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn kill_command_checks_the_command_line() {
+    let mut child = Command::new("sleep").arg("300").spawn().unwrap();
+    let pid = child.id();
+
+    let out = run_sh(&kill_command(pid, "nginx", "TERM"));
+    assert_eq!(
+        parse_kill(&out),
+        KillResult::Mismatch("sleep 300".to_string())
+    );
+    assert!(child.try_wait().unwrap().is_none(), "no signal was sent");
+
+    // Quotes in the pattern are matched literally.
+    let out = run_sh(&kill_command(pid, "sleep' 3", "TERM"));
+    assert!(matches!(parse_kill(&out), KillResult::Mismatch(_)));
+
+    let out = run_sh(&kill_command(pid, "sleep 3", "KILL"));
+    assert!(matches!(parse_kill(&out), KillResult::Sent { .. }));
+    assert!(!child.wait().unwrap().success());
+
+    let out = run_sh(&kill_command(pid, "sleep", "TERM"));
+    assert_eq!(parse_kill(&out), KillResult::Missing);
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use ssh_hub::connection::SshConnection;
+use ssh_hub::timeout::TimeoutPolicy;
+use ssh_hub::tools::remote_rollout::handler::{output_tail, plan, rollout};
+use ssh_hub::tools::remote_rollout::{OutcomeStatus, RemoteRolloutInput};
+use ssh_hub::tools::{ErrorCode, ToolError};
+
+fn input(servers: &[&str]) -> RemoteRolloutInput {
+    RemoteRolloutInput {
+        servers: servers.iter().map(ToString::to_string).collect(),
+        command: "systemctl restart app".to_string(),
+        canary: None,
+        batch_size: None,
+        expect_exit_code: None,
+        expect_output: None,
+        max_failures: None,
+        timeout: None,
+        override_window: None,
+    }
+}
+
+fn no_policy(_server: &str) -> TimeoutPolicy {
+    TimeoutPolicy::default()
+}
+
+#[test]
+fn test_plan_canary_and_batches() {
+    let mut req = input(&["a", "b", "c", "d", "e"]);
+    req.canary = Some("c".to_string());
+    req.batch_size = Some(2);
+    let p = plan(req, no_policy).unwrap();
+    assert_eq!(p.canary, "c");
+    assert_eq!(p.batches, [vec!["a", "b"], vec!["d", "e"]]);
+    assert_eq!(p.max_failures, 0);
+    assert_eq!(p.timeouts["c"], Some(120_000));
+}
+
+#[test]
+fn test_plan_defaults() {
+    let p = plan(input(&["a", "b", "c"]), no_policy).unwrap();
+    assert_eq!(p.canary, "a");
+    assert_eq!(p.batch_size, 1);
+    assert_eq!(p.batches, [vec!["b"], vec!["c"]]);
+
+    let mut req = input(&["a"]);
+    req.batch_size = Some(0);
+    req.timeout = Some(10_000_000);
+    let p = plan(req, no_policy).unwrap();
+    assert_eq!(p.batch_size, 1);
+    assert!(p.batches.is_empty());
+    assert_eq!(p.timeouts["a"], Some(600_000));
+}
+
+#[test]
+fn test_plan_rejects_invalid_input() {
+    assert!(plan(input(&[]), no_policy)
+        .unwrap_err()
+        .contains("at least one"));
+    assert!(plan(input(&["a", "b", "a"]), no_policy)
+        .unwrap_err()
+        .contains("more than once"));
+
+    let mut req = input(&["a", "b"]);
+    req.canary = Some("z".to_string());
+    assert!(plan(req, no_policy).unwrap_err().contains("canary"));
+
+    let mut req = input(&["a"]);
+    req.expect_output = Some("(".to_string());
+    assert!(plan(req, no_policy).unwrap_err().contains("regex"));
+
+    let mut req = input(&["a"]);
+    req.command = "nohup ./server &".to_string();
+    assert!(plan(req, no_policy).unwrap_err().contains("backgrounding"));
+
+    // No timeout needs every server to allow it
+    let policy = |server: &str| TimeoutPolicy {
+        unlimited: server == "trusted",
+        ..TimeoutPolicy::default()
+    };
+    let mut req = input(&["trusted", "b"]);
+    req.timeout = Some(0);
+    let err = plan(req, policy).unwrap_err();
+    assert!(err.starts_with("server 'b': timeout 0"), "{err}");
+    let mut req = input(&["trusted"]);
+    req.timeout = Some(0);
+    assert_eq!(plan(req, policy).unwrap().timeouts["trusted"], None);
+}
+
+#[test]
+fn test_assertion_check() {
+    let mut req = input(&["a"]);
+    req.expect_output = Some(r"active \(running\)".to_string());
+    let assertion = plan(req, no_policy).unwrap().assertion;
+    assert!(assertion.check(0, "Active: active (running)\n").is_ok());
+    assert!(assertion
+        .check(0, "Active: failed\n")
+        .unwrap_err()
+        .contains("does not match"));
+    assert!(assertion
+        .check(3, "active (running)")
+        .unwrap_err()
+        .contains("exit code 3"));
+
+    let mut req = input(&["a"]);
+    req.expect_exit_code = Some(3);
+    assert!(plan(req, no_policy).unwrap().assertion.check(3, "").is_ok());
+}
+
+#[test]
+fn test_output_tail() {
+    let stdout = (1..=30)
+        .map(|i| format!("line {i}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let tail = output_tail(&stdout, "");
+    assert!(tail.starts_with("line 11\n"));
+    assert!(tail.ends_with("line 30"));
+
+    assert_eq!(output_tail("", "boom\n"), "[stderr]\nboom");
+    assert_eq!(output_tail("ok\n", "warn\n"), "ok\n[stderr]\nwarn");
+    assert_eq!(output_tail("", ""), "");
+}
+
+#[tokio::test]
+async fn test_failed_canary_skips_the_rest() {
+    let mut req = input(&["a", "b", "c"]);
+    req.batch_size = Some(2);
+    let p = plan(req, no_policy).unwrap();
+    let report = rollout(&p, |server| async move {
+        let message = format!("server '{server}' is unreachable");
+        Err::<Arc<SshConnection>, _>(ToolError::new(ErrorCode::ConnectionFailed, message))
+    })
+    .await;
+
+    assert_eq!(report.failed, 1);
+    assert_eq!(report.skipped, 2);
+    assert_eq!(report.passed, 0);
+    assert!(report.aborted.unwrap().contains("canary 'a' failed"));
+    assert_eq!(report.servers[0].status, OutcomeStatus::Failed);
+    assert_eq!(
+        report.servers[0].reason.as_deref(),
+        Some("server 'a' is unreachable")
+    );
+    assert_eq!(report.servers[1].batch, 1);
+    assert_eq!(report.servers[2].status, OutcomeStatus::Skipped);
+}
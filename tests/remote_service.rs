@@ -0,0 +1,196 @@
+use std::sync::Arc;
+
+use serde_json::json;
+use ssh_hub::connection::{ExecResult, FakeExecutor};
+use ssh_hub::timeout::TimeoutPolicy;
+use ssh_hub::tools::remote_service::handler::{
+    action_command, handle, logs_command, parse_backend, parse_compose_state, parse_service_status,
+    parse_systemd_show, service_script, status_command,
+};
+use ssh_hub::tools::remote_service::{RemoteServiceInput, ServiceAction, ServiceBackend};
+use ssh_hub::tools::{ErrorCode, ToolError};
+
+const SYSTEMCTL_SHOW: &str = "\
+LoadState=loaded
+ActiveState=active
+SubState=running
+UnitFileState=enabled
+MainPID=812
+ActiveEnterTimestamp=Fri 2026-10-16 12:43:10 UTC
+";
+
+fn input(value: serde_json::Value) -> RemoteServiceInput {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn parses_detected_backend() {
+    assert_eq!(parse_backend("systemd\n"), Some(ServiceBackend::Systemd));
+    assert_eq!(parse_backend("service"), Some(ServiceBackend::Service));
+    assert_eq!(parse_backend("compose"), Some(ServiceBackend::Compose));
+    assert_eq!(parse_backend("none"), None);
+}
+
+#[test]
+fn parses_systemctl_show() {
+    let state = parse_systemd_show(SYSTEMCTL_SHOW).unwrap();
+    assert_eq!(state.state, "active");
+    assert!(state.running);
+    assert_eq!(state.detail.as_deref(), Some("running"));
+    assert_eq!(state.enabled.as_deref(), Some("enabled"));
+    assert_eq!(state.pid, Some(812));
+    assert_eq!(state.since.as_deref(), Some("Fri 2026-10-16 12:43:10 UTC"));
+
+    let stopped = parse_systemd_show(
+        "LoadState=loaded\nActiveState=failed\nSubState=failed\nMainPID=0\nActiveEnterTimestamp=\n",
+    )
+    .unwrap();
+    assert!(!stopped.running);
+    assert_eq!(stopped.pid, None);
+    assert_eq!(stopped.since, None);
+
+    assert!(parse_systemd_show("LoadState=not-found\nActiveState=inactive\n").is_err());
+}
+
+#[test]
+fn parses_init_script_exit_codes() {
+    assert!(parse_service_status("EXIT=0\n").unwrap().running);
+    let stopped = parse_service_status("EXIT=3\n").unwrap();
+    assert_eq!(stopped.state, "stopped");
+    assert!(!stopped.running);
+    assert!(parse_service_status("EXIT=4\n").is_err());
+    let odd = parse_service_status("EXIT=1\n").unwrap();
+    assert_eq!(odd.state, "unknown");
+    assert_eq!(odd.detail.as_deref(), Some("status exited with 1"));
+}
+
+#[test]
+fn parses_compose_containers() {
+    let one = parse_compose_state("running 4242 healthy 2026-10-16T12:43:10.5Z\n");
+    assert_eq!(one.state, "running");
+    assert!(one.running);
+    assert_eq!(one.detail.as_deref(), Some("healthy"));
+    assert_eq!(one.pid, Some(4242));
+    assert_eq!(one.containers, Some(1));
+
+    let scaled =
+        parse_compose_state("running 10 - 2026-10-16T12:43:10Z\nexited 0 - 2026-10-16T12:40:00Z\n");
+    assert_eq!(scaled.state, "mixed");
+    assert!(!scaled.running);
+    assert_eq!(scaled.pid, None);
+    assert_eq!(scaled.detail, None);
+    assert_eq!(scaled.containers, Some(2));
+
+    let missing = parse_compose_state("");
+    assert_eq!(missing.state, "missing");
+    assert_eq!(missing.containers, Some(0));
+}
+
+#[test]
+fn builds_backend_commands() {
+    assert_eq!(
+        action_command(ServiceBackend::Systemd, ServiceAction::Restart, "nginx"),
+        "systemctl restart 'nginx'"
+    );
+    assert_eq!(
+        action_command(ServiceBackend::Service, ServiceAction::Stop, "nginx"),
+        "service 'nginx' stop"
+    );
+    assert_eq!(
+        action_command(ServiceBackend::Compose, ServiceAction::Start, "web"),
+        "dc up -d 'web'"
+    );
+    assert!(logs_command(ServiceBackend::Service, "nginx", 10).is_none());
+    assert!(logs_command(ServiceBackend::Systemd, "nginx", 10)
+        .unwrap()
+        .starts_with("journalctl -u 'nginx' -n 10"));
+    assert!(status_command(ServiceBackend::Compose, "web").contains("{{.State.Status}}"));
+
+    let script = service_script(
+        ServiceBackend::Compose,
+        Some("/srv/app"),
+        "dc stop 'web'",
+        false,
+    );
+    assert!(script.starts_with("dc() {"));
+    assert!(script.ends_with("cd '/srv/app' && dc stop 'web'"));
+    let sudo = service_script(ServiceBackend::Systemd, None, "systemctl stop 'x'", true);
+    assert!(sudo.starts_with("sudo -n sh -c "));
+}
+
+#[tokio::test]
+async fn detects_systemd_and_restarts() {
+    let conn = Arc::new(
+        FakeExecutor::new("~")
+            .on_command("/run/systemd/system", "systemd\n")
+            .on_command("systemctl restart", "")
+            .on_command("systemctl show", SYSTEMCTL_SHOW),
+    );
+    let output = handle(
+        conn.clone(),
+        input(json!({"server": "fake", "service": "nginx", "action": "restart"})),
+        TimeoutPolicy::default(),
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["backend"], "systemd");
+    assert_eq!(json["state"]["running"], true);
+    assert_eq!(conn.commands().len(), 3);
+}
+
+#[tokio::test]
+async fn reports_failed_action() {
+    let conn = Arc::new(FakeExecutor::new("~").on_command_result(
+        "systemctl start",
+        ExecResult {
+            stdout: String::new(),
+            stderr: "Job for app.service failed.\n".to_string(),
+            exit_code: 1,
+        },
+    ));
+    let output = handle(
+        conn.clone(),
+        input(json!({
+            "server": "fake", "service": "app", "action": "start", "backend": "systemd"
+        })),
+        TimeoutPolicy::default(),
+    )
+    .await;
+    assert_eq!(
+        ToolError::from_json(&output),
+        Some(ToolError::new(
+            ErrorCode::Failed,
+            "start app failed: exit 1: Job for app.service failed."
+        ))
+    );
+    assert_eq!(
+        conn.commands().len(),
+        1,
+        "no detection with an explicit backend"
+    );
+}
+
+#[tokio::test]
+async fn rejects_bad_names_and_init_script_logs() {
+    let conn = Arc::new(FakeExecutor::new("~"));
+    let output = handle(
+        conn.clone(),
+        input(json!({"server": "fake", "service": "x; reboot", "action": "status"})),
+        TimeoutPolicy::default(),
+    )
+    .await;
+    let error = ToolError::from_json(&output).unwrap();
+    assert_eq!(error.code, ErrorCode::InvalidInput);
+    assert!(error.message.starts_with("invalid service name"));
+
+    let output = handle(
+        conn.clone(),
+        input(json!({
+            "server": "fake", "service": "cron", "action": "logs", "backend": "service"
+        })),
+        TimeoutPolicy::default(),
+    )
+    .await;
+    assert!(output.contains("keeps no logs"), "{output}");
+    assert!(conn.commands().is_empty());
+}
@@ -0,0 +1,62 @@
+use ssh_hub::server_registry::RemoteShell;
+
+// --- working directory prefix ---
+
+#[test]
+fn posix_escapes_path_and_expands_home() {
+    assert_eq!(
+        RemoteShell::Posix.in_dir("~/my app", "ls"),
+        "cd $HOME/'my app' && ls"
+    );
+}
+
+#[test]
+fn powershell_maps_home() {
+    assert_eq!(
+        RemoteShell::Powershell.in_dir("~", "dir"),
+        "Set-Location -ErrorAction Stop -LiteralPath $HOME; dir"
+    );
+    assert_eq!(
+        RemoteShell::Powershell.in_dir("~/src/app", "dir"),
+        "Set-Location -ErrorAction Stop -LiteralPath (Join-Path $HOME 'src/app'); dir"
+    );
+}
+
+#[test]
+fn powershell_doubles_single_quotes() {
+    assert_eq!(
+        RemoteShell::Powershell.in_dir("C:/Users/o'brien", "dir"),
+        "Set-Location -ErrorAction Stop -LiteralPath 'C:/Users/o''brien'; dir"
+    );
+}
+
+#[test]
+fn cmd_maps_home_and_separators() {
+    assert_eq!(
+        RemoteShell::Cmd.in_dir("~", "dir"),
+        "cd /d \"%USERPROFILE%\" && dir"
+    );
+    assert_eq!(
+        RemoteShell::Cmd.in_dir("~/src/app", "dir"),
+        "cd /d \"%USERPROFILE%\\src\\app\" && dir"
+    );
+}
+
+#[test]
+fn cmd_drops_double_quotes() {
+    assert_eq!(
+        RemoteShell::Cmd.in_dir("C:\\a\"b", "dir"),
+        "cd /d \"C:\\ab\" && dir"
+    );
+}
+
+// --- display ---
+
+#[test]
+fn display_matches_config_names() {
+    assert_eq!(RemoteShell::Posix.to_string(), "posix");
+    assert_eq!(RemoteShell::Powershell.to_string(), "powershell");
+    assert_eq!(RemoteShell::Cmd.to_string(), "cmd");
+    assert!(RemoteShell::Posix.is_posix());
+    assert!(!RemoteShell::Cmd.is_posix());
+}
@@ -0,0 +1,219 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use ssh_hub::connection::{FakeExecutor, FileStat, RemoteCapabilities};
+use ssh_hub::server_registry::RemoteShell;
+use ssh_hub::timeout::TimeoutPolicy;
+use ssh_hub::tools::remote_stat::handler::{handle, parse_stat, stat_command, MAX_PATHS};
+use ssh_hub::tools::remote_stat::{EntryType, PathStat};
+use ssh_hub::tools::{ErrorCode, ToolError};
+
+const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+fn paths(names: &[&str]) -> Vec<String> {
+    names.iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn test_parse_gnu_find_output() {
+    let stdout = "S0\t5 1760601234.5120000000 644 deploy www-data f\n\
+                  H0\t2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824\n\
+                  S2\t4096 1760601000.0000000000 4755 root root d\n\
+                  S3\t11 1760600000.0 777 me me l\n\
+                  L3\t/srv/app/releases/42\n";
+    let stats = parse_stat(stdout, paths(&["a.txt", "gone", "bin", "current"]), true);
+    assert_eq!(
+        stats[0],
+        PathStat {
+            path: "a.txt".to_string(),
+            exists: true,
+            kind: Some(EntryType::File),
+            size: Some(5),
+            mtime: Some(1_760_601_234),
+            mode: Some("644".to_string()),
+            owner: Some("deploy".to_string()),
+            group: Some("www-data".to_string()),
+            sha256: Some(HELLO_SHA256.to_string()),
+            ..PathStat::default()
+        }
+    );
+    assert_eq!(
+        stats[1],
+        PathStat {
+            path: "gone".to_string(),
+            ..PathStat::default()
+        }
+    );
+    assert_eq!(stats[2].kind, Some(EntryType::Dir));
+    assert_eq!(stats[2].mode.as_deref(), Some("4755"));
+    assert_eq!(stats[2].sha256, None);
+    assert_eq!(stats[3].kind, Some(EntryType::Symlink));
+    assert_eq!(
+        stats[3].link_target.as_deref(),
+        Some("/srv/app/releases/42")
+    );
+    assert_eq!(stats[3].error, None);
+}
+
+#[test]
+fn test_parse_stat_words_and_failures() {
+    // BSD `stat -f` with %Mp%Lp, then GNU `stat -c`
+    let stdout = "S0\t0 1760601234 0644 me staff Regular File\n\
+                  S1\t0 1760601234 600 me me regular empty file\n\
+                  H1\t\n\
+                  S2\t\n\
+                  S3\t0 1760601234 660 root disk block special file\n\
+                  S4\t0 1760601234 666 root root Character Device\n\
+                  S5\t0 1760601234 755 me me Whiteout\n";
+    let stats = parse_stat(stdout, paths(&["a", "b", "c", "d", "e", "f"]), true);
+    assert_eq!(stats[0].kind, Some(EntryType::File));
+    assert_eq!(stats[0].mode.as_deref(), Some("644"));
+    assert_eq!(stats[0].error.as_deref(), Some("could not read it to hash"));
+    assert_eq!(stats[1].kind, Some(EntryType::File));
+    assert_eq!(stats[1].error.as_deref(), Some("could not read it to hash"));
+    assert!(stats[2].exists);
+    assert_eq!(
+        stats[2].error.as_deref(),
+        Some("could not read its attributes")
+    );
+    assert_eq!(stats[3].kind, Some(EntryType::BlockDevice));
+    assert_eq!(stats[4].kind, Some(EntryType::CharDevice));
+    assert_eq!(stats[5].kind, Some(EntryType::Other));
+}
+
+fn run_script(script: &str, stdin: &str) -> String {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_stat_scripts_against_local_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    std::fs::write(dir.join("hello.txt"), "hello").unwrap();
+    std::fs::create_dir(dir.join("sub dir")).unwrap();
+    std::os::unix::fs::symlink("hello.txt", dir.join("link")).unwrap();
+    let requested = paths(&["hello.txt", "sub dir", "link", "missing"]);
+    let stdin: String = requested
+        .iter()
+        .map(|name| format!("{}\n", dir.join(name).display()))
+        .collect::<Vec<_>>()
+        .concat();
+
+    for file_stat in [FileStat::FindPrintf, FileStat::StatC] {
+        let stdout = run_script(&stat_command(file_stat, Some("sha256sum")), &stdin);
+        let stats = parse_stat(&stdout, requested.clone(), true);
+        let kinds: Vec<_> = stats.iter().map(|s| s.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                Some(EntryType::File),
+                Some(EntryType::Dir),
+                Some(EntryType::Symlink),
+                None
+            ],
+            "{file_stat:?}: {stdout}"
+        );
+        assert_eq!(stats[0].size, Some(5));
+        assert_eq!(stats[0].sha256.as_deref(), Some(HELLO_SHA256));
+        assert_eq!(stats[2].link_target.as_deref(), Some("hello.txt"));
+        assert_eq!(stats[2].sha256, None);
+        assert!(!stats[3].exists);
+        assert!(stats.iter().all(|s| s.error.is_none()), "{stats:?}");
+    }
+
+    // Without hashing nothing is read
+    let stdout = run_script(&stat_command(FileStat::FindPrintf, None), &stdin);
+    assert!(!stdout.contains("H0"), "{stdout}");
+}
+
+async fn stat(conn: FakeExecutor, input: Value) -> String {
+    let input = serde_json::from_value(input).unwrap();
+    handle(
+        Arc::new(conn),
+        input,
+        RemoteCapabilities::default(),
+        TimeoutPolicy::default(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_stat_tool_output() {
+    let conn = FakeExecutor::new("/srv").on_command(
+        "while IFS= read -r p",
+        "S0\t5 1760601234.5 644 me me f\nH0\t2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824\n",
+    );
+    let output = stat(
+        conn,
+        json!({"server": "s", "paths": ["a.txt", "~/b"], "sha256": true}),
+    )
+    .await;
+    let value: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(
+        value,
+        json!({"paths": [
+            {
+                "path": "a.txt",
+                "exists": true,
+                "type": "file",
+                "size": 5,
+                "mtime": 1_760_601_234,
+                "mode": "644",
+                "owner": "me",
+                "group": "me",
+                "sha256": HELLO_SHA256,
+            },
+            {"path": "~/b", "exists": false},
+        ]})
+    );
+}
+
+#[tokio::test]
+async fn test_stat_tool_errors() {
+    let empty = stat(
+        FakeExecutor::new("/srv"),
+        json!({"server": "s", "paths": []}),
+    )
+    .await;
+    let empty = ToolError::from_json(&empty).unwrap();
+    assert_eq!(empty.code, ErrorCode::InvalidInput);
+    assert!(empty.message.starts_with("paths must"), "{empty:?}");
+    let many = vec!["x"; MAX_PATHS + 1];
+    let many = stat(
+        FakeExecutor::new("/srv"),
+        json!({"server": "s", "paths": many}),
+    )
+    .await;
+    assert!(many.contains("at most 1000"), "{many}");
+    let newline = stat(
+        FakeExecutor::new("/srv"),
+        json!({"server": "s", "paths": ["a\nb"]}),
+    )
+    .await;
+    let newline = ToolError::from_json(&newline).unwrap();
+    assert!(newline.message.starts_with("invalid path"), "{newline:?}");
+    let windows = stat(
+        FakeExecutor::new("C:/app").with_shell(RemoteShell::Powershell),
+        json!({"server": "s", "paths": ["a"]}),
+    )
+    .await;
+    assert!(windows.contains("requires a POSIX shell"), "{windows}");
+}
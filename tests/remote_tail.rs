@@ -0,0 +1,26 @@
+use ssh_hub::tools::remote_tail::handler::{follow_chunk_end, MAX_CHUNK_BYTES};
+
+fn full_chunk() -> Vec<u8> {
+    vec![b'x'; usize::try_from(MAX_CHUNK_BYTES).unwrap()]
+}
+
+#[test]
+fn test_follow_consumes_complete_lines() {
+    assert_eq!(follow_chunk_end(b"one\ntwo\nthr"), 8);
+    assert_eq!(follow_chunk_end(b"partial"), 0);
+    assert_eq!(follow_chunk_end(b""), 0);
+    // Offsets count bytes, so invalid UTF-8 doesn't shift them
+    assert_eq!(follow_chunk_end(b"\xff\xfe bad\nnext"), 7);
+}
+
+#[test]
+fn test_follow_takes_a_full_chunk_without_newline() {
+    let chunk = full_chunk();
+    assert_eq!(follow_chunk_end(&chunk), chunk.len());
+
+    // A character cut off at the end is left for the next poll
+    let mut chunk = full_chunk();
+    let len = chunk.len();
+    chunk[len - 1] = "é".as_bytes()[0];
+    assert_eq!(follow_chunk_end(&chunk), len - 1);
+}
@@ -0,0 +1,89 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::process::Command;
+
+use serde_json::json;
+use ssh_hub::tools::remote_wait_for::handler::Condition;
+use ssh_hub::tools::RemoteWaitForInput;
+
+fn condition(value: serde_json::Value) -> Result<Condition, String> {
+    let input: RemoteWaitForInput = serde_json::from_value(value).unwrap();
+    Condition::from_input(&input, "/srv/app")
+}
+
+fn check(condition: &Condition) -> i32 {
+    Command::new("sh")
+        .args(["-c", &condition.check_command()])
+        .output()
+        .unwrap()
+        .status
+        .code()
+        .unwrap()
+}
+
+#[test]
+fn test_condition_from_input() {
+    assert_eq!(
+        condition(json!({"server": "s", "port": 8080})),
+        Ok(Condition::Port {
+            host: "localhost".to_string(),
+            port: 8080
+        })
+    );
+    assert_eq!(
+        condition(json!({"server": "s", "path": "run/ready"})),
+        Ok(Condition::Path("/srv/app/run/ready".to_string()))
+    );
+    assert_eq!(
+        condition(json!({"server": "s", "url": "http://localhost/health"})).map(|c| c.to_string()),
+        Ok("url http://localhost/health".to_string())
+    );
+    assert!(condition(json!({"server": "s"})).is_err());
+    assert!(condition(json!({"server": "s", "port": 1, "path": "/x"})).is_err());
+    assert!(condition(json!({"server": "s", "host": "db", "path": "/x"})).is_err());
+}
+
+#[test]
+fn test_port_check() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let open = Condition::Port {
+        host: "127.0.0.1".to_string(),
+        port,
+    };
+    assert_eq!(check(&open), 0);
+    drop(listener);
+    assert_ne!(check(&open), 0);
+}
+
+#[test]
+fn test_path_check() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("it's ready");
+    let condition = Condition::Path(path.to_string_lossy().into_owned());
+    assert_eq!(check(&condition), 1);
+    std::fs::write(&path, "").unwrap();
+    assert_eq!(check(&condition), 0);
+}
+
+#[test]
+fn test_url_check() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+        for status in ["503 Service Unavailable", "200 OK"] {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            )
+            .unwrap();
+        }
+    });
+    let condition = Condition::Url(format!("http://127.0.0.1:{port}/health"));
+    assert_ne!(check(&condition), 0);
+    assert_eq!(check(&condition), 0);
+    server.join().unwrap();
+}
@@ -0,0 +1,62 @@
+use ssh_hub::tools::remote_watch::handler::{
+    diff_snapshots, parse_watch_listing, watch_command, Changes,
+};
+use ssh_hub::utils::glob::GlobPattern;
+
+const BEFORE: &str = "\
+120 1760621000.5 ./dist/app.js
+80 1760621000.5 ./dist/app.css
+300 1760620000 ./src/main.rs
+";
+
+const AFTER: &str = "\
+121 1760621090.25 ./dist/app.js
+80 1760621000.5 ./dist/app.css
+40 1760621090.25 ./dist/chunk-1.js
+";
+
+fn paths(list: &[&str]) -> Vec<String> {
+    list.iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn parses_listing_filtered_by_pattern() {
+    let glob = GlobPattern::new("dist/**").unwrap();
+    let files = parse_watch_listing(BEFORE, &glob);
+    assert_eq!(
+        files.keys().collect::<Vec<_>>(),
+        vec!["dist/app.css", "dist/app.js"]
+    );
+    assert_eq!(files["dist/app.js"].size, 120);
+    assert_eq!(files["dist/app.js"].mtime, "1760621000.5");
+
+    let any = GlobPattern::new("*").unwrap();
+    assert_eq!(
+        parse_watch_listing("garbage\n12 1 ./a b.txt\n", &any).len(),
+        1
+    );
+}
+
+#[test]
+fn diffs_snapshots() {
+    let glob = GlobPattern::new("**").unwrap();
+    let before = parse_watch_listing(BEFORE, &glob);
+    let after = parse_watch_listing(AFTER, &glob);
+    let changes = diff_snapshots(&before, &after);
+    assert_eq!(
+        changes,
+        Changes {
+            added: paths(&["dist/chunk-1.js"]),
+            removed: paths(&["src/main.rs"]),
+            modified: paths(&["dist/app.js"]),
+        }
+    );
+    assert!(diff_snapshots(&after, &after).is_empty());
+}
+
+#[test]
+fn lists_from_the_pattern_base_dir() {
+    let command = watch_command("/srv/app", "dist", "-printf '%s %T@ %p\\n'");
+    assert!(command.starts_with("cd '/srv/app' && find 'dist' -type f -printf"));
+    assert!(watch_command("~", "", "-printf x").contains("find . -type f"));
+}
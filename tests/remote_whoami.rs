@@ -0,0 +1,63 @@
+use ssh_hub::tools::remote_whoami::handler::parse_whoami;
+use ssh_hub::tools::remote_whoami::SudoAccess;
+
+fn probe(sudo: &str) -> String {
+    format!(
+        "USER=deploy\nUID=1001\nGID=1001\nGROUPS=deploy docker adm\nHOME=/home/deploy\nUMASK=0002\nSUDO={sudo}\n"
+    )
+}
+
+#[test]
+fn parses_identity() {
+    let out = parse_whoami(&probe("ok"));
+    assert_eq!(out.user, "deploy");
+    assert_eq!(out.uid, Some(1001));
+    assert_eq!(out.gid, Some(1001));
+    assert_eq!(out.groups, vec!["deploy", "docker", "adm"]);
+    assert_eq!(out.home, "/home/deploy");
+    assert_eq!(out.umask, "0002");
+    assert!(!out.root);
+    assert_eq!(out.sudo, SudoAccess::Passwordless);
+}
+
+#[test]
+fn sudo_states() {
+    let sudo = |msg: &str| parse_whoami(&probe(msg)).sudo;
+    assert_eq!(sudo("missing"), SudoAccess::NotInstalled);
+    assert_eq!(
+        sudo("sudo: a password is required"),
+        SudoAccess::PasswordRequired
+    );
+    assert_eq!(
+        sudo("deploy is not in the sudoers file.  This incident will be reported."),
+        SudoAccess::Denied
+    );
+    assert_eq!(
+        sudo("Sorry, user deploy may not run sudo on web-1."),
+        SudoAccess::Denied
+    );
+    assert_eq!(sudo("sudo: something unexpected"), SudoAccess::Unknown);
+}
+
+#[test]
+fn root_detected_from_uid() {
+    let out = parse_whoami(
+        "USER=root\nUID=0\nGID=0\nGROUPS=root\nHOME=/root\nUMASK=0022\nSUDO=missing\n",
+    );
+    assert!(out.root);
+    assert_eq!(out.sudo, SudoAccess::NotInstalled);
+}
+
+#[test]
+fn serializes_sudo_in_snake_case() {
+    let json = serde_json::to_value(parse_whoami(&probe("sudo: a password is required"))).unwrap();
+    assert_eq!(json["sudo"], "password_required");
+}
+
+#[test]
+fn empty_output_is_unknown() {
+    let out = parse_whoami("");
+    assert_eq!(out.uid, None);
+    assert!(!out.root);
+    assert_eq!(out.sudo, SudoAccess::Unknown);
+}
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+
+use ssh_hub::server_registry::ServerRegistry;
+use ssh_hub::tools::run_saved_command::handler::{parse_args, render};
+use ssh_hub::tools::{ErrorCode, RunSavedCommandInput};
+
+fn commands() -> BTreeMap<String, String> {
+    let config: ServerRegistry = toml::from_str(
+        r#"
+[servers.staging]
+host = "staging.example.com"
+user = "deploy"
+
+[servers.staging.commands]
+deploy = "cd /srv/app && git pull && sudo systemctl restart app"
+checkout = "cd /srv/app && git checkout {branch} && echo {{done}}"
+bad = "echo {unclosed"
+"#,
+    )
+    .unwrap();
+    config.get("staging").unwrap().commands.clone()
+}
+
+fn input(command: &str, args: &[(&str, &str)]) -> RunSavedCommandInput {
+    RunSavedCommandInput {
+        server: "staging".to_string(),
+        command: command.to_string(),
+        args: Some(
+            args.iter()
+                .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+                .collect(),
+        ),
+        timeout: None,
+        override_window: None,
+    }
+}
+
+#[test]
+fn test_saved_commands_render_with_escaped_args() {
+    let commands = commands();
+    assert_eq!(
+        render(&commands, &input("deploy", &[])).unwrap(),
+        "cd /srv/app && git pull && sudo systemctl restart app"
+    );
+    assert_eq!(
+        render(
+            &commands,
+            &input("checkout", &[("branch", "main; rm -rf /")])
+        )
+        .unwrap(),
+        "cd /srv/app && git checkout 'main; rm -rf /' && echo {done}"
+    );
+}
+
+#[test]
+fn test_saved_commands_leave_shell_braces_alone() {
+    let saved = |command: &str| -> BTreeMap<String, String> {
+        [("cmd".to_string(), command.to_string())].into()
+    };
+    for command in [
+        "cd ${APP_DIR:-/srv/app} && ls",
+        "df -P | awk '{print $1}'",
+        "{ date; uptime; } > /tmp/out",
+        "mkdir -p /tmp/{a,b}",
+        "echo '{x}' \\{y\\} \"${HOME} {z}\"",
+        "find . -exec rm {} +",
+    ] {
+        assert_eq!(
+            render(&saved(command), &input("cmd", &[])).unwrap(),
+            command
+        );
+    }
+    assert_eq!(
+        render(
+            &saved("awk '{print $1}' {file} | grep {x} \"{x}\" && echo ${DIR}/{{b}}"),
+            &input("cmd", &[("file", "a b"), ("x", "$y")])
+        )
+        .unwrap(),
+        "awk '{print $1}' 'a b' | grep '$y' \"{x}\" && echo ${DIR}/{b}"
+    );
+}
+
+#[test]
+fn test_saved_command_errors() {
+    let commands = commands();
+    let missing = render(&commands, &input("checkout", &[])).unwrap_err();
+    assert_eq!(missing.code, ErrorCode::InvalidInput);
+    assert!(
+        missing
+            .message
+            .contains("missing required argument 'branch'"),
+        "{missing:?}"
+    );
+    let unknown = render(&commands, &input("deploy", &[("force", "1")])).unwrap_err();
+    assert!(
+        unknown.message.contains("unknown argument 'force'"),
+        "{unknown:?}"
+    );
+    let malformed = render(&commands, &input("bad", &[])).unwrap_err();
+    assert_eq!(malformed.code, ErrorCode::Failed);
+    assert!(malformed.message.contains("is malformed"), "{malformed:?}");
+
+    let unsaved = render(&commands, &input("restart", &[])).unwrap_err();
+    assert_eq!(unsaved.code, ErrorCode::NotFound);
+    assert_eq!(
+        unsaved.message,
+        "server 'staging' has no saved command 'restart'. Saved commands: bad, checkout, deploy"
+    );
+    let none = render(&BTreeMap::new(), &input("deploy", &[])).unwrap_err();
+    assert!(
+        none.message.contains("[servers.staging.commands]"),
+        "{none:?}"
+    );
+
+    let reserved: BTreeMap<String, String> =
+        [("who".to_string(), "echo {server}".to_string())].into();
+    assert!(render(&reserved, &input("who", &[("server", "x")]))
+        .unwrap_err()
+        .message
+        .contains("reserved"));
+}
+
+#[test]
+fn test_parse_cli_args() {
+    let words = [
+        "branch=main".to_string(),
+        "note=a=b".to_string(),
+        "empty=".to_string(),
+    ];
+    assert_eq!(
+        parse_args(&words).unwrap(),
+        BTreeMap::from([
+            ("branch".to_string(), "main".to_string()),
+            ("empty".to_string(), String::new()),
+            ("note".to_string(), "a=b".to_string()),
+        ])
+    );
+    assert!(parse_args(&["main".to_string()]).is_err());
+    assert!(parse_args(&["=main".to_string()]).is_err());
+}
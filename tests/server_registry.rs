@@ -1,5 +1,12 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use ssh_hub::cli::sanitize_registry;
 use ssh_hub::metadata::SystemMetadata;
-use ssh_hub::server_registry::{AuthMethod, ServerEntry, ServerRegistry};
+use ssh_hub::server_registry::{
+    config_file_name, AuthMethod, CommandShell, RemoteShell, ServerChanges, ServerEntry,
+    ServerRegistry,
+};
 
 #[test]
 fn test_parse_config() {
@@ -58,8 +65,31 @@ fn test_roundtrip() {
             remote_path: "/home/test".to_string(),
             identity: None,
             auth: AuthMethod::Auto,
+            agent_socket: None,
             resolve_host: None,
+            resolve: None,
+            address_family: None,
+            bind_address: None,
+            bind_interface: None,
             metadata: None,
+            shell: None,
+            command_shell: None,
+            strict_host_key: None,
+            max_channels: None,
+            read_replica: None,
+            readonly: false,
+            maintenance: None,
+            rate_limits: None,
+            hooks: None,
+            scratch_root: None,
+            snapshot_dir: None,
+            tags: Vec::new(),
+            needs: Vec::new(),
+            commands: BTreeMap::new(),
+            max_timeout_ms: None,
+            default_timeout_ms: None,
+            no_timeout: false,
+            discovery: None,
         },
     );
 
@@ -101,15 +131,43 @@ fn test_metadata_roundtrip() {
         remote_path: "/home/test".to_string(),
         identity: None,
         auth: AuthMethod::Auto,
+        agent_socket: None,
         resolve_host: None,
+        resolve: None,
+        address_family: None,
+        bind_address: None,
+        bind_interface: None,
         metadata: None,
+        shell: None,
+        command_shell: None,
+        strict_host_key: None,
+        max_channels: None,
+        read_replica: None,
+        readonly: false,
+        maintenance: None,
+        rate_limits: None,
+        hooks: None,
+        scratch_root: None,
+        snapshot_dir: None,
+        tags: Vec::new(),
+        needs: Vec::new(),
+        commands: BTreeMap::new(),
+        max_timeout_ms: None,
+        default_timeout_ms: None,
+        no_timeout: false,
+        discovery: None,
     };
     entry.metadata = Some(SystemMetadata {
         os: Some("linux".into()),
         distro: Some("Ubuntu 22.04".into()),
         arch: Some("x86_64".into()),
         shell: Some("/bin/bash".into()),
+        shell_version: Some("GNU bash, version 5.1.16(1)-release".into()),
         package_manager: Some("apt".into()),
+        hostname: Some("test-1".into()),
+        uptime_seconds: Some(3600),
+        umask: Some("0022".into()),
+        address_family: Some("ipv6".into()),
         collected_at: Some(1_700_000_000),
     });
     config.insert("test".to_string(), entry);
@@ -121,6 +179,7 @@ fn test_metadata_roundtrip() {
     assert_eq!(meta.os.as_deref(), Some("linux"));
     assert_eq!(meta.distro.as_deref(), Some("Ubuntu 22.04"));
     assert_eq!(meta.arch.as_deref(), Some("x86_64"));
+    assert_eq!(meta.address_family.as_deref(), Some("ipv6"));
     assert_eq!(meta.shell.as_deref(), Some("/bin/bash"));
     assert_eq!(meta.package_manager.as_deref(), Some("apt"));
     assert_eq!(meta.collected_at, Some(1_700_000_000));
@@ -138,8 +197,31 @@ fn test_resolve_host_roundtrip() {
             remote_path: "~".to_string(),
             identity: None,
             auth: AuthMethod::Auto,
+            agent_socket: None,
             resolve_host: Some("gcloud compute instances describe my-vm --format='get(networkInterfaces[0].accessConfigs[0].natIP)'".to_string()),
+            resolve: None,
+            address_family: None,
+            bind_address: None,
+            bind_interface: None,
             metadata: None,
+            shell: None,
+            command_shell: None,
+            strict_host_key: None,
+            max_channels: None,
+            read_replica: None,
+            readonly: false,
+            maintenance: None,
+            rate_limits: None,
+            hooks: None,
+            scratch_root: None,
+            snapshot_dir: None,
+            tags: Vec::new(),
+            needs: Vec::new(),
+            commands: BTreeMap::new(),
+            max_timeout_ms: None,
+            default_timeout_ms: None,
+            no_timeout: false,
+            discovery: None,
         },
     );
 
@@ -178,11 +260,689 @@ fn test_resolve_host_not_serialized_when_none() {
             remote_path: "~".to_string(),
             identity: None,
             auth: AuthMethod::Auto,
+            agent_socket: None,
             resolve_host: None,
+            resolve: None,
+            address_family: None,
+            bind_address: None,
+            bind_interface: None,
             metadata: None,
+            shell: None,
+            command_shell: None,
+            strict_host_key: None,
+            max_channels: None,
+            read_replica: None,
+            readonly: false,
+            maintenance: None,
+            rate_limits: None,
+            hooks: None,
+            scratch_root: None,
+            snapshot_dir: None,
+            tags: Vec::new(),
+            needs: Vec::new(),
+            commands: BTreeMap::new(),
+            max_timeout_ms: None,
+            default_timeout_ms: None,
+            no_timeout: false,
+            discovery: None,
         },
     );
 
     let serialized = toml::to_string_pretty(&config).unwrap();
     assert!(!serialized.contains("resolve_host"));
 }
+
+#[test]
+fn test_max_channels_roundtrip() {
+    let toml_str = r#"
+[servers.busy]
+host = "10.0.0.5"
+user = "deploy"
+max_channels = 4
+
+[servers.plain]
+host = "10.0.0.6"
+user = "deploy"
+"#;
+    let config: ServerRegistry = toml::from_str(toml_str).unwrap();
+    assert_eq!(config.get("busy").unwrap().max_channels, Some(4));
+    assert_eq!(config.get("plain").unwrap().max_channels, None);
+
+    let serialized = toml::to_string_pretty(&config).unwrap();
+    assert_eq!(serialized.matches("max_channels").count(), 1);
+}
+
+#[test]
+fn test_max_timeout_ms_roundtrip() {
+    let toml_str = r#"
+[servers.ci]
+host = "10.0.0.7"
+user = "build"
+max_timeout_ms = 7200000
+
+[servers.plain]
+host = "10.0.0.6"
+user = "deploy"
+"#;
+    let config: ServerRegistry = toml::from_str(toml_str).unwrap();
+    assert_eq!(config.get("ci").unwrap().max_timeout_ms, Some(7_200_000));
+    assert_eq!(config.get("plain").unwrap().max_timeout_ms, None);
+
+    let serialized = toml::to_string_pretty(&config).unwrap();
+    assert_eq!(serialized.matches("max_timeout_ms").count(), 1);
+}
+
+#[test]
+fn test_sync_max_pull_bytes_roundtrip() {
+    let toml_str = "[sync]\nmax_pull_bytes = 1073741824\n";
+    let config: ServerRegistry = toml::from_str(toml_str).unwrap();
+    assert_eq!(
+        config.sync.as_ref().and_then(|s| s.max_pull_bytes),
+        Some(1_073_741_824)
+    );
+    let serialized = toml::to_string_pretty(&config).unwrap();
+    assert!(serialized.contains("max_pull_bytes = 1073741824"));
+
+    let empty: ServerRegistry = toml::from_str("").unwrap();
+    assert!(empty.sync.is_none());
+    assert!(!toml::to_string_pretty(&empty).unwrap().contains("sync"));
+}
+
+#[test]
+fn test_read_replica() {
+    let toml_str = r#"
+[servers.app1]
+host = "10.0.0.5"
+user = "deploy"
+read_replica = "app2"
+
+[servers.app2]
+host = "10.0.0.6"
+user = "deploy"
+
+[servers.self_ref]
+host = "10.0.0.7"
+user = "deploy"
+read_replica = "self_ref"
+
+[servers.dangling]
+host = "10.0.0.8"
+user = "deploy"
+read_replica = "gone"
+"#;
+    let config: ServerRegistry = toml::from_str(toml_str).unwrap();
+    assert_eq!(config.read_replica("app1"), Some("app2"));
+    assert_eq!(config.read_replica("app2"), None);
+    assert_eq!(config.read_replica("self_ref"), None);
+    assert_eq!(config.read_replica("dangling"), None);
+    assert_eq!(config.read_replica("missing"), None);
+}
+
+#[test]
+fn test_readonly_roundtrip() {
+    let toml_str = r#"
+[servers.prod]
+host = "10.0.0.5"
+user = "ops"
+readonly = true
+
+[servers.dev]
+host = "10.0.0.6"
+user = "ops"
+"#;
+    let config: ServerRegistry = toml::from_str(toml_str).unwrap();
+    assert!(config.get("prod").unwrap().readonly);
+    assert!(!config.get("dev").unwrap().readonly);
+
+    let serialized = toml::to_string_pretty(&config).unwrap();
+    assert_eq!(serialized.matches("readonly").count(), 1);
+}
+
+#[test]
+fn test_shell_roundtrip() {
+    let toml_str = r#"
+[servers.win]
+host = "10.0.0.7"
+user = "admin"
+shell = "powershell"
+
+[servers.linux]
+host = "10.0.0.8"
+user = "deploy"
+"#;
+    let config: ServerRegistry = toml::from_str(toml_str).unwrap();
+    assert_eq!(
+        config.get("win").unwrap().shell,
+        Some(RemoteShell::Powershell)
+    );
+    assert_eq!(config.get("linux").unwrap().shell, None);
+
+    let serialized = toml::to_string_pretty(&config).unwrap();
+    assert_eq!(serialized.matches("shell = ").count(), 1);
+}
+
+#[test]
+fn test_remote_shell_inferred_from_metadata() {
+    let with_shell = |shell: &str| ServerEntry {
+        metadata: Some(SystemMetadata {
+            shell: Some(shell.to_string()),
+            ..SystemMetadata::default()
+        }),
+        ..ServerEntry::default()
+    };
+    assert_eq!(ServerEntry::default().remote_shell(), RemoteShell::Posix);
+    assert_eq!(with_shell("/bin/bash").remote_shell(), RemoteShell::Posix);
+    assert_eq!(with_shell("cmd").remote_shell(), RemoteShell::Cmd);
+    assert_eq!(
+        with_shell("powershell").remote_shell(),
+        RemoteShell::Powershell
+    );
+
+    let pinned = ServerEntry {
+        shell: Some(RemoteShell::Posix),
+        ..with_shell("powershell")
+    };
+    assert_eq!(pinned.remote_shell(), RemoteShell::Posix);
+}
+
+#[test]
+fn test_command_shell_from_config_and_metadata() {
+    let with_login = |shell: &str| ServerEntry {
+        metadata: Some(SystemMetadata {
+            shell: Some(shell.to_string()),
+            ..SystemMetadata::default()
+        }),
+        ..ServerEntry::default()
+    };
+    assert_eq!(ServerEntry::default().command_shell(), CommandShell::Login);
+    assert_eq!(with_login("/bin/zsh").command_shell(), CommandShell::Login);
+    assert_eq!(
+        with_login("/usr/bin/fish").command_shell(),
+        CommandShell::Sh
+    );
+    assert_eq!(
+        with_login("/opt/homebrew/bin/nu").command_shell(),
+        CommandShell::Sh
+    );
+    assert_eq!(
+        with_login("powershell").command_shell(),
+        CommandShell::Login
+    );
+
+    let config: ServerRegistry = toml::from_str(
+        r#"
+[servers.app]
+host = "10.0.0.9"
+user = "deploy"
+command_shell = "bash"
+"#,
+    )
+    .unwrap();
+    let configured = ServerEntry {
+        metadata: with_login("/usr/bin/fish").metadata,
+        ..config.get("app").unwrap().clone()
+    };
+    assert_eq!(configured.command_shell(), CommandShell::Bash);
+    let serialized = toml::to_string_pretty(&config).unwrap();
+    assert!(serialized.contains("command_shell = \"bash\""));
+}
+
+#[test]
+fn test_server_entry_default_uses_serde_defaults() {
+    let entry = ServerEntry::default();
+    assert_eq!(entry.port, 22);
+    assert_eq!(entry.remote_path, "~");
+    assert_eq!(entry.auth, AuthMethod::Auto);
+}
+
+#[test]
+fn test_sanitize_registry_redacts_identifying_fields() {
+    let toml_str = r#"
+[servers.prod]
+host = "prod.example.com"
+user = "deploy"
+port = 2222
+remote_path = "/var/www/app"
+identity = "~/.ssh/id_prod"
+resolve = "10.1.2.3"
+resolve_host = "gcloud compute instances describe prod"
+
+[servers.prod.metadata]
+os = "linux"
+hostname = "prod-web-17"
+
+[servers.prod.commands]
+ship = "cd /srv/secret-app && git pull"
+
+[servers.dev]
+host = "devbox"
+user = "me"
+
+[templates.restart]
+description = "Restart a service"
+command = "sudo systemctl restart {name}"
+
+[templates.restart.params.name]
+default = "web"
+"#;
+    let config: ServerRegistry = toml::from_str(toml_str).unwrap();
+    let clean = sanitize_registry(&config);
+    let out = toml::to_string_pretty(&clean).unwrap();
+
+    for secret in [
+        "prod.example.com",
+        "deploy",
+        "/var/www/app",
+        "id_prod",
+        "10.1.2.3",
+        "gcloud",
+        "systemctl",
+        "\"web\"",
+        "prod-web-17",
+        "secret-app",
+    ] {
+        assert!(!out.contains(secret), "{secret} leaked into bundle");
+    }
+    let prod = clean.get("prod").unwrap();
+    assert_eq!(prod.port, 2222);
+    assert_eq!(prod.identity.as_deref(), Some("<redacted>"));
+    assert_eq!(prod.metadata.as_ref().unwrap().os.as_deref(), Some("linux"));
+    let dev = clean.get("dev").unwrap();
+    assert_eq!(dev.remote_path, "~");
+    assert_eq!(dev.identity, None);
+    assert_eq!(clean.templates["restart"].description, "Restart a service");
+}
+
+fn project_config() -> ServerRegistry {
+    toml::from_str(
+        r#"
+[servers.staging]
+host = "staging.example.com"
+user = "deploy"
+
+[projects."/home/me/app"]
+server = "staging"
+remote_path = "/srv/app/"
+
+[projects."/home/me/app/web"]
+server = "staging"
+remote_path = "/srv/web"
+
+[projects."/home/me/tools"]
+server = "prod"
+remote_path = "~/tools"
+"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_remote_for_local() {
+    let config = project_config();
+    assert_eq!(
+        config.remote_for_local("staging", Path::new("/home/me/app")),
+        Some("/srv/app/".to_string())
+    );
+    assert_eq!(
+        config.remote_for_local("staging", Path::new("/home/me/app/src/main.rs")),
+        Some("/srv/app/src/main.rs".to_string())
+    );
+    // The nested project wins
+    assert_eq!(
+        config.remote_for_local("staging", Path::new("/home/me/app/web/index.html")),
+        Some("/srv/web/index.html".to_string())
+    );
+    // Other servers' projects and unmapped paths don't match
+    assert_eq!(
+        config.remote_for_local("staging", Path::new("/home/me/tools/x")),
+        None
+    );
+    assert_eq!(
+        config.remote_for_local("staging", Path::new("/home/me/application")),
+        None
+    );
+}
+
+#[test]
+fn test_local_for_remote() {
+    let config = project_config();
+    assert_eq!(
+        config.local_for_remote("staging", "/srv/app"),
+        Some(PathBuf::from("/home/me/app"))
+    );
+    assert_eq!(
+        config.local_for_remote("staging", "/srv/app/config/app.toml"),
+        Some(PathBuf::from("/home/me/app/config/app.toml"))
+    );
+    assert_eq!(
+        config.local_for_remote("prod", "~/tools/bin"),
+        Some(PathBuf::from("/home/me/tools/bin"))
+    );
+    assert_eq!(config.local_for_remote("staging", "/srv/application"), None);
+    assert_eq!(config.local_for_remote("prod", "/srv/app"), None);
+
+    let mut config = project_config();
+    config.projects.get_mut("/home/me/app").unwrap().remote_path = "/".to_string();
+    assert_eq!(
+        config.local_for_remote("staging", "/etc/hosts"),
+        Some(PathBuf::from("/home/me/app/etc/hosts"))
+    );
+    // A deeper remote root is preferred over `/`
+    assert_eq!(
+        config.local_for_remote("staging", "/srv/web/a.css"),
+        Some(PathBuf::from("/home/me/app/web/a.css"))
+    );
+}
+
+#[test]
+fn test_sanitize_registry_redacts_projects() {
+    let clean = sanitize_registry(&project_config());
+    assert_eq!(clean.projects.len(), 3);
+    for (root, mapping) in &clean.projects {
+        assert!(!root.contains("/home/me"), "{root}");
+        assert_eq!(mapping.remote_path, "<redacted>");
+    }
+    assert!(clean.projects.values().any(|m| m.server == "prod"));
+}
+
+#[test]
+fn test_has_tags_requires_every_tag() {
+    let entry = ServerEntry {
+        tags: vec!["gpu".to_string(), "env=staging".to_string()],
+        ..ServerEntry::default()
+    };
+    assert!(entry.has_tags(&[]));
+    assert!(entry.has_tags(&["gpu".to_string()]));
+    assert!(entry.has_tags(&["env=staging".to_string(), "gpu".to_string()]));
+    assert!(!entry.has_tags(&["gpu".to_string(), "env=prod".to_string()]));
+    // Tags match whole, not by prefix
+    assert!(!entry.has_tags(&["env".to_string()]));
+}
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn test_server_changes_between_configs() {
+    let old: ServerRegistry = toml::from_str(
+        r#"
+[servers.keep]
+host = "10.0.0.1"
+user = "deploy"
+
+[servers.moved]
+host = "10.0.0.2"
+user = "deploy"
+
+[servers.gone]
+host = "10.0.0.3"
+user = "deploy"
+"#,
+    )
+    .unwrap();
+    let new: ServerRegistry = toml::from_str(
+        r#"
+[servers.keep]
+host = "10.0.0.1"
+user = "deploy"
+
+[servers.moved]
+host = "10.0.0.9"
+user = "deploy"
+
+[servers.fresh]
+host = "10.0.0.4"
+user = "deploy"
+"#,
+    )
+    .unwrap();
+
+    let changes = old.server_changes(&new);
+    assert_eq!(changes.added, names(&["fresh"]));
+    assert_eq!(changes.removed, names(&["gone"]));
+    assert_eq!(changes.changed, names(&["moved"]));
+    assert!(changes.servers_added_or_removed());
+    assert!(old.server_changes(&old).is_empty());
+}
+
+#[test]
+fn test_connection_settings_count_as_changes() {
+    let base = "[servers.web]\nhost = \"10.0.0.1\"\nuser = \"deploy\"\n";
+    let old: ServerRegistry = toml::from_str(base).unwrap();
+    for setting in [
+        "address_family = \"inet6\"",
+        "bind_address = \"192.0.2.10\"",
+        "bind_interface = \"eth1\"",
+        "agent_socket = \"/run/user/1000/agent.sock\"",
+        "strict_host_key = \"yes\"",
+    ] {
+        let new: ServerRegistry = toml::from_str(&format!("{base}{setting}\n")).unwrap();
+        assert_eq!(old.changed_servers(&new), names(&["web"]), "{setting}");
+    }
+}
+
+#[test]
+fn test_server_changes_merge_across_reloads() {
+    let mut changes = ServerChanges {
+        added: names(&["a"]),
+        removed: names(&["b"]),
+        changed: names(&["c"]),
+    };
+    changes.merge(ServerChanges {
+        added: names(&["b", "d"]),
+        removed: names(&["a", "c"]),
+        changed: names(&["d"]),
+    });
+    // a came and went; b went and came back; c was changed then removed
+    assert_eq!(changes.added, names(&["d"]));
+    assert_eq!(changes.removed, names(&["c"]));
+    assert_eq!(changes.changed, names(&["b"]));
+}
+
+const FLEET: &str = r#"
+[defaults]
+user = "deploy"
+identity = "~/.ssh/id_fleet"
+
+[servers."gpu-*"]
+user = "ml"
+port = 2222
+
+[servers.gpu-1]
+host = "10.0.1.1"
+
+[servers.gpu-2]
+host = "10.0.1.2"
+user = "root"
+
+[servers.web]
+host = "10.0.2.1"
+"#;
+
+#[test]
+fn test_defaults_and_patterns_fill_unset_settings() {
+    let config = ServerRegistry::parse(FLEET).unwrap();
+    assert_eq!(config.servers.len(), 3, "patterns are not servers");
+    assert_eq!(config.patterns.len(), 1);
+
+    let gpu1 = config.get("gpu-1").unwrap();
+    assert_eq!(gpu1.user, "ml", "pattern overrides defaults");
+    assert_eq!(gpu1.port, 2222);
+    assert_eq!(gpu1.identity.as_deref(), Some("~/.ssh/id_fleet"));
+
+    let gpu2 = config.get("gpu-2").unwrap();
+    assert_eq!(gpu2.user, "root", "explicit entry overrides the pattern");
+    assert_eq!(gpu2.port, 2222);
+
+    let web = config.get("web").unwrap();
+    assert_eq!(web.user, "deploy");
+    assert_eq!(web.port, 22);
+}
+
+#[test]
+fn test_later_pattern_overrides_earlier() {
+    let config = ServerRegistry::parse(
+        r#"
+[servers."gpu-*"]
+user = "ml"
+
+[servers."gpu-a?"]
+user = "research"
+
+[servers.gpu-a1]
+host = "10.0.1.1"
+"#,
+    )
+    .unwrap();
+    assert_eq!(config.get("gpu-a1").unwrap().user, "research");
+}
+
+#[test]
+fn test_missing_setting_names_the_server() {
+    let err = ServerRegistry::parse(
+        r#"
+[defaults]
+user = "deploy"
+
+[servers.web]
+port = 22
+"#,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("server 'web'"), "{err}");
+}
+
+#[test]
+fn test_save_keeps_inherited_settings_out_of_entries() {
+    let mut config = ServerRegistry::parse(FLEET).unwrap();
+    config.servers.get_mut("web").unwrap().port = 2200;
+    let saved = config.to_toml().unwrap();
+    let table: toml::Table = toml::from_str(&saved).unwrap();
+
+    assert_eq!(table["defaults"]["user"].as_str(), Some("deploy"));
+    let servers = table["servers"].as_table().unwrap();
+    assert_eq!(servers["gpu-*"]["port"].as_integer(), Some(2222));
+    let gpu1 = servers["gpu-1"].as_table().unwrap();
+    assert!(!gpu1.contains_key("user") && !gpu1.contains_key("identity"));
+    assert_eq!(servers["web"]["port"].as_integer(), Some(2200));
+
+    let reparsed = ServerRegistry::parse(&saved).unwrap();
+    assert_eq!(reparsed.get("gpu-1").unwrap().user, "ml");
+    assert_eq!(reparsed.get("web").unwrap().port, 2200);
+}
+
+#[test]
+fn test_sanitize_redacts_defaults_and_patterns() {
+    let clean = sanitize_registry(&ServerRegistry::parse(FLEET).unwrap());
+    assert_eq!(clean.defaults["user"].as_str(), Some("<redacted>"));
+    assert_eq!(clean.patterns[0].1["user"].as_str(), Some("<redacted>"));
+    assert_eq!(clean.patterns[0].1["port"].as_integer(), Some(2222));
+}
+
+#[test]
+fn test_config_path_from_environment() {
+    let default = ServerRegistry::default_config_path().unwrap();
+    assert!(default.ends_with("ssh-hub/servers.toml"));
+    assert_eq!(
+        ServerRegistry::config_path_from(None, None, None).unwrap(),
+        default
+    );
+    assert_eq!(
+        ServerRegistry::config_path_from(Some(PathBuf::new()), Some(PathBuf::new()), None).unwrap(),
+        default
+    );
+    assert_eq!(
+        ServerRegistry::config_path_from(None, Some(PathBuf::from("/etc/ssh-hub-ci")), None)
+            .unwrap(),
+        Path::new("/etc/ssh-hub-ci/servers.toml")
+    );
+    // The file wins over the directory
+    assert_eq!(
+        ServerRegistry::config_path_from(
+            Some(PathBuf::from("/tmp/staging.toml")),
+            Some(PathBuf::from("/etc/ssh-hub-ci")),
+            None
+        )
+        .unwrap(),
+        Path::new("/tmp/staging.toml")
+    );
+    let relative =
+        ServerRegistry::config_path_from(Some(PathBuf::from("ci.toml")), None, None).unwrap();
+    assert_eq!(relative, std::env::current_dir().unwrap().join("ci.toml"));
+}
+
+#[test]
+fn test_config_path_for_profile() {
+    assert_eq!(config_file_name(None), "servers.toml");
+    assert_eq!(config_file_name(Some("work")), "servers-work.toml");
+    let default = ServerRegistry::default_config_path().unwrap();
+    assert_eq!(
+        ServerRegistry::config_path_from(None, None, Some("work")).unwrap(),
+        default.with_file_name("servers-work.toml")
+    );
+    assert_eq!(
+        ServerRegistry::config_path_from(None, Some(PathBuf::from("/etc/hub")), Some("work"))
+            .unwrap(),
+        Path::new("/etc/hub/servers-work.toml")
+    );
+    // An explicit file is used whatever the profile
+    assert_eq!(
+        ServerRegistry::config_path_from(Some(PathBuf::from("/tmp/a.toml")), None, Some("work"))
+            .unwrap(),
+        Path::new("/tmp/a.toml")
+    );
+}
+
+#[test]
+fn test_entries_for_the_same_target_share_a_transport() {
+    let cfg = ServerRegistry::parse(
+        r#"
+        [servers.api]
+        host = "10.0.0.5"
+        user = "deploy"
+        remote_path = "/srv/api"
+
+        [servers.web]
+        host = "10.0.0.5"
+        user = "deploy"
+        remote_path = "/srv/web"
+
+        [servers.web-root]
+        host = "10.0.0.5"
+        user = "root"
+
+        [servers.web-alt]
+        host = "10.0.0.5"
+        user = "deploy"
+        port = 2222
+
+        [servers.web-key]
+        host = "10.0.0.5"
+        user = "deploy"
+        identity = "~/.ssh/deploy_ed25519"
+
+        [servers.web-agent]
+        host = "10.0.0.5"
+        user = "deploy"
+        agent_socket = "/run/agent.sock"
+
+        [servers.web-slots]
+        host = "10.0.0.5"
+        user = "deploy"
+        max_channels = 2
+        "#,
+    )
+    .unwrap();
+    let params = |name: &str| ssh_hub::cli::params_from_config(name, cfg.get(name).unwrap());
+    let api = params("api");
+    let web = params("web");
+    assert!(api.same_transport(&web));
+    assert_ne!(api.remote_path, web.remote_path);
+    for other in ["web-root", "web-alt", "web-key", "web-agent", "web-slots"] {
+        assert!(!api.same_transport(&params(other)), "{other}");
+    }
+    let mut agent = web.clone();
+    agent.auth_method = ssh_hub::server_registry::AuthMethod::Agent;
+    assert!(!api.same_transport(&agent));
+}
@@ -0,0 +1,217 @@
+use std::net::SocketAddr;
+
+use anyhow::anyhow;
+use serde_json::{json, Value};
+use ssh_hub::connection::socks;
+use ssh_hub::tools::socks_proxy::handler::{start, stop, ProxyStore};
+use ssh_hub::tools::{ErrorCode, SocksProxyInput, ToolError};
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio::net::{TcpListener, TcpStream};
+
+fn peer() -> SocketAddr {
+    "127.0.0.1:50000".parse().unwrap()
+}
+
+/// A CONNECT request for `host`:`port` by domain name, greeting included.
+fn connect_request(host: &str, port: u16) -> Vec<u8> {
+    let mut request = vec![0x05, 0x01, 0x00, 0x05, 0x01, 0x00, 0x03];
+    request.push(u8::try_from(host.len()).unwrap());
+    request.extend_from_slice(host.as_bytes());
+    request.extend_from_slice(&port.to_be_bytes());
+    request
+}
+
+#[tokio::test]
+async fn test_handshake_parses_each_address_type() {
+    let cases: [(&[u8], &str); 3] = [
+        (&[0x01, 10, 0, 0, 7], "10.0.0.7"),
+        (
+            &[0x04, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
+            "::1",
+        ),
+        (&[0x03, 4, b'd', b'b', b'.', b'x'], "db.x"),
+    ];
+    for (address, expected) in cases {
+        let (mut client, mut server) = duplex(1024);
+        let mut request = vec![0x05, 0x02, 0x02, 0x00, 0x05, 0x01, 0x00];
+        request.extend_from_slice(address);
+        request.extend_from_slice(&5432u16.to_be_bytes());
+        client.write_all(&request).await.unwrap();
+
+        let target = socks::handshake(&mut server).await.unwrap();
+        assert_eq!(target, (expected.to_string(), 5432));
+        let mut method = [0u8; 2];
+        client.read_exact(&mut method).await.unwrap();
+        assert_eq!(method, [0x05, 0x00], "no authentication is chosen");
+    }
+}
+
+#[tokio::test]
+async fn test_handshake_refuses_authentication_only_clients() {
+    let (mut client, mut server) = duplex(1024);
+    client.write_all(&[0x05, 0x01, 0x02]).await.unwrap();
+    let err = socks::handshake(&mut server).await.unwrap_err();
+    assert!(err.to_string().contains("authentication"), "{err}");
+    let mut reply = [0u8; 2];
+    client.read_exact(&mut reply).await.unwrap();
+    assert_eq!(reply, [0x05, 0xFF]);
+}
+
+#[tokio::test]
+async fn test_handshake_refuses_commands_other_than_connect() {
+    let (mut client, mut server) = duplex(1024);
+    // BIND to 10.0.0.7:80
+    client
+        .write_all(&[0x05, 0x01, 0x00, 0x05, 0x02, 0x00, 0x01, 10, 0, 0, 7, 0, 80])
+        .await
+        .unwrap();
+    assert!(socks::handshake(&mut server).await.is_err());
+    let mut reply = [0u8; 12];
+    client.read_exact(&mut reply).await.unwrap();
+    assert_eq!(reply[2..4], [0x05, socks::REPLY_COMMAND_NOT_SUPPORTED]);
+}
+
+#[tokio::test]
+async fn test_handshake_rejects_other_protocols() {
+    let (mut client, mut server) = duplex(1024);
+    client.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+    let err = socks::handshake(&mut server).await.unwrap_err();
+    assert!(err.to_string().contains("Not a SOCKS5 client"), "{err}");
+}
+
+#[tokio::test]
+async fn test_proxy_relays_both_ways() {
+    let (mut client, server) = duplex(1024);
+    let (target, mut remote) = duplex(1024);
+    let task = tokio::spawn(socks::proxy(server, peer(), move |host, port, from| {
+        assert_eq!((host.as_str(), port, from), ("db.internal", 5432, peer()));
+        async move { Ok::<DuplexStream, anyhow::Error>(target) }
+    }));
+
+    client
+        .write_all(&connect_request("db.internal", 5432))
+        .await
+        .unwrap();
+    let mut replies = [0u8; 12];
+    client.read_exact(&mut replies).await.unwrap();
+    assert_eq!(replies[2..4], [0x05, socks::REPLY_SUCCEEDED]);
+
+    client.write_all(b"ping").await.unwrap();
+    let mut buf = [0u8; 4];
+    remote.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"ping");
+    remote.write_all(b"pong").await.unwrap();
+    client.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"pong");
+
+    drop(remote);
+    drop(client);
+    task.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_proxy_reports_unreachable_targets() {
+    let (mut client, server) = duplex(1024);
+    let task = tokio::spawn(socks::proxy(server, peer(), |_, _, _| async {
+        Err::<DuplexStream, _>(anyhow!("open failed"))
+    }));
+    client
+        .write_all(&connect_request("gone.internal", 80))
+        .await
+        .unwrap();
+    let mut replies = [0u8; 12];
+    client.read_exact(&mut replies).await.unwrap();
+    assert_eq!(replies[2..4], [0x05, socks::REPLY_HOST_UNREACHABLE]);
+    let err = task.await.unwrap().unwrap_err();
+    assert!(format!("{err:#}").contains("gone.internal:80"), "{err:#}");
+}
+
+fn input(value: Value) -> SocksProxyInput {
+    serde_json::from_value(value).unwrap()
+}
+
+/// Opens targets directly, standing in for the SSH tunnel.
+async fn open_direct(host: String, port: u16, _: SocketAddr) -> anyhow::Result<TcpStream> {
+    Ok(TcpStream::connect((host.as_str(), port)).await?)
+}
+
+#[tokio::test]
+async fn test_socks_proxy_tool_starts_reuses_and_stops() {
+    let echo = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let echo_port = echo.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (mut stream, _) = echo.accept().await.unwrap();
+        let (mut read, mut write) = stream.split();
+        tokio::io::copy(&mut read, &mut write).await.unwrap();
+    });
+
+    let store = ProxyStore::new();
+    let started: Value =
+        serde_json::from_str(&start(&store, input(json!({"server": "s"})), open_direct).await)
+            .unwrap();
+    assert_eq!(started["running"], true);
+    assert!(started.get("already_running").is_none());
+    let address = started["address"].as_str().unwrap().to_string();
+    assert!(address.starts_with("127.0.0.1:"), "{address}");
+    assert_eq!(started["proxy_url"], format!("socks5h://{address}"));
+
+    let mut client = TcpStream::connect(&address).await.unwrap();
+    client
+        .write_all(&connect_request("127.0.0.1", echo_port))
+        .await
+        .unwrap();
+    let mut replies = [0u8; 12];
+    client.read_exact(&mut replies).await.unwrap();
+    assert_eq!(replies[2..4], [0x05, socks::REPLY_SUCCEEDED]);
+    client.write_all(b"hello").await.unwrap();
+    let mut buf = [0u8; 5];
+    client.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"hello");
+
+    let again: Value =
+        serde_json::from_str(&start(&store, input(json!({"server": "s"})), open_direct).await)
+            .unwrap();
+    assert_eq!(again["already_running"], true);
+    assert_eq!(again["address"], address.as_str());
+
+    let port: u16 = address.rsplit(':').next().unwrap().parse().unwrap();
+    let other = start(
+        &store,
+        input(json!({"server": "s", "port": port.wrapping_add(1)})),
+        open_direct,
+    )
+    .await;
+    let other = ToolError::from_json(&other).unwrap();
+    assert_eq!(other.code, ErrorCode::InvalidInput);
+    assert!(
+        other
+            .message
+            .starts_with("a SOCKS proxy for 's' is already running"),
+        "{other:?}"
+    );
+
+    let stopped: Value = serde_json::from_str(&stop(&store, "s").await).unwrap();
+    assert_eq!(stopped, json!({"server": "s", "running": false}));
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert!(TcpStream::connect(&address).await.is_err());
+}
+
+#[tokio::test]
+async fn test_socks_proxy_tool_reports_busy_ports() {
+    let taken = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = taken.local_addr().unwrap().port();
+    let store = ProxyStore::new();
+    let result = start(
+        &store,
+        input(json!({"server": "s", "port": port})),
+        open_direct,
+    )
+    .await;
+    let error = ToolError::from_json(&result).unwrap();
+    assert!(
+        error
+            .message
+            .starts_with(&format!("cannot listen on 127.0.0.1:{port}")),
+        "{result}"
+    );
+}
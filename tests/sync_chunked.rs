@@ -0,0 +1,135 @@
+use std::path::Path;
+
+use ssh_hub::connection::resolve::LocalBind;
+use ssh_hub::connection::ConnectionParams;
+use ssh_hub::server_registry::{AddressFamily, AuthMethod, RemoteShell, StrictHostKey};
+use ssh_hub::tools::sync_chunked::{
+    progress_message, read_state, resume_offset, state_key, write_state, Direction, SourceVersion,
+};
+
+const SOURCE: SourceVersion = SourceVersion {
+    size: 500 * 1024 * 1024,
+    mtime: 1_760_000_000,
+};
+
+fn params(host: &str) -> ConnectionParams {
+    ConnectionParams {
+        host: host.to_string(),
+        user: "deploy".to_string(),
+        port: 22,
+        remote_path: "~".to_string(),
+        identity: None,
+        auth_method: AuthMethod::Auto,
+        agent_socket: None,
+        server_name: None,
+        resolve: None,
+        shell: RemoteShell::Posix,
+        max_channels: None,
+        strict_host_key: StrictHostKey::Ask,
+        address_family: AddressFamily::Any,
+        bind: LocalBind::default(),
+    }
+}
+
+// --- resume_offset ---
+
+#[test]
+fn resumes_from_partial_of_same_source() {
+    assert_eq!(
+        resume_offset(Some(SOURCE), SOURCE, Some(450 * 1024 * 1024)),
+        450 * 1024 * 1024
+    );
+}
+
+#[test]
+fn restarts_when_source_changed() {
+    let changed = SourceVersion {
+        mtime: SOURCE.mtime + 1,
+        ..SOURCE
+    };
+    assert_eq!(resume_offset(Some(changed), SOURCE, Some(1024)), 0);
+    let grown = SourceVersion {
+        size: SOURCE.size + 1,
+        ..SOURCE
+    };
+    assert_eq!(resume_offset(Some(grown), SOURCE, Some(1024)), 0);
+}
+
+#[test]
+fn restarts_without_state_or_partial() {
+    assert_eq!(resume_offset(None, SOURCE, Some(1024)), 0);
+    assert_eq!(resume_offset(Some(SOURCE), SOURCE, None), 0);
+}
+
+#[test]
+fn never_resumes_past_the_source_size() {
+    assert_eq!(
+        resume_offset(Some(SOURCE), SOURCE, Some(SOURCE.size + 10)),
+        SOURCE.size
+    );
+}
+
+// --- state ---
+
+#[test]
+fn state_round_trips() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("transfers").join("abc.json");
+    assert_eq!(read_state(&path), None);
+    write_state(&path, SOURCE).unwrap();
+    assert_eq!(read_state(&path), Some(SOURCE));
+}
+
+#[test]
+fn unreadable_state_is_ignored() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("abc.json");
+    std::fs::write(&path, "not json").unwrap();
+    assert_eq!(read_state(&path), None);
+}
+
+#[test]
+fn state_key_is_stable() {
+    let local = Path::new("/tmp/model.ckpt");
+    let key = state_key(Direction::Pull, &params("gpu"), "ckpt/model.ckpt", local);
+    assert_eq!(key.len(), 16);
+    assert_eq!(
+        key,
+        state_key(Direction::Pull, &params("gpu"), "ckpt/model.ckpt", local)
+    );
+}
+
+#[test]
+fn state_key_differs_by_direction_server_and_paths() {
+    let local = Path::new("/tmp/model.ckpt");
+    let key = state_key(Direction::Pull, &params("gpu"), "ckpt/model.ckpt", local);
+    assert_ne!(
+        key,
+        state_key(Direction::Push, &params("gpu"), "ckpt/model.ckpt", local)
+    );
+    assert_ne!(
+        key,
+        state_key(Direction::Pull, &params("gpu2"), "ckpt/model.ckpt", local)
+    );
+    assert_ne!(
+        key,
+        state_key(Direction::Pull, &params("gpu"), "ckpt/other.ckpt", local)
+    );
+    assert_ne!(
+        key,
+        state_key(
+            Direction::Pull,
+            &params("gpu"),
+            "ckpt/model.ckpt",
+            Path::new("/tmp/other.ckpt")
+        )
+    );
+}
+
+#[test]
+fn progress_message_uses_readable_sizes() {
+    assert_eq!(
+        progress_message(450 * 1024 * 1024, SOURCE.size),
+        "450.0 MiB of 500.0 MiB"
+    );
+}
@@ -0,0 +1,123 @@
+use ssh_hub::tools::sync_journal::{relativize_changes, JournalToken};
+
+#[test]
+fn test_journal_token_round_trip() {
+    let token = JournalToken {
+        pid: 4242,
+        offset: 1024,
+    };
+    assert_eq!(token.to_string(), "4242:1024");
+    assert_eq!(JournalToken::parse("4242:1024"), Some(token));
+}
+
+#[test]
+fn test_journal_token_rejects_malformed() {
+    assert_eq!(JournalToken::parse(""), None);
+    assert_eq!(JournalToken::parse("4242"), None);
+    assert_eq!(JournalToken::parse("abc:12"), None);
+    assert_eq!(JournalToken::parse("12:-1"), None);
+}
+
+#[test]
+fn test_relativize_changes_dedupes_and_sorts() {
+    let log = "/home/u/app/src/b.rs\n/home/u/app/src/a.rs\n/home/u/app/src/b.rs\n";
+    assert_eq!(
+        relativize_changes(log, "/home/u/app"),
+        vec!["src/a.rs".to_string(), "src/b.rs".to_string()]
+    );
+}
+
+#[test]
+fn test_relativize_changes_drops_outside_and_git() {
+    let log = "/home/u/app/.git/index\n/home/u/app2/x\n/home/u/app\n/home/u/app/ok.txt\n";
+    assert_eq!(
+        relativize_changes(log, "/home/u/app/"),
+        vec!["ok.txt".to_string()]
+    );
+}
+
+/// Run `command` with `sh -c` and a fake `inotifywait` first on `PATH`,
+/// returning stdout.
+#[cfg(unix)]
+fn sh(command: &str, home: &std::path::Path) -> String {
+    let path = format!(
+        "{}:{}",
+        home.join("bin").display(),
+        std::env::var("PATH").unwrap()
+    );
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("HOME", home)
+        .env("PATH", path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{command}: {output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Whether `pid` is running, counting a zombie as gone.
+#[cfg(unix)]
+fn running(pid: &str) -> bool {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "stat=", "-p", pid])
+        .output()
+        .unwrap();
+    let stat = String::from_utf8_lossy(&output.stdout);
+    output.status.success() && !stat.trim().starts_with('Z')
+}
+
+/// Wait up to 5 seconds for `ready`.
+#[cfg(unix)]
+fn eventually(mut ready: impl FnMut() -> bool) -> bool {
+    (0..50).any(|_| {
+        let done = ready();
+        if !done {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        done
+    })
+}
+
+#[cfg(unix)]
+#[test]
+fn test_watcher_is_reused_and_stopped() {
+    use ssh_hub::tools::sync_journal::{ensure_command, stop_command};
+    use std::os::unix::fs::PermissionsExt;
+
+    let home = tempfile::tempdir().unwrap();
+    let bin = home.path().join("bin");
+    std::fs::create_dir(&bin).unwrap();
+    let fake = bin.join("inotifywait");
+    std::fs::write(
+        &fake,
+        "#!/bin/sh\necho $$ > \"$HOME/watcher\"\nexec sleep 600\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let dir = home.path().join("app");
+    std::fs::create_dir(&dir).unwrap();
+    let dir = dir.to_str().unwrap();
+
+    let first = sh(&ensure_command(dir), home.path());
+    let token = JournalToken::parse(first.trim()).unwrap();
+    assert_eq!(token.offset, 0);
+    let second = sh(&ensure_command(dir), home.path());
+    assert_eq!(JournalToken::parse(second.trim()), Some(token.clone()));
+
+    let watcher = home.path().join("watcher");
+    assert!(eventually(|| watcher.exists()));
+    let watcher = std::fs::read_to_string(&watcher).unwrap();
+    let watcher = watcher.trim();
+    assert!(running(watcher));
+
+    // Stopping takes the watcher down with its supervisor
+    sh(&stop_command(dir), home.path());
+    assert!(eventually(|| !running(&token.pid.to_string())));
+    assert!(eventually(|| !running(watcher)));
+
+    // The next ensure starts a fresh journal
+    let third = sh(&ensure_command(dir), home.path());
+    assert_ne!(JournalToken::parse(third.trim()).unwrap().pid, token.pid);
+    sh(&stop_command(dir), home.path());
+}
@@ -0,0 +1,79 @@
+use ssh_hub::tools::sync_manifest::{
+    build_local_manifest, diff_manifests, parse_remote_manifest, Manifest, ManifestEntry,
+};
+
+fn entry(sha256: &str) -> ManifestEntry {
+    ManifestEntry {
+        size: 1,
+        mtime: 0,
+        sha256: sha256.to_string(),
+    }
+}
+
+#[test]
+fn test_parse_remote_manifest() {
+    let output = "\
+12 1700000000.1234567890 ./src/main.rs\n\
+3 1700000001.0000000000 ./dir with space/a.txt\n\
+--ssh-hub-sha256--\n\
+aaaa  ./src/main.rs\n\
+bbbb  ./dir with space/a.txt\n";
+
+    let manifest = parse_remote_manifest(output);
+    assert_eq!(manifest.len(), 2);
+
+    let main = &manifest["src/main.rs"];
+    assert_eq!(main.size, 12);
+    assert_eq!(main.mtime, 1_700_000_000);
+    assert_eq!(main.sha256, "aaaa");
+    assert_eq!(manifest["dir with space/a.txt"].sha256, "bbbb");
+}
+
+#[test]
+fn test_parse_remote_manifest_empty() {
+    assert!(parse_remote_manifest("").is_empty());
+}
+
+#[test]
+fn test_parse_remote_manifest_skips_malformed() {
+    let output = "garbage\nnotanumber 1 ./x\n--ssh-hub-sha256--\nzzzz  ./unknown\n";
+    assert!(parse_remote_manifest(output).is_empty());
+}
+
+#[test]
+fn test_build_local_manifest_hashes() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("hello.txt"), "hello").unwrap();
+
+    let manifest = build_local_manifest(dir.path(), &["hello.txt".to_string()]).unwrap();
+    let entry = &manifest["hello.txt"];
+    assert_eq!(entry.size, 5);
+    assert_eq!(
+        entry.sha256,
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+}
+
+#[test]
+fn test_diff_manifests() {
+    let local: Manifest = [
+        ("same".to_string(), entry("1")),
+        ("changed".to_string(), entry("2")),
+        ("new".to_string(), entry("3")),
+    ]
+    .into_iter()
+    .collect();
+    let remote: Manifest = [
+        ("same".to_string(), entry("1")),
+        ("changed".to_string(), entry("x")),
+        ("stale".to_string(), entry("4")),
+    ]
+    .into_iter()
+    .collect();
+
+    let diff = diff_manifests(&local, &remote);
+    assert_eq!(diff.local_only, vec!["new"]);
+    assert_eq!(diff.remote_only, vec!["stale"]);
+    assert_eq!(diff.modified, vec!["changed"]);
+    assert_eq!(diff.unchanged, 1);
+}
@@ -0,0 +1,117 @@
+use ssh_hub::tools::sync_pull::handler::{
+    check_pull_size, format_size, parse_listing, pull_ignore, select_pull_files,
+};
+use ssh_hub::utils::gitignore::retain_included;
+
+fn listing() -> Vec<(String, u64)> {
+    vec![
+        ("src/main.rs".to_string(), 2_000),
+        ("node_modules/left-pad/index.js".to_string(), 300_000),
+        ("node_modules/react/index.js".to_string(), 700_000),
+        ("dist/app.js".to_string(), 50_000),
+        ("debug.log".to_string(), 10_000),
+        ("README.md".to_string(), 1_000),
+    ]
+}
+
+fn paths(files: &[(String, u64)]) -> Vec<&str> {
+    files.iter().map(|(p, _)| p.as_str()).collect()
+}
+
+#[test]
+fn test_parse_listing_splits_gitignore_and_files() {
+    let stdout = "node_modules/\n*.log\n--ssh-hub-files--\n\
+                  1024 ./src/main.rs\n12 ./dir with spaces/a b.txt\nbogus line\n";
+    let (gitignore, files) = parse_listing(stdout);
+    assert_eq!(gitignore, "node_modules/\n*.log\n");
+    assert_eq!(
+        files,
+        vec![
+            ("src/main.rs".to_string(), 1024),
+            ("dir with spaces/a b.txt".to_string(), 12),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_listing_without_gitignore() {
+    let (gitignore, files) = parse_listing("\n--ssh-hub-files--\n5 ./a\n");
+    assert_eq!(gitignore.trim(), "");
+    assert_eq!(files, vec![("a".to_string(), 5)]);
+}
+
+#[test]
+fn test_parse_listing_without_marker_is_empty() {
+    let (gitignore, files) = parse_listing("cd: no such directory\n");
+    assert!(gitignore.is_empty());
+    assert!(files.is_empty());
+}
+
+#[test]
+fn test_select_pull_files_applies_gitignore_and_exclude() {
+    let ignore = pull_ignore("node_modules/\n*.log\n", &["dist".to_string()]);
+    let files = select_pull_files(listing(), &ignore);
+    assert_eq!(paths(&files), vec!["README.md", "src/main.rs"]);
+}
+
+#[test]
+fn test_include_keeps_only_matching_files() {
+    let mut files = listing();
+    let include = vec!["*.md".to_string(), "src/".to_string()];
+    retain_included(&mut files, &include, |(path, _)| path);
+    assert_eq!(paths(&files), vec!["src/main.rs", "README.md"]);
+
+    let mut files = listing();
+    retain_included(&mut files, &[], |(path, _)| path);
+    assert_eq!(files.len(), listing().len(), "no patterns keep everything");
+}
+
+#[test]
+fn test_select_pull_files_drops_git_dir() {
+    let files = vec![
+        (".git/HEAD".to_string(), 20),
+        (".gitignore".to_string(), 10),
+    ];
+    let files = select_pull_files(files, &pull_ignore("", &[]));
+    assert_eq!(paths(&files), vec![".gitignore"]);
+}
+
+#[test]
+fn test_select_pull_files_negation_keeps_file() {
+    let ignore = pull_ignore("*.log\n!keep.log\n", &[]);
+    let files = vec![("a.log".to_string(), 1), ("keep.log".to_string(), 1)];
+    assert_eq!(paths(&select_pull_files(files, &ignore)), vec!["keep.log"]);
+}
+
+#[test]
+fn test_check_pull_size_under_limit() {
+    assert!(check_pull_size(&listing(), 10_000_000).is_ok());
+}
+
+#[test]
+fn test_check_pull_size_zero_disables_cap() {
+    assert!(check_pull_size(&listing(), 0).is_ok());
+}
+
+#[test]
+fn test_check_pull_size_names_largest_entries() {
+    let error = check_pull_size(&listing(), 100_000).unwrap_err();
+    assert!(error.contains("6 files"), "{error}");
+    assert!(error.contains("97.6 KiB limit"), "{error}");
+    let largest = error.split("Largest: ").nth(1).unwrap();
+    assert!(
+        largest.starts_with("node_modules/ (976.5 KiB), dist/ (48.8 KiB), debug.log (9.7 KiB)"),
+        "{error}"
+    );
+    assert!(error.contains("'exclude'"), "{error}");
+}
+
+#[test]
+fn test_format_size_units() {
+    assert_eq!(format_size(0), "0 B");
+    assert_eq!(format_size(1023), "1023 B");
+    assert_eq!(format_size(1024), "1.0 KiB");
+    assert_eq!(format_size(1536), "1.5 KiB");
+    assert_eq!(format_size(512 * 1024 * 1024), "512.0 MiB");
+    assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+}
@@ -0,0 +1,121 @@
+use ssh_hub::tools::sync_push::handler::{select_push_files, write_tar_gz};
+
+#[test]
+fn test_select_push_files_accepts_files_within_base() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+    std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+
+    let (files, failed) = select_push_files(
+        dir.path(),
+        &[
+            "src/main.rs".to_string(),
+            "./Cargo.toml".to_string(),
+            "src/../src/main.rs".to_string(),
+        ],
+    );
+    assert_eq!(files, ["src/main.rs", "Cargo.toml"]);
+    assert!(failed.is_empty());
+}
+
+#[test]
+fn test_select_push_files_reports_unknown_and_escaping_paths() {
+    let root = tempfile::tempdir().unwrap();
+    let base = root.path().join("project");
+    std::fs::create_dir_all(base.join("src")).unwrap();
+    std::fs::write(root.path().join("secret"), "x").unwrap();
+    std::fs::write(base.join("README.md"), "x").unwrap();
+
+    let (files, failed) = select_push_files(
+        &base,
+        &[
+            "missing.rs".to_string(),
+            "../secret".to_string(),
+            "src".to_string(),
+            "README.md".to_string(),
+        ],
+    );
+    assert_eq!(files, ["README.md"]);
+    let failed: Vec<&str> = failed.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(failed, ["missing.rs", "../secret", "src"]);
+}
+
+#[test]
+fn test_write_tar_gz_keeps_files_and_modes() {
+    use std::io::Read;
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("bin")).unwrap();
+    std::fs::write(dir.path().join("bin/run.sh"), "#!/bin/sh\n").unwrap();
+    std::fs::write(dir.path().join("README.md"), "hello\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let perms = std::fs::Permissions::from_mode(0o755);
+        std::fs::set_permissions(dir.path().join("bin/run.sh"), perms).unwrap();
+    }
+
+    let mut archive = Vec::new();
+    let files = ["bin/run.sh".to_string(), "README.md".to_string()];
+    write_tar_gz(&mut archive, dir.path(), &files).unwrap();
+
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(archive.as_slice()));
+    let mut entries = Vec::new();
+    for entry in tar.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let mut content = String::new();
+        entry.read_to_string(&mut content).unwrap();
+        let path = entry.path().unwrap().to_string_lossy().into_owned();
+        entries.push((path, entry.header().mode().unwrap() & 0o111 != 0, content));
+    }
+    assert_eq!(
+        entries,
+        [
+            (
+                "bin/run.sh".to_string(),
+                cfg!(unix),
+                "#!/bin/sh\n".to_string()
+            ),
+            ("README.md".to_string(), false, "hello\n".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_write_tar_gz_rejects_escaping_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    let err = write_tar_gz(Vec::new(), dir.path(), &["../outside".to_string()]).unwrap_err();
+    assert!(!err.to_string().is_empty());
+}
+
+#[tokio::test]
+async fn test_write_tar_gz_streams_through_a_small_pipe() {
+    use tokio::io::AsyncReadExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    // Incompressible enough to be many times the pipe's buffer
+    let content: Vec<u8> = (0..4_000_000u32)
+        .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+        .collect();
+    std::fs::write(dir.path().join("data.bin"), &content).unwrap();
+
+    let (mut reader, writer) = tokio::io::duplex(4096);
+    let base = dir.path().to_path_buf();
+    let compress = tokio::task::spawn_blocking(move || {
+        write_tar_gz(
+            tokio_util::io::SyncIoBridge::new(writer),
+            &base,
+            &["data.bin".to_string()],
+        )
+    });
+    let mut archive = Vec::new();
+    reader.read_to_end(&mut archive).await.unwrap();
+    compress.await.unwrap().unwrap();
+
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(archive.as_slice()));
+    let mut entry = tar.entries().unwrap().next().unwrap().unwrap();
+    let mut unpacked = Vec::new();
+    std::io::Read::read_to_end(&mut entry, &mut unpacked).unwrap();
+    assert_eq!(unpacked, content);
+}
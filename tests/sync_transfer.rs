@@ -0,0 +1,315 @@
+use std::time::Duration;
+
+use ssh_hub::connection::resolve::LocalBind;
+use ssh_hub::connection::{ConnectionParams, FileStat, RemoteCapabilities, Sha256Tool};
+use ssh_hub::server_registry::{AddressFamily, AuthMethod, RemoteShell, StrictHostKey};
+use ssh_hub::tools::sync_transfer::{
+    home_relative, rsync_remote_spec, rsync_ssh_command, select_strategy, set_attrs_command,
+    FileAttrs, TransferShape, TransferStats, TransferStrategy, LARGE_FILE_BYTES,
+};
+
+const ALL_CAPS: RemoteCapabilities = RemoteCapabilities {
+    tar: true,
+    rsync: true,
+    sha256: Sha256Tool::Sha256sum,
+    file_stat: FileStat::FindPrintf,
+    sftp_only: false,
+};
+
+const SFTP_ONLY: RemoteCapabilities = RemoteCapabilities {
+    tar: false,
+    rsync: false,
+    sha256: Sha256Tool::Sha256sum,
+    file_stat: FileStat::FindPrintf,
+    sftp_only: true,
+};
+
+fn file(bytes: u64) -> TransferShape {
+    TransferShape {
+        is_dir: false,
+        file_count: Some(1),
+        total_bytes: bytes,
+    }
+}
+
+fn dir(file_count: Option<usize>) -> TransferShape {
+    TransferShape {
+        is_dir: true,
+        file_count,
+        total_bytes: 0,
+    }
+}
+
+fn params(host: &str) -> ConnectionParams {
+    ConnectionParams {
+        host: host.to_string(),
+        user: "deploy".to_string(),
+        port: 22,
+        remote_path: "~".to_string(),
+        identity: None,
+        auth_method: AuthMethod::Auto,
+        agent_socket: None,
+        server_name: None,
+        resolve: None,
+        shell: RemoteShell::Posix,
+        max_channels: None,
+        strict_host_key: StrictHostKey::Ask,
+        address_family: AddressFamily::Any,
+        bind: LocalBind::default(),
+    }
+}
+
+#[test]
+fn test_small_file_uses_cat() {
+    assert_eq!(
+        select_strategy(&file(1024), ALL_CAPS, None),
+        Ok(TransferStrategy::Cat)
+    );
+}
+
+#[test]
+fn test_large_file_uses_sftp() {
+    assert_eq!(
+        select_strategy(&file(LARGE_FILE_BYTES), ALL_CAPS, None),
+        Ok(TransferStrategy::Sftp)
+    );
+}
+
+#[test]
+fn test_directory_prefers_tar() {
+    assert_eq!(
+        select_strategy(&dir(Some(20)), ALL_CAPS, None),
+        Ok(TransferStrategy::Tar)
+    );
+    assert_eq!(
+        select_strategy(&dir(None), ALL_CAPS, None),
+        Ok(TransferStrategy::Tar)
+    );
+}
+
+#[test]
+fn test_single_file_directory_uses_cat() {
+    assert_eq!(
+        select_strategy(&dir(Some(1)), ALL_CAPS, None),
+        Ok(TransferStrategy::Cat)
+    );
+}
+
+#[test]
+fn test_directory_without_tar_uses_sftp() {
+    assert_eq!(
+        select_strategy(&dir(Some(20)), RemoteCapabilities::default(), None),
+        Ok(TransferStrategy::Sftp)
+    );
+}
+
+#[test]
+fn test_rsync_never_auto_selected() {
+    for shape in [file(1), file(LARGE_FILE_BYTES), dir(Some(5000)), dir(None)] {
+        assert_ne!(
+            select_strategy(&shape, ALL_CAPS, None),
+            Ok(TransferStrategy::Rsync)
+        );
+    }
+}
+
+#[test]
+fn test_override_honored_when_supported() {
+    assert_eq!(
+        select_strategy(&dir(Some(20)), ALL_CAPS, Some(TransferStrategy::Rsync)),
+        Ok(TransferStrategy::Rsync)
+    );
+    assert_eq!(
+        select_strategy(&file(1), ALL_CAPS, Some(TransferStrategy::Sftp)),
+        Ok(TransferStrategy::Sftp)
+    );
+}
+
+#[test]
+fn test_override_rejected_when_unsupported() {
+    let none = RemoteCapabilities::default();
+    assert!(select_strategy(&dir(Some(2)), none, Some(TransferStrategy::Tar)).is_err());
+    assert!(select_strategy(&dir(Some(2)), none, Some(TransferStrategy::Rsync)).is_err());
+    assert!(select_strategy(&file(1), ALL_CAPS, Some(TransferStrategy::Tar)).is_err());
+}
+
+#[test]
+fn test_strategy_serde_names() {
+    let parsed: TransferStrategy = serde_json::from_str("\"sftp\"").unwrap();
+    assert_eq!(parsed, TransferStrategy::Sftp);
+    assert_eq!(
+        serde_json::to_string(&TransferStrategy::Rsync).unwrap(),
+        "\"rsync\""
+    );
+}
+
+#[test]
+fn test_transfer_stats_throughput() {
+    let stats = TransferStats::new(TransferStrategy::Tar, 2_000_000, Duration::from_secs(2));
+    assert_eq!(stats.elapsed_ms, 2000);
+    assert_eq!(stats.bytes_per_sec, 1_000_000);
+
+    let instant = TransferStats::new(TransferStrategy::Cat, 10, Duration::ZERO);
+    assert_eq!(instant.bytes_per_sec, 10_000);
+}
+
+#[test]
+fn test_home_relative() {
+    assert_eq!(home_relative("~"), ".");
+    assert_eq!(home_relative("~/app/src"), "app/src");
+    assert_eq!(home_relative("/srv/app"), "/srv/app");
+}
+
+#[test]
+fn test_rsync_remote_spec() {
+    assert_eq!(
+        rsync_remote_spec(&params("example.com"), "~/app"),
+        "deploy@example.com:app"
+    );
+    assert_eq!(
+        rsync_remote_spec(&params("::1"), "/srv/app"),
+        "deploy@[::1]:/srv/app"
+    );
+}
+
+#[test]
+fn test_rsync_ssh_goes_to_the_pooled_address() {
+    let mut pinned = params("app.example.com");
+    pinned.resolve = Some("203.0.113.7".to_string());
+    assert_eq!(
+        rsync_ssh_command(&pinned, "203.0.113.7:22".parse().unwrap()),
+        "ssh -p 22 -o BatchMode=yes -o HostName=203.0.113.7 -o HostKeyAlias='app.example.com'"
+    );
+
+    let mut other_port = params("app.example.com");
+    other_port.port = 2222;
+    other_port.identity = Some("/home/me/.ssh/id ed25519".into());
+    assert_eq!(
+        rsync_ssh_command(&other_port, "[2001:db8::5]:2222".parse().unwrap()),
+        "ssh -p 2222 -o BatchMode=yes -o HostName=2001:db8::5 \
+         -o HostKeyAlias='[app.example.com]:2222' -i '/home/me/.ssh/id ed25519'"
+    );
+}
+
+#[test]
+fn test_sftp_only_always_selects_sftp() {
+    for shape in [file(1024), file(LARGE_FILE_BYTES), dir(Some(1)), dir(None)] {
+        assert_eq!(
+            select_strategy(&shape, SFTP_ONLY, None),
+            Ok(TransferStrategy::Sftp)
+        );
+    }
+    assert_eq!(
+        select_strategy(&dir(None), SFTP_ONLY, Some(TransferStrategy::Sftp)),
+        Ok(TransferStrategy::Sftp)
+    );
+}
+
+#[test]
+fn test_sftp_only_rejects_shell_strategies() {
+    for strategy in [
+        TransferStrategy::Cat,
+        TransferStrategy::Tar,
+        TransferStrategy::Rsync,
+    ] {
+        let err = select_strategy(&dir(None), SFTP_ONLY, Some(strategy)).unwrap_err();
+        assert!(err.contains("POSIX shell"), "{err}");
+    }
+}
+
+#[test]
+fn test_capabilities_parse_gnu() {
+    let caps = RemoteCapabilities::parse("tar\nrsync\nsha256sum\nfind-printf\n");
+    assert_eq!(caps, ALL_CAPS);
+    assert_eq!(caps.sha256_command(), "sha256sum");
+    assert_eq!(caps.stat_action(true), "-printf '%s %T@ %p\\n'");
+}
+
+#[test]
+fn test_capabilities_parse_macos() {
+    let caps = RemoteCapabilities::parse("tar\nrsync\nshasum\nstat-f\n");
+    assert_eq!(caps.sha256, Sha256Tool::Shasum);
+    assert_eq!(caps.file_stat, FileStat::StatF);
+    assert_eq!(caps.sha256_command(), "shasum -a 256");
+    assert_eq!(caps.stat_action(false), "-exec stat -f '%z %N' {} +");
+    assert_eq!(caps.stat_action(true), "-exec stat -f '%z %m %N' {} +");
+}
+
+#[test]
+fn test_capabilities_parse_busybox() {
+    // busybox tar without gzip is not reported, and stat -c stands in for
+    // find -printf
+    let caps = RemoteCapabilities::parse("sha256sum\nstat-c\n");
+    assert!(!caps.tar);
+    assert_eq!(caps.sha256, Sha256Tool::Sha256sum);
+    assert_eq!(caps.file_stat, FileStat::StatC);
+    assert_eq!(caps.stat_action(true), "-exec stat -c '%s %Y %n' {} +");
+}
+
+#[test]
+fn test_capabilities_parse_prefers_sha256sum() {
+    let caps = RemoteCapabilities::parse("sha256sum\nshasum\n");
+    assert_eq!(caps.sha256, Sha256Tool::Sha256sum);
+}
+
+#[test]
+fn test_capabilities_parse_empty_assumes_gnu() {
+    let caps = RemoteCapabilities::parse("");
+    assert_eq!(caps, RemoteCapabilities::default());
+    assert_eq!(caps.file_stat, FileStat::FindPrintf);
+    assert_eq!(caps.sha256_command(), "sha256sum");
+}
+
+#[test]
+fn test_set_attrs_command() {
+    let files = [
+        (
+            "/srv/app/run.sh".to_string(),
+            FileAttrs {
+                mode: Some(0o755),
+                mtime: 1_792_164_580,
+            },
+        ),
+        (
+            "/srv/app/it's.txt".to_string(),
+            FileAttrs {
+                mode: None,
+                mtime: 0,
+            },
+        ),
+    ];
+    assert_eq!(
+        set_attrs_command(&files),
+        "chmod 755 '/srv/app/run.sh' && TZ=UTC0 touch -c -t 202610161529.40 '/srv/app/run.sh' \
+         && TZ=UTC0 touch -c -t 197001010000.00 '/srv/app/it'\\''s.txt'"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_set_attrs_command_applies_local_attrs() {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let dir = tempfile::tempdir().unwrap();
+    let local = dir.path().join("build.sh");
+    std::fs::write(&local, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&local, std::fs::Permissions::from_mode(0o750)).unwrap();
+    let attrs = FileAttrs::of(&local).unwrap();
+    assert_eq!(attrs.mode, Some(0o750));
+
+    let copy = dir.path().join("copy.sh");
+    std::fs::write(&copy, "#!/bin/sh\n").unwrap();
+    let attrs = FileAttrs {
+        mtime: 1_000_000_000,
+        ..attrs
+    };
+    let command = set_attrs_command(&[(copy.to_str().unwrap().to_string(), attrs)]);
+    let status = std::process::Command::new("sh")
+        .args(["-c", &command])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let meta = std::fs::metadata(&copy).unwrap();
+    assert_eq!(meta.permissions().mode() & 0o777, 0o750);
+    assert_eq!(meta.mtime(), 1_000_000_000);
+}
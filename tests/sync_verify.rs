@@ -0,0 +1,46 @@
+use ssh_hub::tools::sync_verify::{parse_sha256sum, FileVerification, VerifyStatus};
+
+const HASH_A: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+const HASH_B: &str = "486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7";
+
+#[test]
+fn test_parse_sha256sum_text_and_binary_modes() {
+    let output = format!("{HASH_A}  src/a.rs\n{HASH_B} *bin/b.dat\n");
+    let hashes = parse_sha256sum(&output);
+    assert_eq!(hashes.len(), 2);
+    assert_eq!(hashes["src/a.rs"], HASH_A);
+    assert_eq!(hashes["bin/b.dat"], HASH_B);
+}
+
+#[test]
+fn test_parse_sha256sum_keeps_spaces_in_names() {
+    let output = format!("{HASH_A}  dir with spaces/file name.txt\n");
+    let hashes = parse_sha256sum(&output);
+    assert_eq!(hashes["dir with spaces/file name.txt"], HASH_A);
+}
+
+#[test]
+fn test_parse_sha256sum_unescapes_special_names() {
+    let output = format!("\\{HASH_A}  odd\\\\name\\nline\n");
+    let hashes = parse_sha256sum(&output);
+    assert_eq!(hashes["odd\\name\nline"], HASH_A);
+}
+
+#[test]
+fn test_parse_sha256sum_skips_error_lines() {
+    let output = format!("{HASH_A}  ok.txt\nsha256sum: missing.txt: No such file or directory\n");
+    let hashes = parse_sha256sum(&output);
+    assert_eq!(hashes.len(), 1);
+    assert!(hashes.contains_key("ok.txt"));
+}
+
+#[test]
+fn test_file_verification_serializes_snake_case() {
+    let entry = FileVerification {
+        path: "a.rs".to_string(),
+        status: VerifyStatus::Mismatch,
+    };
+    let json = serde_json::to_value(&entry).unwrap();
+    assert_eq!(json["path"], "a.rs");
+    assert_eq!(json["status"], "mismatch");
+}
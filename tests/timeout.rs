@@ -0,0 +1,143 @@
+use ssh_hub::server_registry::ServerRegistry;
+use ssh_hub::timeout::{TimeoutPolicy, MAX_TIMEOUT_MS};
+
+fn capped_at(max_ms: u64) -> TimeoutPolicy {
+    TimeoutPolicy {
+        max_ms: Some(max_ms),
+        ..TimeoutPolicy::default()
+    }
+}
+
+#[test]
+fn test_defaults_and_caps_at_ten_minutes() {
+    let policy = TimeoutPolicy::default();
+    assert_eq!(policy.resolve(None, false), Ok(Some(120_000)));
+    assert_eq!(policy.resolve(Some(5_000), false), Ok(Some(5_000)));
+    assert_eq!(
+        policy.resolve(Some(3_600_000), false),
+        Ok(Some(MAX_TIMEOUT_MS))
+    );
+    // A higher server cap needs long_running
+    assert_eq!(
+        capped_at(7_200_000).resolve(Some(3_600_000), false),
+        Ok(Some(MAX_TIMEOUT_MS))
+    );
+}
+
+#[test]
+fn test_long_running_uses_server_cap() {
+    let policy = capped_at(7_200_000);
+    assert_eq!(policy.resolve(Some(3_600_000), true), Ok(Some(3_600_000)));
+    assert_eq!(policy.resolve(Some(10_800_000), true), Ok(Some(7_200_000)));
+    // Without a server cap, long_running stays at the default cap
+    assert_eq!(
+        TimeoutPolicy::default().resolve(Some(3_600_000), true),
+        Ok(Some(MAX_TIMEOUT_MS))
+    );
+}
+
+#[test]
+fn test_lower_server_cap_always_applies() {
+    let policy = capped_at(60_000);
+    assert_eq!(policy.resolve(None, false), Ok(Some(60_000)));
+    assert_eq!(policy.resolve(Some(300_000), true), Ok(Some(60_000)));
+}
+
+#[test]
+fn test_server_default_applies_when_the_call_sets_none() {
+    let policy = TimeoutPolicy {
+        default_ms: Some(300_000),
+        ..TimeoutPolicy::default()
+    };
+    assert_eq!(policy.resolve(None, false), Ok(Some(300_000)));
+    assert_eq!(policy.resolve(Some(1_000), false), Ok(Some(1_000)));
+}
+
+#[test]
+fn test_zero_means_no_timeout_only_when_allowed() {
+    let err = TimeoutPolicy::default()
+        .resolve(Some(0), false)
+        .unwrap_err();
+    assert!(err.contains("no_timeout = true"), "{err}");
+
+    let trusted = TimeoutPolicy {
+        unlimited: true,
+        ..TimeoutPolicy::default()
+    };
+    assert_eq!(trusted.resolve(Some(0), false), Ok(None));
+    assert_eq!(trusted.resolve(None, false), Ok(Some(120_000)));
+    // A server default of 0 makes no timeout the default
+    let unlimited_default = TimeoutPolicy {
+        default_ms: Some(0),
+        ..trusted
+    };
+    assert_eq!(unlimited_default.resolve(None, false), Ok(None));
+    assert_eq!(
+        unlimited_default.resolve(Some(5_000), false),
+        Ok(Some(5_000))
+    );
+}
+
+#[test]
+fn test_policy_from_server_config() {
+    let cfg = ServerRegistry::parse(
+        r#"
+        [defaults]
+        default_timeout_ms = 300000
+
+        [servers.ci]
+        host = "10.0.0.7"
+        user = "build"
+        max_timeout_ms = 7200000
+        no_timeout = true
+
+        [servers.plain]
+        host = "10.0.0.6"
+        user = "deploy"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        TimeoutPolicy::for_server(cfg.get("ci").unwrap()),
+        TimeoutPolicy {
+            default_ms: Some(300_000),
+            max_ms: Some(7_200_000),
+            unlimited: true,
+        }
+    );
+    let plain = TimeoutPolicy::for_server(cfg.get("plain").unwrap());
+    assert!(!plain.unlimited);
+    assert_eq!(plain.default_ms, Some(300_000));
+
+    let serialized = toml::to_string_pretty(&cfg).unwrap();
+    assert_eq!(serialized.matches("no_timeout").count(), 1);
+}
+
+#[test]
+fn test_operations_keep_their_own_limit_unless_raised() {
+    let policy = TimeoutPolicy::default();
+    assert_eq!(policy.operation(300_000), Some(300_000));
+
+    // A longer server default raises it, a shorter one doesn't lower it
+    let slow = TimeoutPolicy {
+        default_ms: Some(900_000),
+        ..TimeoutPolicy::default()
+    };
+    assert_eq!(slow.operation(120_000), Some(MAX_TIMEOUT_MS));
+    let raised = TimeoutPolicy {
+        max_ms: Some(7_200_000),
+        ..slow
+    };
+    assert_eq!(raised.operation(120_000), Some(900_000));
+    let quick = TimeoutPolicy {
+        default_ms: Some(5_000),
+        ..TimeoutPolicy::default()
+    };
+    assert_eq!(quick.operation(120_000), Some(120_000));
+
+    let unlimited = TimeoutPolicy {
+        unlimited: true,
+        ..TimeoutPolicy::default()
+    };
+    assert_eq!(unlimited.operation(120_000), None);
+}
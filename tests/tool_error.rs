@@ -0,0 +1,87 @@
+use std::io;
+
+use ssh_hub::connection::resolve::Unreachable;
+use ssh_hub::connection::{AuthFailed, ConnectionLost, EncryptedKey, HostKeyRejected, TimedOut};
+use ssh_hub::tools::{remote_error, tool_error, ErrorCode, ToolError};
+
+#[test]
+fn test_code_of_connection_errors() {
+    let cases = [
+        (
+            anyhow::Error::new(Unreachable("web.internal".to_string())),
+            ErrorCode::ConnectionFailed,
+        ),
+        (
+            anyhow::Error::new(ConnectionLost("channel closed".to_string())),
+            ErrorCode::ConnectionDead,
+        ),
+        (
+            anyhow::Error::new(AuthFailed("Tried: agent, default keys.".to_string())),
+            ErrorCode::AuthFailed,
+        ),
+        (
+            anyhow::Error::new(HostKeyRejected("key changed".to_string())),
+            ErrorCode::HostKeyRejected,
+        ),
+        (
+            anyhow::Error::new(TimedOut("Command timed out after 5000ms".to_string())),
+            ErrorCode::Timeout,
+        ),
+    ];
+    for (error, code) in cases {
+        assert_eq!(ErrorCode::of(&error), code, "{error}");
+    }
+
+    // The type decides, wherever it is in the chain
+    let error = anyhow::Error::new(EncryptedKey {
+        path: "~/.ssh/id_ed25519".into(),
+    })
+    .context("Failed to connect to SSH server");
+    assert_eq!(ErrorCode::of(&error), ErrorCode::KeyEncrypted);
+}
+
+#[test]
+fn test_code_of_io_errors_and_untyped_errors() {
+    let missing = anyhow::Error::new(io::Error::new(io::ErrorKind::NotFound, "gone"));
+    assert_eq!(ErrorCode::of(&missing), ErrorCode::FileNotFound);
+    let denied = anyhow::Error::new(io::Error::new(io::ErrorKind::PermissionDenied, "no"));
+    assert_eq!(ErrorCode::of(&denied), ErrorCode::PermissionDenied);
+    let other = anyhow::Error::new(io::Error::other("broken pipe"));
+    assert_eq!(ErrorCode::of(&other), ErrorCode::Failed);
+
+    // Wording alone never picks a code
+    let text = anyhow::anyhow!("cat: /etc/x: No such file or directory; Permission denied");
+    assert_eq!(ErrorCode::of(&text), ErrorCode::Failed);
+}
+
+#[test]
+fn test_is_error_only_matches_the_envelope() {
+    assert!(ToolError::is_error(&tool_error(
+        ErrorCode::Failed,
+        "exit 2"
+    )));
+    assert!(!ToolError::is_error(r#"{"exit_code": 0}"#));
+    assert!(!ToolError::is_error("Error: text a handler printed"));
+    assert!(!ToolError::is_error("     1\tError: in the file"));
+    assert!(!ToolError::is_error(r#"{"error": {"code": "FAILED"}}"#));
+}
+
+#[test]
+fn test_envelope_json() {
+    let timeout = anyhow::Error::new(TimedOut("Command timed out after 5000ms".to_string()));
+    let text = remote_error(&timeout, timeout.to_string());
+    let error = ToolError::from_json(&text).unwrap();
+    assert_eq!(error.code, ErrorCode::Timeout);
+    assert_eq!(error.message, "Command timed out after 5000ms");
+    assert!(error.retriable);
+    assert!(error.suggestion.is_some());
+
+    let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(json["error"]["code"], "TIMEOUT");
+    assert_eq!(json["error"]["retriable"], true);
+
+    let failed = ToolError::new(ErrorCode::Failed, "exit 2");
+    assert!(!failed.retriable);
+    let json: serde_json::Value = serde_json::from_str(&failed.to_json()).unwrap();
+    assert!(json["error"].get("suggestion").is_none());
+}
@@ -1,6 +1,9 @@
+use ssh_hub::utils::glyphs::{detect_ascii, Glyphs};
 use ssh_hub::utils::path::{
-    format_with_line_numbers, normalize_remote_path, shell_escape, validate_path_within,
+    format_with_line_numbers, format_with_line_numbers_using, normalize_remote_path, shell_escape,
+    validate_path_within,
 };
+use ssh_hub::utils::validate::{is_valid_mode, is_valid_name};
 
 #[test]
 fn test_normalize_absolute_path() {
@@ -26,6 +29,30 @@ fn test_format_with_line_numbers() {
     assert!(formatted.contains("2\u{2192}line2"));
 }
 
+#[test]
+fn test_format_with_line_numbers_ascii() {
+    let formatted = format_with_line_numbers_using("line1\nline2", 9, Glyphs::ASCII);
+    assert_eq!(formatted, "    10->line1\n    11->line2");
+    assert!(formatted.is_ascii());
+}
+
+#[test]
+fn test_ascii_glyphs_are_ascii() {
+    assert!(Glyphs::ASCII.arrow.is_ascii());
+    assert!(Glyphs::ASCII.spinner.iter().all(|f| f.is_ascii()));
+}
+
+#[test]
+fn test_detect_ascii() {
+    assert!(detect_ascii(Some("dumb"), Some("en_US.UTF-8")));
+    assert!(detect_ascii(Some("xterm-256color"), Some("C")));
+    assert!(detect_ascii(None, Some("POSIX")));
+    assert!(detect_ascii(None, Some("en_US.ISO-8859-1")));
+    assert!(!detect_ascii(Some("xterm-256color"), Some("en_US.UTF-8")));
+    assert!(!detect_ascii(Some("xterm"), Some("C.utf8")));
+    assert!(!detect_ascii(None, None));
+}
+
 #[test]
 fn test_normalize_tilde_path() {
     assert_eq!(
@@ -106,3 +133,29 @@ fn test_validate_path_within_nested_ok() {
     std::fs::write(&file_path, "ok").unwrap();
     assert!(validate_path_within(dir.path(), "sub/deep/file.txt").is_ok());
 }
+
+#[test]
+fn test_is_valid_name() {
+    for name in [
+        "nginx",
+        "python3-pip",
+        "g++",
+        "getty@tty1.service",
+        "libc6:amd64",
+    ] {
+        assert!(is_valid_name(name), "{name}");
+    }
+    for name in ["", "-y", "a b", "x;y", "$(id)", "a/b"] {
+        assert!(!is_valid_name(name), "{name}");
+    }
+}
+
+#[test]
+fn test_is_valid_mode() {
+    for mode in ["644", "0755", "1777"] {
+        assert!(is_valid_mode(mode), "{mode}");
+    }
+    for mode in ["", "64", "888", "u+x", "07555"] {
+        assert!(!is_valid_mode(mode), "{mode}");
+    }
+}
@@ -0,0 +1,171 @@
+use std::fs;
+use std::path::Path;
+
+use ssh_hub::utils::walk::{
+    config_excludes_file, load_gitignore, load_gitignore_for, walk_dir, walk_dir_with_sizes,
+    GitRepo, GitUserConfig, WalkedFile,
+};
+
+fn write(path: &Path, content: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn user(home: &Path) -> GitUserConfig {
+    GitUserConfig {
+        home: home.to_path_buf(),
+        config_home: home.join(".config"),
+    }
+}
+
+#[test]
+fn test_config_excludes_file() {
+    let config = "[user]\n\temail = me@example.com\n[core]\n\teditor = vim\n\texcludesFile = ~/.gitignore_global\n";
+    assert_eq!(
+        config_excludes_file(config).as_deref(),
+        Some("~/.gitignore_global")
+    );
+    assert_eq!(
+        config_excludes_file("[Core]\nexcludesfile = \"/etc/my ignore\" ; global\n").as_deref(),
+        Some("/etc/my ignore")
+    );
+    // The last setting wins
+    assert_eq!(
+        config_excludes_file("[core]\nexcludesFile = a\n[core]\nexcludesFile = b # later\n")
+            .as_deref(),
+        Some("b")
+    );
+    assert_eq!(
+        config_excludes_file("[alias]\nexcludesFile = nope\n[core \"x\"]\nexcludesFile = no\n"),
+        None
+    );
+    assert_eq!(config_excludes_file(""), None);
+}
+
+#[test]
+fn test_repo_found_from_subdirectories_and_worktrees() {
+    let root = tempfile::tempdir().unwrap();
+    let root = root.path().canonicalize().unwrap();
+    let repo = root.join("repo");
+    fs::create_dir_all(repo.join(".git/worktrees/wt")).unwrap();
+    fs::create_dir_all(repo.join("web/src")).unwrap();
+
+    assert_eq!(
+        GitRepo::find(&repo),
+        Some(GitRepo {
+            common_dir: repo.join(".git"),
+            prefix: String::new(),
+        })
+    );
+    assert_eq!(
+        GitRepo::find(&repo.join("web/src")).unwrap().prefix,
+        "web/src"
+    );
+
+    let worktree = root.join("wt");
+    write(
+        &worktree.join(".git"),
+        &format!("gitdir: {}\n", repo.join(".git/worktrees/wt").display()),
+    );
+    write(&repo.join(".git/worktrees/wt/commondir"), "../..\n");
+    let found = GitRepo::find(&worktree).unwrap();
+    assert_eq!(found.common_dir.canonicalize().unwrap(), repo.join(".git"));
+
+    let outside = tempfile::tempdir().unwrap();
+    assert_eq!(GitRepo::find(outside.path()), None);
+}
+
+#[test]
+fn test_global_excludes_and_info_exclude_are_honored() {
+    let home = tempfile::tempdir().unwrap();
+    let repo = tempfile::tempdir().unwrap();
+    let (home, repo) = (home.path(), repo.path());
+    write(
+        &home.join(".gitconfig"),
+        "[core]\n\texcludesFile = ~/.gitignore_global\n",
+    );
+    write(&home.join(".gitignore_global"), "*.swp\n.DS_Store\n");
+    write(&repo.join(".git/info/exclude"), "/web/local/\nnotes.txt\n");
+    write(&repo.join("web/.gitignore"), "!keep.swp\n");
+    for file in [
+        "web/index.html",
+        "web/.index.html.swp",
+        "web/keep.swp",
+        "web/.DS_Store",
+        "web/notes.txt",
+        "web/local/dev.env",
+        "web/sub/local/kept.txt",
+    ] {
+        write(&repo.join(file), "");
+    }
+
+    let web = repo.join("web");
+    let gitignore = load_gitignore_for(&web, None, Some(&user(home)));
+    let mut files = walk_dir(&web, &gitignore).unwrap();
+    files.sort();
+    assert_eq!(
+        files,
+        [".gitignore", "index.html", "keep.swp", "sub/local/kept.txt"]
+    );
+
+    // Without a user config only the repository's rules apply
+    let gitignore = load_gitignore_for(&web, Some(&["*.html".to_string()]), None);
+    assert!(gitignore.is_ignored("local", true));
+    assert!(gitignore.is_ignored("index.html", false));
+    assert!(!gitignore.is_ignored(".index.html.swp", false));
+}
+
+#[test]
+fn test_default_global_excludes_file() {
+    let home = tempfile::tempdir().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    write(&home.path().join(".config/git/ignore"), "*~\n");
+    let gitignore = load_gitignore_for(dir.path(), None, Some(&user(home.path())));
+    assert!(gitignore.is_ignored("main.rs~", false));
+    assert!(!gitignore.is_ignored("main.rs", false));
+}
+
+#[test]
+fn test_parallel_walk_matches_ignore_rules() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    write(&root.join(".gitignore"), "build/\n*.log\n!keep.log\n");
+    let mut expected = vec![".gitignore".to_string()];
+    // Wide and deep enough that several threads take part
+    for a in 0..20 {
+        for b in 0..5 {
+            let file = format!("src/m{a}/n{b}/deep/f{a}_{b}.rs");
+            write(&root.join(&file), &"x".repeat(a));
+            expected.push(file);
+        }
+        write(&root.join(format!("src/m{a}/build/out.o")), "");
+        write(&root.join(format!("src/m{a}/debug.log")), "");
+    }
+    write(&root.join("keep.log"), "kept");
+    expected.push("keep.log".to_string());
+    write(&root.join(".git/HEAD"), "ref: refs/heads/main\n");
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(root.join("src"), root.join("link")).unwrap();
+    expected.sort();
+
+    let gitignore = load_gitignore(root, None);
+    assert_eq!(walk_dir(root, &gitignore).unwrap(), expected);
+
+    let sized = walk_dir_with_sizes(root, &gitignore).unwrap();
+    assert_eq!(
+        sized.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(),
+        expected
+    );
+    assert!(sized.contains(&WalkedFile {
+        path: "src/m7/n0/deep/f7_0.rs".to_string(),
+        size: 7,
+    }));
+    assert_eq!(sized.iter().map(|f| f.size).sum::<u64>(), 5 * 190 + 4 + 23);
+}
+
+#[test]
+fn test_walk_missing_dir_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing");
+    assert!(walk_dir(&missing, &load_gitignore(&missing, None)).is_err());
+}
@@ -0,0 +1,135 @@
+use std::process::Command;
+use std::time::Duration;
+
+use ssh_hub::tools::workspace::registry::{
+    is_workspace_path, sanitize_label, Workspace, WorkspaceRegistry,
+};
+use ssh_hub::tools::workspace::session::{
+    create_command, session_dir, session_id, SessionDirs, SESSION_PREFIX,
+};
+
+fn workspace(server: &str, path: &str, expires_at: u64, pid: u32) -> Workspace {
+    Workspace {
+        server: server.to_string(),
+        path: path.to_string(),
+        created_at: 0,
+        expires_at,
+        pid,
+    }
+}
+
+#[test]
+fn test_sanitize_label() {
+    assert_eq!(sanitize_label("fix-bug_42"), "fix-bug_42");
+    assert_eq!(sanitize_label("Try New Build!"), "try-new-build");
+    assert_eq!(sanitize_label("../../etc"), "etc");
+    assert_eq!(sanitize_label("  "), "");
+    assert_eq!(sanitize_label(&"x".repeat(100)).len(), 32);
+}
+
+#[test]
+fn test_is_workspace_path() {
+    assert!(is_workspace_path("/tmp/ssh-hub-AbC123xy"));
+    assert!(is_workspace_path("/scratch/agents/ssh-hub-build.AbC123xy"));
+    assert!(is_workspace_path("~/ssh-hub-AbC123xy"));
+
+    assert!(!is_workspace_path("/tmp"));
+    assert!(!is_workspace_path("/tmp/ssh-hub-"));
+    assert!(!is_workspace_path("/home/deploy"));
+    assert!(!is_workspace_path("tmp/ssh-hub-AbC123xy"));
+    assert!(!is_workspace_path("/tmp/ssh-hub-x/.."));
+    assert!(!is_workspace_path("/tmp/../ssh-hub-x"));
+    assert!(!is_workspace_path("/tmp/ssh-hub-x/"));
+}
+
+#[test]
+fn test_registry_selection() {
+    let mut registry = WorkspaceRegistry::default();
+    registry.add(workspace("web", "/tmp/ssh-hub-a", 100, 1));
+    registry.add(workspace("web", "/tmp/ssh-hub-b", 200, 2));
+    registry.add(workspace("db", "/tmp/ssh-hub-a", 100, 1));
+
+    assert_eq!(registry.on_server("web").len(), 2);
+    let expired = registry.expired("web", 150);
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].path, "/tmp/ssh-hub-a");
+    assert!(registry.expired("web", 99).is_empty());
+
+    let owned = registry.owned_by(1);
+    assert_eq!(owned.len(), 2);
+    assert!(owned.iter().any(|w| w.server == "db"));
+
+    assert!(registry.find("db", "/tmp/ssh-hub-a").is_some());
+    assert!(registry.find("db", "/tmp/ssh-hub-b").is_none());
+    assert!(registry.remove("web", "/tmp/ssh-hub-a"));
+    assert!(!registry.remove("web", "/tmp/ssh-hub-a"));
+    assert!(registry.find("db", "/tmp/ssh-hub-a").is_some());
+}
+
+#[test]
+fn test_registry_roundtrip() {
+    let mut registry = WorkspaceRegistry::default();
+    registry.add(workspace("web", "/tmp/ssh-hub-a", 100, 1));
+    let json = serde_json::to_string(&registry).unwrap();
+    let parsed: WorkspaceRegistry = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, registry);
+    assert_eq!(
+        serde_json::from_str::<WorkspaceRegistry>("{}").unwrap(),
+        WorkspaceRegistry::default()
+    );
+}
+
+#[test]
+fn test_session_id_and_dir() {
+    assert_eq!(
+        session_id(4242, Duration::from_millis(0x19a_3f0c_2b10)),
+        "4242-19a3f0c2b10"
+    );
+    assert_eq!(session_dir("/tmp", "1-a"), "/tmp/ssh-hub-session-1-a");
+    assert_eq!(
+        session_dir("/scratch/", "1-a"),
+        "/scratch/ssh-hub-session-1-a"
+    );
+    assert_eq!(session_dir("~", "1-a"), "~/ssh-hub-session-1-a");
+    // Session directories pass the same safety check as workspaces
+    assert!(is_workspace_path(&session_dir("/tmp", "1-a")));
+
+    let dirs = SessionDirs::new();
+    let pid = std::process::id().to_string();
+    assert!(dirs.id().starts_with(&format!("{pid}-")));
+    assert!(session_dir("/tmp", dirs.id()).contains(SESSION_PREFIX));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_session_dir_create_command() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let root = tempfile::tempdir().unwrap();
+    let path = session_dir(&root.path().join("nested").to_string_lossy(), "1-a");
+    let run = || {
+        Command::new("sh")
+            .arg("-c")
+            .arg(create_command(&path))
+            .output()
+            .unwrap()
+    };
+
+    let output = run();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), path);
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o700);
+
+    // A second call reuses the directory
+    let output = run();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), path);
+
+    // A symlink planted at the name is refused
+    std::fs::remove_dir(&path).unwrap();
+    std::os::unix::fs::symlink(root.path(), &path).unwrap();
+    let output = run();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}